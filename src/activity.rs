@@ -0,0 +1,125 @@
+//! Live activity events
+//!
+//! The watcher and rule engine publish what they do with each file (and the
+//! daemon its activity log messages) to an in-process channel. The daemon
+//! streams them to subscribed clients over IPC, so the TUI shows the
+//! daemon's activity as it happens. Events are dropped when nobody listens.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber; a slower subscriber skips older ones
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Something that happened to a file, or a daemon message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityEvent {
+    pub time: chrono::DateTime<chrono::Local>,
+    #[serde(flatten)]
+    pub kind: ActivityKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ActivityKind {
+    /// A new or changed file was seen
+    Detected { path: PathBuf },
+    /// A rule matched a file and its action is about to run
+    Matched { path: PathBuf, rule: String },
+    /// A rule's action ran; `destination` is where the file ended up
+    Applied {
+        path: PathBuf,
+        rule: String,
+        /// The action's `type`, e.g. `move`
+        #[serde(default)]
+        action: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<PathBuf>,
+    },
+    /// A rule's action failed
+    Failed {
+        path: PathBuf,
+        rule: String,
+        #[serde(default)]
+        action: String,
+        error: String,
+    },
+    /// An entry of the daemon's activity log
+    Message { message: String },
+}
+
+impl ActivityEvent {
+    /// One-line description for activity displays
+    pub fn summary(&self) -> String {
+        match &self.kind {
+            ActivityKind::Detected { path } => format!("Detected {}", path.display()),
+            ActivityKind::Matched { path, rule } => {
+                format!("Rule '{}' matched {}", rule, path.display())
+            }
+            ActivityKind::Applied {
+                path,
+                rule,
+                destination: Some(destination),
+                ..
+            } if destination != path => {
+                format!("{}: {} → {}", rule, path.display(), destination.display())
+            }
+            ActivityKind::Applied { path, rule, .. } => format!("{}: {}", rule, path.display()),
+            ActivityKind::Failed {
+                path, rule, error, ..
+            } => {
+                format!("{} failed for {}: {}", rule, path.display(), error)
+            }
+            ActivityKind::Message { message } => message.clone(),
+        }
+    }
+}
+
+static CHANNEL: LazyLock<broadcast::Sender<ActivityEvent>> =
+    LazyLock::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+/// Publish an event to current subscribers
+pub fn publish(kind: ActivityKind) {
+    if CHANNEL.receiver_count() == 0 {
+        return;
+    }
+    let _ = CHANNEL.send(ActivityEvent {
+        time: chrono::Local::now(),
+        kind,
+    });
+}
+
+/// Receive events published from now on
+pub fn subscribe() -> broadcast::Receiver<ActivityEvent> {
+    CHANNEL.subscribe()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_and_serialize() {
+        let mut rx = subscribe();
+        publish(ActivityKind::Applied {
+            path: PathBuf::from("/dl/a.pdf"),
+            rule: "Activity test".to_string(),
+            action: "move".to_string(),
+            destination: Some(PathBuf::from("/docs/a.pdf")),
+        });
+        // Other tests running rules publish to the same channel
+        let event = std::iter::from_fn(|| rx.try_recv().ok())
+            .find(|e| e.summary().starts_with("Activity test"))
+            .unwrap();
+        assert_eq!(event.summary(), "Activity test: /dl/a.pdf → /docs/a.pdf");
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["kind"], "applied");
+        assert_eq!(json["rule"], "Activity test");
+        assert_eq!(json["action"], "move");
+        let parsed: ActivityEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, event);
+    }
+}
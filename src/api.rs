@@ -0,0 +1,494 @@
+//! Local HTTP API - daemon status and control for dashboards and launchers
+//!
+//! Opt-in with `[api] enabled = true`. The daemon then serves plain HTTP on
+//! 127.0.0.1 only, and every request must send the configured token as
+//! `Authorization: Bearer <token>`. Each route is answered by an IPC command,
+//! and bodies are the same JSON the IPC socket sends:
+//!
+//! | Route | Command |
+//! |-------|---------|
+//! | `GET /v1/status` | `status` |
+//! | `GET /v1/health` | `health` |
+//! | `GET /v1/rules` | `get_rules` |
+//! | `GET /v1/history?limit=N` | `get_history` |
+//! | `POST /v1/scan` | `scan` |
+//! | `POST /v1/pause`, `POST /v1/resume` | `pause_watch`, `resume_watch` |
+//!
+//! POST bodies are optional `{"path": "..."}` objects; without a path, every
+//! watch is scanned, paused or resumed.
+
+use anyhow::{Context, Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info};
+
+use crate::ipc::{DaemonCommand, DaemonResponse};
+
+/// Answers the commands behind the routes
+pub type Handler = dyn Fn(DaemonCommand) -> DaemonResponse + Send + Sync;
+
+/// Largest request head (request line and headers) accepted
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+/// Largest request body accepted
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// History entries returned when the request doesn't set `limit`
+const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+/// `[api]`: the local HTTP API served by the daemon
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ApiConfig {
+    /// Serve the API (read when the daemon starts)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Port on 127.0.0.1
+    #[serde(default = "default_port")]
+    pub port: u16,
+
+    /// Token clients send as `Authorization: Bearer <token>` (`$NAME` reads
+    /// an environment variable)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub token: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_port(),
+            token: String::new(),
+        }
+    }
+}
+
+fn default_port() -> u16 {
+    7325
+}
+
+impl ApiConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check that an enabled API has a token
+    pub fn validate(&self) -> Result<()> {
+        if self.enabled && crate::expand_env(&self.token).trim().is_empty() {
+            bail!("api.token is required when the API is enabled");
+        }
+        Ok(())
+    }
+}
+
+/// Listen on the configured port of 127.0.0.1
+pub async fn bind(config: &ApiConfig) -> Result<TcpListener> {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, config.port))
+        .await
+        .with_context(|| format!("Failed to serve the API on 127.0.0.1:{}", config.port))
+}
+
+/// Answer API requests until the task is dropped
+pub async fn serve(listener: TcpListener, config: &ApiConfig, handler: Arc<Handler>) {
+    let token: Arc<str> = crate::expand_env(&config.token).trim().into();
+    if let Ok(address) = listener.local_addr() {
+        info!("API listening on http://{}", address);
+    }
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        tokio::spawn(handle_connection(
+            stream,
+            Arc::clone(&token),
+            Arc::clone(&handler),
+        ));
+    }
+}
+
+/// Answer one request and close the connection
+async fn handle_connection(mut stream: TcpStream, token: Arc<str>, handler: Arc<Handler>) {
+    // A client that doesn't send its request in time is dropped
+    let read = tokio::time::timeout(Duration::from_secs(5), read_request(&mut stream)).await;
+    let (status, body) = match read {
+        Ok(Ok(request)) => {
+            debug!("API {} {}", request.method, request.path);
+            respond(&request, &token, &*handler)
+        }
+        Ok(Err(e)) => (400, error_body(&format!("{:#}", e))),
+        Err(_) => return,
+    };
+    let _ = stream.write_all(&http_response(status, &body)).await;
+    let _ = stream.shutdown().await;
+}
+
+/// An HTTP request, as much of it as the API looks at
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut head = String::new();
+    loop {
+        let read = (&mut reader)
+            .take((MAX_HEAD_BYTES - head.len()) as u64)
+            .read_line(&mut head)
+            .await?;
+        if head.ends_with("\r\n\r\n") || head.ends_with("\n\n") {
+            break;
+        }
+        if head.len() >= MAX_HEAD_BYTES {
+            bail!("Request headers are too large");
+        }
+        if read == 0 {
+            bail!("Incomplete request");
+        }
+    }
+    let (mut request, content_length) = parse_head(&head)?;
+    if content_length > MAX_BODY_BYTES {
+        bail!("Request body is too large");
+    }
+    request.body = vec![0; content_length];
+    reader.read_exact(&mut request.body).await?;
+    Ok(request)
+}
+
+/// The request line and headers, and the body's length
+fn parse_head(head: &str) -> Result<(Request, usize)> {
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        bail!("Invalid request line");
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query.to_string(),
+        ..Default::default()
+    };
+    let mut content_length = 0;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().context("Invalid Content-Length")?;
+        } else if name.eq_ignore_ascii_case("authorization") {
+            request.authorization = Some(value.to_string());
+        }
+    }
+    Ok((request, content_length))
+}
+
+/// `{"path": ...}` in a POST body
+#[derive(Debug, Default, Deserialize)]
+struct PathBody {
+    #[serde(default)]
+    path: Option<PathBuf>,
+}
+
+/// The status code and JSON body answering a request
+fn respond(request: &Request, token: &str, handler: &Handler) -> (u16, String) {
+    // Browsers send CORS preflights without credentials
+    if request.method == "OPTIONS" {
+        return (204, String::new());
+    }
+    let authorized = request
+        .authorization
+        .as_deref()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| !token.is_empty() && token_matches(given.trim(), token));
+    if !authorized {
+        return (401, error_body("Missing or wrong token"));
+    }
+
+    let command = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/v1/status") => DaemonCommand::Status,
+        ("GET", "/v1/health") => DaemonCommand::Health,
+        ("GET", "/v1/rules") => DaemonCommand::GetRules,
+        ("GET", "/v1/history") => {
+            let limit = match query_value(&request.query, "limit") {
+                Some(limit) => match limit.parse() {
+                    Ok(limit) => limit,
+                    Err(_) => return (400, error_body("limit must be a number")),
+                },
+                None => DEFAULT_HISTORY_LIMIT,
+            };
+            DaemonCommand::GetHistory { limit }
+        }
+        ("POST", "/v1/scan" | "/v1/pause" | "/v1/resume") => {
+            let body: PathBody = if request.body.iter().all(u8::is_ascii_whitespace) {
+                PathBody::default()
+            } else {
+                match serde_json::from_slice(&request.body) {
+                    Ok(body) => body,
+                    Err(e) => return (400, error_body(&format!("Invalid body: {}", e))),
+                }
+            };
+            let pause = match request.path.as_str() {
+                "/v1/scan" => return answer(handler(DaemonCommand::Scan { path: body.path })),
+                path => path == "/v1/pause",
+            };
+            return answer(set_paused(handler, body.path, pause));
+        }
+        (
+            _,
+            "/v1/status" | "/v1/health" | "/v1/rules" | "/v1/history" | "/v1/scan" | "/v1/pause"
+            | "/v1/resume",
+        ) => return (405, error_body("Method not allowed")),
+        _ => return (404, error_body("Not found")),
+    };
+    answer(handler(command))
+}
+
+/// Pause or resume one watch, or every watch
+fn set_paused(handler: &Handler, path: Option<PathBuf>, pause: bool) -> DaemonResponse {
+    let paths = match path {
+        Some(path) => vec![path],
+        None => match handler(DaemonCommand::Status) {
+            DaemonResponse::Status { watch_paths, .. } => watch_paths,
+            other => return other,
+        },
+    };
+    for path in paths {
+        let response = if pause {
+            handler(DaemonCommand::PauseWatch { path })
+        } else {
+            handler(DaemonCommand::ResumeWatch { path })
+        };
+        if matches!(response, DaemonResponse::Error { .. }) {
+            return response;
+        }
+    }
+    DaemonResponse::Ok
+}
+
+fn answer(response: DaemonResponse) -> (u16, String) {
+    let status = match response {
+        DaemonResponse::Error { .. } => 400,
+        _ => 200,
+    };
+    (status, serde_json::to_string(&response).unwrap_or_default())
+}
+
+fn error_body(message: &str) -> String {
+    let response = DaemonResponse::Error {
+        message: message.to_string(),
+    };
+    serde_json::to_string(&response).unwrap_or_default()
+}
+
+/// Compare tokens without stopping at the first difference
+fn token_matches(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn query_value<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| value)
+}
+
+fn http_response(status: u16, body: &str) -> Vec<u8> {
+    let reason = match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Error",
+    };
+    format!(
+        "HTTP/1.1 {} {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Authorization, Content-Type\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )
+    .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn request(head: &str, body: &str) -> Request {
+        let (mut request, _) = parse_head(head).unwrap();
+        request.body = body.as_bytes().to_vec();
+        request
+    }
+
+    #[test]
+    fn test_api_routes() {
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&commands);
+        let handler = move |command: DaemonCommand| {
+            let response = match &command {
+                DaemonCommand::Status => DaemonResponse::Status {
+                    running: true,
+                    uptime_seconds: 1,
+                    watches: 2,
+                    rules: 0,
+                    files_processed: 0,
+                    files_unmatched: 0,
+                    watch_paths: vec!["/a".into(), "/b".into()],
+                    paused_watches: vec![],
+                    log_file: None,
+                    profile: None,
+                },
+                DaemonCommand::PauseWatch { path } if path.as_os_str() == "/c" => {
+                    DaemonResponse::Error {
+                        message: "Not a watched folder: /c".to_string(),
+                    }
+                }
+                _ => DaemonResponse::Ok,
+            };
+            seen.lock().unwrap().push(format!("{:?}", command));
+            response
+        };
+        let auth = "Authorization: Bearer s3cret\r\n";
+
+        let status = request(&format!("GET /v1/status HTTP/1.1\r\n{}\r\n", auth), "");
+        let (code, body) = respond(&status, "s3cret", &handler);
+        assert_eq!(code, 200);
+        assert!(body.contains(r#""watches":2"#));
+
+        // Without the right token nothing reaches the daemon
+        let anonymous = request("GET /v1/status HTTP/1.1\r\n\r\n", "");
+        assert_eq!(respond(&anonymous, "s3cret", &handler).0, 401);
+        let wrong = request(
+            "GET /v1/status HTTP/1.1\r\nauthorization: Bearer s3creT\r\n\r\n",
+            "",
+        );
+        assert_eq!(respond(&wrong, "s3cret", &handler).0, 401);
+        // Tokens of another length, longer or a prefix, don't match either
+        for given in ["s3cre", "s3cret!", "s3crets3cret", ""] {
+            let head = format!(
+                "GET /v1/status HTTP/1.1\r\nAuthorization: Bearer {}\r\n\r\n",
+                given
+            );
+            assert_eq!(respond(&request(&head, ""), "s3cret", &handler).0, 401);
+        }
+        // Nor does anything when no token is configured
+        let empty = request(
+            "GET /v1/status HTTP/1.1\r\nAuthorization: Bearer \r\n\r\n",
+            "",
+        );
+        assert_eq!(respond(&empty, "", &handler).0, 401);
+        assert_eq!(commands.lock().unwrap().len(), 1);
+
+        let history = request(
+            &format!("GET /v1/history?limit=5 HTTP/1.1\r\n{}\r\n", auth),
+            "",
+        );
+        assert_eq!(respond(&history, "s3cret", &handler).0, 200);
+        let scan = request(&format!("POST /v1/scan HTTP/1.1\r\n{}\r\n", auth), "");
+        assert_eq!(respond(&scan, "s3cret", &handler).0, 200);
+
+        // Pausing without a path pauses every watch; an unknown one fails
+        commands.lock().unwrap().clear();
+        let pause_all = request(&format!("POST /v1/pause HTTP/1.1\r\n{}\r\n", auth), "");
+        assert_eq!(respond(&pause_all, "s3cret", &handler).0, 200);
+        assert_eq!(
+            *commands.lock().unwrap(),
+            [
+                "Status",
+                r#"PauseWatch { path: "/a" }"#,
+                r#"PauseWatch { path: "/b" }"#
+            ]
+        );
+        let pause_other = request(
+            &format!("POST /v1/pause HTTP/1.1\r\n{}\r\n", auth),
+            r#"{"path": "/c"}"#,
+        );
+        let (code, body) = respond(&pause_other, "s3cret", &handler);
+        assert_eq!(code, 400);
+        assert!(body.contains("Not a watched folder"));
+
+        let wrong_method = request(&format!("GET /v1/scan HTTP/1.1\r\n{}\r\n", auth), "");
+        assert_eq!(respond(&wrong_method, "s3cret", &handler).0, 405);
+        let unknown = request(&format!("GET /v2/status HTTP/1.1\r\n{}\r\n", auth), "");
+        assert_eq!(respond(&unknown, "s3cret", &handler).0, 404);
+    }
+
+    #[tokio::test]
+    async fn test_api_server() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let config = ApiConfig {
+            enabled: true,
+            port: address.port(),
+            token: "s3cret".to_string(),
+        };
+        let handler: Arc<Handler> = Arc::new(|_| DaemonResponse::Ok);
+        let server = tokio::spawn(async move { serve(listener, &config, handler).await });
+
+        // Send raw bytes and read the whole response
+        let exchange = |bytes: Vec<u8>| async move {
+            let mut stream = TcpStream::connect(address).await.unwrap();
+            stream.write_all(&bytes).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+        let ok =
+            exchange(b"POST /v1/scan HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n".to_vec())
+                .await;
+        assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"), "{}", ok);
+        let unauthorized = exchange(b"GET /v1/status HTTP/1.1\r\n\r\n".to_vec()).await;
+        assert!(unauthorized.starts_with("HTTP/1.1 401 "));
+
+        // Oversized requests are turned down before they're read in full
+        let mut huge_head = b"GET /".to_vec();
+        huge_head.resize(MAX_HEAD_BYTES, b'a');
+        let response = exchange(huge_head).await;
+        assert!(response.starts_with("HTTP/1.1 400 "));
+        assert!(response.contains("too large"));
+        let huge_body = format!(
+            "POST /v1/scan HTTP/1.1\r\nAuthorization: Bearer s3cret\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_BYTES + 1
+        );
+        let response = exchange(huge_body.into_bytes()).await;
+        assert!(response.starts_with("HTTP/1.1 400 "));
+        assert!(response.contains("too large"));
+
+        // Neither is something that isn't HTTP, text or not
+        for garbage in [
+            &b"hello\n\n"[..],
+            &[0x16, 0x03, 0x01, 0xfc, 0xff, b'\n', b'\n'],
+        ] {
+            let response = exchange(garbage.to_vec()).await;
+            assert!(response.starts_with("HTTP/1.1 400 "), "{}", response);
+        }
+        server.abort();
+    }
+}
@@ -2,13 +2,12 @@
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
+use super::keymap::KeyAction;
 use super::state::{
-    AppState, Mode, RuleEditorField, RuleEditorState, SettingsItem, View, WatchEditorField,
-    WatchEditorState,
+    ActionTypeSelection, AppState, LogLevel, Mode, RuleEditorField, RuleEditorState, SettingsItem,
+    View, WatchEditorField, WatchEditorState,
 };
-#[cfg(unix)]
 use crate::autostart;
-use crate::theme::Theme;
 
 /// Process pending update if flagged (call after UI redraw)
 pub fn process_pending_update(state: &mut AppState) {
@@ -38,8 +37,14 @@ pub fn handle_key(state: &mut AppState, key: KeyEvent) {
             handle_theme_picker_key(state, key);
             return;
         }
+        Mode::PresetPicker => {
+            handle_preset_picker_key(state, key);
+            return;
+        }
         Mode::Help => {
-            if matches!(key.code, KeyCode::Esc | KeyCode::Char('?') | KeyCode::Enter) {
+            if matches!(key.code, KeyCode::Esc | KeyCode::Enter)
+                || state.keymap.is(KeyAction::Help, &key)
+            {
                 state.mode = Mode::Normal;
                 state.show_help = false;
             }
@@ -69,94 +74,103 @@ pub fn handle_key(state: &mut AppState, key: KeyEvent) {
             // No input during update
             return;
         }
+        Mode::RuleTester => {
+            handle_rule_tester_key(state, key);
+            return;
+        }
+        Mode::LogSearch => {
+            handle_log_search_key(state, key);
+            return;
+        }
         Mode::Normal => {}
     }
 
     // Legacy help popup support
     if state.show_help {
-        if matches!(key.code, KeyCode::Esc | KeyCode::Char('?') | KeyCode::Enter) {
+        if matches!(key.code, KeyCode::Esc | KeyCode::Enter)
+            || state.keymap.is(KeyAction::Help, &key)
+        {
             state.show_help = false;
         }
         return;
     }
 
+    // Ctrl+C always quits, whatever [keybindings] says
+    if key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('c') {
+        state.should_quit = true;
+        return;
+    }
+
     // Global keybindings
-    match (key.modifiers, key.code) {
-        (KeyModifiers::CONTROL, KeyCode::Char('c'))
-        | (KeyModifiers::CONTROL, KeyCode::Char('q')) => {
-            state.should_quit = true;
-            return;
-        }
-        (_, KeyCode::Char('q')) => {
-            state.should_quit = true;
-            return;
-        }
-        (_, KeyCode::Char('?')) | (_, KeyCode::F(1)) => {
-            state.mode = Mode::Help;
-            state.show_help = true;
-            return;
-        }
-        (_, KeyCode::Tab) => {
-            state.next_view();
-            return;
-        }
-        (KeyModifiers::SHIFT, KeyCode::BackTab) => {
-            state.prev_view();
-            return;
-        }
-        // Number keys for quick navigation
-        (_, KeyCode::Char('1')) => {
-            state.view = View::Dashboard;
-            return;
-        }
-        (_, KeyCode::Char('2')) => {
-            state.view = View::Rules;
-            return;
-        }
-        (_, KeyCode::Char('3')) => {
-            state.view = View::Watches;
-            return;
-        }
-        (_, KeyCode::Char('4')) => {
-            state.view = View::Log;
-            return;
-        }
-        // Theme picker (just 't', like Feedo)
-        (_, KeyCode::Char('t')) => {
-            // Set picker index to current theme
-            state.theme_picker_index = Theme::all()
-                .iter()
-                .position(|t| *t == state.theme.inner())
-                .unwrap_or(0);
-            state.original_theme = Some(state.theme);
-            state.mode = Mode::ThemePicker;
-            return;
-        }
-        // Settings dialog
-        (_, KeyCode::Char('s')) => {
-            state.settings_index = 0;
-            state.mode = Mode::Settings;
-            return;
-        }
-        // About dialog
-        (_, KeyCode::Char('A')) => {
-            state.mode = Mode::About;
-            return;
+    if let Some(action) = state.keymap.global_action(&key) {
+        match action {
+            KeyAction::Quit => state.should_quit = true,
+            KeyAction::Help => {
+                state.mode = Mode::Help;
+                state.show_help = true;
+            }
+            KeyAction::NextView => state.next_view(),
+            KeyAction::PrevView => state.prev_view(),
+            KeyAction::Theme => {
+                // Set picker index to current theme
+                state.theme_picker_index = state
+                    .theme
+                    .choices(&state.config)
+                    .iter()
+                    .position(|t| *t == state.theme)
+                    .unwrap_or(0);
+                state.original_theme = Some(state.theme);
+                state.mode = Mode::ThemePicker;
+            }
+            KeyAction::Settings => {
+                state.settings_index = 0;
+                state.mode = Mode::Settings;
+            }
+            KeyAction::About => state.mode = Mode::About,
+            KeyAction::TestRules => state.mode = Mode::RuleTester,
+            KeyAction::Scan => {
+                state.scan_requested = true;
+                state.set_status("Scanning all watched folders");
+            }
+            _ => {}
         }
-        _ => {}
+        return;
+    }
+
+    // Number keys for quick navigation
+    let view = match key.code {
+        KeyCode::Char('1') => Some(View::Dashboard),
+        KeyCode::Char('2') => Some(View::Rules),
+        KeyCode::Char('3') => Some(View::Watches),
+        KeyCode::Char('4') => Some(View::Log),
+        KeyCode::Char('5') => Some(View::Preview),
+        KeyCode::Char('6') => Some(View::History),
+        KeyCode::Char('7') => Some(View::Stats),
+        KeyCode::Char('8') => Some(View::Duplicates),
+        _ => None,
+    };
+    if let Some(view) = view {
+        state.view = view;
+        return;
     }
 
     // View-specific keybindings
+    let key = state.keymap.navigation(key);
     match state.view {
         View::Dashboard => handle_dashboard_key(state, key),
         View::Rules => handle_rules_key(state, key),
         View::Watches => handle_watches_key(state, key),
         View::Log => handle_log_key(state, key),
+        View::Preview => handle_preview_key(state, key),
+        View::History => handle_history_key(state, key),
+        View::Stats => handle_stats_key(state, key),
+        View::Duplicates => handle_duplicates_key(state, key),
     }
 }
 
 fn handle_theme_picker_key(state: &mut AppState, key: KeyEvent) {
-    let themes = Theme::all();
+    let key = state.keymap.navigation(key);
+    let themes = state.theme.choices(&state.config);
     let len = themes.len();
 
     match key.code {
@@ -169,41 +183,89 @@ fn handle_theme_picker_key(state: &mut AppState, key: KeyEvent) {
         }
         KeyCode::Enter => {
             // Apply selected theme
-            let selected_theme = Theme::from(themes[state.theme_picker_index]);
+            let selected_theme = themes[state.theme_picker_index];
             state.theme = selected_theme;
             state.original_theme = None;
 
             // Save to config
-            state.config.general.theme = Some(selected_theme.inner().slug().to_string());
+            state.config.general.theme = Some(selected_theme.slug().to_string());
             save_config(state);
 
             state.mode = Mode::Normal;
             state.set_status(format!("Theme set to {}", selected_theme.name()));
         }
-        KeyCode::Down | KeyCode::Char('j') => {
+        KeyCode::Down => {
             state.theme_picker_index = (state.theme_picker_index + 1) % len;
             // Preview theme
-            state.theme = Theme::from(themes[state.theme_picker_index]);
+            state.theme = themes[state.theme_picker_index];
         }
-        KeyCode::Up | KeyCode::Char('k') => {
+        KeyCode::Up => {
             state.theme_picker_index = state.theme_picker_index.checked_sub(1).unwrap_or(len - 1);
             // Preview theme
-            state.theme = Theme::from(themes[state.theme_picker_index]);
+            state.theme = themes[state.theme_picker_index];
         }
-        KeyCode::Home | KeyCode::Char('g') => {
+        KeyCode::Home => {
             state.theme_picker_index = 0;
-            state.theme = Theme::from(themes[state.theme_picker_index]);
+            state.theme = themes[state.theme_picker_index];
         }
-        KeyCode::End | KeyCode::Char('G') => {
+        KeyCode::End => {
             state.theme_picker_index = len - 1;
-            state.theme = Theme::from(themes[state.theme_picker_index]);
+            state.theme = themes[state.theme_picker_index];
+        }
+        _ => {}
+    }
+}
+
+fn handle_preset_picker_key(state: &mut AppState, key: KeyEvent) {
+    let key = state.keymap.navigation(key);
+    let presets = &crate::presets::PRESETS;
+    let len = presets.len();
+
+    match key.code {
+        KeyCode::Esc => state.mode = Mode::Normal,
+        KeyCode::Enter => {
+            // Add the preset's rules after the existing ones
+            let rules = presets[state.preset_picker_index].rules(None, &state.config.rules);
+            let first = state.config.rules.len();
+            let names: Vec<_> = rules.iter().map(|r| format!("'{}'", r.name)).collect();
+            state.config.rules.extend(rules);
+            state.selected_rule = Some(first);
+            state.mode = Mode::Normal;
+            let noun = if names.len() == 1 { "rule" } else { "rules" };
+            state.set_status(format!("Added {} {}", noun, names.join(", ")));
+            save_config(state);
+        }
+        KeyCode::Down => {
+            state.preset_picker_index = (state.preset_picker_index + 1) % len;
+        }
+        KeyCode::Up => {
+            state.preset_picker_index = state.preset_picker_index.checked_sub(1).unwrap_or(len - 1);
         }
+        KeyCode::Home => state.preset_picker_index = 0,
+        KeyCode::End => state.preset_picker_index = len - 1,
         _ => {}
     }
 }
 
 fn handle_dashboard_key(state: &mut AppState, key: KeyEvent) {
+    let last = state.activity.len().saturating_sub(1);
+
     match key.code {
+        // Scroll the live activity pane (0 = newest at the top)
+        KeyCode::Up => {
+            state.activity_scroll = state.activity_scroll.saturating_sub(1);
+        }
+        KeyCode::Down => {
+            state.activity_scroll = (state.activity_scroll + 1).min(last);
+        }
+        KeyCode::PageUp => {
+            state.activity_scroll = state.activity_scroll.saturating_sub(10);
+        }
+        KeyCode::PageDown => {
+            state.activity_scroll = (state.activity_scroll + 10).min(last);
+        }
+        KeyCode::Home => state.activity_scroll = 0,
+        KeyCode::End => state.activity_scroll = last,
         KeyCode::Char('r') => state.view = View::Rules,
         KeyCode::Char('w') => state.view = View::Watches,
         KeyCode::Char('l') => state.view = View::Log,
@@ -225,13 +287,18 @@ fn handle_rules_key(state: &mut AppState, key: KeyEvent) {
         state.mode = Mode::AddRule;
         return;
     }
+    if key.code == KeyCode::Char('p') {
+        state.preset_picker_index = 0;
+        state.mode = Mode::PresetPicker;
+        return;
+    }
 
     if len == 0 {
         return;
     }
 
     match key.code {
-        KeyCode::Up | KeyCode::Char('k') => {
+        KeyCode::Up => {
             state.selected_rule = Some(
                 state
                     .selected_rule
@@ -239,7 +306,7 @@ fn handle_rules_key(state: &mut AppState, key: KeyEvent) {
                     .unwrap_or(0),
             );
         }
-        KeyCode::Down | KeyCode::Char('j') => {
+        KeyCode::Down => {
             state.selected_rule = Some(
                 state
                     .selected_rule
@@ -247,13 +314,13 @@ fn handle_rules_key(state: &mut AppState, key: KeyEvent) {
                     .unwrap_or(0),
             );
         }
-        KeyCode::Home | KeyCode::Char('g') => {
+        KeyCode::Home => {
             state.selected_rule = Some(0);
         }
-        KeyCode::End | KeyCode::Char('G') => {
+        KeyCode::End => {
             state.selected_rule = Some(len.saturating_sub(1));
         }
-        KeyCode::Enter | KeyCode::Char(' ') => {
+        _ if state.keymap.is(KeyAction::ToggleRule, &key) => {
             // Toggle rule enabled status
             if let Some(rule) = state.current_rule_mut() {
                 rule.enabled = !rule.enabled;
@@ -275,6 +342,39 @@ fn handle_rules_key(state: &mut AppState, key: KeyEvent) {
                 state.set_status("Select a rule first");
             }
         }
+        KeyCode::Char('c') => {
+            // Duplicate selected rule below it
+            if let Some(idx) = state.selected_rule.filter(|&i| i < len) {
+                let mut rule = state.config.rules[idx].clone();
+                rule.name = copy_name(&state.config.rules, &rule.name);
+                let name = rule.name.clone();
+                state.config.rules.insert(idx + 1, rule);
+                state.selected_rule = Some(idx + 1);
+                state.set_status(format!("Created rule '{}'", name));
+                save_config(state);
+            } else {
+                state.set_status("Select a rule first");
+            }
+        }
+        KeyCode::Char('K') | KeyCode::Char('J') => {
+            // Move selected rule up/down (rules are tried in order)
+            if let Some(idx) = state.selected_rule.filter(|&i| i < len) {
+                let target = if key.code == KeyCode::Char('K') {
+                    idx.checked_sub(1)
+                } else {
+                    Some(idx + 1).filter(|&i| i < len)
+                };
+                if let Some(target) = target {
+                    state.config.rules.swap(idx, target);
+                    state.selected_rule = Some(target);
+                    let name = state.config.rules[target].name.clone();
+                    state.set_status(format!("Moved rule '{}' to position {}", name, target + 1));
+                    save_config(state);
+                }
+            } else {
+                state.set_status("Select a rule first");
+            }
+        }
         KeyCode::Char('d') | KeyCode::Delete => {
             // Delete selected rule
             if let Some(idx) = state.selected_rule {
@@ -289,9 +389,8 @@ fn handle_rules_key(state: &mut AppState, key: KeyEvent) {
                         state.selected_rule = Some(state.config.rules.len() - 1);
                     }
 
-                    // Save config
-                    save_config(state);
                     state.set_status(format!("Deleted rule '{}'", rule_name));
+                    save_config(state);
                 }
             } else {
                 state.set_status("Select a rule first");
@@ -321,7 +420,7 @@ fn handle_watches_key(state: &mut AppState, key: KeyEvent) {
     }
 
     match key.code {
-        KeyCode::Up | KeyCode::Char('k') => {
+        KeyCode::Up => {
             state.selected_watch = Some(
                 state
                     .selected_watch
@@ -329,7 +428,7 @@ fn handle_watches_key(state: &mut AppState, key: KeyEvent) {
                     .unwrap_or(0),
             );
         }
-        KeyCode::Down | KeyCode::Char('j') => {
+        KeyCode::Down => {
             state.selected_watch = Some(
                 state
                     .selected_watch
@@ -337,12 +436,26 @@ fn handle_watches_key(state: &mut AppState, key: KeyEvent) {
                     .unwrap_or(0),
             );
         }
-        KeyCode::Home | KeyCode::Char('g') => {
+        KeyCode::Home => {
             state.selected_watch = Some(0);
         }
-        KeyCode::End | KeyCode::Char('G') => {
+        KeyCode::End => {
             state.selected_watch = Some(len.saturating_sub(1));
         }
+        KeyCode::Char('f') => {
+            // Find duplicates in the selected watch
+            if let Some(watch) = state
+                .selected_watch
+                .and_then(|i| state.config.watches.get(i))
+            {
+                let root = crate::expand_path(&watch.path);
+                let review = crate::dedupe::review_dir(&root);
+                let options = crate::dedupe::ScanOptions::default();
+                state.dedupe = Some(super::state::DedupeState::new(root, options, review));
+                state.view = View::Duplicates;
+                state.set_status("Scanning for duplicates...");
+            }
+        }
         KeyCode::Char('e') => {
             // Edit selected watch
             if let Some(idx) = state.selected_watch {
@@ -377,6 +490,7 @@ fn handle_watches_key(state: &mut AppState, key: KeyEvent) {
                 state.set_status("Select a watch first");
             }
         }
+        KeyCode::Char('p') => state.toggle_watch_pause(),
         KeyCode::Char('o') | KeyCode::Enter => {
             // Open folder in file manager
             if let Some(i) = state.selected_watch
@@ -391,7 +505,7 @@ fn handle_watches_key(state: &mut AppState, key: KeyEvent) {
 }
 
 fn handle_log_key(state: &mut AppState, key: KeyEvent) {
-    let len = state.log_entries.len();
+    let len = state.visible_log().len();
 
     match key.code {
         KeyCode::Char('c') => {
@@ -399,10 +513,53 @@ fn handle_log_key(state: &mut AppState, key: KeyEvent) {
             state.log_scroll = 0;
             state.set_status("Log cleared");
         }
-        KeyCode::Up | KeyCode::Char('k') => {
+        KeyCode::Char('/') => {
+            state.log_query_cursor = state.log_query.chars().count();
+            state.mode = Mode::LogSearch;
+        }
+        KeyCode::Char('f') => {
+            state.log_level_filter = state.log_level_filter.next();
+            state.log_scroll = 0;
+        }
+        KeyCode::Char('r') => {
+            // Cycle through the rules, then back to all of them
+            let rules: Vec<&String> = state.config.rules.iter().map(|r| &r.name).collect();
+            let next = match &state.log_rule_filter {
+                None => rules.first(),
+                Some(current) => rules
+                    .iter()
+                    .position(|name| *name == current)
+                    .and_then(|i| rules.get(i + 1)),
+            };
+            state.log_rule_filter = next.map(|name| name.to_string());
+            state.log_scroll = 0;
+        }
+        KeyCode::Char('e') | KeyCode::Char('E') => {
+            // Older errors are further down the list
+            let older = key.code == KeyCode::Char('e');
+            let visible = state.visible_log();
+            let found = if older {
+                (state.log_scroll + 1..visible.len()).find(|&i| visible[i].level == LogLevel::Error)
+            } else {
+                (0..state.log_scroll.min(visible.len()))
+                    .rev()
+                    .find(|&i| visible[i].level == LogLevel::Error)
+            };
+            match found {
+                Some(i) => state.log_scroll = i,
+                None => state.set_status("No more errors"),
+            }
+        }
+        KeyCode::Esc => {
+            state.log_query.clear();
+            state.log_level_filter = Default::default();
+            state.log_rule_filter = None;
+            state.log_scroll = 0;
+        }
+        KeyCode::Up => {
             state.log_scroll = state.log_scroll.saturating_sub(1);
         }
-        KeyCode::Down | KeyCode::Char('j') => {
+        KeyCode::Down => {
             if state.log_scroll < len.saturating_sub(1) {
                 state.log_scroll += 1;
             }
@@ -413,22 +570,321 @@ fn handle_log_key(state: &mut AppState, key: KeyEvent) {
         KeyCode::PageDown => {
             state.log_scroll = (state.log_scroll + 10).min(len.saturating_sub(1));
         }
-        KeyCode::Home | KeyCode::Char('g') => {
+        KeyCode::Home => {
             state.log_scroll = 0;
         }
-        KeyCode::End | KeyCode::Char('G') => {
+        KeyCode::End => {
             state.log_scroll = len.saturating_sub(1);
         }
         _ => {}
     }
 }
 
+fn handle_stats_key(state: &mut AppState, key: KeyEvent) {
+    let last = state
+        .stats
+        .as_ref()
+        .map_or(0, |stats| stats.rules.len().saturating_sub(1));
+
+    match key.code {
+        KeyCode::Char('p') => {
+            state.stats_period = state.stats_period.toggle();
+            state.load_stats();
+        }
+        KeyCode::Char('r') => state.load_stats(),
+        KeyCode::Up => {
+            state.stats_scroll = state.stats_scroll.saturating_sub(1);
+        }
+        KeyCode::Down => {
+            state.stats_scroll = (state.stats_scroll + 1).min(last);
+        }
+        KeyCode::PageUp => {
+            state.stats_scroll = state.stats_scroll.saturating_sub(10);
+        }
+        KeyCode::PageDown => {
+            state.stats_scroll = (state.stats_scroll + 10).min(last);
+        }
+        KeyCode::Home => state.stats_scroll = 0,
+        KeyCode::End => state.stats_scroll = last,
+        _ => {}
+    }
+}
+
+fn handle_log_search_key(state: &mut AppState, key: KeyEvent) {
+    match key.code {
+        KeyCode::Enter => state.mode = Mode::Normal,
+        KeyCode::Esc => {
+            state.log_query.clear();
+            state.log_query_cursor = 0;
+            state.log_scroll = 0;
+            state.mode = Mode::Normal;
+        }
+        _ => {
+            handle_text_input(&mut state.log_query, &mut state.log_query_cursor, key);
+            state.log_scroll = 0;
+        }
+    }
+}
+
+fn handle_preview_key(state: &mut AppState, key: KeyEvent) {
+    let last = state.preview_rows().saturating_sub(1);
+
+    match key.code {
+        KeyCode::Char('r') => {
+            state.preview_stale = true;
+            state.set_status("Refreshing preview...");
+        }
+        KeyCode::Char('m') => {
+            state.preview_matched_only = !state.preview_matched_only;
+            state.preview_scroll = 0;
+        }
+        KeyCode::Up => {
+            state.preview_scroll = state.preview_scroll.saturating_sub(1);
+        }
+        KeyCode::Down => {
+            state.preview_scroll = (state.preview_scroll + 1).min(last);
+        }
+        KeyCode::PageUp => {
+            state.preview_scroll = state.preview_scroll.saturating_sub(10);
+        }
+        KeyCode::PageDown => {
+            state.preview_scroll = (state.preview_scroll + 10).min(last);
+        }
+        KeyCode::Home => state.preview_scroll = 0,
+        KeyCode::End => state.preview_scroll = last,
+        _ => {}
+    }
+}
+
+fn handle_history_key(state: &mut AppState, key: KeyEvent) {
+    let len = state.history.len();
+    if len == 0 {
+        return;
+    }
+
+    match key.code {
+        KeyCode::Up => {
+            state.selected_history = Some(
+                state
+                    .selected_history
+                    .map(|i| i.saturating_sub(1))
+                    .unwrap_or(0),
+            );
+        }
+        KeyCode::Down => {
+            state.selected_history = Some(
+                state
+                    .selected_history
+                    .map(|i| (i + 1).min(len - 1))
+                    .unwrap_or(0),
+            );
+        }
+        KeyCode::PageUp => {
+            state.selected_history = state.selected_history.map(|i| i.saturating_sub(10));
+        }
+        KeyCode::PageDown => {
+            state.selected_history = state.selected_history.map(|i| (i + 10).min(len - 1));
+        }
+        KeyCode::Home => state.selected_history = Some(0),
+        KeyCode::End => state.selected_history = Some(len - 1),
+        _ if state.keymap.is(KeyAction::Undo, &key) => {
+            let Some(entry) = state
+                .selected_history
+                .and_then(|i| state.history.get(i))
+                .cloned()
+            else {
+                state.set_status("Select an action first");
+                return;
+            };
+            match crate::history::undo(&entry) {
+                Ok(()) => {
+                    let message = format!("Undid {}: {}", entry.action, entry.undo_summary());
+                    state.log(LogLevel::Success, message.clone());
+                    state.set_status(message);
+                }
+                Err(e) => state.set_status(format!("Can't undo: {:#}", e)),
+            }
+            state.load_history();
+        }
+        _ => {}
+    }
+}
+
+fn handle_duplicates_key(state: &mut AppState, key: KeyEvent) {
+    use crate::dedupe::Resolution;
+
+    let Some(dedupe) = state.dedupe.as_mut() else {
+        return;
+    };
+    let confirm_delete = std::mem::take(&mut dedupe.confirm_delete);
+    if key.code == KeyCode::Char('r') {
+        dedupe.stale = true;
+        state.set_status("Scanning for duplicates...");
+        return;
+    }
+    let len = dedupe.groups.len();
+    if len == 0 || dedupe.scanning {
+        return;
+    }
+
+    let resolution = match key.code {
+        KeyCode::Up => {
+            dedupe.selected = dedupe.selected.saturating_sub(1);
+            None
+        }
+        KeyCode::Down => {
+            dedupe.selected = (dedupe.selected + 1).min(len - 1);
+            None
+        }
+        KeyCode::PageUp => {
+            dedupe.selected = dedupe.selected.saturating_sub(10);
+            None
+        }
+        KeyCode::PageDown => {
+            dedupe.selected = (dedupe.selected + 10).min(len - 1);
+            None
+        }
+        KeyCode::Home => {
+            dedupe.selected = 0;
+            None
+        }
+        KeyCode::End => {
+            dedupe.selected = len - 1;
+            None
+        }
+        // Pick which file of the group is kept
+        KeyCode::Left | KeyCode::Right => {
+            let group = &mut dedupe.groups[dedupe.selected];
+            let files = group.files.len();
+            group.keep = if key.code == KeyCode::Right {
+                (group.keep + 1) % files
+            } else {
+                (group.keep + files - 1) % files
+            };
+            None
+        }
+        KeyCode::Char('d') if !confirm_delete => {
+            dedupe.confirm_delete = true;
+            let count = dedupe.groups[dedupe.selected].files.len() - 1;
+            state.status_message = Some(format!("Press d again to delete {} duplicate(s)", count));
+            None
+        }
+        KeyCode::Char('d') => Some(Resolution::Delete),
+        KeyCode::Char('l') => Some(Resolution::Hardlink),
+        KeyCode::Char('m') => Some(Resolution::Move(dedupe.review.clone())),
+        _ => None,
+    };
+    let Some(resolution) = resolution else {
+        return;
+    };
+
+    let group = dedupe.groups[dedupe.selected].clone();
+    let run = crate::dedupe::resolve(std::slice::from_ref(&group), &resolution);
+    let mut message = format!(
+        "{}: {} {} duplicate(s), freed {}",
+        group.kept().display(),
+        resolution.verb(),
+        run.resolved,
+        crate::progress::format_bytes(run.freed)
+    );
+    if run.failed.is_empty() {
+        dedupe.groups.remove(dedupe.selected);
+        dedupe.selected = dedupe.selected.min(dedupe.groups.len().saturating_sub(1));
+        state.log(LogLevel::Success, message.clone());
+    } else {
+        message.push_str(&format!(
+            ", {} failed: {}",
+            run.failed.len(),
+            run.failed[0].1
+        ));
+        dedupe.stale = true;
+        state.log(LogLevel::Error, message.clone());
+    }
+    state.set_status(message);
+}
+
+fn handle_rule_tester_key(state: &mut AppState, key: KeyEvent) {
+    let tester = &mut state.rule_tester;
+    let last = tester.rows().saturating_sub(1);
+
+    match key.code {
+        KeyCode::Esc => state.mode = Mode::Normal,
+        KeyCode::Enter => {
+            let path = tester.path.trim();
+            tester.report = if path.is_empty() {
+                Some(Err("Enter the path of a file to test".to_string()))
+            } else {
+                Some(
+                    crate::tester::test(&state.config, std::path::Path::new(path))
+                        .map_err(|e| format!("{:#}", e)),
+                )
+            };
+            tester.scroll = 0;
+        }
+        KeyCode::Tab => {
+            if let Some(completed) = complete_path(&tester.path) {
+                tester.cursor = completed.chars().count();
+                tester.path = completed;
+            }
+        }
+        KeyCode::Up => tester.scroll = tester.scroll.saturating_sub(1),
+        KeyCode::Down => tester.scroll = (tester.scroll + 1).min(last),
+        KeyCode::PageUp => tester.scroll = tester.scroll.saturating_sub(10),
+        KeyCode::PageDown => tester.scroll = (tester.scroll + 10).min(last),
+        _ => handle_text_input(&mut tester.path, &mut tester.cursor, key),
+    }
+}
+
+/// Complete the last component of a typed path: fully if one entry matches
+/// (with a `/` after a directory), else as far as all matches agree
+fn complete_path(input: &str) -> Option<String> {
+    let (dir, prefix) = match input.rfind('/') {
+        Some(i) => input.split_at(i + 1),
+        None => ("", input),
+    };
+    let listed = if dir.is_empty() {
+        std::path::PathBuf::from(".")
+    } else {
+        crate::expand_path(std::path::Path::new(dir))
+    };
+
+    let mut matches: Vec<(String, bool)> = std::fs::read_dir(listed)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            let is_dir = entry.path().is_dir();
+            Some((name, is_dir))
+        })
+        .filter(|(name, _)| {
+            name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.'))
+        })
+        .collect();
+    matches.sort();
+
+    let completed = match matches.as_slice() {
+        [] => return None,
+        [(name, true)] => format!("{}/", name),
+        [(name, false)] => name.clone(),
+        [(first, _), rest @ ..] => {
+            let mut common = first.clone();
+            for (name, _) in rest {
+                while !name.starts_with(&common) {
+                    common.pop();
+                }
+            }
+            common
+        }
+    };
+    Some(format!("{}{}", dir, completed))
+}
+
 fn handle_about_key(state: &mut AppState, key: KeyEvent) {
     match key.code {
         KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
             state.mode = Mode::Normal;
         }
-        KeyCode::Char('g') | KeyCode::Char('G') => {
+        KeyCode::Char('g') => {
             // Open GitHub repository
             let _ = open::that("https://github.com/ricardodantas/hazelnut");
         }
@@ -453,6 +909,7 @@ fn handle_update_confirm_key(state: &mut AppState, key: KeyEvent) {
 }
 
 fn handle_settings_key(state: &mut AppState, key: KeyEvent) {
+    let key = state.keymap.navigation(key);
     let items = SettingsItem::all();
     let len = items.len();
 
@@ -460,16 +917,16 @@ fn handle_settings_key(state: &mut AppState, key: KeyEvent) {
         KeyCode::Esc => {
             state.mode = Mode::Normal;
         }
-        KeyCode::Up | KeyCode::Char('k') => {
+        KeyCode::Up => {
             state.settings_index = state.settings_index.checked_sub(1).unwrap_or(len - 1);
         }
-        KeyCode::Down | KeyCode::Char('j') => {
+        KeyCode::Down => {
             state.settings_index = (state.settings_index + 1) % len;
         }
-        KeyCode::Home | KeyCode::Char('g') => {
+        KeyCode::Home => {
             state.settings_index = 0;
         }
-        KeyCode::End | KeyCode::Char('G') => {
+        KeyCode::End => {
             state.settings_index = len - 1;
         }
         KeyCode::Enter | KeyCode::Char(' ') => {
@@ -497,9 +954,11 @@ fn handle_settings_action(state: &mut AppState) {
         SettingsItem::ThemeSelection => {
             // Switch to theme picker
             state.original_theme = Some(state.theme);
-            state.theme_picker_index = Theme::all()
+            state.theme_picker_index = state
+                .theme
+                .choices(&state.config)
                 .iter()
-                .position(|t| *t == state.theme.inner())
+                .position(|t| *t == state.theme)
                 .unwrap_or(0);
             state.mode = Mode::ThemePicker;
         }
@@ -527,7 +986,6 @@ fn handle_settings_action(state: &mut AppState) {
             ));
             save_config(state);
         }
-        #[cfg(unix)]
         SettingsItem::AutoStartOnBoot => match autostart::toggle() {
             Ok(enabled) => {
                 let status = if enabled { "enabled" } else { "disabled" };
@@ -549,14 +1007,9 @@ fn handle_settings_action(state: &mut AppState) {
             save_config(state);
         }
         SettingsItem::Notifications => {
-            state.config.general.notifications_enabled =
-                !state.config.general.notifications_enabled;
-            let status = if state.config.general.notifications_enabled {
-                "enabled"
-            } else {
-                "disabled"
-            };
-            state.set_status(format!("Notifications: {}", status));
+            let level = state.config.general.notification_level().next();
+            state.config.general.set_notification_level(level);
+            state.set_status(format!("Notifications: {}", level));
             save_config(state);
         }
     }
@@ -627,7 +1080,8 @@ fn toggle_daemon(state: &mut AppState) {
 
     // Use spawn() instead of status() so we don't block the TUI event loop.
     match Command::new(&daemon_cmd)
-        .args([arg])
+        .args(crate::instance::daemon_args())
+        .arg(arg)
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()
@@ -650,16 +1104,94 @@ fn toggle_daemon(state: &mut AppState) {
 }
 
 fn save_config(state: &mut AppState) {
-    // Always save to default path (~/.config/hazelnut/config.toml)
-    if let Err(e) = state.config.save(None) {
+    // Save to the file the config came from (~/.config/hazelnut/config.toml by default)
+    if let Err(e) = state.config.save(state.config_path.as_deref()) {
         state.set_status(format!("Failed to save config: {}", e));
+        return;
     }
-    // Restart embedded watcher when daemon is not running so it picks up changes
-    if !state.daemon_running {
+    // Rules or watches may have changed
+    state.preview_stale = true;
+    if state.daemon_running {
+        // Have the daemon pick up the change now rather than on its next poll
+        use crate::ipc::{DaemonCommand, DaemonResponse};
+        match crate::ipc::send_command(&DaemonCommand::Reload) {
+            Ok(DaemonResponse::Error { message }) => {
+                state.set_status(format!("Daemon reload failed: {}", message));
+            }
+            Err(e) => state.set_status(format!("Daemon reload failed: {}", e)),
+            Ok(_) => {}
+        }
+    } else {
+        // Restart embedded watcher when daemon is not running so it picks up changes
         state.watcher_needs_restart = true;
     }
 }
 
+/// A name for a copy of rule `name` that no other rule has
+fn copy_name(rules: &[crate::rules::Rule], name: &str) -> String {
+    let taken = |candidate: &str| rules.iter().any(|r| r.name == candidate);
+    let mut candidate = format!("{} (copy)", name);
+    let mut n = 2;
+    while taken(&candidate) {
+        candidate = format!("{} (copy {})", name, n);
+        n += 1;
+    }
+    candidate
+}
+
+/// Why the rule in the editor can't be saved, if it can't
+fn check_rule(state: &AppState, editor: &RuleEditorState) -> Option<String> {
+    let name = editor.name.trim();
+    if name.is_empty() {
+        return Some("Rule name is required".to_string());
+    }
+    let duplicate = state
+        .config
+        .rules
+        .iter()
+        .enumerate()
+        .any(|(i, r)| r.name == name && Some(i) != editor.editing_index);
+    if duplicate {
+        return Some(format!("A rule named '{}' already exists", name));
+    }
+    let missing = match editor.action_type {
+        ActionTypeSelection::Move | ActionTypeSelection::Copy => editor
+            .action_destination
+            .trim()
+            .is_empty()
+            .then_some("a destination"),
+        ActionTypeSelection::Rename => editor
+            .action_pattern
+            .trim()
+            .is_empty()
+            .then_some("a pattern"),
+        ActionTypeSelection::Run => editor
+            .action_command
+            .trim()
+            .is_empty()
+            .then_some("a command"),
+        ActionTypeSelection::Script => editor
+            .action_script
+            .trim()
+            .is_empty()
+            .then_some("a script written in the config file"),
+        ActionTypeSelection::Plugin => editor
+            .plugin_call
+            .is_none()
+            .then_some("a plugin set in the config file"),
+        _ => None,
+    };
+    if let Some(missing) = missing {
+        return Some(format!("{} needs {}", editor.action_type.name(), missing));
+    }
+    editor
+        .to_rule()
+        .condition
+        .validate()
+        .err()
+        .map(|e| format!("Invalid condition: {:#}", e))
+}
+
 fn handle_rule_editor_key(state: &mut AppState, key: KeyEvent) {
     let Some(ref mut editor) = state.rule_editor else {
         state.mode = Mode::Normal;
@@ -682,19 +1214,29 @@ fn handle_rule_editor_key(state: &mut AppState, key: KeyEvent) {
             editor.field = editor.field.prev();
         }
         KeyCode::Enter => {
-            // Save the rule
-            if editor.name.trim().is_empty() {
-                state.set_status("Rule name is required");
+            // Save the rule, unless the daemon would reject it
+            let editor = editor.clone();
+            if let Some(problem) = check_rule(state, &editor) {
+                state.set_status(problem);
                 return;
             }
 
-            let rule = editor.to_rule();
+            let mut rule = editor.to_rule();
+            rule.name = rule.name.trim().to_string();
             let rule_name = rule.name.clone();
 
             if let Some(idx) = editor.editing_index {
                 // Update existing rule
                 if idx < state.config.rules.len() {
-                    state.config.rules[idx] = rule;
+                    // Keep watches limited to this rule pointing at it
+                    let old_name = std::mem::replace(&mut state.config.rules[idx], rule).name;
+                    if old_name != rule_name {
+                        for watch in &mut state.config.watches {
+                            for name in watch.rules.iter_mut().filter(|n| **n == old_name) {
+                                *name = rule_name.clone();
+                            }
+                        }
+                    }
                     state.set_status(format!("Updated rule '{}'", rule_name));
                 }
             } else {
@@ -728,6 +1270,14 @@ fn handle_rule_editor_field_input(editor: &mut RuleEditorState, key: KeyEvent) {
                 editor.enabled = !editor.enabled;
             }
         }
+        RuleEditorField::StopProcessing => {
+            if matches!(
+                key.code,
+                KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right
+            ) {
+                editor.stop_processing = !editor.stop_processing;
+            }
+        }
         RuleEditorField::Extension => {
             handle_text_input(&mut editor.extension, &mut editor.cursor_extension, key)
         }
@@ -983,10 +1533,10 @@ fn handle_watch_editor_field_input(editor: &mut WatchEditorState, key: KeyEvent)
                 return;
             }
             match key.code {
-                KeyCode::Up | KeyCode::Char('k') => {
+                KeyCode::Up => {
                     editor.rules_cursor = editor.rules_cursor.saturating_sub(1);
                 }
-                KeyCode::Down | KeyCode::Char('j') => {
+                KeyCode::Down => {
                     if editor.rules_cursor < rule_count.saturating_sub(1) {
                         editor.rules_cursor += 1;
                     }
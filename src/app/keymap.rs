@@ -0,0 +1,414 @@
+//! Configurable keybindings
+//!
+//! `[keybindings]` maps actions to keys, e.g. `quit = "x"` or
+//! `down = ["j", "ctrl-n"]`. Actions not listed keep their default keys, and
+//! a key bound in the config is taken away from other actions' defaults.
+//! Navigation keys are turned into the arrow, Home/End and PgUp/PgDn keys
+//! the views handle, so rebinding them works in every list.
+
+use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+
+use crate::config::KeyList;
+
+/// Something a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyAction {
+    Quit,
+    Help,
+    NextView,
+    PrevView,
+    Settings,
+    Theme,
+    About,
+    TestRules,
+    /// Scan every watched folder now
+    Scan,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    /// Enable or disable the selected rule
+    ToggleRule,
+    /// Undo the selected History entry
+    Undo,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 17] = [
+        Self::Quit,
+        Self::Help,
+        Self::NextView,
+        Self::PrevView,
+        Self::Settings,
+        Self::Theme,
+        Self::About,
+        Self::TestRules,
+        Self::Scan,
+        Self::Up,
+        Self::Down,
+        Self::PageUp,
+        Self::PageDown,
+        Self::Top,
+        Self::Bottom,
+        Self::ToggleRule,
+        Self::Undo,
+    ];
+
+    /// Name in `[keybindings]`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::Help => "help",
+            Self::NextView => "next_view",
+            Self::PrevView => "prev_view",
+            Self::Settings => "settings",
+            Self::Theme => "theme",
+            Self::About => "about",
+            Self::TestRules => "test_rules",
+            Self::Scan => "scan",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::PageUp => "page_up",
+            Self::PageDown => "page_down",
+            Self::Top => "top",
+            Self::Bottom => "bottom",
+            Self::ToggleRule => "toggle_rule",
+            Self::Undo => "undo",
+        }
+    }
+
+    fn defaults(self) -> &'static [&'static str] {
+        match self {
+            Self::Quit => &["q", "ctrl-q"],
+            Self::Help => &["?", "f1"],
+            Self::NextView => &["tab"],
+            Self::PrevView => &["backtab"],
+            Self::Settings => &["s"],
+            Self::Theme => &["t"],
+            Self::About => &["A"],
+            Self::TestRules => &["T"],
+            Self::Scan => &["S"],
+            Self::Up => &["up", "k"],
+            Self::Down => &["down", "j"],
+            Self::PageUp => &["pageup"],
+            Self::PageDown => &["pagedown"],
+            Self::Top => &["home", "g"],
+            Self::Bottom => &["end", "G"],
+            Self::ToggleRule => &["enter", "space"],
+            Self::Undo => &["u"],
+        }
+    }
+
+    /// Whether the action works in every view (otherwise views handle it)
+    fn is_global(self) -> bool {
+        matches!(
+            self,
+            Self::Quit
+                | Self::Help
+                | Self::NextView
+                | Self::PrevView
+                | Self::Settings
+                | Self::Theme
+                | Self::About
+                | Self::TestRules
+                | Self::Scan
+        )
+    }
+
+    /// The key views handle for a navigation action
+    fn navigation_key(self) -> Option<KeyCode> {
+        match self {
+            Self::Up => Some(KeyCode::Up),
+            Self::Down => Some(KeyCode::Down),
+            Self::PageUp => Some(KeyCode::PageUp),
+            Self::PageDown => Some(KeyCode::PageDown),
+            Self::Top => Some(KeyCode::Home),
+            Self::Bottom => Some(KeyCode::End),
+            _ => None,
+        }
+    }
+}
+
+/// A key with its modifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    /// Parse e.g. `q`, `G`, `ctrl-n`, `alt-enter`, `space`, `pagedown` or `f5`
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        // A lone "-" is the minus key, not a separator
+        while let Some((modifier, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => anyhow::bail!("Unknown modifier '{}' in key '{}'", modifier, s),
+            };
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => anyhow::bail!("Unknown key '{}'", s),
+                },
+            },
+        };
+
+        // shift-tab is how terminals send backtab; shift-a is A
+        let code = match code {
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+        Ok(Self {
+            code,
+            modifiers: modifiers - KeyModifiers::SHIFT,
+        })
+    }
+
+    /// Whether a pressed key is this one. Shift isn't compared, as it's
+    /// already in the character (or the backtab key).
+    fn matches(&self, key: &KeyEvent) -> bool {
+        self.code == key.code && self.modifiers == key.modifiers - KeyModifiers::SHIFT
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "Shift+Tab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Keys of every action
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: BTreeMap<KeyAction, Vec<Key>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = KeyAction::ALL
+            .iter()
+            .map(|&action| {
+                let keys = action
+                    .defaults()
+                    .iter()
+                    .map(|key| Key::parse(key).expect("valid default key"))
+                    .collect();
+                (action, keys)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// The default keys with `[keybindings]` applied
+    pub fn new(config: &BTreeMap<String, KeyList>) -> Result<Self> {
+        let mut keymap = Self::default();
+        let mut configured: Vec<(Key, KeyAction)> = Vec::new();
+        for (name, keys) in config {
+            let action = KeyAction::ALL
+                .into_iter()
+                .find(|action| action.name() == name)
+                .with_context(|| format!("Unknown action '{}' in [keybindings]", name))?;
+            let keys = keys
+                .as_slice()
+                .iter()
+                .map(|key| Key::parse(key))
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("[keybindings] {}", name))?;
+            for &key in &keys {
+                if let Some((_, other)) = configured
+                    .iter()
+                    .find(|(k, other)| *k == key && *other != action)
+                {
+                    anyhow::bail!(
+                        "Key '{}' is bound to both {} and {} in [keybindings]",
+                        key,
+                        other.name(),
+                        action.name()
+                    );
+                }
+                configured.push((key, action));
+            }
+            keymap.bindings.insert(action, keys);
+        }
+
+        // A key given to an action in the config isn't another action's default
+        for (action, keys) in &mut keymap.bindings {
+            keys.retain(|key| {
+                configured
+                    .iter()
+                    .all(|(k, other)| k != key || other == action)
+            });
+        }
+        Ok(keymap)
+    }
+
+    /// The global action bound to a key, if any
+    pub fn global_action(&self, key: &KeyEvent) -> Option<KeyAction> {
+        self.bindings
+            .iter()
+            .filter(|(action, _)| action.is_global())
+            .find(|(_, keys)| keys.iter().any(|k| k.matches(key)))
+            .map(|(&action, _)| action)
+    }
+
+    /// Whether a key is bound to an action
+    pub fn is(&self, action: KeyAction, key: &KeyEvent) -> bool {
+        self.bindings
+            .get(&action)
+            .is_some_and(|keys| keys.iter().any(|k| k.matches(key)))
+    }
+
+    /// A key bound to a navigation action, as the key the views handle
+    /// (e.g. `j` as ↓). Other keys are returned as they are.
+    pub fn navigation(&self, key: KeyEvent) -> KeyEvent {
+        self.bindings
+            .iter()
+            .filter(|(_, keys)| keys.iter().any(|k| k.matches(&key)))
+            .find_map(|(action, _)| action.navigation_key())
+            .map_or(key, |code| KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    /// Keys of an action for help texts, e.g. `q/Ctrl+q`
+    pub fn label(&self, action: KeyAction) -> String {
+        let keys = self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[]);
+        if keys.is_empty() {
+            return "(unbound)".to_string();
+        }
+        keys.iter()
+            .map(Key::to_string)
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// The first key of an action, for short hints
+    pub fn first(&self, action: KeyAction) -> String {
+        self.bindings
+            .get(&action)
+            .and_then(|keys| keys.first())
+            .map_or_else(|| "-".to_string(), Key::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_keymap() {
+        let defaults = Keymap::default();
+        assert_eq!(
+            defaults.global_action(&press(KeyCode::Char('q'), KeyModifiers::NONE)),
+            Some(KeyAction::Quit)
+        );
+        // Terminals send shift with capitals and backtab
+        assert_eq!(
+            defaults.global_action(&press(KeyCode::Char('T'), KeyModifiers::SHIFT)),
+            Some(KeyAction::TestRules)
+        );
+        assert_eq!(
+            defaults.global_action(&press(KeyCode::BackTab, KeyModifiers::SHIFT)),
+            Some(KeyAction::PrevView)
+        );
+
+        let config: BTreeMap<String, KeyList> =
+            toml::from_str("quit = \"x\"\ndown = [\"n\", \"ctrl-j\"]\nscan = \"q\"\n").unwrap();
+        let keymap = Keymap::new(&config).unwrap();
+        assert_eq!(
+            keymap.global_action(&press(KeyCode::Char('x'), KeyModifiers::NONE)),
+            Some(KeyAction::Quit)
+        );
+        assert_eq!(
+            keymap.global_action(&press(KeyCode::Char('q'), KeyModifiers::NONE)),
+            Some(KeyAction::Scan)
+        );
+        assert_eq!(
+            keymap
+                .navigation(press(KeyCode::Char('j'), KeyModifiers::CONTROL))
+                .code,
+            KeyCode::Down
+        );
+        // j isn't bound anymore
+        assert_eq!(
+            keymap
+                .navigation(press(KeyCode::Char('j'), KeyModifiers::NONE))
+                .code,
+            KeyCode::Char('j')
+        );
+        assert_eq!(keymap.label(KeyAction::Down), "n/Ctrl+j");
+        assert_eq!(keymap.label(KeyAction::Quit), "x");
+
+        for bad in [
+            "jump = \"j\"",
+            "quit = \"hyper-q\"",
+            "quit = \"x\"\nhelp = \"x\"",
+        ] {
+            let config: BTreeMap<String, KeyList> = toml::from_str(bad).unwrap();
+            assert!(Keymap::new(&config).is_err(), "{}", bad);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! TUI Application module
 
 mod events;
+mod keymap;
 mod state;
 mod ui;
 
@@ -8,7 +9,7 @@ pub use state::AppState;
 
 use anyhow::Result;
 use crossterm::{
-    event::{self, Event},
+    event::{self, Event, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
@@ -25,15 +26,71 @@ use std::sync::mpsc;
 /// Messages from background tasks
 enum BackgroundMsg {
     UpdateAvailable(String),
+    /// The daemon's activity stream connected (true) or closed (false);
+    /// only the Unix daemon streams its activity
+    #[cfg_attr(not(unix), allow(dead_code))]
+    ActivityStream(bool),
+    /// A live event from the daemon
+    #[cfg_attr(not(unix), allow(dead_code))]
+    Activity(crate::activity::ActivityEvent),
+    /// The dry-run preview finished
+    Preview(Vec<crate::preview::WatchPreview>),
+    /// A duplicates scan finished
+    Duplicates(std::result::Result<Vec<crate::dedupe::DuplicateGroup>, String>),
+}
+
+/// Check the settings only the TUI reads, with the key of each problem
+pub fn check_settings(config: &Config) -> Vec<(&'static str, anyhow::Error)> {
+    let mut problems = Vec::new();
+    if let Err(e) = Theme::load(config) {
+        problems.push(("theme", e));
+    }
+    if let Err(e) = keymap::Keymap::new(&config.keybindings) {
+        problems.push(("keybindings", e));
+    }
+    problems
 }
 
 /// Run the TUI application
 pub async fn run(config_path: Option<PathBuf>) -> Result<()> {
-    // Load config from specified path or default (~/.config/hazelnut/config.toml)
+    run_with(config_path, None).await
+}
+
+/// Run the TUI on the Duplicates view of `root`, with `review` as the
+/// folder duplicates are moved to
+pub async fn run_dedupe(
+    config_path: Option<PathBuf>,
+    root: PathBuf,
+    options: crate::dedupe::ScanOptions,
+    review: PathBuf,
+) -> Result<()> {
+    let dedupe = state::DedupeState::new(root, options, review);
+    run_with(config_path, Some(dedupe)).await
+}
+
+async fn run_with(config_path: Option<PathBuf>, dedupe: Option<state::DedupeState>) -> Result<()> {
+    // Load config from specified path or default (~/.config/hazelnut/config.toml,
+    // or <instance>.toml)
     let config = Config::load(config_path.as_deref())?;
 
     // Load theme from config or use default
-    let theme = Theme::load(&config);
+    let theme = Theme::load(&config)?;
+    let keymap = keymap::Keymap::new(&config.keybindings)?;
+
+    // The TUI's own log would draw over the screen, so it goes to a file
+    let rotation = crate::logfile::Rotation::from_config(&config.general);
+    if let Err(e) = crate::logfile::init_at(&crate::tui_log_file(), rotation) {
+        eprintln!("{:#}", e);
+    }
+
+    // Give the terminal back if the TUI panics, or the console is left in
+    // raw mode and the panic message is lost on the alternate screen
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(std::io::stdout(), LeaveAlternateScreen);
+        default_hook(info);
+    }));
 
     // Initialize terminal
     enable_raw_mode()?;
@@ -45,6 +102,27 @@ pub async fn run(config_path: Option<PathBuf>) -> Result<()> {
 
     // Create app state
     let mut state = AppState::new(config.clone(), theme);
+    state.keymap = keymap;
+    state.config_path = config_path;
+    if dedupe.is_some() {
+        // Resolutions delete into the quarantine and go in the history
+        crate::quarantine::set(config.quarantine.clone());
+        crate::rules::set_verify_moves(config.general.verify_moves);
+        crate::progress::set_max_rate(
+            config
+                .general
+                .max_transfer_rate
+                .as_ref()
+                .map(|rate| rate.bytes()),
+        );
+        crate::history::init(
+            config.general.history_retention_days,
+            config.general.history_checksums,
+        );
+        crate::hashing::init(config.general.hash_algorithm, config.general.hash_threads);
+        state.view = state::View::Duplicates;
+        state.dedupe = dedupe;
+    }
 
     // Start daemon on launch if configured (Unix only)
     #[cfg(unix)]
@@ -59,7 +137,8 @@ pub async fn run(config_path: Option<PathBuf>) -> Result<()> {
             .unwrap_or_else(|| std::path::PathBuf::from("hazelnutd"));
 
         match Command::new(&daemon_cmd)
-            .args(["start"])
+            .args(crate::instance::daemon_args())
+            .arg("start")
             .stdout(Stdio::null())
             .stderr(Stdio::null())
             .spawn()
@@ -76,8 +155,14 @@ pub async fn run(config_path: Option<PathBuf>) -> Result<()> {
 
     // Spawn background update check
     let (tx, rx) = mpsc::channel();
+    let bg_tx = tx.clone();
+    #[cfg(unix)]
+    {
+        let tx = tx.clone();
+        std::thread::spawn(move || stream_daemon_activity(tx));
+    }
     std::thread::spawn(move || {
-        let check = crate::check_for_updates_crates_io_timeout(std::time::Duration::from_secs(5));
+        let check = crate::check_for_updates_timeout(std::time::Duration::from_secs(5));
         if let crate::VersionCheck::UpdateAvailable { latest, .. } = check {
             let _ = tx.send(BackgroundMsg::UpdateAvailable(latest));
         }
@@ -100,7 +185,9 @@ pub async fn run(config_path: Option<PathBuf>) -> Result<()> {
     };
 
     // Main loop
-    let result = run_app(&mut terminal, &mut state, rx, &mut embedded_watcher);
+    let result = run_app(&mut terminal, &mut state, bg_tx, rx, &mut embedded_watcher);
+    crate::stats::flush();
+    crate::date_added::flush();
 
     // Restore terminal
     disable_raw_mode()?;
@@ -113,18 +200,53 @@ pub async fn run(config_path: Option<PathBuf>) -> Result<()> {
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
     state: &mut AppState,
+    bg_tx: mpsc::Sender<BackgroundMsg>,
     bg_rx: mpsc::Receiver<BackgroundMsg>,
     embedded_watcher: &mut Option<crate::Watcher>,
 ) -> Result<()> {
+    // Activity of the embedded watcher, published in this process
+    let mut local_activity = crate::activity::subscribe();
+
     loop {
         // Check for background messages (non-blocking)
-        if let Ok(msg) = bg_rx.try_recv() {
+        while let Ok(msg) = bg_rx.try_recv() {
             match msg {
                 BackgroundMsg::UpdateAvailable(version) => {
                     state.set_update_available(version);
                 }
+                BackgroundMsg::ActivityStream(connected) => {
+                    state.activity_stream = connected;
+                }
+                BackgroundMsg::Activity(event) => state.push_activity(&event),
+                BackgroundMsg::Duplicates(result) => {
+                    if let Some(dedupe) = state.dedupe.as_mut() {
+                        dedupe.scanning = false;
+                        match result {
+                            Ok(groups) => {
+                                dedupe.groups = groups;
+                                dedupe.error = None;
+                            }
+                            Err(e) => {
+                                dedupe.groups.clear();
+                                dedupe.error = Some(e);
+                            }
+                        }
+                        dedupe.selected =
+                            dedupe.selected.min(dedupe.groups.len().saturating_sub(1));
+                    }
+                }
+                BackgroundMsg::Preview(preview) => {
+                    state.preview = Some(preview);
+                    state.preview_loading = false;
+                    state.preview_scroll = state
+                        .preview_scroll
+                        .min(state.preview_rows().saturating_sub(1));
+                }
             }
         }
+        while let Ok(event) = local_activity.try_recv() {
+            state.push_activity(&event);
+        }
 
         // Draw UI
         terminal.draw(|frame| ui::render(frame, state))?;
@@ -136,12 +258,49 @@ fn run_app(
             terminal.draw(|frame| ui::render(frame, state))?;
         }
 
-        // Handle events
-        if event::poll(Duration::from_millis(100))?
-            && let Event::Key(key) = event::read()?
-            && key.kind == crossterm::event::KeyEventKind::Press
+        // Handle events, all that are queued so fast typing doesn't lag
+        let mut timeout = Duration::from_millis(100);
+        while !state.should_quit && event::poll(timeout)? {
+            timeout = Duration::ZERO;
+            match event::read()? {
+                // Windows reports key releases too; act on presses (and repeats) only
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    events::handle_key(state, normalize_key(key));
+                }
+                // The legacy Windows console keeps what was drawn outside the
+                // new size, so start from a clean screen
+                Event::Resize(..) => terminal.clear()?,
+                _ => {}
+            }
+        }
+
+        // Build the dry-run preview off the UI thread, when it's being looked at
+        if state.view == state::View::Preview
+            && (state.preview.is_none() || state.preview_stale)
+            && !state.preview_loading
         {
-            events::handle_key(state, key);
+            state.preview_stale = false;
+            state.preview_loading = true;
+            let config = state.config.clone();
+            let tx = bg_tx.clone();
+            std::thread::spawn(move || {
+                let _ = tx.send(BackgroundMsg::Preview(crate::preview::build(&config)));
+            });
+        }
+
+        // Look for duplicates off the UI thread; hashing a large tree takes a while
+        if let Some(dedupe) = state.dedupe.as_mut()
+            && dedupe.stale
+            && !dedupe.scanning
+        {
+            dedupe.stale = false;
+            dedupe.scanning = true;
+            let (root, options) = (dedupe.root.clone(), dedupe.options.clone());
+            let tx = bg_tx.clone();
+            std::thread::spawn(move || {
+                let result = crate::dedupe::scan(&root, &options).map_err(|e| format!("{:#}", e));
+                let _ = tx.send(BackgroundMsg::Duplicates(result));
+            });
         }
 
         // Stop embedded watcher if daemon was started
@@ -164,9 +323,32 @@ fn run_app(
             }
         }
 
+        // Scan every watched folder, in the daemon if it's running
+        if state.scan_requested {
+            state.scan_requested = false;
+            if state.daemon_running {
+                use crate::ipc::{DaemonCommand, DaemonResponse};
+                match crate::ipc::send_command(&DaemonCommand::Scan { path: None }) {
+                    Ok(DaemonResponse::Ok) => {}
+                    Ok(DaemonResponse::Error { message }) => {
+                        state.set_status(format!("Scan failed: {}", message))
+                    }
+                    Ok(_) => state.set_status("Scan failed: unexpected response from daemon"),
+                    Err(e) => state.set_status(format!("Scan failed: {}", e)),
+                }
+            } else if let Some(watcher) = embedded_watcher.as_mut() {
+                for watch in &state.config.watches {
+                    if !crate::sftp::is_remote(&watch.path) {
+                        watcher.scan(&crate::expand_path(&watch.path), watch.recursive);
+                    }
+                }
+            }
+        }
+
         // Process embedded watcher events in a background thread to avoid blocking the UI
         if let Some(watcher) = embedded_watcher {
-            // Only poll events (non-blocking) and spawn processing if there are events
+            // Only poll events (non-blocking) and process if there are events
+            // or downloads waiting to finish
             let events = match watcher.poll() {
                 Ok(events) => events,
                 Err(e) => {
@@ -174,7 +356,7 @@ fn run_app(
                     vec![]
                 }
             };
-            if !events.is_empty() {
+            if !events.is_empty() || watcher.has_pending() {
                 match watcher.process_polled_events(events) {
                     Ok(count) if count > 0 => {
                         tracing::info!("Processed {} files", count);
@@ -187,6 +369,9 @@ fn run_app(
             }
         }
 
+        crate::stats::flush_if_due();
+        crate::date_added::flush_if_due();
+
         // Tick for animations
         state.tick();
 
@@ -198,22 +383,86 @@ fn run_app(
     Ok(())
 }
 
+/// Windows reports characters typed with AltGr (e.g. `@`, `{` or `\` on
+/// many European layouts) as Ctrl+Alt+character; treat them as plain
+/// characters so they type and match keybindings like on other platforms
+fn normalize_key(key: KeyEvent) -> KeyEvent {
+    let altgr = KeyModifiers::CONTROL | KeyModifiers::ALT;
+    match key.code {
+        event::KeyCode::Char(c) if key.modifiers.contains(altgr) && !c.is_ascii_alphanumeric() => {
+            KeyEvent::new(key.code, key.modifiers - altgr)
+        }
+        _ => key,
+    }
+}
+
+/// How often to try subscribing to the daemon's activity while it's not running
+#[cfg(unix)]
+const ACTIVITY_RETRY: Duration = Duration::from_secs(2);
+
+/// Forward the daemon's live activity to the UI, reconnecting whenever the
+/// daemon (re)starts. Returns once the UI is gone.
+#[cfg(unix)]
+fn stream_daemon_activity(tx: mpsc::Sender<BackgroundMsg>) {
+    loop {
+        if let Ok(events) = crate::ipc::subscribe() {
+            if tx.send(BackgroundMsg::ActivityStream(true)).is_err() {
+                return;
+            }
+            for event in events {
+                if tx.send(BackgroundMsg::Activity(event)).is_err() {
+                    return;
+                }
+            }
+            if tx.send(BackgroundMsg::ActivityStream(false)).is_err() {
+                return;
+            }
+        }
+        std::thread::sleep(ACTIVITY_RETRY);
+    }
+}
+
 /// Create an embedded file watcher for use when the daemon is not running.
 /// This enables file watching on all platforms (including Windows).
 fn create_embedded_watcher(config: &crate::Config) -> Result<crate::Watcher> {
-    let engine = crate::RuleEngine::new(config.rules.clone());
+    crate::quiet_hours::set(config.quiet_hours.clone());
+    crate::plugins::set(config.plugins.clone());
+    crate::quarantine::set(config.quarantine.clone());
+    crate::rules::set_verify_moves(config.general.verify_moves);
+    crate::progress::set_max_rate(
+        config
+            .general
+            .max_transfer_rate
+            .as_ref()
+            .map(|rate| rate.bytes()),
+    );
+    crate::stats::init(config.general.stats_retention_days);
+    crate::history::init(
+        config.general.history_retention_days,
+        config.general.history_checksums,
+    );
+    crate::hashing::init(config.general.hash_algorithm, config.general.hash_threads);
+    let engine = crate::RuleEngine::new(config.rules.clone())
+        .with_hooks(config.hooks.clone())
+        .with_shadow(crate::shadow::ShadowRules::from_config(config))
+        .with_dry_run(config.general.dry_run);
     let mut watcher = crate::Watcher::new(
         engine,
         config.general.polling_interval_secs,
         config.general.debounce_seconds,
-    )?;
+    )?
+    .with_stability(crate::watcher::Stability::new(
+        config.general.stable_seconds,
+        config.general.temp_extensions.clone(),
+    ))
+    .with_batching(crate::watcher::BatchLimits::from_config(&config.general));
 
     for watch in &config.watches {
         let expanded_path = crate::expand_path(&watch.path);
-        if let Err(e) =
-            watcher.watch_with_rules(&expanded_path, watch.recursive, watch.rules.clone())
-        {
+        if let Err(e) = watcher.add_watch(watch) {
             tracing::error!("Failed to watch {}: {}", expanded_path.display(), e);
+        } else if watch.scan_on_start {
+            watcher.scan(&expanded_path, watch.recursive);
         }
     }
 
@@ -1,33 +1,25 @@
 //! Application state management
 
+use super::keymap::Keymap;
 use crate::config::Config;
-use crate::rules::{Action, Condition, Rule};
+use crate::plugins::PluginCall;
+use crate::rules::{Action, Condition, OnConflict, Rule, TierStage};
 use crate::theme::Theme;
 use std::collections::VecDeque;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Check if the daemon is currently running by checking the PID file
 #[cfg(unix)]
 fn is_daemon_running() -> bool {
-    let pid_file = dirs::state_dir()
-        .unwrap_or_else(|| {
-            dirs::home_dir()
-                .map(|h| h.join(".local").join("state"))
-                .unwrap_or_else(|| PathBuf::from("/tmp"))
-        })
-        .join("hazelnut")
-        .join("hazelnutd.pid");
+    crate::daemon_pid().is_some()
+}
 
-    if let Ok(pid_str) = std::fs::read_to_string(&pid_file)
-        && let Ok(pid) = pid_str.trim().parse::<i32>()
-    {
-        // Check if process is running using kill -0
-        return crate::process_is_running(pid);
-    }
-    false
+#[cfg(windows)]
+fn is_daemon_running() -> bool {
+    crate::service::is_running()
 }
 
-#[cfg(not(unix))]
+#[cfg(not(any(unix, windows)))]
 fn is_daemon_running() -> bool {
     false
 }
@@ -40,6 +32,8 @@ pub enum Mode {
     Normal,
     /// Theme picker dialog
     ThemePicker,
+    /// Rule preset picker dialog
+    PresetPicker,
     /// Help dialog
     Help,
     /// Settings dialog
@@ -58,13 +52,16 @@ pub enum Mode {
     UpdateConfirm,
     /// Update in progress
     Updating,
+    /// Rule tester dialog
+    RuleTester,
+    /// Typing a search in the log view
+    LogSearch,
 }
 
 /// Settings menu items
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SettingsItem {
     DaemonControl,
-    #[cfg(unix)]
     AutoStartOnBoot,
     ThemeSelection,
     PollingInterval,
@@ -77,7 +74,6 @@ impl SettingsItem {
     pub fn all() -> &'static [SettingsItem] {
         &[
             SettingsItem::DaemonControl,
-            #[cfg(unix)]
             SettingsItem::AutoStartOnBoot,
             SettingsItem::ThemeSelection,
             SettingsItem::PollingInterval,
@@ -90,7 +86,6 @@ impl SettingsItem {
     pub fn label(&self) -> &'static str {
         match self {
             SettingsItem::DaemonControl => "Start/Stop Daemon",
-            #[cfg(unix)]
             SettingsItem::AutoStartOnBoot => "Auto-start on Boot",
             SettingsItem::ThemeSelection => "Theme",
             SettingsItem::PollingInterval => "Polling Interval",
@@ -103,7 +98,6 @@ impl SettingsItem {
     pub fn icon(&self) -> &'static str {
         match self {
             SettingsItem::DaemonControl => "🔌",
-            #[cfg(unix)]
             SettingsItem::AutoStartOnBoot => "🖥️",
             SettingsItem::ThemeSelection => "🎨",
             SettingsItem::PollingInterval => "⏱",
@@ -126,9 +120,18 @@ pub struct AppState {
     /// Loaded configuration
     pub config: Config,
 
+    /// File the configuration was loaded from and is saved to (None = default)
+    pub config_path: Option<PathBuf>,
+
     /// Current theme
     pub theme: Theme,
 
+    /// Keys of the TUI's actions, from `[keybindings]`
+    pub keymap: Keymap,
+
+    /// A scan of every watched folder was asked for
+    pub scan_requested: bool,
+
     /// Index of selected rule (if in rules view)
     pub selected_rule: Option<usize>,
 
@@ -144,9 +147,64 @@ pub struct AppState {
     /// Status message to display
     pub status_message: Option<String>,
 
-    /// Scroll offset for log view
+    /// Selected entry in the log view, counted from the newest one shown
     pub log_scroll: usize,
 
+    /// Search in the log view (case-insensitive; matches message, file and rule)
+    pub log_query: String,
+
+    /// Cursor position in the log search
+    pub log_query_cursor: usize,
+
+    /// Severity filter of the log view
+    pub log_level_filter: LevelFilter,
+
+    /// Only show entries of this rule in the log view
+    pub log_rule_filter: Option<String>,
+
+    /// Files rules were applied to or failed on, for the dashboard's live
+    /// activity pane (oldest first)
+    pub activity: VecDeque<crate::activity::ActivityEvent>,
+
+    /// Entries the activity pane is scrolled back from the newest
+    pub activity_scroll: usize,
+
+    /// Dry-run preview of the watched folders (None until built)
+    pub preview: Option<Vec<crate::preview::WatchPreview>>,
+
+    /// Flag: build the preview again (set on refresh and config changes)
+    pub preview_stale: bool,
+
+    /// The preview is being built in the background
+    pub preview_loading: bool,
+
+    /// Preview rows scrolled past
+    pub preview_scroll: usize,
+
+    /// Hide files no rule would handle in the preview
+    pub preview_matched_only: bool,
+
+    /// Recent moves and renames, newest first
+    pub history: Vec<crate::history::HistoryEntry>,
+
+    /// The history file when `history` was read, to reload it on changes
+    pub history_stamp: Option<(i64, i64)>,
+
+    /// Currently selected history entry
+    pub selected_history: Option<usize>,
+
+    /// Statistics of the Stats view's period (None until loaded)
+    pub stats: Option<crate::stats::Summary>,
+
+    /// Period the Stats view covers
+    pub stats_period: StatsPeriod,
+
+    /// Scroll offset of the Stats view's rule table
+    pub stats_scroll: usize,
+
+    /// Duplicates view (None until a folder is scanned)
+    pub dedupe: Option<DedupeState>,
+
     /// Show help popup (deprecated, use mode instead)
     pub show_help: bool,
 
@@ -156,6 +214,9 @@ pub struct AppState {
     /// Theme picker index
     pub theme_picker_index: usize,
 
+    /// Rule preset picker index
+    pub preset_picker_index: usize,
+
     /// Settings dialog selected item index
     pub settings_index: usize,
 
@@ -168,6 +229,9 @@ pub struct AppState {
     /// Watch editor state
     pub watch_editor: Option<WatchEditorState>,
 
+    /// Rule tester state (kept between openings)
+    pub rule_tester: RuleTesterState,
+
     /// Update available notification
     pub update_available: Option<String>,
 
@@ -186,8 +250,21 @@ pub struct AppState {
     /// Cached file position for daemon log reading
     pub log_file_position: u64,
 
+    /// Whether the daemon's activity is streamed over IPC (the log file
+    /// then only provides history)
+    pub activity_stream: bool,
+
     /// Flag: watcher needs restart (set when daemon is stopped from settings)
     pub watcher_needs_restart: bool,
+
+    /// Large copies/moves currently in progress
+    pub transfers: Vec<crate::progress::TransferProgress>,
+
+    /// Folder scans currently running
+    pub scans: Vec<crate::progress::ScanProgress>,
+
+    /// Watches paused at runtime (canonical paths)
+    pub paused_watches: Vec<PathBuf>,
 }
 
 /// Available views in the TUI
@@ -198,6 +275,46 @@ pub enum View {
     Rules,
     Watches,
     Log,
+    /// What the rules would do with the files already in watched folders
+    Preview,
+    /// Recent moves and renames, which can be undone
+    History,
+    /// Per-rule counts and recent activity
+    Stats,
+    /// Groups of identical files under a folder
+    Duplicates,
+}
+
+/// Period the Stats view covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsPeriod {
+    #[default]
+    Day,
+    Week,
+}
+
+impl StatsPeriod {
+    pub fn toggle(self) -> Self {
+        match self {
+            Self::Day => Self::Week,
+            Self::Week => Self::Day,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Day => "Last 24 hours",
+            Self::Week => "Last 7 days",
+        }
+    }
+
+    /// Sparkline slots and hours per slot
+    pub fn slots(self) -> (usize, i64) {
+        match self {
+            Self::Day => (24, 1),
+            Self::Week => (42, 4),
+        }
+    }
 }
 
 /// A log entry for activity tracking
@@ -218,40 +335,108 @@ pub enum LogLevel {
     Error,
 }
 
+/// Severity filter of the log view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LevelFilter {
+    #[default]
+    All,
+    Errors,
+    /// Warnings and errors
+    Warnings,
+    Successes,
+}
+
+impl LevelFilter {
+    pub fn next(self) -> Self {
+        match self {
+            Self::All => Self::Errors,
+            Self::Errors => Self::Warnings,
+            Self::Warnings => Self::Successes,
+            Self::Successes => Self::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Errors => "errors",
+            Self::Warnings => "warnings & errors",
+            Self::Successes => "successes",
+        }
+    }
+
+    pub fn matches(self, level: LogLevel) -> bool {
+        match self {
+            Self::All => true,
+            Self::Errors => level == LogLevel::Error,
+            Self::Warnings => matches!(level, LogLevel::Warning | LogLevel::Error),
+            Self::Successes => level == LogLevel::Success,
+        }
+    }
+}
+
 impl AppState {
     /// Create a new application state from config
     pub fn new(config: Config, theme: Theme) -> Self {
         // Find current theme index
-        let theme_picker_index = Theme::all()
+        let theme_picker_index = theme
+            .choices(&config)
             .iter()
-            .position(|t| *t == theme.inner())
+            .position(|t| *t == theme)
             .unwrap_or(0);
 
         let mut state = Self {
             view: View::default(),
             mode: Mode::default(),
             config,
+            config_path: None,
             theme,
+            keymap: Keymap::default(),
+            scan_requested: false,
             selected_rule: None,
             selected_watch: None,
             log_entries: VecDeque::new(),
             should_quit: false,
             status_message: None,
             log_scroll: 0,
+            log_query: String::new(),
+            log_query_cursor: 0,
+            log_level_filter: LevelFilter::All,
+            log_rule_filter: None,
+            activity: VecDeque::new(),
+            activity_scroll: 0,
+            preview: None,
+            preview_stale: false,
+            preview_loading: false,
+            preview_scroll: 0,
+            preview_matched_only: false,
+            history: Vec::new(),
+            history_stamp: None,
+            selected_history: None,
+            stats: None,
+            stats_period: StatsPeriod::Day,
+            stats_scroll: 0,
+            dedupe: None,
             show_help: false,
             frame: 0,
             theme_picker_index,
+            preset_picker_index: 0,
             settings_index: 0,
             daemon_running: is_daemon_running(),
             rule_editor: None,
             watch_editor: None,
+            rule_tester: RuleTesterState::default(),
             update_available: None,
             package_manager: crate::detect_package_manager(),
             update_status: None,
             original_theme: None,
             pending_update: false,
             log_file_position: 0,
+            activity_stream: false,
             watcher_needs_restart: false,
+            transfers: Vec::new(),
+            scans: Vec::new(),
+            paused_watches: Vec::new(),
         };
 
         // Add welcome log entries
@@ -294,7 +479,7 @@ impl AppState {
 
     /// Add a log entry
     pub fn log(&mut self, level: LogLevel, message: impl Into<String>) {
-        self.log_entries.push_back(LogEntry {
+        self.push_log(LogEntry {
             timestamp: chrono::Local::now(),
             level,
             message: message.into(),
@@ -308,20 +493,97 @@ impl AppState {
         }
     }
 
+    /// Append to the log, keeping a scrolled-back log view on the same entry
+    fn push_log(&mut self, entry: LogEntry) {
+        if self.log_scroll > 0 && self.log_entry_shown(&entry) {
+            self.log_scroll += 1;
+        }
+        self.log_entries.push_back(entry);
+    }
+
+    /// Whether the log view's search and filters let an entry through
+    pub fn log_entry_shown(&self, entry: &LogEntry) -> bool {
+        if !self.log_level_filter.matches(entry.level) {
+            return false;
+        }
+        if let Some(rule) = &self.log_rule_filter
+            && entry.rule.as_ref() != Some(rule)
+        {
+            return false;
+        }
+        if self.log_query.is_empty() {
+            return true;
+        }
+        let query = self.log_query.to_lowercase();
+        entry.message.to_lowercase().contains(&query)
+            || entry
+                .file
+                .as_ref()
+                .is_some_and(|f| f.to_string_lossy().to_lowercase().contains(&query))
+            || entry
+                .rule
+                .as_ref()
+                .is_some_and(|r| r.to_lowercase().contains(&query))
+    }
+
+    /// Log entries the log view shows, newest first
+    pub fn visible_log(&self) -> Vec<&LogEntry> {
+        self.log_entries
+            .iter()
+            .rev()
+            .filter(|entry| self.log_entry_shown(entry))
+            .collect()
+    }
+
+    /// Add a live activity event, from the daemon or the embedded watcher
+    pub fn push_activity(&mut self, event: &crate::activity::ActivityEvent) {
+        use crate::activity::ActivityKind;
+
+        let (level, file, rule) = match &event.kind {
+            ActivityKind::Detected { path } => (LogLevel::Info, Some(path), None),
+            ActivityKind::Matched { path, rule } => (LogLevel::Info, Some(path), Some(rule)),
+            ActivityKind::Applied { path, rule, .. } => (LogLevel::Success, Some(path), Some(rule)),
+            ActivityKind::Failed { path, rule, .. } => (LogLevel::Error, Some(path), Some(rule)),
+            ActivityKind::Message { message } if message.starts_with("Error") => {
+                (LogLevel::Error, None, None)
+            }
+            ActivityKind::Message { .. } => (LogLevel::Info, None, None),
+        };
+        self.push_log(LogEntry {
+            timestamp: event.time,
+            level,
+            message: event.summary(),
+            file: file.cloned(),
+            rule: rule.cloned(),
+        });
+
+        while self.log_entries.len() > self.config.general.log_retention.max(1) {
+            self.log_entries.pop_front();
+        }
+
+        if matches!(
+            event.kind,
+            ActivityKind::Applied { .. } | ActivityKind::Failed { .. }
+        ) {
+            self.activity.push_back(event.clone());
+            // Keep a scrolled-back pane on the same entries
+            if self.activity_scroll > 0 {
+                self.activity_scroll += 1;
+            }
+            while self.activity.len() > self.config.general.log_retention.max(1) {
+                self.activity.pop_front();
+            }
+            self.activity_scroll = self
+                .activity_scroll
+                .min(self.activity.len().saturating_sub(1));
+        }
+    }
+
     /// Load daemon log entries from the log file (incremental)
     pub fn load_daemon_logs(&mut self) {
         use std::io::{Read, Seek, SeekFrom};
 
-        // Use ~/.local/state/hazelnut/ on all platforms for consistency
-        // This matches the path used by the daemon
-        let log_path = dirs::state_dir()
-            .unwrap_or_else(|| {
-                dirs::home_dir()
-                    .map(|h| h.join(".local").join("state"))
-                    .unwrap_or_else(|| PathBuf::from("/tmp"))
-            })
-            .join("hazelnut")
-            .join("hazelnutd.log");
+        let log_path = crate::logfile::path(&self.config.general);
 
         let Ok(mut file) = std::fs::File::open(&log_path) else {
             return;
@@ -329,9 +591,16 @@ impl AppState {
 
         let file_len = file.metadata().map(|m| m.len()).unwrap_or(0);
 
+        if self.activity_stream && self.log_file_position > 0 {
+            // Live entries arrive over IPC; skip what the daemon logged meanwhile
+            self.log_file_position = file_len;
+            return;
+        }
+
         if file_len < self.log_file_position {
             // File was truncated/rotated — reset
             self.log_entries.clear();
+            self.log_scroll = 0;
             self.log_file_position = 0;
         }
 
@@ -342,6 +611,7 @@ impl AppState {
         if file.seek(SeekFrom::Start(self.log_file_position)).is_err() {
             return;
         }
+        let first_read = self.log_file_position == 0;
 
         let mut new_content = String::new();
         if file.read_to_string(&mut new_content).is_err() {
@@ -352,10 +622,17 @@ impl AppState {
 
         let max_entries = self.config.general.log_retention;
 
-        for line in new_content.lines() {
+        let lines: Vec<&str> = new_content.lines().collect();
+        // Fill up with lines from before the last rotation
+        let rotated = if first_read && lines.len() < max_entries {
+            crate::logfile::rotated_lines(&log_path, max_entries - lines.len())
+        } else {
+            Vec::new()
+        };
+        for line in rotated.iter().map(String::as_str).chain(lines) {
             let clean_line = strip_ansi_codes(line);
             if let Some(entry) = parse_daemon_log_line(&clean_line) {
-                self.log_entries.push_back(entry);
+                self.push_log(entry);
             }
         }
 
@@ -365,6 +642,28 @@ impl AppState {
         }
     }
 
+    /// Files of a watch shown in the preview, with the current filter
+    pub fn preview_files<'a>(
+        &self,
+        watch: &'a crate::preview::WatchPreview,
+    ) -> impl Iterator<Item = &'a crate::preview::FilePreview> {
+        let matched_only = self.preview_matched_only;
+        watch
+            .files
+            .iter()
+            .filter(move |f| !matched_only || !f.planned.is_empty())
+    }
+
+    /// Rows of the preview: a header per watch and a line per shown file
+    pub fn preview_rows(&self) -> usize {
+        self.preview.as_ref().map_or(0, |watches| {
+            watches
+                .iter()
+                .map(|w| 1 + self.preview_files(w).count())
+                .sum()
+        })
+    }
+
     /// Set a temporary status message
     pub fn set_status(&mut self, message: impl Into<String>) {
         self.status_message = Some(message.into());
@@ -381,17 +680,25 @@ impl AppState {
             View::Dashboard => View::Rules,
             View::Rules => View::Watches,
             View::Watches => View::Log,
-            View::Log => View::Dashboard,
+            View::Log => View::Preview,
+            View::Preview => View::History,
+            View::History => View::Stats,
+            View::Stats => View::Duplicates,
+            View::Duplicates => View::Dashboard,
         };
     }
 
     /// Navigate to the previous view
     pub fn prev_view(&mut self) {
         self.view = match self.view {
-            View::Dashboard => View::Log,
+            View::Dashboard => View::Duplicates,
             View::Rules => View::Dashboard,
             View::Watches => View::Rules,
             View::Log => View::Watches,
+            View::Preview => View::Log,
+            View::History => View::Preview,
+            View::Stats => View::History,
+            View::Duplicates => View::Stats,
         };
     }
 
@@ -402,6 +709,136 @@ impl AppState {
         // Refresh daemon logs every ~2 seconds (20 frames at 100ms poll)
         if self.frame.is_multiple_of(20) {
             self.load_daemon_logs();
+            self.load_paused_watches();
+        }
+
+        // Refresh transfer progress every ~1 second
+        if self.frame.is_multiple_of(10) {
+            self.load_transfers();
+        }
+
+        if self.view == View::History {
+            self.load_history();
+        }
+
+        // Counts are written out about once a minute; reload every ~5 seconds
+        if self.view == View::Stats && (self.stats.is_none() || self.frame.is_multiple_of(50)) {
+            self.load_stats();
+        }
+    }
+
+    /// Summarize the statistics of the Stats view's period. Rules that
+    /// didn't process anything are listed too, with zero counts.
+    pub fn load_stats(&mut self) {
+        let (slots, slot_hours) = self.stats_period.slots();
+        let mut summary = crate::stats::current().summary(chrono::Utc::now(), slots, slot_hours);
+        for rule in &self.config.rules {
+            if !summary.rules.iter().any(|r| r.rule == rule.name) {
+                summary.rules.push(crate::stats::RuleTotals {
+                    rule: rule.name.clone(),
+                    ..Default::default()
+                });
+            }
+        }
+        self.stats_scroll = self.stats_scroll.min(summary.rules.len().saturating_sub(1));
+        self.stats = Some(summary);
+    }
+
+    /// Reload the action history if its file changed
+    pub fn load_history(&mut self) {
+        let stamp = crate::history::stamp();
+        if stamp == self.history_stamp && !self.history.is_empty() {
+            return;
+        }
+        self.history_stamp = stamp;
+        // Keep the same entry selected as new ones arrive above it
+        let selected = self
+            .selected_history
+            .and_then(|i| self.history.get(i))
+            .map(|entry| entry.id);
+        self.history = crate::history::recent();
+        self.selected_history = selected
+            .and_then(|id| self.history.iter().position(|entry| entry.id == id))
+            .or((!self.history.is_empty()).then_some(0));
+    }
+
+    /// Fetch in-flight transfers and running scans from the daemon (or the
+    /// embedded watcher)
+    fn load_transfers(&mut self) {
+        if !self.daemon_running {
+            self.transfers = crate::progress::active();
+            self.scans = crate::progress::active_scans();
+            return;
+        }
+        (self.transfers, self.scans) =
+            match crate::ipc::send_command(&crate::ipc::DaemonCommand::GetProgress) {
+                Ok(crate::ipc::DaemonResponse::Progress { transfers, scans }) => (transfers, scans),
+                _ => (Vec::new(), Vec::new()),
+            };
+    }
+
+    /// Fetch paused watches from the daemon (or the embedded watcher)
+    pub fn load_paused_watches(&mut self) {
+        if !self.daemon_running {
+            self.paused_watches = crate::watcher::paused_watches();
+            return;
+        }
+        self.paused_watches = match crate::ipc::send_command(&crate::ipc::DaemonCommand::Status) {
+            Ok(crate::ipc::DaemonResponse::Status { paused_watches, .. }) => paused_watches,
+            _ => Vec::new(),
+        };
+    }
+
+    /// Whether a configured watch is paused
+    pub fn is_watch_paused(&self, path: &Path) -> bool {
+        !self.paused_watches.is_empty()
+            && self
+                .paused_watches
+                .contains(&crate::watcher::watch_key(path))
+    }
+
+    /// Pause the selected watch, or resume it if it's paused
+    pub fn toggle_watch_pause(&mut self) {
+        use crate::ipc::{DaemonCommand, DaemonResponse};
+
+        let Some(path) = self
+            .selected_watch
+            .and_then(|i| self.config.watches.get(i))
+            .map(|w| crate::expand_path(&w.path))
+        else {
+            self.set_status("Select a watch first");
+            return;
+        };
+        let pause = !self.is_watch_paused(&path);
+
+        let result = if self.daemon_running {
+            let cmd = if pause {
+                DaemonCommand::PauseWatch { path: path.clone() }
+            } else {
+                DaemonCommand::ResumeWatch { path: path.clone() }
+            };
+            match crate::ipc::send_command(&cmd) {
+                Ok(DaemonResponse::Ok) => Ok(()),
+                Ok(DaemonResponse::Error { message }) => Err(message),
+                Ok(_) => Err("Unexpected response from daemon".to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        } else {
+            if pause {
+                crate::watcher::pause_watch(&path);
+            } else {
+                crate::watcher::resume_watch(&path);
+            }
+            Ok(())
+        };
+
+        match result {
+            Ok(()) => {
+                self.load_paused_watches();
+                let verb = if pause { "Paused" } else { "Resumed" };
+                self.set_status(format!("{} watch '{}'", verb, path.display()));
+            }
+            Err(e) => self.set_status(format!("Failed to update watch: {}", e)),
         }
     }
 }
@@ -412,6 +849,7 @@ pub enum RuleEditorField {
     #[default]
     Name,
     Enabled,
+    StopProcessing,
     // Conditions
     Extension,
     NameGlob,
@@ -435,7 +873,8 @@ impl RuleEditorField {
     pub fn next(self) -> Self {
         match self {
             Self::Name => Self::Enabled,
-            Self::Enabled => Self::Extension,
+            Self::Enabled => Self::StopProcessing,
+            Self::StopProcessing => Self::Extension,
             Self::Extension => Self::NameGlob,
             Self::NameGlob => Self::NameRegex,
             Self::NameRegex => Self::SizeGreater,
@@ -458,7 +897,8 @@ impl RuleEditorField {
         match self {
             Self::Name => Self::ActionArgs,
             Self::Enabled => Self::Name,
-            Self::Extension => Self::Enabled,
+            Self::StopProcessing => Self::Enabled,
+            Self::Extension => Self::StopProcessing,
             Self::NameGlob => Self::Extension,
             Self::NameRegex => Self::NameGlob,
             Self::SizeGreater => Self::NameRegex,
@@ -487,6 +927,9 @@ pub enum ActionTypeSelection {
     Delete,
     Run,
     Archive,
+    Tier,
+    Script,
+    Plugin,
     Nothing,
 }
 
@@ -500,6 +943,9 @@ impl ActionTypeSelection {
             Self::Delete,
             Self::Run,
             Self::Archive,
+            Self::Tier,
+            Self::Script,
+            Self::Plugin,
             Self::Nothing,
         ]
     }
@@ -513,6 +959,9 @@ impl ActionTypeSelection {
             Self::Delete => "Delete",
             Self::Run => "Run Command",
             Self::Archive => "Archive",
+            Self::Tier => "Tier (stages in config)",
+            Self::Script => "Script (set in config)",
+            Self::Plugin => "Plugin (set in config)",
             Self::Nothing => "Nothing",
         }
     }
@@ -525,7 +974,10 @@ impl ActionTypeSelection {
             Self::Trash => Self::Delete,
             Self::Delete => Self::Run,
             Self::Run => Self::Archive,
-            Self::Archive => Self::Nothing,
+            Self::Archive => Self::Tier,
+            Self::Tier => Self::Script,
+            Self::Script => Self::Plugin,
+            Self::Plugin => Self::Nothing,
             Self::Nothing => Self::Move,
         }
     }
@@ -539,7 +991,87 @@ impl ActionTypeSelection {
             Self::Delete => Self::Trash,
             Self::Run => Self::Delete,
             Self::Archive => Self::Run,
-            Self::Nothing => Self::Archive,
+            Self::Tier => Self::Archive,
+            Self::Script => Self::Tier,
+            Self::Plugin => Self::Script,
+            Self::Nothing => Self::Plugin,
+        }
+    }
+}
+
+/// State of the Duplicates view
+#[derive(Debug, Clone, Default)]
+pub struct DedupeState {
+    /// Folder scanned
+    pub root: PathBuf,
+    /// What the scan looks at
+    pub options: crate::dedupe::ScanOptions,
+    /// Where `m` moves duplicates
+    pub review: PathBuf,
+    /// Groups found, the largest waste first
+    pub groups: Vec<crate::dedupe::DuplicateGroup>,
+    /// Selected group
+    pub selected: usize,
+    /// Flag: scan the folder (again)
+    pub stale: bool,
+    /// A scan is running in the background
+    pub scanning: bool,
+    /// Why the last scan failed
+    pub error: Option<String>,
+    /// `d` was pressed once; the next `d` deletes
+    pub confirm_delete: bool,
+}
+
+impl DedupeState {
+    /// Scan `root`, moving duplicates to its review folder, which is left out
+    pub fn new(root: PathBuf, mut options: crate::dedupe::ScanOptions, review: PathBuf) -> Self {
+        options.exclude.push(review.clone());
+        Self {
+            root,
+            options,
+            review,
+            stale: true,
+            ..Default::default()
+        }
+    }
+
+    /// Bytes all the duplicates take up
+    pub fn wasted(&self) -> u64 {
+        self.groups.iter().map(|g| g.wasted()).sum()
+    }
+}
+
+/// State for the rule tester dialog
+#[derive(Debug, Clone, Default)]
+pub struct RuleTesterState {
+    /// Path of the file to test
+    pub path: String,
+
+    /// Cursor position in the path
+    pub cursor: usize,
+
+    /// Result of the last test (Err: why it couldn't run)
+    pub report: Option<Result<crate::tester::TestReport, String>>,
+
+    /// Result lines scrolled past
+    pub scroll: usize,
+}
+
+impl RuleTesterState {
+    /// Result rows the dialog shows: the verdict, the watch, warnings, a
+    /// blank line, then each rule and its conditions
+    pub fn rows(&self) -> usize {
+        match &self.report {
+            Some(Ok(report)) => {
+                3 + report.warnings.len()
+                    + report
+                        .rules
+                        .iter()
+                        .map(|rule| 1 + rule.checks.len())
+                        .sum::<usize>()
+            }
+            Some(Err(_)) => 1,
+            None => 0,
         }
     }
 }
@@ -597,6 +1129,9 @@ pub struct WatchEditorState {
 
     /// Cursor position for path field
     pub cursor_path: usize,
+
+    /// Original watch, so settings not shown in the editor survive a save
+    pub base_watch: crate::config::WatchConfig,
 }
 
 impl WatchEditorState {
@@ -611,6 +1146,7 @@ impl WatchEditorState {
             available_rules,
             rules_cursor: 0,
             cursor_path: 0,
+            base_watch: crate::config::WatchConfig::default(),
         }
     }
 
@@ -632,6 +1168,7 @@ impl WatchEditorState {
             available_rules,
             rules_cursor,
             cursor_path,
+            base_watch: watch.clone(),
         }
     }
 
@@ -655,6 +1192,7 @@ impl WatchEditorState {
             path: std::path::PathBuf::from(&self.path),
             recursive: self.recursive,
             rules: self.rules_filter.clone(),
+            ..self.base_watch.clone()
         }
     }
 }
@@ -672,6 +1210,20 @@ pub struct RuleEditorState {
     pub name: String,
     pub enabled: bool,
     pub stop_processing: bool,
+    /// Kept from the original rule (not editable in the TUI)
+    pub on_reimport: Option<crate::rules::ReimportMode>,
+    /// Kept from the original rule (not editable in the TUI)
+    pub defer_in_quiet_hours: bool,
+    /// Kept from the original rule (not editable in the TUI)
+    pub active_hours: Option<crate::rules::ActiveHours>,
+    /// Kept from the original rule (not editable in the TUI)
+    pub active_days: crate::rules::ActiveDays,
+    /// Kept from the original rule (not editable in the TUI)
+    pub folder: bool,
+    /// Kept from the original rule (not editable in the TUI)
+    pub notify_limit: Option<crate::notifications::RateLimit>,
+    /// Kept from the original rule (not editable in the TUI)
+    pub notify: Option<crate::rules::RuleNotify>,
 
     // Condition fields
     pub extension: String,
@@ -683,6 +1235,8 @@ pub struct RuleEditorState {
     pub age_less: String,
     pub is_directory: Option<bool>,
     pub is_hidden: Option<bool>,
+    /// Original condition, so fields not shown in the editor survive a save
+    pub base_condition: Condition,
 
     // Action fields
     pub action_type: ActionTypeSelection,
@@ -690,8 +1244,17 @@ pub struct RuleEditorState {
     pub action_pattern: String,
     pub action_command: String,
     pub action_args: String,
-    pub action_overwrite: bool,
+    /// Kept from the rule; set in the config file
+    pub action_on_conflict: OnConflict,
+    /// Kept from the rule (`preserve_metadata = false`); set in the config file
+    pub action_skip_metadata: bool,
     pub action_delete_original: bool,
+    /// Tier stages are edited in the config file; kept as-is on save
+    pub tier_stages: Vec<TierStage>,
+    /// Action scripts are written in the config file; kept as-is on save
+    pub action_script: String,
+    /// Plugin actions are set in the config file; kept as-is on save
+    pub plugin_call: Option<PluginCall>,
 
     // Cursor positions for text fields
     pub cursor_name: usize,
@@ -725,12 +1288,12 @@ impl RuleEditorState {
             action_pattern,
             action_command,
             action_args,
-            action_overwrite,
+            action_on_conflict,
             action_delete_original,
         ) = match &rule.action {
             Action::Move {
                 destination,
-                overwrite,
+                on_conflict,
                 ..
             } => (
                 ActionTypeSelection::Move,
@@ -738,12 +1301,12 @@ impl RuleEditorState {
                 String::new(),
                 String::new(),
                 String::new(),
-                *overwrite,
+                *on_conflict,
                 false,
             ),
             Action::Copy {
                 destination,
-                overwrite,
+                on_conflict,
                 ..
             } => (
                 ActionTypeSelection::Copy,
@@ -751,7 +1314,7 @@ impl RuleEditorState {
                 String::new(),
                 String::new(),
                 String::new(),
-                *overwrite,
+                *on_conflict,
                 false,
             ),
             Action::Rename { pattern } => (
@@ -760,7 +1323,7 @@ impl RuleEditorState {
                 pattern.clone(),
                 String::new(),
                 String::new(),
-                false,
+                OnConflict::Fail,
                 false,
             ),
             Action::Trash => (
@@ -769,7 +1332,7 @@ impl RuleEditorState {
                 String::new(),
                 String::new(),
                 String::new(),
-                false,
+                OnConflict::Fail,
                 false,
             ),
             Action::Delete => (
@@ -778,7 +1341,7 @@ impl RuleEditorState {
                 String::new(),
                 String::new(),
                 String::new(),
-                false,
+                OnConflict::Fail,
                 false,
             ),
             Action::Run { command, args } => (
@@ -787,7 +1350,7 @@ impl RuleEditorState {
                 String::new(),
                 command.clone(),
                 shlex::try_join(args.iter().map(|s| s.as_str())).unwrap_or_else(|_| args.join(" ")),
-                false,
+                OnConflict::Fail,
                 false,
             ),
             Action::Archive {
@@ -802,16 +1365,43 @@ impl RuleEditorState {
                 String::new(),
                 String::new(),
                 String::new(),
-                false,
+                OnConflict::Fail,
                 *delete_original,
             ),
+            Action::Tier { .. } => (
+                ActionTypeSelection::Tier,
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                OnConflict::Fail,
+                false,
+            ),
+            Action::Script { .. } => (
+                ActionTypeSelection::Script,
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                OnConflict::Fail,
+                false,
+            ),
+            Action::Plugin(_) => (
+                ActionTypeSelection::Plugin,
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                OnConflict::Fail,
+                false,
+            ),
             Action::Nothing => (
                 ActionTypeSelection::Nothing,
                 String::new(),
                 String::new(),
                 String::new(),
                 String::new(),
-                false,
+                OnConflict::Fail,
                 false,
             ),
         };
@@ -822,6 +1412,13 @@ impl RuleEditorState {
             name: rule.name.clone(),
             enabled: rule.enabled,
             stop_processing: rule.stop_processing,
+            on_reimport: rule.on_reimport,
+            defer_in_quiet_hours: rule.defer_in_quiet_hours,
+            active_hours: rule.active_hours,
+            active_days: rule.active_days.clone(),
+            folder: rule.folder,
+            notify_limit: rule.notify_limit,
+            notify: rule.notify.clone(),
             extension: rule.condition.extension.clone().unwrap_or_default(),
             name_glob: rule.condition.name_matches.clone().unwrap_or_default(),
             name_regex: rule.condition.name_regex.clone().unwrap_or_default(),
@@ -847,12 +1444,35 @@ impl RuleEditorState {
                 .unwrap_or_default(),
             is_directory: rule.condition.is_directory,
             is_hidden: rule.condition.is_hidden,
+            base_condition: rule.condition.clone(),
+            tier_stages: match &rule.action {
+                Action::Tier { stages } => stages.clone(),
+                _ => Vec::new(),
+            },
+            action_script: match &rule.action {
+                Action::Script { script } => script.clone(),
+                _ => String::new(),
+            },
+            plugin_call: match &rule.action {
+                Action::Plugin(call) => Some(call.clone()),
+                _ => None,
+            },
             action_type,
             action_destination: action_destination.clone(),
             action_pattern: action_pattern.clone(),
             action_command: action_command.clone(),
             action_args: action_args.clone(),
-            action_overwrite,
+            action_on_conflict,
+            action_skip_metadata: matches!(
+                rule.action,
+                Action::Move {
+                    preserve_metadata: false,
+                    ..
+                } | Action::Copy {
+                    preserve_metadata: false,
+                    ..
+                }
+            ),
             action_delete_original,
             // Set cursor positions to end of each field
             cursor_name: rule.name.len(),
@@ -909,7 +1529,6 @@ impl RuleEditorState {
             } else {
                 Some(self.extension.clone())
             },
-            extensions: Vec::new(),
             name_matches: if self.name_glob.is_empty() {
                 None
             } else {
@@ -926,18 +1545,22 @@ impl RuleEditorState {
             age_days_less_than: self.age_less.parse().ok(),
             is_directory: self.is_directory,
             is_hidden: self.is_hidden,
+            // Keep conditions the editor doesn't expose (extension lists, media)
+            ..self.base_condition.clone()
         };
 
         let action = match self.action_type {
             ActionTypeSelection::Move => Action::Move {
                 destination: PathBuf::from(&self.action_destination),
                 create_destination: true,
-                overwrite: self.action_overwrite,
+                on_conflict: self.action_on_conflict,
+                preserve_metadata: !self.action_skip_metadata,
             },
             ActionTypeSelection::Copy => Action::Copy {
                 destination: PathBuf::from(&self.action_destination),
                 create_destination: true,
-                overwrite: self.action_overwrite,
+                on_conflict: self.action_on_conflict,
+                preserve_metadata: !self.action_skip_metadata,
             },
             ActionTypeSelection::Rename => Action::Rename {
                 pattern: self.action_pattern.clone(),
@@ -961,6 +1584,16 @@ impl RuleEditorState {
                 },
                 delete_original: self.action_delete_original,
             },
+            ActionTypeSelection::Tier => Action::Tier {
+                stages: self.tier_stages.clone(),
+            },
+            ActionTypeSelection::Script => Action::Script {
+                script: self.action_script.clone(),
+            },
+            ActionTypeSelection::Plugin => match &self.plugin_call {
+                Some(call) => Action::Plugin(call.clone()),
+                None => Action::Nothing,
+            },
             ActionTypeSelection::Nothing => Action::Nothing,
         };
 
@@ -970,6 +1603,13 @@ impl RuleEditorState {
             condition,
             action,
             stop_processing: self.stop_processing,
+            on_reimport: self.on_reimport,
+            defer_in_quiet_hours: self.defer_in_quiet_hours,
+            active_hours: self.active_hours,
+            active_days: self.active_days.clone(),
+            folder: self.folder,
+            notify_limit: self.notify_limit,
+            notify: self.notify.clone(),
         }
     }
 }
@@ -988,6 +1628,9 @@ fn parse_daemon_log_line(line: &str) -> Option<LogEntry> {
     if line.is_empty() {
         return None;
     }
+    if line.starts_with('{') {
+        return parse_daemon_json_line(line);
+    }
 
     // Find timestamp and level using split_whitespace (handles multiple spaces
     // in tracing output like "2026-02-04T20:12:37Z  INFO message")
@@ -1013,8 +1656,44 @@ fn parse_daemon_log_line(line: &str) -> Option<LogEntry> {
     Some(LogEntry {
         timestamp,
         level,
+        rule: rule_in_message(&message),
         message,
         file: None,
-        rule: None,
     })
 }
+
+/// A line of the daemon's JSON log (`log_format = "json"`)
+fn parse_daemon_json_line(line: &str) -> Option<LogEntry> {
+    let json: serde_json::Value = serde_json::from_str(line).ok()?;
+    let field = |name: &str| json.get(name).and_then(|value| value.as_str());
+    let timestamp = field("timestamp")
+        .and_then(|time| chrono::DateTime::parse_from_rfc3339(time).ok())
+        .map(|dt| dt.with_timezone(&chrono::Local))
+        .unwrap_or_else(chrono::Local::now);
+    let level = match field("level").unwrap_or_default() {
+        "WARN" => LogLevel::Warning,
+        "ERROR" => LogLevel::Error,
+        _ => LogLevel::Info,
+    };
+    let mut message = field("message").unwrap_or_default().to_string();
+    if let Some(error) = field("error") {
+        message = format!("{}: {}", message, error);
+    }
+    let rule = field("rule")
+        .filter(|rule| !rule.is_empty())
+        .map(str::to_string)
+        .or_else(|| rule_in_message(&message));
+    Some(LogEntry {
+        timestamp,
+        level,
+        rule,
+        message,
+        file: field("path").map(PathBuf::from),
+    })
+}
+
+/// The rule a daemon log message is about, from "Rule 'NAME' ..."
+fn rule_in_message(message: &str) -> Option<String> {
+    let rest = message.strip_prefix("Rule '")?;
+    Some(rest[..rest.find('\'')?].to_string())
+}
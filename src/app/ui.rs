@@ -5,15 +5,18 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, Clear, List, ListItem, Paragraph, Tabs, Wrap},
+    widgets::{
+        Block, BorderType, Borders, Clear, List, ListItem, Paragraph, Sparkline, Tabs, Wrap,
+    },
 };
 
+use super::keymap::KeyAction;
 use super::state::{
-    AppState, LogLevel, Mode, RuleEditorField, SettingsItem, View, WatchEditorField,
+    AppState, LevelFilter, LogLevel, Mode, RuleEditorField, SettingsItem, View, WatchEditorField,
 };
-#[cfg(unix)]
 use crate::autostart;
-use crate::theme::Theme;
+use crate::notifications::NotificationLevel;
+use crate::theme::ThemeColors;
 
 /// ASCII art logo for Hazelnut
 const LOGO: &str = r#"
@@ -60,6 +63,11 @@ pub fn render(frame: &mut Frame, state: &AppState) {
         render_theme_picker(frame, state);
     }
 
+    // Render preset picker if active
+    if state.mode == Mode::PresetPicker {
+        render_preset_picker(frame, state);
+    }
+
     // Render settings dialog if active
     if state.mode == Mode::Settings {
         render_settings_dialog(frame, state);
@@ -80,6 +88,11 @@ pub fn render(frame: &mut Frame, state: &AppState) {
         render_about_dialog(frame, state);
     }
 
+    // Render rule tester if active
+    if state.mode == Mode::RuleTester {
+        render_rule_tester(frame, state);
+    }
+
     // Render update confirmation dialog
     if state.mode == Mode::UpdateConfirm {
         render_update_confirm_dialog(frame, state);
@@ -134,6 +147,38 @@ fn render_tabs(frame: &mut Frame, state: &AppState, area: Rect) {
                 "○"
             }
         ),
+        format!(
+            "{}  Preview",
+            if state.view == View::Preview {
+                "●"
+            } else {
+                "○"
+            }
+        ),
+        format!(
+            "{}  History",
+            if state.view == View::History {
+                "●"
+            } else {
+                "○"
+            }
+        ),
+        format!(
+            "{}  Stats",
+            if state.view == View::Stats {
+                "●"
+            } else {
+                "○"
+            }
+        ),
+        format!(
+            "{}  Duplicates",
+            if state.view == View::Duplicates {
+                "●"
+            } else {
+                "○"
+            }
+        ),
     ]
     .into_iter()
     .map(Line::from)
@@ -144,6 +189,10 @@ fn render_tabs(frame: &mut Frame, state: &AppState, area: Rect) {
         View::Rules => 1,
         View::Watches => 2,
         View::Log => 3,
+        View::Preview => 4,
+        View::History => 5,
+        View::Stats => 6,
+        View::Duplicates => 7,
     };
 
     let tabs = Tabs::new(titles)
@@ -168,6 +217,10 @@ fn render_main(frame: &mut Frame, state: &AppState, area: Rect) {
         View::Rules => render_rules(frame, state, area),
         View::Watches => render_watches(frame, state, area),
         View::Log => render_log(frame, state, area),
+        View::Preview => render_preview(frame, state, area),
+        View::History => render_history(frame, state, area),
+        View::Stats => render_stats(frame, state, area),
+        View::Duplicates => render_duplicates(frame, state, area),
     }
 }
 
@@ -259,11 +312,15 @@ fn render_dashboard(frame: &mut Frame, state: &AppState, area: Rect) {
         .block(Block::default());
     frame.render_widget(logo, logo_area);
 
-    // Content area
+    // Content area: stats and quick actions, live activity below
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(16), Constraint::Min(0)])
+        .split(content_area);
     let content_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(content_area);
+        .split(rows[0]);
 
     // Left: Stats
     let enabled_rules = state.config.rules.iter().filter(|r| r.enabled).count();
@@ -343,25 +400,37 @@ fn render_dashboard(frame: &mut Frame, state: &AppState, area: Rect) {
         Line::from(""),
         Line::from(vec![
             Span::styled("  ", Style::default()),
-            Span::styled("[s]", colors.key_hint()),
+            Span::styled(
+                format!("[{}]", state.keymap.first(KeyAction::Settings)),
+                colors.key_hint(),
+            ),
             Span::styled(" Settings", colors.text()),
         ]),
         Line::from(""),
         Line::from(vec![
             Span::styled("  ", Style::default()),
-            Span::styled("[?]", colors.key_hint()),
+            Span::styled(
+                format!("[{}]", state.keymap.first(KeyAction::Help)),
+                colors.key_hint(),
+            ),
             Span::styled(" Help & keybindings", colors.text()),
         ]),
         Line::from(""),
         Line::from(vec![
             Span::styled("  ", Style::default()),
-            Span::styled("[t]", colors.key_hint()),
+            Span::styled(
+                format!("[{}]", state.keymap.first(KeyAction::Theme)),
+                colors.key_hint(),
+            ),
             Span::styled(" Change theme", colors.text()),
         ]),
         Line::from(""),
         Line::from(vec![
             Span::styled("  ", Style::default()),
-            Span::styled("[q]", colors.key_hint()),
+            Span::styled(
+                format!("[{}]", state.keymap.first(KeyAction::Quit)),
+                colors.key_hint(),
+            ),
             Span::styled(" Quit", colors.text()),
         ]),
     ];
@@ -374,6 +443,101 @@ fn render_dashboard(frame: &mut Frame, state: &AppState, area: Rect) {
             .title_style(colors.text_primary()),
     );
     frame.render_widget(actions, content_chunks[1]);
+
+    render_activity(frame, state, rows[1]);
+}
+
+/// Files rules were applied to or failed on, newest first, colored by outcome
+fn render_activity(frame: &mut Frame, state: &AppState, area: Rect) {
+    use crate::activity::ActivityKind;
+
+    let colors = state.theme.colors();
+
+    // Without the stream, the daemon's work only shows up in the Log view
+    let title = if state.daemon_running && !state.activity_stream {
+        " Live Activity (not connected to daemon) ".to_string()
+    } else if state.activity_scroll > 0 {
+        format!(
+            " Live Activity ({}/{}) [g: newest] ",
+            state.activity_scroll + 1,
+            state.activity.len()
+        )
+    } else {
+        format!(" Live Activity ({}) ", state.activity.len())
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(colors.block())
+        .title(title)
+        .title_style(colors.text_primary());
+
+    if state.activity.is_empty() {
+        let empty = Paragraph::new(vec![
+            Line::from(""),
+            Line::styled("  No files processed yet", colors.text_muted()),
+            Line::styled("  Waiting for file events...", colors.text_dim()),
+        ])
+        .block(block);
+        frame.render_widget(empty, area);
+        return;
+    }
+
+    let file_name = |path: &std::path::Path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string())
+    };
+
+    let items: Vec<ListItem> = state
+        .activity
+        .iter()
+        .rev()
+        .skip(state.activity_scroll)
+        .filter_map(|event| {
+            let time = event.time.format("%H:%M:%S").to_string();
+            let line = match &event.kind {
+                ActivityKind::Applied {
+                    path,
+                    rule,
+                    action,
+                    destination,
+                } => {
+                    let result = match destination {
+                        Some(destination) if destination != path => {
+                            format!(" → {}", destination.display())
+                        }
+                        _ => String::new(),
+                    };
+                    Line::from(vec![
+                        Span::styled(" ✓ ", colors.text_success()),
+                        Span::styled(format!("[{}] ", time), colors.text_muted()),
+                        Span::styled(file_name(path), colors.text_success()),
+                        Span::styled(format!("  {} · {}", rule, action), colors.text_dim()),
+                        Span::styled(result, colors.text()),
+                    ])
+                }
+                ActivityKind::Failed {
+                    path,
+                    rule,
+                    action,
+                    error,
+                } => Line::from(vec![
+                    Span::styled(" ✗ ", colors.text_error()),
+                    Span::styled(format!("[{}] ", time), colors.text_muted()),
+                    Span::styled(file_name(path), colors.text_error()),
+                    Span::styled(
+                        format!("  {} · {} failed: ", rule, action),
+                        colors.text_dim(),
+                    ),
+                    Span::styled(error.as_str(), colors.text_error()),
+                ]),
+                _ => return None,
+            };
+            Some(ListItem::new(line))
+        })
+        .collect();
+
+    frame.render_widget(List::new(items).block(block), area);
 }
 
 fn render_rules(frame: &mut Frame, state: &AppState, area: Rect) {
@@ -394,7 +558,9 @@ fn render_rules(frame: &mut Frame, state: &AppState, area: Rect) {
             Line::from(vec![
                 Span::styled("  Or press ", colors.text_dim()),
                 Span::styled("[n]", colors.key_hint()),
-                Span::styled(" to create a new rule", colors.text_dim()),
+                Span::styled(" to create a new rule, ", colors.text_dim()),
+                Span::styled("[p]", colors.key_hint()),
+                Span::styled(" to start from a preset", colors.text_dim()),
             ]),
         ])
         .block(
@@ -440,12 +606,19 @@ fn render_rules(frame: &mut Frame, state: &AppState, area: Rect) {
                 crate::rules::Action::Delete => "⚠ Delete".to_string(),
                 crate::rules::Action::Run { command, .. } => format!("$ {}", command),
                 crate::rules::Action::Archive { .. } => "📦 Archive".to_string(),
+                crate::rules::Action::Tier { stages } => {
+                    format!("⧗ Tier ({} stages)", stages.len())
+                }
+                crate::rules::Action::Script { .. } => "λ Script".to_string(),
+                crate::rules::Action::Plugin(call) => format!("⚙ {}", call.name),
                 crate::rules::Action::Nothing => "∅ Nothing".to_string(),
             };
 
             ListItem::new(Line::from(vec![
                 Span::styled(format!(" {} ", status_icon), status_style),
                 Span::styled(&rule.name, base_style.add_modifier(Modifier::BOLD)),
+                // Folder rules act on whole folders
+                Span::styled(if rule.folder { " 📁" } else { "" }, base_style),
                 Span::styled(format!("  {}", action_preview), colors.text_dim()),
             ]))
             .style(base_style)
@@ -509,19 +682,33 @@ fn render_watches(frame: &mut Frame, state: &AppState, area: Rect) {
             };
 
             let recursive_indicator = if watch.recursive { " (recursive)" } else { "" };
+            let schedule_indicator = watch
+                .schedule
+                .as_ref()
+                .map(|s| format!(" ⏰ {}", s))
+                .unwrap_or_default();
             let path_str = watch.path.display().to_string();
 
+            let paused = state.is_watch_paused(&watch.path);
+            let paused_indicator = if paused { " ⏸ paused" } else { "" };
+
             // Check if path exists
-            let (icon, path_style) = if watch.path.exists() {
-                ("📁", colors.text())
-            } else {
+            let (icon, path_style) = if crate::sftp::is_remote(&watch.path) {
+                (if paused { "⏸" } else { "🌐" }, colors.text())
+            } else if !watch.path.exists() {
                 ("⚠", colors.text_warning())
+            } else if paused {
+                ("⏸", colors.text_muted())
+            } else {
+                ("📁", colors.text())
             };
 
             ListItem::new(Line::from(vec![
                 Span::styled(format!(" {} ", icon), base_style),
                 Span::styled(path_str, path_style),
                 Span::styled(recursive_indicator, colors.text_muted()),
+                Span::styled(schedule_indicator, colors.text_muted()),
+                Span::styled(paused_indicator, colors.text_warning()),
             ]))
             .style(base_style)
         })
@@ -545,6 +732,19 @@ fn render_watches(frame: &mut Frame, state: &AppState, area: Rect) {
 fn render_log(frame: &mut Frame, state: &AppState, area: Rect) {
     let colors = state.theme.colors();
 
+    // In-flight transfers and scans get a pane above the log
+    let area = if state.transfers.is_empty() && state.scans.is_empty() {
+        area
+    } else {
+        let height = ((state.transfers.len() + state.scans.len()) as u16).min(4) + 2;
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(height), Constraint::Min(0)])
+            .split(area);
+        render_transfers(frame, state, chunks[0]);
+        chunks[1]
+    };
+
     if state.log_entries.is_empty() {
         let empty = Paragraph::new(vec![
             Line::from(""),
@@ -564,11 +764,85 @@ fn render_log(frame: &mut Frame, state: &AppState, area: Rect) {
         return;
     }
 
-    let items: Vec<ListItem> = state
-        .log_entries
+    let entries = state.visible_log();
+    let filtered = !state.log_query.is_empty()
+        || state.log_level_filter != LevelFilter::All
+        || state.log_rule_filter.is_some();
+    let searching = state.mode == Mode::LogSearch;
+
+    let title = if filtered {
+        format!(
+            " Activity Log ({}/{}) ",
+            entries.len(),
+            state.log_entries.len()
+        )
+    } else {
+        format!(" Activity Log ({}) ", state.log_entries.len())
+    };
+    let hints = format!(
+        " /: search │ f: {} │ r: {} │ e/E: next/prev error │ {}c: clear ",
+        state.log_level_filter.label(),
+        state.log_rule_filter.as_deref().unwrap_or("all rules"),
+        if filtered { "Esc: reset │ " } else { "" }
+    );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(if state.view == View::Log {
+            colors.block_focus()
+        } else {
+            colors.block()
+        })
+        .title(title)
+        .title_style(colors.text_primary())
+        .title_bottom(Line::from(hints).centered());
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    // The search line sits above the entries while there is a search
+    let (search_area, list_area) = if searching || !state.log_query.is_empty() {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(inner);
+        (Some(chunks[0]), chunks[1])
+    } else {
+        (None, inner)
+    };
+    if let Some(search_area) = search_area {
+        let search = Paragraph::new(Line::from(vec![
+            Span::styled(" / ", colors.text_primary()),
+            Span::styled(&state.log_query, colors.text()),
+        ]));
+        frame.render_widget(search, search_area);
+        if searching {
+            // " / " is 3 columns
+            let cursor_x = search_area.x + 3 + state.log_query_cursor as u16;
+            if cursor_x < search_area.x + search_area.width {
+                frame.set_cursor_position((cursor_x, search_area.y));
+            }
+        }
+    }
+
+    if entries.is_empty() {
+        let empty = Paragraph::new(Line::styled(
+            "  No entries match the search and filters",
+            colors.text_muted(),
+        ));
+        frame.render_widget(empty, list_area);
+        return;
+    }
+
+    // Keep the selected entry in view
+    let selected = state.log_scroll.min(entries.len() - 1);
+    let visible = (list_area.height as usize).max(1);
+    let offset = (selected + 1).saturating_sub(visible);
+
+    let items: Vec<ListItem> = entries
         .iter()
-        .rev()
-        .map(|entry| {
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+        .map(|(i, entry)| {
             let (icon, level_style) = match entry.level {
                 LogLevel::Info => ("ℹ", colors.text_info()),
                 LogLevel::Success => ("✓", colors.text_success()),
@@ -583,21 +857,393 @@ fn render_log(frame: &mut Frame, state: &AppState, area: Rect) {
                 Span::styled(format!("[{}] ", time), colors.text_muted()),
                 Span::styled(&entry.message, colors.text()),
             ]))
+            .style(if i == selected {
+                colors.selected()
+            } else {
+                Style::default()
+            })
+        })
+        .collect();
+
+    frame.render_widget(List::new(items), list_area);
+}
+
+/// Files in watched folders with what the rules would do to them
+fn render_preview(frame: &mut Frame, state: &AppState, area: Rect) {
+    let colors = state.theme.colors();
+
+    let title = if state.preview_loading {
+        " Preview (dry run) — scanning... ".to_string()
+    } else {
+        format!(
+            " Preview (dry run){} [r: refresh │ m: {}] ",
+            if state.preview_matched_only {
+                " — matched only"
+            } else {
+                ""
+            },
+            if state.preview_matched_only {
+                "show all"
+            } else {
+                "matched only"
+            }
+        )
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(colors.block_focus())
+        .title(title)
+        .title_style(colors.text_primary());
+
+    let Some(ref watches) = state.preview else {
+        let loading = Paragraph::new(vec![
+            Line::from(""),
+            Line::styled("  Scanning watched folders...", colors.text_muted()),
+        ])
+        .block(block);
+        frame.render_widget(loading, area);
+        return;
+    };
+    if watches.is_empty() {
+        let empty = Paragraph::new(vec![
+            Line::from(""),
+            Line::styled("  No watch folders configured", colors.text_muted()),
+        ])
+        .block(block);
+        frame.render_widget(empty, area);
+        return;
+    }
+
+    let mut items: Vec<ListItem> = Vec::new();
+    for watch in watches {
+        let summary = match &watch.error {
+            Some(error) => Span::styled(format!("  {}", error), colors.text_error()),
+            None => Span::styled(
+                format!(
+                    "  {} of {} files would be handled{}",
+                    watch.matched(),
+                    watch.files.len(),
+                    if watch.truncated {
+                        format!(" (first {} listed)", crate::preview::MAX_FILES)
+                    } else {
+                        String::new()
+                    }
+                ),
+                colors.text_dim(),
+            ),
+        };
+        items.push(ListItem::new(Line::from(vec![
+            Span::styled(
+                format!(" 📁 {}", watch.path.display()),
+                colors.text_primary().add_modifier(Modifier::BOLD),
+            ),
+            summary,
+        ])));
+
+        for file in state.preview_files(watch) {
+            let name = file
+                .path
+                .strip_prefix(&watch.path)
+                .unwrap_or(&file.path)
+                .display()
+                .to_string();
+            let mut spans = Vec::new();
+            if let Some(error) = &file.error {
+                spans.push(Span::styled("   ✗ ", colors.text_error()));
+                spans.push(Span::styled(name, colors.text_error()));
+                spans.push(Span::styled(format!("  {}", error), colors.text_error()));
+            } else if file.planned.is_empty() {
+                spans.push(Span::styled("   · ", colors.text_muted()));
+                spans.push(Span::styled(name, colors.text_muted()));
+                spans.push(Span::styled("  no rule", colors.text_muted()));
+            } else {
+                spans.push(Span::styled("   ✓ ", colors.text_success()));
+                spans.push(Span::styled(name, colors.text()));
+                for (i, planned) in file.planned.iter().enumerate() {
+                    let rule = if planned.rule.is_empty() {
+                        "catch-all"
+                    } else {
+                        &planned.rule
+                    };
+                    spans.push(Span::styled(
+                        format!(
+                            "{}{} · {}",
+                            if i == 0 { "  " } else { ", then " },
+                            rule,
+                            planned.action
+                        ),
+                        colors.text_dim(),
+                    ));
+                    if let Some(destination) = &planned.destination {
+                        spans.push(Span::styled(
+                            format!(" → {}", destination.display()),
+                            colors.text_success(),
+                        ));
+                    }
+                }
+            }
+            items.push(ListItem::new(Line::from(spans)));
+        }
+    }
+
+    let items: Vec<ListItem> = items.into_iter().skip(state.preview_scroll).collect();
+    frame.render_widget(List::new(items).block(block), area);
+}
+
+fn render_history(frame: &mut Frame, state: &AppState, area: Rect) {
+    let colors = state.theme.colors();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(colors.block_focus())
+        .title(format!(
+            " Recent Actions ({}) [u: undo] ",
+            state.history.len()
+        ))
+        .title_style(colors.text_primary());
+
+    if state.history.is_empty() {
+        let empty = Paragraph::new(vec![
+            Line::from(""),
+            Line::styled("  No actions yet", colors.text_muted()),
+        ])
+        .block(block);
+        frame.render_widget(empty, area);
+        return;
+    }
+
+    // Keep the selected entry in view
+    let visible = area.height.saturating_sub(2).max(1) as usize;
+    let offset = state
+        .selected_history
+        .map_or(0, |i| (i + 1).saturating_sub(visible));
+
+    let items: Vec<ListItem> = state
+        .history
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+        .map(|(i, entry)| {
+            let is_selected = state.selected_history == Some(i);
+            let base_style = if is_selected {
+                colors.selected()
+            } else {
+                colors.text()
+            };
+            let (icon, path_style) = if entry.undone.is_some() {
+                ("↩", colors.text_muted())
+            } else if !entry.succeeded() {
+                ("✗", colors.text_error())
+            } else {
+                ("→", colors.text())
+            };
+            let rule = if entry.rule.is_empty() {
+                "catch-all"
+            } else {
+                &entry.rule
+            };
+
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!(" #{:<5} {} ", entry.id, entry.time.format("%m-%d %H:%M")),
+                    colors.text_muted(),
+                ),
+                Span::styled(format!("{} ", icon), base_style),
+                Span::styled(rule.to_string(), base_style.add_modifier(Modifier::BOLD)),
+                Span::styled(format!(" · {}  ", entry.action), colors.text_dim()),
+                Span::styled(
+                    match &entry.to {
+                        Some(to) => format!("{} → {}", entry.from.display(), to.display()),
+                        None => entry.from.display().to_string(),
+                    },
+                    path_style,
+                ),
+                Span::styled(
+                    match (&entry.undone, &entry.error) {
+                        (Some(_), _) => "  (undone)".to_string(),
+                        (None, Some(error)) => format!("  ({})", error),
+                        (None, None) => String::new(),
+                    },
+                    colors.text_muted(),
+                ),
+            ]))
+            .style(base_style)
+        })
+        .collect();
+
+    frame.render_widget(List::new(items).block(block), area);
+}
+
+fn render_duplicates(frame: &mut Frame, state: &AppState, area: Rect) {
+    let colors = state.theme.colors();
+
+    let Some(ref dedupe) = state.dedupe else {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(colors.block_focus())
+            .title(" Duplicates ")
+            .title_style(colors.text_primary());
+        let empty = Paragraph::new(vec![
+            Line::from(""),
+            Line::styled(
+                "  Press f on a watch in the Watches view to look for duplicate files in it,",
+                colors.text_muted(),
+            ),
+            Line::styled(
+                "  or run `hazelnut dedupe <folder>` for any folder",
+                colors.text_muted(),
+            ),
+        ])
+        .block(block);
+        frame.render_widget(empty, area);
+        return;
+    };
+
+    let root = dedupe.root.display().to_string();
+    let scanning = dedupe.scanning || dedupe.stale;
+    let title = if scanning {
+        format!(" Duplicates in {} (scanning...) ", root)
+    } else {
+        format!(
+            " Duplicates in {} ({} group(s), {} wasted) [←/→: keep · d: delete · l: hard link · m: move · r: rescan] ",
+            root,
+            dedupe.groups.len(),
+            crate::progress::format_bytes(dedupe.wasted())
+        )
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(colors.block_focus())
+        .title(title)
+        .title_style(colors.text_primary());
+
+    if dedupe.groups.is_empty() {
+        let message = match (&dedupe.error, scanning) {
+            (Some(error), _) => Line::styled(format!("  {}", error), colors.text_error()),
+            (None, true) => Line::styled("  Hashing files...", colors.text_muted()),
+            (None, false) => Line::styled("  No duplicate files", colors.text_muted()),
+        };
+        frame.render_widget(
+            Paragraph::new(vec![Line::from(""), message]).block(block),
+            area,
+        );
+        return;
+    }
+
+    // The groups, and below them the files of the selected one
+    let selected = &dedupe.groups[dedupe.selected];
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),
+            Constraint::Length((selected.files.len() as u16 + 2).min(12)),
+        ])
+        .split(area);
+
+    let relative = |path: &std::path::Path| {
+        path.strip_prefix(&dedupe.root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+
+    let visible = chunks[0].height.saturating_sub(2).max(1) as usize;
+    let offset = (dedupe.selected + 1).saturating_sub(visible);
+    let items: Vec<ListItem> = dedupe
+        .groups
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+        .map(|(i, group)| {
+            let style = if i == dedupe.selected {
+                colors.selected()
+            } else {
+                colors.text()
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!(
+                        " {:>3} × {:>9}  {:>9} wasted  ",
+                        group.files.len(),
+                        crate::progress::format_bytes(group.size),
+                        crate::progress::format_bytes(group.wasted())
+                    ),
+                    colors.text_muted(),
+                ),
+                Span::styled(relative(group.kept()), style),
+            ]))
+            .style(style)
+        })
+        .collect();
+    frame.render_widget(List::new(items).block(block), chunks[0]);
+
+    let files: Vec<ListItem> = selected
+        .files
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let (label, style) = if i == selected.keep {
+                ("★ keep ", colors.text_success())
+            } else {
+                ("  dup  ", colors.text_dim())
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!(" {}", label), style),
+                Span::styled(relative(path), colors.text()),
+            ]))
         })
         .collect();
+    let files_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(colors.block())
+        .title(format!(" {} ", selected.hash))
+        .title_style(colors.text_muted());
+    frame.render_widget(List::new(files).block(files_block), chunks[1]);
+}
+
+fn render_transfers(frame: &mut Frame, state: &AppState, area: Rect) {
+    const BAR_WIDTH: usize = 20;
+    let colors = state.theme.colors();
 
+    let items: Vec<ListItem> = state
+        .transfers
+        .iter()
+        .map(|transfer| {
+            let filled = ((transfer.percent() / 100.0) * BAR_WIDTH as f64).round() as usize;
+            let filled = filled.min(BAR_WIDTH);
+            ListItem::new(Line::from(vec![
+                Span::styled(" ⇄ ", colors.text_info()),
+                Span::styled("█".repeat(filled), colors.text_success()),
+                Span::styled("░".repeat(BAR_WIDTH - filled), colors.text_muted()),
+                Span::styled(format!(" {}", transfer.summary()), colors.text()),
+            ]))
+        })
+        .chain(state.scans.iter().map(|scan| {
+            ListItem::new(Line::from(vec![
+                Span::styled(" ⟳ ", colors.text_info()),
+                Span::styled(scan.summary(), colors.text()),
+            ]))
+        }))
+        .collect();
+
+    let title = if state.scans.is_empty() {
+        format!(" Transfers ({}) ", state.transfers.len())
+    } else if state.transfers.is_empty() {
+        format!(" Scans ({}) ", state.scans.len())
+    } else {
+        format!(
+            " Transfers ({}) · Scans ({}) ",
+            state.transfers.len(),
+            state.scans.len()
+        )
+    };
     let list = List::new(items).block(
         Block::default()
             .borders(Borders::ALL)
-            .border_style(if state.view == View::Log {
-                colors.block_focus()
-            } else {
-                colors.block()
-            })
-            .title(format!(
-                " Activity Log ({}) [c: clear] ",
-                state.log_entries.len()
-            ))
+            .border_style(colors.block())
+            .title(title)
             .title_style(colors.text_primary()),
     );
 
@@ -606,6 +1252,7 @@ fn render_log(frame: &mut Frame, state: &AppState, area: Rect) {
 
 fn render_status_bar(frame: &mut Frame, state: &AppState, area: Rect) {
     let colors = state.theme.colors();
+    let keys = &state.keymap;
 
     let content = if let Some(ref msg) = state.status_message {
         vec![
@@ -615,17 +1262,19 @@ fn render_status_bar(frame: &mut Frame, state: &AppState, area: Rect) {
     } else {
         vec![
             Span::styled(" ", Style::default()),
-            Span::styled("Tab", colors.key_hint()),
+            Span::styled(keys.first(KeyAction::NextView), colors.key_hint()),
             Span::styled(": views  ", colors.text_muted()),
-            Span::styled("?", colors.key_hint()),
+            Span::styled(keys.first(KeyAction::Help), colors.key_hint()),
             Span::styled(": help  ", colors.text_muted()),
-            Span::styled("s", colors.key_hint()),
+            Span::styled(keys.first(KeyAction::Settings), colors.key_hint()),
             Span::styled(": settings  ", colors.text_muted()),
-            Span::styled("t", colors.key_hint()),
+            Span::styled(keys.first(KeyAction::Theme), colors.key_hint()),
             Span::styled(": theme  ", colors.text_muted()),
-            Span::styled("A", colors.key_hint()),
+            Span::styled(keys.first(KeyAction::TestRules), colors.key_hint()),
+            Span::styled(": test rules  ", colors.text_muted()),
+            Span::styled(keys.first(KeyAction::About), colors.key_hint()),
             Span::styled(": about  ", colors.text_muted()),
-            Span::styled("q", colors.key_hint()),
+            Span::styled(keys.first(KeyAction::Quit), colors.key_hint()),
             Span::styled(": quit", colors.text_muted()),
         ]
     };
@@ -637,11 +1286,12 @@ fn render_status_bar(frame: &mut Frame, state: &AppState, area: Rect) {
 
 fn render_help_popup(frame: &mut Frame, state: &AppState) {
     let colors = state.theme.colors();
+    let keys = &state.keymap;
     let area = frame.area();
 
     // Calculate popup size
     let popup_width = 60u16.min(area.width.saturating_sub(4));
-    let popup_height = 32u16.min(area.height.saturating_sub(4));
+    let popup_height = 56u16.min(area.height.saturating_sub(4));
 
     let popup_area = Rect {
         x: (area.width - popup_width) / 2,
@@ -659,104 +1309,155 @@ fn render_help_popup(frame: &mut Frame, state: &AppState) {
             "  Navigation",
             colors.text_primary().add_modifier(Modifier::BOLD),
         )]),
-        Line::from(vec![
-            Span::styled("  Tab / Shift+Tab    ", colors.key_hint()),
-            Span::styled("Switch between views", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  1-4                ", colors.key_hint()),
-            Span::styled("Jump to view directly", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  j/k or ↑/↓         ", colors.key_hint()),
-            Span::styled("Navigate lists", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  g/G                ", colors.key_hint()),
-            Span::styled("Go to first/last item", colors.text()),
-        ]),
+        help_line(
+            &format!(
+                "{} / {}",
+                keys.label(KeyAction::NextView),
+                keys.label(KeyAction::PrevView)
+            ),
+            "Switch between views",
+            &colors,
+        ),
+        help_line("1-7", "Jump to view directly", &colors),
+        help_line(&keys.label(KeyAction::Up), "Move up", &colors),
+        help_line(&keys.label(KeyAction::Down), "Move down", &colors),
+        help_line(
+            &format!(
+                "{} / {}",
+                keys.label(KeyAction::PageUp),
+                keys.label(KeyAction::PageDown)
+            ),
+            "Scroll a page",
+            &colors,
+        ),
+        help_line(
+            &format!(
+                "{} / {}",
+                keys.label(KeyAction::Top),
+                keys.label(KeyAction::Bottom)
+            ),
+            "Go to first/last item",
+            &colors,
+        ),
         Line::from(""),
         Line::from(vec![Span::styled(
             "  Rules View",
             colors.text_primary().add_modifier(Modifier::BOLD),
         )]),
-        Line::from(vec![
-            Span::styled("  Enter/Space        ", colors.key_hint()),
-            Span::styled("Toggle rule on/off", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  e                  ", colors.key_hint()),
-            Span::styled("Edit selected rule", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  n                  ", colors.key_hint()),
-            Span::styled("Create new rule", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  d                  ", colors.key_hint()),
-            Span::styled("Delete selected rule", colors.text()),
-        ]),
+        help_line(
+            &keys.label(KeyAction::ToggleRule),
+            "Toggle rule on/off",
+            &colors,
+        ),
+        help_line("e", "Edit selected rule", &colors),
+        help_line("n", "Create new rule", &colors),
+        help_line("c", "Duplicate selected rule", &colors),
+        help_line("p", "Add rules from a preset", &colors),
+        help_line("K/J", "Move selected rule up/down", &colors),
+        help_line("d", "Delete selected rule", &colors),
         Line::from(""),
         Line::from(vec![Span::styled(
             "  Watches View",
             colors.text_primary().add_modifier(Modifier::BOLD),
         )]),
-        Line::from(vec![
-            Span::styled("  e                  ", colors.key_hint()),
-            Span::styled("Edit selected watch", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  a/n                ", colors.key_hint()),
-            Span::styled("Add new watch", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  d                  ", colors.key_hint()),
-            Span::styled("Delete selected watch", colors.text()),
-        ]),
+        help_line("e", "Edit selected watch", &colors),
+        help_line("a/n", "Add new watch", &colors),
+        help_line("d", "Delete selected watch", &colors),
+        help_line("p", "Pause/resume selected watch", &colors),
+        help_line("f", "Find duplicate files in selected watch", &colors),
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            "  Log View",
+            colors.text_primary().add_modifier(Modifier::BOLD),
+        )]),
+        help_line("/", "Search messages, files and rules", &colors),
+        help_line("f / r", "Filter by severity / rule", &colors),
+        help_line("e/E", "Jump to next/previous error", &colors),
+        help_line("Esc / c", "Reset search and filters / clear log", &colors),
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            "  Preview View",
+            colors.text_primary().add_modifier(Modifier::BOLD),
+        )]),
+        help_line("r / m", "Refresh / show matched files only", &colors),
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            "  History View",
+            colors.text_primary().add_modifier(Modifier::BOLD),
+        )]),
+        help_line(
+            &keys.label(KeyAction::Undo),
+            "Undo selected move/rename",
+            &colors,
+        ),
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            "  Stats View",
+            colors.text_primary().add_modifier(Modifier::BOLD),
+        )]),
+        help_line("p / r", "Switch day/week / refresh", &colors),
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            "  Duplicates View",
+            colors.text_primary().add_modifier(Modifier::BOLD),
+        )]),
+        help_line("←/→", "Pick the file kept", &colors),
+        help_line(
+            "d d / l / m",
+            "Delete / hard-link / move the others",
+            &colors,
+        ),
+        help_line("r", "Scan again", &colors),
         Line::from(""),
         Line::from(vec![Span::styled(
             "  Dashboard",
             colors.text_primary().add_modifier(Modifier::BOLD),
         )]),
-        Line::from(vec![
-            Span::styled("  D                  ", colors.key_hint()),
-            Span::styled("Toggle daemon on/off", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  U                  ", colors.key_hint()),
-            Span::styled("Update hazelnut (if available)", colors.text()),
-        ]),
+        help_line(
+            &format!(
+                "{} / {}",
+                keys.first(KeyAction::Down),
+                keys.first(KeyAction::Up)
+            ),
+            "Scroll live activity",
+            &colors,
+        ),
+        help_line("D", "Toggle daemon on/off", &colors),
+        help_line("U", "Update hazelnut (if available)", &colors),
         Line::from(""),
         Line::from(vec![Span::styled(
             "  General",
             colors.text_primary().add_modifier(Modifier::BOLD),
         )]),
-        Line::from(vec![
-            Span::styled("  s                  ", colors.key_hint()),
-            Span::styled("Open settings", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  t                  ", colors.key_hint()),
-            Span::styled("Open theme selector", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  A                  ", colors.key_hint()),
-            Span::styled("About Hazelnut", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  ?                  ", colors.key_hint()),
-            Span::styled("Toggle this help", colors.text()),
-        ]),
-        Line::from(vec![
-            Span::styled("  q / Ctrl+c         ", colors.key_hint()),
-            Span::styled("Quit application", colors.text()),
-        ]),
+        help_line(&keys.label(KeyAction::Settings), "Open settings", &colors),
+        help_line(
+            &keys.label(KeyAction::Theme),
+            "Open theme selector",
+            &colors,
+        ),
+        help_line(
+            &keys.label(KeyAction::TestRules),
+            "Test rules against a file",
+            &colors,
+        ),
+        help_line(&keys.label(KeyAction::About), "About Hazelnut", &colors),
+        help_line(
+            &keys.label(KeyAction::Scan),
+            "Scan all watched folders now",
+            &colors,
+        ),
+        help_line(&keys.label(KeyAction::Help), "Toggle this help", &colors),
+        help_line(
+            &format!("{}/Ctrl+c", keys.label(KeyAction::Quit)),
+            "Quit application",
+            &colors,
+        ),
         Line::from(""),
         Line::from(vec![
             Span::styled("  Press ", colors.text_muted()),
             Span::styled("Esc", colors.key_hint()),
             Span::styled(" or ", colors.text_muted()),
-            Span::styled("?", colors.key_hint()),
+            Span::styled(keys.first(KeyAction::Help), colors.key_hint()),
             Span::styled(" to close", colors.text_muted()),
         ]),
     ];
@@ -775,6 +1476,14 @@ fn render_help_popup(frame: &mut Frame, state: &AppState) {
     frame.render_widget(help, popup_area);
 }
 
+/// A line of the help popup: keys, then what they do
+fn help_line(keys: &str, description: &str, colors: &ThemeColors) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("  {:<19}", keys), colors.key_hint()),
+        Span::styled(description.to_string(), colors.text()),
+    ])
+}
+
 /// Helper to create a centered rect
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_width = r.width * percent_x / 100;
@@ -794,24 +1503,20 @@ fn render_theme_picker(frame: &mut Frame, state: &AppState) {
     let popup_area = centered_rect(50, 70, area);
     frame.render_widget(Clear, popup_area);
 
-    let themes = Theme::all();
+    let themes = state.theme.choices(&state.config);
     let items: Vec<ListItem> = themes
         .iter()
         .enumerate()
-        .map(|(i, theme_name)| {
-            let palette = theme_name.palette();
+        .map(|(i, theme)| {
+            let palette = theme.colors();
             let selected = i == state.theme_picker_index;
 
             // Create color preview squares
-            let preview = format!(
-                "  {} {} ",
-                if selected { "▸" } else { " " },
-                theme_name.display_name()
-            );
+            let preview = format!("  {} {} ", if selected { "▸" } else { " " }, theme.name());
 
             let style = if selected {
                 Style::default()
-                    .fg(palette.accent)
+                    .fg(palette.primary)
                     .bg(palette.selection)
                     .add_modifier(Modifier::BOLD)
             } else {
@@ -820,7 +1525,7 @@ fn render_theme_picker(frame: &mut Frame, state: &AppState) {
 
             ListItem::new(Line::from(vec![
                 Span::styled(preview, style),
-                Span::styled("█", Style::default().fg(palette.accent)),
+                Span::styled("█", Style::default().fg(palette.primary)),
                 Span::styled("█", Style::default().fg(palette.secondary)),
                 Span::styled("█", Style::default().fg(palette.success)),
                 Span::styled("█", Style::default().fg(palette.warning)),
@@ -845,6 +1550,54 @@ fn render_theme_picker(frame: &mut Frame, state: &AppState) {
     frame.render_widget(theme_list, popup_area);
 }
 
+fn render_preset_picker(frame: &mut Frame, state: &AppState) {
+    let colors = state.theme.colors();
+    let area = frame.area();
+
+    let popup_area = centered_rect(70, 50, area);
+    frame.render_widget(Clear, popup_area);
+
+    let presets = &crate::presets::PRESETS;
+    let items: Vec<ListItem> = presets
+        .iter()
+        .enumerate()
+        .map(|(i, preset)| {
+            let selected = i == state.preset_picker_index;
+            let style = if selected {
+                colors.selected().add_modifier(Modifier::BOLD)
+            } else {
+                colors.text()
+            };
+            let destination = preset
+                .destination
+                .map(|d| format!(" → {}", d))
+                .unwrap_or_default();
+            ListItem::new(vec![
+                Line::from(Span::styled(
+                    format!("  {} {}", if selected { "▸" } else { " " }, preset.name),
+                    style,
+                )),
+                Line::from(Span::styled(
+                    format!("      {}{}", preset.description, destination),
+                    colors.text_dim(),
+                )),
+            ])
+        })
+        .collect();
+
+    let preset_list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(colors.primary))
+            .border_type(BorderType::Rounded)
+            .style(Style::default().bg(colors.bg))
+            .title(" 📋 Add Rules from Preset ")
+            .title_bottom(Line::from(" ↑↓ navigate │ ↵ add │ Esc cancel ").centered()),
+    );
+
+    frame.render_widget(preset_list, popup_area);
+}
+
 fn render_settings_dialog(frame: &mut Frame, state: &AppState) {
     let colors = state.theme.colors();
     let area = frame.area();
@@ -923,7 +1676,6 @@ fn get_settings_value_display(state: &AppState, item: SettingsItem) -> String {
                 "○ Stopped".to_string()
             }
         }
-        #[cfg(unix)]
         SettingsItem::AutoStartOnBoot => {
             if autostart::is_enabled() {
                 "✓ Enabled".to_string()
@@ -945,14 +1697,11 @@ fn get_settings_value_display(state: &AppState, item: SettingsItem) -> String {
                 "✗ Disabled".to_string()
             }
         }
-        SettingsItem::Notifications => {
-            let status = if state.config.general.notifications_enabled {
-                "✓ Enabled"
-            } else {
-                "✗ Disabled"
-            };
-            status.to_string()
-        }
+        SettingsItem::Notifications => match state.config.general.notification_level() {
+            NotificationLevel::All => "✓ All".to_string(),
+            NotificationLevel::Errors => "✓ Errors only".to_string(),
+            NotificationLevel::Off => "✗ Disabled".to_string(),
+        },
     }
 }
 
@@ -966,7 +1715,7 @@ fn render_rule_editor(frame: &mut Frame, state: &AppState) {
 
     // Calculate popup size - wider for the editor
     let popup_width = 70u16.min(area.width.saturating_sub(4));
-    let popup_height = 28u16.min(area.height.saturating_sub(4));
+    let popup_height = 29u16.min(area.height.saturating_sub(4));
 
     let popup_area = Rect {
         x: (area.width - popup_width) / 2,
@@ -1035,6 +1784,24 @@ fn render_rule_editor(frame: &mut Frame, state: &AppState) {
                 field_style(RuleEditorField::Enabled),
             ),
         ]),
+        Line::from(vec![
+            Span::styled(
+                format!(" {} ", cursor(RuleEditorField::StopProcessing)),
+                field_style(RuleEditorField::StopProcessing),
+            ),
+            Span::styled(
+                "Stop after:  ",
+                label_style(RuleEditorField::StopProcessing),
+            ),
+            Span::styled(
+                if editor.stop_processing {
+                    "✓ Yes"
+                } else {
+                    "✗ No"
+                },
+                field_style(RuleEditorField::StopProcessing),
+            ),
+        ]),
         Line::from(""),
         // Conditions Section
         Line::from(vec![Span::styled(
@@ -1284,23 +2051,23 @@ fn render_rule_editor(frame: &mut Frame, state: &AppState) {
     let prefix_len = 18u16;
     // Row = line_index + 1 (for border)
     // Line indices:
-    //  0: empty, 1: header, 2: Name, 3: Enabled, 4: empty, 5: header
-    //  6: Extension, 7: NameGlob, 8: NameRegex, 9: SizeGreater, 10: SizeLess
-    // 11: AgeGreater, 12: AgeLess, 13: IsDirectory, 14: IsHidden, 15: empty
-    // 16: header, 17: ActionType, 18: ActionDestination, 19: ActionPattern, 20: ActionCommand, 21: ActionArgs
+    //  0: empty, 1: header, 2: Name, 3: Enabled, 4: StopProcessing, 5: empty, 6: header
+    //  7: Extension, 8: NameGlob, 9: NameRegex, 10: SizeGreater, 11: SizeLess
+    // 12: AgeGreater, 13: AgeLess, 14: IsDirectory, 15: IsHidden, 16: empty
+    // 17: header, 18: ActionType, 19: ActionDestination, 20: ActionPattern, 21: ActionCommand, 22: ActionArgs
     let (field_row, cursor_offset) = match editor.field {
         RuleEditorField::Name => (3, editor.cursor_name), // line 2 + 1
-        RuleEditorField::Extension => (7, editor.cursor_extension), // line 6 + 1
-        RuleEditorField::NameGlob => (8, editor.cursor_name_glob), // line 7 + 1
-        RuleEditorField::NameRegex => (9, editor.cursor_name_regex), // line 8 + 1
-        RuleEditorField::SizeGreater => (10, editor.cursor_size_greater), // line 9 + 1
-        RuleEditorField::SizeLess => (11, editor.cursor_size_less), // line 10 + 1
-        RuleEditorField::AgeGreater => (12, editor.cursor_age_greater), // line 11 + 1
-        RuleEditorField::AgeLess => (13, editor.cursor_age_less), // line 12 + 1
-        RuleEditorField::ActionDestination => (19, editor.cursor_action_destination), // line 18 + 1
-        RuleEditorField::ActionPattern => (20, editor.cursor_action_pattern), // line 19 + 1
-        RuleEditorField::ActionCommand => (21, editor.cursor_action_command), // line 20 + 1
-        RuleEditorField::ActionArgs => (22, editor.cursor_action_args), // line 21 + 1
+        RuleEditorField::Extension => (8, editor.cursor_extension), // line 7 + 1
+        RuleEditorField::NameGlob => (9, editor.cursor_name_glob), // line 8 + 1
+        RuleEditorField::NameRegex => (10, editor.cursor_name_regex), // line 9 + 1
+        RuleEditorField::SizeGreater => (11, editor.cursor_size_greater), // line 10 + 1
+        RuleEditorField::SizeLess => (12, editor.cursor_size_less), // line 11 + 1
+        RuleEditorField::AgeGreater => (13, editor.cursor_age_greater), // line 12 + 1
+        RuleEditorField::AgeLess => (14, editor.cursor_age_less), // line 13 + 1
+        RuleEditorField::ActionDestination => (20, editor.cursor_action_destination), // line 19 + 1
+        RuleEditorField::ActionPattern => (21, editor.cursor_action_pattern), // line 20 + 1
+        RuleEditorField::ActionCommand => (22, editor.cursor_action_command), // line 21 + 1
+        RuleEditorField::ActionArgs => (23, editor.cursor_action_args), // line 22 + 1
         // Non-text fields don't need cursor
         _ => (0, 0),
     };
@@ -1322,6 +2089,7 @@ fn field_help(field: RuleEditorField) -> &'static str {
     match field {
         Name => "Type a descriptive name for this rule",
         Enabled => "Space/←→ to toggle on/off",
+        StopProcessing => "Space/←→ to toggle — skip later rules once this one matches",
         Extension => "e.g. 'pdf', 'jpg' — leave empty for any",
         NameGlob => "Glob pattern, e.g. 'Screenshot*.png' or '*.tmp'",
         NameRegex => "Regex pattern, e.g. '^invoice_\\d+\\.pdf$'",
@@ -1512,6 +2280,342 @@ fn watch_field_help(field: WatchEditorField) -> &'static str {
     }
 }
 
+/// Per-rule counts, destinations and activity over the last day or week
+fn render_stats(frame: &mut Frame, state: &AppState, area: Rect) {
+    let colors = state.theme.colors();
+    let period = state.stats_period;
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Totals
+            Constraint::Length(7), // Sparklines
+            Constraint::Min(0),    // Rules and destinations
+        ])
+        .split(area);
+
+    let block = |title: String| {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(colors.block())
+            .title(title)
+            .title_style(colors.text_primary())
+    };
+
+    let Some(ref stats) = state.stats else {
+        let loading = Paragraph::new(Line::styled("  Loading...", colors.text_muted()))
+            .block(block(" 📊 Statistics ".to_string()));
+        frame.render_widget(loading, area);
+        return;
+    };
+
+    // Totals
+    let total = stats.total();
+    let totals = if state.config.general.stats_retention_days == 0 {
+        Line::styled(
+            " Statistics are off (stats_retention_days = 0)",
+            colors.text_warning(),
+        )
+    } else {
+        Line::from(vec![
+            Span::styled(" Processed ", colors.text_dim()),
+            Span::styled(total.processed.to_string(), colors.text_success()),
+            Span::styled("  │  Failed ", colors.text_dim()),
+            Span::styled(
+                total.failed.to_string(),
+                if total.failed > 0 {
+                    colors.text_error()
+                } else {
+                    colors.text()
+                },
+            ),
+            Span::styled("  │  Unmatched ", colors.text_dim()),
+            Span::styled(stats.unmatched.to_string(), colors.text()),
+            Span::styled("  │  Moved/copied ", colors.text_dim()),
+            Span::styled(crate::progress::format_bytes(total.bytes), colors.text()),
+        ])
+    };
+    let title = format!(
+        " 📊 Statistics — {} [p: {} │ r: refresh] ",
+        period.label(),
+        period.toggle().label().to_lowercase()
+    );
+    frame.render_widget(
+        Paragraph::new(totals).block(block(title).border_style(colors.block_focus())),
+        chunks[0],
+    );
+
+    // Activity over the period, oldest slot on the left
+    let per = match period.slots().1 {
+        1 => "per hour".to_string(),
+        hours => format!("per {} hours", hours),
+    };
+    let sparklines = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[1]);
+    frame.render_widget(
+        Sparkline::default()
+            .block(block(format!(" Processed {} ", per)))
+            .data(stats.processed.iter().copied())
+            .style(colors.text_success()),
+        sparklines[0],
+    );
+    frame.render_widget(
+        Sparkline::default()
+            .block(block(format!(" Failed {} ", per)))
+            .data(stats.failed.iter().copied())
+            .style(colors.text_error()),
+        sparklines[1],
+    );
+
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(chunks[2]);
+
+    // Per-rule counts
+    let name_width = stats
+        .rules
+        .iter()
+        .map(|r| r.rule.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp(9, 30);
+    let mut items = vec![ListItem::new(Line::styled(
+        format!(
+            " {:<name_width$} {:>9} {:>7} {:>10}",
+            "Rule", "Processed", "Failed", "Size"
+        ),
+        colors.text_dim().add_modifier(Modifier::BOLD),
+    ))];
+    items.extend(stats.rules.iter().skip(state.stats_scroll).map(|totals| {
+        let name = if totals.rule.is_empty() {
+            "catch-all".to_string()
+        } else {
+            totals.rule.chars().take(name_width).collect()
+        };
+        let idle = totals.processed == 0 && totals.failed == 0;
+        ListItem::new(Line::from(vec![
+            Span::styled(
+                format!(" {:<name_width$}", name),
+                if idle {
+                    colors.text_muted()
+                } else {
+                    colors.text()
+                },
+            ),
+            Span::styled(
+                format!(" {:>9}", totals.processed),
+                if idle {
+                    colors.text_muted()
+                } else {
+                    colors.text_success()
+                },
+            ),
+            Span::styled(
+                format!(" {:>7}", totals.failed),
+                if totals.failed > 0 {
+                    colors.text_error()
+                } else {
+                    colors.text_muted()
+                },
+            ),
+            Span::styled(
+                format!(" {:>10}", crate::progress::format_bytes(totals.bytes)),
+                colors.text_muted(),
+            ),
+        ]))
+    }));
+    frame.render_widget(
+        List::new(items).block(block(format!(
+            " Rules ({}) [j/k: scroll] ",
+            stats.rules.len()
+        ))),
+        bottom[0],
+    );
+
+    // Where files went
+    let destinations: Vec<ListItem> = if stats.destinations.is_empty() {
+        vec![ListItem::new(Line::styled(
+            " Nothing moved or copied",
+            colors.text_muted(),
+        ))]
+    } else {
+        stats
+            .destinations
+            .iter()
+            .map(|(folder, files)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!(" {:>6} ", files), colors.text_secondary()),
+                    Span::styled(folder.display().to_string(), colors.text()),
+                ]))
+            })
+            .collect()
+    };
+    frame.render_widget(
+        List::new(destinations).block(block(" Top Destinations ".to_string())),
+        bottom[1],
+    );
+}
+
+fn render_rule_tester(frame: &mut Frame, state: &AppState) {
+    let colors = state.theme.colors();
+    let tester = &state.rule_tester;
+    let popup_area = centered_rect(80, 80, frame.area());
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(colors.primary))
+        .border_type(BorderType::Rounded)
+        .style(Style::default().bg(colors.bg))
+        .title(" 🔍 Rule Tester ")
+        .title_style(colors.text_primary())
+        .title_bottom(
+            Line::from(" Enter: test │ Tab: complete path │ ↑↓: scroll │ Esc: close ").centered(),
+        );
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2), // Path
+            Constraint::Min(0),    // Result
+        ])
+        .split(inner);
+
+    let input = Paragraph::new(Line::from(vec![
+        Span::styled(" File: ", colors.text_primary()),
+        if tester.path.is_empty() {
+            Span::styled("(type a path, Tab completes)", colors.text_muted())
+        } else {
+            Span::styled(&tester.path, colors.text())
+        },
+    ]));
+    frame.render_widget(input, chunks[0]);
+    // " File: " is 7 columns
+    let cursor_x = chunks[0].x + 7 + tester.cursor as u16;
+    if cursor_x < chunks[0].x + chunks[0].width {
+        frame.set_cursor_position((cursor_x, chunks[0].y));
+    }
+
+    let mut lines: Vec<Line> = Vec::new();
+    match &tester.report {
+        None => lines.push(Line::styled(
+            " Every rule's conditions are checked against the file, and none of their actions run.",
+            colors.text_muted(),
+        )),
+        Some(Err(error)) => lines.push(Line::styled(format!(" ✗ {}", error), colors.text_error())),
+        Some(Ok(report)) => {
+            let selected: Vec<String> = report
+                .selected()
+                .map(|r| format!("{} ({})", r.rule, r.action))
+                .collect();
+            lines.push(if !selected.is_empty() {
+                Line::styled(
+                    format!(" ✓ Would run: {}", selected.join(", then ")),
+                    colors.text_success().add_modifier(Modifier::BOLD),
+                )
+            } else if let Some(action) = report.unmatched_action {
+                Line::styled(
+                    format!(
+                        " ✓ No rule matches; the watch's unmatched_action ({}) would run",
+                        action
+                    ),
+                    colors.text_success().add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Line::styled(
+                    " ✗ No rule would act on it",
+                    colors.text_error().add_modifier(Modifier::BOLD),
+                )
+            });
+            lines.push(Line::styled(
+                match &report.watch {
+                    Some(watch) => format!("   Watch: {}", watch.display()),
+                    None => "   Watch: none".to_string(),
+                },
+                colors.text_dim(),
+            ));
+            for warning in &report.warnings {
+                lines.push(Line::styled(
+                    format!(" ⚠ {}", warning),
+                    colors.text_warning(),
+                ));
+            }
+            lines.push(Line::from(""));
+
+            for rule in &report.rules {
+                use crate::rules::Verdict;
+                let (icon, style, reason) = match &rule.verdict {
+                    Verdict::Selected => ("✓", colors.text_success(), "would run".to_string()),
+                    Verdict::NoMatch => (
+                        "✗",
+                        colors.text_error(),
+                        "conditions don't match".to_string(),
+                    ),
+                    Verdict::Disabled => ("·", colors.text_muted(), "disabled".to_string()),
+                    Verdict::NotInWatch => (
+                        "·",
+                        colors.text_muted(),
+                        "not in this watch's rules".to_string(),
+                    ),
+                    Verdict::FoldersOnly => (
+                        "·",
+                        colors.text_muted(),
+                        "folder rule, only matches folders".to_string(),
+                    ),
+                    Verdict::OwnDestination => (
+                        "·",
+                        colors.text_muted(),
+                        "its destination is inside this folder".to_string(),
+                    ),
+                    Verdict::Stopped { by } => (
+                        "·",
+                        colors.text_warning(),
+                        format!("matches, but '{}' stops processing first", by),
+                    ),
+                };
+                let no_conditions = rule.checks.is_empty() && rule.verdict != Verdict::FoldersOnly;
+                lines.push(Line::from(vec![
+                    Span::styled(format!(" {} ", icon), style),
+                    Span::styled(
+                        rule.rule.clone(),
+                        colors.text().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(format!(" · {}", rule.action), colors.text_dim()),
+                    Span::styled(format!("  {}", reason), style),
+                    Span::styled(
+                        if no_conditions {
+                            " (no conditions, matches everything)"
+                        } else {
+                            ""
+                        },
+                        colors.text_muted(),
+                    ),
+                ]));
+                for check in &rule.checks {
+                    let (icon, style) = if check.passed {
+                        ("✓", colors.text_success())
+                    } else {
+                        ("✗", colors.text_error())
+                    };
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("     {} ", icon), style),
+                        Span::styled(format!("{}: {}", check.name, check.expected), colors.text()),
+                        Span::styled(format!("  (file: {})", check.actual), colors.text_dim()),
+                    ]));
+                }
+            }
+        }
+    }
+
+    let result = Paragraph::new(lines).scroll((tester.scroll as u16, 0));
+    frame.render_widget(result, chunks[1]);
+}
+
 fn render_about_dialog(frame: &mut Frame, state: &AppState) {
     let colors = state.theme.colors();
     let area = frame.area();
@@ -3,18 +3,42 @@
 //! Supports:
 //! - macOS: LaunchAgent plist
 //! - Linux: systemd user service
+//! - Windows: a value in the per-user `Run` registry key, set with `reg.exe`,
+//!   that starts `hazelnutd run` in a minimized console
+//!
+//! Each named instance gets its own entry (e.g. `hazelnutd-work.service`).
 
 use std::fs;
 use std::io;
 use std::path::PathBuf;
 
+/// Registry key of the programs Windows starts when the user logs in
+const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
 /// Check if auto-start is currently enabled
 pub fn is_enabled() -> bool {
+    if cfg!(windows) {
+        return reg(&["query", RUN_KEY, "/v", &run_value_name()]).is_ok();
+    }
     get_autostart_path().map(|p| p.exists()).unwrap_or(false)
 }
 
 /// Enable auto-start for the daemon
 pub fn enable() -> io::Result<()> {
+    if cfg!(windows) {
+        return reg(&[
+            "add",
+            RUN_KEY,
+            "/v",
+            &run_value_name(),
+            "/t",
+            "REG_SZ",
+            "/d",
+            &run_command(&get_daemon_binary_path()?),
+            "/f",
+        ]);
+    }
+
     let path = get_autostart_path().ok_or_else(|| {
         io::Error::new(
             io::ErrorKind::Unsupported,
@@ -43,6 +67,13 @@ pub fn enable() -> io::Result<()> {
 
 /// Disable auto-start for the daemon
 pub fn disable() -> io::Result<()> {
+    if cfg!(windows) {
+        if is_enabled() {
+            reg(&["delete", RUN_KEY, "/v", &run_value_name(), "/f"])?;
+        }
+        return Ok(());
+    }
+
     let path = get_autostart_path().ok_or_else(|| {
         io::Error::new(
             io::ErrorKind::Unsupported,
@@ -83,7 +114,7 @@ fn get_autostart_path() -> Option<PathBuf> {
         dirs::home_dir().map(|h| {
             h.join("Library")
                 .join("LaunchAgents")
-                .join("me.ricardodantas.hazelnutd.plist")
+                .join(format!("{}.plist", launchd_label()))
         })
     }
 
@@ -96,14 +127,17 @@ fn get_autostart_path() -> Option<PathBuf> {
                     .map(|h| h.join(".config"))
                     .unwrap_or_else(|| PathBuf::from("/etc"))
             });
-            Some(
-                config
-                    .join("systemd")
-                    .join("user")
-                    .join("hazelnutd.service"),
-            )
+            Some(config.join("systemd").join("user").join(format!(
+                "{}.service",
+                crate::instance::suffixed("hazelnutd")
+            )))
         } else {
-            dirs::config_dir().map(|c| c.join("autostart").join("hazelnutd.desktop"))
+            dirs::config_dir().map(|c| {
+                c.join("autostart").join(format!(
+                    "{}.desktop",
+                    crate::instance::suffixed("hazelnutd")
+                ))
+            })
         }
     }
 
@@ -113,9 +147,50 @@ fn get_autostart_path() -> Option<PathBuf> {
     }
 }
 
+/// Name of the daemon's value in the `Run` key (per instance)
+fn run_value_name() -> String {
+    crate::instance::suffixed("hazelnutd")
+}
+
+/// Command line of the `Run` value. `hazelnutd` is a console program, so
+/// `start /min` keeps its window out of the way; closing it stops the daemon.
+fn run_command(binary: &std::path::Path) -> String {
+    std::iter::once(format!(
+        "cmd.exe /c start \"{}\" /min \"{}\"",
+        run_value_name(),
+        binary.display()
+    ))
+    .chain(crate::instance::daemon_args())
+    .chain(std::iter::once("run".to_string()))
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// Run `reg.exe`, turning a non-zero exit into an error
+fn reg(args: &[&str]) -> io::Result<()> {
+    let output = std::process::Command::new("reg").args(args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// LaunchAgent label, with the instance name for a named instance
+#[cfg(target_os = "macos")]
+fn launchd_label() -> String {
+    match crate::instance::name() {
+        Some(name) => format!("me.ricardodantas.hazelnutd.{}", name),
+        None => "me.ricardodantas.hazelnutd".to_string(),
+    }
+}
+
 /// Get the content for the autostart file
 fn get_autostart_content() -> io::Result<String> {
     let binary_path = get_daemon_binary_path()?;
+    let instance_args = crate::instance::daemon_args();
 
     #[cfg(target_os = "macos")]
     {
@@ -125,10 +200,10 @@ fn get_autostart_content() -> io::Result<String> {
 <plist version="1.0">
 <dict>
     <key>Label</key>
-    <string>me.ricardodantas.hazelnutd</string>
+    <string>{}</string>
     <key>ProgramArguments</key>
     <array>
-        <string>{}</string>
+        <string>{}</string>{}
         <string>run</string>
     </array>
     <key>RunAtLoad</key>
@@ -136,13 +211,19 @@ fn get_autostart_content() -> io::Result<String> {
     <key>KeepAlive</key>
     <false/>
     <key>StandardOutPath</key>
-    <string>/tmp/hazelnutd.stdout.log</string>
+    <string>/dev/null</string>
     <key>StandardErrorPath</key>
-    <string>/tmp/hazelnutd.stderr.log</string>
+    <string>{}</string>
 </dict>
 </plist>
 "#,
-            binary_path.display()
+            launchd_label(),
+            binary_path.display(),
+            instance_args
+                .iter()
+                .map(|arg| format!("\n        <string>{}</string>", arg))
+                .collect::<String>(),
+            crate::daemon_log_file().display(),
         ))
     }
 
@@ -156,32 +237,41 @@ After=default.target
 
 [Service]
 Type=simple
-ExecStart={} run
+ExecStart={}{} run
 Restart=on-failure
 RestartSec=5
 
 [Install]
 WantedBy=default.target
 "#,
-                binary_path.display()
+                binary_path.display(),
+                instance_args
+                    .iter()
+                    .map(|arg| format!(" {}", arg))
+                    .collect::<String>()
             ))
         } else {
             Ok(format!(
                 r#"[Desktop Entry]
 Type=Application
 Name=Hazelnut Daemon
-Exec={} run
+Exec={}{} run
 Hidden=false
 NoDisplay=true
 X-GNOME-Autostart-enabled=true
 "#,
-                binary_path.display()
+                binary_path.display(),
+                instance_args
+                    .iter()
+                    .map(|arg| format!(" {}", arg))
+                    .collect::<String>()
             ))
         }
     }
 
     #[cfg(not(any(target_os = "macos", target_os = "linux")))]
     {
+        let _ = (binary_path, instance_args);
         Err(io::Error::new(
             io::ErrorKind::Unsupported,
             "Auto-start not supported on this platform",
@@ -191,13 +281,16 @@ X-GNOME-Autostart-enabled=true
 
 /// Find the daemon binary path
 fn get_daemon_binary_path() -> io::Result<PathBuf> {
+    let binary = format!("hazelnutd{}", std::env::consts::EXE_SUFFIX);
+
     // First try to find hazelnutd in PATH
-    if let Ok(output) = std::process::Command::new("which")
-        .arg("hazelnutd")
-        .output()
+    let which = if cfg!(windows) { "where" } else { "which" };
+    if let Ok(output) = std::process::Command::new(which).arg("hazelnutd").output()
         && output.status.success()
     {
-        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        // `where` lists every match
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let path = stdout.lines().next().unwrap_or_default().trim();
         if !path.is_empty() {
             return Ok(PathBuf::from(path));
         }
@@ -217,14 +310,23 @@ fn get_daemon_binary_path() -> io::Result<PathBuf> {
         }
     }
 
-    // Last resort: check if cargo installed it
+    // Check if cargo installed it
     if let Some(home) = dirs::home_dir() {
-        let cargo_bin = home.join(".cargo").join("bin").join("hazelnutd");
+        let cargo_bin = home.join(".cargo").join("bin").join(&binary);
         if cargo_bin.exists() {
             return Ok(cargo_bin);
         }
     }
 
+    // Last resort: next to this binary, as unpacked from a release archive
+    if let Some(sibling) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join(&binary)))
+        && sibling.exists()
+    {
+        return Ok(sibling);
+    }
+
     Err(io::Error::new(
         io::ErrorKind::NotFound,
         "Could not find hazelnutd binary. Make sure it's installed and in PATH.",
@@ -241,3 +343,16 @@ fn is_systemd_available() -> bool {
         .map(|o| o.status.success())
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_command() {
+        assert_eq!(
+            run_command(std::path::Path::new(r"C:\Tools\hazelnutd.exe")),
+            r#"cmd.exe /c start "hazelnutd" /min "C:\Tools\hazelnutd.exe" run"#
+        );
+    }
+}
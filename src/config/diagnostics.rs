@@ -0,0 +1,845 @@
+//! Config diagnostics for `hazelnut config validate`
+//!
+//! Serde silently ignores keys it doesn't know, and a config can parse yet
+//! not do what was meant: a watch naming a rule that doesn't exist, a move
+//! to a folder that isn't there, a bound no file can meet. `diagnose` finds
+//! those and reports each with the line it's on, as an error (part of the
+//! config won't work) or a warning (probably not what was meant).
+//!
+//! Unknown keys are found by probing: a key is replaced with an integer and
+//! then with a string. Serde rejects at least one of them for any field it
+//! knows, and accepts both for a key it ignores.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use toml::de::{DeTable, DeValue};
+
+use super::Config;
+use crate::notifications::NotificationLevel;
+use crate::plugins::PluginCall;
+use crate::rules::{Action, Condition};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found in the config
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 1-based line the problem is on, if known
+    pub line: Option<usize>,
+    /// 1-based column on that line, for syntax errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    /// The key concerned, e.g. `rule[2].action.destination`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub message: String,
+}
+
+/// Everything found in a config file
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    /// The config, if it parsed
+    pub config: Option<Config>,
+    pub items: Vec<Diagnostic>,
+    /// Line of each key, e.g. `watch[0].path`
+    lines: HashMap<String, usize>,
+}
+
+impl Diagnostics {
+    pub fn errors(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.items.iter().filter(|d| d.severity == severity).count()
+    }
+
+    /// Report a problem with a key, on the line of the key (or of the
+    /// nearest table around it)
+    pub fn push(&mut self, severity: Severity, key: &str, message: impl Into<String>) {
+        let mut path = key;
+        let line = loop {
+            if let Some(&line) = self.lines.get(path) {
+                break Some(line);
+            }
+            match path.rfind(['.', '[']) {
+                Some(i) => path = &path[..i],
+                None => break None,
+            }
+        };
+        self.items.push(Diagnostic {
+            severity,
+            line,
+            column: None,
+            key: (!key.is_empty()).then(|| key.to_string()),
+            message: message.into(),
+        });
+    }
+}
+
+/// Check a config file's contents
+pub fn diagnose(content: &str) -> Diagnostics {
+    let mut diagnostics = Diagnostics::default();
+    let document = match DeTable::parse(content) {
+        Ok(document) => document,
+        Err(e) => {
+            diagnostics.items.push(parse_error(content, &e));
+            return diagnostics;
+        }
+    };
+    collect_lines(content, document.get_ref(), "", &mut diagnostics.lines);
+
+    // An older format is checked as it will be once upgraded
+    let migration = match super::migrate::migrate(content) {
+        Ok(migration) => migration,
+        Err(e) => {
+            diagnostics.push(Severity::Error, "version", format!("{:#}", e));
+            return diagnostics;
+        }
+    };
+    let upgraded = match &migration {
+        Some(migration) => {
+            for change in &migration.changes {
+                diagnostics.push(
+                    Severity::Warning,
+                    &change.key,
+                    format!(
+                        "{} (upgrading from config version {} when loaded)",
+                        change.message, migration.from
+                    ),
+                );
+            }
+            migration.content.as_str()
+        }
+        None => content,
+    };
+
+    let config: Config = match toml::from_str(upgraded) {
+        Ok(config) => config,
+        Err(e) => {
+            let mut error = parse_error(upgraded, &e);
+            if migration.is_some() {
+                // Lines in the upgraded file aren't lines in this one
+                error.line = None;
+                error.column = None;
+            }
+            diagnostics.items.push(error);
+            return diagnostics;
+        }
+    };
+    let variables = config.variables.clone();
+    let config = match super::variables::resolve(config) {
+        Ok(config) => config,
+        Err(e) => {
+            diagnostics
+                .items
+                .push(variable_error(content, &variables, &e));
+            return diagnostics;
+        }
+    };
+    if let Ok(table) = upgraded.parse::<toml::Table>() {
+        // JSON keeps unset options (as null), so they can be suggested too
+        let serialized = serde_json::to_value(&config).ok();
+        unknown_keys(&table, &serialized, &mut diagnostics);
+    }
+    check(&config, &mut diagnostics);
+    diagnostics.items.sort_by_key(|d| d.line);
+    diagnostics.config = Some(config);
+    diagnostics
+}
+
+fn parse_error(content: &str, error: &toml::de::Error) -> Diagnostic {
+    Diagnostic {
+        severity: Severity::Error,
+        line: error.span().map(|span| line_of(content, span.start)),
+        column: error.span().map(|span| column_of(content, span.start)),
+        key: None,
+        message: error.message().trim_end().to_string(),
+    }
+}
+
+/// An unknown variable, on the first line referencing one
+fn variable_error(
+    content: &str,
+    variables: &BTreeMap<String, String>,
+    error: &anyhow::Error,
+) -> Diagnostic {
+    let line = content.lines().position(|line| {
+        !line.trim_start().starts_with('#')
+            && super::variables::references(line).any(|name| !variables.contains_key(name))
+    });
+    Diagnostic {
+        severity: Severity::Error,
+        line: line.map(|i| i + 1),
+        column: None,
+        key: None,
+        message: error.to_string(),
+    }
+}
+
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+fn column_of(content: &str, offset: usize) -> usize {
+    let before = &content[..offset.min(content.len())];
+    let start = before.rfind('\n').map_or(0, |i| i + 1);
+    before[start..].chars().count() + 1
+}
+
+fn collect_lines(content: &str, table: &DeTable, prefix: &str, lines: &mut HashMap<String, usize>) {
+    for (key, value) in table {
+        let path = join(prefix, key.get_ref());
+        lines.insert(path.clone(), line_of(content, key.span().start));
+        match value.get_ref() {
+            DeValue::Table(table) => collect_lines(content, table, &path, lines),
+            DeValue::Array(array) => {
+                for (i, item) in array.iter().enumerate() {
+                    let path = format!("{}[{}]", path, i);
+                    lines.insert(path.clone(), line_of(content, item.span().start));
+                    if let DeValue::Table(table) = item.get_ref() {
+                        collect_lines(content, table, &path, lines);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// A step into a TOML value
+#[derive(Clone)]
+enum Step<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+fn get_mut<'v>(value: &'v mut toml::Value, path: &[Step]) -> Option<&'v mut toml::Value> {
+    path.iter().try_fold(value, |value, step| match step {
+        Step::Key(key) => value.as_table_mut()?.get_mut(*key),
+        Step::Index(i) => value.as_array_mut()?.get_mut(*i),
+    })
+}
+
+fn get<'v>(value: &'v serde_json::Value, path: &[Step]) -> Option<&'v serde_json::Value> {
+    path.iter().try_fold(value, |value, step| match step {
+        Step::Key(key) => value.get(*key),
+        Step::Index(i) => value.get(*i),
+    })
+}
+
+fn unknown_keys(
+    table: &toml::Table,
+    serialized: &Option<serde_json::Value>,
+    diagnostics: &mut Diagnostics,
+) {
+    let root = toml::Value::Table(table.clone());
+    let mut path = Vec::new();
+    walk(&root, &root, &mut path, "", serialized, diagnostics);
+}
+
+fn walk<'a>(
+    root: &toml::Value,
+    value: &'a toml::Value,
+    path: &mut Vec<Step<'a>>,
+    prefix: &str,
+    serialized: &Option<serde_json::Value>,
+    diagnostics: &mut Diagnostics,
+) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key_path = join(prefix, key);
+                path.push(Step::Key(key));
+                if is_known(root, path) {
+                    walk(root, value, path, &key_path, serialized, diagnostics);
+                } else {
+                    let known = serialized
+                        .as_ref()
+                        .and_then(|s| get(s, &path[..path.len() - 1]))
+                        .and_then(|v| v.as_object());
+                    let hint = known
+                        .and_then(|known| closest(key, known.keys().map(String::as_str)))
+                        .map(|name| format!(" (did you mean `{}`?)", name))
+                        .unwrap_or_default();
+                    diagnostics.push(
+                        Severity::Warning,
+                        &key_path,
+                        format!("Unknown key `{}` is ignored{}", key, hint),
+                    );
+                }
+                path.pop();
+            }
+        }
+        toml::Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                path.push(Step::Index(i));
+                walk(
+                    root,
+                    item,
+                    path,
+                    &format!("{}[{}]", prefix, i),
+                    serialized,
+                    diagnostics,
+                );
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether the config uses a key: replacing it with a value of the wrong
+/// type makes it fail to load
+fn is_known(root: &toml::Value, path: &[Step]) -> bool {
+    let probes = [
+        toml::Value::Integer(0),
+        toml::Value::String("\u{1}".to_string()),
+    ];
+    probes.into_iter().any(|probe| {
+        let mut probed = root.clone();
+        match get_mut(&mut probed, path) {
+            Some(value) => *value = probe,
+            None => return true,
+        }
+        Config::deserialize(probed).is_err()
+    })
+}
+
+/// The known key most like a mistyped one, if any is close
+fn closest<'a>(key: &str, known: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    known
+        .map(|name| (edit_distance(key, name), name))
+        .filter(|&(distance, name)| distance <= 2.max(name.len() / 4))
+        .min()
+        .map(|(_, name)| name)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(current)
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+/// Mistakes in a config that loaded
+fn check(config: &Config, diagnostics: &mut Diagnostics) {
+    let mut names = HashSet::new();
+    for (i, rule) in config.rules.iter().enumerate() {
+        let key = format!("rule[{}]", i);
+        if !names.insert(rule.name.as_str()) {
+            diagnostics.push(
+                Severity::Warning,
+                &format!("{}.name", key),
+                format!(
+                    "Another rule is also named '{}'; watches' `rules` lists can't tell them apart",
+                    rule.name
+                ),
+            );
+        }
+        if let Err(e) = rule.condition.validate() {
+            diagnostics.push(
+                Severity::Error,
+                &format!("{}.condition", key),
+                format!("Rule '{}': {:#}", rule.name, e),
+            );
+        }
+        for (bound, message) in never_matches(&rule.condition) {
+            diagnostics.push(
+                Severity::Warning,
+                &format!("{}.condition.{}", key, bound),
+                format!("Rule '{}' never matches: {}", rule.name, message),
+            );
+        }
+        check_action(
+            &rule.action,
+            &format!("{}.action", key),
+            &format!("Rule '{}'", rule.name),
+            diagnostics,
+        );
+        let owner = format!("Rule '{}'", rule.name);
+        for (j, call) in rule.condition.plugins.iter().enumerate() {
+            let key = format!("{}.condition.plugins[{}]", key, j);
+            check_plugin(config, call, &key, &owner, diagnostics);
+        }
+        if let Action::Plugin(call) = &rule.action {
+            check_plugin(
+                config,
+                call,
+                &format!("{}.action", key),
+                &owner,
+                diagnostics,
+            );
+        }
+    }
+
+    let mut paths = HashSet::new();
+    for (i, watch) in config.watches.iter().enumerate() {
+        let key = format!("watch[{}]", i);
+        let path = crate::expand_path(&watch.path);
+        let remote = crate::sftp::is_remote(&watch.path);
+        if !paths.insert(path.clone()) {
+            diagnostics.push(
+                Severity::Warning,
+                &format!("{}.path", key),
+                format!("{} is watched more than once", watch.path.display()),
+            );
+        }
+        if !remote && !path.is_dir() {
+            diagnostics.push(
+                Severity::Warning,
+                &format!("{}.path", key),
+                format!("Watched folder {} doesn't exist", path.display()),
+            );
+        }
+        for name in &watch.rules {
+            if !config.rules.iter().any(|rule| &rule.name == name) {
+                diagnostics.push(
+                    Severity::Error,
+                    &format!("{}.rules", key),
+                    format!(
+                        "Watch {} lists rule '{}', but no rule has that name",
+                        watch.path.display(),
+                        name
+                    ),
+                );
+            }
+        }
+        for pattern in &watch.exclude_dirs {
+            if glob::Pattern::new(pattern).is_err() {
+                diagnostics.push(
+                    Severity::Warning,
+                    &format!("{}.exclude_dirs", key),
+                    format!(
+                        "exclude_dirs pattern '{}' isn't a valid glob, so it only matches a folder with exactly that name",
+                        pattern
+                    ),
+                );
+            }
+        }
+        if !watch.recursive {
+            let unused: Vec<_> = [
+                ("exclude_dirs", !watch.exclude_dirs.is_empty()),
+                ("max_depth", watch.max_depth.is_some()),
+                ("remove_empty_dirs", watch.remove_empty_dirs),
+            ]
+            .into_iter()
+            .filter_map(|(name, set)| set.then_some(name))
+            .collect();
+            for name in unused {
+                diagnostics.push(
+                    Severity::Warning,
+                    &format!("{}.{}", key, name),
+                    format!("{} has no effect without `recursive = true`", name),
+                );
+            }
+        }
+        if watch.poll_interval_secs.is_some() && watch.mode != super::WatchMode::Poll {
+            diagnostics.push(
+                Severity::Warning,
+                &format!("{}.poll_interval_secs", key),
+                "poll_interval_secs has no effect without `mode = \"poll\"`",
+            );
+        }
+        if let Err(e) = crate::quota::validate(watch) {
+            diagnostics.push(
+                Severity::Error,
+                &format!("{}.quota_action", key),
+                format!("{:#}", e),
+            );
+        }
+        for (name, set) in [
+            ("max_size", watch.max_size.is_some()),
+            ("remove_empty_dirs", watch.remove_empty_dirs),
+        ] {
+            if set && remote {
+                diagnostics.push(
+                    Severity::Warning,
+                    &format!("{}.{}", key, name),
+                    format!("{} has no effect on remote folders", name),
+                );
+            }
+        }
+        if watch.max_size.is_none() {
+            let unused: Vec<_> = [
+                (
+                    "quota_action",
+                    watch.quota_action != crate::retention::RetentionAction::Trash,
+                ),
+                ("quota_archive_to", watch.quota_archive_to.is_some()),
+            ]
+            .into_iter()
+            .filter_map(|(name, set)| set.then_some(name))
+            .collect();
+            for name in unused {
+                diagnostics.push(
+                    Severity::Warning,
+                    &format!("{}.{}", key, name),
+                    format!("{} has no effect without `max_size`", name),
+                );
+            }
+        }
+        if let Some(action) = &watch.unmatched_action {
+            check_action(
+                action,
+                &format!("{}.unmatched_action", key),
+                &format!("Watch {}", watch.path.display()),
+                diagnostics,
+            );
+            if let Action::Plugin(call) = action {
+                check_plugin(
+                    config,
+                    call,
+                    &format!("{}.unmatched_action", key),
+                    &format!("Watch {}", watch.path.display()),
+                    diagnostics,
+                );
+            }
+        }
+    }
+
+    for (name, profile) in &config.profiles {
+        let key = format!("profile.{}", name);
+        for path in &profile.watches {
+            let expanded = crate::expand_path(path);
+            if !paths.contains(&expanded) {
+                diagnostics.push(
+                    Severity::Error,
+                    &format!("{}.watches", key),
+                    format!(
+                        "Profile '{}' lists watch {}, but it isn't watched",
+                        name,
+                        path.display()
+                    ),
+                );
+            }
+        }
+        for rule in &profile.rules {
+            if !names.contains(rule.as_str()) {
+                diagnostics.push(
+                    Severity::Error,
+                    &format!("{}.rules", key),
+                    format!(
+                        "Profile '{}' lists rule '{}', but no rule has that name",
+                        name, rule
+                    ),
+                );
+            }
+        }
+    }
+
+    for (i, policy) in config.retention.iter().enumerate() {
+        let key = format!("retention[{}]", i);
+        if let Err(e) = policy.validate() {
+            diagnostics.push(Severity::Error, &key, format!("{:#}", e));
+        }
+        let path = crate::expand_path(&policy.path);
+        if !path.is_dir() {
+            diagnostics.push(
+                Severity::Warning,
+                &format!("{}.path", key),
+                format!("Retention folder {} doesn't exist", path.display()),
+            );
+        }
+    }
+    if let Err(e) = config.notifications.validate() {
+        diagnostics.push(Severity::Error, "notifications", format!("{:#}", e));
+    }
+    if let Err(e) = config.api.validate() {
+        diagnostics.push(Severity::Error, "api.token", format!("{:#}", e));
+    }
+    // Rules with `notify` on send anyway, unless the level is explicitly off
+    let rules_notify = config.general.notification_level.is_none()
+        && config.rules.iter().any(|rule| {
+            rule.notify
+                .as_ref()
+                .is_some_and(|notify| notify.is_enabled())
+        });
+    if config.general.notification_level() == NotificationLevel::Off
+        && !config.notifications.is_default()
+        && !rules_notify
+        && diagnostics.lines.contains_key("notifications")
+    {
+        diagnostics.push(
+            Severity::Warning,
+            "notifications",
+            "[notifications] has no effect while notifications are off in [general]",
+        );
+    }
+}
+
+fn check_action(action: &Action, key: &str, owner: &str, diagnostics: &mut Diagnostics) {
+    if let Err(e) = action.validate() {
+        diagnostics.push(
+            Severity::Error,
+            &format!("{}.script", key),
+            format!("{}: {:#}", owner, e),
+        );
+    }
+    match action {
+        Action::Move {
+            destination,
+            create_destination: false,
+            ..
+        }
+        | Action::Copy {
+            destination,
+            create_destination: false,
+            ..
+        } if !crate::sftp::is_remote(destination) => {
+            let path = crate::expand_path(destination);
+            if !contains_placeholder(destination) && !path.is_dir() {
+                diagnostics.push(
+                    Severity::Error,
+                    &format!("{}.destination", key),
+                    format!(
+                        "{}: destination {} doesn't exist and create_destination is false, so every {} fails",
+                        owner,
+                        path.display(),
+                        action.kind()
+                    ),
+                );
+            }
+        }
+        Action::Tier { stages } if stages.is_empty() => diagnostics.push(
+            Severity::Error,
+            &format!("{}.stages", key),
+            format!("{}: a tier action needs at least one stage", owner),
+        ),
+        Action::Run { command, .. } if command.trim().is_empty() => diagnostics.push(
+            Severity::Error,
+            &format!("{}.command", key),
+            format!("{}: the run action's command is empty", owner),
+        ),
+        _ => {}
+    }
+}
+
+/// A `type = "plugin:<name>"` needs a `[plugin.<name>]` table
+fn check_plugin(
+    config: &Config,
+    call: &PluginCall,
+    key: &str,
+    owner: &str,
+    diagnostics: &mut Diagnostics,
+) {
+    if !config.plugins.contains_key(&call.name) {
+        diagnostics.push(
+            Severity::Error,
+            &format!("{}.type", key),
+            format!(
+                "{} uses plugin '{}', but there is no [plugin.{}] table",
+                owner, call.name, call.name
+            ),
+        );
+    }
+}
+
+/// Destinations with `{...}` placeholders are only known per file
+fn contains_placeholder(path: &Path) -> bool {
+    path.to_string_lossy().contains('{')
+}
+
+/// Lower and upper bounds of a condition that no file can meet, with the
+/// key of the upper bound
+fn never_matches(condition: &Condition) -> Vec<(&'static str, String)> {
+    let bounds: [(&str, &str, Option<f64>, Option<f64>); 6] = [
+        (
+            "size_greater_than",
+            "size_less_than",
+            condition.size_greater_than.map(|v| v as f64),
+            condition.size_less_than.map(|v| v as f64),
+        ),
+        (
+            "age_days_greater_than",
+            "age_days_less_than",
+            condition.age_days_greater_than.map(|v| v as f64),
+            condition.age_days_less_than.map(|v| v as f64),
+        ),
+        (
+            "duration_secs_greater_than",
+            "duration_secs_less_than",
+            condition.duration_secs_greater_than,
+            condition.duration_secs_less_than,
+        ),
+        (
+            "width_greater_than",
+            "width_less_than",
+            condition.width_greater_than.map(f64::from),
+            condition.width_less_than.map(f64::from),
+        ),
+        (
+            "height_greater_than",
+            "height_less_than",
+            condition.height_greater_than.map(f64::from),
+            condition.height_less_than.map(f64::from),
+        ),
+        (
+            "framerate_greater_than",
+            "framerate_less_than",
+            condition.framerate_greater_than,
+            condition.framerate_less_than,
+        ),
+    ];
+    bounds
+        .into_iter()
+        .filter_map(|(greater, less, low, high)| match (low, high) {
+            (Some(low), Some(high)) if low >= high => Some((
+                less,
+                format!("{} ({}) isn't below {} ({})", greater, low, less, high),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = format!(
+            r#"version = 2
+[general]
+log_levle = "debug"
+
+[[watch]]
+path = "{dir}"
+rules = ["PDFs", "Invoices"]
+max_depth = 2
+
+[[rule]]
+name = "PDFs"
+[rule.condition]
+extension = "pdf"
+size_greater_than = 100
+size_less_than = 10
+[rule.action]
+type = "move"
+destination = "{dir}/missing"
+create_destination = false
+"#,
+            dir = dir.path().display()
+        );
+        let diagnostics = diagnose(&content);
+        assert!(diagnostics.config.is_some());
+        let found: Vec<_> = diagnostics
+            .items
+            .iter()
+            .map(|d| (d.severity, d.line, d.key.as_deref().unwrap_or("")))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Severity::Warning, Some(3), "general.log_levle"),
+                (Severity::Error, Some(7), "watch[0].rules"),
+                (Severity::Warning, Some(8), "watch[0].max_depth"),
+                (
+                    Severity::Warning,
+                    Some(15),
+                    "rule[0].condition.size_less_than"
+                ),
+                (Severity::Error, Some(18), "rule[0].action.destination"),
+            ]
+        );
+        assert!(
+            diagnostics.items[0]
+                .message
+                .contains("did you mean `log_level`")
+        );
+        assert_eq!(diagnostics.errors(), 2);
+
+        let broken = diagnose(
+            "version = 2\n[general]\nlog_level = \"info\"\nnotifications_enabled = \"yes\"\n",
+        );
+        assert!(broken.config.is_none());
+        assert_eq!(broken.items.len(), 1);
+        assert_eq!(broken.items[0].line, Some(4));
+    }
+
+    #[test]
+    fn test_diagnose_syntax_error() {
+        let diagnostics = diagnose("version = 2\n[general]\nlog_level = \"info\nmax_depth = 2\n");
+        assert!(diagnostics.config.is_none());
+        assert_eq!(diagnostics.items.len(), 1);
+        let error = &diagnostics.items[0];
+        assert_eq!(error.severity, Severity::Error);
+        // Where the string should have ended
+        assert_eq!((error.line, error.column), (Some(3), Some(18)));
+    }
+
+    #[test]
+    fn test_diagnose_rules() {
+        let diagnostics = diagnose(
+            r#"version = 2
+[[rule]]
+name = "Bad regex"
+[rule.condition]
+name_regex = "(unclosed"
+[rule.action]
+type = "trash"
+
+[[rule]]
+name = "Bad glob"
+[rule.condition]
+name_matches = "[a-"
+extention = "pdf"
+[rule.action]
+type = "trash"
+"#,
+        );
+        let found: Vec<_> = diagnostics
+            .items
+            .iter()
+            .map(|d| (d.severity, d.line, d.key.as_deref().unwrap_or("")))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Severity::Error, Some(4), "rule[0].condition"),
+                (Severity::Error, Some(11), "rule[1].condition"),
+                (Severity::Warning, Some(13), "rule[1].condition.extention"),
+            ]
+        );
+        assert!(diagnostics.items[0].message.contains("name_regex"));
+        assert!(diagnostics.items[1].message.contains("name_matches"));
+        assert!(
+            diagnostics.items[2]
+                .message
+                .contains("did you mean `extension`")
+        );
+    }
+}
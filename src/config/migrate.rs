@@ -0,0 +1,267 @@
+//! Config format versions and upgrades
+//!
+//! The config file carries a `version`; files without one are version 1.
+//! When the format changes (a key is renamed, a table restructured), a step
+//! is added to `STEPS` that rewrites a file of the previous version. Steps
+//! edit the parsed document in place, so comments and layout survive.
+
+use anyhow::{Result, bail};
+use std::path::{Path, PathBuf};
+use toml_edit::DocumentMut;
+
+/// Rewrites a config into the next version, noting each change
+type Step = fn(&mut DocumentMut, &mut Vec<Change>);
+
+/// Upgrade steps, oldest first: the first turns version 1 into version 2
+const STEPS: &[Step] = &[overwrite_to_on_conflict];
+
+/// Version of the config format this build reads and writes
+pub const CURRENT_VERSION: u32 = 1 + STEPS.len() as u32;
+
+/// A change made while upgrading
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// The key concerned, as it's called now, e.g. `general.theme`
+    pub key: String,
+    pub message: String,
+}
+
+/// A config file upgraded to the current version
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub from: u32,
+    pub to: u32,
+    /// The upgraded file
+    pub content: String,
+    pub changes: Vec<Change>,
+}
+
+/// Upgrade a config file's contents. `None` if it's already current, or
+/// isn't valid TOML (parsing it reports that).
+pub fn migrate(content: &str) -> Result<Option<Migration>> {
+    upgrade(content, STEPS)
+}
+
+fn upgrade(content: &str, steps: &[Step]) -> Result<Option<Migration>> {
+    let Ok(mut document) = content.parse::<DocumentMut>() else {
+        return Ok(None);
+    };
+    let current = 1 + steps.len() as u32;
+    let from = match document.get("version") {
+        None => 1,
+        Some(item) => match item.as_integer().and_then(|v| u32::try_from(v).ok()) {
+            Some(version) if version >= 1 => version,
+            _ => bail!(
+                "Invalid config version {}, expected a number from 1 to {}",
+                item.to_string().trim(),
+                current
+            ),
+        },
+    };
+    if from > current {
+        bail!(
+            "Config version {} is newer than this hazelnut understands (up to {}); update hazelnut",
+            from,
+            current
+        );
+    }
+    if from == current {
+        return Ok(None);
+    }
+
+    let mut changes = Vec::new();
+    for step in &steps[from as usize - 1..] {
+        step(&mut document, &mut changes);
+    }
+    let content = if document.contains_key("version") {
+        document["version"] = toml_edit::value(i64::from(current));
+        document.to_string()
+    } else {
+        stamp(&document.to_string(), current)
+    };
+    changes.push(Change {
+        key: "version".to_string(),
+        message: format!("Set version = {}", current),
+    });
+    Ok(Some(Migration {
+        from,
+        to: current,
+        content,
+        changes,
+    }))
+}
+
+/// Version 2: move and copy actions take `on_conflict` instead of the
+/// `overwrite` flag
+fn overwrite_to_on_conflict(document: &mut DocumentMut, changes: &mut Vec<Change>) {
+    for (section, key) in [("rule", "action"), ("watch", "unmatched_action")] {
+        let Some(entries) = document
+            .get_mut(section)
+            .and_then(|item| item.as_array_of_tables_mut())
+        else {
+            continue;
+        };
+        for (i, entry) in entries.iter_mut().enumerate() {
+            let Some(action) = entry
+                .get_mut(key)
+                .and_then(|action| action.as_table_like_mut())
+            else {
+                continue;
+            };
+            let Some(overwrite) = action.remove("overwrite") else {
+                continue;
+            };
+            let path = format!("{}[{}].{}", section, i, key);
+            if overwrite.as_bool() == Some(true) {
+                action.insert("on_conflict", toml_edit::value("overwrite"));
+                changes.push(Change {
+                    key: format!("{}.on_conflict", path),
+                    message: format!(
+                        "Replaced {}.overwrite = true with on_conflict = \"overwrite\"",
+                        path
+                    ),
+                });
+            } else {
+                changes.push(Change {
+                    key: format!("{}.on_conflict", path),
+                    message: format!(
+                        "Removed {}.overwrite = false (on_conflict defaults to \"fail\")",
+                        path
+                    ),
+                });
+            }
+            // Keeps `{ type = "copy" }` spaced once a trailing key is gone
+            if let Some(inline) = entry.get_mut(key).and_then(|a| a.as_inline_table_mut()) {
+                inline.fmt();
+            }
+        }
+    }
+}
+
+/// Add `version` at the top of a file, below any `#:schema` directive
+fn stamp(content: &str, version: u32) -> String {
+    let directives: usize = content
+        .split_inclusive('\n')
+        .take_while(|line| line.starts_with("#:"))
+        .map(str::len)
+        .sum();
+    let (head, rest) = content.split_at(directives);
+    format!("{}version = {}\n\n{}", head, version, rest)
+}
+
+/// Where the file is kept before being upgraded, e.g. `config.toml.v1.bak`
+pub fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename_highlight(document: &mut DocumentMut, changes: &mut Vec<Change>) {
+        let Some(custom) = document
+            .get_mut("theme")
+            .and_then(|theme| theme.get_mut("custom"))
+            .and_then(|custom| custom.as_table_like_mut())
+        else {
+            return;
+        };
+        if let Some(color) = custom.remove("highlight") {
+            custom.insert("selection", color);
+            changes.push(Change {
+                key: "theme.custom.selection".to_string(),
+                message: "Renamed theme.custom.highlight to selection".to_string(),
+            });
+        }
+    }
+
+    #[test]
+    fn test_migrate() {
+        assert_eq!(CURRENT_VERSION, 2);
+        assert!(migrate("version = 2\n").unwrap().is_none());
+        assert!(migrate("[general]\n").unwrap().is_some());
+        assert!(migrate("version = 99\n").is_err());
+        assert!(migrate("version = \"one\"\n").is_err());
+
+        let old = "#:schema ./config.schema.json\n# My colors\n[theme.custom]\nhighlight = \"#283457\" # selected item\n";
+        let migration = upgrade(old, &[rename_highlight]).unwrap().unwrap();
+        assert_eq!((migration.from, migration.to), (1, 2));
+        assert_eq!(
+            migration.content,
+            "#:schema ./config.schema.json\nversion = 2\n\n# My colors\n[theme.custom]\nselection = \"#283457\" # selected item\n"
+        );
+        assert_eq!(migration.changes.len(), 2);
+        assert_eq!(migration.changes[0].key, "theme.custom.selection");
+
+        // Upgrading an upgraded file changes nothing
+        assert!(
+            upgrade(&migration.content, &[rename_highlight])
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            backup_path(Path::new("/c/work.toml"), 1),
+            Path::new("/c/work.toml.v1.bak")
+        );
+    }
+
+    #[test]
+    fn test_overwrite_to_on_conflict() {
+        let old = r#"[[rule]]
+name = "PDFs"
+[rule.action]
+type = "move"
+destination = "~/Documents"
+overwrite = true # replace older scans
+
+[[rule]]
+name = "Backups"
+action = { type = "copy", destination = "/backup", overwrite = false }
+
+[[watch]]
+path = "~/Downloads"
+unmatched_action = { type = "trash" }
+"#;
+        let migration = migrate(old).unwrap().unwrap();
+        assert_eq!((migration.from, migration.to), (1, 2));
+        assert_eq!(
+            migration.content,
+            r#"version = 2
+
+[[rule]]
+name = "PDFs"
+[rule.action]
+type = "move"
+destination = "~/Documents"
+on_conflict = "overwrite"
+
+[[rule]]
+name = "Backups"
+action = { type = "copy", destination = "/backup" }
+
+[[watch]]
+path = "~/Downloads"
+unmatched_action = { type = "trash" }
+"#
+        );
+        let keys: Vec<_> = migration.changes.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "rule[0].action.on_conflict",
+                "rule[1].action.on_conflict",
+                "version"
+            ]
+        );
+        let config: crate::config::Config = toml::from_str(&migration.content).unwrap();
+        assert!(matches!(
+            config.rules[0].action,
+            crate::rules::Action::Move {
+                on_conflict: crate::rules::OnConflict::Overwrite,
+                ..
+            }
+        ));
+    }
+}
@@ -1,8 +1,20 @@
 //! Configuration management
 
+mod diagnostics;
+mod migrate;
+mod reload;
 mod schema;
+mod sync;
+mod variables;
 
-pub use schema::{Config, WatchConfig};
+pub use diagnostics::{Diagnostic, Diagnostics, Severity, diagnose};
+pub use reload::ConfigReloader;
+pub use sync::{SyncReport, sync};
+
+pub use schema::{
+    ColorMode, Config, CustomTheme, GeneralConfig, KeyList, LogFormat, ProfileConfig, ReportConfig,
+    ShadowConfig, SymlinkMode, ThemeConfig, WatchConfig, WatchMode,
+};
 
 use anyhow::{Context, Result};
 use std::path::{Path, PathBuf};
@@ -16,19 +28,100 @@ impl Config {
             .context("Could not determine config path")?;
 
         if config_path.exists() {
-            let content = std::fs::read_to_string(&config_path)
-                .with_context(|| format!("Failed to read config from {}", config_path.display()))?;
-
-            let config: Config = toml::from_str(&content).with_context(|| {
-                format!("Failed to parse config from {}", config_path.display())
-            })?;
-
-            Ok(config)
+            let content = read_upgraded(&config_path)?;
+            Self::parse(&content, &config_path)
         } else {
             Ok(Self::default())
         }
     }
 
+    /// Parse the contents of a config file (`path` is only used in errors).
+    /// An older format is upgraded in memory.
+    pub fn parse(content: &str, path: &Path) -> Result<Self> {
+        let migration = migrate::migrate(content)
+            .with_context(|| format!("Failed to parse config from {}", path.display()))?;
+        let content = migration.as_ref().map_or(content, |m| m.content.as_str());
+        let config = toml::from_str(content)
+            .with_context(|| format!("Failed to parse config from {}", path.display()))?;
+        variables::resolve(config)
+            .with_context(|| format!("Failed to parse config from {}", path.display()))
+    }
+
+    /// Check for mistakes the parser doesn't catch, such as invalid patterns
+    pub fn validate(&self) -> Result<()> {
+        for rule in &self.rules {
+            rule.condition
+                .validate()
+                .with_context(|| format!("Rule '{}'", rule.name))?;
+            rule.action
+                .validate()
+                .with_context(|| format!("Rule '{}'", rule.name))?;
+        }
+        for watch in &self.watches {
+            crate::quota::validate(watch)?;
+        }
+        for policy in &self.retention {
+            policy.validate()?;
+        }
+        self.notifications.validate()?;
+        self.api.validate()?;
+        Ok(())
+    }
+
+    /// The config as a profile sees it: watches the profile doesn't list are
+    /// dropped and rules it doesn't list are disabled. Fails if the profile
+    /// doesn't exist or lists a watch or rule the config doesn't have.
+    pub fn with_profile(&self, name: &str) -> Result<Self> {
+        let Some(profile) = self.profiles.get(name) else {
+            let names: Vec<_> = self.profiles.keys().map(String::as_str).collect();
+            if names.is_empty() {
+                anyhow::bail!("Unknown profile '{}' (no [profile.*] sections)", name);
+            }
+            anyhow::bail!(
+                "Unknown profile '{}' (profiles: {})",
+                name,
+                names.join(", ")
+            );
+        };
+
+        let mut config = self.clone();
+        if !profile.watches.is_empty() {
+            use crate::watcher::watch_key;
+            let keys: Vec<_> = profile.watches.iter().map(|p| watch_key(p)).collect();
+            for (path, key) in profile.watches.iter().zip(&keys) {
+                if !self.watches.iter().any(|w| &watch_key(&w.path) == key) {
+                    anyhow::bail!(
+                        "Profile '{}' lists watch {}, but it isn't watched",
+                        name,
+                        path.display()
+                    );
+                }
+            }
+            config
+                .watches
+                .retain(|watch| keys.contains(&watch_key(&watch.path)));
+        }
+        if !profile.rules.is_empty() {
+            if let Some(missing) = profile
+                .rules
+                .iter()
+                .find(|rule| !self.rules.iter().any(|r| &r.name == *rule))
+            {
+                anyhow::bail!(
+                    "Profile '{}' lists rule '{}', but no rule has that name",
+                    name,
+                    missing
+                );
+            }
+            for rule in &mut config.rules {
+                if !profile.rules.contains(&rule.name) {
+                    rule.enabled = false;
+                }
+            }
+        }
+        Ok(config)
+    }
+
     /// Save configuration to a file (with advisory file locking)
     pub fn save(&self, path: Option<&Path>) -> Result<()> {
         let config_path = path
@@ -43,41 +136,177 @@ impl Config {
             })?;
         }
 
-        let content = toml::to_string_pretty(self).context("Failed to serialize config")?;
+        let content = toml::to_string_pretty(&variables::restore(self)?)
+            .context("Failed to serialize config")?;
+        write_locked(&config_path, &content)
+    }
+
+    /// Append rules to the end of a config file, leaving the rest of the file
+    /// (comments included) as it is. The file is created if it doesn't exist.
+    pub fn append_rules(path: Option<&Path>, rules: &[crate::Rule]) -> Result<PathBuf> {
+        let config_path = path
+            .map(PathBuf::from)
+            .or_else(Self::default_path)
+            .context("Could not determine config path")?;
+        let mut content = if config_path.exists() {
+            read_upgraded(&config_path)?
+        } else {
+            String::new()
+        };
+        if !content.is_empty() {
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push('\n');
+        }
+        content.push_str(&Self::rules_toml(rules)?);
+
+        // Don't write a file the daemon would refuse to load
+        Self::parse(&content, &config_path)?.validate()?;
+        if let Some(parent) = config_path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create config directory: {}", parent.display())
+            })?;
+        }
+        write_locked(&config_path, &content)?;
+        Ok(config_path)
+    }
 
-        // Use a lockfile to prevent concurrent writes
-        let lock_path = config_path.with_extension("toml.lock");
-        let lock_file = std::fs::OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(&lock_path)
-            .with_context(|| format!("Failed to create lock file: {}", lock_path.display()))?;
+    /// Enable or disable the rule called `name` in a config file, leaving
+    /// the rest of the file (comments included) as it is
+    pub fn set_rule_enabled(path: Option<&Path>, name: &str, enabled: bool) -> Result<PathBuf> {
+        let config_path = path
+            .map(PathBuf::from)
+            .or_else(Self::default_path)
+            .context("Could not determine config path")?;
+        let content = read_upgraded(&config_path)?;
+        let mut document = content
+            .parse::<toml_edit::DocumentMut>()
+            .with_context(|| format!("Failed to parse config from {}", config_path.display()))?;
+        let rule = document
+            .get_mut("rule")
+            .and_then(|rules| rules.as_array_of_tables_mut())
+            .and_then(|rules| {
+                rules
+                    .iter_mut()
+                    .find(|rule| rule.get("name").and_then(|n| n.as_str()) == Some(name))
+            })
+            .with_context(|| format!("No rule named '{}' in {}", name, config_path.display()))?;
+        rule["enabled"] = toml_edit::value(enabled);
+        let content = document.to_string();
 
-        use fs2::FileExt;
-        lock_file
-            .lock_exclusive()
-            .with_context(|| "Failed to acquire config file lock")?;
+        // Don't write a file the daemon would refuse to load
+        Self::parse(&content, &config_path)?.validate()?;
+        write_locked(&config_path, &content)?;
+        Ok(config_path)
+    }
 
-        let result = std::fs::write(&config_path, content)
-            .with_context(|| format!("Failed to write config to {}", config_path.display()));
+    /// JSON Schema of the config file, for editors (taplo, Even Better TOML)
+    pub fn json_schema() -> serde_json::Value {
+        let mut schema = schemars::schema_for!(Config);
+        // Plugin actions are (de)serialized by hand, so they're added here
+        if let Some(actions) = schema
+            .pointer_mut("/$defs/Action/oneOf")
+            .and_then(|one_of| one_of.as_array_mut())
+        {
+            actions.push(serde_json::json!({ "$ref": "#/$defs/PluginCall" }));
+        }
+        schema.insert("title".to_string(), "Hazelnut configuration".into());
+        schema.to_value()
+    }
 
-        let _ = lock_file.unlock();
-        // Clean up the lock file after successful write
-        let _ = std::fs::remove_file(&lock_path);
+    /// Rules as `[[rule]]` tables, the way `append_rules` writes them
+    pub fn rules_toml(rules: &[crate::Rule]) -> Result<String> {
+        #[derive(serde::Serialize)]
+        struct Rules<'a> {
+            rule: &'a [crate::Rule],
+        }
 
-        result
+        toml::to_string(&Rules { rule: rules }).context("Failed to serialize rules")
     }
 
     /// Get the default config file path
     /// Returns the default config path: `~/.config/hazelnut/config.toml`
+    /// (`<instance>.toml` for a named instance).
     /// Uses the same path on all platforms for consistency.
     pub fn default_path() -> Option<PathBuf> {
-        dirs::home_dir().map(|h| h.join(".config").join("hazelnut").join("config.toml"))
+        let file = match crate::instance::name() {
+            Some(name) => format!("{}.toml", name),
+            None => "config.toml".to_string(),
+        };
+        dirs::home_dir().map(|h| h.join(".config").join("hazelnut").join(file))
     }
 
-    /// Get the default data directory
+    /// Get the default data directory (`instances/<name>/` inside it for a
+    /// named instance)
     pub fn data_dir() -> Option<PathBuf> {
-        dirs::data_dir().map(|d| d.join("hazelnut"))
+        let dir = dirs::data_dir().map(|d| d.join("hazelnut"))?;
+        Some(match crate::instance::name() {
+            Some(name) => dir.join("instances").join(name),
+            None => dir,
+        })
+    }
+}
+
+/// Read a config file, upgrading it first if it's in an older format. The
+/// old file is kept next to it and the changes are printed to stderr.
+fn read_upgraded(path: &Path) -> Result<String> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config from {}", path.display()))?;
+    let Some(migration) = migrate::migrate(&content)
+        .with_context(|| format!("Failed to read config from {}", path.display()))?
+    else {
+        return Ok(content);
+    };
+
+    let backup = migrate::backup_path(path, migration.from);
+    let saved = std::fs::write(&backup, &content)
+        .with_context(|| format!("Failed to back up config to {}", backup.display()))
+        .and_then(|()| write_locked(path, &migration.content));
+    match saved {
+        Ok(()) => eprintln!(
+            "Upgraded {} from config version {} to {} (previous file: {}):",
+            path.display(),
+            migration.from,
+            migration.to,
+            backup.display()
+        ),
+        Err(e) => eprintln!(
+            "Upgraded {} from config version {} to {} for this run only ({:#}):",
+            path.display(),
+            migration.from,
+            migration.to,
+            e
+        ),
+    }
+    for change in &migration.changes {
+        eprintln!("  - {}", change.message);
     }
+    Ok(migration.content)
+}
+
+/// Write a config file while holding its lock file
+fn write_locked(path: &Path, content: &str) -> Result<()> {
+    // Use a lockfile to prevent concurrent writes
+    let lock_path = path.with_extension("toml.lock");
+    let lock_file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to create lock file: {}", lock_path.display()))?;
+
+    use fs2::FileExt;
+    lock_file
+        .lock_exclusive()
+        .with_context(|| "Failed to acquire config file lock")?;
+
+    let result = std::fs::write(path, content)
+        .with_context(|| format!("Failed to write config to {}", path.display()));
+
+    let _ = lock_file.unlock();
+    // Clean up the lock file after successful write
+    let _ = std::fs::remove_file(&lock_path);
+
+    result
 }
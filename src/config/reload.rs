@@ -0,0 +1,138 @@
+//! Picking up edits to the config file
+//!
+//! The daemon polls its config file and reloads it once an edit has settled.
+//! A new config is only handed out if it parses and validates, so a broken
+//! edit leaves the running config in place.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::Config;
+
+/// Size and modification time of the config file
+type Stamp = (u64, Option<SystemTime>);
+
+/// Watches a config file for changes
+#[derive(Debug)]
+pub struct ConfigReloader {
+    path: PathBuf,
+    /// Stamp of the last loaded version
+    loaded: Option<Stamp>,
+    /// Stamp seen at the last check, if it differed from `loaded`
+    changing: Option<Stamp>,
+    /// Contents of the last loaded version, so touching the file doesn't reload
+    content: Option<String>,
+}
+
+impl ConfigReloader {
+    /// Watch `path`, treating its current contents as loaded
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            loaded: stamp(&path),
+            changing: None,
+            content: std::fs::read_to_string(&path).ok(),
+            path,
+        }
+    }
+
+    /// The watched file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read, parse and validate the file now (e.g. on SIGHUP)
+    pub fn load(&mut self) -> Result<Config> {
+        self.loaded = stamp(&self.path);
+        self.changing = None;
+        if !self.path.exists() {
+            self.content = None;
+            return Ok(Config::default());
+        }
+        let content = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read config from {}", self.path.display()))?;
+        self.content = Some(content.clone());
+        let config = Config::parse(&content, &self.path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the file for changes. Returns the new config (or why it can't
+    /// be used) once the contents changed and the file stayed the same for
+    /// one check, so a half-written save isn't loaded.
+    pub fn poll(&mut self) -> Option<Result<Config>> {
+        // A missing file is usually an editor replacing it; wait for the new one
+        let current = stamp(&self.path)?;
+        if Some(current) == self.loaded {
+            self.changing = None;
+            return None;
+        }
+        if self.changing != Some(current) {
+            self.changing = Some(current);
+            return None;
+        }
+        let content = std::fs::read_to_string(&self.path).ok();
+        if content.is_some() && content == self.content {
+            self.loaded = Some(current);
+            self.changing = None;
+            return None;
+        }
+        Some(self.load())
+    }
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_after_edit_settles() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[[watch]]\npath = \"~/Downloads\"\n").unwrap();
+
+        let mut reloader = ConfigReloader::new(&path);
+        assert!(reloader.poll().is_none());
+
+        // An edit is picked up on the second check that sees it
+        std::fs::write(
+            &path,
+            "[[watch]]\npath = \"~/Downloads\"\n\n[[watch]]\npath = \"~/Desktop\"\n",
+        )
+        .unwrap();
+        assert!(reloader.poll().is_none());
+        let config = reloader.poll().unwrap().unwrap();
+        assert_eq!(config.watches.len(), 2);
+        assert!(reloader.poll().is_none());
+
+        // A broken edit is reported once
+        std::fs::write(&path, "[[watch]\n").unwrap();
+        assert!(reloader.poll().is_none());
+        assert!(reloader.poll().unwrap().is_err());
+        assert!(reloader.poll().is_none());
+
+        // So is one that parses but doesn't validate
+        std::fs::write(
+            &path,
+            "[[rule]]\nname = \"Bad\"\n[rule.condition]\nname_regex = \"(\"\n[rule.action]\ntype = \"delete\"\n",
+        )
+        .unwrap();
+        assert!(reloader.poll().is_none());
+        let error = reloader.poll().unwrap().unwrap_err();
+        assert!(format!("{:#}", error).contains("Rule 'Bad'"));
+
+        // Rewriting the same contents doesn't reload
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(reloader.poll().is_none());
+        std::fs::write(&path, content).unwrap();
+        assert!(reloader.poll().is_none());
+        assert!(reloader.poll().is_none());
+    }
+}
@@ -1,16 +1,40 @@
 //! Configuration schema
 
-use crate::rules::Rule;
+use crate::api::ApiConfig;
+use crate::notifications::NotificationsConfig;
+use crate::plugins::PluginConfig;
+use crate::quarantine::QuarantineConfig;
+use crate::quiet_hours::QuietHours;
+use crate::quota::ByteSize;
+use crate::retention::{RetentionAction, RetentionPolicy};
+use crate::rules::{Action, Hook, Rule};
+use crate::schedule::Schedule;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// Main configuration structure
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Config {
+    /// Config format version; files without one are version 1, and older
+    /// files are upgraded when loaded
+    #[serde(default = "default_version")]
+    pub version: u32,
+
     /// General settings
     #[serde(default)]
     pub general: GeneralConfig,
 
+    /// Values referenced as `{var.name}` in any string of the config
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+
+    /// Strings with variables resolved, paired with how they were written
+    /// (for saving the config back)
+    #[serde(skip)]
+    pub(crate) substitutions: Vec<(String, String)>,
+
     /// Watched folders
     #[serde(default, rename = "watch")]
     pub watches: Vec<WatchConfig>,
@@ -18,20 +42,118 @@ pub struct Config {
     /// Organization rules
     #[serde(default, rename = "rule")]
     pub rules: Vec<Rule>,
+
+    /// Commands run when files land in a destination folder
+    #[serde(default, rename = "hook")]
+    pub hooks: Vec<Hook>,
+
+    /// Programs providing conditions and actions, used as `type = "plugin:<name>"`
+    #[serde(default, rename = "plugin", skip_serializing_if = "BTreeMap::is_empty")]
+    pub plugins: BTreeMap<String, PluginConfig>,
+
+    /// Folders cleared of old files on a schedule
+    #[serde(default, rename = "retention", skip_serializing_if = "Vec::is_empty")]
+    pub retention: Vec<RetentionPolicy>,
+
+    /// Unmatched-files report
+    #[serde(default)]
+    pub report: ReportConfig,
+
+    /// Candidate rules evaluated alongside the active ones
+    #[serde(default, skip_serializing_if = "ShadowConfig::is_disabled")]
+    pub shadow: ShadowConfig,
+
+    /// Daily window without notifications (and without deferred rules)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
+
+    /// Where notifications go: the desktop, Slack, Telegram, email, webhooks
+    #[serde(default, skip_serializing_if = "NotificationsConfig::is_default")]
+    pub notifications: NotificationsConfig,
+
+    /// Local HTTP API served by the daemon
+    #[serde(default, skip_serializing_if = "ApiConfig::is_default")]
+    pub api: ApiConfig,
+
+    /// Holding area for files that delete and overwrite actions remove
+    #[serde(default, skip_serializing_if = "QuarantineConfig::is_default")]
+    pub quarantine: QuarantineConfig,
+
+    /// TUI colors: a custom theme and the terminal's color support
+    #[serde(default, skip_serializing_if = "ThemeConfig::is_default")]
+    pub theme: ThemeConfig,
+
+    /// TUI keys per action, replacing the action's default keys
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keybindings: BTreeMap<String, KeyList>,
+
+    /// Named subsets of the watches and rules, chosen when the daemon starts
+    /// (`--profile`) or switched over IPC
+    #[serde(
+        default,
+        rename = "profile",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: default_version(),
+            general: GeneralConfig::default(),
+            variables: BTreeMap::new(),
+            substitutions: Vec::new(),
+            watches: Vec::new(),
+            rules: Vec::new(),
+            hooks: Vec::new(),
+            plugins: BTreeMap::new(),
+            retention: Vec::new(),
+            report: ReportConfig::default(),
+            api: ApiConfig::default(),
+            quarantine: QuarantineConfig::default(),
+            shadow: ShadowConfig::default(),
+            quiet_hours: None,
+            notifications: NotificationsConfig::default(),
+            theme: ThemeConfig::default(),
+            keybindings: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_version() -> u32 {
+    super::migrate::CURRENT_VERSION
 }
 
 /// General application settings
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GeneralConfig {
     /// Log level (trace, debug, info, warn, error)
     #[serde(default = "default_log_level")]
     pub log_level: String,
 
-    /// Path to log file
+    /// Path to the daemon's log file (default: ~/.local/state/hazelnut/hazelnutd.log)
     #[serde(default)]
     pub log_file: Option<PathBuf>,
 
-    /// Seconds to wait before processing a file (debounce)
+    /// Rotate the log file at this size in MB (0 = no limit)
+    #[serde(default = "default_log_max_size_mb")]
+    pub log_max_size_mb: u64,
+
+    /// Rotate the log file after this many days (0 = no limit)
+    #[serde(default = "default_log_max_age_days")]
+    pub log_max_age_days: u32,
+
+    /// Rotated log files to keep
+    #[serde(default = "default_log_max_files")]
+    pub log_max_files: usize,
+
+    /// Format of the daemon's log file
+    #[serde(default)]
+    pub log_format: LogFormat,
+
+    /// Seconds a file must go without change events before it is processed
     #[serde(default = "default_debounce")]
     pub debounce_seconds: u64,
 
@@ -47,13 +169,74 @@ pub struct GeneralConfig {
     #[serde(default)]
     pub start_daemon_on_launch: bool,
 
+    /// Only log and notify what rules would do, without touching any file
+    #[serde(default)]
+    pub dry_run: bool,
+
     /// Enable desktop notifications for errors
     #[serde(default)]
     pub notifications_enabled: bool,
 
-    /// Theme name
+    /// Which notifications to send (`all`, `errors` or `off`); takes
+    /// precedence over `notifications_enabled`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification_level: Option<crate::notifications::NotificationLevel>,
+
+    /// Theme name (`custom` for the `[theme.custom]` colors)
     #[serde(default)]
     pub theme: Option<String>,
+
+    /// Seconds a new file's size must stay unchanged before rules run (0 = immediately)
+    #[serde(default = "default_stable_seconds")]
+    pub stable_seconds: u64,
+
+    /// Extensions of in-progress downloads, which are never processed
+    #[serde(default = "default_temp_extensions")]
+    pub temp_extensions: Vec<String>,
+
+    /// Maximum files processed per second during a burst (0 = unlimited)
+    #[serde(default = "default_max_files_per_second")]
+    pub max_files_per_second: u32,
+
+    /// Maximum files waiting to be processed; further files wait for the next scan
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+
+    /// Bursts of at least this many files get one summary notification
+    #[serde(default = "default_batch_summary_threshold")]
+    pub batch_summary_threshold: usize,
+
+    /// Files whose actions run at the same time (1 = one after another)
+    #[serde(default = "default_action_workers")]
+    pub action_workers: usize,
+
+    /// Days of hourly statistics to keep (0 = don't collect)
+    #[serde(default = "default_stats_retention_days")]
+    pub stats_retention_days: u32,
+
+    /// Days of action history to keep (0 = keep everything)
+    #[serde(default = "default_history_retention_days")]
+    pub history_retention_days: u32,
+
+    /// Record a checksum of each file in the action history
+    #[serde(default = "default_history_checksums")]
+    pub history_checksums: bool,
+
+    /// Hash for file fingerprints (blake3 or sha256)
+    #[serde(default)]
+    pub hash_algorithm: crate::hashing::HashAlgorithm,
+
+    /// Threads for hashing large files (0 = all cores)
+    #[serde(default)]
+    pub hash_threads: usize,
+
+    /// Compare checksums after a move across filesystems, before the original is removed
+    #[serde(default)]
+    pub verify_moves: bool,
+
+    /// Bytes per second copies may use, e.g. "20MB" (unlimited if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_transfer_rate: Option<ByteSize>,
 }
 
 impl Default for GeneralConfig {
@@ -61,20 +244,72 @@ impl Default for GeneralConfig {
         Self {
             log_level: default_log_level(),
             log_file: None,
+            log_max_size_mb: default_log_max_size_mb(),
+            log_max_age_days: default_log_max_age_days(),
+            log_max_files: default_log_max_files(),
+            log_format: LogFormat::default(),
             debounce_seconds: default_debounce(),
             polling_interval_secs: default_polling_interval(),
             log_retention: default_log_retention(),
             start_daemon_on_launch: false,
+            dry_run: false,
             notifications_enabled: false,
+            notification_level: None,
             theme: None,
+            stable_seconds: default_stable_seconds(),
+            temp_extensions: default_temp_extensions(),
+            max_files_per_second: default_max_files_per_second(),
+            queue_capacity: default_queue_capacity(),
+            batch_summary_threshold: default_batch_summary_threshold(),
+            action_workers: default_action_workers(),
+            stats_retention_days: default_stats_retention_days(),
+            history_retention_days: default_history_retention_days(),
+            history_checksums: true,
+            hash_algorithm: Default::default(),
+            hash_threads: 0,
+            verify_moves: false,
+            max_transfer_rate: None,
         }
     }
 }
 
+impl GeneralConfig {
+    /// The notifications to send: `notification_level`, or else
+    /// `notifications_enabled` as `all` or `off`
+    pub fn notification_level(&self) -> crate::notifications::NotificationLevel {
+        use crate::notifications::NotificationLevel;
+
+        self.notification_level
+            .unwrap_or(if self.notifications_enabled {
+                NotificationLevel::All
+            } else {
+                NotificationLevel::Off
+            })
+    }
+
+    /// Set the level, keeping `notifications_enabled` in line with it
+    pub fn set_notification_level(&mut self, level: crate::notifications::NotificationLevel) {
+        self.notification_level = Some(level);
+        self.notifications_enabled = level != crate::notifications::NotificationLevel::Off;
+    }
+}
+
 fn default_log_level() -> String {
     "info".to_string()
 }
 
+fn default_log_max_size_mb() -> u64 {
+    10
+}
+
+fn default_log_max_age_days() -> u32 {
+    7
+}
+
+fn default_log_max_files() -> usize {
+    5
+}
+
 fn default_debounce() -> u64 {
     2
 }
@@ -87,8 +322,240 @@ fn default_log_retention() -> usize {
     1000
 }
 
+fn default_stable_seconds() -> u64 {
+    2
+}
+
+fn default_temp_extensions() -> Vec<String> {
+    crate::watcher::DEFAULT_TEMP_EXTENSIONS
+        .iter()
+        .map(|e| e.to_string())
+        .collect()
+}
+
+fn default_max_files_per_second() -> u32 {
+    100
+}
+
+fn default_queue_capacity() -> usize {
+    100_000
+}
+
+fn default_batch_summary_threshold() -> usize {
+    20
+}
+
+fn default_action_workers() -> usize {
+    4
+}
+
+fn default_stats_retention_days() -> u32 {
+    90
+}
+
+fn default_history_retention_days() -> u32 {
+    90
+}
+
+fn default_history_checksums() -> bool {
+    true
+}
+
+/// Periodic report of files no rule matched
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReportConfig {
+    /// Cron expression for when the daemon sends the report (unset = never)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
+
+    /// Only report files at least this many days old
+    #[serde(default = "default_report_older_than_days")]
+    pub older_than_days: u64,
+
+    /// Maximum number of file names listed per folder
+    #[serde(default = "default_report_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            schedule: None,
+            older_than_days: default_report_older_than_days(),
+            max_files: default_report_max_files(),
+        }
+    }
+}
+
+fn default_report_older_than_days() -> u64 {
+    7
+}
+
+fn default_report_max_files() -> usize {
+    5
+}
+
+/// Shadow evaluation of a candidate rule set
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ShadowConfig {
+    /// Config file whose `[[rule]]` entries are compared against the active rules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules_file: Option<PathBuf>,
+}
+
+impl ShadowConfig {
+    fn is_disabled(&self) -> bool {
+        self.rules_file.is_none()
+    }
+}
+
+/// Watches and rules active under a profile
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ProfileConfig {
+    /// Watched folders to keep (empty = all)
+    #[serde(default)]
+    pub watches: Vec<PathBuf>,
+
+    /// Rules to keep enabled, by name (empty = all)
+    #[serde(default)]
+    pub rules: Vec<String>,
+}
+
+/// Keys bound to a TUI action: one key or a list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            Self::One(key) => std::slice::from_ref(key),
+            Self::Many(keys) => keys,
+        }
+    }
+}
+
+/// How the daemon writes its log file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Plain lines for reading
+    #[default]
+    Text,
+    /// One JSON object per line, with an event per action (rule, path,
+    /// action, outcome, duration) for `hazelnut logs` and log collectors
+    Json,
+}
+
+/// TUI color settings (`[theme]`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ThemeConfig {
+    /// Colors the terminal can show
+    #[serde(default)]
+    pub color_mode: ColorMode,
+
+    /// Colors of the `custom` theme
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom: Option<CustomTheme>,
+}
+
+impl ThemeConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Truecolor if `$COLORTERM` says so, otherwise 256 colors
+    #[default]
+    Auto,
+    /// 24-bit colors
+    Truecolor,
+    /// The 256-color palette; theme colors are approximated
+    #[serde(rename = "256")]
+    Ansi256,
+}
+
+/// A user-defined theme (`[theme.custom]`), selected with `theme = "custom"`.
+/// Colors are `"#rrggbb"`, a color name like `"lightblue"` or a 256-color
+/// index like `"208"`; unset ones come from the `base` theme.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CustomTheme {
+    /// Built-in theme the unset colors come from (default: the default theme)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<String>,
+
+    /// Dimmed text and borders
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub muted: Option<String>,
+
+    /// Titles, focused borders and the active tab
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent: Option<String>,
+
+    /// Key hints and values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary: Option<String>,
+
+    /// Background of the selected item
+    #[serde(default, alias = "highlight", skip_serializing_if = "Option::is_none")]
+    pub selection: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub success: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+}
+
+/// How a watched folder detects changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    /// Native events, or polling when the folder is on a network filesystem
+    #[default]
+    Auto,
+    /// Native filesystem events (inotify, FSEvents, ReadDirectoryChangesW)
+    Events,
+    /// Periodic snapshots of modification time and size
+    Poll,
+}
+
+/// How a watch treats symbolic links
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkMode {
+    /// Skip symlinks and anything reached through them
+    #[default]
+    Ignore,
+    /// Process linked files and descend into linked folders, visiting each
+    /// real folder once
+    Follow,
+    /// Hand symlinks to rules as entries of their own, without descending
+    /// into linked folders
+    Link,
+}
+
 /// Configuration for a watched folder
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 pub struct WatchConfig {
     /// Path to watch
     pub path: PathBuf,
@@ -100,6 +567,73 @@ pub struct WatchConfig {
     /// Only apply rules with these names (empty = all rules)
     #[serde(default)]
     pub rules: Vec<String>,
+
+    /// Change detection backend
+    #[serde(default, skip_serializing_if = "is_default_mode")]
+    pub mode: WatchMode,
+
+    /// Seconds between snapshots in poll mode (default: general.polling_interval_secs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_interval_secs: Option<u64>,
+
+    /// Process files already in the folder when watching starts
+    #[serde(default)]
+    pub scan_on_start: bool,
+
+    /// Cron expression for periodic rescans (e.g. "0 3 * * *"), run by the daemon
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
+
+    /// Subdirectory names (or glob patterns) skipped by recursive watches
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_dirs: Vec<String>,
+
+    /// How many levels of subdirectories recursive watches descend (default: unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+
+    /// Action for files no rule matches (e.g. move to `_Unsorted`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unmatched_action: Option<Action>,
+
+    /// How symbolic links inside the folder are handled
+    #[serde(default, skip_serializing_if = "is_default_symlinks")]
+    pub symlinks: SymlinkMode,
+
+    /// Remove subfolders left empty after rules moved their files out
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub remove_empty_dirs: bool,
+
+    /// Size the daemon keeps the folder under by removing its oldest files (e.g. "20GB")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<ByteSize>,
+
+    /// What happens to files removed to stay under `max_size`
+    #[serde(default, skip_serializing_if = "is_default_quota_action")]
+    pub quota_action: RetentionAction,
+
+    /// Folder the archives go in (required for `quota_action = "archive"`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota_archive_to: Option<PathBuf>,
+}
+
+impl WatchConfig {
+    /// Whether the watch applies a rule: it lists it, or lists none
+    pub fn runs_rule(&self, name: &str) -> bool {
+        self.rules.is_empty() || self.rules.iter().any(|n| n == name)
+    }
+}
+
+fn is_default_mode(mode: &WatchMode) -> bool {
+    *mode == WatchMode::Auto
+}
+
+fn is_default_symlinks(mode: &SymlinkMode) -> bool {
+    *mode == SymlinkMode::Ignore
+}
+
+fn is_default_quota_action(action: &RetentionAction) -> bool {
+    *action == RetentionAction::Trash
 }
 
 #[cfg(test)]
@@ -117,6 +651,17 @@ mod tests {
         assert_eq!(config.watches.len(), 1);
         assert_eq!(config.watches[0].path.to_string_lossy(), "~/Downloads");
         assert!(!config.watches[0].recursive);
+        assert!(!config.watches[0].scan_on_start);
+        assert_eq!(config.general.stable_seconds, 2);
+        assert!(
+            config
+                .general
+                .temp_extensions
+                .contains(&"crdownload".to_string())
+        );
+        assert_eq!(config.general.max_files_per_second, 100);
+        assert_eq!(config.watches[0].mode, WatchMode::Auto);
+        assert_eq!(config.watches[0].symlinks, SymlinkMode::Ignore);
     }
 
     #[test]
@@ -129,6 +674,10 @@ mod tests {
             [[watch]]
             path = "~/Downloads"
             recursive = true
+            scan_on_start = true
+            exclude_dirs = ["node_modules", ".cache"]
+            max_depth = 2
+            symlinks = "follow"
             rules = ["pdfs", "images"]
 
             [[rule]]
@@ -148,7 +697,254 @@ mod tests {
         assert_eq!(config.general.debounce_seconds, 5);
         assert_eq!(config.watches.len(), 1);
         assert!(config.watches[0].recursive);
+        assert!(config.watches[0].scan_on_start);
+        assert_eq!(
+            config.watches[0].exclude_dirs,
+            vec!["node_modules", ".cache"]
+        );
+        assert_eq!(config.watches[0].max_depth, Some(2));
+        assert_eq!(config.watches[0].symlinks, SymlinkMode::Follow);
         assert_eq!(config.rules.len(), 1);
         assert_eq!(config.rules[0].name, "pdfs");
     }
+
+    #[test]
+    fn test_parse_poll_mode() {
+        let toml = r#"
+            [[watch]]
+            path = "/mnt/nas/inbox"
+            mode = "poll"
+            poll_interval_secs = 30
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.watches[0].mode, WatchMode::Poll);
+        assert_eq!(config.watches[0].poll_interval_secs, Some(30));
+    }
+
+    #[test]
+    fn test_parse_unmatched_action() {
+        let toml = r#"
+            [[watch]]
+            path = "~/Downloads"
+
+            [watch.unmatched_action]
+            type = "move"
+            destination = "~/Downloads/_Unsorted"
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        assert!(matches!(
+            config.watches[0].unmatched_action,
+            Some(Action::Move { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_hooks() {
+        let toml = r#"
+            [[hook]]
+            destination = "~/Documents/Paperless"
+            command = "paperless-consume"
+            args = ["{path}"]
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.hooks.len(), 1);
+        assert_eq!(config.hooks[0].command, "paperless-consume");
+        assert!(!config.hooks[0].recursive);
+    }
+
+    #[test]
+    fn test_parse_report_config() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(config.report.schedule.is_none());
+        assert_eq!(config.report.older_than_days, 7);
+
+        let toml = r#"
+            [report]
+            schedule = "0 9 * * mon"
+            older_than_days = 14
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert!(config.report.schedule.is_some());
+        assert_eq!(config.report.older_than_days, 14);
+        assert_eq!(config.report.max_files, 5);
+    }
+
+    #[test]
+    fn test_notification_level() {
+        use crate::notifications::NotificationLevel;
+
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.general.notification_level(), NotificationLevel::Off);
+        let config: Config = toml::from_str("[general]\nnotifications_enabled = true").unwrap();
+        assert_eq!(config.general.notification_level(), NotificationLevel::All);
+
+        let toml = r#"
+            [general]
+            notifications_enabled = true
+            notification_level = "errors"
+        "#;
+        let mut config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.general.notification_level(),
+            NotificationLevel::Errors
+        );
+        config
+            .general
+            .set_notification_level(NotificationLevel::Off);
+        assert!(!config.general.notifications_enabled);
+    }
+
+    #[test]
+    fn test_parse_shadow_config() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(config.shadow.rules_file.is_none());
+        assert!(!toml::to_string(&config).unwrap().contains("[shadow]"));
+
+        let toml = r#"
+            [shadow]
+            rules_file = "~/.config/hazelnut/candidate.toml"
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.shadow.rules_file,
+            Some(PathBuf::from("~/.config/hazelnut/candidate.toml"))
+        );
+    }
+
+    #[test]
+    fn test_parse_watch_schedule() {
+        let toml = r#"
+            [[watch]]
+            path = "~/Downloads"
+            schedule = "0 3 * * *"
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.watches[0].schedule.as_ref().map(|s| s.as_str()),
+            Some("0 3 * * *")
+        );
+
+        let invalid = r#"
+            [[watch]]
+            path = "~/Downloads"
+            schedule = "every night"
+        "#;
+        assert!(toml::from_str::<Config>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_profiles() {
+        let toml = r#"
+            [[watch]]
+            path = "/nonexistent/inbox"
+            [[watch]]
+            path = "/nonexistent/work"
+
+            [[rule]]
+            name = "PDFs"
+            [rule.condition]
+            extension = "pdf"
+            [rule.action]
+            type = "trash"
+
+            [[rule]]
+            name = "Images"
+            [rule.action]
+            type = "trash"
+
+            [profile.work]
+            watches = ["/nonexistent/work"]
+            rules = ["PDFs"]
+
+            [profile.home]
+            rules = ["Images", "Typo"]
+        "#;
+
+        let config: Config = toml::from_str(toml).unwrap();
+        let work = config.with_profile("work").unwrap();
+        assert_eq!(work.watches.len(), 1);
+        assert_eq!(work.watches[0].path, PathBuf::from("/nonexistent/work"));
+        let enabled: Vec<_> = work.rules.iter().map(|r| r.enabled).collect();
+        assert_eq!(enabled, vec![true, false]);
+
+        let error = config.with_profile("home").unwrap_err().to_string();
+        assert!(error.contains("'Typo'"), "{}", error);
+        let error = config.with_profile("travel").unwrap_err().to_string();
+        assert!(error.contains("profiles: home, work"), "{}", error);
+    }
+
+    #[test]
+    fn test_set_rule_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let toml = r#"# My rules
+[[watch]]
+path = "/nonexistent/inbox"
+rules = ["PDFs"]
+
+[[rule]]
+name = "PDFs"  # keep this comment
+[rule.action]
+type = "trash"
+
+[[rule]]
+name = "Images"
+enabled = false
+[rule.action]
+type = "trash"
+"#;
+        std::fs::write(&path, toml).unwrap();
+
+        Config::set_rule_enabled(Some(&path), "PDFs", false).unwrap();
+        Config::set_rule_enabled(Some(&path), "Images", true).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("# keep this comment"));
+        let config = Config::load(Some(&path)).unwrap();
+        let enabled: Vec<_> = config.rules.iter().map(|r| r.enabled).collect();
+        assert_eq!(enabled, vec![false, true]);
+        assert!(Config::set_rule_enabled(Some(&path), "Typo", true).is_err());
+
+        assert!(config.watches[0].runs_rule("PDFs"));
+        assert!(!config.watches[0].runs_rule("Images"));
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = Config::json_schema();
+        let defs = &schema["$defs"];
+        let actions: Vec<_> = defs["Action"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| {
+                v["properties"]["type"]["const"]
+                    .as_str()
+                    .or(v["$ref"].as_str())
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                "move",
+                "copy",
+                "rename",
+                "trash",
+                "delete",
+                "run",
+                "archive",
+                "tier",
+                "script",
+                "nothing",
+                "#/$defs/PluginCall"
+            ]
+        );
+        assert!(defs["Condition"]["properties"]["name_regex"].is_object());
+        assert_eq!(schema["properties"]["rule"]["type"], "array");
+        assert_eq!(defs["Schedule"]["type"], "string");
+    }
 }
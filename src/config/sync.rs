@@ -0,0 +1,448 @@
+//! Config sync - the config directory as a git repository
+//!
+//! `hazelnut config sync` commits edits to the files in the config directory
+//! with a message naming what changed ("Add rule 'PDFs', change watch
+//! ~/Downloads"), then rebases onto and pushes to the `origin` remote, if
+//! one is set. Runs the system `git`.
+
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+use std::process::Command;
+
+/// Branch used for repositories created by sync, so machines agree on it
+const BRANCH: &str = "main";
+
+/// Kept out of the repository: lock files and migration backups
+const GITIGNORE: &str = "*.lock\n*.bak\n";
+
+/// Changes listed in a commit's subject line before "and N more"
+const SUBJECT_CHANGES: usize = 3;
+
+/// Settings that hold secrets, which sync won't commit unless they refer to
+/// an environment variable
+const SECRETS: &[&[&str]] = &[
+    &["api", "token"],
+    &["notifications", "email", "password"],
+    &["notifications", "telegram", "bot_token"],
+];
+
+/// What a sync did
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// The directory wasn't a repository yet
+    pub initialized: bool,
+    /// Message of the commit made for local edits
+    pub committed: Option<String>,
+    /// Commits came in from the remote
+    pub pulled: bool,
+    /// Pushed to the remote
+    pub pushed: bool,
+}
+
+/// Commit the edits in the config file's directory and, unless `local`,
+/// exchange commits with `origin` (set to `remote` if given)
+pub fn sync(config_path: &Path, remote: Option<&str>, local: bool) -> Result<SyncReport> {
+    let dir = config_path
+        .parent()
+        .context("The config file has no directory")?;
+    let mut report = SyncReport::default();
+
+    if let Ok(content) = std::fs::read_to_string(config_path) {
+        let secrets = literal_secrets(&content);
+        if !secrets.is_empty() {
+            bail!(
+                "{} in {} would be committed to git; set {} to an environment variable instead, e.g. \"$HAZELNUT_SECRET\"",
+                secrets.join(" and "),
+                config_path.display(),
+                if secrets.len() == 1 { "it" } else { "them" }
+            );
+        }
+    }
+
+    if !dir.join(".git").exists() {
+        git(dir, &["init", "-q", "-b", BRANCH])?;
+        let gitignore = dir.join(".gitignore");
+        if !gitignore.exists() {
+            std::fs::write(&gitignore, GITIGNORE)
+                .with_context(|| format!("Failed to write {}", gitignore.display()))?;
+        }
+        report.initialized = true;
+    }
+    if let Some(url) = remote {
+        let verb = if git(dir, &["remote", "get-url", "origin"]).is_ok() {
+            "set-url"
+        } else {
+            "add"
+        };
+        git(dir, &["remote", verb, "origin", url])?;
+    }
+    let has_remote = !local && git(dir, &["remote", "get-url", "origin"]).is_ok();
+
+    let branch = git(dir, &["symbolic-ref", "--short", "HEAD"])?;
+    let upstream = format!("origin/{}", branch);
+    let mut remote_branch = false;
+    if has_remote {
+        git(dir, &["fetch", "-q", "origin"])?;
+        remote_branch = git(dir, &["rev-parse", "--verify", "-q", &upstream]).is_ok();
+        // A new repository joins the remote's history, so the local files
+        // are committed as an edit of the shared config
+        if remote_branch && git(dir, &["rev-parse", "--verify", "-q", "HEAD"]).is_err() {
+            git(dir, &["reset", "-q", &upstream])?;
+            report.pulled = true;
+        }
+    }
+
+    git(dir, &["add", "-A"])?;
+    if git(dir, &["diff", "--cached", "--quiet"]).is_err() {
+        let message = commit_message(dir)?;
+        git_committing(dir, &["commit", "-q", "-m", &message])?;
+        report.committed = Some(message);
+    }
+
+    if remote_branch {
+        let incoming = git(
+            dir,
+            &["rev-list", "--count", &format!("HEAD..{}", upstream)],
+        )?;
+        if incoming != "0" {
+            report.pulled = true;
+            if let Err(e) = git_committing(dir, &["rebase", "-q", &upstream]) {
+                let _ = git(dir, &["rebase", "--abort"]);
+                bail!(
+                    "{:#}\nThe local and remote configs changed the same lines; merge them in {} with git, then sync again",
+                    e,
+                    dir.display()
+                );
+            }
+        }
+    }
+    if has_remote {
+        let ahead = !remote_branch
+            || git(
+                dir,
+                &["rev-list", "--count", &format!("{}..HEAD", upstream)],
+            )? != "0";
+        if ahead {
+            git(dir, &["push", "-q", "-u", "origin", &branch])?;
+            report.pushed = true;
+        }
+    }
+    Ok(report)
+}
+
+/// The `SECRETS` a config file sets to something other than a single
+/// `$NAME` or `${NAME}` reference
+fn literal_secrets(content: &str) -> Vec<String> {
+    let Ok(table) = content.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+    SECRETS
+        .iter()
+        .filter(|path| {
+            let mut value = table.get(path[0]);
+            for key in &path[1..] {
+                value = value.and_then(|v| v.get(key));
+            }
+            value
+                .and_then(toml::Value::as_str)
+                .is_some_and(|secret| !secret.trim().is_empty() && !is_reference(secret.trim()))
+        })
+        .map(|path| path.join("."))
+        .collect()
+}
+
+fn is_reference(value: &str) -> bool {
+    let name = match value.strip_prefix("${") {
+        Some(rest) => rest.strip_suffix('}'),
+        None => value.strip_prefix('$'),
+    };
+    name.is_some_and(|name| {
+        !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Run git in a directory, returning its trimmed output
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    run_git(dir, &[], args)
+}
+
+/// Run a git command that makes commits, with a fallback identity on
+/// machines where git has none
+fn git_committing(dir: &Path, args: &[&str]) -> Result<String> {
+    let has_identity = git(dir, &["config", "user.email"]).is_ok_and(|email| !email.is_empty());
+    let identity: &[&str] = if has_identity {
+        &[]
+    } else {
+        &[
+            "-c",
+            "user.name=hazelnut",
+            "-c",
+            "user.email=hazelnut@localhost",
+        ]
+    };
+    run_git(dir, identity, args)
+}
+
+fn run_git(dir: &Path, options: &[&str], args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(options)
+        .args(args)
+        .output()
+        .context("Failed to run git (is it installed?)")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// A message listing the staged changes: a subject with the first few, and
+/// all of them in the body when there are more
+fn commit_message(dir: &Path) -> Result<String> {
+    let has_head = git(dir, &["rev-parse", "--verify", "-q", "HEAD"]).is_ok();
+    let staged = git(dir, &["diff", "--cached", "--name-status", "--no-renames"])?;
+    let mut changes = Vec::new();
+    for line in staged.lines() {
+        let Some((status, file)) = line.split_once('\t') else {
+            continue;
+        };
+        let old = has_head
+            .then(|| git(dir, &["show", &format!("HEAD:{}", file)]).ok())
+            .flatten();
+        let new = std::fs::read_to_string(dir.join(file)).ok();
+        let described = match (status, old, new) {
+            ("M", Some(old), Some(new)) if file.ends_with(".toml") => describe_edit(&old, &new)
+                .map(|edits| {
+                    edits
+                        .into_iter()
+                        .map(|edit| format!("{}: {}", file, edit))
+                        .collect::<Vec<_>>()
+                }),
+            _ => None,
+        };
+        match described {
+            Some(edits) if !edits.is_empty() => changes.extend(edits),
+            _ => changes.push(match status {
+                "A" => format!("add {}", file),
+                "D" => format!("delete {}", file),
+                _ => format!("update {}", file),
+            }),
+        }
+    }
+    // Most syncs only touch config.toml, which needn't be named
+    let only_file = staged.lines().count() == 1;
+    if only_file {
+        for change in &mut changes {
+            if let Some((_, edit)) = change.split_once(".toml: ") {
+                *change = edit.to_string();
+            }
+        }
+    }
+
+    let mut subject = capitalize(
+        &changes
+            .iter()
+            .take(SUBJECT_CHANGES)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    if changes.len() > SUBJECT_CHANGES {
+        subject.push_str(&format!(" and {} more", changes.len() - SUBJECT_CHANGES));
+        let body: Vec<_> = changes
+            .iter()
+            .map(|c| format!("- {}", capitalize(c)))
+            .collect();
+        return Ok(format!("{}\n\n{}", subject, body.join("\n")));
+    }
+    Ok(subject)
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The edits between two versions of a config file: rules by name, watches
+/// by path, and other sections by key. None if either doesn't parse.
+fn describe_edit(old: &str, new: &str) -> Option<Vec<String>> {
+    let old: toml::Table = toml::from_str(old).ok()?;
+    let new: toml::Table = toml::from_str(new).ok()?;
+    let mut edits = Vec::new();
+    for (key, noun, id) in [("rule", "rule", "name"), ("watch", "watch", "path")] {
+        let entries = |table: &toml::Table| -> Vec<(String, toml::Value)> {
+            let Some(toml::Value::Array(items)) = table.get(key) else {
+                return Vec::new();
+            };
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let label = match item.get(id).and_then(toml::Value::as_str) {
+                        Some(label) if key == "rule" => format!("'{}'", label),
+                        Some(label) => label.to_string(),
+                        None => format!("#{}", i + 1),
+                    };
+                    (label, item.clone())
+                })
+                .collect()
+        };
+        let (before, after) = (entries(&old), entries(&new));
+        for (label, item) in &after {
+            match before.iter().find(|(old_label, _)| old_label == label) {
+                None => edits.push(format!("add {} {}", noun, label)),
+                Some((_, old_item)) if old_item != item => {
+                    edits.push(format!("change {} {}", noun, label))
+                }
+                _ => {}
+            }
+        }
+        for (label, _) in &before {
+            if !after.iter().any(|(new_label, _)| new_label == label) {
+                edits.push(format!("remove {} {}", noun, label));
+            }
+        }
+    }
+    let sections = old
+        .keys()
+        .chain(new.keys().filter(|k| !old.contains_key(*k)));
+    for key in sections.filter(|k| *k != "rule" && *k != "watch") {
+        match (old.get(key), new.get(key)) {
+            (None, Some(_)) => edits.push(format!("add [{}]", key)),
+            (Some(_), None) => edits.push(format!("remove [{}]", key)),
+            (Some(a), Some(b)) if a != b => edits.push(format!("change [{}]", key)),
+            _ => {}
+        }
+    }
+    Some(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_edit() {
+        let old = r#"
+            [general]
+            log_level = "info"
+
+            [[watch]]
+            path = "~/Downloads"
+
+            [[rule]]
+            name = "PDFs"
+            [rule.condition]
+            extension = "pdf"
+            [rule.action]
+            type = "trash"
+
+            [[rule]]
+            name = "Old"
+            [rule.action]
+            type = "delete"
+        "#;
+        let new = r#"
+            [general]
+            log_level = "debug"
+
+            [[watch]]
+            path = "~/Downloads"
+
+            [[watch]]
+            path = "~/Desktop"
+
+            [[rule]]
+            name = "PDFs"
+            [rule.condition]
+            extension = "pdf"
+            [rule.action]
+            type = "move"
+            destination = "~/Documents"
+
+            [[rule]]
+            name = "Screenshots"
+            [rule.action]
+            type = "nothing"
+        "#;
+        assert_eq!(
+            describe_edit(old, new).unwrap(),
+            [
+                "change rule 'PDFs'",
+                "add rule 'Screenshots'",
+                "remove rule 'Old'",
+                "add watch ~/Desktop",
+                "change [general]",
+            ]
+        );
+        // Reformatting isn't an edit, and files that don't parse aren't described
+        assert!(
+            describe_edit(old, &old.replace("    ", ""))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(describe_edit(old, "[[rule]").is_none());
+    }
+
+    #[test]
+    fn test_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        let status = Command::new("git")
+            .args(["init", "-q", "--bare", "-b", BRANCH])
+            .arg(&remote)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let remote = remote.to_str().unwrap();
+        let machine = |name: &str, content: &str| {
+            let config = dir.path().join(name).join("config.toml");
+            std::fs::create_dir(config.parent().unwrap()).unwrap();
+            std::fs::write(&config, content).unwrap();
+            config
+        };
+        let edit = |config: &Path, from: &str, to: &str| {
+            let content = std::fs::read_to_string(config).unwrap();
+            std::fs::write(config, content.replace(from, to)).unwrap();
+        };
+
+        // The first machine creates the repository and pushes it
+        let a = machine("a", "[general]\nlog_level = \"info\"\n");
+        let report = sync(&a, Some(remote), false).unwrap();
+        assert!(report.initialized && report.pushed && !report.pulled);
+        assert!(report.committed.is_some());
+
+        // The second joins the shared history, then pushes an edit
+        let b = machine("b", "[general]\nlog_level = \"info\"\n");
+        let report = sync(&b, Some(remote), false).unwrap();
+        assert!(report.pulled && report.committed.is_none() && !report.pushed);
+        edit(&b, "info", "debug");
+        let report = sync(&b, None, false).unwrap();
+        assert_eq!(report.committed.as_deref(), Some("Change [general]"));
+        assert!(report.pushed);
+
+        // Both changed the same line: the sync stops and leaves the file be
+        edit(&a, "info", "warn");
+        let error = sync(&a, None, false).unwrap_err();
+        assert!(format!("{:#}", error).contains("changed the same lines"));
+        assert!(std::fs::read_to_string(&a).unwrap().contains("warn"));
+        assert!(!a.parent().unwrap().join(".git/rebase-merge").exists());
+
+        // Secrets are only committed as environment variable references
+        std::fs::write(&b, "[api]\ntoken = \"hunter2\"\n").unwrap();
+        let error = sync(&b, None, false).unwrap_err();
+        assert!(error.to_string().starts_with("api.token in"));
+        std::fs::write(&b, "[api]\ntoken = \"${HAZELNUT_API_TOKEN}\"\n").unwrap();
+        assert!(sync(&b, None, false).unwrap().pushed);
+    }
+}
@@ -0,0 +1,180 @@
+//! User-defined variables - `{var.name}` in any string of the config is
+//! replaced with the value from the `[variables]` table
+//!
+//! Substitution happens once, when the config is parsed. The resolved strings
+//! remember what they were written as, so saving the config writes the
+//! references back rather than their values.
+
+use std::sync::LazyLock;
+
+use anyhow::{Result, bail};
+use regex::{Captures, Regex};
+use toml::Value;
+
+use super::Config;
+
+static REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{var\.([A-Za-z0-9_-]+)\}").unwrap());
+
+/// Names of the variables referenced in a string
+pub(crate) fn references(s: &str) -> impl Iterator<Item = &str> {
+    REFERENCE
+        .captures_iter(s)
+        .filter_map(|caps| caps.get(1).map(|name| name.as_str()))
+}
+
+/// Replace variable references in every string of the config
+pub(crate) fn resolve(config: Config) -> Result<Config> {
+    let mut value = Value::try_from(&config)?;
+    let mut substitutions = Vec::new();
+    if let Value::Table(table) = &mut value {
+        for (key, value) in table.iter_mut() {
+            if key != "variables" {
+                substitute(value, &config, &mut substitutions)?;
+            }
+        }
+    }
+    if substitutions.is_empty() {
+        return Ok(config);
+    }
+
+    let mut resolved: Config = value.try_into()?;
+    resolved.substitutions = substitutions;
+    Ok(resolved)
+}
+
+fn substitute(
+    value: &mut Value,
+    config: &Config,
+    substitutions: &mut Vec<(String, String)>,
+) -> Result<()> {
+    match value {
+        Value::String(s) if s.contains("{var.") => {
+            let mut unknown = None;
+            let resolved =
+                REFERENCE.replace_all(s, |caps: &Captures| match config.variables.get(&caps[1]) {
+                    Some(value) => value.clone(),
+                    None => {
+                        unknown.get_or_insert_with(|| caps[1].to_string());
+                        caps[0].to_string()
+                    }
+                });
+            if let Some(name) = unknown {
+                let known = config
+                    .variables
+                    .keys()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>();
+                if known.is_empty() {
+                    bail!(
+                        "Unknown variable '{}' in \"{}\" (no [variables] are defined)",
+                        name,
+                        s
+                    );
+                }
+                bail!(
+                    "Unknown variable '{}' in \"{}\" (variables: {})",
+                    name,
+                    s,
+                    known.join(", ")
+                );
+            }
+            let resolved = resolved.into_owned();
+            if resolved != *s {
+                substitutions.push((resolved.clone(), std::mem::replace(s, resolved)));
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                substitute(item, config, substitutions)?;
+            }
+        }
+        Value::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                substitute(item, config, substitutions)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The config as it should be saved: strings still holding a resolved value
+/// get their variable references back
+pub(crate) fn restore(config: &Config) -> Result<Config> {
+    if config.substitutions.is_empty() {
+        return Ok(config.clone());
+    }
+    let mut value = Value::try_from(config)?;
+    unsubstitute(&mut value, &config.substitutions);
+    Ok(value.try_into()?)
+}
+
+fn unsubstitute(value: &mut Value, substitutions: &[(String, String)]) {
+    match value {
+        Value::String(s) => {
+            if let Some((_, original)) = substitutions.iter().find(|(resolved, _)| resolved == s) {
+                *s = original.clone();
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                unsubstitute(item, substitutions);
+            }
+        }
+        Value::Table(table) => {
+            for (key, item) in table.iter_mut() {
+                if key != "variables" {
+                    unsubstitute(item, substitutions);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Action;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_variables() {
+        let toml = r#"
+[variables]
+archive_root = "/srv/archive"
+
+[[watch]]
+path = "{var.archive_root}/Inbox"
+
+[[rule]]
+name = "Invoices"
+[rule.condition]
+extension = "pdf"
+[rule.action]
+type = "move"
+destination = "{var.archive_root}/Invoices"
+"#;
+        let config = resolve(toml::from_str(toml).unwrap()).unwrap();
+        assert_eq!(config.watches[0].path, PathBuf::from("/srv/archive/Inbox"));
+        assert_eq!(
+            config.rules[0].action.destination(),
+            Some(PathBuf::from("/srv/archive/Invoices"))
+        );
+
+        // Saving writes the references back, unless the value was changed
+        let mut edited = config.clone();
+        if let Action::Move { destination, .. } = &mut edited.rules[0].action {
+            *destination = PathBuf::from("~/Receipts");
+        }
+        let saved = toml::to_string(&restore(&edited).unwrap()).unwrap();
+        assert!(saved.contains("path = \"{var.archive_root}/Inbox\""));
+        assert!(saved.contains("destination = \"~/Receipts\""));
+        assert!(saved.contains("archive_root = \"/srv/archive\""));
+
+        let unknown = "[[watch]]\npath = \"{var.nope}/Inbox\"";
+        let err = resolve(toml::from_str(unknown).unwrap()).unwrap_err();
+        assert!(err.to_string().contains("Unknown variable 'nope'"));
+    }
+}
@@ -2,107 +2,193 @@
 //!
 //! Background service that watches directories and applies rules.
 //!
-//! This daemon is Unix-only as it uses Unix signals and process management.
+//! On Unix it forks into the background and is controlled with signals and
+//! an IPC socket. On Windows it runs in the foreground (`run`, which
+//! auto-start uses) or as a Windows service (`service install`).
 
-// On non-Unix platforms, provide a stub that exits with a helpful message
-#[cfg(not(unix))]
+// On other platforms, provide a stub that exits with a helpful message
+#[cfg(not(any(unix, windows)))]
 fn main() {
-    eprintln!("Error: hazelnutd is only available on Unix systems (Linux, macOS).");
-    eprintln!("The daemon requires Unix signals and process management features.");
+    eprintln!("Error: hazelnutd is only available on Unix systems and Windows.");
     eprintln!();
-    eprintln!("On Windows, you can still use the hazelnut TUI application.");
+    eprintln!("You can still use the hazelnut TUI application.");
     std::process::exit(1);
 }
 
-// All Unix-specific code is in this module
-#[cfg(unix)]
-mod unix_daemon {
+// Shared by the Unix and Windows daemons
+#[cfg(any(unix, windows))]
+mod common {
     use anyhow::{Context, Result};
-    use clap::Parser;
-    use std::fs;
-    use std::path::PathBuf;
-    use std::process::{Command, Stdio};
     use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-    #[derive(Parser, Debug)]
-    #[command(name = "hazelnutd")]
-    #[command(author, version, about = "Hazelnut background daemon")]
-    pub struct Cli {
-        /// Path to config file
-        #[arg(short, long, value_name = "FILE")]
-        pub config: Option<std::path::PathBuf>,
+    /// Create a watcher for a reloaded config. Folders that can't be watched
+    /// are reported and returned, but don't fail the reload.
+    pub fn build_watcher(
+        config: &hazelnut::Config,
+    ) -> Result<(hazelnut::Watcher, Vec<std::path::PathBuf>)> {
+        let engine = hazelnut::RuleEngine::new(config.rules.clone())
+            .with_hooks(config.hooks.clone())
+            .with_shadow(hazelnut::shadow::ShadowRules::from_config(config))
+            .with_dry_run(config.general.dry_run);
+        let mut watcher = hazelnut::Watcher::new(
+            engine,
+            config.general.polling_interval_secs,
+            config.general.debounce_seconds,
+        )
+        .context("Failed to create new watcher")?
+        .with_stability(hazelnut::watcher::Stability::new(
+            config.general.stable_seconds,
+            config.general.temp_extensions.clone(),
+        ))
+        .with_batching(hazelnut::watcher::BatchLimits::from_config(&config.general));
+        let mut failed = Vec::new();
+        for watch in &config.watches {
+            let expanded_path = hazelnut::expand_path(&watch.path);
+            if let Err(e) = watcher.add_watch(watch) {
+                tracing::error!("Failed to watch {}: {}", expanded_path.display(), e);
+                hazelnut::notifications::notify_watch_error(
+                    &expanded_path.display().to_string(),
+                    &e.to_string(),
+                );
+                failed.push(expanded_path);
+            }
+        }
+        Ok((watcher, failed))
+    }
 
-        #[command(subcommand)]
-        pub command: Commands,
+    /// Log to stdout and the log file, at `log_level` unless HAZELNUT_LOG is
+    /// set, writing the file as text or JSON lines as `log_format` says
+    pub fn init_logging(config: &hazelnut::Config) -> Result<()> {
+        hazelnut::logfile::init(&config.general)?;
+        let filter =
+            std::env::var("HAZELNUT_LOG").unwrap_or_else(|_| config.general.log_level.clone());
+        let json = config.general.log_format == hazelnut::config::LogFormat::Json;
+        hazelnut::logfile::set_json(json);
+        tracing_subscriber::registry()
+            .with(tracing_subscriber::EnvFilter::new(filter))
+            .with(tracing_subscriber::fmt::layer().with_target(false))
+            .with((!json).then(|| {
+                tracing_subscriber::fmt::layer()
+                    .with_target(false)
+                    .with_ansi(false)
+                    .with_writer(|| hazelnut::logfile::Writer)
+            }))
+            .with(json.then(|| {
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .flatten_event(true)
+                    .with_writer(|| hazelnut::logfile::Writer)
+            }))
+            .init();
+        Ok(())
     }
 
-    #[derive(clap::Subcommand, Debug)]
-    pub enum Commands {
-        /// Start the daemon in background
-        Start,
+    /// The config with only the active profile's watches and rules, in dry
+    /// run mode if `--dry-run` was given
+    pub fn apply_profile(
+        config: &hazelnut::Config,
+        profile: Option<&str>,
+        dry_run: bool,
+    ) -> Result<hazelnut::Config> {
+        let mut config = match profile {
+            Some(name) => config.with_profile(name)?,
+            None => config.clone(),
+        };
+        config.general.dry_run |= dry_run;
+        Ok(config)
+    }
 
-        /// Stop the running daemon
-        Stop,
+    /// Hand the settings kept in globals to the modules using them, at start
+    /// and after each reload
+    pub fn configure(config: &hazelnut::Config) {
+        hazelnut::notifications::init(config);
+        hazelnut::quiet_hours::set(config.quiet_hours.clone());
+        hazelnut::plugins::set(config.plugins.clone());
+        hazelnut::quarantine::set(config.quarantine.clone());
+        hazelnut::rules::set_verify_moves(config.general.verify_moves);
+        hazelnut::progress::set_max_rate(
+            config
+                .general
+                .max_transfer_rate
+                .as_ref()
+                .map(|rate| rate.bytes()),
+        );
+        hazelnut::stats::init(config.general.stats_retention_days);
+        hazelnut::history::init(
+            config.general.history_retention_days,
+            config.general.history_checksums,
+        );
+        hazelnut::hashing::init(config.general.hash_algorithm, config.general.hash_threads);
+    }
+}
 
-        /// Restart the daemon
-        Restart,
+// All Unix-specific code is in this module
+#[cfg(unix)]
+mod unix_daemon {
+    use super::common::{apply_profile, build_watcher, configure, init_logging};
+    use anyhow::{Context, Result};
+    use hazelnut::daemon_cli::{Cli, Commands};
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process::{Command, Stdio};
 
-        /// Show daemon status
-        Status,
+    /// How long `start` waits for the background daemon to come up
+    const START_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
-        /// Reload configuration (HUP signal)
-        Reload,
+    /// How long `stop` waits for the daemon to exit before killing it
+    const STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
-        /// Run in foreground (for debugging)
-        Run,
-    }
+    /// `status --check` fails once the main loop hasn't polled for this long.
+    /// Generous, since a large copy holds up the loop while it runs.
+    const STALL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
 
-    /// Get the PID file path
-    /// Uses ~/.local/state/hazelnut/ on all platforms for consistency
+    /// Get the PID file path ($XDG_RUNTIME_DIR/hazelnut/, or ~/.local/state/hazelnut/)
     fn pid_file_path() -> PathBuf {
-        dirs::state_dir()
-            .unwrap_or_else(|| {
-                dirs::home_dir()
-                    .map(|h| h.join(".local").join("state"))
-                    .unwrap_or_else(|| PathBuf::from("/tmp"))
-            })
-            .join("hazelnut")
-            .join("hazelnutd.pid")
-    }
-
-    /// Get the log file path
-    /// Uses dirs::state_dir() with fallback for portability
-    fn log_file_path() -> PathBuf {
-        dirs::state_dir()
-            .unwrap_or_else(|| {
-                dirs::home_dir()
-                    .map(|h| h.join(".local").join("state"))
-                    .unwrap_or_else(|| PathBuf::from("/tmp"))
-            })
-            .join("hazelnut")
-            .join("hazelnutd.log")
+        hazelnut::daemon_pid_file()
     }
 
-    /// Read PID from file
-    fn read_pid() -> Option<i32> {
-        let pid_file = pid_file_path();
-        if pid_file.exists() {
-            fs::read_to_string(&pid_file)
-                .ok()
-                .and_then(|s| s.trim().parse().ok())
-        } else {
-            None
-        }
+    /// Get the log file path (`log_file`, or ~/.local/state/hazelnut/)
+    fn log_file_path(config_path: Option<&std::path::Path>) -> PathBuf {
+        hazelnut::Config::load(config_path)
+            .map(|config| hazelnut::logfile::path(&config.general))
+            .unwrap_or_else(|_| hazelnut::daemon_log_file())
     }
 
-    /// Write PID to file
-    fn write_pid(pid: u32) -> Result<()> {
+    /// Take the PID file for this process. The file stays locked while the
+    /// daemon runs, so a second daemon (started in the background or with
+    /// `run`) refuses to start.
+    fn lock_pid_file() -> Result<fs::File> {
+        use fs2::FileExt;
+        use std::io::Write;
+
+        if let Some(pid) = hazelnut::daemon_pid() {
+            anyhow::bail!("Daemon is already running (PID: {})", pid);
+        }
         let pid_file = pid_file_path();
         if let Some(parent) = pid_file.parent() {
             fs::create_dir_all(parent)?;
         }
-        fs::write(&pid_file, pid.to_string())?;
-        Ok(())
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&pid_file)
+            .with_context(|| format!("Failed to open PID file {}", pid_file.display()))?;
+        if FileExt::try_lock_exclusive(&file).is_err() {
+            anyhow::bail!(
+                "Daemon is already running (PID file {} is locked)",
+                pid_file.display()
+            );
+        }
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        file.sync_all()?;
+        // Older versions kept the PID file in the state directory
+        let legacy = hazelnut::daemon_state_dir().join("hazelnutd.pid");
+        if legacy != pid_file && hazelnut::instance::name().is_none() {
+            let _ = fs::remove_file(legacy);
+        }
+        Ok(file)
     }
 
     /// Remove PID file
@@ -142,52 +228,58 @@ mod unix_daemon {
 
     /// Get daemon status
     fn get_status() -> (bool, Option<i32>) {
-        if let Some(pid) = read_pid() {
-            if is_process_running(pid) {
-                return (true, Some(pid));
-            }
-            // Stale PID file
+        if let Some(pid) = hazelnut::daemon_pid() {
+            return (true, Some(pid));
+        }
+        // Stale PID file
+        if pid_file_path().exists() {
             remove_pid_file();
         }
         (false, None)
     }
 
     pub async fn run(cli: Cli) -> Result<()> {
+        hazelnut::instance::set(cli.instance.as_deref())?;
         match cli.command {
-            Commands::Start => {
-                start_daemon(cli.config)?;
+            Commands::Start { profile } => {
+                start_daemon(cli.config, profile.as_deref())?;
             }
             Commands::Stop => {
                 stop_daemon()?;
             }
-            Commands::Restart => {
-                let _ = stop_daemon();
-                std::thread::sleep(std::time::Duration::from_millis(500));
-                start_daemon(cli.config)?;
+            Commands::Restart { profile } => {
+                // Keep the running daemon's profile unless another is given
+                let profile = profile.or_else(|| {
+                    use hazelnut::ipc::{DaemonCommand, DaemonResponse};
+                    match ipc_request(&DaemonCommand::Status) {
+                        Some(DaemonResponse::Status { profile, .. }) => profile,
+                        _ => None,
+                    }
+                });
+                // stop_daemon waits for the old process to exit
+                stop_daemon()?;
+                start_daemon(cli.config, profile.as_deref())?;
+            }
+            Commands::Status {
+                check: true, wait, ..
+            } => {
+                check_status(wait.map(std::time::Duration::from_secs));
             }
-            Commands::Status => {
-                show_status();
+            Commands::Status { json, .. } => {
+                show_status(json)?;
             }
             Commands::Reload => {
                 reload_config()?;
             }
-            Commands::Run => {
-                // Initialize logging for foreground mode
-                tracing_subscriber::registry()
-                    .with(tracing_subscriber::EnvFilter::new(
-                        std::env::var("HAZELNUT_LOG").unwrap_or_else(|_| "info".to_string()),
-                    ))
-                    .with(tracing_subscriber::fmt::layer().with_target(false))
-                    .init();
-
-                run_daemon(cli.config).await?;
+            Commands::Run { profile, dry_run } => {
+                run_daemon(cli.config, profile, dry_run).await?;
             }
         }
 
         Ok(())
     }
 
-    fn start_daemon(config_path: Option<PathBuf>) -> Result<()> {
+    fn start_daemon(config_path: Option<PathBuf>, profile: Option<&str>) -> Result<()> {
         let (running, pid) = get_status();
         if running {
             println!("🌰 Daemon is already running (PID: {})", pid.unwrap());
@@ -201,14 +293,19 @@ mod unix_daemon {
 
         // Build command
         let mut cmd = Command::new(&exe);
-        cmd.arg("run");
-
+        cmd.args(hazelnut::instance::daemon_args());
         if let Some(ref config) = config_path {
-            cmd.arg("--config").arg(config);
+            // The daemon runs from `/`, so relative paths must be resolved now
+            cmd.arg("--config").arg(std::path::absolute(config)?);
+        }
+        cmd.arg("run");
+        if let Some(profile) = profile {
+            cmd.arg("--profile").arg(profile);
         }
 
-        // Set up log file
-        let log_path = log_file_path();
+        // The daemon writes its own log; stderr only catches errors from
+        // before logging is set up
+        let log_path = log_file_path(config_path.as_deref());
         if let Some(parent) = log_path.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -219,12 +316,11 @@ mod unix_daemon {
             .open(&log_path)
             .context("Failed to open log file")?;
 
-        let log_file_err = log_file.try_clone()?;
-
         // Start the daemon process
         cmd.stdin(Stdio::null())
-            .stdout(log_file)
-            .stderr(log_file_err);
+            .stdout(Stdio::null())
+            .stderr(log_file)
+            .current_dir("/");
 
         // On Unix, use setsid to detach from terminal
         {
@@ -236,10 +332,29 @@ mod unix_daemon {
             }
         }
 
-        let child = cmd.spawn().context("Failed to start daemon")?;
+        let mut child = cmd.spawn().context("Failed to start daemon")?;
         let pid = child.id();
 
-        write_pid(pid)?;
+        // Wait until the daemon has taken its PID file, so errors such as an
+        // invalid config are reported here instead of only in the log
+        let started = std::time::Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                anyhow::bail!(
+                    "Daemon exited during startup ({}), see {}",
+                    status,
+                    log_path.display()
+                );
+            }
+            if hazelnut::daemon_pid() == Some(pid as i32) {
+                break;
+            }
+            if started.elapsed() >= START_TIMEOUT {
+                println!("  Daemon hasn't written its PID file yet");
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
 
         println!("✓ Daemon started (PID: {})", pid);
         println!("  Log file: {}", log_path.display());
@@ -247,7 +362,26 @@ mod unix_daemon {
         Ok(())
     }
 
+    /// Send a command over IPC, returning None if the daemon can't be reached
+    fn ipc_request(cmd: &hazelnut::ipc::DaemonCommand) -> Option<hazelnut::ipc::DaemonResponse> {
+        hazelnut::ipc::send_command(cmd).ok()
+    }
+
+    /// Wait up to `timeout` for a process to exit
+    fn wait_for_exit(pid: i32, timeout: std::time::Duration) -> bool {
+        let started = std::time::Instant::now();
+        while is_process_running(pid) {
+            if started.elapsed() >= timeout {
+                return false;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        true
+    }
+
     fn stop_daemon() -> Result<()> {
+        use hazelnut::ipc::{DaemonCommand, DaemonResponse};
+
         let (running, pid) = get_status();
 
         if !running {
@@ -258,52 +392,158 @@ mod unix_daemon {
         let pid = pid.unwrap();
         println!("🌰 Stopping daemon (PID: {})...", pid);
 
-        // Send SIGTERM
-        if send_signal(pid, libc::SIGTERM) {
-            // Wait for process to exit (up to 5 seconds)
-            for _ in 0..50 {
-                if !is_process_running(pid) {
-                    remove_pid_file();
-                    println!("✓ Daemon stopped");
-                    return Ok(());
-                }
-                std::thread::sleep(std::time::Duration::from_millis(100));
-            }
+        // Ask over IPC first, so the daemon finishes its current pass;
+        // fall back to SIGTERM if the socket doesn't answer
+        let requested = matches!(ipc_request(&DaemonCommand::Stop), Some(DaemonResponse::Ok))
+            || send_signal(pid, libc::SIGTERM);
 
-            // Force kill if still running
-            println!("  Sending SIGKILL...");
-            send_signal(pid, libc::SIGKILL);
-            std::thread::sleep(std::time::Duration::from_millis(100));
-            remove_pid_file();
-            println!("✓ Daemon killed");
-        } else {
+        if !requested {
             remove_pid_file();
             println!("✗ Failed to stop daemon (process may have already exited)");
+            return Ok(());
+        }
+
+        if wait_for_exit(pid, STOP_TIMEOUT) {
+            println!("✓ Daemon stopped");
+            return Ok(());
         }
 
+        // Force kill if still running
+        println!("  Sending SIGKILL...");
+        send_signal(pid, libc::SIGKILL);
+        wait_for_exit(pid, std::time::Duration::from_secs(1));
+        remove_pid_file();
+        let _ = fs::remove_file(hazelnut::ipc::socket_path());
+        println!("✓ Daemon killed");
+
         Ok(())
     }
 
-    fn show_status() {
-        let (running, pid) = get_status();
+    /// Number of recent log entries shown by `status`
+    const STATUS_LOG_ENTRIES: usize = 5;
 
-        if running {
-            let pid = pid.unwrap();
-            println!("🌰 Hazelnut daemon is running");
-            println!("   PID: {}", pid);
-            println!("   PID file: {}", pid_file_path().display());
-            println!("   Log file: {}", log_file_path().display());
+    fn show_status(json: bool) -> Result<()> {
+        // Clears a stale PID file
+        get_status();
+        let report = hazelnut::ipc::StatusReport::collect(STATUS_LOG_ENTRIES);
 
-            #[cfg(target_os = "linux")]
-            if let Some(uptime) = hazelnut::read_process_uptime(pid as u32) {
-                println!("   Uptime: {}", uptime);
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+
+        let Some(pid) = report.pid else {
+            println!("🌰 Hazelnut daemon is not running");
+            return Ok(());
+        };
+        println!("🌰 Hazelnut daemon is running");
+        if let Some(instance) = &report.instance {
+            println!("   Instance: {}", instance);
+        }
+        println!("   PID: {}", pid);
+        println!("   PID file: {}", report.pid_file.display());
+        println!("   Log file: {}", report.log_file.display());
+        if let Some(uptime) = report.uptime_seconds {
+            println!("   Uptime: {}", hazelnut::format_uptime(uptime));
+        }
+        if !report.responding {
+            println!("   (IPC socket not responding, details unavailable)");
+            return Ok(());
+        }
+
+        if let Some(profile) = &report.profile {
+            println!("   Profile: {}", profile);
+        }
+        println!("   Rules: {}", report.rules.unwrap_or_default());
+        println!(
+            "   Files: {} processed, {} unmatched",
+            report.files_processed.unwrap_or_default(),
+            report.files_unmatched.unwrap_or_default()
+        );
+        println!("   Watches: {}", report.watches.len());
+        for watch in &report.watches {
+            let paused = if watch.paused { " (paused)" } else { "" };
+            println!("     {}{}", watch.path.display(), paused);
+        }
+        if !report.recent_activity.is_empty() {
+            println!("   Recent activity:");
+            for entry in &report.recent_activity {
+                println!("     {}", entry);
             }
+        }
+        Ok(())
+    }
+
+    /// Result of a health check; the value is the exit code of `status --check`
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Health {
+        Healthy = 0,
+        NotRunning = 1,
+        Unresponsive = 2,
+        Degraded = 3,
+    }
+
+    /// Check the daemon's health, with a one-line explanation
+    fn check_health() -> (Health, String) {
+        use hazelnut::ipc::{DaemonCommand, DaemonResponse};
+
+        let (running, pid) = get_status();
+        let Some(pid) = pid.filter(|_| running) else {
+            return (Health::NotRunning, "daemon is not running".to_string());
+        };
+        match ipc_request(&DaemonCommand::Health) {
+            Some(DaemonResponse::Health {
+                last_poll_seconds, ..
+            }) if last_poll_seconds >= STALL_TIMEOUT.as_secs() => (
+                Health::Unresponsive,
+                format!(
+                    "daemon (PID {}) hasn't polled for events in {}",
+                    pid,
+                    hazelnut::format_uptime(last_poll_seconds)
+                ),
+            ),
+            Some(DaemonResponse::Health { failed_watches, .. }) if !failed_watches.is_empty() => {
+                let paths: Vec<String> = failed_watches
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect();
+                (
+                    Health::Degraded,
+                    format!("daemon (PID {}) couldn't watch: {}", pid, paths.join(", ")),
+                )
+            }
+            Some(DaemonResponse::Health { .. }) => {
+                (Health::Healthy, format!("daemon (PID {}) is healthy", pid))
+            }
+            _ => (
+                Health::Unresponsive,
+                format!("daemon (PID {}) is not responding over IPC", pid),
+            ),
+        }
+    }
+
+    /// `status --check`: print the health and exit with its code, retrying
+    /// for up to `wait` (e.g. while the daemon starts)
+    fn check_status(wait: Option<std::time::Duration>) -> ! {
+        let started = std::time::Instant::now();
+        let (health, message) = loop {
+            let (health, message) = check_health();
+            if health == Health::Healthy || wait.is_none_or(|wait| started.elapsed() >= wait) {
+                break (health, message);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(250));
+        };
+        if health == Health::Healthy {
+            println!("✓ {}", message);
         } else {
-            println!("🌰 Hazelnut daemon is not running");
+            println!("✗ {}", message);
         }
+        std::process::exit(health as i32)
     }
 
     fn reload_config() -> Result<()> {
+        use hazelnut::ipc::{DaemonCommand, DaemonResponse};
+
         let (running, pid) = get_status();
 
         if !running {
@@ -314,19 +554,424 @@ mod unix_daemon {
         let pid = pid.unwrap();
         println!("🌰 Reloading configuration (PID: {})...", pid);
 
-        if send_signal(pid, libc::SIGHUP) {
-            println!("✓ Reload signal sent");
-        } else {
-            println!("✗ Failed to send reload signal");
+        match ipc_request(&DaemonCommand::Reload) {
+            Some(DaemonResponse::Ok) => println!("✓ Reload requested"),
+            Some(DaemonResponse::Error { message }) => anyhow::bail!(message),
+            _ if send_signal(pid, libc::SIGHUP) => println!("✓ Reload signal sent"),
+            _ => println!("✗ Failed to send reload signal"),
         }
 
         Ok(())
     }
 
-    async fn run_daemon(config_path: Option<std::path::PathBuf>) -> Result<()> {
-        use std::collections::VecDeque;
-        use std::sync::{Arc, Mutex};
+    /// Daemon state reported over IPC, refreshed by the main loop
+    struct IpcState {
+        start_time: std::time::Instant,
+        watches: std::sync::atomic::AtomicUsize,
+        /// Rules in use, for the API's rule list
+        rules: std::sync::Mutex<Vec<hazelnut::rules::Rule>>,
+        files_processed: std::sync::atomic::AtomicU64,
+        files_unmatched: std::sync::atomic::AtomicU64,
+        /// Configured watch paths, for validating pause/resume requests
+        watch_paths: std::sync::Mutex<Vec<std::path::PathBuf>>,
+        /// Watches that couldn't be set up
+        failed_watches: std::sync::Mutex<Vec<std::path::PathBuf>>,
+        log_file: std::sync::Mutex<Option<std::path::PathBuf>>,
+        /// Watches to scan on the next tick, as requested
+        scan_requests: std::sync::Mutex<Vec<std::path::PathBuf>>,
+        /// Milliseconds after `start_time` of the last update from the main loop
+        last_poll_ms: std::sync::atomic::AtomicU64,
+        /// Active profile
+        profile: std::sync::Mutex<Option<String>>,
+        /// Configured profiles, with the reason each can't be used (if any)
+        profiles: std::sync::Mutex<std::collections::BTreeMap<String, Option<String>>>,
+        /// Profile to switch to on the next tick, as requested
+        profile_request: std::sync::Mutex<Option<Option<String>>>,
+    }
+
+    impl IpcState {
+        fn new(start_time: std::time::Instant) -> Self {
+            Self {
+                start_time,
+                watches: Default::default(),
+                rules: Default::default(),
+                files_processed: Default::default(),
+                files_unmatched: Default::default(),
+                watch_paths: Default::default(),
+                failed_watches: Default::default(),
+                log_file: Default::default(),
+                scan_requests: Default::default(),
+                last_poll_ms: Default::default(),
+                profile: Default::default(),
+                profiles: Default::default(),
+                profile_request: Default::default(),
+            }
+        }
+
+        fn update(&self, config: &hazelnut::Config, watcher: &hazelnut::Watcher) {
+            use std::sync::atomic::Ordering::Relaxed;
+            self.watches.store(config.watches.len(), Relaxed);
+            if let Ok(mut rules) = self.rules.lock() {
+                *rules = config.rules.clone();
+            }
+            self.files_processed
+                .store(watcher.files_processed(), Relaxed);
+            self.files_unmatched
+                .store(watcher.files_unmatched(), Relaxed);
+            if let Ok(mut watch_paths) = self.watch_paths.lock() {
+                watch_paths.clear();
+                watch_paths.extend(config.watches.iter().map(|w| w.path.clone()));
+            }
+            if let Ok(mut log_file) = self.log_file.lock() {
+                *log_file = Some(hazelnut::logfile::path(&config.general));
+            }
+            let elapsed = u64::try_from(self.start_time.elapsed().as_millis()).unwrap_or(u64::MAX);
+            self.last_poll_ms.store(elapsed, Relaxed);
+        }
+
+        /// Record the active profile, and which profiles of the full config
+        /// (`base`) could be switched to
+        fn set_profiles(&self, base: &hazelnut::Config, active: Option<&str>) {
+            if let Ok(mut profile) = self.profile.lock() {
+                *profile = active.map(str::to_string);
+            }
+            if let Ok(mut profiles) = self.profiles.lock() {
+                *profiles = base
+                    .profiles
+                    .keys()
+                    .map(|name| {
+                        let error = base.with_profile(name).err().map(|e| format!("{:#}", e));
+                        (name.clone(), error)
+                    })
+                    .collect();
+            }
+        }
+
+        /// Queue a switch to another profile (None: everything)
+        fn request_profile(&self, name: Option<String>) -> hazelnut::ipc::DaemonResponse {
+            use hazelnut::ipc::DaemonResponse;
+            if let Some(name) = &name {
+                let profiles = self.profiles.lock().map(|p| p.clone()).unwrap_or_default();
+                match profiles.get(name) {
+                    Some(None) => {}
+                    Some(Some(error)) => {
+                        return DaemonResponse::Error {
+                            message: error.clone(),
+                        };
+                    }
+                    None => {
+                        let names: Vec<_> = profiles.keys().map(String::as_str).collect();
+                        return DaemonResponse::Error {
+                            message: if names.is_empty() {
+                                format!("Unknown profile '{}' (no [profile.*] sections)", name)
+                            } else {
+                                format!(
+                                    "Unknown profile '{}' (profiles: {})",
+                                    name,
+                                    names.join(", ")
+                                )
+                            },
+                        };
+                    }
+                }
+            }
+            if let Ok(mut request) = self.profile_request.lock() {
+                *request = Some(name);
+            }
+            DaemonResponse::Ok
+        }
+
+        /// Profile switch requested since the last call
+        fn take_profile_request(&self) -> Option<Option<String>> {
+            self.profile_request
+                .lock()
+                .ok()
+                .and_then(|mut request| request.take())
+        }
+
+        fn set_failed_watches(&self, failed: Vec<std::path::PathBuf>) {
+            if let Ok(mut failed_watches) = self.failed_watches.lock() {
+                *failed_watches = failed;
+            }
+        }
+
+        fn health(&self) -> hazelnut::ipc::DaemonResponse {
+            use std::sync::atomic::Ordering::Relaxed;
+            let since_poll = std::time::Duration::from_millis(self.last_poll_ms.load(Relaxed));
+            hazelnut::ipc::DaemonResponse::Health {
+                last_poll_seconds: self
+                    .start_time
+                    .elapsed()
+                    .saturating_sub(since_poll)
+                    .as_secs(),
+                failed_watches: self
+                    .failed_watches
+                    .lock()
+                    .map(|paths| paths.clone())
+                    .unwrap_or_default(),
+            }
+        }
+
+        fn status(&self) -> hazelnut::ipc::DaemonResponse {
+            use std::sync::atomic::Ordering::Relaxed;
+            hazelnut::ipc::DaemonResponse::Status {
+                running: true,
+                uptime_seconds: self.start_time.elapsed().as_secs(),
+                watches: self.watches.load(Relaxed),
+                rules: self
+                    .rules
+                    .lock()
+                    .map(|rules| rules.len())
+                    .unwrap_or_default(),
+                files_processed: self.files_processed.load(Relaxed),
+                files_unmatched: self.files_unmatched.load(Relaxed),
+                watch_paths: self
+                    .watch_paths
+                    .lock()
+                    .map(|paths| paths.clone())
+                    .unwrap_or_default(),
+                paused_watches: hazelnut::watcher::paused_watches(),
+                log_file: self.log_file.lock().ok().and_then(|path| path.clone()),
+                profile: self.profile.lock().ok().and_then(|profile| profile.clone()),
+            }
+        }
+
+        /// Raw notifications, for all watches or one configured watch
+        fn raw_events(
+            &self,
+            after: Option<u64>,
+            path: Option<&std::path::Path>,
+        ) -> hazelnut::ipc::DaemonResponse {
+            use hazelnut::ipc::DaemonResponse;
+            if let Some(path) = path
+                && !self.is_watched(path)
+            {
+                return DaemonResponse::Error {
+                    message: format!("Not a watched folder: {}", path.display()),
+                };
+            }
+            let (events, next) = hazelnut::watcher::raw_events(after, path);
+            DaemonResponse::RawEvents { events, next }
+        }
+
+        /// Whether a path is one of the configured watches
+        fn is_watched(&self, path: &std::path::Path) -> bool {
+            use hazelnut::watcher::watch_key;
+            let key = watch_key(path);
+            self.watch_paths
+                .lock()
+                .is_ok_and(|paths| paths.iter().any(|p| watch_key(p) == key))
+        }
+
+        /// Queue a scan of one configured watch, or all of them
+        fn request_scan(&self, path: Option<&std::path::Path>) -> hazelnut::ipc::DaemonResponse {
+            use hazelnut::ipc::DaemonResponse;
+            let paths = match path {
+                Some(path) if !self.is_watched(path) => {
+                    return DaemonResponse::Error {
+                        message: format!("Not a watched folder: {}", path.display()),
+                    };
+                }
+                Some(path) => vec![path.to_path_buf()],
+                None => self
+                    .watch_paths
+                    .lock()
+                    .map(|paths| paths.clone())
+                    .unwrap_or_default(),
+            };
+            if let Ok(mut requests) = self.scan_requests.lock() {
+                requests.extend(paths);
+            }
+            DaemonResponse::Ok
+        }
+
+        /// Scans requested since the last call
+        fn take_scan_requests(&self) -> Vec<std::path::PathBuf> {
+            self.scan_requests
+                .lock()
+                .map(|mut requests| std::mem::take(&mut *requests))
+                .unwrap_or_default()
+        }
+
+        /// Answer a command from the daemon's state, for IPC and the API
+        /// (stop, reload, the log and subscriptions are only served over IPC)
+        fn answer(&self, cmd: hazelnut::ipc::DaemonCommand) -> hazelnut::ipc::DaemonResponse {
+            use hazelnut::ipc::{DaemonCommand, DaemonResponse};
+            match cmd {
+                DaemonCommand::Status | DaemonCommand::GetStats => self.status(),
+                DaemonCommand::GetProgress => DaemonResponse::Progress {
+                    transfers: hazelnut::progress::active(),
+                    scans: hazelnut::progress::active_scans(),
+                },
+                DaemonCommand::PauseWatch { path } => self.set_paused(&path, true),
+                DaemonCommand::ResumeWatch { path } => self.set_paused(&path, false),
+                DaemonCommand::GetRawEvents { after, path } => {
+                    self.raw_events(after, path.as_deref())
+                }
+                DaemonCommand::Health => self.health(),
+                DaemonCommand::Scan { path } => self.request_scan(path.as_deref()),
+                DaemonCommand::SetProfile { name } => self.request_profile(name),
+                DaemonCommand::GetRules => DaemonResponse::Rules {
+                    rules: self
+                        .rules
+                        .lock()
+                        .map(|rules| rules.clone())
+                        .unwrap_or_default(),
+                },
+                DaemonCommand::GetHistory { limit } => {
+                    let mut entries = hazelnut::history::recent();
+                    entries.truncate(limit);
+                    DaemonResponse::History { entries }
+                }
+                DaemonCommand::Stop
+                | DaemonCommand::Reload
+                | DaemonCommand::GetLog { .. }
+                | DaemonCommand::Subscribe => DaemonResponse::Error {
+                    message: "Only available over IPC".to_string(),
+                },
+            }
+        }
+
+        /// Pause or resume a configured watch
+        fn set_paused(
+            &self,
+            path: &std::path::Path,
+            paused: bool,
+        ) -> hazelnut::ipc::DaemonResponse {
+            use hazelnut::ipc::DaemonResponse;
+            use hazelnut::watcher::watch_key;
+            use tracing::info;
+
+            let key = watch_key(path);
+            if !self.is_watched(path) {
+                return DaemonResponse::Error {
+                    message: format!("Not a watched folder: {}", path.display()),
+                };
+            }
+            if paused {
+                if hazelnut::watcher::pause_watch(path) {
+                    info!("Paused watch: {}", key.display());
+                }
+            } else if hazelnut::watcher::resume_watch(path) {
+                info!("Resumed watch: {}", key.display());
+            }
+            DaemonResponse::Ok
+        }
+    }
+
+    /// Accept IPC connections until a Stop command is received.
+    /// Connections are handled one at a time, so nothing is accepted after Stop.
+    async fn serve_ipc(
+        listener: tokio::net::UnixListener,
+        state: std::sync::Arc<IpcState>,
+        log_buffer: std::sync::Arc<std::sync::Mutex<std::collections::VecDeque<String>>>,
+        stop: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    ) {
+        use hazelnut::ipc::{DaemonCommand, DaemonResponse};
         use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+        use tokio::time::Duration;
+        use tracing::info;
+
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let reader = BufReader::new(stream);
+            let mut lines = reader.lines();
+            // Apply a per-connection read timeout so a slow/malicious client
+            // cannot block other clients indefinitely.
+            let read_result = tokio::time::timeout(Duration::from_secs(5), lines.next_line()).await;
+            let Ok(Ok(Some(line))) = read_result else {
+                continue;
+            };
+            let mut stopping = false;
+            let mut subscription = None;
+            let response = match serde_json::from_str::<DaemonCommand>(&line) {
+                Ok(cmd) => match cmd {
+                    DaemonCommand::Stop => {
+                        info!("Stop requested via IPC");
+                        stopping = true;
+                        DaemonResponse::Ok
+                    }
+                    DaemonCommand::Reload => match i32::try_from(std::process::id()) {
+                        Ok(pid) => {
+                            send_signal_safe(pid, libc::SIGHUP);
+                            DaemonResponse::Ok
+                        }
+                        Err(_) => DaemonResponse::Error {
+                            message: "PID too large for signal delivery".to_string(),
+                        },
+                    },
+                    DaemonCommand::GetLog { limit } => {
+                        let entries = if let Ok(ring) = log_buffer.lock() {
+                            let skip = ring.len().saturating_sub(limit);
+                            ring.iter().skip(skip).cloned().collect()
+                        } else {
+                            vec![]
+                        };
+                        DaemonResponse::Log { entries }
+                    }
+                    DaemonCommand::Subscribe => {
+                        // Subscribe before answering, so no event is missed
+                        subscription = Some(hazelnut::activity::subscribe());
+                        DaemonResponse::Ok
+                    }
+                    cmd => state.answer(cmd),
+                },
+                Err(e) => DaemonResponse::Error {
+                    message: format!("Invalid command: {e}"),
+                },
+            };
+            let resp_json = serde_json::to_string(&response).unwrap_or_default();
+            let mut w = lines.into_inner().into_inner();
+            let _ = w.write_all(format!("{resp_json}\n").as_bytes()).await;
+            let _ = w.flush().await;
+
+            if let Some(events) = subscription {
+                tokio::spawn(stream_activity(w, events));
+                continue;
+            }
+            if stopping {
+                // The main loop sees the flag on its next tick
+                stop.store(true, std::sync::atomic::Ordering::SeqCst);
+                return;
+            }
+        }
+    }
+
+    /// Send activity events to a subscribed client until it disconnects
+    async fn stream_activity(
+        mut stream: tokio::net::UnixStream,
+        mut events: tokio::sync::broadcast::Receiver<hazelnut::activity::ActivityEvent>,
+    ) {
+        use tokio::io::AsyncWriteExt;
+        use tokio::sync::broadcast::error::RecvError;
+
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                // A slow client misses some events rather than holding up others
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            };
+            let response = hazelnut::ipc::DaemonResponse::Activity { event };
+            let Ok(mut line) = serde_json::to_string(&response) else {
+                continue;
+            };
+            line.push('\n');
+            if stream.write_all(line.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    async fn run_daemon(
+        config_path: Option<std::path::PathBuf>,
+        mut profile: Option<String>,
+        dry_run: bool,
+    ) -> Result<()> {
+        use std::collections::{HashSet, VecDeque};
+        use std::sync::{Arc, Mutex};
         use tokio::signal::unix::{SignalKind, signal};
         use tokio::time::{Duration, interval};
         use tracing::info;
@@ -334,12 +979,20 @@ mod unix_daemon {
         /// Maximum number of log entries kept in the ring buffer.
         const MAX_LOG_ENTRIES: usize = 500;
 
+        /// How often watches with a `max_size` are measured
+        const QUOTA_INTERVAL: Duration = Duration::from_secs(60);
+
         // In-memory ring buffer for log entries returned by GetLog.
         let log_buffer: Arc<Mutex<VecDeque<String>>> =
             Arc::new(Mutex::new(VecDeque::with_capacity(MAX_LOG_ENTRIES)));
 
         /// Push a message into the ring buffer, evicting the oldest if full.
         fn push_log(buf: &Mutex<VecDeque<String>>, msg: String, max: usize) {
+            // Subscribers get the entry without its timestamp
+            let message = msg.split_once("] ").map_or(msg.as_str(), |(_, m)| m);
+            hazelnut::activity::publish(hazelnut::activity::ActivityKind::Message {
+                message: message.to_string(),
+            });
             if let Ok(mut ring) = buf.lock() {
                 if ring.len() >= max {
                     ring.pop_front();
@@ -348,8 +1001,15 @@ mod unix_daemon {
             }
         }
 
-        // Write PID file for foreground mode too
-        write_pid(std::process::id())?;
+        // Load the config first, so `start` reports errors in it. `config`
+        // is what the profile sees; `base_config` is the whole file.
+        let mut base_config = hazelnut::Config::load(config_path.as_deref())?;
+        base_config.validate()?;
+        let mut config = apply_profile(&base_config, profile.as_deref(), dry_run)?;
+        init_logging(&config)?;
+
+        // Held until exit; also written in foreground mode
+        let _pid_file = lock_pid_file()?;
 
         let start_time = std::time::Instant::now();
 
@@ -366,36 +1026,49 @@ mod unix_daemon {
         let mut sigint = signal(SignalKind::interrupt())?;
         let mut sighup = signal(SignalKind::hangup())?;
 
-        let config_path_clone = config_path.clone();
-        let mut config = hazelnut::Config::load(config_path.as_deref())?;
-
-        // Initialize notifications
-        hazelnut::notifications::init(config.general.notifications_enabled);
+        configure(&config);
 
         info!(
             "Loaded config with {} watch paths and {} rules",
             config.watches.len(),
             config.rules.len()
         );
+        if let Some(profile) = &profile {
+            info!("Using profile '{}'", profile);
+        }
+        if config.general.dry_run {
+            info!("Dry run: rules are only logged, no file will be touched");
+        }
 
-        let engine = hazelnut::RuleEngine::new(config.rules.clone());
+        let engine = hazelnut::RuleEngine::new(config.rules.clone())
+            .with_hooks(config.hooks.clone())
+            .with_shadow(hazelnut::shadow::ShadowRules::from_config(&config))
+            .with_dry_run(config.general.dry_run);
         let mut watcher = hazelnut::Watcher::new(
             engine,
             config.general.polling_interval_secs,
             config.general.debounce_seconds,
-        )?;
-
+        )?
+        .with_stability(hazelnut::watcher::Stability::new(
+            config.general.stable_seconds,
+            config.general.temp_extensions.clone(),
+        ))
+        .with_batching(hazelnut::watcher::BatchLimits::from_config(&config.general));
+
+        let mut failed_watches = Vec::new();
         for watch in &config.watches {
             let expanded_path = hazelnut::expand_path(&watch.path);
             info!("Watching: {}", expanded_path.display());
-            if let Err(e) =
-                watcher.watch_with_rules(&expanded_path, watch.recursive, watch.rules.clone())
-            {
+            if let Err(e) = watcher.add_watch(watch) {
                 tracing::error!("Failed to watch {}: {}", expanded_path.display(), e);
                 hazelnut::notifications::notify_watch_error(
                     &expanded_path.display().to_string(),
                     &e.to_string(),
                 );
+                failed_watches.push(expanded_path);
+            } else if watch.scan_on_start {
+                info!("Scanning existing files in {}", expanded_path.display());
+                watcher.scan(&expanded_path, watch.recursive);
             }
         }
 
@@ -415,11 +1088,77 @@ mod unix_daemon {
         // Flag to signal stop from spawned IPC tasks
         let stop_flag = Arc::new(AtomicBool::new(false));
 
+        // Periodic rescans for watches with a `schedule`
+        let mut scheduler =
+            hazelnut::schedule::Scheduler::new(&config.watches, chrono::Local::now());
+        // Runs of the `[[retention]]` policies
+        let retention_scheduler = |config: &hazelnut::Config| {
+            hazelnut::schedule::Scheduler::with(
+                config
+                    .retention
+                    .iter()
+                    .map(|policy| Some(policy.schedule.clone())),
+                chrono::Local::now(),
+            )
+        };
+        let mut retention = retention_scheduler(&config);
+        // Indexes of the retention policies being applied, each skipped
+        // until its last run has finished
+        let retention_running = Arc::new(Mutex::new(HashSet::new()));
+        // Next check of the watches' quotas, skipped while one is still running
+        let mut next_quota_check = std::time::Instant::now() + QUOTA_INTERVAL;
+        let quota_running = Arc::new(AtomicBool::new(false));
+        // Next unmatched-files report, if `[report]` has a schedule
+        let next_report_after = |config: &hazelnut::Config| {
+            config
+                .report
+                .schedule
+                .as_ref()
+                .and_then(|s| s.next_after(chrono::Local::now()))
+        };
+        let mut next_report = next_report_after(&config);
+
+        // Serve IPC from its own task so status and transfer progress stay
+        // available while the main loop is busy running actions
+        let ipc_state = Arc::new(IpcState::new(start_time));
+        ipc_state.update(&config, &watcher);
+        ipc_state.set_profiles(&base_config, profile.as_deref());
+        ipc_state.set_failed_watches(failed_watches);
+        let ipc_task = tokio::spawn(serve_ipc(
+            ipc_listener,
+            Arc::clone(&ipc_state),
+            Arc::clone(&log_buffer),
+            Arc::clone(&stop_flag),
+        ));
+
+        // The HTTP API answers from the same state as IPC
+        let mut api_task = None;
+        if config.api.enabled {
+            match hazelnut::api::bind(&config.api).await {
+                Ok(listener) => {
+                    let state = Arc::clone(&ipc_state);
+                    let handler: Arc<hazelnut::api::Handler> =
+                        Arc::new(move |cmd| state.answer(cmd));
+                    let api = config.api.clone();
+                    api_task = Some(tokio::spawn(async move {
+                        hazelnut::api::serve(listener, &api, handler).await
+                    }));
+                }
+                Err(e) => tracing::error!("{:#}", e),
+            }
+        }
+
         // Poll for events periodically
         let mut poll_interval = interval(Duration::from_millis(500));
 
+        // Picks up edits to the config file; SIGHUP re-reads it right away
+        let mut reloader = config_path
+            .clone()
+            .or_else(hazelnut::Config::default_path)
+            .map(hazelnut::config::ConfigReloader::new);
+
         loop {
-            tokio::select! {
+            let reloaded = tokio::select! {
                 _ = sigterm.recv() => {
                     info!("Received SIGTERM, shutting down...");
                     break;
@@ -430,43 +1169,12 @@ mod unix_daemon {
                 }
                 _ = sighup.recv() => {
                     info!("Received SIGHUP, reloading configuration...");
-                    match hazelnut::Config::load(config_path_clone.as_deref()) {
-                        Ok(new_config) => {
-                            config = new_config;
-                            // Update notification settings
-                            hazelnut::notifications::init(config.general.notifications_enabled);
-                            // Recreate watcher with new rules, polling interval, and debounce
-                            let engine = hazelnut::RuleEngine::new(config.rules.clone());
-                            match hazelnut::Watcher::new(
-                                engine,
-                                config.general.polling_interval_secs,
-                                config.general.debounce_seconds,
-                            ) {
-                                Ok(mut new_watcher) => {
-                                    for watch in &config.watches {
-                                        let expanded_path = hazelnut::expand_path(&watch.path);
-                                        if let Err(e) = new_watcher.watch_with_rules(&expanded_path, watch.recursive, watch.rules.clone()) {
-                                            tracing::error!("Failed to watch {}: {}", expanded_path.display(), e);
-                                            hazelnut::notifications::notify_watch_error(
-                                                &expanded_path.display().to_string(),
-                                                &e.to_string(),
-                                            );
-                                        }
-                                    }
-                                    new_watcher.carry_over_files_processed(&watcher);
-                                    watcher = new_watcher;
-                                    info!("Configuration reloaded: {} watches, {} rules",
-                                        config.watches.len(), config.rules.len());
-                                }
-                                Err(e) => {
-                                    tracing::error!("Failed to create new watcher: {}", e);
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!("Failed to reload config: {}", e);
-                        }
-                    }
+                    let reloaded = match reloader.as_mut() {
+                        Some(reloader) => reloader.load(),
+                        None => hazelnut::Config::load(config_path.as_deref())
+                            .and_then(|config| config.validate().map(|_| config)),
+                    };
+                    Some((reloaded, profile.clone()))
                 }
                 _ = poll_interval.tick() => {
                     // Check if stop was requested by an IPC task
@@ -474,6 +1182,143 @@ mod unix_daemon {
                         info!("Stop flag set, shutting down...");
                         break;
                     }
+                    hazelnut::notifications::flush_digest();
+                    hazelnut::stats::flush_if_due();
+                    hazelnut::date_added::flush_if_due();
+                    hazelnut::quarantine::purge_if_due();
+                    for index in scheduler.due(chrono::Local::now()) {
+                        let watch = &config.watches[index];
+                        let expanded_path = hazelnut::expand_path(&watch.path);
+                        info!("Scheduled scan of {}", expanded_path.display());
+                        push_log(
+                            &log_buffer,
+                            format!(
+                                "[{}] Scheduled scan: {}",
+                                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                                expanded_path.display()
+                            ),
+                            MAX_LOG_ENTRIES,
+                        );
+                        watcher.scan(&expanded_path, watch.recursive);
+                    }
+                    for path in ipc_state.take_scan_requests() {
+                        let key = hazelnut::watcher::watch_key(&path);
+                        let Some(watch) = config
+                            .watches
+                            .iter()
+                            .find(|w| hazelnut::watcher::watch_key(&w.path) == key)
+                        else {
+                            continue;
+                        };
+                        let expanded_path = hazelnut::expand_path(&watch.path);
+                        info!("Manual scan of {}", expanded_path.display());
+                        push_log(
+                            &log_buffer,
+                            format!(
+                                "[{}] Manual scan: {}",
+                                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                                expanded_path.display()
+                            ),
+                            MAX_LOG_ENTRIES,
+                        );
+                        watcher.scan(&expanded_path, watch.recursive);
+                    }
+                    for index in retention.due(chrono::Local::now()) {
+                        if !retention_running.lock().unwrap().insert(index) {
+                            info!("Retention policy {} is still running, skipping", index + 1);
+                            continue;
+                        }
+                        // Trashing or archiving many files can take a while
+                        let policy = config.retention[index].clone();
+                        let dry_run = config.general.dry_run;
+                        let log_buf = Arc::clone(&log_buffer);
+                        let running = Arc::clone(&retention_running);
+                        std::thread::spawn(move || {
+                            let run = policy.apply(dry_run);
+                            info!(
+                                "Retention ({}) of {}: {} file(s), {} failed",
+                                policy.action().kind(),
+                                policy.path.display(),
+                                run.removed.len(),
+                                run.failed.len()
+                            );
+                            push_log(
+                                &log_buf,
+                                format!(
+                                    "[{}] Retention: {} old file(s) in {} ({})",
+                                    chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                                    run.removed.len(),
+                                    policy.path.display(),
+                                    policy.action().kind()
+                                ),
+                                MAX_LOG_ENTRIES,
+                            );
+                            running.lock().unwrap().remove(&index);
+                        });
+                    }
+                    if std::time::Instant::now() >= next_quota_check {
+                        next_quota_check = std::time::Instant::now() + QUOTA_INTERVAL;
+                        let watches: Vec<_> = config
+                            .watches
+                            .iter()
+                            .filter(|w| w.max_size.is_some() && !hazelnut::sftp::is_remote(&w.path))
+                            .cloned()
+                            .collect();
+                        if !watches.is_empty()
+                            && !quota_running.swap(true, std::sync::atomic::Ordering::SeqCst)
+                        {
+                            let dry_run = config.general.dry_run;
+                            let running = Arc::clone(&quota_running);
+                            let log_buf = Arc::clone(&log_buffer);
+                            std::thread::spawn(move || {
+                                for watch in &watches {
+                                    let Some(run) = hazelnut::quota::enforce(watch, dry_run) else {
+                                        continue;
+                                    };
+                                    let Some(message) = hazelnut::quota::summary(watch, &run, dry_run)
+                                    else {
+                                        continue;
+                                    };
+                                    info!("Quota of {}", message);
+                                    push_log(
+                                        &log_buf,
+                                        format!(
+                                            "[{}] Quota: {}",
+                                            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                                            message
+                                        ),
+                                        MAX_LOG_ENTRIES,
+                                    );
+                                    hazelnut::notifications::notify(
+                                        hazelnut::notifications::NotificationKind::Quota,
+                                        &message,
+                                    );
+                                }
+                                running.store(false, std::sync::atomic::Ordering::SeqCst);
+                            });
+                        }
+                    }
+                    if next_report.is_some_and(|t| t <= chrono::Local::now()) {
+                        next_report = next_report_after(&config);
+                        // Evaluating every file can take a while; don't block the loop
+                        let report_config = config.clone();
+                        let log_buf = Arc::clone(&log_buffer);
+                        std::thread::spawn(move || {
+                            let reports = hazelnut::report::send(&report_config);
+                            info!("Sent unmatched-files report for {} folder(s)", reports.len());
+                            for report in reports {
+                                push_log(
+                                    &log_buf,
+                                    format!(
+                                        "[{}] Report: {}",
+                                        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                                        report.summary(report_config.report.older_than_days)
+                                    ),
+                                    MAX_LOG_ENTRIES,
+                                );
+                            }
+                        });
+                    }
                     match watcher.process_events() {
                         Ok(count) if count > 0 => {
                             let msg = format!("[{}] Processed {} file(s)", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), count);
@@ -487,101 +1332,104 @@ mod unix_daemon {
                         }
                         _ => {}
                     }
+                    ipc_state.update(&config, &watcher);
+                    let changed = reloader.as_mut().and_then(|r| r.poll());
+                    if changed.is_some() {
+                        info!("Config file changed, reloading configuration...");
+                    }
+                    let requested = ipc_state.take_profile_request();
+                    if let Some(requested) = &requested {
+                        info!("Switching to profile '{}'", requested.as_deref().unwrap_or("(none)"));
+                    }
+                    match (changed, requested) {
+                        (None, None) => None,
+                        (changed, requested) => Some((
+                            changed.unwrap_or_else(|| Ok(base_config.clone())),
+                            requested.unwrap_or_else(|| profile.clone()),
+                        )),
+                    }
                 }
-                result = ipc_listener.accept() => {
-                    if let Ok((stream, _)) = result {
-                        let log_buf = Arc::clone(&log_buffer);
-                        let uptime_start = start_time;
-                        // Capture stats at command-handling time (not accept time)
-                        // so they reflect current state after potential SIGHUP reloads.
-                        let num_watches = config.watches.len();
-                        let num_rules = config.rules.len();
-                        let files_count = watcher.files_processed();
-                        let stop = Arc::clone(&stop_flag);
-
-                        // Handle IPC synchronously to avoid race between stop flag
-                        // and accepting new connections.
-                        let reader = BufReader::new(stream);
-                        let mut lines = reader.lines();
-                        // Apply a per-connection read timeout so a slow/malicious client
-                        // cannot block the daemon event loop indefinitely.
-                        let read_result = tokio::time::timeout(
-                            Duration::from_secs(5),
-                            lines.next_line(),
-                        ).await;
-                        if let Ok(Ok(Some(line))) = read_result {
-                            let response = match serde_json::from_str::<hazelnut::ipc::DaemonCommand>(&line) {
-                                Ok(cmd) => match cmd {
-                                    hazelnut::ipc::DaemonCommand::Status => {
-                                        hazelnut::ipc::DaemonResponse::Status {
-                                            running: true,
-                                            uptime_seconds: uptime_start.elapsed().as_secs(),
-                                            watches: num_watches,
-                                            rules: num_rules,
-                                            files_processed: files_count,
-                                        }
-                                    }
-                                    hazelnut::ipc::DaemonCommand::Stop => {
-                                        info!("Stop requested via IPC");
-                                        let resp = serde_json::to_string(&hazelnut::ipc::DaemonResponse::Ok).unwrap_or_default();
-                                        let stream = lines.into_inner().into_inner();
-                                        let mut w = stream;
-                                        let _ = w.write_all(format!("{resp}\n").as_bytes()).await;
-                                        let _ = w.flush().await;
-                                        stop.store(true, std::sync::atomic::Ordering::SeqCst);
-                                        // Break immediately — no more connections accepted
-                                        break;
-                                    }
-                                    hazelnut::ipc::DaemonCommand::Reload => {
-                                        match i32::try_from(std::process::id()) {
-                                            Ok(pid) => {
-                                                send_signal_safe(pid, libc::SIGHUP);
-                                                hazelnut::ipc::DaemonResponse::Ok
-                                            }
-                                            Err(_) => hazelnut::ipc::DaemonResponse::Error {
-                                                message: "PID too large for signal delivery".to_string(),
-                                            },
-                                        }
-                                    }
-                                    hazelnut::ipc::DaemonCommand::GetLog { limit } => {
-                                        let entries = if let Ok(ring) = log_buf.lock() {
-                                            let skip = ring.len().saturating_sub(limit);
-                                            ring.iter().skip(skip).cloned().collect()
-                                        } else {
-                                            vec![]
-                                        };
-                                        hazelnut::ipc::DaemonResponse::Log { entries }
-                                    }
-                                    hazelnut::ipc::DaemonCommand::GetStats => {
-                                        hazelnut::ipc::DaemonResponse::Status {
-                                            running: true,
-                                            uptime_seconds: uptime_start.elapsed().as_secs(),
-                                            watches: num_watches,
-                                            rules: num_rules,
-                                            files_processed: files_count,
-                                        }
-                                    }
-                                },
-                                Err(e) => hazelnut::ipc::DaemonResponse::Error {
-                                    message: format!("Invalid command: {e}"),
-                                },
-                            };
-                            let resp_json = serde_json::to_string(&response).unwrap_or_default();
-                            let stream = lines.into_inner().into_inner();
-                            let mut w = stream;
-                            let _ = w.write_all(format!("{resp_json}\n").as_bytes()).await;
-                            let _ = w.flush().await;
-                        }
-
-                        // Check stop flag after every IPC command
-                        if stop_flag.load(std::sync::atomic::Ordering::SeqCst) {
-                            break;
-                        }
+            };
+
+            let Some((reloaded, new_profile)) = reloaded else {
+                continue;
+            };
+            // Swap in the new config only once everything is in place
+            match reloaded.and_then(|new_base| {
+                let new_config = apply_profile(&new_base, new_profile.as_deref(), dry_run)?;
+                let (new_watcher, failed) = build_watcher(&new_config)?;
+                Ok((new_base, new_config, new_watcher, failed))
+            }) {
+                Ok((new_base, new_config, mut new_watcher, failed)) => {
+                    if new_config.api != config.api {
+                        tracing::warn!("Changes to [api] apply when the daemon restarts");
+                    }
+                    if new_config.general.log_format != config.general.log_format {
+                        tracing::warn!("A changed log_format applies when the daemon restarts");
+                    }
+                    base_config = new_base;
+                    config = new_config;
+                    profile = new_profile;
+                    configure(&config);
+                    if let Err(e) = hazelnut::logfile::init(&config.general) {
+                        tracing::error!("Keeping the current log file: {:#}", e);
                     }
+                    new_watcher.carry_over_files_processed(&watcher);
+                    watcher = new_watcher;
+                    scheduler =
+                        hazelnut::schedule::Scheduler::new(&config.watches, chrono::Local::now());
+                    retention = retention_scheduler(&config);
+                    next_report = next_report_after(&config);
+                    ipc_state.update(&config, &watcher);
+                    ipc_state.set_profiles(&base_config, profile.as_deref());
+                    ipc_state.set_failed_watches(failed);
+                    let in_profile = profile
+                        .as_ref()
+                        .map(|profile| format!(" (profile '{}')", profile))
+                        .unwrap_or_default();
+                    info!(
+                        "Configuration reloaded: {} watches, {} rules{}",
+                        config.watches.len(),
+                        config.rules.len(),
+                        in_profile
+                    );
+                    push_log(
+                        &log_buffer,
+                        format!(
+                            "[{}] Configuration reloaded: {} watches, {} rules{}",
+                            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                            config.watches.len(),
+                            config.rules.len(),
+                            in_profile
+                        ),
+                        MAX_LOG_ENTRIES,
+                    );
+                }
+                Err(e) => {
+                    tracing::error!("Failed to reload config, keeping the current one: {:#}", e);
+                    // Parse errors quote the config over several lines
+                    let error = format!("{:#}", e);
+                    let summary = error.lines().next().unwrap_or_default();
+                    hazelnut::notifications::notify_config_error(summary);
+                    push_log(
+                        &log_buffer,
+                        format!(
+                            "[{}] Error: Config not reloaded: {}",
+                            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                            summary
+                        ),
+                        MAX_LOG_ENTRIES,
+                    );
                 }
             }
         }
 
+        ipc_task.abort();
+        if let Some(api_task) = api_task {
+            api_task.abort();
+        }
+        hazelnut::stats::flush();
+        hazelnut::date_added::flush();
         remove_pid_file();
         let _ = std::fs::remove_file(&sock_path);
         info!("Daemon stopped");
@@ -589,10 +1437,496 @@ mod unix_daemon {
     }
 }
 
+// Windows has neither fork nor signals: the daemon runs in the foreground,
+// or the service manager starts and stops it
+#[cfg(windows)]
+mod windows_daemon {
+    use super::common::{apply_profile, build_watcher, configure, init_logging};
+    use anyhow::{Context, Result};
+    use hazelnut::daemon_cli::{Cli, Commands, ServiceCommand};
+    use std::collections::HashSet;
+    use std::ffi::{OsStr, OsString};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::time::{Duration, Instant};
+    use tracing::info;
+    use windows_service::service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    };
+    use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+    use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+
+    /// How often events are processed and a stop request is checked for
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// How long `stop` waits for the service to stop
+    const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// `--config` of `service run`, for the service's main function
+    static SERVICE_CONFIG: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+    pub fn run(cli: Cli) -> Result<()> {
+        hazelnut::instance::set(cli.instance.as_deref())?;
+        match cli.command {
+            Commands::Run { profile, dry_run } => run_foreground(cli.config, profile, dry_run),
+            Commands::Service { command } => match command {
+                ServiceCommand::Install => install(cli.config),
+                ServiceCommand::Uninstall => uninstall(),
+                ServiceCommand::Start => start(),
+                ServiceCommand::Stop => stop(),
+                ServiceCommand::Run => run_service(cli.config),
+            },
+            // The service is the background daemon on Windows
+            Commands::Start { profile: None } => start(),
+            Commands::Stop => stop(),
+            Commands::Restart { profile: None } => {
+                stop()?;
+                start()
+            }
+            Commands::Start { .. } | Commands::Restart { .. } => anyhow::bail!(
+                "The service can't use a profile; use `hazelnutd run --profile NAME` instead"
+            ),
+            Commands::Status { json: true, .. } => {
+                anyhow::bail!("`status --json` is only available on Unix")
+            }
+            Commands::Status {
+                check: true, wait, ..
+            } => check_status(wait.map(Duration::from_secs)),
+            Commands::Status { .. } => show_status(),
+            Commands::Reload => {
+                println!("The daemon reloads its config file whenever it changes");
+                Ok(())
+            }
+        }
+    }
+
+    /// `run`: watch until Ctrl+C
+    fn run_foreground(
+        config_path: Option<PathBuf>,
+        profile: Option<String>,
+        dry_run: bool,
+    ) -> Result<()> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        std::thread::spawn(move || {
+            let Ok(runtime) = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            else {
+                return;
+            };
+            if runtime.block_on(tokio::signal::ctrl_c()).is_ok() {
+                info!("Received Ctrl+C, shutting down...");
+                flag.store(true, Ordering::Relaxed);
+            }
+        });
+        watch(config_path, profile, dry_run, false, &stop)
+    }
+
+    /// Load the config, then watch and apply rules until `stop` is set.
+    /// `service` says which name the running daemon holds.
+    fn watch(
+        config_path: Option<PathBuf>,
+        profile: Option<String>,
+        dry_run: bool,
+        service: bool,
+        stop: &AtomicBool,
+    ) -> Result<()> {
+        let base_config = hazelnut::Config::load(config_path.as_deref())?;
+        base_config.validate()?;
+        let mut config = apply_profile(&base_config, profile.as_deref(), dry_run)?;
+        init_logging(&config)?;
+
+        // Held until exit
+        let _running = hazelnut::service::lock(service)?;
+
+        configure(&config);
+        info!(
+            "Loaded config with {} watch paths and {} rules",
+            config.watches.len(),
+            config.rules.len()
+        );
+        if let Some(profile) = &profile {
+            info!("Using profile '{}'", profile);
+        }
+        if config.general.dry_run {
+            info!("Dry run: rules are only logged, no file will be touched");
+        }
+
+        let (mut watcher, failed) = build_watcher(&config)?;
+        for watch in &config.watches {
+            let expanded_path = hazelnut::expand_path(&watch.path);
+            if failed.contains(&expanded_path) {
+                continue;
+            }
+            info!("Watching: {}", expanded_path.display());
+            if watch.scan_on_start {
+                info!("Scanning existing files in {}", expanded_path.display());
+                watcher.scan(&expanded_path, watch.recursive);
+            }
+        }
+        info!("Daemon running (PID: {})", std::process::id());
+
+        let mut scheduler =
+            hazelnut::schedule::Scheduler::new(&config.watches, chrono::Local::now());
+        let retention_scheduler = |config: &hazelnut::Config| {
+            hazelnut::schedule::Scheduler::with(
+                config
+                    .retention
+                    .iter()
+                    .map(|policy| Some(policy.schedule.clone())),
+                chrono::Local::now(),
+            )
+        };
+        let mut retention = retention_scheduler(&config);
+        let retention_running = Arc::new(Mutex::new(HashSet::new()));
+        let mut reloader = config_path
+            .clone()
+            .or_else(hazelnut::Config::default_path)
+            .map(hazelnut::config::ConfigReloader::new);
+
+        while !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(POLL_INTERVAL);
+            hazelnut::notifications::flush_digest();
+            hazelnut::stats::flush_if_due();
+            hazelnut::date_added::flush_if_due();
+            hazelnut::quarantine::purge_if_due();
+            for index in scheduler.due(chrono::Local::now()) {
+                let watch = &config.watches[index];
+                let expanded_path = hazelnut::expand_path(&watch.path);
+                info!("Scheduled scan of {}", expanded_path.display());
+                watcher.scan(&expanded_path, watch.recursive);
+            }
+            for index in retention.due(chrono::Local::now()) {
+                if !retention_running.lock().unwrap().insert(index) {
+                    info!("Retention policy {} is still running, skipping", index + 1);
+                    continue;
+                }
+                // Trashing or archiving many files can take a while
+                let policy = config.retention[index].clone();
+                let dry_run = config.general.dry_run;
+                let running = Arc::clone(&retention_running);
+                std::thread::spawn(move || {
+                    let run = policy.apply(dry_run);
+                    info!(
+                        "Retention ({}) of {}: {} file(s), {} failed",
+                        policy.action().kind(),
+                        policy.path.display(),
+                        run.removed.len(),
+                        run.failed.len()
+                    );
+                    running.lock().unwrap().remove(&index);
+                });
+            }
+            match watcher.process_events() {
+                Ok(count) if count > 0 => info!("Processed {} files", count),
+                Err(e) => tracing::error!("Error processing events: {}", e),
+                _ => {}
+            }
+
+            let Some(reloaded) = reloader.as_mut().and_then(|r| r.poll()) else {
+                continue;
+            };
+            info!("Config file changed, reloading configuration...");
+            // Swap in the new config only once everything is in place
+            match reloaded.and_then(|new_base| {
+                let new_config = apply_profile(&new_base, profile.as_deref(), dry_run)?;
+                let (new_watcher, _) = build_watcher(&new_config)?;
+                Ok((new_config, new_watcher))
+            }) {
+                Ok((new_config, mut new_watcher)) => {
+                    if new_config.general.log_format != config.general.log_format {
+                        tracing::warn!("A changed log_format applies when the daemon restarts");
+                    }
+                    config = new_config;
+                    configure(&config);
+                    if let Err(e) = hazelnut::logfile::init(&config.general) {
+                        tracing::error!("Keeping the current log file: {:#}", e);
+                    }
+                    new_watcher.carry_over_files_processed(&watcher);
+                    watcher = new_watcher;
+                    scheduler =
+                        hazelnut::schedule::Scheduler::new(&config.watches, chrono::Local::now());
+                    retention = retention_scheduler(&config);
+                    info!(
+                        "Configuration reloaded: {} watches, {} rules",
+                        config.watches.len(),
+                        config.rules.len()
+                    );
+                }
+                Err(e) => {
+                    tracing::error!("Failed to reload config, keeping the current one: {:#}", e);
+                    // Parse errors quote the config over several lines
+                    let error = format!("{:#}", e);
+                    hazelnut::notifications::notify_config_error(
+                        error.lines().next().unwrap_or_default(),
+                    );
+                }
+            }
+        }
+
+        hazelnut::stats::flush();
+        hazelnut::date_added::flush();
+        info!("Daemon stopped");
+        Ok(())
+    }
+
+    windows_service::define_windows_service!(ffi_service_main, service_main);
+
+    /// `service run`: hand the process to the service manager, which calls
+    /// `service_main` and returns once the service has stopped
+    fn run_service(config_path: Option<PathBuf>) -> Result<()> {
+        let _ = SERVICE_CONFIG.set(config_path);
+        windows_service::service_dispatcher::start(hazelnut::service::name(), ffi_service_main)
+            .context("Failed to connect to the service manager (`service run` is for it alone)")
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        if let Err(e) = serve() {
+            tracing::error!("Service failed: {:#}", e);
+        }
+    }
+
+    /// Report the service running, watch until the service manager asks it
+    /// to stop, and report it stopped
+    fn serve() -> Result<()> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let handle =
+            service_control_handler::register(hazelnut::service::name(), move |control| {
+                match control {
+                    ServiceControl::Stop | ServiceControl::Shutdown => {
+                        flag.store(true, Ordering::Relaxed);
+                        ServiceControlHandlerResult::NoError
+                    }
+                    ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+                    _ => ServiceControlHandlerResult::NotImplemented,
+                }
+            })?;
+        let status = |state, controls_accepted, exit_code| ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted,
+            exit_code,
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        };
+
+        handle.set_service_status(status(
+            ServiceState::Running,
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+            ServiceExitCode::NO_ERROR,
+        ))?;
+        let config_path = SERVICE_CONFIG.get().cloned().flatten();
+        let result = watch(config_path, None, false, true, &stop);
+        // A service-specific code makes the failure show in the event log
+        let exit_code = match result {
+            Ok(()) => ServiceExitCode::NO_ERROR,
+            Err(_) => ServiceExitCode::ServiceSpecific(1),
+        };
+        handle.set_service_status(status(
+            ServiceState::Stopped,
+            ServiceControlAccept::empty(),
+            exit_code,
+        ))?;
+        result
+    }
+
+    /// Display name of the service, with the instance for a named one
+    fn display_name() -> String {
+        match hazelnut::instance::name() {
+            Some(name) => format!("Hazelnut ({})", name),
+            None => "Hazelnut".to_string(),
+        }
+    }
+
+    /// Service manager errors, pointing to an administrator prompt when
+    /// access was denied
+    fn scm_error(action: &str, e: windows_service::Error) -> anyhow::Error {
+        if let windows_service::Error::Winapi(io) = &e
+            && io.kind() == std::io::ErrorKind::PermissionDenied
+        {
+            return anyhow::anyhow!(
+                "Failed to {}: access denied (run this from an administrator prompt)",
+                action
+            );
+        }
+        anyhow::Error::new(e).context(format!("Failed to {}", action))
+    }
+
+    fn open_service(access: ServiceAccess) -> Result<windows_service::service::Service> {
+        let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+            .map_err(|e| scm_error("connect to the service manager", e))?;
+        manager
+            .open_service(hazelnut::service::name(), access)
+            .map_err(|e| {
+                scm_error(
+                    &format!("open the {} service", hazelnut::service::name()),
+                    e,
+                )
+            })
+    }
+
+    /// State of the installed service, or `None` if it isn't installed
+    fn service_state() -> Option<ServiceState> {
+        let service = open_service(ServiceAccess::QUERY_STATUS).ok()?;
+        service
+            .query_status()
+            .ok()
+            .map(|status| status.current_state)
+    }
+
+    /// Register the service with the config file resolved now, since the
+    /// service doesn't run as this user
+    fn install(config_path: Option<PathBuf>) -> Result<()> {
+        let config = config_path
+            .or_else(hazelnut::Config::default_path)
+            .context("Could not find the config file; pass it with --config")?;
+        let config = std::path::absolute(&config)
+            .with_context(|| format!("Failed to resolve {}", config.display()))?;
+        hazelnut::Config::load(Some(config.as_path()))?.validate()?;
+
+        let manager = ServiceManager::local_computer(
+            None::<&str>,
+            ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+        )
+        .map_err(|e| scm_error("connect to the service manager", e))?;
+        let info = ServiceInfo {
+            name: OsString::from(hazelnut::service::name()),
+            display_name: OsString::from(display_name()),
+            service_type: ServiceType::OWN_PROCESS,
+            start_type: ServiceStartType::AutoStart,
+            error_control: ServiceErrorControl::Normal,
+            executable_path: std::env::current_exe().context("Couldn't find the running binary")?,
+            launch_arguments: hazelnut::service::launch_arguments(&config),
+            dependencies: vec![],
+            // LocalSystem
+            account_name: None,
+            account_password: None,
+        };
+        let service = manager
+            .create_service(&info, ServiceAccess::CHANGE_CONFIG)
+            .map_err(|e| scm_error("install the service", e))?;
+        service
+            .set_description("Watches folders and organizes files with Hazelnut's rules")
+            .map_err(|e| scm_error("describe the service", e))?;
+        println!(
+            "✓ Installed the {} service, using {}",
+            hazelnut::service::name(),
+            config.display()
+        );
+        println!("  It starts with Windows; start it now with `hazelnutd service start`");
+        Ok(())
+    }
+
+    fn uninstall() -> Result<()> {
+        let service = open_service(
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )?;
+        // Removed once it has stopped
+        service
+            .delete()
+            .map_err(|e| scm_error("uninstall the service", e))?;
+        let state = service
+            .query_status()
+            .map_err(|e| scm_error("query the service", e))?
+            .current_state;
+        if state != ServiceState::Stopped {
+            service
+                .stop()
+                .map_err(|e| scm_error("stop the service", e))?;
+        }
+        println!("✓ Uninstalled the {} service", hazelnut::service::name());
+        Ok(())
+    }
+
+    fn start() -> Result<()> {
+        if hazelnut::service::is_running() {
+            println!("Daemon is already running");
+            return Ok(());
+        }
+        let service = open_service(ServiceAccess::START).context(
+            "Install the service with `hazelnutd service install`, \
+             or run the daemon in the foreground with `hazelnutd run`",
+        )?;
+        service
+            .start::<&OsStr>(&[])
+            .map_err(|e| scm_error("start the service", e))?;
+        println!("✓ Started the {} service", hazelnut::service::name());
+        Ok(())
+    }
+
+    fn stop() -> Result<()> {
+        let service = open_service(ServiceAccess::QUERY_STATUS | ServiceAccess::STOP)?;
+        if service_state() == Some(ServiceState::Stopped) {
+            println!("Service is not running");
+            return Ok(());
+        }
+        service
+            .stop()
+            .map_err(|e| scm_error("stop the service", e))?;
+        let started = Instant::now();
+        while service_state() != Some(ServiceState::Stopped) {
+            if started.elapsed() >= STOP_TIMEOUT {
+                anyhow::bail!("The service didn't stop within {:?}", STOP_TIMEOUT);
+            }
+            std::thread::sleep(Duration::from_millis(250));
+        }
+        println!("✓ Stopped the {} service", hazelnut::service::name());
+        Ok(())
+    }
+
+    fn show_status() -> Result<()> {
+        if hazelnut::service::is_running() {
+            println!("🌰 Hazelnut daemon is running");
+        } else {
+            println!("🌰 Hazelnut daemon is not running");
+        }
+        if let Some(instance) = hazelnut::instance::name() {
+            println!("   Instance: {}", instance);
+        }
+        let service = match service_state() {
+            Some(ServiceState::Running) => "running",
+            Some(ServiceState::Stopped) => "stopped",
+            Some(ServiceState::StartPending) => "starting",
+            Some(ServiceState::StopPending) => "stopping",
+            Some(_) => "paused",
+            None => "not installed",
+        };
+        println!("   Service: {}", service);
+        Ok(())
+    }
+
+    /// `status --check`: exit 0 once the daemon runs, 1 if it doesn't by
+    /// the end of `wait`
+    fn check_status(wait: Option<Duration>) -> ! {
+        let started = Instant::now();
+        while !hazelnut::service::is_running() {
+            if wait.is_none_or(|wait| started.elapsed() >= wait) {
+                println!("✗ daemon is not running");
+                std::process::exit(1);
+            }
+            std::thread::sleep(Duration::from_millis(250));
+        }
+        println!("✓ daemon is running");
+        std::process::exit(0)
+    }
+}
+
 #[cfg(unix)]
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     use clap::Parser;
-    let cli = unix_daemon::Cli::parse();
+    let cli = hazelnut::daemon_cli::Cli::parse();
     unix_daemon::run(cli).await
 }
+
+#[cfg(windows)]
+fn main() -> anyhow::Result<()> {
+    use clap::Parser;
+    let cli = hazelnut::daemon_cli::Cli::parse();
+    windows_daemon::run(cli)
+}
@@ -0,0 +1,102 @@
+//! Command line of the daemon, `hazelnutd`
+//!
+//! Kept in the library so `hazelnut completions --daemon` can generate
+//! completions for it.
+
+#[derive(clap::Parser, Debug)]
+#[command(name = "hazelnutd")]
+#[command(author, version, about = "Hazelnut background daemon")]
+pub struct Cli {
+    /// Path to config file
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<std::path::PathBuf>,
+
+    /// Run or control a named instance, with its own config
+    /// (default: ~/.config/hazelnut/NAME.toml), PID file, socket and log
+    #[arg(short, long, value_name = "NAME")]
+    pub instance: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Commands {
+    /// Start the daemon in background
+    Start {
+        /// Only use the watches and rules of this `[profile.NAME]`
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+
+    /// Stop the running daemon
+    Stop,
+
+    /// Restart the daemon
+    Restart {
+        /// Only use the watches and rules of this `[profile.NAME]`
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+
+    /// Show daemon status
+    Status {
+        /// Print the status as JSON
+        #[arg(long, conflicts_with = "check")]
+        json: bool,
+
+        /// Only check health; exit 0 if healthy, 1 if not running,
+        /// 2 if not responding, 3 if some watches failed
+        #[arg(long)]
+        check: bool,
+
+        /// With --check, keep checking for up to SECS until healthy
+        #[arg(long, value_name = "SECS", requires = "check")]
+        wait: Option<u64>,
+    },
+
+    /// Reload configuration (HUP signal)
+    Reload,
+
+    /// Run in foreground (for debugging)
+    Run {
+        /// Only use the watches and rules of this `[profile.NAME]`
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+
+        /// Only log and notify what rules would do, without touching any
+        /// file (like `dry_run = true` in the config)
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Install and control the daemon as a Windows service
+    #[cfg(windows)]
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommand,
+    },
+}
+
+/// `hazelnutd service` subcommands; all but `run` need an administrator
+/// prompt
+#[cfg(windows)]
+#[derive(clap::Subcommand, Debug)]
+pub enum ServiceCommand {
+    /// Register the service, started when Windows starts, with this
+    /// instance and config file
+    Install,
+
+    /// Stop and remove the service
+    Uninstall,
+
+    /// Start the service
+    Start,
+
+    /// Stop the service
+    Stop,
+
+    /// Entry point for the service manager
+    #[command(hide = true)]
+    Run,
+}
@@ -0,0 +1,213 @@
+//! Date-added store
+//!
+//! Tracks when a file first arrived in a watched location, so age-based
+//! lifecycle rules can measure time since arrival rather than modification
+//! time (which downloads and copies often preserve from the source).
+//!
+//! Entries are persisted as JSON in the data directory and carried along
+//! when hazelnut moves a file, so a file keeps its original arrival date as
+//! it moves through tiers. Changes are written out at most once every few
+//! seconds (`flush_if_due`) and on exit (`flush`), and entries for files
+//! that are gone are dropped about once an hour, so a burst of new files
+//! doesn't rewrite and re-check the whole store for each one.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How long changes may wait before they're written out
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often entries for files that are gone are dropped
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Persistent map of file path → date the file was first seen
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DateAddedStore {
+    #[serde(default)]
+    entries: HashMap<PathBuf, DateTime<Local>>,
+
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl DateAddedStore {
+    /// Load the store from a file (missing or unreadable files give an empty store)
+    pub fn load(path: &Path) -> Self {
+        let mut store: Self = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        store.path = Some(path.to_path_buf());
+        store
+    }
+
+    /// Default location: `<data_dir>/hazelnut/date_added.json`
+    pub fn default_path() -> Option<PathBuf> {
+        crate::Config::data_dir().map(|d| d.join("date_added.json"))
+    }
+
+    /// Write the store back to disk (no-op for in-memory stores)
+    pub fn save(&self) -> Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string(self)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write date-added store {}", path.display()))
+    }
+
+    /// Get the recorded date for a path
+    pub fn get(&self, path: &Path) -> Option<DateTime<Local>> {
+        self.entries.get(path).copied()
+    }
+
+    /// Get the recorded date, recording a best guess from file metadata if unknown
+    pub fn get_or_insert(&mut self, path: &Path) -> DateTime<Local> {
+        if let Some(date) = self.get(path) {
+            return date;
+        }
+        let date = guess_date_added(path);
+        self.entries.insert(path.to_path_buf(), date);
+        date
+    }
+
+    /// Carry a file's date over to its new location after a move
+    pub fn record_move(&mut self, from: &Path, to: &Path) {
+        let date = self
+            .entries
+            .remove(from)
+            .unwrap_or_else(|| guess_date_added(to));
+        self.entries.insert(to.to_path_buf(), date);
+    }
+
+    /// Drop entries whose files no longer exist
+    pub fn prune(&mut self) {
+        self.entries.retain(|path, _| path.exists());
+    }
+}
+
+/// Best guess for an untracked file: creation time where the platform
+/// provides it, otherwise modification time, never later than now.
+fn guess_date_added(path: &Path) -> DateTime<Local> {
+    let now = Local::now();
+    std::fs::metadata(path)
+        .and_then(|m| m.created().or_else(|_| m.modified()))
+        .map(DateTime::<Local>::from)
+        .map(|date| date.min(now))
+        .unwrap_or(now)
+}
+
+/// The shared store and its bookkeeping
+struct Shared {
+    store: DateAddedStore,
+    /// Whether there are changes not written out yet
+    dirty: bool,
+    flushed: Instant,
+    pruned: Instant,
+}
+
+static STORE: LazyLock<Mutex<Shared>> = LazyLock::new(|| {
+    Mutex::new(Shared {
+        store: DateAddedStore::default_path()
+            .map(|p| DateAddedStore::load(&p))
+            .unwrap_or_default(),
+        dirty: false,
+        flushed: Instant::now(),
+        pruned: Instant::now(),
+    })
+});
+
+/// Date a file was added, from the shared store (recorded on first lookup)
+pub fn date_added(path: &Path) -> DateTime<Local> {
+    let Ok(mut shared) = STORE.lock() else {
+        return guess_date_added(path);
+    };
+    if let Some(date) = shared.store.get(path) {
+        return date;
+    }
+    shared.dirty = true;
+    shared.store.get_or_insert(path)
+}
+
+/// Date a file was added, without recording it on first lookup (for previews)
+pub fn peek_date_added(path: &Path) -> DateTime<Local> {
+    STORE
+        .lock()
+        .ok()
+        .and_then(|shared| shared.store.get(path))
+        .unwrap_or_else(|| guess_date_added(path))
+}
+
+/// Record that a file was moved, keeping its date-added in the shared store
+pub fn record_move(from: &Path, to: &Path) {
+    let Ok(mut shared) = STORE.lock() else {
+        return;
+    };
+    shared.store.record_move(from, to);
+    shared.dirty = true;
+}
+
+/// Write out changes if the last write was a while ago
+pub fn flush_if_due() {
+    let due = STORE
+        .lock()
+        .is_ok_and(|shared| shared.dirty && shared.flushed.elapsed() >= FLUSH_INTERVAL);
+    if due {
+        flush();
+    }
+}
+
+/// Write out changes to the shared store, dropping entries for files that
+/// are gone if that's due
+pub fn flush() {
+    let Ok(mut shared) = STORE.lock() else {
+        return;
+    };
+    shared.flushed = Instant::now();
+    if shared.pruned.elapsed() >= PRUNE_INTERVAL {
+        shared.pruned = Instant::now();
+        let before = shared.store.entries.len();
+        shared.store.prune();
+        shared.dirty |= shared.store.entries.len() != before;
+    }
+    if !shared.dirty {
+        return;
+    }
+    match shared.store.save() {
+        Ok(()) => shared.dirty = false,
+        Err(e) => warn!("{}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_move_keeps_date() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_path = dir.path().join("store.json");
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        std::fs::write(&a, "x").unwrap();
+
+        let mut store = DateAddedStore::load(&store_path);
+        let added = store.get_or_insert(&a);
+        std::fs::rename(&a, &b).unwrap();
+        store.record_move(&a, &b);
+        store.save().unwrap();
+
+        let reloaded = DateAddedStore::load(&store_path);
+        assert_eq!(reloaded.get(&b), Some(added));
+        assert_eq!(reloaded.get(&a), None);
+    }
+}
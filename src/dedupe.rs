@@ -0,0 +1,408 @@
+//! Duplicate files - identical contents found across a folder tree
+//!
+//! `scan` groups the files under a folder by size and hashes only those that
+//! share a size with another, through the hash cache, so scanning an
+//! unchanged tree again reads nothing. Files with the same hash make a
+//! `DuplicateGroup`, which keeps one file (the oldest, unless another is
+//! picked) and resolves the rest: deleted (into the quarantine, if that's
+//! enabled), replaced by hard links to the kept file, or moved to a review
+//! folder. Deletes and moves are recorded in the action history under
+//! `Dedupe`, so `hazelnut undo` can bring the files back.
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::warn;
+
+use crate::rules::{Action, OnConflict};
+
+/// Folder duplicates are moved to, inside the scanned one, by default
+pub const REVIEW_DIR: &str = "_Duplicates";
+
+/// Name resolutions are recorded under in the history
+const LABEL: &str = "Dedupe";
+
+/// What a scan looks at
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Smaller files are skipped (empty files are all "identical")
+    pub min_size: u64,
+    /// Also look at hidden files and folders
+    pub hidden: bool,
+    /// Folders left out, such as the review folder
+    pub exclude: Vec<PathBuf>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            min_size: 1,
+            hidden: false,
+            exclude: Vec::new(),
+        }
+    }
+}
+
+/// Files with identical contents
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateGroup {
+    /// Size of each file
+    pub size: u64,
+    /// Content hash, e.g. `blake3:…`
+    pub hash: String,
+    /// The files, oldest first
+    pub files: Vec<PathBuf>,
+    /// Index in `files` of the one kept
+    pub keep: usize,
+}
+
+impl DuplicateGroup {
+    /// The file kept when the group is resolved
+    pub fn kept(&self) -> &Path {
+        &self.files[self.keep]
+    }
+
+    /// The files a resolution acts on
+    pub fn duplicates(&self) -> impl Iterator<Item = &PathBuf> {
+        self.files
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| *i != self.keep)
+            .map(|(_, path)| path)
+    }
+
+    /// Bytes the duplicates take up
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64 - 1)
+    }
+}
+
+/// What happens to the duplicates in a group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// Delete them
+    Delete,
+    /// Replace them with hard links to the kept file
+    Hardlink,
+    /// Move them to a review folder
+    Move(PathBuf),
+}
+
+impl Resolution {
+    /// Past tense for messages, e.g. "deleted"
+    pub fn verb(&self) -> &'static str {
+        match self {
+            Resolution::Delete => "deleted",
+            Resolution::Hardlink => "hard-linked",
+            Resolution::Move(_) => "moved",
+        }
+    }
+}
+
+/// What resolving groups did
+#[derive(Debug, Default)]
+pub struct ResolveRun {
+    /// Duplicates resolved
+    pub resolved: usize,
+    /// Bytes freed
+    pub freed: u64,
+    /// Duplicates the resolution failed on, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// Where duplicates found under `root` are moved by default
+pub fn review_dir(root: &Path) -> PathBuf {
+    root.join(REVIEW_DIR)
+}
+
+/// Find the groups of identical files under `root`, the largest waste first
+pub fn scan(root: &Path, options: &ScanOptions) -> Result<Vec<DuplicateGroup>> {
+    if !root.is_dir() {
+        bail!("{} is not a folder", root.display());
+    }
+    let mut by_size: HashMap<u64, Vec<(PathBuf, SystemTime)>> = HashMap::new();
+    let mut seen = HashSet::new();
+    collect(root, options, &mut seen, &mut by_size);
+
+    let algorithm = crate::hashing::algorithm();
+    let mut groups = Vec::new();
+    for (size, files) in by_size {
+        if files.len() < 2 {
+            continue;
+        }
+        let mut by_hash: HashMap<String, Vec<(PathBuf, SystemTime)>> = HashMap::new();
+        for (path, modified) in files {
+            match crate::hash_cache::hash_file(&path, algorithm) {
+                Ok(hash) => by_hash.entry(hash).or_default().push((path, modified)),
+                Err(e) => warn!("Failed to hash {}: {}", path.display(), e),
+            }
+        }
+        for (hash, mut files) in by_hash {
+            if files.len() < 2 {
+                continue;
+            }
+            files.sort();
+            files.sort_by_key(|(_, modified)| *modified);
+            groups.push(DuplicateGroup {
+                size,
+                hash: format!("{}:{}", algorithm.name(), hash),
+                files: files.into_iter().map(|(path, _)| path).collect(),
+                keep: 0,
+            });
+        }
+    }
+    groups.sort_by(|a, b| {
+        b.wasted()
+            .cmp(&a.wasted())
+            .then_with(|| a.files[0].cmp(&b.files[0]))
+    });
+    Ok(groups)
+}
+
+/// Add the files under `dir` to `by_size`. Symbolic links are skipped, and
+/// so is a file hard-linked to one already seen: it takes up no more space.
+fn collect(
+    dir: &Path,
+    options: &ScanOptions,
+    seen: &mut HashSet<(u64, u64)>,
+    by_size: &mut HashMap<u64, Vec<(PathBuf, SystemTime)>>,
+) {
+    let Ok(read) = std::fs::read_dir(dir) else {
+        warn!("Can't read {}", dir.display());
+        return;
+    };
+    for entry in read.flatten() {
+        let path = entry.path();
+        if !options.hidden && entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if !options.exclude.contains(&path) {
+                collect(&path, options, seen, by_size);
+            }
+            continue;
+        }
+        if !metadata.is_file() || metadata.len() < options.min_size {
+            continue;
+        }
+        if let Some(id) = file_id(&metadata)
+            && !seen.insert(id)
+        {
+            continue;
+        }
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        by_size
+            .entry(metadata.len())
+            .or_default()
+            .push((path, modified));
+    }
+}
+
+/// Device and inode, which hard links share
+#[cfg(unix)]
+fn file_id(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Resolve the duplicates of each group, keeping its `kept` file. A
+/// duplicate that changed since the scan is left alone, and so is the whole
+/// group if the kept file did.
+pub fn resolve(groups: &[DuplicateGroup], resolution: &Resolution) -> ResolveRun {
+    let mut run = ResolveRun::default();
+    for group in groups {
+        let kept = group.kept();
+        let problem = if !kept.is_file() {
+            Some(format!("{} is gone", kept.display()))
+        } else if !unchanged(group, kept) {
+            Some(format!("{} changed since the scan", kept.display()))
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            for path in group.duplicates() {
+                run.failed.push((path.clone(), problem.clone()));
+            }
+            continue;
+        }
+        for path in group.duplicates() {
+            match resolve_one(group, kept, path, resolution) {
+                Ok(()) => {
+                    run.resolved += 1;
+                    run.freed += group.size;
+                }
+                Err(e) => {
+                    warn!("Couldn't resolve duplicate {}: {:#}", path.display(), e);
+                    run.failed.push((path.clone(), format!("{:#}", e)));
+                }
+            }
+        }
+    }
+    run
+}
+
+fn resolve_one(
+    group: &DuplicateGroup,
+    kept: &Path,
+    path: &Path,
+    resolution: &Resolution,
+) -> Result<()> {
+    if !unchanged(group, path) {
+        bail!("It changed since the scan");
+    }
+
+    let action = match resolution {
+        Resolution::Hardlink => return hard_link(kept, path),
+        Resolution::Delete => Action::Delete,
+        Resolution::Move(review) => Action::Move {
+            destination: review.clone(),
+            create_destination: true,
+            on_conflict: OnConflict::RenameWithSuffix,
+            preserve_metadata: true,
+        },
+    };
+    let result = action.execute_to(path);
+    let conflict = crate::rules::take_conflict();
+    crate::history::record(
+        LABEL,
+        &action,
+        path,
+        &result,
+        Some(&group.hash),
+        conflict.as_deref(),
+    );
+    result.map(|_| ())
+}
+
+/// Whether `path` still has the group's size and hash
+fn unchanged(group: &DuplicateGroup, path: &Path) -> bool {
+    let algorithm = crate::hashing::algorithm();
+    path.metadata().is_ok_and(|m| m.len() == group.size)
+        && crate::hash_cache::hash_file(path, algorithm)
+            .is_ok_and(|hash| format!("{}:{}", algorithm.name(), hash) == group.hash)
+}
+
+/// Replace `path` with a hard link to `kept`, through a temporary link so
+/// the file is never missing
+fn hard_link(kept: &Path, path: &Path) -> Result<()> {
+    let name = path
+        .file_name()
+        .context("The duplicate has no file name")?
+        .to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.hazelnut-link", name));
+    std::fs::hard_link(kept, &temp).with_context(|| {
+        format!(
+            "Failed to link {} to {} (hard links only work within one filesystem)",
+            path.display(),
+            kept.display()
+        )
+    })?;
+    if let Err(e) = std::fs::rename(&temp, path) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e).with_context(|| format!("Failed to replace {}", path.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        for (name, contents) in [
+            ("one.jpg", "photo"),
+            ("a/copy.jpg", "photo"),
+            ("a/b/copy 2.jpg", "photo"),
+            ("other.jpg", "photX"),
+            ("a/notes.txt", "notes!"),
+            ("a/notes copy.txt", "notes!"),
+            (".hidden.jpg", "photo"),
+            ("empty1", ""),
+            ("empty2", ""),
+        ] {
+            std::fs::write(root.join(name), contents).unwrap();
+        }
+        let old = SystemTime::now() - std::time::Duration::from_secs(3600);
+        std::fs::File::open(root.join("a/b/copy 2.jpg"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        let groups = scan(root, &ScanOptions::default()).unwrap();
+        assert_eq!(groups.len(), 2);
+        // The most space wasted first, the oldest file kept
+        assert_eq!(groups[0].wasted(), 10);
+        assert_eq!(groups[0].kept(), root.join("a/b/copy 2.jpg"));
+        assert_eq!(groups[0].files.len(), 3);
+        assert_eq!(
+            groups[1].files,
+            [root.join("a/notes copy.txt"), root.join("a/notes.txt")]
+        );
+
+        let review = review_dir(root);
+        let run = resolve(&groups[..1], &Resolution::Move(review.clone()));
+        assert_eq!((run.resolved, run.freed), (2, 10));
+        assert!(review.join("one.jpg").exists());
+        assert!(review.join("copy.jpg").exists());
+        // The review folder isn't scanned again
+        let options = ScanOptions {
+            exclude: vec![review],
+            ..Default::default()
+        };
+        assert_eq!(scan(root, &options).unwrap().len(), 1);
+
+        // Hard-linked files are no longer duplicates
+        let run = resolve(&groups[1..], &Resolution::Hardlink);
+        assert_eq!(run.resolved, 1);
+        assert!(run.failed.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(root.join("a/notes.txt")).unwrap(),
+            "notes!"
+        );
+        assert!(scan(root, &options).unwrap().is_empty());
+
+        // A file edited since the scan is left alone
+        std::fs::write(root.join("a/copy.jpg"), "photo").unwrap();
+        let groups = scan(root, &options).unwrap();
+        assert_eq!(groups[0].kept(), root.join("a/b/copy 2.jpg"));
+        std::fs::write(root.join("a/copy.jpg"), "edited").unwrap();
+        let run = resolve(&groups, &Resolution::Delete);
+        assert_eq!(run.resolved, 0);
+        assert_eq!(run.failed.len(), 1);
+        assert!(root.join("a/copy.jpg").exists());
+    }
+
+    #[test]
+    fn test_resolve_kept_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(root.join(name), "same").unwrap();
+        }
+        let groups = scan(root, &ScanOptions::default()).unwrap();
+        assert_eq!(groups.len(), 1);
+
+        // The kept file is edited between the scan and resolving
+        std::fs::write(groups[0].kept(), "edit").unwrap();
+        let run = resolve(&groups, &Resolution::Delete);
+        assert_eq!(run.resolved, 0);
+        assert_eq!(run.failed.len(), 2);
+        for path in &groups[0].files {
+            assert!(path.exists());
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Content fingerprints of organized files
+//!
+//! Records a content hash of every file a `move` or `copy` action files away,
+//! so a re-downloaded file can be recognized as byte-identical to something
+//! hazelnut already organized, even under a different name. Hashes are
+//! cached by size and modification time to avoid re-reading unchanged files,
+//! and recomputed when `hash_algorithm` changes.
+//!
+//! Only used when a rule sets `on_reimport`; entries are persisted as JSON
+//! in the data directory.
+
+use crate::hashing::HashAlgorithm;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+use tracing::{debug, warn};
+
+/// Hash of a file at a given size and modification time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CachedHash {
+    size: u64,
+    modified: Option<SystemTime>,
+    hash: String,
+}
+
+/// Persistent map of content hash → files hazelnut filed with that content
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FingerprintStore {
+    /// Filed files by content hash
+    #[serde(default)]
+    filed: HashMap<String, Vec<PathBuf>>,
+
+    /// Last known hash of each filed file
+    #[serde(default)]
+    cache: HashMap<PathBuf, CachedHash>,
+
+    /// Algorithm of the stored hashes (stores without one used SHA-256)
+    #[serde(default = "legacy_algorithm")]
+    algorithm: HashAlgorithm,
+
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+fn legacy_algorithm() -> HashAlgorithm {
+    HashAlgorithm::Sha256
+}
+
+impl FingerprintStore {
+    /// Load the store from a file (missing or unreadable files give an empty store)
+    pub fn load(path: &Path) -> Self {
+        let mut store: Self = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        store.path = Some(path.to_path_buf());
+        store
+    }
+
+    /// Default location: `<data_dir>/hazelnut/fingerprints.json`
+    pub fn default_path() -> Option<PathBuf> {
+        crate::Config::data_dir().map(|d| d.join("fingerprints.json"))
+    }
+
+    /// Write the store back to disk (no-op for in-memory stores)
+    pub fn save(&self) -> Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string(self)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write fingerprint store {}", path.display()))
+    }
+
+    /// Hash of a file, from the cache if its size and modification time are unchanged
+    fn hash(&mut self, path: &Path) -> std::io::Result<String> {
+        let metadata = std::fs::metadata(path)?;
+        let size = metadata.len();
+        let modified = metadata.modified().ok();
+        if let Some(cached) = self.cache.get(path)
+            && cached.size == size
+            && cached.modified == modified
+        {
+            return Ok(cached.hash.clone());
+        }
+        let hash = crate::hash_cache::hash_file(path, self.algorithm)?;
+        self.cache.insert(
+            path.to_path_buf(),
+            CachedHash {
+                size,
+                modified,
+                hash: hash.clone(),
+            },
+        );
+        Ok(hash)
+    }
+
+    /// Rehash the filed files if they were hashed with another algorithm.
+    /// Files that are gone are dropped.
+    pub fn use_algorithm(&mut self, algorithm: HashAlgorithm) {
+        if self.algorithm == algorithm {
+            return;
+        }
+        debug!(
+            "Rehashing {} filed file(s) with {}",
+            self.cache.len(),
+            algorithm.name()
+        );
+        self.algorithm = algorithm;
+        self.filed.clear();
+        let paths: Vec<PathBuf> = self.cache.drain().map(|(path, _)| path).collect();
+        for path in paths {
+            if let Ok(hash) = self.hash(&path) {
+                self.filed.entry(hash).or_default().push(path);
+            }
+        }
+    }
+
+    /// Record a file that was just filed
+    pub fn record(&mut self, path: &Path) -> std::io::Result<()> {
+        self.use_algorithm(crate::hashing::algorithm());
+        self.forget(path);
+        let hash = self.hash(path)?;
+        self.filed.entry(hash).or_default().push(path.to_path_buf());
+        Ok(())
+    }
+
+    /// A previously filed file with exactly the same content as `path`
+    /// (other than `path` itself). Files that were deleted or changed since
+    /// they were filed are dropped.
+    pub fn find_copy(&mut self, path: &Path) -> std::io::Result<Option<PathBuf>> {
+        self.use_algorithm(crate::hashing::algorithm());
+        // Only the shared cache, which rehashes the file once it changes:
+        // incoming files aren't filed
+        let hash = crate::hash_cache::hash_file(path, self.algorithm)?;
+        let Some(candidates) = self.filed.get(&hash).cloned() else {
+            return Ok(None);
+        };
+        for candidate in candidates {
+            if candidate == path {
+                continue;
+            }
+            match self.hash(&candidate) {
+                Ok(current) if current == hash => return Ok(Some(candidate)),
+                _ => {
+                    debug!("Filed copy changed or gone: {}", candidate.display());
+                    self.cache.remove(&candidate);
+                    self.unfile(&hash, &candidate);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Remove a file from the store
+    pub fn forget(&mut self, path: &Path) {
+        if let Some(cached) = self.cache.remove(path) {
+            self.unfile(&cached.hash, path);
+        }
+    }
+
+    fn unfile(&mut self, hash: &str, path: &Path) {
+        if let Some(paths) = self.filed.get_mut(hash) {
+            paths.retain(|p| p != path);
+            if paths.is_empty() {
+                self.filed.remove(hash);
+            }
+        }
+    }
+}
+
+static STORE: LazyLock<Mutex<FingerprintStore>> = LazyLock::new(|| {
+    Mutex::new(
+        FingerprintStore::default_path()
+            .map(|p| FingerprintStore::load(&p))
+            .unwrap_or_default(),
+    )
+});
+
+/// Record a filed file in the shared store
+pub fn record_filed(path: &Path) {
+    let Ok(mut store) = STORE.lock() else {
+        return;
+    };
+    if let Err(e) = store.record(path) {
+        warn!("Failed to fingerprint {}: {}", path.display(), e);
+        return;
+    }
+    if let Err(e) = store.save() {
+        warn!("{}", e);
+    }
+}
+
+/// A previously filed copy of `path` from the shared store
+pub fn find_filed_copy(path: &Path) -> Option<PathBuf> {
+    let mut store = STORE.lock().ok()?;
+    let copy = match store.find_copy(path) {
+        Ok(copy) => copy,
+        Err(e) => {
+            warn!("Failed to fingerprint {}: {}", path.display(), e);
+            None
+        }
+    };
+    if let Err(e) = store.save() {
+        warn!("{}", e);
+    }
+    copy
+}
+
+/// Drop a file that was replaced from the shared store
+pub fn forget(path: &Path) {
+    let Ok(mut store) = STORE.lock() else {
+        return;
+    };
+    store.forget(path);
+    if let Err(e) = store.save() {
+        warn!("{}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_path = dir.path().join("fingerprints.json");
+        let filed = dir.path().join("Archive/invoice.pdf");
+        let incoming = dir.path().join("invoice (1).pdf");
+        let other = dir.path().join("other.pdf");
+        std::fs::create_dir_all(filed.parent().unwrap()).unwrap();
+        std::fs::write(&filed, "same bytes").unwrap();
+        std::fs::write(&incoming, "same bytes").unwrap();
+        std::fs::write(&other, "different").unwrap();
+
+        let mut store = FingerprintStore::load(&store_path);
+        store.record(&filed).unwrap();
+        store.save().unwrap();
+
+        let mut store = FingerprintStore::load(&store_path);
+        assert_eq!(store.find_copy(&incoming).unwrap(), Some(filed.clone()));
+        assert_eq!(store.find_copy(&other).unwrap(), None);
+        // A file is not a copy of itself
+        assert_eq!(store.find_copy(&filed).unwrap(), None);
+
+        // Once the filed copy changes, it no longer counts
+        std::fs::write(&filed, "edited").unwrap();
+        assert_eq!(store.find_copy(&incoming).unwrap(), None);
+        assert!(store.filed.is_empty());
+    }
+
+    #[test]
+    fn test_rehash_on_algorithm_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let filed = dir.path().join("Archive/invoice.pdf");
+        let incoming = dir.path().join("invoice (1).pdf");
+        std::fs::create_dir_all(filed.parent().unwrap()).unwrap();
+        std::fs::write(&filed, "abc").unwrap();
+        std::fs::write(&incoming, "abc").unwrap();
+
+        let mut store = FingerprintStore::default();
+        store.record(&filed).unwrap();
+        store.use_algorithm(HashAlgorithm::Sha256);
+        assert!(
+            store
+                .filed
+                .contains_key("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+
+        // Stores written before the algorithm was configurable are SHA-256
+        let legacy: FingerprintStore = serde_json::from_str("{}").unwrap();
+        assert_eq!(legacy.algorithm, HashAlgorithm::Sha256);
+
+        store.use_algorithm(HashAlgorithm::Blake3);
+        assert!(
+            store
+                .filed
+                .contains_key("6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85")
+        );
+        assert_eq!(store.find_copy(&incoming).unwrap(), Some(filed));
+    }
+}
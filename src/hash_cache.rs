@@ -0,0 +1,307 @@
+//! Persistent cache of file content hashes
+//!
+//! Hashing a large file means reading all of it, and rescans of big folders
+//! would otherwise hash the same unchanged files again and again (for
+//! `on_reimport` fingerprints and history checksums). Hashes are kept in
+//! `hash_cache.db` in the data directory, keyed by path, size and
+//! modification time, so a file is read again only once it changes. The
+//! least recently used entries are dropped past `MAX_ENTRIES`.
+//!
+//! `hazelnut cache stats` shows how well it works and `hazelnut cache
+//! clear` empties it.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+use crate::hashing::HashAlgorithm;
+
+/// Entries kept before the least recently used are dropped
+const MAX_ENTRIES: i64 = 1_000_000;
+
+/// Misses between checks for too many entries
+const PRUNE_EVERY: i64 = 1000;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS hashes (
+        path TEXT NOT NULL,
+        algorithm TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        modified INTEGER NOT NULL,      -- mtime in ns
+        hash TEXT NOT NULL,
+        used INTEGER NOT NULL,          -- Unix time in seconds
+        PRIMARY KEY (path, algorithm)
+    );
+    CREATE INDEX IF NOT EXISTS hashes_used ON hashes (used);
+    CREATE TABLE IF NOT EXISTS counters (
+        name TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+";
+
+/// How much the cache holds and how often it helped
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CacheStats {
+    /// Cached hashes
+    pub entries: u64,
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that had to read the file
+    pub misses: u64,
+    /// Size of the database on disk
+    pub bytes: u64,
+}
+
+impl CacheStats {
+    /// Share of lookups answered from the cache, in percent
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 * 100.0 / total as f64,
+        }
+    }
+}
+
+/// The hash cache database
+pub struct HashCache {
+    conn: Mutex<Connection>,
+    path: PathBuf,
+}
+
+impl HashCache {
+    /// Open the database, creating it if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open hash cache {}", path.display()))?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        // The daemon and the CLI may hash at the same time
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        conn.execute_batch(SCHEMA)
+            .with_context(|| format!("Failed to set up hash cache {}", path.display()))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Default location: `<data_dir>/hazelnut/hash_cache.db`
+    pub fn default_path() -> Option<PathBuf> {
+        crate::Config::data_dir().map(|d| d.join("hash_cache.db"))
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Hex-encoded hash of a file's contents, from the cache if its size and
+    /// modification time haven't changed since it was last hashed
+    pub fn hash(&self, path: &Path, algorithm: HashAlgorithm) -> std::io::Result<String> {
+        let metadata = std::fs::metadata(path)?;
+        let size = metadata.len() as i64;
+        let Some(modified) = metadata.modified().ok().and_then(nanos) else {
+            return crate::hashing::hash_file(path, algorithm);
+        };
+        let key = path.to_string_lossy();
+
+        match self.lookup(&key, algorithm, size, modified) {
+            Ok(Some(hash)) => return Ok(hash),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read hash cache: {:#}", e),
+        }
+        let hash = crate::hashing::hash_file(path, algorithm)?;
+        if let Err(e) = self.store(&key, algorithm, size, modified, &hash) {
+            warn!("Failed to update hash cache: {:#}", e);
+        }
+        Ok(hash)
+    }
+
+    fn lookup(
+        &self,
+        key: &str,
+        algorithm: HashAlgorithm,
+        size: i64,
+        modified: i64,
+    ) -> Result<Option<String>> {
+        let conn = self.conn();
+        let hash: Option<String> = conn
+            .query_row(
+                "SELECT hash FROM hashes
+                 WHERE path = ?1 AND algorithm = ?2 AND size = ?3 AND modified = ?4",
+                params![key, algorithm.name(), size, modified],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if hash.is_some() {
+            conn.execute(
+                "UPDATE hashes SET used = ?3 WHERE path = ?1 AND algorithm = ?2",
+                params![key, algorithm.name(), now()],
+            )?;
+            count(&conn, "hits")?;
+        }
+        Ok(hash)
+    }
+
+    fn store(
+        &self,
+        key: &str,
+        algorithm: HashAlgorithm,
+        size: i64,
+        modified: i64,
+        hash: &str,
+    ) -> Result<()> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT OR REPLACE INTO hashes (path, algorithm, size, modified, hash, used)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![key, algorithm.name(), size, modified, hash, now()],
+        )?;
+        if count(&conn, "misses")? % PRUNE_EVERY == 0 {
+            conn.execute(
+                "DELETE FROM hashes WHERE rowid IN
+                 (SELECT rowid FROM hashes ORDER BY used DESC LIMIT -1 OFFSET ?1)",
+                params![MAX_ENTRIES],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Number of entries and lookups so far
+    pub fn stats(&self) -> Result<CacheStats> {
+        let conn = self.conn();
+        let counter = |name: &str| -> Result<u64> {
+            let value: Option<i64> = conn
+                .query_row(
+                    "SELECT value FROM counters WHERE name = ?1",
+                    params![name],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(value.unwrap_or(0) as u64)
+        };
+        let entries: i64 = conn.query_row("SELECT COUNT(*) FROM hashes", [], |row| row.get(0))?;
+        let bytes = ["", "-wal"]
+            .iter()
+            .filter_map(|suffix| {
+                let mut name = self.path.clone().into_os_string();
+                name.push(suffix);
+                std::fs::metadata(name).ok()
+            })
+            .map(|m| m.len())
+            .sum();
+        Ok(CacheStats {
+            entries: entries as u64,
+            hits: counter("hits")?,
+            misses: counter("misses")?,
+            bytes,
+        })
+    }
+
+    /// Drop every entry and reset the counters, returning how many entries
+    /// there were
+    pub fn clear(&self) -> Result<usize> {
+        let conn = self.conn();
+        let removed = conn.execute("DELETE FROM hashes", [])?;
+        conn.execute("DELETE FROM counters", [])?;
+        conn.execute_batch("VACUUM")?;
+        Ok(removed)
+    }
+}
+
+/// Add one to a counter, returning its new value
+fn count(conn: &Connection, name: &str) -> Result<i64> {
+    Ok(conn.query_row(
+        "INSERT INTO counters (name, value) VALUES (?1, 1)
+         ON CONFLICT (name) DO UPDATE SET value = value + 1
+         RETURNING value",
+        params![name],
+        |row| row.get(0),
+    )?)
+}
+
+fn nanos(time: SystemTime) -> Option<i64> {
+    let since = time.duration_since(SystemTime::UNIX_EPOCH).ok()?;
+    i64::try_from(since.as_nanos()).ok()
+}
+
+fn now() -> i64 {
+    chrono::Local::now().timestamp()
+}
+
+static SHARED: OnceLock<Option<HashCache>> = OnceLock::new();
+
+/// The cache in the data directory, opened on first use
+fn shared() -> Option<&'static HashCache> {
+    SHARED
+        .get_or_init(|| {
+            let path = HashCache::default_path()?;
+            HashCache::open(&path)
+                .inspect_err(|e| warn!("{:#}", e))
+                .ok()
+        })
+        .as_ref()
+}
+
+/// Hex-encoded hash of a file's contents through the shared cache (hashed
+/// directly if the cache can't be opened)
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> std::io::Result<String> {
+    match shared() {
+        Some(cache) => cache.hash(path, algorithm),
+        None => crate::hashing::hash_file(path, algorithm),
+    }
+}
+
+/// Statistics of the shared cache
+pub fn stats() -> Result<CacheStats> {
+    shared()
+        .context("No data directory for the hash cache")?
+        .stats()
+}
+
+/// Empty the shared cache
+pub fn clear() -> Result<usize> {
+    shared()
+        .context("No data directory for the hash cache")?
+        .clear()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_until_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = HashCache::open(&dir.path().join("hash_cache.db")).unwrap();
+        let file = dir.path().join("big.iso");
+        std::fs::write(&file, "first").unwrap();
+
+        let first = cache.hash(&file, HashAlgorithm::Blake3).unwrap();
+        assert_eq!(
+            first,
+            crate::hashing::hash_file(&file, HashAlgorithm::Blake3).unwrap()
+        );
+        assert_eq!(cache.hash(&file, HashAlgorithm::Blake3).unwrap(), first);
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 1, 1));
+        assert_eq!(stats.hit_rate(), 50.0);
+
+        // Another size (or mtime) means the file is read again
+        std::fs::write(&file, "second").unwrap();
+        assert_ne!(cache.hash(&file, HashAlgorithm::Blake3).unwrap(), first);
+        // Each algorithm has its own entry
+        cache.hash(&file, HashAlgorithm::Sha256).unwrap();
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (2, 1, 3));
+
+        assert_eq!(cache.clear().unwrap(), 2);
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (0, 0, 0));
+    }
+}
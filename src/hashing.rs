@@ -0,0 +1,216 @@
+//! File content hashing
+//!
+//! BLAKE3 is the default: it's fast, and large files are hashed on several
+//! threads. SHA-256 is available where hashes must match standard tools such
+//! as `sha256sum`. Both are set with `hash_algorithm` and `hash_threads` in
+//! `[general]`.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Files at least this large are hashed on several threads (BLAKE3 only)
+const PARALLEL_MIN_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Read size when hashing on one thread
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Read size when hashing in parallel; each read is split across the threads
+const PARALLEL_BUFFER_SIZE: usize = 8 * 1024 * 1024;
+
+/// Hash function for file contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// BLAKE3, parallel on large files
+    #[default]
+    Blake3,
+    /// SHA-256, compatible with `sha256sum` and audit tooling
+    Sha256,
+}
+
+impl HashAlgorithm {
+    /// Name as written in the config
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    /// The algorithm a name from [`name`](Self::name) stands for
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Blake3, Self::Sha256]
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+    }
+}
+
+static USE_SHA256: AtomicBool = AtomicBool::new(false);
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Thread pool for a `hash_threads` setting other than 0 (all cores, the
+/// global pool) or 1 (no pool), kept until the setting changes
+static POOL: Mutex<Option<SizedPool>> = Mutex::new(None);
+
+/// A thread pool and its number of threads
+type SizedPool = (usize, Arc<rayon::ThreadPool>);
+
+/// Set the configured algorithm and the threads used by `hash_file`
+pub fn init(algorithm: HashAlgorithm, threads: usize) {
+    USE_SHA256.store(algorithm == HashAlgorithm::Sha256, Ordering::Relaxed);
+    THREADS.store(threads, Ordering::Relaxed);
+}
+
+/// The configured algorithm
+pub fn algorithm() -> HashAlgorithm {
+    if USE_SHA256.load(Ordering::Relaxed) {
+        HashAlgorithm::Sha256
+    } else {
+        HashAlgorithm::Blake3
+    }
+}
+
+/// Hex-encoded hash of a file's contents, using the configured threads
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> std::io::Result<String> {
+    hash_file_with(path, algorithm, THREADS.load(Ordering::Relaxed))
+}
+
+/// Hex-encoded hash of a file's contents. `threads` limits parallel hashing
+/// of large files (0 = all cores, 1 = single-threaded).
+pub fn hash_file_with(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    threads: usize,
+) -> std::io::Result<String> {
+    hash_file_inner(path, algorithm, threads, PARALLEL_MIN_SIZE)
+}
+
+fn hash_file_inner(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    threads: usize,
+    parallel_min_size: u64,
+) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    match algorithm {
+        HashAlgorithm::Sha256 => {
+            use sha2::{Digest, Sha256};
+            let mut hasher = Sha256::new();
+            read_chunks(&mut file, BUFFER_SIZE, |chunk| hasher.update(chunk))?;
+            Ok(hasher
+                .finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect())
+        }
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            let parallel = threads != 1 && file.metadata()?.len() >= parallel_min_size;
+            if parallel {
+                let pool = pool(threads);
+                read_chunks(&mut file, PARALLEL_BUFFER_SIZE, |chunk| match &pool {
+                    Some(pool) => pool.install(|| {
+                        hasher.update_rayon(chunk);
+                    }),
+                    None => {
+                        hasher.update_rayon(chunk);
+                    }
+                })?;
+            } else {
+                read_chunks(&mut file, BUFFER_SIZE, |chunk| {
+                    hasher.update(chunk);
+                })?;
+            }
+            Ok(hasher.finalize().to_hex().to_string())
+        }
+    }
+}
+
+/// Feed a reader to `update` in chunks of up to `size` bytes
+fn read_chunks(
+    reader: &mut impl Read,
+    size: usize,
+    mut update: impl FnMut(&[u8]),
+) -> std::io::Result<()> {
+    let mut buffer = vec![0u8; size];
+    loop {
+        // Fill the buffer, so parallel hashing gets large chunks
+        let mut filled = 0;
+        while filled < size {
+            match reader.read(&mut buffer[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if filled == 0 {
+            return Ok(());
+        }
+        update(&buffer[..filled]);
+        if filled < size {
+            return Ok(());
+        }
+    }
+}
+
+/// Pool for `threads` hashing threads (None = use the global pool)
+fn pool(threads: usize) -> Option<Arc<rayon::ThreadPool>> {
+    if threads == 0 {
+        return None;
+    }
+    let mut cached = POOL.lock().ok()?;
+    if let Some((n, pool)) = cached.as_ref()
+        && *n == threads
+    {
+        return Some(Arc::clone(pool));
+    }
+    let pool = Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("hazelnut-hash-{i}"))
+            .build()
+            .ok()?,
+    );
+    *cached = Some((threads, Arc::clone(&pool)));
+    Some(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc.txt");
+        std::fs::write(&path, "abc").unwrap();
+
+        assert_eq!(
+            hash_file_with(&path, HashAlgorithm::Sha256, 0).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash_file_with(&path, HashAlgorithm::Blake3, 0).unwrap(),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+
+        // Parallel hashing gives the same result as a single thread
+        let big = dir.path().join("big.bin");
+        let content: Vec<u8> = (0..3 * PARALLEL_BUFFER_SIZE / 2)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        std::fs::write(&big, &content).unwrap();
+        let expected = blake3::hash(&content).to_hex().to_string();
+        for threads in [0, 1, 2] {
+            assert_eq!(
+                hash_file_inner(&big, HashAlgorithm::Blake3, threads, 0).unwrap(),
+                expected
+            );
+        }
+    }
+}
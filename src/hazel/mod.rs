@@ -0,0 +1,744 @@
+//! Importing rules from Hazel
+//!
+//! Hazel exports rules as `.hazelrules` files: property lists, usually
+//! binary `NSKeyedArchiver` archives. Their layout isn't documented and
+//! shifts between Hazel versions, so the importer goes by shape rather than
+//! exact keys: anything with both conditions and actions is taken as a
+//! rule. Whatever can't be translated faithfully is reported instead of
+//! guessed, and rules with untranslated conditions are imported disabled.
+
+pub mod plist;
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::rules::{Action, Condition, OnConflict, Rule};
+use plist::Value;
+
+/// Rules read from a Hazel export
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub rules: Vec<Rule>,
+    /// Conditions and actions that couldn't be translated
+    pub notes: Vec<Note>,
+}
+
+/// Something about a Hazel rule the import couldn't carry over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    /// Name of the Hazel rule
+    pub rule: String,
+    pub message: String,
+}
+
+/// Read a `.hazelrules` file (or any plist holding Hazel rules). Names
+/// already taken in `existing` get a number appended.
+pub fn import(path: &Path, existing: &[Rule]) -> Result<Imported> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    from_bytes(&bytes, existing).with_context(|| format!("Failed to import {}", path.display()))
+}
+
+/// Translate the rules in a property list
+pub fn from_bytes(bytes: &[u8], existing: &[Rule]) -> Result<Imported> {
+    let root = plist::parse(bytes)?;
+    let mut found = Vec::new();
+    find_rules(&root, &mut found);
+    if found.is_empty() {
+        anyhow::bail!("No rules found; is this a Hazel rule export?");
+    }
+
+    let mut imported = Imported::default();
+    for (i, dict) in found.into_iter().enumerate() {
+        let name = get(dict, &["name", "displayname", "title", "rulename"])
+            .and_then(Value::as_str)
+            .filter(|n| !n.trim().is_empty())
+            .map(|n| n.trim().to_string())
+            .unwrap_or_else(|| format!("Hazel rule {}", i + 1));
+        let mut notes = Vec::new();
+        let (condition, complete) = match translate_conditions(dict, &mut notes) {
+            Ok(translated) => translated,
+            Err(reason) => {
+                imported.notes.push(Note {
+                    rule: name,
+                    message: format!("skipped: {}", reason),
+                });
+                continue;
+            }
+        };
+        let action = translate_actions(dict, &mut notes);
+        imported.notes.extend(notes.into_iter().map(|message| Note {
+            rule: name.clone(),
+            message,
+        }));
+        let Some(action) = action else {
+            imported.notes.push(Note {
+                rule: name,
+                message: "skipped: none of its actions could be imported".to_string(),
+            });
+            continue;
+        };
+        let enabled = get(dict, &["enabled", "isenabled", "active"])
+            .and_then(Value::as_bool)
+            .unwrap_or(true);
+        let mut rule = Rule::new(name.clone(), condition, action);
+        rule.enabled = enabled && complete;
+        if enabled && !complete {
+            imported.notes.push(Note {
+                rule: name,
+                message: "imported disabled until its conditions are reviewed".to_string(),
+            });
+        }
+        imported.rules.push(rule);
+    }
+    imported.rules = crate::rules::unique_names(imported.rules, existing);
+    Ok(imported)
+}
+
+type Dict = BTreeMap<String, Value>;
+
+const CONDITION_KEYS: &[&str] = &["conditions", "condition", "criteria", "predicate"];
+const ACTION_KEYS: &[&str] = &["actions", "action"];
+
+/// Collect every dictionary that has both conditions and actions
+fn find_rules<'a>(value: &'a Value, found: &mut Vec<&'a Dict>) {
+    match value {
+        Value::Dict(dict) => {
+            if get(dict, CONDITION_KEYS).is_some() && get(dict, ACTION_KEYS).is_some() {
+                found.push(dict);
+            } else {
+                dict.values().for_each(|v| find_rules(v, found));
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| find_rules(v, found)),
+        _ => {}
+    }
+}
+
+/// Lowercase letters and digits only, so `actionType`, `action_type` and
+/// `Action Type` compare equal
+fn normalize(key: &str) -> String {
+    key.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// The first field whose normalized key is one of `keys`
+fn get<'a>(dict: &'a Dict, keys: &[&str]) -> Option<&'a Value> {
+    keys.iter()
+        .find_map(|key| dict.iter().find(|(k, _)| normalize(k) == *key))
+        .map(|(_, v)| v)
+        .filter(|v| **v != Value::Null)
+}
+
+fn get_str<'a>(dict: &'a Dict, keys: &[&str]) -> Option<&'a str> {
+    get(dict, keys).and_then(Value::as_str)
+}
+
+/// A list of dictionaries, or a single one
+fn dicts(value: &Value) -> Vec<&Dict> {
+    match value {
+        Value::Array(items) => items.iter().filter_map(Value::as_dict).collect(),
+        Value::Dict(dict) => vec![dict],
+        _ => Vec::new(),
+    }
+}
+
+const GROUP_KEYS: &[&str] = &["conditions", "subconditions", "subpredicates", "criteria"];
+
+/// Build one condition from all of a rule's Hazel conditions. Returns
+/// whether every one of them was translated, or why none can be.
+fn translate_conditions(rule: &Dict, notes: &mut Vec<String>) -> Result<(Condition, bool), String> {
+    let mut condition = Condition::default();
+    let value = get(rule, CONDITION_KEYS).unwrap_or(&Value::Null);
+
+    // Either a list of conditions, or a group holding one plus how to combine them
+    let (items, matching) = match value.as_dict() {
+        Some(group) if get(group, GROUP_KEYS).is_some() => (
+            dicts(get(group, GROUP_KEYS).unwrap_or(&Value::Null)),
+            get(
+                group,
+                &["matchtype", "match", "compoundtype", "logicaltype"],
+            ),
+        ),
+        _ => (dicts(value), None),
+    };
+    let matching = match matching {
+        Some(Value::String(s)) => match normalize(s).as_str() {
+            "any" | "or" => "any",
+            "none" | "not" => "none",
+            _ => "all",
+        },
+        // NSCompoundPredicate's types
+        Some(Value::Integer(0)) => "none",
+        Some(Value::Integer(2)) => "any",
+        _ => "all",
+    };
+    if matching == "none" || (matching == "any" && items.len() > 1) {
+        return Err(format!(
+            "it matches {} of its conditions; hazelnut rules need all to match",
+            matching
+        ));
+    }
+
+    let mut complete = true;
+    for item in items {
+        if let Err(reason) = translate_condition(item, &mut condition) {
+            notes.push(format!("condition {}", reason));
+            complete = false;
+        }
+    }
+    Ok((condition, complete))
+}
+
+/// Comparisons found in Hazel conditions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Is,
+    Contains,
+    StartsWith,
+    EndsWith,
+    Greater,
+    Less,
+    InLast,
+    NotInLast,
+    Other,
+}
+
+fn operator(text: &str) -> Op {
+    match normalize(text).as_str() {
+        "isinthelast" | "inthelast" | "iswithinthelast" | "withinthelast" => Op::InLast,
+        "isnotinthelast" | "notinthelast" => Op::NotInLast,
+        "is" | "equals" | "equalto" | "isequalto" => Op::Is,
+        "contains" => Op::Contains,
+        "startswith" | "beginswith" => Op::StartsWith,
+        "endswith" => Op::EndsWith,
+        "isgreaterthan" | "greaterthan" => Op::Greater,
+        "islessthan" | "lessthan" => Op::Less,
+        _ => Op::Other,
+    }
+}
+
+/// Add one Hazel condition to `condition`, or say why it can't be
+fn translate_condition(item: &Dict, condition: &mut Condition) -> Result<(), String> {
+    if get(item, GROUP_KEYS).is_some() {
+        return Err("group: nested condition groups aren't supported".to_string());
+    }
+    let attribute = get_str(
+        item,
+        &["attribute", "attributename", "attributetype", "key"],
+    )
+    .unwrap_or_default();
+    let op_text = get_str(item, &["operator", "operatortype", "comparison"]).unwrap_or_default();
+    let op = operator(op_text);
+    let value = get(item, &["value", "argument", "operand", "amount"]);
+    let text = value.and_then(Value::as_str).unwrap_or_default().trim();
+    let unit_text = get_str(item, &["unit", "units", "timeunit", "sizeunit"]).unwrap_or_default();
+    let unit = normalize(unit_text);
+    let shown = match value {
+        Some(Value::Integer(n)) => n.to_string(),
+        Some(Value::Real(n)) => n.to_string(),
+        _ => text.to_string(),
+    };
+    let describe = [attribute, op_text, &shown, unit_text]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let describe = format!("\"{}\"", describe);
+    let unsupported = |why: &str| Err(format!("{}: {}", describe, why));
+
+    let attribute = normalize(attribute);
+    match attribute.as_str() {
+        "name" | "filename" | "fullname" | "namewithextension" => {
+            if condition.name_regex.is_some() {
+                return unsupported("only one name condition per rule can be imported");
+            }
+            if text.is_empty() {
+                return unsupported("no text to match");
+            }
+            let escaped = regex::escape(text);
+            // Hazel's "Name" leaves out the extension
+            let rest = if attribute.contains("full") || attribute.contains("extension") {
+                "$"
+            } else {
+                r"(\.[^.]*)?$"
+            };
+            condition.name_regex = Some(match op {
+                Op::Is => format!("(?i)^{}{}", escaped, rest),
+                Op::Contains => format!("(?i){}", escaped),
+                Op::StartsWith => format!("(?i)^{}", escaped),
+                Op::EndsWith => format!("(?i){}{}", escaped, rest),
+                _ => {
+                    return unsupported(
+                        "only is, contains, starts with and ends with are supported",
+                    );
+                }
+            });
+        }
+        "extension" | "fileextension" => {
+            if op != Op::Is || text.is_empty() {
+                return unsupported("only \"extension is\" is supported");
+            }
+            condition.extension = Some(text.trim_start_matches('.').to_lowercase());
+        }
+        "kind" | "filekind" => {
+            if op != Op::Is {
+                return unsupported("only \"kind is\" is supported");
+            }
+            let extensions: &[&str] = match normalize(text).as_str() {
+                "folder" => {
+                    condition.is_directory = Some(true);
+                    return Ok(());
+                }
+                "image" | "picture" => {
+                    &["png", "jpg", "jpeg", "gif", "heic", "webp", "tiff", "bmp"]
+                }
+                "movie" | "video" => &["mp4", "mov", "m4v", "mkv", "avi", "webm"],
+                "music" | "audio" => &["mp3", "m4a", "aac", "flac", "wav", "ogg"],
+                "pdf" => &["pdf"],
+                "text" => &["txt", "md", "rtf"],
+                "archive" => &["zip", "tar", "gz", "tgz", "bz2", "xz", "7z", "rar"],
+                _ => return unsupported("this kind has no hazelnut equivalent"),
+            };
+            if !condition.extensions.is_empty() {
+                return unsupported("only one kind condition per rule can be imported");
+            }
+            condition.extensions = extensions.iter().map(|e| e.to_string()).collect();
+        }
+        "size" | "filesize" => {
+            let Some(amount) = value.and_then(Value::as_f64) else {
+                return unsupported("no size to compare with");
+            };
+            let scale = match unit.as_str() {
+                "" | "b" | "bytes" => 1.0,
+                "kb" | "kilobytes" => 1e3,
+                "mb" | "megabytes" => 1e6,
+                "gb" | "gigabytes" => 1e9,
+                "tb" | "terabytes" => 1e12,
+                _ => return unsupported("unknown size unit"),
+            };
+            let bytes = (amount * scale).round() as u64;
+            match op {
+                Op::Greater => condition.size_greater_than = Some(bytes),
+                Op::Less => condition.size_less_than = Some(bytes),
+                _ => return unsupported("only greater than and less than are supported"),
+            }
+        }
+        a if a.starts_with("date") || a.ends_with("date") || a == "lastopened" => {
+            let Some(amount) = value.and_then(Value::as_f64) else {
+                return unsupported(
+                    "only \"is in the last\" and \"is not in the last\" are supported",
+                );
+            };
+            let days = match unit.as_str() {
+                "" | "day" | "days" => amount,
+                "week" | "weeks" => amount * 7.0,
+                "month" | "months" => amount * 30.0,
+                "year" | "years" => amount * 365.0,
+                _ => return unsupported("ages are whole days in hazelnut"),
+            }
+            .round() as u64;
+            match op {
+                Op::InLast => condition.age_days_less_than = Some(days),
+                Op::NotInLast => condition.age_days_greater_than = Some(days),
+                _ => {
+                    return unsupported(
+                        "only \"is in the last\" and \"is not in the last\" are supported",
+                    );
+                }
+            }
+            // Kept, but left for review: the rule would act on a different date
+            if !a.contains("modified") {
+                return Err(format!(
+                    "{}: imported as an age, which hazelnut measures from the modification date",
+                    describe
+                ));
+            }
+        }
+        "hidden" | "ishidden" => {
+            condition.is_hidden = Some(value.and_then(Value::as_bool).unwrap_or(true));
+        }
+        _ => return unsupported("no hazelnut equivalent"),
+    }
+    Ok(())
+}
+
+/// The first of a rule's Hazel actions that hazelnut can perform
+fn translate_actions(rule: &Dict, notes: &mut Vec<String>) -> Option<Action> {
+    let mut translated = None;
+    for item in dicts(get(rule, ACTION_KEYS).unwrap_or(&Value::Null)) {
+        let kind = get_str(
+            item,
+            &["$class", "type", "actiontype", "kind", "identifier"],
+        )
+        .unwrap_or_default();
+        match translate_action(item, &normalize(kind)) {
+            Ok(action) if translated.is_none() => translated = Some(action),
+            Ok(action) => notes.push(format!(
+                "action \"{}\": hazelnut rules have one action; add another {} rule for it",
+                kind,
+                action.kind()
+            )),
+            Err(reason) => notes.push(format!("action \"{}\": {}", kind, reason)),
+        }
+    }
+    translated
+}
+
+fn translate_action(item: &Dict, kind: &str) -> Result<Action, String> {
+    let destination = || {
+        get(
+            item,
+            &[
+                "destination",
+                "destinationfolder",
+                "folder",
+                "target",
+                "targetfolder",
+                "directory",
+                "path",
+                "url",
+                "alias",
+                "bookmark",
+            ],
+        )
+        .and_then(path_of)
+        .ok_or_else(|| "its folder couldn't be read from the export".to_string())
+    };
+    if ["applescript", "javascript", "automator", "workflow"]
+        .iter()
+        .any(|k| kind.contains(k))
+    {
+        return Err("only shell scripts can be run".to_string());
+    }
+    if kind.contains("trash") {
+        Ok(Action::Trash)
+    } else if kind.contains("delete") {
+        Ok(Action::Delete)
+    } else if kind.contains("copy") {
+        Ok(Action::Copy {
+            destination: destination()?,
+            create_destination: true,
+            on_conflict: OnConflict::Fail,
+            preserve_metadata: true,
+        })
+    } else if kind.contains("move") {
+        Ok(Action::Move {
+            destination: destination()?,
+            create_destination: true,
+            on_conflict: OnConflict::Fail,
+            preserve_metadata: true,
+        })
+    } else if kind.contains("shell") || kind.contains("script") {
+        if let Some(script) = get_str(item, &["script", "scriptsource", "source", "code", "text"]) {
+            let shell = get_str(item, &["shell", "interpreter"])
+                .filter(|s| s.starts_with('/'))
+                .unwrap_or("sh");
+            Ok(Action::Run {
+                command: shell.to_string(),
+                args: vec![
+                    "-c".to_string(),
+                    script.to_string(),
+                    "sh".to_string(),
+                    "{path}".to_string(),
+                ],
+            })
+        } else {
+            let script = get(item, &["scriptfile", "scriptpath", "file", "path", "url"])
+                .and_then(path_of)
+                .ok_or_else(|| "its script couldn't be read from the export".to_string())?;
+            Ok(Action::Run {
+                command: script.to_string_lossy().into_owned(),
+                args: vec!["{path}".to_string()],
+            })
+        }
+    } else if kind.contains("archive") && !kind.contains("unarchive") {
+        Ok(Action::Archive {
+            destination: None,
+            delete_original: false,
+        })
+    } else {
+        Err("no hazelnut equivalent".to_string())
+    }
+}
+
+/// A path from a string, file URL, bookmark, or an object holding one
+fn path_of(value: &Value) -> Option<PathBuf> {
+    match value {
+        Value::String(s) => {
+            let s = s.trim();
+            let path = s.strip_prefix("file://").map(percent_decode);
+            let path = path.unwrap_or_else(|| s.to_string());
+            (path.starts_with('/') || path.starts_with('~')).then(|| PathBuf::from(path))
+        }
+        Value::Data(data) => bookmark_path(data),
+        Value::Array(items) => items.iter().find_map(path_of),
+        Value::Dict(dict) => dict.values().find_map(path_of),
+        _ => None,
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// The path stored in macOS bookmark data (how Hazel remembers folders)
+fn bookmark_path(data: &[u8]) -> Option<PathBuf> {
+    if !data.starts_with(b"book") {
+        return None;
+    }
+    let u32_at = |at: usize| {
+        data.get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+    };
+    // Offsets inside the bookmark count from the end of its header
+    let base = u32_at(12)?;
+    let toc = base + u32_at(base)?;
+    if u32_at(toc + 4)? != 0xffff_fffe {
+        return None;
+    }
+    let count = u32_at(toc + 16)?;
+    for i in 0..count.min(256) {
+        let entry = toc + 20 + i * 12;
+        // 0x1004 is the path, as an array of component strings
+        if u32_at(entry)? != 0x1004 {
+            continue;
+        }
+        let item = base + u32_at(entry + 4)?;
+        if u32_at(item + 4)? != 0x0601 {
+            return None;
+        }
+        let mut path = PathBuf::from("/");
+        for j in 0..u32_at(item)? / 4 {
+            let component = base + u32_at(item + 8 + j * 4)?;
+            if u32_at(component + 4)? != 0x0101 {
+                return None;
+            }
+            let len = u32_at(component)?;
+            path.push(std::str::from_utf8(data.get(component + 8..component + 8 + len)?).ok()?);
+        }
+        return Some(path);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+  <key>rules</key>
+  <array>
+    <dict>
+      <key>name</key><string>Invoices</string>
+      <key>conditions</key>
+      <dict>
+        <key>matchType</key><string>all</string>
+        <key>conditions</key>
+        <array>
+          <dict>
+            <key>attribute</key><string>Name</string>
+            <key>operator</key><string>contains</string>
+            <key>value</key><string>invoice (1)</string>
+          </dict>
+          <dict>
+            <key>attribute</key><string>Extension</string>
+            <key>operator</key><string>is</string>
+            <key>value</key><string>.PDF</string>
+          </dict>
+          <dict>
+            <key>attribute</key><string>Date Last Modified</string>
+            <key>operator</key><string>is not in the last</string>
+            <key>value</key><integer>2</integer>
+            <key>unit</key><string>weeks</string>
+          </dict>
+        </array>
+      </dict>
+      <key>actions</key>
+      <array>
+        <dict>
+          <key>type</key><string>Move</string>
+          <key>destination</key><string>file:///Users/me/Documents/My%20Invoices/</string>
+        </dict>
+        <dict><key>type</key><string>Add tags</string></dict>
+      </array>
+    </dict>
+    <dict>
+      <key>name</key><string>Invoices</string>
+      <key>conditions</key>
+      <array>
+        <dict>
+          <key>attribute</key><string>Kind</string>
+          <key>operator</key><string>is</string>
+          <key>value</key><string>Image</string>
+        </dict>
+        <dict>
+          <key>attribute</key><string>Color label</string>
+          <key>operator</key><string>is</string>
+          <key>value</key><string>Red</string>
+        </dict>
+      </array>
+      <key>actions</key>
+      <dict><key>type</key><string>Move to Trash</string></dict>
+    </dict>
+    <dict>
+      <key>name</key><string>Tagger</string>
+      <key>conditions</key><array/>
+      <key>actions</key>
+      <array><dict><key>type</key><string>Run AppleScript</string></dict></array>
+    </dict>
+  </array>
+</dict>
+</plist>"#;
+        let imported = from_bytes(xml.as_bytes(), &[]).unwrap();
+        assert_eq!(imported.rules.len(), 2);
+
+        let invoices = &imported.rules[0];
+        assert!(invoices.enabled);
+        assert_eq!(
+            invoices.condition.name_regex.as_deref(),
+            Some(r"(?i)invoice \(1\)")
+        );
+        assert_eq!(invoices.condition.extension.as_deref(), Some("pdf"));
+        assert_eq!(invoices.condition.age_days_greater_than, Some(14));
+        assert_eq!(
+            invoices.action.destination(),
+            Some(PathBuf::from("/Users/me/Documents/My Invoices/"))
+        );
+
+        // Untranslatable conditions leave the rule disabled, and names stay unique
+        let images = &imported.rules[1];
+        assert_eq!(images.name, "Invoices (2)");
+        assert!(!images.enabled);
+        assert_eq!(images.condition.extensions.len(), 8);
+        assert!(matches!(images.action, Action::Trash));
+
+        let notes: Vec<_> = imported.notes.iter().map(|n| n.rule.as_str()).collect();
+        assert_eq!(
+            notes,
+            ["Invoices", "Invoices", "Invoices", "Tagger", "Tagger"]
+        );
+        assert!(imported.notes[1].message.contains("Color label"));
+
+        assert!(from_bytes(b"<plist><dict/></plist>", &[]).is_err());
+    }
+
+    #[test]
+    fn test_import_groups() {
+        let rule = |name: &str, conditions: &str, action: &str| {
+            format!(
+                "<dict><key>name</key><string>{}</string>\
+                 <key>conditions</key>{}\
+                 <key>actions</key><array><dict><key>type</key><string>{}</string></dict></array>\
+                 </dict>",
+                name, conditions, action
+            )
+        };
+        let extension = |ext: &str| {
+            format!(
+                "<dict><key>attribute</key><string>Extension</string>\
+                 <key>operator</key><string>is</string>\
+                 <key>value</key><string>{}</string></dict>",
+                ext
+            )
+        };
+        let group = |matching: &str, items: &[String]| {
+            format!(
+                "<dict><key>matchType</key><string>{}</string>\
+                 <key>conditions</key><array>{}</array></dict>",
+                matching,
+                items.concat()
+            )
+        };
+        let rules = [
+            rule(
+                "Either",
+                &group("any", &[extension("pdf"), extension("txt")]),
+                "Move to Trash",
+            ),
+            rule(
+                "One of one",
+                &group("any", &[extension("pdf")]),
+                "Move to Trash",
+            ),
+            rule(
+                "Nested",
+                &group(
+                    "all",
+                    &[extension("pdf"), group("any", &[extension("txt")])],
+                ),
+                "Move to Trash",
+            ),
+            rule("Tags", &group("all", &[extension("pdf")]), "Add tags"),
+        ];
+        let xml = format!("<plist><array>{}</array></plist>", rules.concat());
+        let imported = from_bytes(xml.as_bytes(), &[]).unwrap();
+
+        // Only an "any" group of one condition means the same in hazelnut
+        let names: Vec<_> = imported.rules.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["One of one", "Nested"]);
+        assert!(imported.rules[0].enabled);
+        // A nested group is left out and reported, and the rule kept disabled
+        assert!(!imported.rules[1].enabled);
+        assert_eq!(
+            imported.rules[1].condition.extension.as_deref(),
+            Some("pdf")
+        );
+
+        let notes: Vec<_> = imported
+            .notes
+            .iter()
+            .map(|n| (n.rule.as_str(), n.message.as_str()))
+            .collect();
+        assert_eq!(
+            notes,
+            [
+                (
+                    "Either",
+                    "skipped: it matches any of its conditions; hazelnut rules need all to match"
+                ),
+                (
+                    "Nested",
+                    "condition group: nested condition groups aren't supported"
+                ),
+                (
+                    "Nested",
+                    "imported disabled until its conditions are reviewed"
+                ),
+                ("Tags", "action \"Add tags\": no hazelnut equivalent"),
+                ("Tags", "skipped: none of its actions could be imported"),
+            ]
+        );
+
+        // Neither a property list nor readable as one
+        assert!(from_bytes(b"bplist00\x00\x01", &[]).is_err());
+        assert!(from_bytes(&[0xca, 0xfe, 0xba, 0xbe], &[]).is_err());
+    }
+}
@@ -0,0 +1,793 @@
+//! Property lists: binary (`bplist00`) and XML, plus the object graphs
+//! `NSKeyedArchiver` stores in them
+//!
+//! Only reading is supported, and only as much as importing rules needs.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use std::collections::BTreeMap;
+
+/// A property list value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Real(f64),
+    /// Seconds since 2001-01-01
+    Date(f64),
+    Data(Vec<u8>),
+    String(String),
+    Array(Vec<Value>),
+    Dict(BTreeMap<String, Value>),
+    /// Object reference in a keyed archive
+    Uid(u64),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Value::Dict(dict) => Some(dict),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(n) => Some(*n as f64),
+            Value::Real(n) => Some(*n),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            Value::Integer(n) => Some(*n != 0),
+            _ => None,
+        }
+    }
+}
+
+/// Nesting deeper than this is treated as corrupt
+const MAX_DEPTH: usize = 128;
+
+/// Objects read from a binary property list, counting each time one is
+/// referenced; more is treated as corrupt (shared references could
+/// otherwise expand exponentially)
+const MAX_OBJECTS: usize = 100_000;
+
+/// Parse a binary or XML property list, unpacking it if it's a keyed archive
+pub fn parse(bytes: &[u8]) -> Result<Value> {
+    let value = if bytes.starts_with(b"bplist00") {
+        Binary::new(bytes)?.root()?
+    } else {
+        let text = std::str::from_utf8(bytes).context("Not a property list")?;
+        Xml { text, pos: 0 }.root()?
+    };
+    Ok(unarchive(value))
+}
+
+/// Reader for the binary format
+struct Binary<'a> {
+    bytes: &'a [u8],
+    offsets: Vec<usize>,
+    ref_size: usize,
+    top: u64,
+    read: std::cell::Cell<usize>,
+}
+
+impl<'a> Binary<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self> {
+        let trailer = bytes
+            .len()
+            .checked_sub(32)
+            .filter(|&start| start >= 8)
+            .map(|start| &bytes[start..])
+            .context("Binary property list is truncated")?;
+        let offset_size = trailer[6] as usize;
+        let ref_size = trailer[7] as usize;
+        let count = be(&trailer[8..16]);
+        let top = be(&trailer[16..24]);
+        let table = be(&trailer[24..32]) as usize;
+        if !(1..=8).contains(&offset_size) || !(1..=8).contains(&ref_size) {
+            anyhow::bail!("Binary property list has an invalid trailer");
+        }
+        let offsets = (0..count as usize)
+            .map(|i| {
+                i.checked_mul(offset_size)
+                    .and_then(|at| at.checked_add(table))
+                    .and_then(|start| bytes.get(start..start.checked_add(offset_size)?))
+                    .map(|b| be(b) as usize)
+                    .context("Binary property list offset table is truncated")
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Objects start inside the file, so reading them can't overflow
+        if offsets.iter().any(|&offset| offset >= bytes.len()) {
+            anyhow::bail!("Binary property list refers to an object past its end");
+        }
+        Ok(Self {
+            bytes,
+            offsets,
+            ref_size,
+            top,
+            read: std::cell::Cell::new(0),
+        })
+    }
+
+    fn root(&self) -> Result<Value> {
+        self.object(self.top, 0)
+    }
+
+    fn slice(&self, start: usize, len: usize) -> Result<&'a [u8]> {
+        start
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(start..end))
+            .context("Binary property list object is truncated")
+    }
+
+    /// An object's length, from its marker or the integer following it;
+    /// returns the length and where the contents start
+    fn length(&self, marker: u8, start: usize) -> Result<(usize, usize)> {
+        let low = (marker & 0x0f) as usize;
+        if low != 0x0f {
+            return Ok((low, start + 1));
+        }
+        let int_marker = *self.slice(start + 1, 1)?.first().unwrap_or(&0);
+        if int_marker & 0xf0 != 0x10 {
+            anyhow::bail!("Binary property list has an invalid length");
+        }
+        let size = 1 << (int_marker & 0x0f);
+        let len = be(self.slice(start + 2, size)?) as usize;
+        Ok((len, start + 2 + size))
+    }
+
+    fn object(&self, index: u64, depth: usize) -> Result<Value> {
+        if depth > MAX_DEPTH {
+            anyhow::bail!("Binary property list is nested too deeply");
+        }
+        self.read.set(self.read.get() + 1);
+        if self.read.get() > MAX_OBJECTS {
+            anyhow::bail!("Binary property list has too many objects");
+        }
+        let start = *self
+            .offsets
+            .get(index as usize)
+            .context("Binary property list refers to a missing object")?;
+        let marker = self.slice(start, 1)?[0];
+        Ok(match marker >> 4 {
+            0x0 => match marker {
+                0x08 => Value::Bool(false),
+                0x09 => Value::Bool(true),
+                _ => Value::Null,
+            },
+            0x1 => {
+                let size = 1 << (marker & 0x0f);
+                let bytes = self.slice(start + 1, size)?;
+                // 8-byte integers are signed, 16-byte ones keep their low half
+                Value::Integer(be(&bytes[bytes.len().saturating_sub(8)..]) as i64)
+            }
+            0x2 => {
+                let bytes = self.slice(start + 1, 1 << (marker & 0x0f))?;
+                Value::Real(match bytes.len() {
+                    4 => f32::from_be_bytes(bytes.try_into()?) as f64,
+                    8 => f64::from_be_bytes(bytes.try_into()?),
+                    _ => anyhow::bail!("Binary property list has an invalid real"),
+                })
+            }
+            0x3 => Value::Date(f64::from_be_bytes(self.slice(start + 1, 8)?.try_into()?)),
+            0x4 => {
+                let (len, from) = self.length(marker, start)?;
+                Value::Data(self.slice(from, len)?.to_vec())
+            }
+            0x5 => {
+                let (len, from) = self.length(marker, start)?;
+                Value::String(String::from_utf8_lossy(self.slice(from, len)?).into_owned())
+            }
+            0x6 => {
+                let (len, from) = self.length(marker, start)?;
+                let units: Vec<u16> = self
+                    .slice(from, len.saturating_mul(2))?
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect();
+                Value::String(String::from_utf16_lossy(&units))
+            }
+            0x8 => Value::Uid(be(self.slice(start + 1, (marker & 0x0f) as usize + 1)?)),
+            0xa | 0xc => {
+                let (len, from) = self.length(marker, start)?;
+                let refs = self.refs(from, len)?;
+                Value::Array(
+                    refs.into_iter()
+                        .map(|r| self.object(r, depth + 1))
+                        .collect::<Result<_>>()?,
+                )
+            }
+            0xd => {
+                let (len, from) = self.length(marker, start)?;
+                let keys = self.refs(from, len)?;
+                let values =
+                    self.refs(from.saturating_add(len.saturating_mul(self.ref_size)), len)?;
+                let mut dict = BTreeMap::new();
+                for (key, value) in keys.into_iter().zip(values) {
+                    let key = match self.object(key, depth + 1)? {
+                        Value::String(key) => key,
+                        _ => anyhow::bail!("Binary property list has a non-string key"),
+                    };
+                    dict.insert(key, self.object(value, depth + 1)?);
+                }
+                Value::Dict(dict)
+            }
+            _ => anyhow::bail!("Binary property list has an unknown object type"),
+        })
+    }
+
+    fn refs(&self, start: usize, count: usize) -> Result<Vec<u64>> {
+        Ok(self
+            .slice(start, count.saturating_mul(self.ref_size))?
+            .chunks_exact(self.ref_size)
+            .map(be)
+            .collect())
+    }
+}
+
+/// Big-endian unsigned integer of up to 8 bytes
+fn be(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |n, &b| (n << 8) | b as u64)
+}
+
+/// Reader for the XML format
+struct Xml<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+/// An XML tag: its name, and whether it's `</name>` or `<name/>`
+struct Tag<'a> {
+    name: &'a str,
+    closing: bool,
+    empty: bool,
+}
+
+impl<'a> Xml<'a> {
+    fn root(&mut self) -> Result<Value> {
+        loop {
+            let tag = self
+                .tag()?
+                .context("Property list has no <plist> element")?;
+            if tag.name == "plist" && !tag.closing {
+                let tag = self.tag()?.context("Property list is empty")?;
+                return self.value(tag, 0);
+            }
+        }
+    }
+
+    /// The next tag, skipping text, comments and declarations
+    fn tag(&mut self) -> Result<Option<Tag<'a>>> {
+        loop {
+            let Some(start) = self.text[self.pos..].find('<').map(|i| self.pos + i) else {
+                return Ok(None);
+            };
+            let rest = &self.text[start..];
+            let skip_to = |end: &str| rest.find(end).map(|i| start + i + end.len());
+            if rest.starts_with("<!--") {
+                self.pos = skip_to("-->").context("Unterminated XML comment")?;
+                continue;
+            }
+            if rest.starts_with("<?") || rest.starts_with("<!") {
+                self.pos = skip_to(">").context("Unterminated XML declaration")?;
+                continue;
+            }
+            let end = skip_to(">").context("Unterminated XML tag")?;
+            let inner = &self.text[start + 1..end - 1];
+            self.pos = end;
+            let closing = inner.starts_with('/');
+            let empty = inner.ends_with('/');
+            let inner = inner.trim_start_matches('/').trim_end_matches('/');
+            let name = inner.split_whitespace().next().unwrap_or_default();
+            return Ok(Some(Tag {
+                name,
+                closing,
+                empty,
+            }));
+        }
+    }
+
+    /// Text up to the closing tag of `name`
+    fn text(&mut self, name: &str) -> Result<String> {
+        let close = format!("</{}>", name);
+        let end = self.text[self.pos..]
+            .find(&close)
+            .map(|i| self.pos + i)
+            .with_context(|| format!("Missing {} in property list", close))?;
+        let text = unescape(&self.text[self.pos..end]);
+        self.pos = end + close.len();
+        Ok(text)
+    }
+
+    fn value(&mut self, tag: Tag<'a>, depth: usize) -> Result<Value> {
+        if depth > MAX_DEPTH {
+            anyhow::bail!("Property list is nested too deeply");
+        }
+        if tag.closing {
+            anyhow::bail!("Unexpected </{}> in property list", tag.name);
+        }
+        if tag.empty {
+            return Ok(match tag.name {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "array" => Value::Array(Vec::new()),
+                "dict" => Value::Dict(BTreeMap::new()),
+                "string" => Value::String(String::new()),
+                "data" => Value::Data(Vec::new()),
+                _ => Value::Null,
+            });
+        }
+        Ok(match tag.name {
+            "string" => Value::String(self.text("string")?),
+            "integer" => {
+                let text = self.text("integer")?;
+                Value::Integer(
+                    text.trim()
+                        .parse()
+                        .with_context(|| format!("Invalid integer '{}'", text))?,
+                )
+            }
+            "real" => {
+                let text = self.text("real")?;
+                Value::Real(
+                    text.trim()
+                        .parse()
+                        .with_context(|| format!("Invalid real '{}'", text))?,
+                )
+            }
+            "true" | "false" => {
+                self.text(tag.name)?;
+                Value::Bool(tag.name == "true")
+            }
+            "date" => {
+                let text = self.text("date")?;
+                let date = chrono::DateTime::parse_from_rfc3339(text.trim())
+                    .with_context(|| format!("Invalid date '{}'", text))?;
+                // Property list dates count from 2001-01-01
+                Value::Date(date.timestamp() as f64 - 978_307_200.0)
+            }
+            "data" => {
+                let text = self.text("data")?;
+                let text: String = text.split_whitespace().collect();
+                Value::Data(
+                    base64::engine::general_purpose::STANDARD
+                        .decode(text)
+                        .context("Invalid base64 data in property list")?,
+                )
+            }
+            "array" => {
+                let mut items = Vec::new();
+                loop {
+                    let tag = self.tag()?.context("Unterminated <array>")?;
+                    if tag.closing && tag.name == "array" {
+                        break Value::Array(items);
+                    }
+                    items.push(self.value(tag, depth + 1)?);
+                }
+            }
+            "dict" => {
+                let mut dict = BTreeMap::new();
+                loop {
+                    let tag = self.tag()?.context("Unterminated <dict>")?;
+                    if tag.closing && tag.name == "dict" {
+                        break Value::Dict(dict);
+                    }
+                    if tag.name != "key" {
+                        anyhow::bail!("Expected <key> in <dict>, found <{}>", tag.name);
+                    }
+                    let key = if tag.empty {
+                        String::new()
+                    } else {
+                        self.text("key")?
+                    };
+                    let tag = self.tag()?.context("Unterminated <dict>")?;
+                    dict.insert(key, self.value(tag, depth + 1)?);
+                }
+            }
+            name => anyhow::bail!("Unknown property list element <{}>", name),
+        })
+    }
+}
+
+/// Replace XML entities
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Turn an `NSKeyedArchiver` archive into the object graph it stores.
+/// Foundation collections, strings, dates and URLs become plain values;
+/// other objects become dictionaries of their fields, with their class name
+/// under `$class`. Cycles, and references past `MAX_OBJECTS`, become
+/// `Null`. Any other property list is returned as it is.
+pub fn unarchive(value: Value) -> Value {
+    let Value::Dict(mut archive) = value else {
+        return value;
+    };
+    let is_archive = archive
+        .get("$archiver")
+        .and_then(Value::as_str)
+        .is_some_and(|a| a == "NSKeyedArchiver");
+    let (Some(Value::Array(objects)), Some(Value::Dict(top)), true) = (
+        archive.remove("$objects"),
+        archive.remove("$top"),
+        is_archive,
+    ) else {
+        return Value::Dict(archive);
+    };
+    let root = top
+        .get("root")
+        .or_else(|| top.values().next())
+        .cloned()
+        .unwrap_or(Value::Null);
+    Unarchiver {
+        objects: &objects,
+        visiting: Vec::new(),
+        resolved: 0,
+    }
+    .resolve(&root)
+}
+
+struct Unarchiver<'a> {
+    objects: &'a [Value],
+    /// Objects being resolved, to break reference cycles
+    visiting: Vec<u64>,
+    /// References resolved so far, up to `MAX_OBJECTS`
+    resolved: usize,
+}
+
+impl Unarchiver<'_> {
+    fn resolve(&mut self, value: &Value) -> Value {
+        match value {
+            Value::Uid(uid) => {
+                self.resolved += 1;
+                if self.visiting.contains(uid)
+                    || self.visiting.len() > MAX_DEPTH
+                    || self.resolved > MAX_OBJECTS
+                {
+                    return Value::Null;
+                }
+                let Some(object) = self.objects.get(*uid as usize) else {
+                    return Value::Null;
+                };
+                if object.as_str() == Some("$null") {
+                    return Value::Null;
+                }
+                self.visiting.push(*uid);
+                let resolved = self.resolve(object);
+                self.visiting.pop();
+                resolved
+            }
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.resolve(v)).collect()),
+            Value::Dict(dict) => self.object(dict),
+            other => other.clone(),
+        }
+    }
+
+    fn object(&mut self, dict: &BTreeMap<String, Value>) -> Value {
+        let class = dict.get("$class").map(|c| self.resolve(c)).and_then(|c| {
+            c.as_dict()
+                .and_then(|c| c.get("$classname"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        });
+        let Some(class) = class else {
+            // A class description, or a plain dictionary
+            return Value::Dict(
+                dict.iter()
+                    .map(|(k, v)| (k.clone(), self.resolve(v)))
+                    .collect(),
+            );
+        };
+        let field = |this: &mut Self, name: &str| {
+            dict.get(name)
+                .map(|v| this.resolve(v))
+                .unwrap_or(Value::Null)
+        };
+        match class.as_str() {
+            "NSArray"
+            | "NSMutableArray"
+            | "NSSet"
+            | "NSMutableSet"
+            | "NSOrderedSet"
+            | "NSMutableOrderedSet" => match field(self, "NS.objects") {
+                Value::Array(items) => Value::Array(items),
+                _ => Value::Array(Vec::new()),
+            },
+            "NSDictionary" | "NSMutableDictionary" => {
+                let (Value::Array(keys), Value::Array(values)) =
+                    (field(self, "NS.keys"), field(self, "NS.objects"))
+                else {
+                    return Value::Dict(BTreeMap::new());
+                };
+                Value::Dict(
+                    keys.into_iter()
+                        .zip(values)
+                        .filter_map(|(k, v)| Some((k.as_str()?.to_string(), v)))
+                        .collect(),
+                )
+            }
+            "NSString" | "NSMutableString" => field(self, "NS.string"),
+            "NSData" | "NSMutableData" => field(self, "NS.data"),
+            "NSDate" => match field(self, "NS.time").as_f64() {
+                Some(time) => Value::Date(time),
+                None => Value::Null,
+            },
+            "NSURL" => field(self, "NS.relative"),
+            _ => {
+                let mut fields: BTreeMap<String, Value> = dict
+                    .iter()
+                    .filter(|(k, _)| k.as_str() != "$class")
+                    .map(|(k, v)| (k.clone(), self.resolve(v)))
+                    .collect();
+                fields.insert("$class".to_string(), Value::String(class));
+                Value::Dict(fields)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>name</key>
+    <string>Tom &amp; Jerry &#x263A;</string>
+    <key>items</key>
+    <array><integer>-3</integer><real>1.5</real><true/><data>aGk=</data></array>
+    <key>empty</key>
+    <dict/>
+</dict>
+</plist>"#;
+        let value = parse(xml.as_bytes()).unwrap();
+        let dict = value.as_dict().unwrap();
+        assert_eq!(dict["name"].as_str(), Some("Tom & Jerry ☺"));
+        assert_eq!(
+            dict["items"],
+            Value::Array(vec![
+                Value::Integer(-3),
+                Value::Real(1.5),
+                Value::Bool(true),
+                Value::Data(b"hi".to_vec()),
+            ])
+        );
+        assert_eq!(dict["empty"], Value::Dict(BTreeMap::new()));
+
+        // A keyed archive of a Rule object whose "self" field points back at it
+        fn push(objects: &mut Vec<Vec<u8>>, bytes: &[u8]) -> u8 {
+            objects.push(bytes.to_vec());
+            objects.len() as u8 - 1
+        }
+        fn string(objects: &mut Vec<Vec<u8>>, text: &str) -> u8 {
+            // Lengths from 15 up follow the marker as an integer
+            let marker = match text.len() {
+                len @ 0..15 => vec![0x50 | len as u8],
+                len => vec![0x5f, 0x10, len as u8],
+            };
+            push(objects, &[&marker, text.as_bytes()].concat())
+        }
+        fn uid(objects: &mut Vec<Vec<u8>>, n: u8) -> u8 {
+            push(objects, &[0x80, n])
+        }
+        let o = &mut Vec::new();
+        // $objects: 0 $null, 1 rule, 2 "PDFs", 3 Rule class, 4 tags, 5 "a", 6 NSArray class
+        let archived = [
+            string(o, "$null"),
+            {
+                let keys = [
+                    string(o, "$class"),
+                    string(o, "name"),
+                    string(o, "tags"),
+                    string(o, "self"),
+                ];
+                let values = [uid(o, 3), uid(o, 2), uid(o, 4), uid(o, 1)];
+                push(o, &[&[0xd4][..], &keys, &values].concat())
+            },
+            string(o, "PDFs"),
+            {
+                let (key, name) = (string(o, "$classname"), string(o, "Rule"));
+                push(o, &[0xd1, key, name])
+            },
+            {
+                let keys = [string(o, "$class"), string(o, "NS.objects")];
+                let class = uid(o, 6);
+                let tag = uid(o, 5);
+                let list = push(o, &[0xa1, tag]);
+                push(o, &[0xd2, keys[0], keys[1], class, list])
+            },
+            string(o, "a"),
+            {
+                let (key, name) = (string(o, "$classname"), string(o, "NSArray"));
+                push(o, &[0xd1, key, name])
+            },
+        ];
+        let archived = push(o, &[&[0xa7][..], &archived].concat());
+        let keys = [
+            string(o, "$archiver"),
+            string(o, "$objects"),
+            string(o, "$top"),
+        ];
+        let archiver = string(o, "NSKeyedArchiver");
+        let top = {
+            let (key, root) = (string(o, "root"), uid(o, 1));
+            push(o, &[0xd1, key, root])
+        };
+        let root = push(
+            o,
+            &[0xd3, keys[0], keys[1], keys[2], archiver, archived, top],
+        );
+
+        let mut bytes = b"bplist00".to_vec();
+        let mut offsets = Vec::new();
+        for object in o.iter() {
+            offsets.push(bytes.len() as u8);
+            bytes.extend_from_slice(object);
+        }
+        let table = bytes.len() as u64;
+        bytes.extend_from_slice(&offsets);
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+        bytes.extend_from_slice(&(o.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&(root as u64).to_be_bytes());
+        bytes.extend_from_slice(&table.to_be_bytes());
+
+        let value = parse(&bytes).unwrap();
+        let rule = value.as_dict().unwrap();
+        assert_eq!(rule["$class"].as_str(), Some("Rule"));
+        assert_eq!(rule["name"].as_str(), Some("PDFs"));
+        assert_eq!(rule["tags"], Value::Array(vec![Value::String("a".into())]));
+        assert_eq!(rule["self"], Value::Null);
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        // {"a": [1, "b"]}
+        let mut bytes = b"bplist00".to_vec();
+        bytes.extend_from_slice(&[0xd1, 1, 2, 0x51, b'a', 0xa2, 3, 4, 0x10, 1, 0x51, b'b']);
+        bytes.extend_from_slice(&[8, 11, 13, 16, 18]);
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+        bytes.extend_from_slice(&5u64.to_be_bytes());
+        bytes.extend_from_slice(&0u64.to_be_bytes());
+        bytes.extend_from_slice(&20u64.to_be_bytes());
+        let value = parse(&bytes).unwrap();
+        assert_eq!(
+            value.as_dict().unwrap()["a"],
+            Value::Array(vec![Value::Integer(1), Value::String("b".into())])
+        );
+
+        // Cut short or with any byte damaged, it's an error or some value,
+        // never a panic
+        for len in 0..bytes.len() {
+            assert!(parse(&bytes[..len]).is_err());
+        }
+        for i in 8..bytes.len() {
+            for byte in [0x00, 0x0f, 0x1f, 0x5f, 0x7f, 0xdf, 0xff] {
+                let mut damaged = bytes.clone();
+                damaged[i] = byte;
+                let _ = parse(&damaged);
+            }
+        }
+        // A trailer or an offset pointing past the end of the address space
+        let mut huge = bytes.clone();
+        let trailer = huge.len() - 32;
+        huge[trailer + 6] = 8;
+        huge[trailer + 8..].fill(0xff);
+        assert!(parse(&huge).is_err());
+        let mut huge = bytes[..20].to_vec();
+        huge.extend_from_slice(&[0xff; 8]);
+        huge.extend_from_slice(&[0, 0, 0, 0, 0, 0, 8, 1]);
+        huge.extend_from_slice(&1u64.to_be_bytes());
+        huge.extend_from_slice(&0u64.to_be_bytes());
+        huge.extend_from_slice(&20u64.to_be_bytes());
+        assert!(parse(&huge).is_err());
+        // An object that contains itself
+        let mut cycle = bytes.clone();
+        cycle[13..16].copy_from_slice(&[0xa2, 2, 2]);
+        assert!(parse(&cycle).is_err());
+        // Arrays each holding the next one twice, 2^64 objects if expanded
+        let mut bytes = b"bplist00".to_vec();
+        let mut offsets = Vec::new();
+        for i in 0..64u8 {
+            offsets.push(bytes.len() as u8);
+            bytes.extend_from_slice(&[0xa2, i + 1, i + 1]);
+        }
+        offsets.push(bytes.len() as u8);
+        bytes.push(0x09);
+        let table = bytes.len() as u64;
+        bytes.extend_from_slice(&offsets);
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+        bytes.extend_from_slice(&(offsets.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&0u64.to_be_bytes());
+        bytes.extend_from_slice(&table.to_be_bytes());
+        assert!(parse(&bytes).is_err());
+
+        // An archive whose objects each refer to the next one twice stops
+        // being expanded rather than taking forever
+        let mut objects = vec![Value::String("$null".into())];
+        for i in 1..=64 {
+            let next = Value::Uid(i + 1);
+            let fields = [("a".to_string(), next.clone()), ("b".to_string(), next)];
+            objects.push(Value::Dict(fields.into_iter().collect()));
+        }
+        objects.push(Value::String("end".into()));
+        let archive = [
+            ("$archiver", Value::String("NSKeyedArchiver".into())),
+            ("$objects", Value::Array(objects)),
+            (
+                "$top",
+                Value::Dict([("root".to_string(), Value::Uid(1))].into_iter().collect()),
+            ),
+        ];
+        let archive = archive.into_iter().map(|(k, v)| (k.to_string(), v));
+        assert!(
+            unarchive(Value::Dict(archive.collect()))
+                .as_dict()
+                .is_some()
+        );
+
+        for xml in [
+            "",
+            "not a plist",
+            "<plist><dict><key>a</key>",
+            "<plist><dict><string>a</string></dict></plist>",
+            "<plist><array><integer>x</integer></array></plist>",
+            "<plist><data>!!</data></plist>",
+            "<plist></array></plist>",
+            "<plist><dict><key>a</key><!-- </dict></plist>",
+        ] {
+            assert!(parse(xml.as_bytes()).is_err(), "{:?}", xml);
+        }
+        assert!(parse(&[0xff, 0xfe, b'<', 0]).is_err());
+    }
+}
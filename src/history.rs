@@ -0,0 +1,1188 @@
+//! History of the actions rules ran
+//!
+//! Every action hazelnut runs on a file is recorded with its source and
+//! destination, the rule, the time, a checksum of the file and whether it
+//! worked. The TUI's History view and `hazelnut undo` revert an action: a
+//! moved or renamed file goes back where it was, a copy is removed and a
+//! trashed file is restored from the trash. A file restored that way is left
+//! alone by the watcher until it changes, so its rule doesn't act on it
+//! straight again.
+//!
+//! The history is an SQLite database, `history.db` in the data directory,
+//! which the daemon and a TUI share. Entries older than
+//! `history_retention_days` are pruned when it's opened and about once an
+//! hour after that, and the space they took is given back. A
+//! `history.jsonl` from earlier versions is imported once.
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::warn;
+
+use crate::rules::Action;
+use crate::stats::csv_field;
+
+/// Actions the TUI lists
+pub const MAX_ENTRIES: usize = 500;
+
+/// How often old entries are pruned
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Layout of the database, kept in `PRAGMA user_version`
+const SCHEMA_VERSION: i64 = 3;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS actions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        time INTEGER NOT NULL,          -- Unix time in milliseconds
+        rule TEXT NOT NULL,
+        action TEXT NOT NULL,
+        source TEXT NOT NULL,
+        destination TEXT,
+        checksum TEXT,
+        outcome TEXT NOT NULL,          -- 'ok' or 'failed'
+        error TEXT,
+        undone INTEGER,                 -- Unix time in milliseconds
+        restored_modified INTEGER,      -- the restored file's mtime, in ns
+        conflict TEXT                   -- how a taken destination was settled
+    );
+    CREATE INDEX IF NOT EXISTS actions_time ON actions (time);
+    CREATE INDEX IF NOT EXISTS actions_source ON actions (source);
+    CREATE INDEX IF NOT EXISTS actions_checksum ON actions (checksum);
+    CREATE INDEX IF NOT EXISTS actions_undone ON actions (undone);
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT
+    );
+";
+
+/// Changes to databases made before a schema version, oldest first
+const UPGRADES: &[(i64, &str)] = &[
+    (2, "ALTER TABLE actions ADD COLUMN conflict TEXT;"),
+    (
+        3,
+        "CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT);",
+    ),
+];
+
+/// `meta` key set once `history.jsonl` has been imported
+const JSONL_IMPORTED: &str = "jsonl_imported";
+
+const COLUMNS: &str =
+    "id, time, rule, action, source, destination, checksum, error, undone, conflict";
+
+/// An action hazelnut ran on a file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unique within the history
+    pub id: i64,
+    pub time: DateTime<Local>,
+    /// Rule name (empty for a watch's `unmatched_action`)
+    pub rule: String,
+    /// The action's `type`, e.g. `move`
+    pub action: String,
+    /// Where the file was
+    pub from: PathBuf,
+    /// Where the action put it, if it put it anywhere
+    pub to: Option<PathBuf>,
+    /// Hash of the file's contents, e.g. `blake3:af1349b9...`
+    pub checksum: Option<String>,
+    /// Why the action failed
+    pub error: Option<String>,
+    /// When the action was undone
+    pub undone: Option<DateTime<Local>>,
+    /// How a file already at the destination was dealt with (`on_conflict`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Whether the action moved the file, so undoing it can move it back
+    pub fn is_move(&self) -> bool {
+        matches!(self.action.as_str(), "move" | "rename" | "tier")
+    }
+
+    /// Whether the action worked, hasn't been undone and is of a kind that
+    /// can be
+    pub fn can_undo(&self) -> bool {
+        self.succeeded()
+            && self.undone.is_none()
+            && (self.is_move() || matches!(self.action.as_str(), "copy" | "trash"))
+    }
+
+    /// What undoing the action did, e.g. "report.pdf is back at /in/report.pdf"
+    pub fn undo_summary(&self) -> String {
+        let name = self.from.file_name().unwrap_or_default().to_string_lossy();
+        match (self.action.as_str(), &self.to) {
+            ("copy", Some(to)) => format!("removed the copy at {}", to.display()),
+            ("trash", _) => format!("{} is back from the trash at {}", name, self.from.display()),
+            _ => format!("{} is back at {}", name, self.from.display()),
+        }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let path = |text: String| PathBuf::from(text);
+        Ok(Self {
+            id: row.get(0)?,
+            time: local_time(row.get(1)?),
+            rule: row.get(2)?,
+            action: row.get(3)?,
+            from: path(row.get(4)?),
+            to: row.get::<_, Option<String>>(5)?.map(path),
+            checksum: row.get(6)?,
+            error: row.get(7)?,
+            undone: row.get::<_, Option<i64>>(8)?.map(local_time),
+            conflict: row.get(9)?,
+        })
+    }
+}
+
+/// An action to record
+#[derive(Debug, Clone, Copy)]
+pub struct NewEntry<'a> {
+    pub rule: &'a str,
+    pub action: &'a str,
+    pub from: &'a Path,
+    pub to: Option<&'a Path>,
+    pub checksum: Option<&'a str>,
+    pub error: Option<&'a str>,
+    pub conflict: Option<&'a str>,
+}
+
+/// The history database
+pub struct History {
+    conn: Mutex<Connection>,
+}
+
+impl History {
+    /// Open the database, creating it if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open history {}", path.display()))?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            bail!(
+                "History {} is from a newer hazelnut; update hazelnut",
+                path.display()
+            );
+        }
+        if version == 0 {
+            // Only takes effect on a new database, before anything is written
+            conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL")?;
+        }
+        // Readers don't block the writer, so the TUI can list while the
+        // daemon records
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        if version < SCHEMA_VERSION {
+            // A new database gets the current layout from SCHEMA
+            let upgrades: String = UPGRADES
+                .iter()
+                .filter(|(to, _)| version > 0 && *to > version)
+                .map(|(_, sql)| *sql)
+                .collect();
+            conn.execute_batch(&format!(
+                "BEGIN IMMEDIATE; {} {} PRAGMA user_version = {}; COMMIT;",
+                SCHEMA, upgrades, SCHEMA_VERSION
+            ))
+            .with_context(|| format!("Failed to set up history {}", path.display()))?;
+        }
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Default location: `<data_dir>/hazelnut/history.db`
+    pub fn default_path() -> Option<PathBuf> {
+        crate::Config::data_dir().map(|d| d.join("history.db"))
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record an action, returning its id
+    pub fn record(&self, entry: &NewEntry) -> Result<i64> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO actions (time, rule, action, source, destination, checksum, outcome, error, conflict)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                Local::now().timestamp_millis(),
+                entry.rule,
+                entry.action,
+                entry.from.to_string_lossy(),
+                entry.to.map(|to| to.to_string_lossy()),
+                entry.checksum,
+                if entry.error.is_some() { "failed" } else { "ok" },
+                entry.error,
+                entry.conflict,
+            ],
+        )
+        .context("Failed to record history")?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// The latest `limit` actions, newest first
+    pub fn entries(&self, limit: usize) -> Vec<HistoryEntry> {
+        let conn = self.conn();
+        let query = format!("SELECT {} FROM actions ORDER BY id DESC LIMIT ?1", COLUMNS);
+        let entries = conn.prepare_cached(&query).and_then(|mut statement| {
+            statement
+                .query_map([limit as i64], HistoryEntry::from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()
+        });
+        entries.unwrap_or_else(|e| {
+            warn!("Failed to read history: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// The action with this id
+    pub fn get(&self, id: i64) -> Result<Option<HistoryEntry>> {
+        let query = format!("SELECT {} FROM actions WHERE id = ?1", COLUMNS);
+        Ok(self
+            .conn()
+            .query_row(&query, [id], HistoryEntry::from_row)
+            .optional()?)
+    }
+
+    /// Revert an action: move a file back where the action found it, under
+    /// its old name, remove a copy, or restore a trashed file. Fails if the
+    /// file isn't where the action put it anymore, has changed since (when
+    /// its checksum was recorded), or something else took its old place.
+    pub fn undo(&self, entry: &HistoryEntry) -> Result<()> {
+        check_undo(entry, &mut Planned::default())?;
+        match (entry.action.as_str(), &entry.to) {
+            (_, Some(to)) if entry.is_move() => {
+                if let Some(parent) = entry.from.parent() {
+                    std::fs::create_dir_all(parent).with_context(|| {
+                        format!("Failed to create directory: {}", parent.display())
+                    })?;
+                }
+                crate::rules::move_exact(to, &entry.from)?;
+                crate::date_added::record_move(to, &entry.from);
+                crate::fingerprint::forget(to);
+            }
+            ("copy", Some(to)) => {
+                std::fs::remove_file(to)
+                    .with_context(|| format!("Failed to remove {}", to.display()))?;
+                crate::fingerprint::forget(to);
+            }
+            _ => restore_from_trash(&entry.from)?,
+        }
+
+        self.conn()
+            .execute(
+                "UPDATE actions SET undone = ?1, restored_modified = ?2 WHERE id = ?3",
+                params![
+                    Local::now().timestamp_millis(),
+                    modified(&entry.from).and_then(nanos),
+                    entry.id
+                ],
+            )
+            .context("Failed to record the undo in the history")?;
+        Ok(())
+    }
+
+    /// Apply an undone action again, taking back `undo`: used when a
+    /// rollback fails partway
+    pub fn redo(&self, entry: &HistoryEntry) -> Result<()> {
+        match (entry.action.as_str(), &entry.to) {
+            (_, Some(to)) if entry.is_move() => {
+                crate::rules::move_exact(&entry.from, to)?;
+                crate::date_added::record_move(&entry.from, to);
+                crate::fingerprint::forget(&entry.from);
+            }
+            ("copy", Some(to)) => crate::rules::copy_exact(&entry.from, to)?,
+            _ => trash::delete(&entry.from)
+                .with_context(|| format!("Failed to trash {}", entry.from.display()))?,
+        }
+
+        self.conn()
+            .execute(
+                "UPDATE actions SET undone = NULL, restored_modified = NULL WHERE id = ?1",
+                [entry.id],
+            )
+            .context("Failed to record the redo in the history")?;
+        Ok(())
+    }
+
+    /// Actions that can still be undone, newest first: all of them, or
+    /// those since a time and/or by one rule
+    pub fn undoable(
+        &self,
+        since: Option<DateTime<Local>>,
+        rule: Option<&str>,
+    ) -> Vec<HistoryEntry> {
+        let conn = self.conn();
+        let query = format!(
+            "SELECT {} FROM actions
+             WHERE outcome = 'ok' AND undone IS NULL AND time >= ?1 AND (?2 IS NULL OR rule = ?2)
+             ORDER BY id DESC",
+            COLUMNS
+        );
+        let since = since.map_or(0, |since| since.timestamp_millis());
+        let entries = conn.prepare_cached(&query).and_then(|mut statement| {
+            statement
+                .query_map(params![since, rule], HistoryEntry::from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()
+        });
+        entries
+            .unwrap_or_else(|e| {
+                warn!("Failed to read history: {}", e);
+                Vec::new()
+            })
+            .into_iter()
+            .filter(HistoryEntry::can_undo)
+            .collect()
+    }
+
+    /// Every action, oldest first: all of them or those since a time
+    pub fn since(&self, since: Option<DateTime<Local>>) -> Vec<HistoryEntry> {
+        let conn = self.conn();
+        let query = format!(
+            "SELECT {} FROM actions WHERE time >= ?1 ORDER BY id",
+            COLUMNS
+        );
+        let since = since.map_or(0, |since| since.timestamp_millis());
+        let entries = conn.prepare_cached(&query).and_then(|mut statement| {
+            statement
+                .query_map([since], HistoryEntry::from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()
+        });
+        entries.unwrap_or_else(|e| {
+            warn!("Failed to read history: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Files put back by an undo, with their modification time then
+    fn restored(&self) -> HashMap<PathBuf, Option<SystemTime>> {
+        let conn = self.conn();
+        let restored = conn
+            .prepare_cached(
+                "SELECT source, restored_modified FROM actions WHERE undone IS NOT NULL",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| {
+                        let modified: Option<i64> = row.get(1)?;
+                        Ok((
+                            PathBuf::from(row.get::<_, String>(0)?),
+                            modified
+                                .map(|ns| SystemTime::UNIX_EPOCH + Duration::from_nanos(ns as u64)),
+                        ))
+                    })?
+                    .collect()
+            });
+        restored.unwrap_or_default()
+    }
+
+    /// Changes whenever an action is recorded or undone
+    pub fn stamp(&self) -> Option<(i64, i64)> {
+        self.conn()
+            .query_row(
+                "SELECT COALESCE(MAX(id), 0), COALESCE(MAX(undone), 0) FROM actions",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .ok()
+    }
+
+    /// Delete actions older than `days` days and give their space back
+    pub fn prune(&self, days: u32) -> Result<usize> {
+        let cutoff = Local::now() - chrono::Duration::days(days.into());
+        let conn = self.conn();
+        let deleted = conn.execute(
+            "DELETE FROM actions WHERE time < ?1",
+            [cutoff.timestamp_millis()],
+        )?;
+        if deleted > 0 {
+            conn.execute_batch("PRAGMA incremental_vacuum")?;
+        }
+        Ok(deleted)
+    }
+
+    /// Import a `history.jsonl` from earlier versions, unless it already
+    /// was (by this or another process). Returns the actions imported, or
+    /// `None` if it already was.
+    fn import_jsonl(&self, path: &Path) -> Result<Option<usize>> {
+        /// A line of the old history file
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Line {
+            Undo {
+                undo: String,
+                time: DateTime<Local>,
+                #[serde(default)]
+                modified: Option<SystemTime>,
+            },
+            Action {
+                id: String,
+                time: DateTime<Local>,
+                rule: String,
+                action: String,
+                from: PathBuf,
+                to: PathBuf,
+            },
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        let mut conn = self.conn();
+        // Taking the write lock up front keeps a second process from
+        // importing at the same time
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let imported = tx
+            .query_row(
+                "SELECT 1 FROM meta WHERE key = ?1",
+                [JSONL_IMPORTED],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if imported {
+            return Ok(None);
+        }
+        let mut ids = HashMap::new();
+        for line in content.lines() {
+            match serde_json::from_str(line) {
+                Ok(Line::Action {
+                    id,
+                    time,
+                    rule,
+                    action,
+                    from,
+                    to,
+                }) => {
+                    tx.execute(
+                        "INSERT INTO actions (time, rule, action, source, destination, outcome)
+                         VALUES (?1, ?2, ?3, ?4, ?5, 'ok')",
+                        params![
+                            time.timestamp_millis(),
+                            rule,
+                            action,
+                            from.to_string_lossy(),
+                            to.to_string_lossy()
+                        ],
+                    )?;
+                    ids.insert(id, tx.last_insert_rowid());
+                }
+                Ok(Line::Undo {
+                    undo,
+                    time,
+                    modified,
+                }) => {
+                    if let Some(id) = ids.get(&undo) {
+                        tx.execute(
+                            "UPDATE actions SET undone = ?1, restored_modified = ?2 WHERE id = ?3",
+                            params![time.timestamp_millis(), modified.and_then(nanos), id],
+                        )?;
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+        tx.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)",
+            params![JSONL_IMPORTED, Local::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+        Ok(Some(ids.len()))
+    }
+}
+
+/// Which paths exist once the undos checked so far have run, so a batch can
+/// be checked as a whole before any of it is undone
+#[derive(Debug, Default)]
+struct Planned {
+    exists: HashMap<PathBuf, bool>,
+}
+
+impl Planned {
+    fn exists(&self, path: &Path) -> bool {
+        self.exists
+            .get(path)
+            .copied()
+            .unwrap_or_else(|| path.exists())
+    }
+
+    /// Whether an earlier undo in the batch puts a file at `path`
+    fn is_planned(&self, path: &Path) -> bool {
+        self.exists.get(path) == Some(&true)
+    }
+
+    fn set(&mut self, path: &Path, exists: bool) {
+        self.exists.insert(path.to_path_buf(), exists);
+    }
+}
+
+/// Why an action can't be undone once the undos in `planned` have run, if
+/// it can't; if it can, its own undo is added to `planned`
+fn check_undo(entry: &HistoryEntry, planned: &mut Planned) -> Result<()> {
+    if entry.undone.is_some() {
+        bail!("Already undone");
+    }
+    if !entry.succeeded() {
+        bail!("The {} failed, so there's nothing to undo", entry.action);
+    }
+    match (entry.action.as_str(), &entry.to) {
+        (_, Some(to)) if entry.is_move() => {
+            check_unchanged(entry, to, planned)?;
+            if planned.exists(&entry.from) {
+                bail!("{} already exists", entry.from.display());
+            }
+            planned.set(to, false);
+            planned.set(&entry.from, true);
+        }
+        ("copy", Some(to)) => {
+            check_unchanged(entry, to, planned)?;
+            if to.is_dir() {
+                bail!("{} is a folder; remove the copy by hand", to.display());
+            }
+            planned.set(to, false);
+        }
+        ("trash", _) => {
+            if planned.exists(&entry.from) {
+                bail!("{} already exists", entry.from.display());
+            }
+            planned.set(&entry.from, true);
+        }
+        _ => bail!("A {} can't be undone", entry.action),
+    }
+    Ok(())
+}
+
+/// Fail unless the file an action left at `to` is still there and, if its
+/// checksum was recorded, still has the same contents
+fn check_unchanged(entry: &HistoryEntry, to: &Path, planned: &Planned) -> Result<()> {
+    if crate::sftp::is_remote(to) {
+        bail!("{} is on a remote server", to.display());
+    }
+    if !planned.exists(to) {
+        bail!("{} is no longer there", to.display());
+    }
+    if planned.is_planned(to) {
+        // Another undo in the batch puts it back first
+        return Ok(());
+    }
+    let Some((name, expected)) = entry.checksum.as_deref().and_then(|c| c.split_once(':')) else {
+        return Ok(());
+    };
+    let Some(algorithm) = crate::hashing::HashAlgorithm::from_name(name) else {
+        return Ok(());
+    };
+    if to.is_file() && crate::hash_cache::hash_file(to, algorithm)? != expected {
+        bail!(
+            "{} has changed since the {}, so it's left where it is",
+            to.display(),
+            entry.action
+        );
+    }
+    Ok(())
+}
+
+/// Check a batch of actions, newest first, before undoing any of it: each
+/// is checked as things will be once the newer ones are undone. Returns the
+/// ones that can't be undone and why.
+pub fn check_rollback(entries: &[HistoryEntry]) -> Vec<(&HistoryEntry, anyhow::Error)> {
+    let mut planned = Planned::default();
+    entries
+        .iter()
+        .filter_map(|entry| {
+            check_undo(entry, &mut planned)
+                .err()
+                .map(|error| (entry, error))
+        })
+        .collect()
+}
+
+/// A start time for a rollback or export: a time ago like `"10 minutes ago"`
+/// or `"2h"`, or a date and time like `"2024-05-01 14:30"`, `"2024-05-01"`
+/// or `"14:30"` (today)
+pub fn parse_since(text: &str, now: DateTime<Local>) -> Result<DateTime<Local>> {
+    let text = text.trim();
+    let ago = text.strip_suffix("ago").unwrap_or(text);
+    if let Ok(interval) = crate::notifications::Interval::try_from(ago.to_string()) {
+        return Ok(now - chrono::Duration::from_std(interval.duration())?);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Local));
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .map(|date| date.and_time(NaiveTime::MIN))
+        })
+        .or_else(|| {
+            NaiveTime::parse_from_str(text, "%H:%M")
+                .ok()
+                .map(|time| now.date_naive().and_time(time))
+        });
+    naive
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+        .with_context(|| {
+            format!(
+                "invalid time '{}', expected e.g. \"10 minutes ago\", \"2h\" or \"2024-05-01 14:30\"",
+                text
+            )
+        })
+}
+
+/// CSV with a header row, one action per line; times are in local time
+/// (RFC 3339)
+pub fn to_csv(entries: &[HistoryEntry]) -> String {
+    let mut csv =
+        String::from("id,time,rule,action,from,to,outcome,error,checksum,undone,conflict\n");
+    let path = |path: Option<&Path>| path.map(|p| csv_field(&p.display().to_string()));
+    for entry in entries {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{}\n",
+            entry.id,
+            entry.time.to_rfc3339(),
+            csv_field(&entry.rule),
+            entry.action,
+            path(Some(&entry.from)).unwrap_or_default(),
+            path(entry.to.as_deref()).unwrap_or_default(),
+            if entry.succeeded() { "ok" } else { "failed" },
+            csv_field(entry.error.as_deref().unwrap_or_default()),
+            entry.checksum.as_deref().unwrap_or_default(),
+            entry.undone.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            csv_field(entry.conflict.as_deref().unwrap_or_default())
+        ));
+    }
+    csv
+}
+
+/// Put the most recently trashed file that was at `path` back there
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn restore_from_trash(path: &Path) -> Result<()> {
+    let item = trash::os_limited::list()
+        .context("Failed to list the trash")?
+        .into_iter()
+        .filter(|item| item.original_path() == path)
+        .max_by_key(|item| item.time_deleted)
+        .with_context(|| format!("{} is no longer in the trash", path.display()))?;
+    trash::os_limited::restore_all([item])
+        .with_context(|| format!("Failed to restore {} from the trash", path.display()))
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+fn restore_from_trash(path: &Path) -> Result<()> {
+    bail!(
+        "Restoring from the trash isn't supported on this system; put {} back from the Trash by hand",
+        path.display()
+    )
+}
+
+fn local_time(millis: i64) -> DateTime<Local> {
+    Local
+        .timestamp_millis_opt(millis)
+        .single()
+        .unwrap_or_default()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn nanos(time: SystemTime) -> Option<i64> {
+    let since = time.duration_since(SystemTime::UNIX_EPOCH).ok()?;
+    i64::try_from(since.as_nanos()).ok()
+}
+
+/// Days of history to keep (0 = keep everything)
+static RETENTION_DAYS: AtomicU32 = AtomicU32::new(90);
+
+/// Whether files are hashed for the history
+static CHECKSUMS: AtomicBool = AtomicBool::new(true);
+
+/// When the shared history was last pruned
+static PRUNED: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Set how many days of history are kept and whether files are hashed
+pub fn init(retention_days: u32, checksums: bool) {
+    RETENTION_DAYS.store(retention_days, Ordering::Relaxed);
+    CHECKSUMS.store(checksums, Ordering::Relaxed);
+    if let Ok(mut pruned) = PRUNED.lock() {
+        *pruned = None;
+    }
+}
+
+static SHARED: OnceLock<History> = OnceLock::new();
+
+/// Held while the shared history is opened, so only one thread opens it
+/// and imports `history.jsonl`
+static OPENING: Mutex<()> = Mutex::new(());
+
+/// The history in the data directory, opened on first use
+fn shared() -> Option<&'static History> {
+    if let Some(history) = SHARED.get() {
+        return Some(history);
+    }
+    let _opening = OPENING.lock().unwrap_or_else(|e| e.into_inner());
+    // Opened by another thread while this one waited
+    if let Some(history) = SHARED.get() {
+        return Some(history);
+    }
+    let path = History::default_path()?;
+    let history = match History::open(&path) {
+        Ok(history) => history,
+        Err(e) => {
+            warn!("{:#}", e);
+            return None;
+        }
+    };
+    let legacy = path.with_file_name("history.jsonl");
+    if legacy.exists() {
+        match history.import_jsonl(&legacy) {
+            Ok(imported) => {
+                if let Some(count) = imported {
+                    tracing::info!("Imported {} actions from {}", count, legacy.display());
+                }
+                // Another process may have imported and renamed it already
+                if let Err(e) = std::fs::rename(&legacy, legacy.with_extension("jsonl.imported"))
+                    && e.kind() != std::io::ErrorKind::NotFound
+                {
+                    warn!("Failed to rename {}: {}", legacy.display(), e);
+                }
+            }
+            Err(e) => warn!("Failed to import {}: {:#}", legacy.display(), e),
+        }
+    }
+    Some(SHARED.get_or_init(|| history))
+}
+
+/// Prune the shared history if it's been a while
+fn prune_if_due(history: &History) {
+    let days = RETENTION_DAYS.load(Ordering::Relaxed);
+    let Ok(mut pruned) = PRUNED.lock() else {
+        return;
+    };
+    if days == 0 || pruned.is_some_and(|at| at.elapsed() < PRUNE_INTERVAL) {
+        return;
+    }
+    *pruned = Some(Instant::now());
+    match history.prune(days) {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("Pruned {} history entries older than {} days", count, days),
+        Err(e) => warn!("Failed to prune history: {:#}", e),
+    }
+}
+
+/// Checksum of a file for the history, if checksums are on
+pub fn checksum(path: &Path) -> Option<String> {
+    if !CHECKSUMS.load(Ordering::Relaxed) || crate::sftp::is_remote(path) || !path.is_file() {
+        return None;
+    }
+    let algorithm = crate::hashing::algorithm();
+    match crate::hash_cache::hash_file(path, algorithm) {
+        Ok(hash) => Some(format!("{}:{}", algorithm.name(), hash)),
+        Err(e) => {
+            warn!("Failed to hash {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Whether an action's result goes in the history: not when nothing
+/// happened to the file
+pub fn is_recorded(action: &Action, result: &Result<Option<PathBuf>>) -> bool {
+    !matches!(
+        (action, result),
+        (Action::Nothing, _) | (Action::Tier { .. }, Ok(None))
+    )
+}
+
+/// Record an action a rule ran on a file, and where it put the file or why
+/// it failed, in the shared history
+pub fn record(
+    rule: &str,
+    action: &Action,
+    from: &Path,
+    result: &Result<Option<PathBuf>>,
+    checksum: Option<&str>,
+    conflict: Option<&str>,
+) {
+    let Some(history) = shared() else {
+        return;
+    };
+    let error = result.as_ref().err().map(|e| format!("{:#}", e));
+    let entry = NewEntry {
+        rule,
+        action: action.kind(),
+        from,
+        to: result.as_ref().ok().and_then(|to| to.as_deref()),
+        checksum,
+        error: error.as_deref(),
+        conflict,
+    };
+    if let Err(e) = history.record(&entry) {
+        warn!("{:#}", e);
+    }
+    prune_if_due(history);
+}
+
+/// Recent actions from the shared history, newest first
+pub fn recent() -> Vec<HistoryEntry> {
+    shared()
+        .map(|history| history.entries(MAX_ENTRIES))
+        .unwrap_or_default()
+}
+
+/// An action from the shared history
+pub fn get(id: i64) -> Result<Option<HistoryEntry>> {
+    shared()
+        .context("No data directory for the history")?
+        .get(id)
+}
+
+/// Actions in the shared history that can still be undone, newest first
+pub fn undoable(since: Option<DateTime<Local>>, rule: Option<&str>) -> Result<Vec<HistoryEntry>> {
+    Ok(shared()
+        .context("No data directory for the history")?
+        .undoable(since, rule))
+}
+
+/// Every action in the shared history since a time, oldest first
+pub fn since(since: Option<DateTime<Local>>) -> Result<Vec<HistoryEntry>> {
+    Ok(shared()
+        .context("No data directory for the history")?
+        .since(since))
+}
+
+/// Undo an action from the shared history
+pub fn undo(entry: &HistoryEntry) -> Result<()> {
+    shared()
+        .context("No data directory for the history")?
+        .undo(entry)
+}
+
+/// Apply an undone action from the shared history again
+pub fn redo(entry: &HistoryEntry) -> Result<()> {
+    shared()
+        .context("No data directory for the history")?
+        .redo(entry)
+}
+
+/// Changes whenever the shared history does
+pub fn stamp() -> Option<(i64, i64)> {
+    shared()?.stamp()
+}
+
+/// Restored files, reloaded when the history changes
+#[derive(Default)]
+struct RestoredCache {
+    stamp: Option<(i64, i64)>,
+    paths: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+static RESTORED: LazyLock<Mutex<RestoredCache>> = LazyLock::new(Default::default);
+
+/// Whether an undo put this file back and it hasn't changed since, so rules
+/// should leave it alone
+pub fn is_restored(path: &Path) -> bool {
+    let Some(history) = shared() else {
+        return false;
+    };
+    let Ok(mut cache) = RESTORED.lock() else {
+        return false;
+    };
+    let current = history.stamp();
+    if current != cache.stamp {
+        cache.paths = history.restored();
+        cache.stamp = current;
+    }
+    cache
+        .paths
+        .get(path)
+        .is_some_and(|&restored| modified(path) == restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry<'a>(rule: &'a str, action: &'a str, from: &'a Path, to: &'a Path) -> NewEntry<'a> {
+        NewEntry {
+            rule,
+            action,
+            from,
+            to: Some(to),
+            checksum: None,
+            error: None,
+            conflict: None,
+        }
+    }
+
+    #[test]
+    fn test_record_and_undo() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::open(&dir.path().join("history.db")).unwrap();
+        let from = dir.path().join("inbox/report.pdf");
+        let to = dir.path().join("docs/report.pdf");
+        std::fs::create_dir_all(to.parent().unwrap()).unwrap();
+        std::fs::write(&to, "x").unwrap();
+        history.record(&entry("PDFs", "move", &from, &to)).unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        history.record(&entry("Rename", "rename", &a, &b)).unwrap();
+        let id = history
+            .record(&NewEntry {
+                checksum: Some("blake3:00"),
+                error: Some("disk full"),
+                to: None,
+                ..entry("Copy", "copy", &a, &b)
+            })
+            .unwrap();
+
+        let entries = history.entries(MAX_ENTRIES);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].rule, "Rename");
+        let failed = history.get(id).unwrap().unwrap();
+        assert_eq!(failed.error.as_deref(), Some("disk full"));
+        assert_eq!(failed.checksum.as_deref(), Some("blake3:00"));
+        assert!(history.undo(&failed).is_err());
+        let moved = &entries[2];
+        assert_eq!(moved.to.as_deref(), Some(to.as_path()));
+
+        history.undo(moved).unwrap();
+        assert!(from.exists());
+        assert!(!to.exists());
+        let entries = history.entries(MAX_ENTRIES);
+        assert!(entries[2].undone.is_some());
+        assert!(entries[1].undone.is_none());
+        assert!(history.undo(&entries[2]).is_err());
+        // The file isn't where the rename put it
+        assert!(history.undo(&entries[1]).is_err());
+
+        let restored = history.restored();
+        assert_eq!(restored.get(&from), Some(&modified(&from)));
+
+        let all = history.since(None);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].rule, "PDFs");
+        let later = Local::now() + chrono::Duration::minutes(1);
+        assert!(history.since(Some(later)).is_empty());
+        let csv = to_csv(&all);
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].contains(",PDFs,move,"));
+        assert!(lines[3].ends_with(",,failed,disk full,blake3:00,,"));
+    }
+
+    #[test]
+    fn test_upgrade_schema() {
+        // A database from before `conflict` was recorded
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE actions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, time INTEGER NOT NULL,
+                rule TEXT NOT NULL, action TEXT NOT NULL, source TEXT NOT NULL,
+                destination TEXT, checksum TEXT, outcome TEXT NOT NULL, error TEXT,
+                undone INTEGER, restored_modified INTEGER
+            );
+            PRAGMA user_version = 1;",
+        )
+        .unwrap();
+        drop(conn);
+
+        let history = History::open(&path).unwrap();
+        let (from, to) = (dir.path().join("a.pdf"), dir.path().join("b/a (1).pdf"));
+        history
+            .record(&NewEntry {
+                conflict: Some("a.pdf exists, renamed to a (1).pdf"),
+                ..entry("PDFs", "move", &from, &to)
+            })
+            .unwrap();
+        let entries = history.entries(10);
+        assert_eq!(
+            entries[0].conflict.as_deref(),
+            Some("a.pdf exists, renamed to a (1).pdf")
+        );
+    }
+
+    #[test]
+    fn test_undo_checks_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::open(&dir.path().join("history.db")).unwrap();
+        let original = dir.path().join("photo.jpg");
+        let copy = dir.path().join("backup/photo.jpg");
+        std::fs::create_dir_all(copy.parent().unwrap()).unwrap();
+        std::fs::write(&original, "pixels").unwrap();
+        std::fs::write(&copy, "pixels").unwrap();
+        let checksum = format!(
+            "blake3:{}",
+            crate::hashing::hash_file(&copy, crate::hashing::HashAlgorithm::Blake3).unwrap()
+        );
+        let copy_id = history
+            .record(&NewEntry {
+                checksum: Some(&checksum),
+                ..entry("Backup", "copy", &original, &copy)
+            })
+            .unwrap();
+        let moved = dir.path().join("photos/photo.jpg");
+        let move_id = history
+            .record(&NewEntry {
+                checksum: Some(&checksum),
+                ..entry(
+                    "Photos",
+                    "move",
+                    &dir.path().join("inbox/photo.jpg"),
+                    &moved,
+                )
+            })
+            .unwrap();
+
+        // The moved file has been edited since
+        std::fs::create_dir_all(moved.parent().unwrap()).unwrap();
+        std::fs::write(&moved, "retouched").unwrap();
+        let entry = history.get(move_id).unwrap().unwrap();
+        assert!(entry.can_undo());
+        let error = history.undo(&entry).unwrap_err().to_string();
+        assert!(error.contains("has changed since the move"), "{}", error);
+        assert!(moved.exists());
+
+        let entry = history.get(copy_id).unwrap().unwrap();
+        history.undo(&entry).unwrap();
+        assert!(!copy.exists());
+        assert!(original.exists());
+        assert!(!history.get(copy_id).unwrap().unwrap().can_undo());
+    }
+
+    #[test]
+    fn test_rollback() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::open(&dir.path().join("history.db")).unwrap();
+        let path = |name: &str| dir.path().join(name);
+        std::fs::write(path("c.pdf"), "x").unwrap();
+        // a.pdf was moved to b.pdf, then renamed to c.pdf
+        history
+            .record(&entry("PDFs", "move", &path("a.pdf"), &path("b.pdf")))
+            .unwrap();
+        history
+            .record(&entry("Rename", "rename", &path("b.pdf"), &path("c.pdf")))
+            .unwrap();
+        // The copy has since been deleted
+        history
+            .record(&entry("PDFs", "copy", &path("c.pdf"), &path("gone.pdf")))
+            .unwrap();
+
+        let entries = history.undoable(None, None);
+        assert_eq!(entries.len(), 3);
+        let problems = check_rollback(&entries);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].0.action, "copy");
+        assert!(problems[0].1.to_string().contains("no longer there"));
+
+        let pdfs = history.undoable(None, Some("PDFs"));
+        assert_eq!(pdfs.len(), 2);
+        // Without the rename undone first, b.pdf isn't there
+        assert_eq!(check_rollback(&pdfs).len(), 2);
+
+        for entry in &entries[1..] {
+            history.undo(entry).unwrap();
+        }
+        assert!(path("a.pdf").exists());
+        assert_eq!(history.undoable(None, None).len(), 1);
+
+        // Taking the undos back, oldest first, puts the file where the
+        // rename left it
+        for entry in entries[1..].iter().rev() {
+            history.redo(entry).unwrap();
+        }
+        assert!(!path("a.pdf").exists());
+        assert!(path("c.pdf").exists());
+        assert_eq!(history.undoable(None, None).len(), 3);
+        for entry in &entries[1..] {
+            history.undo(entry).unwrap();
+        }
+        let later = Local::now() + chrono::Duration::minutes(1);
+        assert!(history.undoable(Some(later), None).is_empty());
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = Local.with_ymd_and_hms(2024, 5, 1, 15, 0, 0).unwrap();
+        let at = |h, m| Local.with_ymd_and_hms(2024, 5, 1, h, m, 0).unwrap();
+        assert_eq!(parse_since("10 minutes ago", now).unwrap(), at(14, 50));
+        assert_eq!(parse_since("2h", now).unwrap(), at(13, 0));
+        assert_eq!(parse_since("14:30", now).unwrap(), at(14, 30));
+        assert_eq!(parse_since("2024-05-01 09:15", now).unwrap(), at(9, 15));
+        assert_eq!(parse_since("2024-05-01", now).unwrap(), at(0, 0));
+        assert!(parse_since("yesterday-ish", now).is_err());
+    }
+
+    #[test]
+    fn test_prune_and_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::open(&dir.path().join("history.db")).unwrap();
+        let legacy = dir.path().join("history.jsonl");
+        std::fs::write(
+            &legacy,
+            concat!(
+                r#"{"id":"1-1-0","time":"2020-01-01T10:00:00+00:00","rule":"PDFs","action":"move","from":"/in/a.pdf","to":"/docs/a.pdf"}"#,
+                "\n",
+                r#"{"undo":"1-1-0","time":"2020-01-01T11:00:00+00:00"}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+        assert_eq!(history.import_jsonl(&legacy).unwrap(), Some(1));
+        // Only once, even if the file is still there
+        assert_eq!(history.import_jsonl(&legacy).unwrap(), None);
+        assert_eq!(history.entries(MAX_ENTRIES).len(), 1);
+        history
+            .record(&entry(
+                "PDFs",
+                "move",
+                Path::new("/in/b.pdf"),
+                Path::new("/docs/b.pdf"),
+            ))
+            .unwrap();
+
+        let entries = history.entries(MAX_ENTRIES);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].from, PathBuf::from("/in/a.pdf"));
+        assert!(entries[1].undone.is_some());
+
+        assert_eq!(history.prune(30).unwrap(), 1);
+        let entries = history.entries(MAX_ENTRIES);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].from, PathBuf::from("/in/b.pdf"));
+    }
+}
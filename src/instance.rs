@@ -0,0 +1,77 @@
+//! Named daemon instances
+//!
+//! `--instance NAME` selects a separate daemon with its own config
+//! (`~/.config/hazelnut/NAME.toml` unless `--config` is given), PID file, IPC
+//! socket, log file, data files and autostart entry, so e.g. personal and
+//! work folders can have their own rules and be started and stopped
+//! independently. Without it, the default instance uses the usual paths.
+
+use anyhow::Result;
+use std::sync::Mutex;
+
+static INSTANCE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Select the instance for this process (None = the default instance)
+pub fn set(name: Option<&str>) -> Result<()> {
+    if let Some(name) = name {
+        validate(name)?;
+    }
+    if let Ok(mut instance) = INSTANCE.lock() {
+        *instance = name.map(str::to_string);
+    }
+    Ok(())
+}
+
+/// Name of the selected instance (None for the default one)
+pub fn name() -> Option<String> {
+    INSTANCE.lock().ok().and_then(|instance| instance.clone())
+}
+
+/// `base` with the instance name appended, e.g. `hazelnutd-work`
+pub fn suffixed(base: &str) -> String {
+    match name() {
+        Some(name) => format!("{}-{}", base, name),
+        None => base.to_string(),
+    }
+}
+
+/// Arguments that select this instance, for starting `hazelnutd`
+pub fn daemon_args() -> Vec<String> {
+    name()
+        .map(|name| vec!["--instance".to_string(), name])
+        .unwrap_or_default()
+}
+
+/// Names end up in file names, so only letters, digits, `-` and `_`
+fn validate(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        anyhow::bail!(
+            "Invalid instance name '{}': use letters, digits, '-' and '_'",
+            name
+        );
+    }
+    // Its config file would be the default instance's
+    if name == "config" {
+        anyhow::bail!("Instance name 'config' is reserved");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate("work").is_ok());
+        assert!(validate("home-2_b").is_ok());
+        assert!(validate("").is_err());
+        assert!(validate("../work").is_err());
+        assert!(validate("my work").is_err());
+        assert!(validate("config").is_err());
+    }
+}
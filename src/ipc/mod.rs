@@ -11,16 +11,20 @@ use std::path::PathBuf;
 pub fn socket_path() -> PathBuf {
     dirs::runtime_dir()
         .or_else(dirs::data_dir)
-        .map(|d| d.join("hazelnut.sock"))
+        .map(|d| d.join(format!("{}.sock", crate::instance::suffixed("hazelnut"))))
         .unwrap_or_else(|| {
             #[cfg(unix)]
             {
                 let uid = crate::current_uid();
-                PathBuf::from(format!("/tmp/hazelnut-{}.sock", uid))
+                let name = crate::instance::suffixed(&format!("hazelnut-{}", uid));
+                PathBuf::from(format!("/tmp/{}.sock", name))
             }
             #[cfg(not(unix))]
             {
-                PathBuf::from("/tmp/hazelnut.sock")
+                PathBuf::from(format!(
+                    "/tmp/{}.sock",
+                    crate::instance::suffixed("hazelnut")
+                ))
             }
         })
 }
@@ -43,6 +47,46 @@ pub enum DaemonCommand {
 
     /// Get statistics
     GetStats,
+
+    /// Get progress of in-flight transfers and folder scans
+    GetProgress,
+
+    /// Stop applying rules to a watched folder until resumed
+    PauseWatch { path: PathBuf },
+
+    /// Resume a paused watch
+    ResumeWatch { path: PathBuf },
+
+    /// Raw filesystem notifications received after `after` (None starts
+    /// recording), optionally only those under a watched folder
+    GetRawEvents {
+        after: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<PathBuf>,
+    },
+
+    /// Cheap liveness check, for `status --check`
+    Health,
+
+    /// Re-evaluate the files already in a watched folder (all watches if None)
+    Scan {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<PathBuf>,
+    },
+
+    /// Keep the connection open and stream activity events as they happen
+    Subscribe,
+
+    /// Switch to a `[profile.NAME]` (None: all watches and rules)
+    SetProfile {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    /// The rules in use (with the active profile applied)
+    GetRules,
+
+    /// The latest actions from the history, newest first
+    GetHistory { limit: usize },
 }
 
 /// Messages from daemon to TUI
@@ -56,11 +100,61 @@ pub enum DaemonResponse {
         watches: usize,
         rules: usize,
         files_processed: u64,
+        /// Files no rule matched
+        #[serde(default)]
+        files_unmatched: u64,
+        /// Configured watch paths
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        watch_paths: Vec<PathBuf>,
+        /// Paused watches (canonical paths)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        paused_watches: Vec<PathBuf>,
+        /// The daemon's log file
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        log_file: Option<PathBuf>,
+        /// Active profile
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<String>,
     },
 
     /// Log entries
     Log { entries: Vec<String> },
 
+    /// In-flight transfers and running folder scans
+    Progress {
+        transfers: Vec<crate::progress::TransferProgress>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        scans: Vec<crate::progress::ScanProgress>,
+    },
+
+    /// Raw filesystem notifications, and the `after` value for the next request
+    RawEvents {
+        events: Vec<crate::watcher::RawEvent>,
+        next: u64,
+    },
+
+    /// Liveness information
+    Health {
+        /// Seconds since the main loop last polled for events
+        last_poll_seconds: u64,
+        /// Configured watches that couldn't be set up
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        failed_watches: Vec<PathBuf>,
+    },
+
+    /// Rules in use
+    Rules { rules: Vec<crate::rules::Rule> },
+
+    /// Recent actions, newest first
+    History {
+        entries: Vec<crate::history::HistoryEntry>,
+    },
+
+    /// A live event, streamed after `Subscribe`
+    Activity {
+        event: crate::activity::ActivityEvent,
+    },
+
     /// Acknowledgment
     Ok,
 
@@ -105,6 +199,66 @@ pub fn send_command(_cmd: &DaemonCommand) -> Result<DaemonResponse> {
     anyhow::bail!("IPC is only supported on Unix platforms")
 }
 
+/// Live activity from the daemon; iterating blocks until the next event and
+/// ends when the daemon closes the connection
+#[cfg(unix)]
+pub struct ActivitySubscription {
+    reader: std::io::BufReader<std::os::unix::net::UnixStream>,
+}
+
+#[cfg(unix)]
+impl Iterator for ActivitySubscription {
+    type Item = crate::activity::ActivityEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use std::io::BufRead;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            if let Ok(DaemonResponse::Activity { event }) = serde_json::from_str(&line) {
+                return Some(event);
+            }
+        }
+    }
+}
+
+/// Subscribe to the daemon's activity events
+#[cfg(unix)]
+pub fn subscribe() -> Result<ActivitySubscription> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let path = socket_path();
+    let mut stream = UnixStream::connect(&path)
+        .with_context(|| format!("Failed to connect to daemon at {}", path.display()))?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut line = serde_json::to_string(&DaemonCommand::Subscribe)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    stream.flush()?;
+
+    let mut reader = BufReader::new(stream);
+    let mut response = String::new();
+    reader
+        .read_line(&mut response)
+        .context("Failed to read daemon response")?;
+    match serde_json::from_str(&response).context("Failed to parse daemon response")? {
+        DaemonResponse::Ok => {}
+        DaemonResponse::Error { message } => anyhow::bail!(message),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    }
+    // Events may be far apart
+    reader.get_ref().set_read_timeout(None)?;
+    Ok(ActivitySubscription { reader })
+}
+
 /// Check if the daemon is running by probing the socket.
 pub fn is_daemon_running() -> bool {
     #[cfg(unix)]
@@ -116,3 +270,116 @@ pub fn is_daemon_running() -> bool {
         false
     }
 }
+
+/// A watched folder in a `StatusReport`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchStatus {
+    pub path: PathBuf,
+    pub paused: bool,
+}
+
+/// Daemon status for `status --json`: what the PID file says, plus what the
+/// daemon reports over IPC (left empty if the socket doesn't answer)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusReport {
+    /// Named instance (None for the default one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    pub running: bool,
+    pub pid: Option<i32>,
+    pub pid_file: PathBuf,
+    pub log_file: PathBuf,
+    /// Whether the daemon answered over IPC
+    pub responding: bool,
+    pub uptime_seconds: Option<u64>,
+    /// Active profile (None if all watches and rules are active)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    pub rules: Option<usize>,
+    pub files_processed: Option<u64>,
+    pub files_unmatched: Option<u64>,
+    pub watches: Vec<WatchStatus>,
+    /// Latest activity log entries, oldest first
+    pub recent_activity: Vec<String>,
+    /// Copies in progress
+    #[serde(default)]
+    pub transfers: Vec<crate::progress::TransferProgress>,
+}
+
+impl StatusReport {
+    /// Gather the daemon's status, with up to `log_entries` recent log entries
+    #[cfg(unix)]
+    pub fn collect(log_entries: usize) -> Self {
+        let pid = crate::daemon_pid();
+        let mut report = Self {
+            instance: crate::instance::name(),
+            running: pid.is_some(),
+            pid,
+            pid_file: crate::daemon_pid_file(),
+            log_file: crate::daemon_log_file(),
+            responding: false,
+            uptime_seconds: None,
+            profile: None,
+            rules: None,
+            files_processed: None,
+            files_unmatched: None,
+            watches: Vec::new(),
+            recent_activity: Vec::new(),
+            transfers: Vec::new(),
+        };
+        if !report.running {
+            return report;
+        }
+
+        if let Ok(DaemonResponse::Status {
+            uptime_seconds,
+            rules,
+            files_processed,
+            files_unmatched,
+            watch_paths,
+            paused_watches,
+            log_file,
+            profile,
+            ..
+        }) = send_command(&DaemonCommand::Status)
+        {
+            report.responding = true;
+            if let Some(log_file) = log_file {
+                report.log_file = log_file;
+            }
+            report.uptime_seconds = Some(uptime_seconds);
+            report.profile = profile;
+            report.rules = Some(rules);
+            report.files_processed = Some(files_processed);
+            report.files_unmatched = Some(files_unmatched);
+            report.watches = watch_paths
+                .into_iter()
+                .map(|path| WatchStatus {
+                    paused: paused_watches.contains(&crate::watcher::watch_key(&path)),
+                    path,
+                })
+                .collect();
+        } else {
+            // Without IPC, fall back to what the process table knows
+            #[cfg(target_os = "linux")]
+            {
+                report.uptime_seconds =
+                    pid.and_then(|pid| crate::read_process_uptime_secs(pid as u32));
+            }
+        }
+
+        if report.responding
+            && let Ok(DaemonResponse::Log { entries }) =
+                send_command(&DaemonCommand::GetLog { limit: log_entries })
+        {
+            report.recent_activity = entries;
+        }
+        if report.responding
+            && let Ok(DaemonResponse::Progress { transfers, .. }) =
+                send_command(&DaemonCommand::GetProgress)
+        {
+            report.transfers = transfers;
+        }
+        report
+    }
+}
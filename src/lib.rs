@@ -2,18 +2,50 @@
 //!
 //! A Hazel-like file organization tool with a TUI interface.
 
+pub mod activity;
+pub mod api;
+#[cfg(feature = "tui")]
 pub mod app;
-#[cfg(unix)]
 pub mod autostart;
 pub mod config;
+pub mod daemon_cli;
+pub mod date_added;
+pub mod dedupe;
+pub mod fingerprint;
+pub mod hash_cache;
+pub mod hashing;
+pub mod hazel;
+pub mod history;
+pub mod instance;
 pub mod ipc;
+pub mod logfile;
 pub mod notifications;
+pub mod organize;
+pub mod plugins;
+pub mod presets;
+pub mod preview;
+pub mod progress;
+pub mod quarantine;
+pub mod quiet_hours;
+pub mod quota;
+pub mod report;
+pub mod retention;
 pub mod rules;
+pub mod schedule;
+pub mod service;
+pub mod sftp;
+pub mod shadow;
+pub mod stats;
+pub mod tester;
+#[cfg(feature = "tui")]
 pub mod theme;
+#[cfg(feature = "update")]
+pub mod update;
 pub mod watcher;
 
 pub use config::Config;
 pub use rules::{Action, Condition, Rule, RuleEngine};
+#[cfg(feature = "tui")]
 pub use theme::Theme;
 pub use watcher::Watcher;
 
@@ -24,6 +56,60 @@ pub fn process_is_running(pid: i32) -> bool {
     unsafe { libc::kill(pid, 0) == 0 }
 }
 
+/// Folder for the daemon's log file: `~/.local/state/hazelnut/` on all platforms
+pub fn daemon_state_dir() -> std::path::PathBuf {
+    dirs::state_dir()
+        .unwrap_or_else(|| {
+            dirs::home_dir()
+                .map(|h| h.join(".local").join("state"))
+                .unwrap_or_else(|| std::path::PathBuf::from("/tmp"))
+        })
+        .join("hazelnut")
+}
+
+/// The daemon's PID file, in `$XDG_RUNTIME_DIR/hazelnut/` where there is
+/// one (so it can't outlive the session), otherwise next to the log file
+pub fn daemon_pid_file() -> std::path::PathBuf {
+    dirs::runtime_dir()
+        .map(|d| d.join("hazelnut"))
+        .unwrap_or_else(daemon_state_dir)
+        .join(format!("{}.pid", instance::suffixed("hazelnutd")))
+}
+
+/// The daemon's default log file, used unless `log_file` is set
+pub fn daemon_log_file() -> std::path::PathBuf {
+    daemon_state_dir().join(format!("{}.log", instance::suffixed("hazelnutd")))
+}
+
+/// The TUI's log file; the TUI can't log to the terminal it draws on
+pub fn tui_log_file() -> std::path::PathBuf {
+    daemon_state_dir().join(format!("{}.log", instance::suffixed("hazelnut")))
+}
+
+/// PID of the running daemon, if any. The daemon holds a lock on its PID
+/// file, so a leftover file whose PID was reused doesn't count. The location
+/// used by older versions is checked too, so upgrading doesn't start a
+/// second daemon.
+#[cfg(unix)]
+pub fn daemon_pid() -> Option<i32> {
+    use fs2::FileExt;
+
+    let read_pid = |path: &std::path::Path| -> Option<i32> {
+        std::fs::read_to_string(path).ok()?.trim().parse().ok()
+    };
+    let pid_file = daemon_pid_file();
+    if let Ok(file) = std::fs::File::open(&pid_file)
+        && FileExt::try_lock_shared(&file).is_err()
+    {
+        return read_pid(&pid_file);
+    }
+    let legacy = daemon_state_dir().join("hazelnutd.pid");
+    if legacy != pid_file && instance::name().is_none() {
+        return read_pid(&legacy).filter(|&pid| process_is_running(pid));
+    }
+    None
+}
+
 /// Safe wrapper around `libc::sysconf`.
 #[cfg(unix)]
 pub fn clock_ticks_per_sec() -> u64 {
@@ -56,6 +142,12 @@ pub fn format_uptime(running_secs: u64) -> String {
 /// Returns a formatted uptime string or `None` if unavailable.
 #[cfg(target_os = "linux")]
 pub fn read_process_uptime(pid: u32) -> Option<String> {
+    read_process_uptime_secs(pid).map(format_uptime)
+}
+
+/// Seconds a process has been running, from /proc (Linux only)
+#[cfg(target_os = "linux")]
+pub fn read_process_uptime_secs(pid: u32) -> Option<u64> {
     let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
     let parts: Vec<&str> = stat.split_whitespace().collect();
     if parts.len() <= 21 {
@@ -66,8 +158,7 @@ pub fn read_process_uptime(pid: u32) -> Option<String> {
     let uptime: f64 = uptime_str.split_whitespace().next()?.parse().ok()?;
     let clock_ticks = clock_ticks_per_sec();
     let start_secs = start_ticks / clock_ticks;
-    let running_secs = uptime as u64 - start_secs;
-    Some(format_uptime(running_secs))
+    Some((uptime as u64).saturating_sub(start_secs))
 }
 
 /// Current version from Cargo.toml
@@ -86,6 +177,7 @@ pub enum VersionCheck {
 
 /// Compare semver versions, returns true if `latest` is newer than `current`.
 /// Pre-release suffixes (everything after `-`) are stripped before comparing.
+#[cfg(feature = "update")]
 fn version_is_newer(latest: &str, current: &str) -> bool {
     let parse = |v: &str| -> Vec<u32> {
         let base = v.split('-').next().unwrap_or(v);
@@ -130,28 +222,38 @@ pub fn expand_path(path: &std::path::Path) -> std::path::PathBuf {
     };
 
     // Then expand $VAR and ${VAR} patterns
+    std::path::PathBuf::from(expand_env(&expanded))
+}
+
+/// Expand environment variables ($VAR, ${VAR}) in a string; unset ones are
+/// left as written
+pub fn expand_env(s: &str) -> String {
     use std::sync::LazyLock;
     static ENV_RE: LazyLock<regex::Regex> = LazyLock::new(|| {
         regex::Regex::new(r"\$\{([^}]+)\}|\$([A-Za-z_][A-Za-z0-9_]*)").expect("invalid env regex")
     });
 
-    let result = ENV_RE.replace_all(&expanded, |caps: &regex::Captures| {
-        let var_name = caps
-            .get(1)
-            .or_else(|| caps.get(2))
-            .map(|m| m.as_str())
-            .unwrap_or("");
-        std::env::var(var_name).unwrap_or_else(|_| caps[0].to_string())
-    });
-
-    std::path::PathBuf::from(result.as_ref())
+    ENV_RE
+        .replace_all(s, |caps: &regex::Captures| {
+            let var_name = caps
+                .get(1)
+                .or_else(|| caps.get(2))
+                .map(|m| m.as_str())
+                .unwrap_or("");
+            std::env::var(var_name).unwrap_or_else(|_| caps[0].to_string())
+        })
+        .into_owned()
 }
 
 /// Detected package manager for installation
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PackageManager {
     Cargo,
-    Homebrew { formula: String },
+    Homebrew {
+        formula: String,
+    },
+    /// Unpacked from a GitHub release archive, updated by `self-update`
+    Release,
 }
 
 impl PackageManager {
@@ -160,6 +262,7 @@ impl PackageManager {
         match self {
             PackageManager::Cargo => "cargo",
             PackageManager::Homebrew { .. } => "brew",
+            PackageManager::Release => "release archive",
         }
     }
 
@@ -168,6 +271,7 @@ impl PackageManager {
         match self {
             PackageManager::Cargo => "cargo install hazelnut".to_string(),
             PackageManager::Homebrew { formula } => format!("brew upgrade {}", formula),
+            PackageManager::Release => "hazelnut self-update".to_string(),
         }
     }
 }
@@ -200,6 +304,20 @@ pub fn detect_package_manager() -> PackageManager {
                 formula: "hazelnut".to_string(),
             };
         }
+
+        // cargo install puts it in $CARGO_HOME/bin, a build from source runs
+        // from target/, and distribution packages (pacman) live in /usr/bin
+        let cargo_bin = std::env::var_os("CARGO_HOME")
+            .map(std::path::PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))
+            .map(|cargo_home| cargo_home.join("bin"));
+        let in_cargo_bin = cargo_bin.is_some_and(|bin| exe_path.starts_with(bin));
+        let in_target = exe_path
+            .ancestors()
+            .any(|dir| dir.file_name().is_some_and(|name| name == "target"));
+        if !in_cargo_bin && !in_target && !exe_path.starts_with("/usr/bin") {
+            return PackageManager::Release;
+        }
     }
 
     // Default to cargo
@@ -258,15 +376,42 @@ pub fn run_update(pm: &PackageManager) -> Result<(), String> {
                 Err(e) => Err(format!("Failed to run brew: {}", e)),
             }
         }
+        #[cfg(feature = "update")]
+        PackageManager::Release => update::self_update().map(|_| ()),
+        #[cfg(not(feature = "update"))]
+        PackageManager::Release => Err("Built without update checks".to_string()),
     }
 }
 
+/// Check for updates on crates.io, or on GitHub Releases when crates.io
+/// can't be reached
+pub fn check_for_updates() -> VersionCheck {
+    check_for_updates_timeout(std::time::Duration::from_secs(5))
+}
+
+/// Check for updates on crates.io, or on GitHub Releases when crates.io
+/// can't be reached, with custom timeout.
+pub fn check_for_updates_timeout(timeout: std::time::Duration) -> VersionCheck {
+    let check = check_for_updates_crates_io_timeout(timeout);
+    #[cfg(feature = "update")]
+    if let VersionCheck::CheckFailed(crates_io) = &check {
+        return match update::latest_release(timeout) {
+            Ok(release) => release.version_check(),
+            Err(github) => {
+                VersionCheck::CheckFailed(format!("crates.io: {}; GitHub: {}", crates_io, github))
+            }
+        };
+    }
+    check
+}
+
 /// Check for updates using crates.io API (no rate limits).
 pub fn check_for_updates_crates_io() -> VersionCheck {
     check_for_updates_crates_io_timeout(std::time::Duration::from_secs(5))
 }
 
 /// Check for updates using crates.io API with custom timeout.
+#[cfg(feature = "update")]
 pub fn check_for_updates_crates_io_timeout(timeout: std::time::Duration) -> VersionCheck {
     let url = "https://crates.io/api/v1/crates/hazelnut";
 
@@ -303,3 +448,9 @@ pub fn check_for_updates_crates_io_timeout(timeout: std::time::Duration) -> Vers
         Err(e) => VersionCheck::CheckFailed(format!("Request failed: {}", e)),
     }
 }
+
+/// Update checks are left out of builds without the `update` feature
+#[cfg(not(feature = "update"))]
+pub fn check_for_updates_crates_io_timeout(_timeout: std::time::Duration) -> VersionCheck {
+    VersionCheck::CheckFailed("Built without update checks".to_string())
+}
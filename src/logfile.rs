@@ -0,0 +1,527 @@
+//! The daemon's log file, with rotation
+//!
+//! The daemon writes its log itself rather than relying on a service manager
+//! to capture its output. The file is rotated once it would grow past
+//! `log_max_size_mb` or is older than `log_max_age_days`; the last
+//! `log_max_files` old logs are kept next to it as `hazelnutd.log.1`
+//! (newest), `hazelnutd.log.2` and so on.
+//!
+//! With `log_format = "json"` each line is a JSON object, and every action
+//! a rule runs is logged as its own event with the rule, path, action,
+//! outcome and how long it took, which `hazelnut logs` reads back.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+use crate::config::GeneralConfig;
+use crate::rules::Action;
+
+/// Target of the action events in the JSON log
+pub const ACTION_TARGET: &str = "hazelnut::action";
+
+/// Whether the log is JSON, so actions are logged as events
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// When the log file is rotated and how many old ones are kept
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rotation {
+    /// Bytes before rotating (0 = no limit)
+    pub max_size: u64,
+    /// Age before rotating (None = no limit)
+    pub max_age: Option<Duration>,
+    /// Rotated files kept
+    pub max_files: usize,
+}
+
+impl Rotation {
+    /// Rotation settings from `[general]`
+    pub fn from_config(general: &GeneralConfig) -> Self {
+        Self {
+            max_size: general.log_max_size_mb.saturating_mul(1024 * 1024),
+            max_age: (general.log_max_age_days > 0)
+                .then(|| Duration::from_secs(u64::from(general.log_max_age_days) * 86400)),
+            max_files: general.log_max_files,
+        }
+    }
+}
+
+/// The log file being written
+#[derive(Debug)]
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    /// When the current file was started
+    started: SystemTime,
+    rotation: Rotation,
+}
+
+impl LogFile {
+    fn open(path: &Path, rotation: Rotation) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            started: metadata.created().unwrap_or_else(|_| SystemTime::now()),
+            file,
+            rotation,
+        })
+    }
+
+    fn write(&mut self, buf: &[u8], now: SystemTime) -> std::io::Result<()> {
+        if self.is_due(buf.len() as u64, now) {
+            self.rotate(now)?;
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(())
+    }
+
+    /// Whether writing `len` more bytes should start a new file. An empty
+    /// file is never rotated, so one long line can't rotate every write.
+    fn is_due(&self, len: u64, now: SystemTime) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let too_big = self.rotation.max_size > 0 && self.size + len > self.rotation.max_size;
+        let too_old = self.rotation.max_age.is_some_and(|max_age| {
+            now.duration_since(self.started)
+                .is_ok_and(|age| age >= max_age)
+        });
+        too_big || too_old
+    }
+
+    fn rotate(&mut self, now: SystemTime) -> std::io::Result<()> {
+        let max_files = self.rotation.max_files;
+        if max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            let _ = std::fs::remove_file(rotated_path(&self.path, max_files));
+            for n in (1..max_files).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    std::fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        // Files from a larger `log_max_files` setting
+        let mut n = max_files + 1;
+        while std::fs::remove_file(rotated_path(&self.path, n)).is_ok() {
+            n += 1;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.started = now;
+        Ok(())
+    }
+}
+
+static LOG: Mutex<Option<LogFile>> = Mutex::new(None);
+
+/// The log file for a config: `log_file`, or the daemon's default
+pub fn path(general: &GeneralConfig) -> PathBuf {
+    general
+        .log_file
+        .as_deref()
+        .map(crate::expand_path)
+        .unwrap_or_else(crate::daemon_log_file)
+}
+
+/// Open the configured log file, or switch to it if the config changed
+pub fn init(general: &GeneralConfig) -> Result<()> {
+    init_at(&path(general), Rotation::from_config(general))
+}
+
+/// Open a log file, or switch to it
+pub fn init_at(path: &Path, rotation: Rotation) -> Result<()> {
+    let mut log = LOG
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Log file lock poisoned"))?;
+    match log.as_mut() {
+        Some(current) if current.path == path => current.rotation = rotation,
+        _ => {
+            let file = LogFile::open(path, rotation)
+                .with_context(|| format!("Failed to open log file {}", path.display()))?;
+            *log = Some(file);
+        }
+    }
+    Ok(())
+}
+
+/// Writes to the log file opened by `init`; writes before that are dropped.
+/// Pass `|| Writer` to `tracing_subscriber`'s `with_writer`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Writer;
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Ok(mut log) = LOG.lock()
+            && let Some(log) = log.as_mut()
+        {
+            log.write(buf, SystemTime::now())?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if let Ok(mut log) = LOG.lock()
+            && let Some(log) = log.as_mut()
+        {
+            log.file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Log actions as events, for a JSON log
+pub fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+/// Log an action a rule ran on a file as an event of the JSON log. Text
+/// logs already say which rule matched and what failed, so nothing is
+/// added to them.
+pub fn log_action(
+    rule: &str,
+    action: &Action,
+    path: &Path,
+    result: &Result<Option<PathBuf>>,
+    duration: Duration,
+) {
+    if !JSON.load(Ordering::Relaxed) {
+        return;
+    }
+    let path = path.display().to_string();
+    let duration_ms = duration.as_millis() as u64;
+    match result {
+        Ok(destination) => {
+            let destination = destination.as_ref().map(|d| d.display().to_string());
+            tracing::info!(
+                target: ACTION_TARGET,
+                rule,
+                path,
+                action = action.kind(),
+                outcome = "ok",
+                duration_ms,
+                destination,
+                "{} {}",
+                action.kind(),
+                path
+            );
+        }
+        Err(e) => {
+            let error = format!("{:#}", e);
+            tracing::warn!(
+                target: ACTION_TARGET,
+                rule,
+                path,
+                action = action.kind(),
+                outcome = "failed",
+                duration_ms,
+                error,
+                "{} {} failed",
+                action.kind(),
+                path
+            );
+        }
+    }
+}
+
+/// An action from the JSON log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionEvent {
+    pub timestamp: DateTime<Utc>,
+    /// Empty for a watch's `unmatched_action`
+    pub rule: String,
+    pub path: PathBuf,
+    pub action: String,
+    /// `ok` or `failed`
+    pub outcome: String,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ActionEvent {
+    /// Whether the action failed
+    pub fn failed(&self) -> bool {
+        self.outcome == "failed"
+    }
+}
+
+/// Which logged actions `actions` returns
+#[derive(Debug, Clone, Default)]
+pub struct ActionFilter {
+    /// Only this rule's actions
+    pub rule: Option<String>,
+    /// Only actions since this time
+    pub since: Option<DateTime<Local>>,
+    /// Only failed actions
+    pub failed: bool,
+}
+
+impl ActionFilter {
+    fn matches(&self, event: &ActionEvent) -> bool {
+        self.rule.as_ref().is_none_or(|rule| *rule == event.rule)
+            && self.since.is_none_or(|since| event.timestamp >= since)
+            && (!self.failed || event.failed())
+    }
+}
+
+/// The actions in a JSON log and its rotated files, oldest first. Lines
+/// that aren't action events, as in a text log, are skipped.
+pub fn actions(path: &Path, filter: &ActionFilter) -> Vec<ActionEvent> {
+    let mut rotated = 0;
+    while rotated_path(path, rotated + 1).exists() {
+        rotated += 1;
+    }
+    let files = (1..=rotated)
+        .rev()
+        .map(|n| rotated_path(path, n))
+        .chain(std::iter::once(path.to_path_buf()));
+
+    let mut events = Vec::new();
+    for file in files {
+        let Ok(file) = File::open(&file) else {
+            continue;
+        };
+        for line in BufReader::new(file).lines().map_while(|line| line.ok()) {
+            if let Some(event) = parse_action(&line)
+                && filter.matches(&event)
+            {
+                events.push(event);
+            }
+        }
+    }
+    events
+}
+
+/// An action event from a line of the JSON log
+fn parse_action(line: &str) -> Option<ActionEvent> {
+    if !line.starts_with('{') {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    if value.get("target")?.as_str()? != ACTION_TARGET {
+        return None;
+    }
+    serde_json::from_value(value).ok()
+}
+
+/// Path of the `n`th rotated log (1 = newest)
+pub fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Up to `max` of the last lines logged before the current file was
+/// started, oldest first, read from the rotated files
+pub fn rotated_lines(path: &Path, max: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut n = 1;
+    while lines.len() < max {
+        let Some(older) = tail_lines(&rotated_path(path, n), max - lines.len()) else {
+            break;
+        };
+        lines.splice(0..0, older);
+        n += 1;
+    }
+    lines
+}
+
+/// The last `max` lines of a file, without reading all of it (None if it
+/// can't be opened)
+fn tail_lines(path: &Path, max: usize) -> Option<Vec<String>> {
+    const BLOCK: u64 = 64 * 1024;
+
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut start = len;
+    let mut buffer = Vec::new();
+    // One more newline than lines wanted, unless the start of the file is reached
+    while start > 0 && buffer.iter().filter(|&&b| b == b'\n').count() <= max {
+        let from = start.saturating_sub(BLOCK);
+        let mut block = vec![0u8; (start - from) as usize];
+        file.seek(SeekFrom::Start(from)).ok()?;
+        file.read_exact(&mut block).ok()?;
+        block.extend_from_slice(&buffer);
+        buffer = block;
+        start = from;
+    }
+    let text = String::from_utf8_lossy(&buffer);
+    let lines: Vec<&str> = text.lines().collect();
+    // A partial first line, if the read didn't reach the start
+    let skip = usize::from(start > 0);
+    let lines = &lines[skip.min(lines.len())..];
+    Some(
+        lines[lines.len().saturating_sub(max)..]
+            .iter()
+            .map(|line| line.to_string())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hazelnutd.log");
+        let rotation = Rotation {
+            max_size: 20,
+            max_age: Some(Duration::from_secs(3600)),
+            max_files: 2,
+        };
+        let mut log = LogFile::open(&path, rotation).unwrap();
+        let now = SystemTime::now();
+
+        // Rotated by size, keeping two old files
+        for line in [
+            "one 123456\n",
+            "two 123456\n",
+            "three 1234\n",
+            "four 12345\n",
+        ] {
+            log.write(line.as_bytes(), now).unwrap();
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "four 12345\n");
+        assert_eq!(
+            std::fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+            "three 1234\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(rotated_path(&path, 2)).unwrap(),
+            "two 123456\n"
+        );
+        assert!(!rotated_path(&path, 3).exists());
+
+        // And by age
+        log.write(b"a\n", now).unwrap();
+        log.write(b"b\n", now + Duration::from_secs(3600)).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "b\n");
+
+        assert_eq!(
+            rotated_lines(&path, 2),
+            vec!["four 12345".to_string(), "a".to_string()]
+        );
+        assert_eq!(rotated_lines(&path, 10).len(), 3);
+    }
+
+    #[test]
+    fn test_actions() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hazelnutd.log");
+        std::fs::write(
+            rotated_path(&path, 1),
+            r#"{"timestamp":"2020-01-01T00:00:00Z","level":"INFO","message":"trash /old.tmp","rule":"Old","path":"/old.tmp","action":"trash","outcome":"ok","duration_ms":2,"target":"hazelnut::action"}
+"#,
+        )
+        .unwrap();
+
+        // Written the way the daemon writes its JSON log
+        let file = path.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_writer(move || {
+                    OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&file)
+                        .unwrap()
+                }),
+        );
+        let action = Action::Copy {
+            destination: PathBuf::from("/sorted"),
+            create_destination: true,
+            on_conflict: Default::default(),
+            preserve_metadata: true,
+        };
+        // Other tests log actions too; only this block writes events
+        struct TextAgain;
+        impl Drop for TextAgain {
+            fn drop(&mut self) {
+                set_json(false);
+            }
+        }
+        tracing::subscriber::with_default(subscriber, || {
+            set_json(true);
+            let _text_again = TextAgain;
+            tracing::info!("Rule 'Photos' matched: /in/a.jpg");
+            log_action(
+                "Photos",
+                &action,
+                Path::new("/in/a.jpg"),
+                &Ok(Some(PathBuf::from("/sorted/a.jpg"))),
+                Duration::from_millis(12),
+            );
+            log_action(
+                "Photos",
+                &action,
+                Path::new("/in/b.jpg"),
+                &Err(anyhow::anyhow!("disk full")),
+                Duration::from_millis(3),
+            );
+        });
+
+        let all = actions(&path, &ActionFilter::default());
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].rule, "Old");
+        assert_eq!(all[1].destination, Some(PathBuf::from("/sorted/a.jpg")));
+        assert_eq!(all[1].duration_ms, 12);
+
+        let failed = actions(
+            &path,
+            &ActionFilter {
+                failed: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].path, PathBuf::from("/in/b.jpg"));
+        assert_eq!(failed[0].error.as_deref(), Some("disk full"));
+
+        let recent = ActionFilter {
+            rule: Some("Photos".to_string()),
+            since: Some(Local::now() - chrono::Duration::hours(1)),
+            failed: false,
+        };
+        assert_eq!(actions(&path, &recent).len(), 2);
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.log");
+        let content: String = (0..20000).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(&path, content).unwrap();
+
+        let lines = tail_lines(&path, 3).unwrap();
+        assert_eq!(lines, vec!["line 19997", "line 19998", "line 19999"]);
+        assert_eq!(tail_lines(&path, 30000).unwrap().len(), 20000);
+        assert!(tail_lines(&dir.path().join("missing.log"), 3).is_none());
+    }
+}
@@ -2,9 +2,10 @@
 //!
 //! Terminal user interface for managing file organization rules.
 
-use anyhow::Result;
-use clap::Parser;
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Parser, Debug)]
@@ -15,140 +16,1856 @@ struct Cli {
     #[arg(short, long, value_name = "FILE")]
     config: Option<PathBuf>,
 
+    /// Named daemon instance to use (default config: ~/.config/hazelnut/NAME.toml)
+    #[arg(short, long, value_name = "NAME")]
+    instance: Option<String>,
+
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
 
-    #[command(subcommand)]
-    command: Option<Commands>,
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Commands {
+    /// Start the TUI (default)
+    Ui,
+
+    /// List all rules
+    List {
+        /// Print the rules as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List, show, enable or disable rules, or add them from a preset
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
+
+    /// Import rules from another organizer
+    Import {
+        #[command(subcommand)]
+        command: ImportCommand,
+    },
+
+    /// Validate config file (`config validate` gives a detailed report)
+    Check {
+        /// Path to config file to validate
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Run rules once without watching (dry-run by default)
+    Run {
+        /// Actually perform actions (not just dry-run)
+        #[arg(long)]
+        apply: bool,
+
+        /// Target directory to process
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+
+    /// Apply the rules to the files in a folder once and exit
+    Organize {
+        /// Folder to organize (a configured watch keeps its settings)
+        dir: PathBuf,
+
+        /// Show what would be done without touching any file
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// List files in watched folders that no rule matches
+    Report {
+        /// Only list files at least this many days old (default: from config)
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+    },
+
+    /// Summarize where the shadow (candidate) rules disagree with the active rules
+    Shadow {
+        /// Clear the shadow log and start over
+        #[arg(long)]
+        clear: bool,
+    },
+
+    /// Hourly processing statistics
+    Stats {
+        #[command(subcommand)]
+        command: StatsCommand,
+    },
+
+    /// The action history
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+
+    /// Actions from the daemon's JSON log (`log_format = "json"`)
+    Logs {
+        /// Only actions by this rule
+        #[arg(long)]
+        rule: Option<String>,
+
+        /// Only actions since this time, e.g. "1h" or "2024-05-01 14:30"
+        #[arg(long, value_name = "TIME")]
+        since: Option<String>,
+
+        /// Only actions that failed
+        #[arg(long)]
+        failed: bool,
+
+        /// Print the actions as JSON, one per line
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// The cache of file content hashes
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+
+    /// Apply the `[[retention]]` policies now, instead of on their schedule
+    Retention {
+        /// List the files that would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Files set aside by delete and overwrite actions (`[quarantine]`)
+    Quarantine {
+        #[command(subcommand)]
+        command: QuarantineCommand,
+    },
+
+    /// Find files with identical contents under a folder, and resolve them
+    /// in the TUI or with --apply
+    Dedupe {
+        /// Folder to scan, with its subfolders
+        dir: PathBuf,
+
+        /// Resolve every group without asking, keeping the oldest file of each
+        #[arg(long, value_enum)]
+        apply: Option<DedupeAction>,
+
+        /// Review folder duplicates are moved to (default: <DIR>/_Duplicates)
+        #[arg(long, value_name = "DIR")]
+        to: Option<PathBuf>,
+
+        /// Skip files smaller than this, e.g. "1MB"
+        #[arg(long, value_name = "SIZE", value_parser = hazelnut::quota::ByteSize::parse)]
+        min_size: Option<hazelnut::quota::ByteSize>,
+
+        /// Also look at hidden files and folders
+        #[arg(long)]
+        hidden: bool,
+
+        /// Print the groups instead of opening the TUI
+        #[arg(long)]
+        list: bool,
+
+        /// Print the groups as JSON
+        #[arg(long, conflicts_with = "apply")]
+        json: bool,
+
+        /// With --apply, only print what would be done
+        #[arg(long, requires = "apply")]
+        dry_run: bool,
+    },
+
+    /// Revert the last action, the last N, or one by its id from the
+    /// History view
+    Undo {
+        /// Id of the action to undo
+        id: Option<i64>,
+
+        /// Undo the last N actions, newest first
+        #[arg(long, value_name = "N", conflicts_with = "id")]
+        last: Option<usize>,
+    },
+
+    /// Revert every action since a time and/or by one rule, as a batch:
+    /// nothing is touched if any of it can't be undone
+    Rollback {
+        /// Start of the batch, e.g. "10 minutes ago", "2h" or "2024-05-01 14:30"
+        #[arg(long, value_name = "TIME")]
+        since: Option<String>,
+
+        /// Only actions by this rule
+        #[arg(long)]
+        rule: Option<String>,
+
+        /// List the actions that would be reverted
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Revert what can be, even if some actions can't be
+        #[arg(long)]
+        partial: bool,
+    },
+
+    /// Inspect the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Check the notification backends in [notifications]
+    Notifications {
+        #[command(subcommand)]
+        command: NotificationsCommand,
+    },
+
+    /// Stop applying rules to a watched folder until resumed (daemon must be running)
+    Pause {
+        /// Watched folder
+        path: PathBuf,
+    },
+
+    /// Resume a paused watch
+    Resume {
+        /// Watched folder
+        path: PathBuf,
+    },
+
+    /// Switch the daemon to a `[profile.NAME]`; without a name, show the
+    /// active profile (daemon must be running)
+    Profile {
+        /// Profile to switch to
+        name: Option<String>,
+
+        /// Go back to all watches and rules
+        #[arg(long, conflicts_with = "name")]
+        all: bool,
+    },
+
+    /// Re-evaluate the files already in a watched folder, or in all
+    /// watches (daemon must be running)
+    Scan {
+        /// Watched folder (default: all watches)
+        path: Option<PathBuf>,
+
+        /// Follow the scans' progress until they finish
+        #[arg(long)]
+        wait: bool,
+    },
+
+    /// Show the daemon's recent activity; with --raw, stream filesystem
+    /// notifications as they arrive (daemon must be running)
+    Events {
+        /// Stream raw notifications from the backend, before debouncing and filters
+        #[arg(long)]
+        raw: bool,
+
+        /// Only show events under this watched folder
+        path: Option<PathBuf>,
+    },
+
+    /// Show daemon status
+    Status {
+        /// Print the status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print a shell completion script (rule and profile names are taken
+    /// from the config, so regenerate it after adding some)
+    Completions {
+        /// Shell to complete in
+        shell: clap_complete::Shell,
+
+        /// Complete `hazelnutd` instead of `hazelnut`
+        #[arg(long)]
+        daemon: bool,
+    },
+
+    /// Check for updates and install if available
+    Update {
+        /// Only check, don't install
+        #[arg(long)]
+        check: bool,
+
+        /// Print the check result as JSON (implies --check)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Replace hazelnut and hazelnutd with the latest GitHub release (for
+    /// installs from a release archive)
+    SelfUpdate,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum StatsCommand {
+    /// Export hourly counts per watch and rule (JSON by default)
+    Export {
+        /// Export as CSV instead of JSON
+        #[arg(long)]
+        csv: bool,
+
+        /// Only export the last this many days
+        #[arg(long, value_name = "DAYS")]
+        days: Option<u32>,
+
+        /// Write to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum HistoryCommand {
+    /// Export every action: what was moved where, when and by which rule
+    Export {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+
+        /// Only actions since this time, e.g. "2024-05-01" or "7d"
+        #[arg(long, value_name = "TIME")]
+        since: Option<String>,
+
+        /// Write to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum CacheCommand {
+    /// Show how many hashes are cached and how often they were reused
+    Stats {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Forget every cached hash
+    Clear,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum QuarantineCommand {
+    /// List the quarantined files, oldest first
+    List {
+        /// Print the list as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Put a quarantined file back where it was
+    Restore {
+        /// Id from `quarantine list`
+        id: String,
+
+        /// Restore it here instead (into it, if it's a folder)
+        #[arg(long, value_name = "PATH")]
+        to: Option<PathBuf>,
+    },
+    /// Remove quarantined files for good: those kept longer than
+    /// retention_days, the ones given, or all of them
+    Purge {
+        /// Ids from `quarantine list`
+        ids: Vec<String>,
+
+        /// Remove every quarantined file
+        #[arg(long, conflicts_with = "ids")]
+        all: bool,
+    },
+}
+
+/// How `dedupe --apply` resolves duplicates
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum DedupeAction {
+    /// Delete them (into the quarantine, if enabled)
+    Delete,
+    /// Replace them with hard links to the kept file
+    Hardlink,
+    /// Move them to the review folder
+    Move,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum NotificationsCommand {
+    /// Send a test notification through every configured backend
+    Test,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum RulesCommand {
+    /// List the rules with the watches that run them and their status
+    List {
+        /// Print the rules as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print a rule's full definition
+    Show {
+        /// Name of the rule
+        name: String,
+    },
+
+    /// Enable a rule in the config and have the daemon reload it
+    Enable {
+        /// Name of the rule
+        name: String,
+    },
+
+    /// Disable a rule in the config and have the daemon reload it
+    Disable {
+        /// Name of the rule
+        name: String,
+    },
+
+    /// Write a preset's rules into the config
+    Add {
+        /// Preset to add (see `rules presets`)
+        #[arg(long)]
+        preset: String,
+
+        /// Folder the rules move files to (default: the preset's)
+        #[arg(long, value_name = "DIR")]
+        dest: Option<PathBuf>,
+    },
+
+    /// List the built-in presets
+    Presets,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ImportCommand {
+    /// Convert rules exported from Hazel (a .hazelrules file) and add them
+    /// to the config
+    Hazel {
+        /// The exported rules
+        path: PathBuf,
+
+        /// Print the converted rules instead of adding them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ConfigCommand {
+    /// Check the config for unknown keys, missing rules and destinations,
+    /// conflicting options and invalid patterns, with line numbers
+    Validate {
+        /// Print the diagnostics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print a JSON Schema of the config format, for editor completion and
+    /// validation
+    Schema,
+    /// Keep the config directory in a git repository: commit edits with a
+    /// message naming what changed, then pull from and push to a remote
+    Sync {
+        /// Remote repository to sync with (saved as `origin`)
+        #[arg(long, value_name = "URL")]
+        remote: Option<String>,
+
+        /// Only commit; don't pull or push
+        #[arg(long)]
+        local: bool,
+    },
+}
+
+/// Show daemon status
+#[cfg(unix)]
+fn show_daemon_status(json: bool, config_path: Option<&Path>) -> Result<()> {
+    use hazelnut::ipc::{DaemonCommand, DaemonResponse};
+
+    if json {
+        let report = hazelnut::ipc::StatusReport::collect(STATUS_LOG_ENTRIES);
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let pid_file = hazelnut::daemon_pid_file();
+    let log_file = hazelnut::Config::load(config_path)
+        .map(|config| hazelnut::logfile::path(&config.general))
+        .unwrap_or_else(|_| hazelnut::daemon_log_file());
+
+    if let Some(pid) = hazelnut::daemon_pid() {
+        let pid = pid as u32;
+        println!("🌰 Hazelnut daemon is running");
+        if let Some(instance) = hazelnut::instance::name() {
+            println!("   Instance: {}", instance);
+        }
+        println!("   PID: {}", pid);
+        println!("   PID file: {}", pid_file.display());
+        println!("   Log file: {}", log_file.display());
+
+        #[cfg(target_os = "linux")]
+        if let Some(uptime) = hazelnut::read_process_uptime(pid) {
+            println!("   Uptime: {}", uptime);
+        }
+
+        if let Ok(DaemonResponse::Progress { transfers, .. }) =
+            hazelnut::ipc::send_command(&DaemonCommand::GetProgress)
+            && !transfers.is_empty()
+        {
+            println!("   Transfers:");
+            for transfer in &transfers {
+                println!("     {}", transfer.summary());
+            }
+        }
+    } else {
+        println!("🌰 Hazelnut daemon is not running");
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn show_daemon_status(json: bool, _config_path: Option<&Path>) -> Result<()> {
+    if json {
+        anyhow::bail!("`status --json` is only available on Unix systems");
+    }
+    if hazelnut::service::is_running() {
+        println!("🌰 Hazelnut daemon is running");
+    } else {
+        println!("🌰 Hazelnut daemon is not running");
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn show_daemon_status(_json: bool, _config_path: Option<&Path>) -> Result<()> {
+    anyhow::bail!("Daemon status is only available on Unix systems")
+}
+
+/// Recent log entries included in `status --json`
+#[cfg(unix)]
+const STATUS_LOG_ENTRIES: usize = 5;
+
+/// Print the shadow log grouped by outcome
+fn show_shadow_summary(config: &hazelnut::Config, clear: bool) -> Result<()> {
+    let log_path =
+        hazelnut::shadow::default_log_path().context("Could not determine data directory")?;
+    if clear {
+        if log_path.exists() {
+            std::fs::remove_file(&log_path)?;
+        }
+        println!("✓ Shadow log cleared");
+        return Ok(());
+    }
+
+    match config.shadow.rules_file {
+        Some(ref rules_file) => println!("Shadow rules: {}", rules_file.display()),
+        None => println!("Shadow evaluation is off (set [shadow] rules_file to enable it)"),
+    }
+
+    let divergences = hazelnut::shadow::read_log(&log_path)?;
+    let Some(first) = divergences.first() else {
+        println!("✓ No differences recorded");
+        return Ok(());
+    };
+    println!(
+        "{} files handled differently since {}",
+        divergences.len(),
+        first.timestamp.format("%Y-%m-%d %H:%M")
+    );
+    for group in hazelnut::shadow::summarize(&divergences) {
+        println!(
+            "  {:>4}× {} → {}",
+            group.count, group.active, group.candidate
+        );
+        println!("         e.g. {}", group.example.display());
+    }
+    Ok(())
+}
+
+/// Organize a folder once and print a summary table
+fn organize(config_path: Option<&Path>, dir: &Path, dry_run: bool) -> Result<()> {
+    let config = hazelnut::Config::load(config_path)?;
+    hazelnut::quiet_hours::set(config.quiet_hours.clone());
+    hazelnut::plugins::set(config.plugins.clone());
+    hazelnut::quarantine::set(config.quarantine.clone());
+    hazelnut::rules::set_verify_moves(config.general.verify_moves);
+    hazelnut::progress::set_max_rate(
+        config
+            .general
+            .max_transfer_rate
+            .as_ref()
+            .map(|rate| rate.bytes()),
+    );
+    hazelnut::stats::init(config.general.stats_retention_days);
+    hazelnut::history::init(
+        config.general.history_retention_days,
+        config.general.history_checksums,
+    );
+    hazelnut::hashing::init(config.general.hash_algorithm, config.general.hash_threads);
+    let engine = hazelnut::RuleEngine::new(config.rules.clone()).with_hooks(config.hooks.clone());
+    let watch = hazelnut::organize::watch_for(&config, dir);
+    let dry_run = dry_run || config.general.dry_run;
+
+    let summary = hazelnut::organize::organize(&watch, &engine, dry_run)?;
+    hazelnut::stats::flush();
+    if dry_run {
+        println!("[dry-run] {}", summary.dir.display());
+    } else {
+        println!("{}", summary.dir.display());
+    }
+    println!();
+    print!("{}", summary.table());
+    if summary.truncated {
+        println!(
+            "Only the first {} files were looked at; run again for the rest",
+            hazelnut::preview::MAX_FILES
+        );
+    }
+    for path in &summary.deferred {
+        println!("⏸ {} (deferred: quiet or inactive hours)", path.display());
+    }
+    for (path, error) in &summary.failed {
+        println!("✗ {}: {}", path.display(), error);
+    }
+    if !summary.failed.is_empty() {
+        anyhow::bail!("{} files couldn't be organized", summary.failed.len());
+    }
+    Ok(())
+}
+
+/// Print a completion script for `hazelnut`, or `hazelnutd` with `daemon`,
+/// with the config's rule and profile names as candidates where they're taken
+fn print_completions(config_path: Option<&Path>, shell: clap_complete::Shell, daemon: bool) {
+    use clap::builder::PossibleValuesParser;
+
+    // A broken config shouldn't stop completions; it only loses the names
+    let config = hazelnut::Config::load(config_path).unwrap_or_default();
+    let rules: Vec<_> = config.rules.iter().map(|r| r.name.clone()).collect();
+    let profiles: Vec<_> = config.profiles.keys().cloned().collect();
+    let names = |names: &[String]| {
+        let names = names.to_vec();
+        move |arg: clap::Arg| {
+            if names.is_empty() {
+                arg
+            } else {
+                arg.value_parser(PossibleValuesParser::new(names))
+            }
+        }
+    };
+
+    let (mut command, name) = if daemon {
+        let mut command = hazelnut::daemon_cli::Cli::command();
+        for subcommand in ["start", "restart", "run"] {
+            command =
+                command.mut_subcommand(subcommand, |c| c.mut_arg("profile", names(&profiles)));
+        }
+        (command, "hazelnutd")
+    } else {
+        let command = Cli::command()
+            .mut_subcommand("rollback", |c| c.mut_arg("rule", names(&rules)))
+            .mut_subcommand("logs", |c| c.mut_arg("rule", names(&rules)))
+            .mut_subcommand("rules", |c| {
+                ["show", "enable", "disable"]
+                    .into_iter()
+                    .fold(c, |c, subcommand| {
+                        c.mut_subcommand(subcommand, |c| c.mut_arg("name", names(&rules)))
+                    })
+            })
+            .mut_subcommand("profile", |c| c.mut_arg("name", names(&profiles)));
+        (command, "hazelnut")
+    };
+    clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+}
+
+/// Export the hourly statistics
+fn export_stats(csv: bool, days: Option<u32>, output: Option<&std::path::Path>) -> Result<()> {
+    let path =
+        hazelnut::stats::Stats::default_path().context("Could not determine data directory")?;
+    let mut stats = hazelnut::stats::Stats::load(&path);
+    if let Some(days) = days {
+        stats.prune(chrono::Utc::now(), days);
+    }
+    let content = if csv {
+        stats.to_csv()
+    } else {
+        serde_json::to_string_pretty(&stats.buckets())? + "\n"
+    };
+    match output {
+        Some(output) => std::fs::write(output, content)
+            .with_context(|| format!("Failed to write {}", output.display()))?,
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// Export the action history
+fn export_history(
+    format: ExportFormat,
+    since: Option<&str>,
+    output: Option<&std::path::Path>,
+) -> Result<()> {
+    let since = since
+        .map(|since| hazelnut::history::parse_since(since, chrono::Local::now()))
+        .transpose()?;
+    let entries = hazelnut::history::since(since)?;
+    let content = match format {
+        ExportFormat::Csv => hazelnut::history::to_csv(&entries),
+        ExportFormat::Json => serde_json::to_string_pretty(&entries)? + "\n",
+    };
+    match output {
+        Some(output) => std::fs::write(output, content)
+            .with_context(|| format!("Failed to write {}", output.display()))?,
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// Print the actions in the daemon's JSON log that match
+fn query_logs(
+    config_path: Option<&std::path::Path>,
+    rule: Option<String>,
+    since: Option<&str>,
+    failed: bool,
+    json: bool,
+) -> Result<()> {
+    use hazelnut::config::LogFormat;
+    use hazelnut::logfile::ActionFilter;
+
+    let config = hazelnut::Config::load(config_path)?;
+    let filter = ActionFilter {
+        rule,
+        since: since
+            .map(|since| hazelnut::history::parse_since(since, chrono::Local::now()))
+            .transpose()?,
+        failed,
+    };
+    let log_file = hazelnut::logfile::path(&config.general);
+    let events = hazelnut::logfile::actions(&log_file, &filter);
+    if json {
+        for event in &events {
+            println!("{}", serde_json::to_string(event)?);
+        }
+        return Ok(());
+    }
+
+    if events.is_empty() {
+        println!("No matching actions in {}", log_file.display());
+        if config.general.log_format != LogFormat::Json {
+            println!(
+                "Actions are only logged with log_format = \"json\" in [general] (after a daemon restart)"
+            );
+        }
+        return Ok(());
+    }
+    for event in &events {
+        let time = event
+            .timestamp
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S");
+        let rule = if event.rule.is_empty() {
+            "unmatched"
+        } else {
+            event.rule.as_str()
+        };
+        let result = match (&event.error, &event.destination) {
+            (Some(error), _) => format!(": {}", error),
+            (None, Some(destination)) => format!(" → {}", destination.display()),
+            (None, None) => String::new(),
+        };
+        println!(
+            "{} {} [{}] {} {}{} ({} ms)",
+            time,
+            if event.failed() { "✗" } else { "✓" },
+            rule,
+            event.action,
+            event.path.display(),
+            result,
+            event.duration_ms
+        );
+    }
+    Ok(())
+}
+
+/// Apply every retention policy once
+fn apply_retention(config_path: Option<&Path>, dry_run: bool) -> Result<()> {
+    let config = hazelnut::Config::load(config_path)?;
+    config.validate()?;
+    if config.retention.is_empty() {
+        println!("No [[retention]] policies in the config");
+        return Ok(());
+    }
+    hazelnut::quarantine::set(config.quarantine.clone());
+    hazelnut::rules::set_verify_moves(config.general.verify_moves);
+    hazelnut::progress::set_max_rate(
+        config
+            .general
+            .max_transfer_rate
+            .as_ref()
+            .map(|rate| rate.bytes()),
+    );
+    hazelnut::history::init(
+        config.general.history_retention_days,
+        config.general.history_checksums,
+    );
+    hazelnut::hashing::init(config.general.hash_algorithm, config.general.hash_threads);
+    let dry_run = dry_run || config.general.dry_run;
+    let mut failed = 0;
+    for policy in &config.retention {
+        let run = policy.apply(dry_run);
+        let done = match policy.action {
+            hazelnut::retention::RetentionAction::Trash => "trashed",
+            hazelnut::retention::RetentionAction::Archive => "archived",
+            hazelnut::retention::RetentionAction::Delete => "deleted",
+        };
+        println!(
+            "{}: {} file(s) older than {} days {}{}",
+            policy.path.display(),
+            run.removed.len(),
+            policy.max_age_days,
+            if dry_run { "would be " } else { "" },
+            done
+        );
+        for path in &run.removed {
+            println!("  ✓ {}", path.display());
+        }
+        for (path, error) in &run.failed {
+            println!("  ✗ {}: {}", path.display(), error);
+        }
+        failed += run.failed.len();
+    }
+    if failed > 0 {
+        anyhow::bail!("{} file(s) couldn't be removed", failed);
+    }
+    Ok(())
+}
+
+/// List, restore or purge quarantined files
+fn quarantine(config_path: Option<&Path>, command: QuarantineCommand) -> Result<()> {
+    let config = hazelnut::Config::load(config_path)?;
+    hazelnut::quarantine::set(config.quarantine.clone());
+    hazelnut::rules::set_verify_moves(config.general.verify_moves);
+    hazelnut::progress::set_max_rate(
+        config
+            .general
+            .max_transfer_rate
+            .as_ref()
+            .map(|rate| rate.bytes()),
+    );
+    let quarantine = hazelnut::quarantine::Quarantine::configured()?;
+    match command {
+        QuarantineCommand::List { json } => {
+            let entries = quarantine.list()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+            } else if entries.is_empty() {
+                println!("The quarantine is empty");
+            } else {
+                for entry in &entries {
+                    println!(
+                        "{:<17}  {}  {:<8}  {:>9}  {}",
+                        entry.id,
+                        entry.time.format("%Y-%m-%d %H:%M"),
+                        entry.reason,
+                        hazelnut::progress::format_bytes(entry.size),
+                        entry.original.display()
+                    );
+                }
+                let total: u64 = entries.iter().map(|e| e.size).sum();
+                println!(
+                    "{} file(s), {}",
+                    entries.len(),
+                    hazelnut::progress::format_bytes(total)
+                );
+                if !config.quarantine.enabled {
+                    println!("(the quarantine is off; set [quarantine] enabled = true)");
+                }
+            }
+        }
+        QuarantineCommand::Restore { id, to } => {
+            let entry = quarantine.get(&id)?;
+            let restored = quarantine.restore(&entry, to.as_deref())?;
+            println!("✓ Restored {}", restored.display());
+        }
+        QuarantineCommand::Purge { ids, all } => {
+            let purged = if all || !ids.is_empty() {
+                let entries = if all {
+                    quarantine.list()?
+                } else {
+                    ids.iter()
+                        .map(|id| quarantine.get(id))
+                        .collect::<Result<Vec<_>>>()?
+                };
+                for entry in &entries {
+                    quarantine.purge(entry)?;
+                }
+                entries
+            } else {
+                quarantine.purge_expired()?
+            };
+            let bytes: u64 = purged.iter().map(|e| e.size).sum();
+            println!(
+                "✓ Purged {} file(s), {}",
+                purged.len(),
+                hazelnut::progress::format_bytes(bytes)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// List the duplicate files under a folder, or resolve them all
+fn dedupe(
+    config_path: Option<&Path>,
+    root: &Path,
+    mut options: hazelnut::dedupe::ScanOptions,
+    review: PathBuf,
+    apply: Option<DedupeAction>,
+    json: bool,
+    dry_run: bool,
+) -> Result<()> {
+    use hazelnut::dedupe::Resolution;
+    use hazelnut::progress::format_bytes;
+
+    let config = hazelnut::Config::load(config_path)?;
+    hazelnut::quarantine::set(config.quarantine.clone());
+    hazelnut::rules::set_verify_moves(config.general.verify_moves);
+    hazelnut::progress::set_max_rate(
+        config
+            .general
+            .max_transfer_rate
+            .as_ref()
+            .map(|rate| rate.bytes()),
+    );
+    hazelnut::history::init(
+        config.general.history_retention_days,
+        config.general.history_checksums,
+    );
+    hazelnut::hashing::init(config.general.hash_algorithm, config.general.hash_threads);
+    options.exclude.push(review.clone());
+
+    let groups = hazelnut::dedupe::scan(root, &options)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+        return Ok(());
+    }
+    if groups.is_empty() {
+        println!("No duplicate files in {}", root.display());
+        return Ok(());
+    }
+    let resolution = apply.map(|apply| match apply {
+        DedupeAction::Delete => Resolution::Delete,
+        DedupeAction::Hardlink => Resolution::Hardlink,
+        DedupeAction::Move => Resolution::Move(review.clone()),
+    });
+    for group in &groups {
+        println!(
+            "{} × {} ({} wasted)",
+            group.files.len(),
+            format_bytes(group.size),
+            format_bytes(group.wasted())
+        );
+        for (i, path) in group.files.iter().enumerate() {
+            match &resolution {
+                _ if i == group.keep => println!("  ★ {} (kept)", path.display()),
+                Some(resolution) if dry_run => {
+                    println!("    {} (would be {})", path.display(), resolution.verb())
+                }
+                _ => println!("    {}", path.display()),
+            }
+        }
+    }
+    let wasted: u64 = groups.iter().map(|g| g.wasted()).sum();
+    let duplicates: usize = groups.iter().map(|g| g.files.len() - 1).sum();
+    println!(
+        "{} duplicate(s) in {} group(s), {} wasted",
+        duplicates,
+        groups.len(),
+        format_bytes(wasted)
+    );
+
+    let Some(resolution) = resolution else {
+        println!(
+            "Resolve them with `--apply delete|hardlink|move`, or run without --list in a terminal"
+        );
+        return Ok(());
+    };
+    if dry_run {
+        return Ok(());
+    }
+    let run = hazelnut::dedupe::resolve(&groups, &resolution);
+    for (path, error) in &run.failed {
+        println!("  ✗ {}: {}", path.display(), error);
+    }
+    println!(
+        "✓ {} {} duplicate(s), freed {}",
+        resolution.verb(),
+        run.resolved,
+        format_bytes(run.freed)
+    );
+    if !run.failed.is_empty() {
+        anyhow::bail!("{} duplicate(s) couldn't be resolved", run.failed.len());
+    }
+    Ok(())
+}
+
+/// Undo an action by id, or the latest `last` actions that can be undone
+fn undo_actions(id: Option<i64>, last: Option<usize>) -> Result<()> {
+    let entries = match id {
+        Some(id) => vec![
+            hazelnut::history::get(id)?
+                .with_context(|| format!("No action with id {} in the history", id))?,
+        ],
+        None => {
+            let mut entries = hazelnut::history::undoable(None, None)?;
+            entries.truncate(last.unwrap_or(1));
+            if entries.is_empty() {
+                println!("Nothing to undo");
+                return Ok(());
+            }
+            entries
+        }
+    };
+    let mut failed = 0;
+    for entry in &entries {
+        match hazelnut::history::undo(entry) {
+            Ok(()) => println!("✓ #{} {}: {}", entry.id, entry.action, entry.undo_summary()),
+            Err(e) => {
+                failed += 1;
+                println!(
+                    "✗ #{} {} {}: {:#}",
+                    entry.id,
+                    entry.action,
+                    entry.from.display(),
+                    e
+                );
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} actions couldn't be undone", failed, entries.len());
+    }
+    Ok(())
+}
+
+/// Undo a batch of actions, checking all of them first. Without `partial`
+/// it's all or nothing: if an undo still fails, the ones before it are
+/// redone.
+fn rollback(since: Option<&str>, rule: Option<&str>, dry_run: bool, partial: bool) -> Result<()> {
+    if since.is_none() && rule.is_none() {
+        anyhow::bail!("Give --since, --rule or both to choose what to roll back");
+    }
+    let since = since
+        .map(|since| hazelnut::history::parse_since(since, chrono::Local::now()))
+        .transpose()?;
+    let entries = hazelnut::history::undoable(since, rule)?;
+    if entries.is_empty() {
+        println!("Nothing to roll back");
+        return Ok(());
+    }
+
+    let problems = hazelnut::history::check_rollback(&entries);
+    if dry_run {
+        for entry in &entries {
+            let problem = problems.iter().find(|(e, _)| e.id == entry.id);
+            match problem {
+                Some((_, e)) => println!(
+                    "✗ #{} {} {}: {:#}",
+                    entry.id,
+                    entry.action,
+                    entry.from.display(),
+                    e
+                ),
+                None => println!("  #{} {} {}", entry.id, entry.action, entry.from.display()),
+            }
+        }
+        println!(
+            "[dry-run] {} actions would be rolled back, {} can't be",
+            entries.len() - problems.len(),
+            problems.len()
+        );
+        return Ok(());
+    }
+    if !problems.is_empty() && !partial {
+        for (entry, e) in &problems {
+            println!(
+                "✗ #{} {} {}: {:#}",
+                entry.id,
+                entry.action,
+                entry.from.display(),
+                e
+            );
+        }
+        anyhow::bail!(
+            "{} of {} actions can't be undone, so nothing was rolled back (--partial rolls back the rest)",
+            problems.len(),
+            entries.len()
+        );
+    }
+
+    let mut failed = Vec::new();
+    let mut undone = Vec::new();
+    for entry in &entries {
+        let result = match problems.iter().find(|(e, _)| e.id == entry.id) {
+            Some((_, e)) => Err(anyhow::anyhow!("{:#}", e)),
+            None => hazelnut::history::undo(entry),
+        };
+        match result {
+            Ok(()) => undone.push(entry),
+            Err(e) if !partial => {
+                // Something changed since the check: put back what was
+                // undone, so the batch is rolled back whole or not at all
+                println!(
+                    "✗ #{} {} {}: {:#}",
+                    entry.id,
+                    entry.action,
+                    entry.from.display(),
+                    e
+                );
+                let mut stuck = 0;
+                for entry in undone.iter().rev() {
+                    if let Err(e) = hazelnut::history::redo(entry) {
+                        stuck += 1;
+                        println!(
+                            "✗ Couldn't redo #{} {} {}: {:#}",
+                            entry.id,
+                            entry.action,
+                            entry.from.display(),
+                            e
+                        );
+                    }
+                }
+                if stuck > 0 {
+                    anyhow::bail!(
+                        "The rollback failed and {} of {} undone actions couldn't be redone",
+                        stuck,
+                        undone.len()
+                    );
+                }
+                anyhow::bail!(
+                    "The rollback failed, so the {} actions undone so far were redone (--partial rolls back the rest)",
+                    undone.len()
+                );
+            }
+            Err(e) => failed.push((entry, e)),
+        }
+    }
+    println!(
+        "✓ Rolled back {} of {} actions",
+        entries.len() - failed.len(),
+        entries.len()
+    );
+    if !failed.is_empty() {
+        println!("Couldn't restore:");
+        for (entry, e) in &failed {
+            println!(
+                "✗ #{} {} {}: {:#}",
+                entry.id,
+                entry.action,
+                entry.from.display(),
+                e
+            );
+        }
+        anyhow::bail!("{} actions couldn't be rolled back", failed.len());
+    }
+    Ok(())
+}
+
+/// Send a test notification through each backend and report how it went
+fn test_notifications(config_path: Option<&std::path::Path>) -> Result<()> {
+    let config = hazelnut::Config::load(config_path)?;
+    config.notifications.validate()?;
+    let results = hazelnut::notifications::send_test(&config.notifications);
+    if results.is_empty() {
+        println!("No notification backends are configured");
+        return Ok(());
+    }
+    let mut failed = 0;
+    for result in &results {
+        match result {
+            Ok(name) => println!("✓ {}", name),
+            Err(e) => {
+                failed += 1;
+                println!("✗ {:#}", e);
+            }
+        }
+    }
+    use hazelnut::notifications::NotificationLevel;
+    match config.general.notification_level() {
+        NotificationLevel::Off => {
+            println!("Note: notifications are off, so the daemon won't send any")
+        }
+        NotificationLevel::Errors => {
+            println!("Note: notification_level is \"errors\", so the daemon only sends failures")
+        }
+        NotificationLevel::All => {}
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} backends failed", failed, results.len());
+    }
+    Ok(())
 }
 
-#[derive(clap::Subcommand, Debug)]
-enum Commands {
-    /// Start the TUI (default)
-    Ui,
+/// A watch path given on the command line, as the daemon knows it
+fn watch_path_arg(path: &std::path::Path) -> Result<PathBuf> {
+    if hazelnut::sftp::is_remote(path) {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(std::path::absolute(hazelnut::expand_path(path))?)
+    }
+}
 
-    /// List all rules
-    List,
+/// Pause or resume a watch in the running daemon
+fn set_watch_paused(path: &std::path::Path, paused: bool) -> Result<()> {
+    use hazelnut::ipc::{DaemonCommand, DaemonResponse};
 
-    /// Validate config file
-    Check {
-        /// Path to config file to validate
-        #[arg(short, long)]
-        config: Option<PathBuf>,
-    },
+    let path = watch_path_arg(path)?;
+    let cmd = if paused {
+        DaemonCommand::PauseWatch { path: path.clone() }
+    } else {
+        DaemonCommand::ResumeWatch { path: path.clone() }
+    };
+    match hazelnut::ipc::send_command(&cmd).context("Is the daemon running?")? {
+        DaemonResponse::Ok => {
+            let verb = if paused { "Paused" } else { "Resumed" };
+            println!("✓ {} {}", verb, path.display());
+            Ok(())
+        }
+        DaemonResponse::Error { message } => anyhow::bail!(message),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    }
+}
 
-    /// Run rules once without watching (dry-run by default)
-    Run {
-        /// Actually perform actions (not just dry-run)
-        #[arg(long)]
-        apply: bool,
+/// Switch the running daemon's profile, or show it
+fn set_profile(config_path: Option<&Path>, name: Option<String>, all: bool) -> Result<()> {
+    use hazelnut::ipc::{DaemonCommand, DaemonResponse};
 
-        /// Target directory to process
-        #[arg(short, long)]
-        dir: Option<PathBuf>,
-    },
+    if name.is_none() && !all {
+        let active = match hazelnut::ipc::send_command(&DaemonCommand::Status)
+            .context("Is the daemon running?")?
+        {
+            DaemonResponse::Status { profile, .. } => profile,
+            DaemonResponse::Error { message } => anyhow::bail!(message),
+            _ => anyhow::bail!("Unexpected response from daemon"),
+        };
+        match &active {
+            Some(profile) => println!("Active profile: {}", profile),
+            None => println!("No profile active (all watches and rules)"),
+        }
+        let config = hazelnut::Config::load(config_path)?;
+        for name in config.profiles.keys() {
+            let marker = if active.as_ref() == Some(name) {
+                "●"
+            } else {
+                "○"
+            };
+            println!("  {} {}", marker, name);
+        }
+        return Ok(());
+    }
 
-    /// Show daemon status
-    Status,
+    let cmd = DaemonCommand::SetProfile { name: name.clone() };
+    match hazelnut::ipc::send_command(&cmd).context("Is the daemon running?")? {
+        DaemonResponse::Ok => {
+            match name {
+                Some(name) => println!("✓ Switched to profile '{}'", name),
+                None => println!("✓ Using all watches and rules"),
+            }
+            Ok(())
+        }
+        DaemonResponse::Error { message } => anyhow::bail!(message),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    }
+}
 
-    /// Check for updates and install if available
-    Update,
+/// Append a preset's rules to the config file
+fn add_preset(path: Option<&Path>, name: &str, dest: Option<&Path>) -> Result<()> {
+    let preset = hazelnut::presets::find(name).with_context(|| {
+        let names: Vec<_> = hazelnut::presets::PRESETS.iter().map(|p| p.name).collect();
+        format!("Unknown preset '{}' (presets: {})", name, names.join(", "))
+    })?;
+    let config = hazelnut::Config::load(path)?;
+    let rules = preset.rules(dest, &config.rules);
+    let path = hazelnut::Config::append_rules(path, &rules)?;
+    for rule in &rules {
+        let target = rule
+            .action
+            .destination()
+            .map(|d| d.display().to_string())
+            .unwrap_or_else(|| rule.action.kind().to_string());
+        println!("✓ Added rule '{}' → {}", rule.name, target);
+    }
+    println!("  to {}", path.display());
+    if config.watches.is_empty() {
+        println!("  No folders are watched yet; add a [[watch]] for the rules to run");
+    }
+    Ok(())
 }
 
-/// Show daemon status
-#[cfg(unix)]
-fn show_daemon_status() {
-    let state_dir = dirs::state_dir()
-        .unwrap_or_else(|| {
-            dirs::home_dir()
-                .map(|h| h.join(".local").join("state"))
-                .unwrap_or_else(|| PathBuf::from("/tmp"))
+/// A rule by name, or an error listing the rules there are
+fn find_rule<'a>(config: &'a hazelnut::Config, name: &str) -> Result<&'a hazelnut::Rule> {
+    config
+        .rules
+        .iter()
+        .find(|rule| rule.name == name)
+        .with_context(|| {
+            let names: Vec<_> = config.rules.iter().map(|r| r.name.as_str()).collect();
+            if names.is_empty() {
+                format!("Unknown rule '{}' (the config has no rules)", name)
+            } else {
+                format!("Unknown rule '{}' (rules: {})", name, names.join(", "))
+            }
         })
-        .join("hazelnut");
+}
 
-    let pid_file = state_dir.join("hazelnutd.pid");
-    let log_file = state_dir.join("hazelnutd.log");
+/// Watches that run a rule, and whether it's active, disabled or in no watch
+fn rule_status<'a>(
+    config: &'a hazelnut::Config,
+    rule: &hazelnut::Rule,
+) -> (Vec<&'a Path>, &'static str) {
+    let watches: Vec<_> = config
+        .watches
+        .iter()
+        .filter(|watch| watch.runs_rule(&rule.name))
+        .map(|watch| watch.path.as_path())
+        .collect();
+    let status = if !rule.enabled {
+        "disabled"
+    } else if watches.is_empty() {
+        "unwatched"
+    } else {
+        "active"
+    };
+    (watches, status)
+}
 
-    let (running, pid) = if let Ok(pid_str) = std::fs::read_to_string(&pid_file) {
-        if let Ok(pid) = pid_str.trim().parse::<i32>() {
-            if hazelnut::process_is_running(pid) {
-                (true, Some(pid as u32))
-            } else {
-                (false, None)
-            }
+/// List the rules with their watches and status
+fn list_rules(config_path: Option<&Path>, json: bool) -> Result<()> {
+    let config = hazelnut::Config::load(config_path)?;
+    if json {
+        let rules: Vec<_> = config
+            .rules
+            .iter()
+            .map(|rule| {
+                let (watches, status) = rule_status(&config, rule);
+                serde_json::json!({
+                    "name": rule.name,
+                    "enabled": rule.enabled,
+                    "folder": rule.folder,
+                    "action": rule.action.kind(),
+                    "watches": watches,
+                    "status": status,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&rules)?);
+        return Ok(());
+    }
+    if config.rules.is_empty() {
+        println!("No rules yet; `hazelnut rules presets` lists ready-made ones");
+        return Ok(());
+    }
+    let width = config
+        .rules
+        .iter()
+        .map(|r| r.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (i, rule) in config.rules.iter().enumerate() {
+        let (watches, status) = rule_status(&config, rule);
+        let icon = match status {
+            "active" => "✓",
+            "disabled" => "✗",
+            _ => "!",
+        };
+        let watches = if watches.is_empty() {
+            "(no watch)".to_string()
         } else {
-            (false, None)
-        }
+            let paths: Vec<_> = watches.iter().map(|p| p.display().to_string()).collect();
+            paths.join(", ")
+        };
+        println!(
+            "  {} [{}] {:<width$}  {:<9}  {}",
+            icon,
+            i + 1,
+            rule.name,
+            status,
+            watches
+        );
+    }
+    Ok(())
+}
+
+/// Print a rule as it would appear in the config, with its watches
+fn show_rule(config_path: Option<&Path>, name: &str) -> Result<()> {
+    let config = hazelnut::Config::load(config_path)?;
+    let rule = find_rule(&config, name)?;
+    let (watches, status) = rule_status(&config, rule);
+    println!("# Status: {}", status);
+    if watches.is_empty() {
+        println!("# No watch runs this rule");
     } else {
-        (false, None)
-    };
+        for watch in watches {
+            println!("# Run by the watch on {}", watch.display());
+        }
+    }
+    print!(
+        "{}",
+        hazelnut::Config::rules_toml(std::slice::from_ref(rule))?
+    );
+    Ok(())
+}
 
-    if running {
-        let pid = pid.unwrap();
-        println!("🌰 Hazelnut daemon is running");
-        println!("   PID: {}", pid);
-        println!("   PID file: {}", pid_file.display());
-        println!("   Log file: {}", log_file.display());
+/// Enable or disable a rule in the config file and have the daemon reload
+fn set_rule_enabled(config_path: Option<&Path>, name: &str, enabled: bool) -> Result<()> {
+    use hazelnut::ipc::{DaemonCommand, DaemonResponse};
 
-        #[cfg(target_os = "linux")]
-        if let Some(uptime) = hazelnut::read_process_uptime(pid) {
-            println!("   Uptime: {}", uptime);
+    let config = hazelnut::Config::load(config_path)?;
+    let verb = if enabled { "enabled" } else { "disabled" };
+    if find_rule(&config, name)?.enabled == enabled {
+        println!("Rule '{}' is already {}", name, verb);
+        return Ok(());
+    }
+    let path = hazelnut::Config::set_rule_enabled(config_path, name, enabled)?;
+    println!("✓ Rule '{}' {} in {}", name, verb, path.display());
+    match hazelnut::ipc::send_command(&DaemonCommand::Reload) {
+        Ok(DaemonResponse::Error { message }) => anyhow::bail!("Daemon reload failed: {}", message),
+        Ok(_) => println!("  The daemon is reloading its config"),
+        Err(_) => println!("  The daemon isn't running; the change applies when it starts"),
+    }
+    Ok(())
+}
+
+/// Convert a Hazel rule export, add the rules to the config, and list what
+/// couldn't be carried over
+fn import_hazel(config_path: Option<&Path>, path: &Path, dry_run: bool) -> Result<()> {
+    let config = hazelnut::Config::load(config_path)?;
+    let imported = hazelnut::hazel::import(path, &config.rules)?;
+    if dry_run {
+        if !imported.rules.is_empty() {
+            print!("{}", hazelnut::Config::rules_toml(&imported.rules)?);
+        }
+    } else if !imported.rules.is_empty() {
+        let written = hazelnut::Config::append_rules(config_path, &imported.rules)?;
+        for rule in &imported.rules {
+            let state = if rule.enabled { "" } else { " (disabled)" };
+            println!("✓ Imported rule '{}'{}", rule.name, state);
         }
+        println!("  to {}", written.display());
+    }
+    if !imported.notes.is_empty() {
+        // On stderr, so --dry-run output stays valid TOML
+        eprintln!("\nNeeds attention:");
+        for note in &imported.notes {
+            eprintln!("  {}: {}", note.rule, note.message);
+        }
+    }
+    if imported.rules.is_empty() {
+        anyhow::bail!("None of the rules could be imported");
+    }
+    if !dry_run && config.watches.is_empty() {
+        println!("  No folders are watched yet; add a [[watch]] for the rules to run");
+    }
+    Ok(())
+}
+
+/// Report everything `config::diagnose` finds in the config file, and exit
+/// with status 1 if there are errors
+fn sync_config(path: Option<&Path>, remote: Option<&str>, local: bool) -> Result<()> {
+    let path = path
+        .map(Path::to_path_buf)
+        .or_else(hazelnut::Config::default_path)
+        .context("Could not determine config path")?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let report = hazelnut::config::sync(&path, remote, local)?;
+
+    if report.initialized {
+        println!("✓ Created a git repository in {}", dir.display());
+    }
+    match &report.committed {
+        Some(message) => println!(
+            "✓ Committed: {}",
+            message.lines().next().unwrap_or_default()
+        ),
+        None => println!("  No local changes to commit"),
+    }
+    if report.pulled {
+        println!("✓ Pulled changes from the remote");
+        // A running daemon picks up the new file on its own
+        if let Err(e) = hazelnut::Config::load(Some(&path)) {
+            println!("⚠ The synced config doesn't load: {:#}", e);
+        }
+    }
+    if report.pushed {
+        println!("✓ Pushed to the remote");
+    }
+    Ok(())
+}
+
+fn validate_config(path: Option<&Path>, json: bool) -> Result<()> {
+    use hazelnut::config::Severity;
+
+    let path = path
+        .map(Path::to_path_buf)
+        .or_else(hazelnut::Config::default_path)
+        .context("Could not determine config path")?;
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config from {}", path.display()))?;
+    let diagnostics = hazelnut::config::diagnose(&content);
+    #[cfg(feature = "tui")]
+    let diagnostics = {
+        let mut diagnostics = diagnostics;
+        if let Some(config) = diagnostics.config.clone() {
+            for (key, e) in hazelnut::app::check_settings(&config) {
+                diagnostics.push(Severity::Error, key, format!("{:#}", e));
+            }
+            diagnostics.items.sort_by_key(|d| d.line);
+        }
+        diagnostics
+    };
+    let (errors, warnings) = (diagnostics.errors(), diagnostics.warnings());
+
+    if json {
+        let result = serde_json::json!({
+            "valid": errors == 0,
+            "path": path,
+            "errors": errors,
+            "warnings": warnings,
+            "diagnostics": diagnostics.items,
+        });
+        println!("{}", serde_json::to_string_pretty(&result)?);
     } else {
-        println!("🌰 Hazelnut daemon is not running");
+        for diagnostic in &diagnostics.items {
+            let severity = match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            match (diagnostic.line, diagnostic.column) {
+                (Some(line), Some(column)) => println!(
+                    "{}:{}:{}: {}: {}",
+                    path.display(),
+                    line,
+                    column,
+                    severity,
+                    diagnostic.message
+                ),
+                (Some(line), None) => println!(
+                    "{}:{}: {}: {}",
+                    path.display(),
+                    line,
+                    severity,
+                    diagnostic.message
+                ),
+                _ => println!("{}: {}: {}", path.display(), severity, diagnostic.message),
+            }
+        }
+        let plural =
+            |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+        if errors > 0 {
+            println!(
+                "✗ {}, {}",
+                plural(errors, "error"),
+                plural(warnings, "warning")
+            );
+        } else if warnings > 0 {
+            println!("✓ Config is valid ({})", plural(warnings, "warning"));
+        } else {
+            println!("✓ Config is valid");
+        }
+    }
+    if errors > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// How long `scan --wait` waits for a requested scan to show up
+const SCAN_START_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How often `scan --wait` asks the daemon for progress
+const SCAN_PROGRESS_POLL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Ask the running daemon to scan a watch, or all watches, optionally
+/// following the progress until the scans are done
+fn request_scan(path: Option<&std::path::Path>, wait: bool) -> Result<()> {
+    use hazelnut::ipc::{DaemonCommand, DaemonResponse};
+
+    let path = path.map(watch_path_arg).transpose()?;
+    let cmd = DaemonCommand::Scan { path: path.clone() };
+    match hazelnut::ipc::send_command(&cmd).context("Is the daemon running?")? {
+        DaemonResponse::Ok => match path {
+            Some(path) => println!("✓ Scanning {}", path.display()),
+            None => println!("✓ Scanning all watched folders"),
+        },
+        DaemonResponse::Error { message } => anyhow::bail!(message),
+        _ => anyhow::bail!("Unexpected response from daemon"),
+    }
+    if !wait {
+        return Ok(());
+    }
+
+    // The daemon starts scans from its main loop, so they may not be
+    // running yet
+    let requested = std::time::Instant::now();
+    let mut seen = false;
+    loop {
+        std::thread::sleep(SCAN_PROGRESS_POLL);
+        let scans = match hazelnut::ipc::send_command(&DaemonCommand::GetProgress)
+            .context("Lost the connection to the daemon")?
+        {
+            DaemonResponse::Progress { scans, .. } => scans,
+            _ => anyhow::bail!("Unexpected response from daemon"),
+        };
+        if scans.is_empty() {
+            if seen || requested.elapsed() >= SCAN_START_TIMEOUT {
+                break;
+            }
+            continue;
+        }
+        seen = true;
+        for scan in &scans {
+            println!("  {}", scan.summary());
+        }
+    }
+    println!("✓ Scan finished");
+    Ok(())
+}
+
+/// Number of activity log entries shown by `events`
+const RECENT_EVENTS: usize = 20;
+
+/// How often `events --raw` asks the daemon for new notifications
+const RAW_EVENTS_POLL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Print the daemon's recent activity
+fn show_recent_events() -> Result<()> {
+    use hazelnut::ipc::{DaemonCommand, DaemonResponse};
+
+    let cmd = DaemonCommand::GetLog {
+        limit: RECENT_EVENTS,
+    };
+    match hazelnut::ipc::send_command(&cmd).context("Is the daemon running?")? {
+        DaemonResponse::Log { entries } => {
+            if entries.is_empty() {
+                println!("No activity yet");
+            }
+            for entry in entries {
+                println!("{}", entry);
+            }
+            println!("\nUse --raw to see filesystem notifications as they arrive");
+            Ok(())
+        }
+        DaemonResponse::Error { message } => anyhow::bail!(message),
+        _ => anyhow::bail!("Unexpected response from daemon"),
     }
 }
 
-#[cfg(not(unix))]
-fn show_daemon_status() {
-    println!("🌰 Daemon status is only available on Unix systems");
+/// Print raw filesystem notifications received by the daemon until interrupted
+fn stream_raw_events(path: Option<&std::path::Path>) -> Result<()> {
+    use hazelnut::ipc::{DaemonCommand, DaemonResponse};
+
+    let path = path.map(watch_path_arg).transpose()?;
+    let request = |after| {
+        let cmd = DaemonCommand::GetRawEvents {
+            after,
+            path: path.clone(),
+        };
+        match hazelnut::ipc::send_command(&cmd).context("Is the daemon running?")? {
+            DaemonResponse::RawEvents { events, next } => Ok((events, next)),
+            DaemonResponse::Error { message } => anyhow::bail!(message),
+            _ => anyhow::bail!("Unexpected response from daemon"),
+        }
+    };
+
+    // The first request only starts recording
+    let (_, mut after) = request(None)?;
+    match path {
+        Some(ref path) => println!("Raw events under {} (Ctrl+C to stop)", path.display()),
+        None => println!("Raw events for all watches (Ctrl+C to stop)"),
+    }
+    loop {
+        std::thread::sleep(RAW_EVENTS_POLL);
+        let (events, next) = request(Some(after))?;
+        for event in events {
+            let paths: Vec<String> = event
+                .paths
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect();
+            print!(
+                "{}  {:<28} {}",
+                event.time.format("%H:%M:%S%.3f"),
+                event.kind,
+                paths.join(" → ")
+            );
+            match event.error {
+                Some(error) => println!(": {}", error),
+                None => println!(),
+            }
+        }
+        after = next;
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    hazelnut::instance::set(cli.instance.as_deref())?;
 
-    // Initialize logging
+    // Initialize logging. The TUI owns the terminal, so it logs to its own
+    // log file (opened once the config is loaded) instead.
     let log_level = if cli.verbose { "debug" } else { "info" };
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("HAZELNUT_LOG").unwrap_or_else(|_| log_level.to_string()),
-        ))
-        .with(tracing_subscriber::fmt::layer().with_target(false))
-        .init();
+    let tui = cfg!(feature = "tui")
+        && match &cli.command {
+            None | Some(Commands::Ui) => true,
+            Some(Commands::Dedupe {
+                apply, list, json, ..
+            }) => apply.is_none() && !list && !json && std::io::stdout().is_terminal(),
+            _ => false,
+        };
+    let registry = tracing_subscriber::registry().with(tracing_subscriber::EnvFilter::new(
+        std::env::var("HAZELNUT_LOG").unwrap_or_else(|_| log_level.to_string()),
+    ));
+    if tui {
+        registry
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_target(false)
+                    .with_ansi(false)
+                    .with_writer(|| hazelnut::logfile::Writer),
+            )
+            .init();
+    } else {
+        registry
+            .with(tracing_subscriber::fmt::layer().with_target(false))
+            .init();
+    }
 
     match cli.command {
+        #[cfg(feature = "tui")]
         None | Some(Commands::Ui) => {
             hazelnut::app::run(cli.config).await?;
         }
-        Some(Commands::List) => {
+        #[cfg(not(feature = "tui"))]
+        None | Some(Commands::Ui) => {
+            anyhow::bail!("This build of hazelnut has no TUI (enable the `tui` feature)");
+        }
+        Some(Commands::List { json }) => {
             let config = hazelnut::Config::load(cli.config.as_deref())?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&config.rules)?);
+                return Ok(());
+            }
             println!("Rules:");
             for (i, rule) in config.rules.iter().enumerate() {
                 let status = if rule.enabled { "✓" } else { "✗" };
                 println!("  {} [{}] {}", status, i + 1, rule.name);
             }
         }
+        Some(Commands::Rules {
+            command: RulesCommand::Add { preset, dest },
+        }) => {
+            add_preset(cli.config.as_deref(), &preset, dest.as_deref())?;
+        }
+        Some(Commands::Rules {
+            command: RulesCommand::List { json },
+        }) => {
+            list_rules(cli.config.as_deref(), json)?;
+        }
+        Some(Commands::Rules {
+            command: RulesCommand::Show { name },
+        }) => {
+            show_rule(cli.config.as_deref(), &name)?;
+        }
+        Some(Commands::Rules {
+            command: RulesCommand::Enable { name },
+        }) => {
+            set_rule_enabled(cli.config.as_deref(), &name, true)?;
+        }
+        Some(Commands::Rules {
+            command: RulesCommand::Disable { name },
+        }) => {
+            set_rule_enabled(cli.config.as_deref(), &name, false)?;
+        }
+        Some(Commands::Rules {
+            command: RulesCommand::Presets,
+        }) => {
+            for preset in &hazelnut::presets::PRESETS {
+                match preset.destination {
+                    Some(destination) => println!(
+                        "  {:<18} {} ({})",
+                        preset.name, preset.description, destination
+                    ),
+                    None => println!("  {:<18} {}", preset.name, preset.description),
+                }
+            }
+        }
+        Some(Commands::Import {
+            command: ImportCommand::Hazel { path, dry_run },
+        }) => {
+            import_hazel(cli.config.as_deref(), &path, dry_run)?;
+        }
         Some(Commands::Check {
             config: config_path,
+            json,
         }) => {
             let path = config_path.or(cli.config);
-            match hazelnut::Config::load(path.as_deref()) {
+            let result = hazelnut::Config::load(path.as_deref())
+                .and_then(|config| config.validate().map(|_| config));
+            match result {
+                Ok(config) if json => {
+                    let result = serde_json::json!({
+                        "valid": true,
+                        "watches": config.watches.len(),
+                        "rules": config.rules.len(),
+                    });
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                }
                 Ok(config) => {
                     println!("✓ Config is valid");
                     println!("  {} watch paths", config.watches.len());
                     println!("  {} rules", config.rules.len());
                 }
                 Err(e) => {
-                    eprintln!("✗ Config error: {}", e);
+                    if json {
+                        let result = serde_json::json!({
+                            "valid": false,
+                            "error": format!("{:#}", e),
+                        });
+                        println!("{}", serde_json::to_string_pretty(&result)?);
+                    } else {
+                        eprintln!("✗ Config error: {:#}", e);
+                    }
                     std::process::exit(1);
                 }
             }
         }
         Some(Commands::Run { apply, dir }) => {
             let config = hazelnut::Config::load(cli.config.as_deref())?;
-            let engine = hazelnut::RuleEngine::new(config.rules);
+            hazelnut::plugins::set(config.plugins.clone());
+            hazelnut::quarantine::set(config.quarantine.clone());
+            hazelnut::rules::set_verify_moves(config.general.verify_moves);
+            hazelnut::progress::set_max_rate(
+                config
+                    .general
+                    .max_transfer_rate
+                    .as_ref()
+                    .map(|rate| rate.bytes()),
+            );
+            if apply && config.general.dry_run {
+                println!("dry_run is set in the config, so nothing will be applied");
+            }
+            let apply = apply && !config.general.dry_run;
+            let engine = hazelnut::RuleEngine::new(config.rules).with_hooks(config.hooks);
 
             let dirs: Vec<_> = if let Some(d) = dir {
                 vec![d]
@@ -156,6 +1873,8 @@ async fn main() -> Result<()> {
                 config
                     .watches
                     .iter()
+                    // Remote files are only handled by the daemon or TUI
+                    .filter(|w| !hazelnut::sftp::is_remote(&w.path))
                     .map(|w| hazelnut::expand_path(&w.path))
                     .collect()
             };
@@ -165,36 +1884,239 @@ async fn main() -> Result<()> {
                 let entries = std::fs::read_dir(&dir)?;
                 for entry in entries.flatten() {
                     let path = entry.path();
-                    if path.is_file() {
-                        let actions = engine.evaluate_all(&path)?;
-                        for action in actions {
-                            if apply {
-                                println!("  Applying: {} -> {:?}", path.display(), action);
-                                action.execute(&path)?;
-                            } else {
-                                println!("  [dry-run] {} -> {:?}", path.display(), action);
+                    let actions = if path.is_dir() {
+                        engine.evaluate_folder(&path)?
+                    } else if path.is_file() {
+                        engine.evaluate_all(&path)?
+                    } else {
+                        continue;
+                    };
+                    for action in actions {
+                        if apply {
+                            println!("  Applying: {} -> {:?}", path.display(), action);
+                            if let Some(landed) = action.execute_to(&path)? {
+                                engine.run_hooks(&landed);
                             }
+                        } else {
+                            println!("  [dry-run] {} -> {:?}", path.display(), action);
                         }
                     }
                 }
             }
         }
-        Some(Commands::Status) => {
-            show_daemon_status();
+        Some(Commands::Organize { dir, dry_run }) => {
+            organize(cli.config.as_deref(), &dir, dry_run)?;
+        }
+        Some(Commands::Report { older_than }) => {
+            let mut config = hazelnut::Config::load(cli.config.as_deref())?;
+            if let Some(days) = older_than {
+                config.report.older_than_days = days;
+            }
+            hazelnut::plugins::set(config.plugins.clone());
+            let reports = hazelnut::report::build(&config);
+            if reports.is_empty() {
+                println!("✓ No unmatched files in watched folders");
+            }
+            for report in reports {
+                println!("{}", report.summary(config.report.older_than_days));
+                for file in &report.unmatched {
+                    println!("  {}", file.display());
+                }
+            }
+        }
+        Some(Commands::Shadow { clear }) => {
+            let config = hazelnut::Config::load(cli.config.as_deref())?;
+            show_shadow_summary(&config, clear)?;
+        }
+        Some(Commands::Stats {
+            command: StatsCommand::Export { csv, days, output },
+        }) => {
+            export_stats(csv, days, output.as_deref())?;
+        }
+        Some(Commands::History {
+            command:
+                HistoryCommand::Export {
+                    format,
+                    since,
+                    output,
+                },
+        }) => {
+            export_history(format, since.as_deref(), output.as_deref())?;
+        }
+        Some(Commands::Logs {
+            rule,
+            since,
+            failed,
+            json,
+        }) => {
+            query_logs(cli.config.as_deref(), rule, since.as_deref(), failed, json)?;
+        }
+        Some(Commands::Cache {
+            command: CacheCommand::Stats { json },
+        }) => {
+            let stats = hazelnut::hash_cache::stats()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                println!("Cached hashes: {}", stats.entries);
+                println!(
+                    "Lookups:       {} ({} hits, {} misses, {:.1}% hit rate)",
+                    stats.hits + stats.misses,
+                    stats.hits,
+                    stats.misses,
+                    stats.hit_rate()
+                );
+                println!(
+                    "Size on disk:  {}",
+                    hazelnut::progress::format_bytes(stats.bytes)
+                );
+            }
+        }
+        Some(Commands::Cache {
+            command: CacheCommand::Clear,
+        }) => {
+            let removed = hazelnut::hash_cache::clear()?;
+            println!("Cleared {} cached hash(es)", removed);
+        }
+        Some(Commands::Retention { dry_run }) => {
+            apply_retention(cli.config.as_deref(), dry_run)?;
+        }
+        Some(Commands::Dedupe {
+            dir,
+            apply,
+            to,
+            min_size,
+            hidden,
+            list: _,
+            json,
+            dry_run,
+        }) => {
+            let root = hazelnut::expand_path(&dir);
+            let review = to
+                .map(|to| hazelnut::expand_path(&to))
+                .unwrap_or_else(|| hazelnut::dedupe::review_dir(&root));
+            let options = hazelnut::dedupe::ScanOptions {
+                min_size: min_size.map_or(1, |size| size.bytes()),
+                hidden,
+                exclude: Vec::new(),
+            };
+            #[cfg(feature = "tui")]
+            if tui {
+                hazelnut::app::run_dedupe(cli.config, root, options, review).await?;
+                return Ok(());
+            }
+            dedupe(
+                cli.config.as_deref(),
+                &root,
+                options,
+                review,
+                apply,
+                json,
+                dry_run,
+            )?;
+        }
+        Some(Commands::Quarantine { command }) => {
+            quarantine(cli.config.as_deref(), command)?;
+        }
+        Some(Commands::Undo { id, last }) => {
+            undo_actions(id, last)?;
+        }
+        Some(Commands::Rollback {
+            since,
+            rule,
+            dry_run,
+            partial,
+        }) => {
+            rollback(since.as_deref(), rule.as_deref(), dry_run, partial)?;
+        }
+        Some(Commands::Config {
+            command: ConfigCommand::Validate { json },
+        }) => {
+            validate_config(cli.config.as_deref(), json)?;
+        }
+        Some(Commands::Config {
+            command: ConfigCommand::Schema,
+        }) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&hazelnut::Config::json_schema())?
+            );
+        }
+        Some(Commands::Config {
+            command: ConfigCommand::Sync { remote, local },
+        }) => {
+            sync_config(cli.config.as_deref(), remote.as_deref(), local)?;
+        }
+        Some(Commands::Notifications {
+            command: NotificationsCommand::Test,
+        }) => {
+            test_notifications(cli.config.as_deref())?;
+        }
+        Some(Commands::Pause { path }) => {
+            set_watch_paused(&path, true)?;
+        }
+        Some(Commands::Resume { path }) => {
+            set_watch_paused(&path, false)?;
+        }
+        Some(Commands::Profile { name, all }) => {
+            set_profile(cli.config.as_deref(), name, all)?;
+        }
+        Some(Commands::Scan { path, wait }) => {
+            request_scan(path.as_deref(), wait)?;
+        }
+        Some(Commands::Events { raw, path }) => {
+            if raw {
+                stream_raw_events(path.as_deref())?;
+            } else {
+                show_recent_events()?;
+            }
+        }
+        Some(Commands::Status { json }) => {
+            show_daemon_status(json, cli.config.as_deref())?;
         }
-        Some(Commands::Update) => {
-            run_update_command();
+        Some(Commands::Completions { shell, daemon }) => {
+            print_completions(cli.config.as_deref(), shell, daemon);
+        }
+        Some(Commands::Update { check, json }) => {
+            if json {
+                print_version_check_json()?;
+            } else {
+                run_update_command(check);
+            }
         }
+        Some(Commands::SelfUpdate) => run_self_update_command(),
     }
+    // Dates carried along by undo, rollback, organize and the like
+    hazelnut::date_added::flush();
 
     Ok(())
 }
 
-/// Run the update command
-fn run_update_command() {
-    use hazelnut::{
-        VERSION, VersionCheck, check_for_updates_crates_io, detect_package_manager, run_update,
+/// Print the result of an update check as JSON
+fn print_version_check_json() -> Result<()> {
+    use hazelnut::{VERSION, VersionCheck, check_for_updates};
+
+    let (latest, error) = match check_for_updates() {
+        VersionCheck::UpdateAvailable { latest, .. } => (Some(latest), None),
+        VersionCheck::UpToDate => (None, None),
+        VersionCheck::CheckFailed(msg) => (None, Some(msg)),
     };
+    let result = serde_json::json!({
+        "current": VERSION,
+        "latest": latest.as_deref().unwrap_or(VERSION),
+        "update_available": latest.is_some(),
+        "error": error,
+    });
+    println!("{}", serde_json::to_string_pretty(&result)?);
+    if error.is_some() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Run the update command
+fn run_update_command(check_only: bool) {
+    use hazelnut::{VERSION, VersionCheck, check_for_updates, detect_package_manager, run_update};
 
     println!("🌰 Checking for updates...\n");
 
@@ -202,12 +2124,16 @@ fn run_update_command() {
     println!("  Installed via: {}", pm.name());
     println!("  Current version: {}", VERSION);
 
-    // Use crates.io API (no rate limits, more reliable)
-    let check = check_for_updates_crates_io();
+    // crates.io first (no rate limits), GitHub Releases if it's unreachable
+    let check = check_for_updates();
 
     match check {
         VersionCheck::UpdateAvailable { latest, .. } => {
             println!("  Latest version: {}", latest);
+            if check_only {
+                println!("\n⬆ Update available! Run `hazelnut update` to install it.");
+                return;
+            }
             println!("\n⬆ Update available! Installing...\n");
 
             match run_update(&pm) {
@@ -232,3 +2158,67 @@ fn run_update_command() {
         }
     }
 }
+
+/// Run the self-update command
+fn run_self_update_command() {
+    use hazelnut::{PackageManager, VERSION, detect_package_manager};
+
+    let pm = detect_package_manager();
+    if pm != PackageManager::Release {
+        println!("hazelnut was installed via {}; update it with:", pm.name());
+        println!("  {}", pm.update_command());
+        std::process::exit(1);
+    }
+
+    #[cfg(feature = "update")]
+    {
+        use hazelnut::VersionCheck;
+        use hazelnut::update::{install, latest_release};
+
+        println!("🌰 Checking GitHub for the latest release...\n");
+        println!("  Current version: {}", VERSION);
+        let release = match latest_release(std::time::Duration::from_secs(15)) {
+            Ok(release) => release,
+            Err(e) => {
+                println!("\n⚠ Could not check for updates: {}", e);
+                std::process::exit(1);
+            }
+        };
+        println!("  Latest version: {}", release.version);
+        if let VersionCheck::UpToDate = release.version_check() {
+            println!("\n✓ Already on the latest version!");
+            return;
+        }
+
+        println!("\n⬆ Downloading {}...\n", release.version);
+        match install(&release) {
+            Ok(installed) => {
+                for path in installed {
+                    println!("  Replaced {}", path.display());
+                }
+                println!("\n✓ Successfully updated to {}!", release.version);
+                #[cfg(unix)]
+                if hazelnut::daemon_pid().is_some() {
+                    println!(
+                        "\nThe daemon is still running the old version; restart it with `hazelnutd restart`."
+                    );
+                }
+                #[cfg(windows)]
+                if hazelnut::service::is_running() {
+                    println!(
+                        "\nThe daemon is still running the old version; restart it (`hazelnutd restart` for the service)."
+                    );
+                }
+            }
+            Err(e) => {
+                println!("✗ Update failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    #[cfg(not(feature = "update"))]
+    {
+        println!("⚠ hazelnut {} was built without update checks", VERSION);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,233 @@
+//! Notification backends and their `[notifications]` settings
+
+use anyhow::{Context, Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::NotificationKind;
+
+/// A notification on its way to the backends
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub message: String,
+}
+
+impl Notification {
+    /// Title line, e.g. "Hazelnut: Rule Error"
+    pub fn title(&self) -> String {
+        format!("Hazelnut: {}", self.kind.prefix())
+    }
+}
+
+/// Somewhere notifications are delivered
+pub trait Backend: Send + Sync {
+    /// Short name for logs, e.g. `slack`
+    fn name(&self) -> &str;
+
+    /// Deliver a notification; may block on the network
+    fn send(&self, notification: &Notification) -> Result<()>;
+}
+
+/// Where notifications are delivered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct NotificationsConfig {
+    /// Show notifications on the desktop
+    #[serde(default = "default_desktop")]
+    pub desktop: bool,
+
+    /// Collect notifications and send one summary this often, e.g. `"15m"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest_interval: Option<super::Interval>,
+
+    /// Post to a Slack channel through an incoming webhook
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slack: Option<SlackConfig>,
+
+    /// Message a Telegram chat from a bot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telegram: Option<TelegramConfig>,
+
+    /// Send an email over SMTP
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<EmailConfig>,
+
+    /// POST each notification as JSON to a URL
+    #[serde(default, rename = "webhook", skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+fn default_desktop() -> bool {
+    true
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            desktop: default_desktop(),
+            digest_interval: None,
+            slack: None,
+            telegram: None,
+            email: None,
+            webhooks: Vec::new(),
+        }
+    }
+}
+
+impl NotificationsConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the settings without contacting anything
+    pub fn validate(&self) -> Result<()> {
+        if let Some(slack) = &self.slack {
+            check_url(&slack.webhook_url).context("notifications.slack.webhook_url")?;
+        }
+        if let Some(telegram) = &self.telegram
+            && (telegram.bot_token.trim().is_empty() || telegram.chat_id.trim().is_empty())
+        {
+            bail!("notifications.telegram needs both bot_token and chat_id");
+        }
+        if let Some(email) = &self.email {
+            if email.to.is_empty() {
+                bail!("notifications.email.to has no addresses");
+            }
+            for address in std::iter::once(&email.from).chain(&email.to) {
+                if !address.contains('@') {
+                    bail!("notifications.email: '{}' isn't an email address", address);
+                }
+            }
+        }
+        for (i, webhook) in self.webhooks.iter().enumerate() {
+            check_url(&webhook.url).with_context(|| format!("notifications.webhook[{}].url", i))?;
+        }
+        Ok(())
+    }
+}
+
+fn check_url(url: &str) -> Result<()> {
+    let url = crate::expand_env(url);
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        bail!("'{}' isn't an http:// or https:// URL", url);
+    }
+    Ok(())
+}
+
+/// Slack incoming webhook
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SlackConfig {
+    /// Webhook URL from Slack's "Incoming Webhooks" app
+    pub webhook_url: String,
+}
+
+/// Telegram bot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TelegramConfig {
+    /// Token from @BotFather
+    pub bot_token: String,
+
+    /// Chat to message: a numeric id, or `@channel` for a public channel
+    pub chat_id: String,
+}
+
+/// SMTP server and addresses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct EmailConfig {
+    /// SMTP server host name
+    pub smtp_host: String,
+
+    /// Server port (default: 587 for starttls, 465 for tls, 25 for none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+
+    /// Connection encryption
+    #[serde(default)]
+    pub security: EmailSecurity,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+
+    /// Sender address, e.g. `Hazelnut <hazelnut@example.com>`
+    pub from: String,
+
+    /// Recipient addresses
+    pub to: Vec<String>,
+}
+
+/// How the SMTP connection is encrypted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum EmailSecurity {
+    /// Upgrade a plain connection with STARTTLS
+    #[default]
+    #[serde(rename = "starttls")]
+    StartTls,
+    /// TLS from the start (SMTPS)
+    #[serde(rename = "tls")]
+    Tls,
+    /// No encryption, e.g. a relay on localhost
+    #[serde(rename = "none")]
+    Plain,
+}
+
+/// Generic webhook
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookConfig {
+    pub url: String,
+
+    /// Extra request headers, e.g. `Authorization`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// The backends a config turns on. Each failure names its backend.
+pub(super) fn build(config: &NotificationsConfig) -> Vec<Result<Box<dyn Backend>>> {
+    let mut backends: Vec<Result<Box<dyn Backend>>> = Vec::new();
+    if config.desktop {
+        backends.push(Ok(Box::new(super::desktop::Desktop)));
+    }
+
+    #[cfg(feature = "remote-notifications")]
+    {
+        use super::{email::Email, http};
+
+        if let Some(slack) = &config.slack {
+            backends.push(Ok(Box::new(http::Slack::new(slack))));
+        }
+        if let Some(telegram) = &config.telegram {
+            backends.push(Ok(Box::new(http::Telegram::new(telegram))));
+        }
+        if let Some(email) = &config.email {
+            backends.push(
+                Email::new(email)
+                    .map(|email| Box::new(email) as Box<dyn Backend>)
+                    .context("email"),
+            );
+        }
+        for webhook in &config.webhooks {
+            backends.push(Ok(Box::new(http::Webhook::new(webhook))));
+        }
+    }
+
+    #[cfg(not(feature = "remote-notifications"))]
+    {
+        let remote = [
+            ("slack", config.slack.is_some()),
+            ("telegram", config.telegram.is_some()),
+            ("email", config.email.is_some()),
+            ("webhook", !config.webhooks.is_empty()),
+        ];
+        for (name, _) in remote.into_iter().filter(|(_, configured)| *configured) {
+            backends.push(Err(anyhow::anyhow!(
+                "{}: this build has no remote notifications (enable the `remote-notifications` feature)",
+                name
+            )));
+        }
+    }
+
+    backends
+}
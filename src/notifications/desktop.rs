@@ -0,0 +1,34 @@
+//! Desktop notifications (libnotify, macOS Notification Center, Windows toasts)
+
+use anyhow::Result;
+
+use super::backend::{Backend, Notification};
+
+pub(super) struct Desktop;
+
+impl Backend for Desktop {
+    fn name(&self) -> &str {
+        "desktop"
+    }
+
+    #[cfg(feature = "notifications")]
+    fn send(&self, notification: &Notification) -> Result<()> {
+        use notify_rust::Timeout;
+
+        notify_rust::Notification::new()
+            .appname("Hazelnut")
+            .summary(&notification.title())
+            .body(&notification.message)
+            .icon(notification.kind.icon())
+            .timeout(Timeout::Milliseconds(5000))
+            .show()?;
+        Ok(())
+    }
+
+    /// Builds without the `notifications` feature only log them
+    #[cfg(not(feature = "notifications"))]
+    fn send(&self, notification: &Notification) -> Result<()> {
+        tracing::info!("{}: {}", notification.kind.prefix(), notification.message);
+        Ok(())
+    }
+}
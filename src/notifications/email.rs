@@ -0,0 +1,70 @@
+//! Email notifications over SMTP
+
+use anyhow::{Context, Result};
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use std::time::Duration;
+
+use super::backend::{Backend, EmailConfig, EmailSecurity, Notification};
+
+pub(super) struct Email {
+    transport: SmtpTransport,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl Email {
+    pub(super) fn new(config: &EmailConfig) -> Result<Self> {
+        let host = crate::expand_env(&config.smtp_host);
+        let mut builder = match config.security {
+            EmailSecurity::StartTls => SmtpTransport::starttls_relay(&host)?,
+            EmailSecurity::Tls => SmtpTransport::relay(&host)?,
+            EmailSecurity::Plain => SmtpTransport::builder_dangerous(&host),
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = &config.username {
+            let password = config.password.as_deref().unwrap_or_default();
+            builder = builder.credentials(Credentials::new(
+                crate::expand_env(username),
+                crate::expand_env(password),
+            ));
+        }
+
+        let mailbox = |address: &String| {
+            address
+                .parse::<Mailbox>()
+                .with_context(|| format!("invalid address '{}'", address))
+        };
+        Ok(Self {
+            transport: builder.timeout(Some(Duration::from_secs(15))).build(),
+            from: mailbox(&config.from)?,
+            to: config.to.iter().map(mailbox).collect::<Result<_>>()?,
+        })
+    }
+}
+
+impl Backend for Email {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<()> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(notification.title())
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message.body(notification.message.clone())?;
+        // lettre's errors already include their cause
+        self.transport
+            .send(&message)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+//! Backends that POST JSON: Slack, Telegram and generic webhooks
+
+use anyhow::{Result, bail};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use super::backend::{Backend, Notification, SlackConfig, TelegramConfig, WebhookConfig};
+
+static AGENT: LazyLock<ureq::Agent> = LazyLock::new(|| {
+    ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(15))
+        .build()
+});
+
+/// POST `body` as JSON. Errors leave out the URL, which often holds a token.
+fn post(url: &str, headers: &[(String, String)], body: serde_json::Value) -> Result<()> {
+    let mut request = AGENT
+        .post(url)
+        .set("User-Agent", &format!("hazelnut/{}", crate::VERSION));
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    match request.send_json(body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(status, response)) => {
+            let text = response.into_string().unwrap_or_default();
+            let text: String = text.trim().chars().take(200).collect();
+            bail!("HTTP {}: {}", status, text)
+        }
+        Err(ureq::Error::Transport(transport)) => {
+            let mut message = transport.kind().to_string();
+            if let Some(detail) = transport.message() {
+                message.push_str(&format!(": {}", detail));
+            }
+            if let Some(source) = std::error::Error::source(&transport) {
+                message.push_str(&format!(": {}", source));
+            }
+            bail!(message)
+        }
+    }
+}
+
+pub(super) struct Slack {
+    webhook_url: String,
+}
+
+impl Slack {
+    pub(super) fn new(config: &SlackConfig) -> Self {
+        Self {
+            webhook_url: crate::expand_env(&config.webhook_url),
+        }
+    }
+}
+
+impl Backend for Slack {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<()> {
+        let text = format!("*{}*\n{}", notification.title(), notification.message);
+        post(&self.webhook_url, &[], serde_json::json!({ "text": text }))
+    }
+}
+
+pub(super) struct Telegram {
+    bot_token: String,
+    chat_id: String,
+}
+
+impl Telegram {
+    pub(super) fn new(config: &TelegramConfig) -> Self {
+        Self {
+            bot_token: crate::expand_env(&config.bot_token),
+            chat_id: crate::expand_env(&config.chat_id),
+        }
+    }
+}
+
+impl Backend for Telegram {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<()> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
+        let text = format!("{}\n{}", notification.title(), notification.message);
+        post(
+            &url,
+            &[],
+            serde_json::json!({ "chat_id": self.chat_id, "text": text }),
+        )
+    }
+}
+
+pub(super) struct Webhook {
+    /// `webhook <host>`, so several webhooks can be told apart in logs
+    name: String,
+    url: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Webhook {
+    pub(super) fn new(config: &WebhookConfig) -> Self {
+        let url = crate::expand_env(&config.url);
+        let host = url
+            .split_once("://")
+            .map_or(url.as_str(), |(_, rest)| rest)
+            .split(['/', '?'])
+            .next()
+            .unwrap_or_default();
+        Self {
+            name: format!("webhook {}", host),
+            headers: config
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), crate::expand_env(value)))
+                .collect(),
+            url,
+        }
+    }
+}
+
+/// Body of a generic webhook request
+fn webhook_payload(notification: &Notification) -> serde_json::Value {
+    serde_json::json!({
+        "kind": notification.kind,
+        "title": notification.title(),
+        "message": notification.message,
+        "time": chrono::Local::now().to_rfc3339(),
+    })
+}
+
+impl Backend for Webhook {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn send(&self, notification: &Notification) -> Result<()> {
+        post(&self.url, &self.headers, webhook_payload(notification))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::NotificationKind;
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    #[test]
+    fn test_webhook() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            (head, String::from_utf8(body).unwrap())
+        });
+
+        let webhook = Webhook::new(&WebhookConfig {
+            url: format!("http://127.0.0.1:{}/hook?token=x", port),
+            headers: [("X-Token".to_string(), "secret".to_string())].into(),
+        });
+        assert_eq!(webhook.name(), format!("webhook 127.0.0.1:{}", port));
+        webhook
+            .send(&Notification {
+                kind: NotificationKind::RuleError,
+                message: "Rule 'PDFs' failed".to_string(),
+            })
+            .unwrap();
+
+        let (head, body) = server.join().unwrap();
+        assert!(head.starts_with("POST /hook?token=x HTTP/1.1"));
+        assert!(head.contains("X-Token: secret"));
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["kind"], "rule_error");
+        assert_eq!(body["title"], "Hazelnut: Rule Error");
+        assert_eq!(body["message"], "Rule 'PDFs' failed");
+    }
+}
@@ -0,0 +1,584 @@
+//! Notifications for error alerts
+//!
+//! Only notifies on errors (and the opt-in unmatched-files report, and files
+//! handled by rules with `notify` on) to avoid being noisy. Bursts of files get one summary instead of one error each,
+//! and notifications during quiet hours are held for a digest. With a
+//! `digest_interval` everything is held and summed up on a schedule, and a
+//! rule's `notify_limit` caps how often it alerts.
+//!
+//! Notifications go to every backend `[notifications]` turns on: the
+//! desktop, Slack, Telegram, email or webhooks. Backends are called on a
+//! thread of their own, so a slow server never holds up file processing.
+
+mod backend;
+mod desktop;
+#[cfg(feature = "remote-notifications")]
+mod email;
+#[cfg(feature = "remote-notifications")]
+mod http;
+mod throttle;
+
+pub use backend::{
+    Backend, EmailConfig, EmailSecurity, Notification, NotificationsConfig, SlackConfig,
+    TelegramConfig, WebhookConfig,
+};
+pub use throttle::{Interval, RateLimit};
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, RwLock, mpsc};
+use std::time::Instant;
+use throttle::Window;
+
+/// Global flag to enable/disable notifications
+static NOTIFICATIONS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Set for `notification_level = "errors"`
+static ERRORS_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Which notifications are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationLevel {
+    /// Errors, summaries and reports
+    All,
+    /// Only failures, sent right away even with a digest interval
+    Errors,
+    /// Nothing, not even from rules with `notify` on
+    Off,
+}
+
+impl NotificationLevel {
+    pub fn next(&self) -> Self {
+        match self {
+            NotificationLevel::Off => NotificationLevel::Errors,
+            NotificationLevel::Errors => NotificationLevel::All,
+            NotificationLevel::All => NotificationLevel::Off,
+        }
+    }
+}
+
+impl std::fmt::Display for NotificationLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            NotificationLevel::All => "all",
+            NotificationLevel::Errors => "errors",
+            NotificationLevel::Off => "off",
+        })
+    }
+}
+
+type Backends = Arc<Vec<Box<dyn Backend>>>;
+
+/// Backends notifications currently go to
+static BACKENDS: LazyLock<RwLock<Backends>> = LazyLock::new(Default::default);
+
+/// Hands notifications to the thread that delivers them
+static QUEUE: LazyLock<mpsc::Sender<(Backends, Notification)>> = LazyLock::new(|| {
+    let (sender, receiver) = mpsc::channel::<(Backends, Notification)>();
+    let spawned = std::thread::Builder::new()
+        .name("notifications".to_string())
+        .spawn(move || {
+            for (backends, notification) in receiver {
+                for backend in backends.iter() {
+                    if let Err(e) = backend.send(&notification) {
+                        tracing::warn!("Failed to send {} notification: {:#}", backend.name(), e);
+                    }
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        tracing::warn!("Failed to start the notification thread: {}", e);
+    }
+    sender
+});
+
+/// The digest interval and per-rule settings in effect
+#[derive(Debug, Default)]
+struct Settings {
+    digest_interval: Option<Interval>,
+    limits: HashMap<String, RateLimit>,
+    /// Rules whose `notify` turns their notifications on or off
+    overrides: HashMap<String, bool>,
+}
+
+static SETTINGS: LazyLock<RwLock<Settings>> = LazyLock::new(Default::default);
+
+/// Each limited rule's current window, by rule name
+static WINDOWS: LazyLock<Mutex<HashMap<String, Window>>> = LazyLock::new(Default::default);
+
+/// Initialize notifications from the config: the level, the backends to
+/// use, the digest interval and rule settings. Backends that can't be set
+/// up are logged and left out.
+pub fn init(config: &crate::Config) {
+    let level = config.general.notification_level();
+    let enabled = level != NotificationLevel::Off;
+    NOTIFICATIONS_ENABLED.store(enabled, Ordering::SeqCst);
+    ERRORS_ONLY.store(level == NotificationLevel::Errors, Ordering::SeqCst);
+    // An explicit `off` silences rules' `notify` as well
+    let silenced = config.general.notification_level == Some(NotificationLevel::Off);
+    let overrides: HashMap<_, _> = config
+        .rules
+        .iter()
+        .filter_map(|rule| Some((rule.name.clone(), rule.notify.as_ref()?.is_enabled())))
+        .filter(|(_, on)| !silenced || !on)
+        .collect();
+    let mut backends = Vec::new();
+    if enabled || overrides.values().any(|on| *on) {
+        for backend in backend::build(&config.notifications) {
+            match backend {
+                Ok(backend) => backends.push(backend),
+                Err(e) => tracing::warn!("Notifications: {:#}", e),
+            }
+        }
+    }
+    if let Ok(mut active) = BACKENDS.write() {
+        *active = Arc::new(backends);
+    }
+
+    let limits: HashMap<_, _> = config
+        .rules
+        .iter()
+        .filter_map(|rule| Some((rule.name.clone(), rule.notify_limit?)))
+        .collect();
+    if let Ok(mut windows) = WINDOWS.lock() {
+        windows.retain(|rule, _| limits.contains_key(rule));
+    }
+    if let Ok(mut settings) = SETTINGS.write() {
+        *settings = Settings {
+            digest_interval: config.notifications.digest_interval,
+            limits,
+            overrides,
+        };
+    }
+}
+
+fn digest_interval() -> Option<Interval> {
+    SETTINGS.read().ok()?.digest_interval
+}
+
+/// Send a test notification through each backend in `config`, waiting for
+/// each to finish. One result per backend, named.
+pub fn send_test(config: &NotificationsConfig) -> Vec<Result<String>> {
+    let notification = Notification {
+        kind: NotificationKind::Test,
+        message: "Notifications from hazelnut arrive here.".to_string(),
+    };
+    backend::build(config)
+        .into_iter()
+        .map(|backend| {
+            let backend = backend?;
+            backend
+                .send(&notification)
+                .with_context(|| backend.name().to_string())?;
+            Ok(backend.name().to_string())
+        })
+        .collect()
+}
+
+/// Check if notifications are enabled
+pub fn is_enabled() -> bool {
+    NOTIFICATIONS_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Whether only failures are sent (`notification_level = "errors"`)
+pub fn errors_only() -> bool {
+    ERRORS_ONLY.load(Ordering::SeqCst)
+}
+
+/// Notification severity level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// Rule execution failed
+    RuleError,
+    /// Watch folder issue
+    WatchError,
+    /// Command execution failed
+    CommandError,
+    /// Scheduled unmatched-files report
+    Report,
+    /// Summary of a burst of files
+    Batch,
+    /// Notifications held back during quiet hours
+    Digest,
+    /// Everything collected over the digest interval
+    Summary,
+    /// Edited config couldn't be loaded
+    ConfigError,
+    /// Sent by `hazelnut notifications test`
+    Test,
+    /// A file handled by a rule with `notify` on
+    Organized,
+    /// Files removed to keep a watched folder under its `max_size`
+    Quota,
+}
+
+impl NotificationKind {
+    /// Whether this reports a failure
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            NotificationKind::RuleError
+                | NotificationKind::WatchError
+                | NotificationKind::CommandError
+                | NotificationKind::ConfigError
+        )
+    }
+
+    #[cfg(feature = "notifications")]
+    fn icon(&self) -> &'static str {
+        match self {
+            NotificationKind::RuleError => "dialog-error",
+            NotificationKind::WatchError => "dialog-warning",
+            NotificationKind::CommandError => "dialog-error",
+            NotificationKind::Report => "dialog-information",
+            NotificationKind::Batch => "dialog-information",
+            NotificationKind::Digest => "dialog-information",
+            NotificationKind::Summary => "dialog-information",
+            NotificationKind::ConfigError => "dialog-error",
+            NotificationKind::Test => "dialog-information",
+            NotificationKind::Organized => "dialog-information",
+            NotificationKind::Quota => "dialog-information",
+        }
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            NotificationKind::RuleError => "Rule Error",
+            NotificationKind::WatchError => "Watch Error",
+            NotificationKind::CommandError => "Command Error",
+            NotificationKind::Report => "Unmatched Files",
+            NotificationKind::Batch => "Batch Complete",
+            NotificationKind::Digest => "While You Were Away",
+            NotificationKind::Summary => "Summary",
+            NotificationKind::ConfigError => "Config Error",
+            NotificationKind::Test => "Test",
+            NotificationKind::Organized => "Organized",
+            NotificationKind::Quota => "Folder Quota",
+        }
+    }
+}
+
+/// Notifications held back during quiet hours or for the digest interval
+#[derive(Debug, Default)]
+struct Digest {
+    /// The first notifications, kept for the digest text
+    entries: Vec<(NotificationKind, String)>,
+    /// All held notifications, including ones beyond `MAX_DIGEST_ENTRIES`
+    total: usize,
+    /// Files organized and failed, counted with a digest interval
+    organized: usize,
+    failed: usize,
+    /// When the digest interval started collecting
+    since: Option<(Instant, chrono::DateTime<chrono::Local>)>,
+}
+
+/// Held notifications kept in memory (the rest are only counted)
+const MAX_DIGEST_ENTRIES: usize = 100;
+
+/// Held notifications listed in the digest text
+const DIGEST_LINES: usize = 5;
+
+static DIGEST: Mutex<Digest> = Mutex::new(Digest {
+    entries: Vec::new(),
+    total: 0,
+    organized: 0,
+    failed: 0,
+    since: None,
+});
+
+fn plural(count: usize, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
+
+impl Digest {
+    /// Start the digest interval, if it isn't running
+    fn start(&mut self) {
+        if self.since.is_none() {
+            self.since = Some((Instant::now(), chrono::Local::now()));
+        }
+    }
+
+    fn hold(&mut self, kind: NotificationKind, message: &str) {
+        self.total += 1;
+        if self.entries.len() < MAX_DIGEST_ENTRIES {
+            self.entries.push((kind, message.to_string()));
+        }
+    }
+
+    /// Digest text, e.g. "3 notifications during quiet hours:" or "142 files
+    /// organized, 2 errors since 09:15:", and one line per notification
+    fn message(&self) -> String {
+        let mut message = match self.since {
+            Some((_, started)) => {
+                let mut counts = Vec::new();
+                if self.organized > 0 {
+                    counts.push(format!("{} organized", plural(self.organized, "file")));
+                }
+                if self.failed > 0 {
+                    counts.push(plural(self.failed, "error"));
+                }
+                if counts.is_empty() {
+                    counts.push(plural(self.total, "notification"));
+                }
+                format!("{} since {}", counts.join(", "), started.format("%H:%M"))
+            }
+            None => format!("{} during quiet hours", plural(self.total, "notification")),
+        };
+        if !self.entries.is_empty() {
+            message.push(':');
+        }
+        for (kind, text) in self.entries.iter().take(DIGEST_LINES) {
+            message.push_str(&format!("\n• {}: {}", kind.prefix(), text));
+        }
+        let listed = self.entries.len().min(DIGEST_LINES);
+        if self.total > listed {
+            message.push_str(&format!("\n…and {} more", self.total - listed));
+        }
+        message
+    }
+}
+
+/// Send a notification if enabled, or hold it for the digest during quiet
+/// hours or with a digest interval
+///
+/// This is fire-and-forget - errors are logged but don't propagate.
+pub fn notify(kind: NotificationKind, message: &str) {
+    if is_enabled() && (kind.is_error() || !errors_only()) {
+        deliver(kind, message);
+    }
+}
+
+/// Send a notification, or hold it for the digest
+fn deliver(kind: NotificationKind, message: &str) {
+    // With errors only, failures don't wait for the digest
+    if digest_interval().is_some() && !(kind.is_error() && errors_only()) {
+        // The digest's file counts already cover batches
+        if kind != NotificationKind::Batch
+            && let Ok(mut digest) = DIGEST.lock()
+        {
+            digest.start();
+            digest.hold(kind, message);
+        }
+        return;
+    }
+
+    if crate::quiet_hours::suppresses(kind) {
+        if let Ok(mut digest) = DIGEST.lock() {
+            digest.hold(kind, message);
+        }
+        return;
+    }
+
+    show(kind, message);
+}
+
+/// Notify about something a rule did, if its `notify` or else
+/// the notification level allows it, within the rule's `notify_limit`
+fn notify_rule(rule_name: &str, kind: NotificationKind, message: &str) {
+    let (enabled, limit) = match SETTINGS.read() {
+        Ok(settings) => (
+            settings
+                .overrides
+                .get(rule_name)
+                .copied()
+                .unwrap_or_else(|| is_enabled() && (kind.is_error() || !errors_only())),
+            settings.limits.get(rule_name).copied(),
+        ),
+        Err(_) => return,
+    };
+    if !enabled {
+        return;
+    }
+    if let Some(limit) = limit
+        && let Ok(mut windows) = WINDOWS.lock()
+    {
+        let now = Instant::now();
+        let window = windows
+            .entry(rule_name.to_string())
+            .or_insert_with(|| Window::new(now));
+        if !window.admit(&limit, now, message) {
+            return;
+        }
+    }
+    deliver(kind, message);
+}
+
+/// Count a file organized, for the digest's summary
+pub fn record_organized() {
+    record(|digest| digest.organized += 1);
+}
+
+/// Count a file a rule failed on, for the digest's summary
+pub fn record_failed() {
+    record(|digest| digest.failed += 1);
+}
+
+fn record(count: impl FnOnce(&mut Digest)) {
+    if is_enabled()
+        && !errors_only()
+        && digest_interval().is_some()
+        && let Ok(mut digest) = DIGEST.lock()
+    {
+        digest.start();
+        count(&mut digest);
+    }
+}
+
+/// Send what's been held back: the digest once quiet hours are over or its
+/// interval has passed, and the notifications rule limits held back once
+/// their period is over
+pub fn flush_digest() {
+    if let Ok(settings) = SETTINGS.read()
+        && let Ok(mut windows) = WINDOWS.lock()
+    {
+        let now = Instant::now();
+        let summaries: Vec<_> = windows
+            .iter_mut()
+            .filter_map(|(rule, window)| {
+                let limit = settings.limits.get(rule)?;
+                window.take_summary(rule, limit, now)
+            })
+            .collect();
+        drop(windows);
+        drop(settings);
+        for summary in summaries {
+            deliver(NotificationKind::RuleError, &summary);
+        }
+    }
+
+    if crate::quiet_hours::is_quiet() {
+        return;
+    }
+    let interval = digest_interval();
+    let digest = match DIGEST.lock() {
+        Ok(mut digest) => {
+            let due = match (interval, digest.since) {
+                (Some(interval), Some((started, _))) => started.elapsed() >= interval.duration(),
+                // Turned off since: send what's left
+                (None, Some(_)) => true,
+                (_, None) => digest.total > 0,
+            };
+            if !due {
+                return;
+            }
+            std::mem::take(&mut *digest)
+        }
+        Err(_) => return,
+    };
+    let kind = match digest.since {
+        Some(_) => NotificationKind::Summary,
+        None => NotificationKind::Digest,
+    };
+    show(kind, &digest.message());
+}
+
+/// Queue a notification for every backend
+fn show(kind: NotificationKind, message: &str) {
+    let backends = match BACKENDS.read() {
+        Ok(backends) if !backends.is_empty() => Arc::clone(&backends),
+        _ => return,
+    };
+    let notification = Notification {
+        kind,
+        message: message.to_string(),
+    };
+    if QUEUE.send((backends, notification)).is_err() {
+        tracing::warn!("Failed to send notification: the notification thread isn't running");
+    }
+}
+
+/// Convenience function for rule errors
+pub fn notify_rule_error(rule_name: &str, error: &str) {
+    notify_rule(
+        rule_name,
+        NotificationKind::RuleError,
+        &format!("Rule '{}' failed: {}", rule_name, error),
+    );
+}
+
+/// Notification for a file organized by a rule with `notify` on
+pub fn notify_organized(rule_name: &str, message: &str) {
+    notify_rule(rule_name, NotificationKind::Organized, message);
+}
+
+/// Convenience function for watch errors
+pub fn notify_watch_error(path: &str, error: &str) {
+    notify(
+        NotificationKind::WatchError,
+        &format!("Watch '{}': {}", path, error),
+    );
+}
+
+/// Convenience function for config reload errors
+pub fn notify_config_error(error: &str) {
+    notify(
+        NotificationKind::ConfigError,
+        &format!("Keeping the previous config: {}", error),
+    );
+}
+
+/// Convenience function for command errors
+pub fn notify_command_error(command: &str, error: &str) {
+    // Truncate command if too long
+    let cmd_display = if command.len() > 50 {
+        let truncated: String = command.chars().take(47).collect();
+        format!("{}...", truncated)
+    } else {
+        command.to_string()
+    };
+    notify(
+        NotificationKind::CommandError,
+        &format!("Command '{}' failed: {}", cmd_display, error),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest_message() {
+        let mut digest = Digest::default();
+        for i in 0..7 {
+            digest.total += 1;
+            digest
+                .entries
+                .push((NotificationKind::RuleError, format!("error {i}")));
+        }
+        let message = digest.message();
+        assert!(message.starts_with("7 notifications during quiet hours:"));
+        assert!(message.contains("• Rule Error: error 4"));
+        assert!(!message.contains("error 5"));
+        assert!(message.ends_with("…and 2 more"));
+    }
+
+    #[test]
+    fn test_summary_message() {
+        let started = chrono::Local::now();
+        let mut digest = Digest {
+            organized: 142,
+            failed: 2,
+            since: Some((Instant::now(), started)),
+            ..Default::default()
+        };
+        let since = started.format("%H:%M");
+        assert_eq!(
+            digest.message(),
+            format!("142 files organized, 2 errors since {}", since)
+        );
+
+        digest.hold(NotificationKind::RuleError, "Rule 'PDFs' failed: disk full");
+        assert_eq!(
+            digest.message(),
+            format!(
+                "142 files organized, 2 errors since {}:\n• Rule Error: Rule 'PDFs' failed: disk full",
+                since
+            )
+        );
+    }
+}
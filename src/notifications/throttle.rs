@@ -0,0 +1,204 @@
+//! Digest intervals and per-rule notification limits
+
+use anyhow::{Context, Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// A length of time written with a unit: `"90s"`, `"15m"`, `"1h"`, `"1d"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+pub struct Interval(Duration);
+
+impl Interval {
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+
+    fn parse(s: &str, default_count: Option<u64>) -> Result<Self> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (count, unit) = s.split_at(split);
+        let count = match (count, default_count) {
+            ("", Some(count)) => count,
+            _ => count
+                .parse::<u64>()
+                .with_context(|| format!("invalid interval '{}', expected e.g. 15m or 1h", s))?,
+        };
+        let seconds = match unit.trim() {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "h" | "hour" | "hours" => 3600,
+            "d" | "day" | "days" => 86400,
+            _ => bail!("invalid interval '{}', expected a unit of s, m, h or d", s),
+        };
+        if count == 0 {
+            bail!("interval '{}' is empty", s);
+        }
+        Ok(Self(Duration::from_secs(count * seconds)))
+    }
+}
+
+impl TryFrom<String> for Interval {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        Self::parse(&s, None)
+    }
+}
+
+impl From<Interval> for String {
+    fn from(interval: Interval) -> Self {
+        interval.to_string()
+    }
+}
+
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.0.as_secs();
+        match [(86400, "d"), (3600, "h"), (60, "m")]
+            .into_iter()
+            .find(|(unit, _)| secs.is_multiple_of(*unit))
+        {
+            Some((unit, name)) => write!(f, "{}{}", secs / unit, name),
+            None => write!(f, "{}s", secs),
+        }
+    }
+}
+
+/// At most `count` notifications per `per`, written `"3/h"` or `"10/15m"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+pub struct RateLimit {
+    pub count: u32,
+    pub per: Interval,
+}
+
+impl TryFrom<String> for RateLimit {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        let (count, per) = s
+            .split_once('/')
+            .with_context(|| format!("invalid limit '{}', expected e.g. 3/h", s))?;
+        let count = count
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid limit '{}', expected e.g. 3/h", s))?;
+        Ok(Self {
+            count,
+            per: Interval::parse(per, Some(1))?,
+        })
+    }
+}
+
+impl From<RateLimit> for String {
+    fn from(limit: RateLimit) -> Self {
+        let per = limit.per.to_string();
+        // "3/h" rather than "3/1h"
+        match per.strip_prefix('1') {
+            Some(unit) if unit.len() == 1 => format!("{}/{}", limit.count, unit),
+            _ => format!("{}/{}", limit.count, per),
+        }
+    }
+}
+
+/// Notifications from one rule within its current limit window
+#[derive(Debug)]
+pub(super) struct Window {
+    started: Instant,
+    sent: u32,
+    held: usize,
+    latest: String,
+}
+
+impl Window {
+    pub(super) fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            sent: 0,
+            held: 0,
+            latest: String::new(),
+        }
+    }
+
+    /// Whether a notification may go out now; one that may not is counted
+    /// for the summary
+    pub(super) fn admit(&mut self, limit: &RateLimit, now: Instant, message: &str) -> bool {
+        if now.duration_since(self.started) >= limit.per.duration() && self.held == 0 {
+            *self = Self::new(now);
+        }
+        if self.sent < limit.count {
+            self.sent += 1;
+            return true;
+        }
+        self.held += 1;
+        self.latest = message.to_string();
+        false
+    }
+
+    /// Once the window is over, what was held back, e.g. "Rule 'PDFs': 12
+    /// more notifications in the last 1h, the latest: ..."
+    pub(super) fn take_summary(
+        &mut self,
+        rule: &str,
+        limit: &RateLimit,
+        now: Instant,
+    ) -> Option<String> {
+        if self.held == 0 || now.duration_since(self.started) < limit.per.duration() {
+            return None;
+        }
+        let summary = format!(
+            "Rule '{}': {} more notification{} in the last {}, the latest: {}",
+            rule,
+            self.held,
+            if self.held == 1 { "" } else { "s" },
+            limit.per,
+            self.latest
+        );
+        *self = Self::new(now);
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit() {
+        let interval = Interval::try_from("15m".to_string()).unwrap();
+        assert_eq!(interval.duration(), Duration::from_secs(900));
+        assert_eq!(String::from(interval), "15m");
+        assert_eq!(
+            String::from(Interval::try_from("90 s".to_string()).unwrap()),
+            "90s"
+        );
+        assert!(Interval::try_from("15".to_string()).is_err());
+        assert!(Interval::try_from("0h".to_string()).is_err());
+        assert!(Interval::try_from("h".to_string()).is_err());
+
+        let limit = RateLimit::try_from("3/h".to_string()).unwrap();
+        assert_eq!(limit.count, 3);
+        assert_eq!(limit.per.duration(), Duration::from_secs(3600));
+        assert_eq!(String::from(limit), "3/h");
+        let limit2 = RateLimit::try_from("10 / 15m".to_string()).unwrap();
+        assert_eq!(String::from(limit2), "10/15m");
+        assert!(RateLimit::try_from("3".to_string()).is_err());
+
+        let start = Instant::now();
+        let mut window = Window::new(start);
+        let admitted: Vec<_> = (0..5)
+            .map(|i| window.admit(&limit, start, &format!("error {}", i)))
+            .collect();
+        assert_eq!(admitted, [true, true, true, false, false]);
+        assert_eq!(window.take_summary("PDFs", &limit, start), None);
+
+        let later = start + Duration::from_secs(3600);
+        assert_eq!(
+            window.take_summary("PDFs", &limit, later).unwrap(),
+            "Rule 'PDFs': 2 more notifications in the last 1h, the latest: error 4"
+        );
+        assert!(window.admit(&limit, later, "error 5"));
+    }
+}
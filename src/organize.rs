@@ -0,0 +1,253 @@
+//! One-shot organizing of a folder
+//!
+//! `hazelnut organize <dir>` applies the configured rules to the files
+//! already in a folder once and exits, for scripts and cron jobs that don't
+//! want a resident daemon. A folder that is one of the configured watches
+//! keeps that watch's settings (its rule list, recursion, filters and
+//! `unmatched_action` and `remove_empty_dirs`); any other folder gets every
+//! rule, non-recursively.
+//! The files are listed like the Preview view does, so a dry run shows
+//! exactly what an actual run would do.
+
+use anyhow::{Result, bail};
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, WatchConfig};
+use crate::preview::FilePreview;
+use crate::rules::RuleEngine;
+
+/// How many files one rule took
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleCount {
+    /// Rule name (empty for the watch's `unmatched_action`)
+    pub rule: String,
+    /// The action's `type`, e.g. `move`
+    pub action: &'static str,
+    pub files: usize,
+}
+
+/// What organizing a folder did, or would do
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    /// The folder (expanded)
+    pub dir: PathBuf,
+    pub dry_run: bool,
+    /// Files (and folders, for folder rules) looked at
+    pub scanned: usize,
+    /// Matches per rule, in the order the rules were first seen
+    pub rules: Vec<RuleCount>,
+    /// Files at least one action ran (or would run) on
+    pub organized: usize,
+    /// Files held back by quiet hours or a rule's active hours
+    pub deferred: Vec<PathBuf>,
+    /// Files an action failed on, and why
+    pub failed: Vec<(PathBuf, String)>,
+    /// Only the first `preview::MAX_FILES` files were looked at
+    pub truncated: bool,
+}
+
+impl Summary {
+    fn count(&mut self, file: &FilePreview) {
+        for planned in &file.planned {
+            match self.rules.iter_mut().find(|c| c.rule == planned.rule) {
+                Some(count) => count.files += 1,
+                None => self.rules.push(RuleCount {
+                    rule: planned.rule.clone(),
+                    action: planned.action,
+                    files: 1,
+                }),
+            }
+        }
+    }
+
+    /// Files no rule matched
+    pub fn left_alone(&self) -> usize {
+        self.scanned - self.organized - self.deferred.len() - self.failed.len()
+    }
+
+    /// A table of the matches per rule followed by the totals
+    pub fn table(&self) -> String {
+        let name = |count: &RuleCount| {
+            if count.rule.is_empty() {
+                "(unmatched action)".to_string()
+            } else {
+                count.rule.clone()
+            }
+        };
+        let width = self
+            .rules
+            .iter()
+            .map(|c| name(c).chars().count())
+            .chain(std::iter::once(4))
+            .max()
+            .unwrap_or(4);
+        let mut table = String::new();
+        if !self.rules.is_empty() {
+            table.push_str(&format!(
+                "{:<width$}  {:<8}  {:>6}\n",
+                "Rule", "Action", "Files"
+            ));
+            for count in &self.rules {
+                table.push_str(&format!(
+                    "{:<width$}  {:<8}  {:>6}\n",
+                    name(count),
+                    count.action,
+                    count.files
+                ));
+            }
+            table.push('\n');
+        }
+        let verb = if self.dry_run {
+            "would be organized"
+        } else {
+            "organized"
+        };
+        table.push_str(&format!(
+            "{} of {} files {}, {} left alone",
+            self.organized,
+            self.scanned,
+            verb,
+            self.left_alone()
+        ));
+        if !self.deferred.is_empty() {
+            table.push_str(&format!(", {} deferred", self.deferred.len()));
+        }
+        if !self.failed.is_empty() {
+            table.push_str(&format!(", {} failed", self.failed.len()));
+        }
+        table.push('\n');
+        table
+    }
+}
+
+/// The watch `dir` is, or a non-recursive one with every rule
+pub fn watch_for(config: &Config, dir: &Path) -> WatchConfig {
+    let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = absolute(&crate::expand_path(dir));
+    config
+        .watches
+        .iter()
+        .find(|w| !crate::sftp::is_remote(&w.path) && absolute(&crate::expand_path(&w.path)) == dir)
+        .cloned()
+        .map(|watch| WatchConfig {
+            path: dir.clone(),
+            ..watch
+        })
+        .unwrap_or_else(|| WatchConfig {
+            path: dir,
+            ..Default::default()
+        })
+}
+
+/// Apply the rules to the files in a watch's folder once, or with `dry_run`
+/// only work out what they would do
+pub fn organize(watch: &WatchConfig, engine: &RuleEngine, dry_run: bool) -> Result<Summary> {
+    if crate::sftp::is_remote(&watch.path) {
+        bail!("Remote folders can only be organized by the daemon");
+    }
+    let root = crate::expand_path(&watch.path);
+    if !root.is_dir() {
+        bail!("{} is not a folder", root.display());
+    }
+    let allowed = (!watch.rules.is_empty()).then_some(watch.rules.as_slice());
+    let filter = crate::watcher::DirFilter::from_watch(watch);
+    let (files, truncated) = crate::preview::preview_watch(watch, engine)?;
+    let mut summary = Summary {
+        dir: root.clone(),
+        dry_run,
+        scanned: files.len(),
+        truncated,
+        ..Default::default()
+    };
+
+    for file in &files {
+        if let Some(error) = &file.error {
+            summary.failed.push((file.path.clone(), error.clone()));
+            continue;
+        }
+        if file.planned.is_empty() {
+            continue;
+        }
+        if dry_run {
+            summary.count(file);
+            summary.organized += 1;
+            continue;
+        }
+        let result = match (&watch.unmatched_action, file.planned[0].rule.is_empty()) {
+            (Some(action), true) => engine.process_unmatched(&file.path, action).map(|_| true),
+            _ if file.path.is_dir() => engine.process_folder(&file.path, allowed, Some(&root)),
+            _ => engine.process_in_watch(&file.path, allowed, Some(&root)),
+        };
+        match result {
+            // Changed since it was listed, so no rule matches it anymore
+            Ok(false) => {}
+            Ok(true) if engine.is_deferred(&file.path) => summary.deferred.push(file.path.clone()),
+            Ok(true) => {
+                summary.count(file);
+                summary.organized += 1;
+                if watch.remove_empty_dirs
+                    && !file.path.exists()
+                    && let Some(parent) = file.path.parent()
+                {
+                    filter.remove_empty_dirs(&root, parent);
+                }
+            }
+            Err(e) => summary.failed.push((file.path.clone(), format!("{:#}", e))),
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Action, Condition, OnConflict, Rule};
+
+    #[test]
+    fn test_organize() {
+        let dir = tempfile::tempdir().unwrap();
+        let inbox = dir.path().join("inbox");
+        std::fs::create_dir(&inbox).unwrap();
+        std::fs::write(inbox.join("a.pdf"), "x").unwrap();
+        std::fs::write(inbox.join("b.pdf"), "x").unwrap();
+        std::fs::write(inbox.join("notes.txt"), "x").unwrap();
+        let docs = dir.path().join("docs");
+        let engine = RuleEngine::new(vec![Rule::new(
+            "PDFs",
+            Condition {
+                extension: Some("pdf".to_string()),
+                ..Default::default()
+            },
+            Action::Move {
+                destination: docs.clone(),
+                create_destination: true,
+                on_conflict: OnConflict::Fail,
+                preserve_metadata: true,
+            },
+        )]);
+        let watch = watch_for(&Config::default(), &inbox);
+        assert_eq!(watch.path, inbox);
+
+        let summary = organize(&watch, &engine, true).unwrap();
+        assert_eq!(summary.scanned, 3);
+        assert_eq!(summary.organized, 2);
+        assert_eq!(summary.left_alone(), 1);
+        assert_eq!(
+            summary.rules,
+            vec![RuleCount {
+                rule: "PDFs".to_string(),
+                action: "move",
+                files: 2,
+            }]
+        );
+        assert!(inbox.join("a.pdf").exists());
+        assert!(summary.table().contains("2 of 3 files would be organized"));
+
+        let summary = organize(&watch, &engine, false).unwrap();
+        assert_eq!(summary.organized, 2);
+        assert!(summary.failed.is_empty());
+        assert!(docs.join("a.pdf").exists());
+        assert!(!inbox.join("b.pdf").exists());
+        assert!(inbox.join("notes.txt").exists());
+    }
+}
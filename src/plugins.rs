@@ -0,0 +1,337 @@
+//! Plugins - conditions and actions provided by external programs
+//!
+//! A plugin is a program declared under `[plugin.<name>]`. Rules use it with
+//! `type = "plugin:<name>"`, as their action or in the condition's `plugins`
+//! list; the table's other keys are passed to the plugin as options, with
+//! pattern variables (`{name}`, `{date}`, …) expanded for each file.
+//!
+//! For every file, the program is started with one JSON request on stdin:
+//!
+//! ```json
+//! {"version": 1, "kind": "condition", "plugin": "dam",
+//!  "options": {"catalog": "photos"},
+//!  "file": {"path": "/in/a.jpg", "name": "a.jpg", "extension": "jpg",
+//!           "size": 1024, "modified": "2026-01-01T10:00:00+01:00", "is_dir": false}}
+//! ```
+//!
+//! and answers with one JSON object on stdout: `{"matches": true}` for a
+//! condition, `{}` or `{"path": "/where/it/landed"}` for an action. An
+//! `"error"` in the answer or a non-zero exit status fails the check or the
+//! action (with the error or stderr as the reason).
+
+use anyhow::{Context, Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{LazyLock, RwLock};
+use std::time::Duration;
+use tracing::debug;
+
+/// Prefix of the `type` that uses a plugin
+pub const TYPE_PREFIX: &str = "plugin:";
+
+/// Version of the request format sent to plugins
+const PROTOCOL_VERSION: u32 = 1;
+
+/// A program that provides conditions and actions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PluginConfig {
+    /// Program to run (looked up in PATH)
+    pub command: String,
+
+    /// Arguments passed before the request is written to stdin
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+
+    /// Seconds to wait for an answer before the program is killed
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+/// A use of a plugin in a rule: `type = "plugin:<name>"` and its options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(
+    try_from = "BTreeMap<String, String>",
+    into = "BTreeMap<String, String>"
+)]
+#[schemars(extend(
+    "properties" = { "type": { "type": "string", "pattern": "^plugin:." } },
+    "required" = ["type"]
+))]
+pub struct PluginCall {
+    /// Name of the `[plugin.<name>]` table
+    pub name: String,
+    /// The table's other keys, passed to the plugin
+    pub options: BTreeMap<String, String>,
+}
+
+impl TryFrom<BTreeMap<String, String>> for PluginCall {
+    type Error = anyhow::Error;
+
+    fn try_from(mut table: BTreeMap<String, String>) -> Result<Self> {
+        let kind = table.remove("type").context("missing field `type`")?;
+        let Some(name) = kind.strip_prefix(TYPE_PREFIX) else {
+            bail!(
+                "unknown type '{}' (plugins are used as `type = \"{}<name>\"`)",
+                kind,
+                TYPE_PREFIX
+            );
+        };
+        if name.is_empty() {
+            bail!("type '{}' names no plugin", kind);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            options: table,
+        })
+    }
+}
+
+impl From<PluginCall> for BTreeMap<String, String> {
+    fn from(call: PluginCall) -> Self {
+        let mut table = call.options;
+        table.insert("type".to_string(), format!("{}{}", TYPE_PREFIX, call.name));
+        table
+    }
+}
+
+impl PluginCall {
+    /// Ask the plugin whether a file matches
+    pub fn matches(&self, path: &Path) -> Result<bool> {
+        let answer = self.call("condition", path)?;
+        answer
+            .matches
+            .with_context(|| format!("Plugin '{}' didn't say whether it matches", self.name))
+    }
+
+    /// Have the plugin act on a file, returning where the file landed if
+    /// the plugin moved or copied it somewhere
+    pub fn execute(&self, path: &Path) -> Result<Option<PathBuf>> {
+        Ok(self.call("action", path)?.path)
+    }
+
+    fn call(&self, kind: &str, path: &Path) -> Result<Answer> {
+        let plugin = configured(&self.name)?;
+        let options = self
+            .options
+            .iter()
+            .map(|(key, value)| {
+                let value = crate::rules::expand_pattern(value, path).unwrap_or(value.clone());
+                (key.clone(), value)
+            })
+            .collect();
+        let request = Request {
+            version: PROTOCOL_VERSION,
+            kind,
+            plugin: &self.name,
+            options,
+            file: FileInfo::of(path),
+        };
+        let output = run(&plugin, &serde_json::to_vec(&request)?)
+            .with_context(|| format!("Plugin '{}'", self.name))?;
+        let answer: Answer = serde_json::from_slice(&output).with_context(|| {
+            format!(
+                "Plugin '{}' gave an invalid answer: {}",
+                self.name,
+                String::from_utf8_lossy(&output).trim()
+            )
+        })?;
+        if let Some(error) = answer.error {
+            bail!("Plugin '{}': {}", self.name, error);
+        }
+        Ok(answer)
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    version: u32,
+    kind: &'a str,
+    plugin: &'a str,
+    options: BTreeMap<String, String>,
+    file: FileInfo,
+}
+
+/// What a plugin is told about a file
+#[derive(Serialize)]
+struct FileInfo {
+    path: PathBuf,
+    name: String,
+    extension: Option<String>,
+    size: Option<u64>,
+    modified: Option<String>,
+    is_dir: bool,
+}
+
+impl FileInfo {
+    fn of(path: &Path) -> Self {
+        let metadata = path.metadata().ok();
+        Self {
+            path: path.to_path_buf(),
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            extension: path.extension().map(|e| e.to_string_lossy().into_owned()),
+            size: metadata.as_ref().map(|m| m.len()),
+            modified: metadata
+                .as_ref()
+                .and_then(|m| m.modified().ok())
+                .map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339()),
+            is_dir: metadata.is_some_and(|m| m.is_dir()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Answer {
+    #[serde(default)]
+    matches: Option<bool>,
+    #[serde(default)]
+    path: Option<PathBuf>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Run a plugin with a request on stdin, returning its stdout
+fn run(plugin: &PluginConfig, request: &[u8]) -> Result<Vec<u8>> {
+    debug!("Running plugin {} {:?}", plugin.command, plugin.args);
+    let mut child = Command::new(&plugin.command)
+        .args(&plugin.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", plugin.command))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A plugin may answer without reading the whole request
+        let _ = stdin.write_all(request);
+    }
+
+    // Grab the PID before moving the child into the wait thread, so it can
+    // be killed on timeout
+    let child_pid = child.id();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(child.wait_with_output());
+    });
+    let timeout = Duration::from_secs(plugin.timeout_secs);
+    let output = match rx.recv_timeout(timeout) {
+        Ok(output) => output?,
+        Err(_) => {
+            #[cfg(unix)]
+            if let Ok(pid) = i32::try_from(child_pid) {
+                // SAFETY: kill(2) is safe to call with any pid/signal
+                unsafe {
+                    libc::kill(pid, libc::SIGKILL);
+                }
+            }
+            #[cfg(not(unix))]
+            let _ = child_pid;
+            bail!(
+                "{} timed out after {}s",
+                plugin.command,
+                plugin.timeout_secs
+            );
+        }
+    };
+    if !output.status.success() {
+        bail!(
+            "{} exited with {}: {}",
+            plugin.command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+static PLUGINS: LazyLock<RwLock<BTreeMap<String, PluginConfig>>> = LazyLock::new(Default::default);
+
+/// Set the plugins rules can use (the config's `[plugin.<name>]` tables)
+pub fn set(plugins: BTreeMap<String, PluginConfig>) {
+    if let Ok(mut active) = PLUGINS.write() {
+        *active = plugins;
+    }
+}
+
+fn configured(name: &str) -> Result<PluginConfig> {
+    PLUGINS
+        .read()
+        .ok()
+        .and_then(|plugins| plugins.get(name).cloned())
+        .with_context(|| format!("No plugin named '{}' (add a [plugin.{}] table)", name, name))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugin_condition_and_action() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("dam.sh");
+        // Matches files whose name starts with the `catalog` option, and
+        // "files" them by moving them into a folder named after it
+        std::fs::write(
+            &script,
+            r#"request=$(cat)
+field() { printf '%s' "$request" | sed "s/.*\"$1\":\"\([^\"]*\)\".*/\1/"; }
+catalog=$(field catalog)
+case "$request" in
+  *'"kind":"condition"'*)
+    case "$(field name)" in
+      "$catalog"*) echo '{"matches": true}' ;;
+      *) echo '{"matches": false}' ;;
+    esac ;;
+  *) mkdir -p "$1/$catalog" && mv "$(field path)" "$1/$catalog/" &&
+     echo "{\"path\": \"$1/$catalog/$(field name)\"}" ;;
+esac
+"#,
+        )
+        .unwrap();
+        let plugin = PluginConfig {
+            command: "sh".to_string(),
+            args: vec![
+                script.display().to_string(),
+                dir.path().display().to_string(),
+            ],
+            timeout_secs: 10,
+        };
+        set(BTreeMap::from([("dam".to_string(), plugin)]));
+
+        let call: PluginCall = toml::from_str(
+            r#"
+            type = "plugin:dam"
+            catalog = "{ext}"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(call.name, "dam");
+        let photo = dir.path().join("jpg-photo.jpg");
+        std::fs::write(&photo, "").unwrap();
+        let other = dir.path().join("notes.txt.md");
+        std::fs::write(&other, "").unwrap();
+
+        assert!(call.matches(&photo).unwrap());
+        assert!(!call.matches(&other).unwrap());
+        let landed = call.execute(&photo).unwrap();
+        assert_eq!(landed, Some(dir.path().join("jpg").join("jpg-photo.jpg")));
+        assert!(!photo.exists());
+
+        // Unknown plugins and other types are errors
+        let missing = PluginCall {
+            name: "missing".to_string(),
+            options: BTreeMap::new(),
+        };
+        assert!(missing.matches(&other).is_err());
+        assert!(toml::from_str::<PluginCall>(r#"type = "move""#).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+//! Built-in rule presets
+//!
+//! Ready-made rules for common chores (screenshots, old downloads, invoices,
+//! camera imports). Adding a preset writes ordinary rules into the config,
+//! which can then be edited like any other; nothing refers back to the preset.
+
+use std::path::{Path, PathBuf};
+
+use crate::rules::{Action, Condition, OnConflict, Rule};
+
+/// A named set of rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preset {
+    /// Name used on the command line, e.g. `screenshots`
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Folder matched files go to unless another is given (none for presets
+    /// that trash files by default)
+    pub destination: Option<&'static str>,
+}
+
+/// Every built-in preset
+pub const PRESETS: [Preset; 4] = [
+    Preset {
+        name: "screenshots",
+        description: "Move screenshots into one folder",
+        destination: Some("~/Pictures/Screenshots"),
+    },
+    Preset {
+        name: "downloads-cleanup",
+        description: "Trash installers older than 2 weeks and archives older than 30 days",
+        destination: None,
+    },
+    Preset {
+        name: "invoices",
+        description: "File PDF invoices and receipts",
+        destination: Some("~/Documents/Invoices"),
+    },
+    Preset {
+        name: "camera-imports",
+        description: "Move photos and videos named by cameras and phones",
+        destination: Some("~/Pictures/Camera"),
+    },
+];
+
+/// Look up a preset by name
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+impl Preset {
+    /// The preset's rules, moving files to `destination` (or the preset's
+    /// default). Names already taken in `existing` get a number appended.
+    pub fn rules(&self, destination: Option<&Path>, existing: &[Rule]) -> Vec<Rule> {
+        let destination = destination
+            .map(Path::to_path_buf)
+            .or_else(|| self.destination.map(PathBuf::from));
+        let action = || match &destination {
+            Some(destination) => Action::Move {
+                destination: destination.clone(),
+                create_destination: true,
+                on_conflict: OnConflict::Fail,
+                preserve_metadata: true,
+            },
+            None => Action::Trash,
+        };
+
+        let rules = match self.name {
+            "screenshots" => vec![Rule::new(
+                "Screenshots",
+                Condition {
+                    extensions: strings(&["png", "jpg", "jpeg"]),
+                    name_regex: Some(
+                        "(?i)^(screenshot|screen shot|bildschirmfoto|capture d.écran|captura de pantalla)"
+                            .to_string(),
+                    ),
+                    ..Default::default()
+                },
+                action(),
+            )],
+            "downloads-cleanup" => vec![
+                Rule::new(
+                    "Old installers",
+                    Condition {
+                        extensions: strings(&[
+                            "dmg", "pkg", "exe", "msi", "deb", "rpm", "appimage",
+                        ]),
+                        age_days_greater_than: Some(14),
+                        ..Default::default()
+                    },
+                    action(),
+                ),
+                Rule::new(
+                    "Old archives",
+                    Condition {
+                        extensions: strings(&["zip", "tar", "gz", "tgz", "bz2", "xz", "7z", "rar"]),
+                        age_days_greater_than: Some(30),
+                        ..Default::default()
+                    },
+                    action(),
+                ),
+            ],
+            "invoices" => vec![Rule::new(
+                "Invoices",
+                Condition {
+                    extension: Some("pdf".to_string()),
+                    name_regex: Some(
+                        "(?i)(invoice|receipt|rechnung|quittung|factura|facture|fattura)".to_string(),
+                    ),
+                    ..Default::default()
+                },
+                action(),
+            )],
+            "camera-imports" => vec![Rule::new(
+                "Camera imports",
+                Condition {
+                    extensions: strings(&[
+                        "jpg", "jpeg", "heic", "png", "dng", "cr2", "cr3", "nef", "arw", "raf",
+                        "orf", "rw2", "mp4", "mov",
+                    ]),
+                    name_regex: Some(
+                        r"^(IMG|DSC|DSCF|DSCN|PXL|GOPR|GX|DJI|MVI|VID)_?\d".to_string(),
+                    ),
+                    ..Default::default()
+                },
+                action(),
+            )],
+            _ => Vec::new(),
+        };
+
+        crate::rules::unique_names(rules, existing)
+    }
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        for preset in &PRESETS {
+            let rules = preset.rules(None, &[]);
+            assert!(!rules.is_empty(), "{}", preset.name);
+            for rule in &rules {
+                rule.condition.validate().unwrap();
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let screenshots = find("Screenshots").unwrap();
+        let rules = screenshots.rules(Some(dir.path()), &[]);
+        assert_eq!(
+            rules[0].action.destination(),
+            Some(dir.path().to_path_buf())
+        );
+        for (name, matches) in [
+            ("Screenshot 2024-01-05 at 10.12.03.png", true),
+            ("Bildschirmfoto vom 2024-01-05.png", true),
+            ("screenshot.pdf", false),
+            ("holiday.png", false),
+        ] {
+            let file = dir.path().join(name);
+            std::fs::write(&file, "x").unwrap();
+            assert_eq!(
+                rules[0].condition.matches(&file).unwrap(),
+                matches,
+                "{}",
+                name
+            );
+        }
+
+        // Adding a preset twice doesn't duplicate rule names
+        let again = screenshots.rules(None, &rules);
+        assert_eq!(again[0].name, "Screenshots (2)");
+        let cleanup = find("downloads-cleanup").unwrap().rules(None, &[]);
+        assert!(cleanup.iter().all(|r| matches!(r.action, Action::Trash)));
+        assert!(find("nope").is_none());
+    }
+}
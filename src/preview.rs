@@ -0,0 +1,276 @@
+//! Dry-run preview of watched folders
+//!
+//! Lists the files currently in each watched folder with the rules that
+//! would match them and where they would end up, without running any
+//! action, so a new config can be checked before the daemon is enabled.
+//! The TUI's Preview view shows it.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, WatchConfig};
+use crate::rules::{Action, RuleEngine};
+use crate::watcher::{DirFilter, EntryKind, SymlinkGuard};
+
+/// Files listed per watch, so huge folders don't stall the preview
+pub const MAX_FILES: usize = 5000;
+
+/// What a rule would do with a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedAction {
+    /// Rule name (empty for the watch's `unmatched_action`)
+    pub rule: String,
+    /// The action's `type`, e.g. `move`
+    pub action: &'static str,
+    /// Where the file would end up, if the action moves or creates it
+    pub destination: Option<PathBuf>,
+}
+
+impl PlannedAction {
+    fn new(rule: &str, action: &Action, path: &Path) -> Self {
+        Self {
+            rule: rule.to_string(),
+            action: action.kind(),
+            destination: action.preview_destination(path),
+        }
+    }
+}
+
+/// A file (or folder, for folder rules) in a watched folder
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePreview {
+    pub path: PathBuf,
+    /// Actions that would run, in order (empty = left alone)
+    pub planned: Vec<PlannedAction>,
+    /// Why the rules couldn't be evaluated
+    pub error: Option<String>,
+}
+
+/// Preview of one watched folder
+#[derive(Debug, Clone)]
+pub struct WatchPreview {
+    /// Watched folder (expanded)
+    pub path: PathBuf,
+    /// Files sorted by path
+    pub files: Vec<FilePreview>,
+    /// More than `MAX_FILES` files were found
+    pub truncated: bool,
+    /// Why the folder couldn't be listed
+    pub error: Option<String>,
+}
+
+impl WatchPreview {
+    /// Files at least one action would run on
+    pub fn matched(&self) -> usize {
+        self.files.iter().filter(|f| !f.planned.is_empty()).count()
+    }
+}
+
+/// Preview every watched folder
+pub fn build(config: &Config) -> Vec<WatchPreview> {
+    let engine = RuleEngine::new(config.rules.clone());
+    config
+        .watches
+        .iter()
+        .map(|watch| {
+            let path = crate::expand_path(&watch.path);
+            if crate::sftp::is_remote(&watch.path) {
+                return WatchPreview {
+                    path,
+                    files: Vec::new(),
+                    truncated: false,
+                    error: Some("Remote folders aren't previewed".to_string()),
+                };
+            }
+            match preview_watch(watch, &engine) {
+                Ok((files, truncated)) => WatchPreview {
+                    path,
+                    files,
+                    truncated,
+                    error: None,
+                },
+                Err(e) => WatchPreview {
+                    path,
+                    files: Vec::new(),
+                    truncated: false,
+                    error: Some(format!("{:#}", e)),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Files in a local watched folder with what would happen to each, and
+/// whether the list was cut at `MAX_FILES`. Hidden files are skipped, like
+/// the watcher does.
+pub fn preview_watch(watch: &WatchConfig, engine: &RuleEngine) -> Result<(Vec<FilePreview>, bool)> {
+    let root = crate::expand_path(&watch.path);
+    let allowed = (!watch.rules.is_empty()).then_some(watch.rules.as_slice());
+    let folder_rules = engine.has_folder_rules(allowed);
+    let filter = DirFilter::from_watch(watch);
+
+    let mut files = Vec::new();
+    let mut truncated = false;
+    let mut guard = SymlinkGuard::new(&root);
+    let mut stack = vec![(root, 0usize)];
+    'walk: while let Some((dir, depth)) = stack.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if is_hidden(&path) {
+                continue;
+            }
+            let preview = match filter.entry_kind(&entry, &mut guard) {
+                EntryKind::Dir => {
+                    // Folder rules take a matching folder as a whole
+                    let planned = if folder_rules {
+                        engine.matching_folder_rules(&path, allowed).map(|rules| {
+                            rules
+                                .iter()
+                                .map(|r| PlannedAction::new(&r.name, &r.action, &path))
+                                .collect::<Vec<_>>()
+                        })
+                    } else {
+                        Ok(Vec::new())
+                    };
+                    match planned {
+                        Ok(planned) if !planned.is_empty() => FilePreview {
+                            path,
+                            planned,
+                            error: None,
+                        },
+                        _ => {
+                            if watch.recursive
+                                && filter
+                                    .allows_dir(&entry.file_name().to_string_lossy(), depth + 1)
+                            {
+                                stack.push((path, depth + 1));
+                            }
+                            continue;
+                        }
+                    }
+                }
+                EntryKind::File => preview_file(watch, engine, allowed, path),
+                EntryKind::Skip => continue,
+            };
+            if files.len() == MAX_FILES {
+                truncated = true;
+                break 'walk;
+            }
+            files.push(preview);
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((files, truncated))
+}
+
+fn preview_file(
+    watch: &WatchConfig,
+    engine: &RuleEngine,
+    allowed: Option<&[String]>,
+    path: PathBuf,
+) -> FilePreview {
+    match engine.matching_rules(&path, allowed) {
+        Ok(rules) => {
+            let mut planned: Vec<_> = rules
+                .iter()
+                .map(|r| PlannedAction::new(&r.name, &r.action, &path))
+                .collect();
+            if planned.is_empty()
+                && let Some(action) = &watch.unmatched_action
+            {
+                planned.push(PlannedAction::new("", action, &path));
+            }
+            FilePreview {
+                path,
+                planned,
+                error: None,
+            }
+        }
+        Err(e) => FilePreview {
+            path,
+            planned: Vec::new(),
+            error: Some(format!("{:#}", e)),
+        },
+    }
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Condition, OnConflict, Rule};
+
+    #[test]
+    fn test_preview_watch() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("doc.pdf"), "x").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "x").unwrap();
+        std::fs::write(dir.path().join("song.mp3"), "x").unwrap();
+        std::fs::write(dir.path().join(".DS_Store"), "x").unwrap();
+
+        let engine = RuleEngine::new(vec![
+            Rule::new(
+                "PDFs",
+                Condition {
+                    extension: Some("pdf".to_string()),
+                    ..Default::default()
+                },
+                Action::Move {
+                    destination: PathBuf::from("/docs"),
+                    create_destination: true,
+                    on_conflict: OnConflict::Fail,
+                    preserve_metadata: true,
+                },
+            ),
+            Rule::new(
+                "Music",
+                Condition {
+                    extension: Some("mp3".to_string()),
+                    ..Default::default()
+                },
+                Action::Trash,
+            ),
+        ]);
+        let watch = WatchConfig {
+            path: dir.path().to_path_buf(),
+            unmatched_action: Some(Action::Rename {
+                pattern: "old_{filename}".to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let (files, truncated) = preview_watch(&watch, &engine).unwrap();
+        assert!(!truncated);
+        let planned: Vec<_> = files.iter().map(|f| f.planned.clone()).collect();
+        assert_eq!(
+            planned,
+            vec![
+                vec![PlannedAction {
+                    rule: "PDFs".to_string(),
+                    action: "move",
+                    destination: Some(PathBuf::from("/docs/doc.pdf")),
+                }],
+                vec![PlannedAction {
+                    rule: String::new(),
+                    action: "rename",
+                    destination: Some(dir.path().join("old_notes.txt")),
+                }],
+                vec![PlannedAction {
+                    rule: "Music".to_string(),
+                    action: "trash",
+                    destination: None,
+                }],
+            ]
+        );
+        // Nothing was touched
+        assert!(dir.path().join("doc.pdf").exists());
+        assert!(dir.path().join("notes.txt").exists());
+    }
+}
@@ -0,0 +1,496 @@
+//! Progress of long-running file transfers and folder scans
+//!
+//! Copies of large files (and moves across filesystems, which copy) register
+//! here while they run, so the daemon can report percentage, throughput and
+//! ETA over IPC instead of looking hung during a multi-gigabyte transfer.
+//! Scans of watched folders register too, with the number of entries
+//! looked at so far.
+//!
+//! Large copies go through a hidden `.name.part` next to the destination, so
+//! one that fails (a NAS dropping off the network) continues from where it
+//! stopped the next time the file is copied there, instead of starting over.
+//! `max_transfer_rate` caps the bytes per second all copies use together.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Files smaller than this are copied with `std::fs::copy` and not tracked
+const MIN_TRACKED_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Bytes copied between progress updates
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Smallest chunk a rate-limited copy uses
+const MIN_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Bytes per second copies may use together (0 = unlimited)
+static MAX_RATE: AtomicU64 = AtomicU64::new(0);
+
+/// When the rate limit allows the next chunk to be copied
+static NEXT_CHUNK_AT: LazyLock<Mutex<Instant>> = LazyLock::new(|| Mutex::new(Instant::now()));
+
+/// Limit the bytes per second copies use (`max_transfer_rate`), or lift the
+/// limit with None
+pub fn set_max_rate(bytes_per_sec: Option<u64>) {
+    MAX_RATE.store(bytes_per_sec.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Snapshot of one in-flight transfer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferProgress {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub total_bytes: u64,
+    pub copied_bytes: u64,
+    /// Bytes an earlier, interrupted copy had already copied
+    #[serde(default)]
+    pub resumed_bytes: u64,
+    /// Average throughput since the transfer started
+    pub bytes_per_sec: u64,
+    /// Estimated seconds remaining (unknown until some data has been copied)
+    pub eta_secs: Option<u64>,
+}
+
+impl TransferProgress {
+    /// Completion in percent (0–100)
+    pub fn percent(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 100.0;
+        }
+        self.copied_bytes as f64 * 100.0 / self.total_bytes as f64
+    }
+
+    /// One-line summary, e.g. "movie.mkv 45% · 38.2 MB/s · 1m 12s left"
+    pub fn summary(&self) -> String {
+        let name = self
+            .source
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.source.display().to_string());
+        let mut summary = format!(
+            "{} {:.0}% · {}/s",
+            name,
+            self.percent(),
+            format_bytes(self.bytes_per_sec)
+        );
+        if let Some(eta) = self.eta_secs {
+            summary.push_str(&format!(" · {} left", format_duration(eta)));
+        }
+        if self.resumed_bytes > 0 {
+            summary.push_str(" · resumed");
+        }
+        summary
+    }
+}
+
+/// A registered transfer
+struct Transfer {
+    source: PathBuf,
+    destination: PathBuf,
+    total_bytes: u64,
+    copied_bytes: u64,
+    resumed_bytes: u64,
+    started: Instant,
+}
+
+impl Transfer {
+    fn progress(&self, now: Instant) -> TransferProgress {
+        let elapsed = now.duration_since(self.started).as_secs_f64();
+        let bytes_per_sec = if elapsed > 0.0 {
+            (self.copied_bytes.saturating_sub(self.resumed_bytes) as f64 / elapsed) as u64
+        } else {
+            0
+        };
+        let eta_secs = (bytes_per_sec > 0)
+            .then(|| self.total_bytes.saturating_sub(self.copied_bytes) / bytes_per_sec);
+        TransferProgress {
+            source: self.source.clone(),
+            destination: self.destination.clone(),
+            total_bytes: self.total_bytes,
+            copied_bytes: self.copied_bytes,
+            resumed_bytes: self.resumed_bytes,
+            bytes_per_sec,
+            eta_secs,
+        }
+    }
+}
+
+static TRANSFERS: LazyLock<Mutex<HashMap<u64, Transfer>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Removes a transfer from the registry when the copy ends (or fails)
+struct Registration(u64);
+
+impl Registration {
+    fn new(source: &Path, destination: &Path, total_bytes: u64, resumed_bytes: u64) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut transfers) = TRANSFERS.lock() {
+            transfers.insert(
+                id,
+                Transfer {
+                    source: source.to_path_buf(),
+                    destination: destination.to_path_buf(),
+                    total_bytes,
+                    copied_bytes: resumed_bytes,
+                    resumed_bytes,
+                    started: Instant::now(),
+                },
+            );
+        }
+        Self(id)
+    }
+
+    fn update(&self, copied_bytes: u64) {
+        if let Ok(mut transfers) = TRANSFERS.lock()
+            && let Some(transfer) = transfers.get_mut(&self.0)
+        {
+            transfer.copied_bytes = copied_bytes;
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Ok(mut transfers) = TRANSFERS.lock() {
+            transfers.remove(&self.0);
+        }
+    }
+}
+
+/// Transfers currently in progress, oldest first
+pub fn active() -> Vec<TransferProgress> {
+    let Ok(transfers) = TRANSFERS.lock() else {
+        return Vec::new();
+    };
+    let now = Instant::now();
+    let mut active: Vec<(u64, TransferProgress)> = transfers
+        .iter()
+        .map(|(id, t)| (*id, t.progress(now)))
+        .collect();
+    active.sort_by_key(|(id, _)| *id);
+    active.into_iter().map(|(_, p)| p).collect()
+}
+
+/// Snapshot of one running folder scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanProgress {
+    /// The watched folder
+    pub path: PathBuf,
+    /// Entries looked at so far
+    pub scanned: u64,
+    /// Entries a rule matched
+    pub matched: u64,
+    pub elapsed_secs: u64,
+}
+
+impl ScanProgress {
+    /// One-line summary, e.g. "Maildir: 120000 scanned · 35 matched · 1m 12s"
+    pub fn summary(&self) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string());
+        format!(
+            "{}: {} scanned · {} matched · {}",
+            name,
+            self.scanned,
+            self.matched,
+            format_duration(self.elapsed_secs)
+        )
+    }
+}
+
+/// A registered scan
+struct Scan {
+    path: PathBuf,
+    scanned: u64,
+    matched: u64,
+    started: Instant,
+}
+
+static SCANS: LazyLock<Mutex<HashMap<u64, Scan>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Reports a scan's counts while it runs; removes it from the registry when
+/// dropped
+pub(crate) struct ScanRegistration(u64);
+
+impl ScanRegistration {
+    pub(crate) fn new(path: &Path) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut scans) = SCANS.lock() {
+            scans.insert(
+                id,
+                Scan {
+                    path: path.to_path_buf(),
+                    scanned: 0,
+                    matched: 0,
+                    started: Instant::now(),
+                },
+            );
+        }
+        Self(id)
+    }
+
+    pub(crate) fn update(&self, scanned: u64, matched: u64) {
+        if let Ok(mut scans) = SCANS.lock()
+            && let Some(scan) = scans.get_mut(&self.0)
+        {
+            scan.scanned = scanned;
+            scan.matched = matched;
+        }
+    }
+}
+
+impl Drop for ScanRegistration {
+    fn drop(&mut self) {
+        if let Ok(mut scans) = SCANS.lock() {
+            scans.remove(&self.0);
+        }
+    }
+}
+
+/// Scans currently running, oldest first
+pub fn active_scans() -> Vec<ScanProgress> {
+    let Ok(scans) = SCANS.lock() else {
+        return Vec::new();
+    };
+    let mut active: Vec<(u64, ScanProgress)> = scans
+        .iter()
+        .map(|(id, scan)| {
+            (
+                *id,
+                ScanProgress {
+                    path: scan.path.clone(),
+                    scanned: scan.scanned,
+                    matched: scan.matched,
+                    elapsed_secs: scan.started.elapsed().as_secs(),
+                },
+            )
+        })
+        .collect();
+    active.sort_by_key(|(id, _)| *id);
+    active.into_iter().map(|(_, p)| p).collect()
+}
+
+/// Copy a file like `std::fs::copy`, reporting progress for large files.
+///
+/// Large files, and every file while a rate limit is set, are copied in
+/// chunks into `.name.part` next to `destination` and renamed into place
+/// when complete. A failed copy leaves the part behind for the next one to
+/// resume.
+pub fn copy_file(source: &Path, destination: &Path) -> std::io::Result<u64> {
+    let metadata = std::fs::metadata(source)?;
+    let total_bytes = metadata.len();
+    let max_rate = MAX_RATE.load(Ordering::Relaxed);
+    if total_bytes < MIN_TRACKED_BYTES && max_rate == 0 {
+        return std::fs::copy(source, destination);
+    }
+
+    let part = part_path(destination);
+    let mut reader = File::open(source)?;
+    let resumed = resumable(&mut reader, &part, &metadata);
+    if resumed > 0 {
+        info!(
+            "Resuming the copy of {} at {} of {}",
+            source.display(),
+            format_bytes(resumed),
+            format_bytes(total_bytes)
+        );
+    }
+    let mut writer = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&part)?;
+    writer.set_len(resumed)?;
+    writer.seek(SeekFrom::Start(resumed))?;
+    reader.seek(SeekFrom::Start(resumed))?;
+
+    let registration = (total_bytes >= MIN_TRACKED_BYTES)
+        .then(|| Registration::new(source, destination, total_bytes, resumed));
+    let chunk_size = match max_rate {
+        0 => CHUNK_SIZE,
+        rate => (rate as usize / 4).clamp(MIN_CHUNK_SIZE, CHUNK_SIZE),
+    };
+    let mut buffer = vec![0u8; chunk_size];
+    let mut copied = resumed;
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..n])?;
+        copied += n as u64;
+        if let Some(registration) = &registration {
+            registration.update(copied);
+        }
+        throttle(n);
+    }
+    writer.sync_all()?;
+    drop(writer);
+    std::fs::set_permissions(&part, metadata.permissions())?;
+    std::fs::rename(&part, destination)?;
+    Ok(copied)
+}
+
+/// Hidden `.name.part` next to `destination`, where a copy is written
+fn part_path(destination: &Path) -> PathBuf {
+    let name = destination
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    destination.with_file_name(format!(".{}.part", name))
+}
+
+/// Bytes of an earlier copy in `part` worth keeping: all of them if the
+/// part was written after the source last changed, isn't longer than it,
+/// and ends with the same bytes as the source at that point; else none
+fn resumable(reader: &mut File, part: &Path, source: &std::fs::Metadata) -> u64 {
+    let Ok(existing) = std::fs::metadata(part) else {
+        return 0;
+    };
+    let len = existing.len();
+    let newer = match (existing.modified(), source.modified()) {
+        (Ok(part), Ok(source)) => part >= source,
+        _ => false,
+    };
+    if len == 0 || len > source.len() || !existing.is_file() || !newer {
+        return 0;
+    }
+    let tail = len.min(CHUNK_SIZE as u64);
+    let read_tail = |file: &mut File| -> std::io::Result<Vec<u8>> {
+        let mut buffer = vec![0u8; tail as usize];
+        file.seek(SeekFrom::Start(len - tail))?;
+        file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    };
+    match (
+        read_tail(reader),
+        File::open(part).and_then(|mut f| read_tail(&mut f)),
+    ) {
+        (Ok(expected), Ok(found)) if expected == found => len,
+        _ => 0,
+    }
+}
+
+/// Wait until the rate limit allows another `bytes`, shared by all copies
+fn throttle(bytes: usize) {
+    let max_rate = MAX_RATE.load(Ordering::Relaxed);
+    if max_rate == 0 {
+        return;
+    }
+    let cost = Duration::from_secs_f64(bytes as f64 / max_rate as f64);
+    let wait = {
+        let Ok(mut next) = NEXT_CHUNK_AT.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        *next = (*next).max(now) + cost;
+        next.saturating_duration_since(now)
+    };
+    std::thread::sleep(wait);
+}
+
+/// Human-readable byte count, e.g. "38.2 MB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Short duration, e.g. "45s", "1m 12s", "2h 5m"
+fn format_duration(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_summary() {
+        let progress = TransferProgress {
+            source: PathBuf::from("/dl/movie.mkv"),
+            destination: PathBuf::from("/nas/movie.mkv"),
+            total_bytes: 4_000_000_000,
+            copied_bytes: 1_800_000_000,
+            resumed_bytes: 0,
+            bytes_per_sec: 38_200_000,
+            eta_secs: Some(72),
+        };
+        assert_eq!(
+            progress.summary(),
+            "movie.mkv 45% · 38.2 MB/s · 1m 12s left"
+        );
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_duration(7500), "2h 5m");
+    }
+
+    #[test]
+    fn test_copy_file_tracks_large_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("big.bin");
+        let destination = dir.path().join("copy.bin");
+        std::fs::write(&source, vec![7u8; MIN_TRACKED_BYTES as usize + 10]).unwrap();
+
+        let copied = copy_file(&source, &destination).unwrap();
+        assert_eq!(copied, MIN_TRACKED_BYTES + 10);
+        assert_eq!(std::fs::metadata(&destination).unwrap().len(), copied);
+        // Finished transfers are no longer reported
+        assert!(active().iter().all(|t| t.source != source));
+    }
+
+    #[test]
+    fn test_copy_file_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("movie.mkv");
+        let destination = dir.path().join("nas/movie.mkv");
+        std::fs::create_dir(dir.path().join("nas")).unwrap();
+        let contents: Vec<u8> = (0..MIN_TRACKED_BYTES + 10)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        std::fs::write(&source, &contents).unwrap();
+        let metadata = std::fs::metadata(&source).unwrap();
+        let mut reader = File::open(&source).unwrap();
+
+        // An interrupted copy is kept if it matches the source
+        let part = part_path(&destination);
+        assert_eq!(part, dir.path().join("nas/.movie.mkv.part"));
+        std::fs::write(&part, &contents[..5_000_000]).unwrap();
+        assert_eq!(resumable(&mut reader, &part, &metadata), 5_000_000);
+        let copied = copy_file(&source, &destination).unwrap();
+        assert_eq!(copied, contents.len() as u64);
+        assert!(std::fs::read(&destination).unwrap() == contents);
+        assert!(!part.exists());
+
+        // A part of some other file is started over
+        std::fs::write(&part, vec![1u8; 5_000_000]).unwrap();
+        assert_eq!(resumable(&mut reader, &part, &metadata), 0);
+        std::fs::write(&part, vec![1u8; contents.len() + 1]).unwrap();
+        assert_eq!(resumable(&mut reader, &part, &metadata), 0);
+        copy_file(&source, &destination).unwrap();
+        assert!(std::fs::read(&destination).unwrap() == contents);
+    }
+}
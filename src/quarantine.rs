@@ -0,0 +1,457 @@
+//! Quarantine - a holding area for files destructive actions would remove
+//!
+//! With `[quarantine] enabled = true`, a `delete` action and a move or copy
+//! that replaces an existing file (`on_conflict = "overwrite"` or
+//! `"keep_newer"`) put the file they'd destroy in the quarantine folder
+//! instead (`quarantine/` in the data directory by default). It stays there
+//! for `retention_days` and is then removed for good; until then
+//! `hazelnut quarantine restore` puts it back.
+//!
+//! Each file is kept as `<id>/<name>`, next to `<id>.json` saying where it
+//! came from. Expired files are purged by the daemon about once an hour, and
+//! whenever a file is quarantined.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How often expired files are purged
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// `[quarantine]`: where files removed by destructive actions are kept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct QuarantineConfig {
+    /// Quarantine files instead of deleting or replacing them
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Days a file is kept before it's removed for good (0 = until purged)
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u32,
+
+    /// Folder the files are kept in (default: `quarantine` in the data directory)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for QuarantineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retention_days: default_retention_days(),
+            dir: None,
+        }
+    }
+}
+
+fn default_retention_days() -> u32 {
+    30
+}
+
+impl QuarantineConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What would have destroyed a quarantined file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Reason {
+    /// A `delete` action
+    Deleted,
+    /// Another file took its place
+    Replaced,
+}
+
+impl std::fmt::Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Reason::Deleted => "deleted",
+            Reason::Replaced => "replaced",
+        })
+    }
+}
+
+/// A file in the quarantine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuarantineEntry {
+    /// Unique within the quarantine, e.g. `20260501-140211`
+    pub id: String,
+    /// When it was quarantined
+    pub time: DateTime<Local>,
+    /// Where it was
+    pub original: PathBuf,
+    pub reason: Reason,
+    /// Size in bytes (of everything in it, for a folder)
+    pub size: u64,
+    /// Where it's kept now
+    #[serde(default, skip_deserializing)]
+    pub kept: PathBuf,
+}
+
+/// A quarantine folder
+pub struct Quarantine {
+    dir: PathBuf,
+    retention_days: u32,
+}
+
+impl Quarantine {
+    pub fn new(dir: PathBuf, retention_days: u32) -> Self {
+        Self {
+            dir,
+            retention_days,
+        }
+    }
+
+    /// The configured quarantine
+    pub fn configured() -> Result<Self> {
+        let config = config();
+        let dir = match &config.dir {
+            Some(dir) => crate::expand_path(dir),
+            None => crate::Config::data_dir()
+                .context("No data directory for the quarantine")?
+                .join("quarantine"),
+        };
+        Ok(Self::new(dir, config.retention_days))
+    }
+
+    /// Move a file or folder into the quarantine
+    pub fn stash(&self, path: &Path, reason: Reason) -> Result<QuarantineEntry> {
+        let name = path.file_name().context("File has no name")?;
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let now = Local::now();
+        let stamp = now.format("%Y%m%d-%H%M%S").to_string();
+        let mut id = stamp.clone();
+        for n in 2.. {
+            match std::fs::create_dir(self.dir.join(&id)) {
+                Ok(()) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    id = format!("{}-{}", stamp, n);
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!("Failed to create a folder in {}", self.dir.display())
+                    });
+                }
+            }
+        }
+        let folder = self.dir.join(&id);
+        let kept = folder.join(name);
+        let entry = QuarantineEntry {
+            id,
+            time: now,
+            original: path.to_path_buf(),
+            reason,
+            size: size_of(path),
+            kept: kept.clone(),
+        };
+        if let Err(e) = crate::rules::move_exact(path, &kept) {
+            let _ = std::fs::remove_dir(&folder);
+            return Err(e).with_context(|| format!("Failed to quarantine {}", path.display()));
+        }
+        let written = serde_json::to_vec_pretty(&entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(self.entry_path(&entry.id), json)?));
+        if let Err(e) = written {
+            // Without its description the file couldn't be found again
+            let _ = crate::rules::move_exact(&kept, path);
+            let _ = std::fs::remove_dir(&folder);
+            return Err(e).context("Failed to record the quarantined file");
+        }
+        info!(
+            "Quarantined {} ({}), restore with `hazelnut quarantine restore {}`",
+            path.display(),
+            reason,
+            entry.id
+        );
+        Ok(entry)
+    }
+
+    /// Every quarantined file, oldest first
+    pub fn list(&self) -> Result<Vec<QuarantineEntry>> {
+        let read = match std::fs::read_dir(&self.dir) {
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", self.dir.display()));
+            }
+        };
+        let mut entries = Vec::new();
+        for file in read.flatten() {
+            let path = file.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let entry = std::fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| Ok(serde_json::from_slice::<QuarantineEntry>(&json)?));
+            match entry {
+                Ok(mut entry) => {
+                    entry.kept = self.kept_path(&entry);
+                    entries.push(entry);
+                }
+                Err(e) => warn!("Skipping {}: {:#}", path.display(), e),
+            }
+        }
+        entries.sort_by(|a, b| a.time.cmp(&b.time).then_with(|| a.id.cmp(&b.id)));
+        Ok(entries)
+    }
+
+    /// A quarantined file by its id
+    pub fn get(&self, id: &str) -> Result<QuarantineEntry> {
+        self.list()?
+            .into_iter()
+            .find(|entry| entry.id == id)
+            .with_context(|| format!("No quarantined file with id {}", id))
+    }
+
+    /// Put a quarantined file back where it was, or at `to` (into it, if
+    /// it's a folder). Fails rather than replace a file already there.
+    pub fn restore(&self, entry: &QuarantineEntry, to: Option<&Path>) -> Result<PathBuf> {
+        let target = match to {
+            Some(to) if to.is_dir() => to.join(entry.kept.file_name().unwrap_or_default()),
+            Some(to) => to.to_path_buf(),
+            None => entry.original.clone(),
+        };
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        crate::rules::move_exact(&entry.kept, &target).with_context(|| {
+            format!(
+                "Failed to restore {} (restore it elsewhere with --to)",
+                entry.original.display()
+            )
+        })?;
+        self.forget(entry);
+        Ok(target)
+    }
+
+    /// Remove a quarantined file for good
+    pub fn purge(&self, entry: &QuarantineEntry) -> Result<()> {
+        let folder = self.dir.join(&entry.id);
+        if folder.exists() {
+            std::fs::remove_dir_all(&folder)
+                .with_context(|| format!("Failed to remove {}", folder.display()))?;
+        }
+        self.forget(entry);
+        Ok(())
+    }
+
+    /// Remove the files kept longer than `retention_days`, returning them
+    pub fn purge_expired(&self) -> Result<Vec<QuarantineEntry>> {
+        if self.retention_days == 0 {
+            return Ok(Vec::new());
+        }
+        let cutoff = Local::now() - chrono::Duration::days(i64::from(self.retention_days));
+        let mut purged = Vec::new();
+        for entry in self.list()? {
+            if entry.time < cutoff {
+                self.purge(&entry)?;
+                purged.push(entry);
+            }
+        }
+        Ok(purged)
+    }
+
+    fn entry_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn kept_path(&self, entry: &QuarantineEntry) -> PathBuf {
+        let name = entry.original.file_name().unwrap_or_default();
+        self.dir.join(&entry.id).join(name)
+    }
+
+    /// Drop the description and the (now empty) folder of an entry
+    fn forget(&self, entry: &QuarantineEntry) {
+        let _ = std::fs::remove_file(self.entry_path(&entry.id));
+        let _ = std::fs::remove_dir(self.dir.join(&entry.id));
+    }
+}
+
+/// Total size of a file, or of the files in a folder
+fn size_of(path: &Path) -> u64 {
+    let Ok(metadata) = path.symlink_metadata() else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .map(|read| read.flatten().map(|entry| size_of(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+static CONFIG: LazyLock<RwLock<QuarantineConfig>> = LazyLock::new(Default::default);
+
+static PURGED: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Set the quarantine settings actions use (the config's `[quarantine]`)
+pub fn set(config: QuarantineConfig) {
+    if let Ok(mut active) = CONFIG.write() {
+        *active = config;
+    }
+}
+
+fn config() -> QuarantineConfig {
+    CONFIG
+        .read()
+        .map(|config| config.clone())
+        .unwrap_or_default()
+}
+
+/// Whether destructive actions quarantine files
+pub fn is_enabled() -> bool {
+    CONFIG.read().is_ok_and(|config| config.enabled)
+}
+
+/// Move a file into the configured quarantine
+pub fn stash(path: &Path, reason: Reason) -> Result<QuarantineEntry> {
+    let quarantine = Quarantine::configured()?;
+    let entry = quarantine.stash(path, reason)?;
+    purge_if_due();
+    Ok(entry)
+}
+
+/// Remove expired files from the configured quarantine, at most about
+/// once an hour
+pub fn purge_if_due() {
+    let Ok(mut purged) = PURGED.lock() else {
+        return;
+    };
+    if purged.is_some_and(|at| at.elapsed() < PURGE_INTERVAL) {
+        return;
+    }
+    *purged = Some(Instant::now());
+    let result = Quarantine::configured().and_then(|quarantine| quarantine.purge_expired());
+    match result {
+        Ok(expired) if expired.is_empty() => {}
+        Ok(expired) => info!(
+            "Purged {} expired file(s) from the quarantine",
+            expired.len()
+        ),
+        Err(e) => warn!("Failed to purge the quarantine: {:#}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stash_restore_purge() {
+        let dir = tempfile::tempdir().unwrap();
+        let quarantine = Quarantine::new(dir.path().join("quarantine"), 30);
+        let inbox = dir.path().join("inbox");
+        std::fs::create_dir(&inbox).unwrap();
+        let file = inbox.join("report.pdf");
+        std::fs::write(&file, "12345").unwrap();
+
+        let first = quarantine.stash(&file, Reason::Deleted).unwrap();
+        assert!(!file.exists());
+        assert!(first.kept.exists());
+        assert_eq!(first.size, 5);
+        std::fs::write(&file, "newer").unwrap();
+        let second = quarantine.stash(&file, Reason::Replaced).unwrap();
+        assert_ne!(first.id, second.id);
+
+        let listed = quarantine.list().unwrap();
+        assert_eq!(listed, [first.clone(), second.clone()]);
+
+        // The original place is free again, so the first goes back there;
+        // the second is restored elsewhere as the place is taken
+        let restored = quarantine.restore(&first, None).unwrap();
+        assert_eq!(restored, file);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "12345");
+        assert!(quarantine.restore(&second, None).is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "12345");
+        assert_eq!(quarantine.list().unwrap(), std::slice::from_ref(&second));
+        let elsewhere = quarantine.restore(&second, Some(dir.path())).unwrap();
+        assert_eq!(elsewhere, dir.path().join("report.pdf"));
+        assert_eq!(std::fs::read_to_string(&elsewhere).unwrap(), "newer");
+        assert!(quarantine.list().unwrap().is_empty());
+        assert_eq!(
+            std::fs::read_dir(dir.path().join("quarantine"))
+                .unwrap()
+                .count(),
+            0
+        );
+
+        // Only files past the retention period are purged
+        let folder = inbox.join("old");
+        std::fs::create_dir(&folder).unwrap();
+        std::fs::write(folder.join("a.txt"), "abc").unwrap();
+        let old = quarantine.stash(&folder, Reason::Deleted).unwrap();
+        assert_eq!(old.size, 3);
+        let recent = quarantine.stash(&file, Reason::Deleted).unwrap();
+        let mut aged = old.clone();
+        aged.time = Local::now() - chrono::Duration::days(31);
+        std::fs::write(
+            quarantine.entry_path(&old.id),
+            serde_json::to_vec(&aged).unwrap(),
+        )
+        .unwrap();
+        let purged = quarantine.purge_expired().unwrap();
+        assert_eq!(purged.len(), 1);
+        assert_eq!(purged[0].id, old.id);
+        assert!(!old.kept.exists());
+        assert_eq!(quarantine.list().unwrap(), [recent]);
+    }
+
+    #[test]
+    fn test_overwrite_stashes_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let quarantine_dir = dir.path().join("quarantine");
+        let inbox = dir.path().join("inbox");
+        let sorted = dir.path().join("sorted");
+        std::fs::create_dir(&inbox).unwrap();
+        std::fs::create_dir(&sorted).unwrap();
+        std::fs::write(inbox.join("a.txt"), "new").unwrap();
+        std::fs::write(sorted.join("a.txt"), "old").unwrap();
+
+        // Other tests run actions too; the quarantine is only on in this block
+        struct Disabled;
+        impl Drop for Disabled {
+            fn drop(&mut self) {
+                set(QuarantineConfig::default());
+            }
+        }
+        set(QuarantineConfig {
+            enabled: true,
+            retention_days: 30,
+            dir: Some(quarantine_dir.clone()),
+        });
+        let disabled = Disabled;
+        let action = crate::rules::Action::Move {
+            destination: sorted.clone(),
+            create_destination: false,
+            on_conflict: crate::rules::OnConflict::Overwrite,
+            preserve_metadata: true,
+        };
+        let landed = action.execute_to(&inbox.join("a.txt")).unwrap();
+        drop(disabled);
+
+        assert_eq!(landed, Some(sorted.join("a.txt")));
+        assert_eq!(
+            std::fs::read_to_string(sorted.join("a.txt")).unwrap(),
+            "new"
+        );
+        let replaced: Vec<_> = Quarantine::new(quarantine_dir, 30)
+            .list()
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.original == sorted.join("a.txt"))
+            .collect();
+        assert_eq!(replaced.len(), 1);
+        assert_eq!(replaced[0].reason, Reason::Replaced);
+        assert_eq!(std::fs::read_to_string(&replaced[0].kept).unwrap(), "old");
+    }
+}
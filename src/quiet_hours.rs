@@ -0,0 +1,135 @@
+//! Quiet hours
+//!
+//! A daily window (e.g. 22:00–07:00) during which desktop notifications are
+//! held back and delivered as one digest once it ends, and rules marked
+//! `defer_in_quiet_hours` leave their files alone until then.
+
+use chrono::{DateTime, Local, NaiveTime};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::{LazyLock, RwLock};
+
+use crate::notifications::NotificationKind;
+
+/// Daily do-not-disturb window, in local time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct QuietHours {
+    /// Start of the window ("HH:MM")
+    #[serde(with = "clock_time")]
+    #[schemars(with = "String")]
+    pub start: NaiveTime,
+
+    /// End of the window ("HH:MM"); earlier than `start` means it ends the next day
+    #[serde(with = "clock_time")]
+    #[schemars(with = "String")]
+    pub end: NaiveTime,
+
+    /// Notification channels still delivered during quiet hours
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<NotificationKind>,
+}
+
+impl QuietHours {
+    /// Whether a time of day falls inside the window
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Whether the window is active at `now`
+    pub fn is_quiet_at(&self, now: DateTime<Local>) -> bool {
+        self.contains(now.time())
+    }
+
+    /// Whether notifications of this kind get through anyway
+    pub fn allows(&self, kind: NotificationKind) -> bool {
+        self.allow.contains(&kind)
+    }
+}
+
+/// `HH:MM` (de)serialization for times of day
+mod clock_time {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.format("%H:%M").to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&s, "%H:%M")
+            .map_err(|_| serde::de::Error::custom(format!("invalid time '{}', expected HH:MM", s)))
+    }
+}
+
+static ACTIVE: LazyLock<RwLock<Option<QuietHours>>> = LazyLock::new(|| RwLock::new(None));
+
+/// Set the quiet hours in effect (None = never quiet)
+pub fn set(quiet_hours: Option<QuietHours>) {
+    if let Ok(mut active) = ACTIVE.write() {
+        *active = quiet_hours;
+    }
+}
+
+/// Whether quiet hours are in effect right now
+pub fn is_quiet() -> bool {
+    ACTIVE
+        .read()
+        .ok()
+        .and_then(|active| active.as_ref().map(|q| q.is_quiet_at(Local::now())))
+        .unwrap_or(false)
+}
+
+/// Whether a notification of this kind should be held for the digest now
+pub fn suppresses(kind: NotificationKind) -> bool {
+    ACTIVE
+        .read()
+        .ok()
+        .and_then(|active| {
+            active
+                .as_ref()
+                .map(|q| q.is_quiet_at(Local::now()) && !q.allows(kind))
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(s, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_quiet_hours_window() {
+        let overnight: QuietHours = toml::from_str(
+            r#"
+            start = "22:00"
+            end = "07:00"
+            allow = ["watch_error"]
+        "#,
+        )
+        .unwrap();
+        assert!(overnight.contains(time("23:30")));
+        assert!(overnight.contains(time("06:59")));
+        assert!(!overnight.contains(time("07:00")));
+        assert!(!overnight.contains(time("12:00")));
+        assert!(overnight.allows(NotificationKind::WatchError));
+        assert!(!overnight.allows(NotificationKind::RuleError));
+
+        let lunch = QuietHours {
+            start: time("12:00"),
+            end: time("13:00"),
+            allow: Vec::new(),
+        };
+        assert!(lunch.contains(time("12:30")));
+        assert!(!lunch.contains(time("22:00")));
+
+        assert!(toml::from_str::<QuietHours>("start = \"10pm\"\nend = \"07:00\"").is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! Folder quotas - watched folders kept under a size
+//!
+//! A watch with `max_size = "20GB"` is checked by the daemon every minute.
+//! While the folder holds more than that, its oldest files are trashed (or
+//! zipped into `quota_archive_to`, or deleted, per `quota_action`) and one
+//! notification sums up what went. Everything in the folder counts toward
+//! its size, but hidden files and files an undo put back are never removed.
+//! Removals are recorded in the action history like a retention policy's.
+
+use anyhow::{Result, anyhow, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{info, warn};
+
+use crate::config::WatchConfig;
+use crate::progress::format_bytes;
+use crate::retention::RetentionAction;
+
+/// A size written with a unit, e.g. "20GB" or "1.5 TB"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+pub struct ByteSize {
+    source: String,
+    bytes: u64,
+}
+
+impl ByteSize {
+    /// Parse a number with an optional unit: B, KB, MB, GB, TB (powers of
+    /// 1000) or KiB, MiB, GiB, TiB (powers of 1024), in any case
+    pub fn parse(text: &str) -> Result<Self> {
+        let trimmed = text.trim();
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split);
+        let invalid = || anyhow!("Invalid size '{}' (expected e.g. \"20GB\")", text);
+        let amount: f64 = number.parse().map_err(|_| invalid())?;
+        let scale = match unit.trim().to_lowercase().as_str() {
+            "" | "b" => 1.0,
+            "k" | "kb" => 1e3,
+            "m" | "mb" => 1e6,
+            "g" | "gb" => 1e9,
+            "t" | "tb" => 1e12,
+            "kib" => 1024.0,
+            "mib" => 1024.0 * 1024.0,
+            "gib" => 1024.0 * 1024.0 * 1024.0,
+            "tib" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+            _ => return Err(invalid()),
+        };
+        let bytes = (amount * scale).round() as u64;
+        if bytes == 0 {
+            bail!("Size '{}' must be more than 0", text);
+        }
+        Ok(Self {
+            source: text.to_string(),
+            bytes,
+        })
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl TryFrom<String> for ByteSize {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<ByteSize> for String {
+    fn from(size: ByteSize) -> Self {
+        size.source
+    }
+}
+
+impl std::fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// What enforcing a quota did
+#[derive(Debug, Default)]
+pub struct QuotaRun {
+    /// Size of the folder before
+    pub size: u64,
+    /// Files removed (or that would be, in a dry run), with their sizes
+    pub removed: Vec<(PathBuf, u64)>,
+    /// Files the action failed on, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+    /// Still over the quota with nothing left to remove
+    pub over: bool,
+}
+
+impl QuotaRun {
+    /// Bytes the removed files took up
+    pub fn freed(&self) -> u64 {
+        self.removed.iter().map(|(_, size)| size).sum()
+    }
+}
+
+/// Check the quota settings the parser can't
+pub fn validate(watch: &WatchConfig) -> Result<()> {
+    if watch.max_size.is_some()
+        && watch.quota_action == RetentionAction::Archive
+        && watch.quota_archive_to.is_none()
+    {
+        bail!(
+            "Watch {}: quota_archive_to is required when quota_action is \"archive\"",
+            watch.path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Remove a watch's oldest files until its folder is under `max_size`,
+/// recording each in the history. A dry run only lists them. None if the
+/// watch has no quota.
+pub fn enforce(watch: &WatchConfig, dry_run: bool) -> Option<QuotaRun> {
+    let limit = watch.max_size.as_ref()?.bytes();
+    let dir = crate::expand_path(&watch.path);
+    let mut run = QuotaRun {
+        size: size_of(&dir),
+        ..Default::default()
+    };
+    if run.size <= limit {
+        return Some(run);
+    }
+
+    let mut candidates = crate::retention::removable(&dir, watch.recursive);
+    candidates.sort_by_key(|(_, modified)| *modified);
+    let action = watch.quota_action.to_action(watch.quota_archive_to.clone());
+    let label = format!("Quota {}", watch.path.display());
+    let mut size = run.size;
+    for (path, _) in candidates {
+        if size <= limit {
+            break;
+        }
+        let bytes = size_of(&path);
+        if dry_run {
+            info!("[dry run] quota would {} {}", action.kind(), path.display());
+        } else {
+            let checksum = crate::history::checksum(&path);
+            let started = Instant::now();
+            let result = action.execute_to(&path);
+            crate::logfile::log_action(&label, &action, &path, &result, started.elapsed());
+            crate::history::record(&label, &action, &path, &result, checksum.as_deref(), None);
+            if let Err(e) = result {
+                warn!(
+                    "Quota failed to {} {}: {:#}",
+                    action.kind(),
+                    path.display(),
+                    e
+                );
+                run.failed.push((path, format!("{:#}", e)));
+                continue;
+            }
+        }
+        size = size.saturating_sub(bytes);
+        run.removed.push((path, bytes));
+    }
+    if size > limit {
+        warn!(
+            "{} is still over its quota of {} with nothing left to remove",
+            dir.display(),
+            format_bytes(limit)
+        );
+        run.over = true;
+    }
+    Some(run)
+}
+
+/// Notification text for a run that removed or failed to remove anything,
+/// e.g. "~/Recordings: trashed 12 file(s) (4.2 GB) to stay under 20GB"
+pub fn summary(watch: &WatchConfig, run: &QuotaRun, dry_run: bool) -> Option<String> {
+    let limit = watch.max_size.as_ref()?;
+    if run.removed.is_empty() && run.failed.is_empty() && !run.over {
+        return None;
+    }
+    let verb = match watch.quota_action {
+        RetentionAction::Trash => "trashed",
+        RetentionAction::Archive => "archived",
+        RetentionAction::Delete => "deleted",
+    };
+    let mut message = format!(
+        "{}: {}{} {} file(s) ({}) to stay under {}",
+        watch.path.display(),
+        if dry_run { "would have " } else { "" },
+        verb,
+        run.removed.len(),
+        format_bytes(run.freed()),
+        limit
+    );
+    if !run.failed.is_empty() {
+        message.push_str(&format!(", {} failed", run.failed.len()));
+    }
+    if run.over {
+        message.push_str(", still over the quota");
+    }
+    Some(message)
+}
+
+/// Bytes a file takes up, or everything in a folder does
+fn size_of(path: &Path) -> u64 {
+    let Ok(metadata) = path.symlink_metadata() else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .map(|read| read.flatten().map(|entry| size_of(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_byte_size() {
+        assert_eq!(ByteSize::parse("20GB").unwrap().bytes(), 20_000_000_000);
+        assert_eq!(ByteSize::parse("1.5 mb").unwrap().bytes(), 1_500_000);
+        assert_eq!(ByteSize::parse("2KiB").unwrap().bytes(), 2048);
+        assert_eq!(ByteSize::parse("512").unwrap().bytes(), 512);
+        assert!(ByteSize::parse("20 GBs").is_err());
+        assert!(ByteSize::parse("GB").is_err());
+        assert!(ByteSize::parse("0MB").is_err());
+    }
+
+    #[test]
+    fn test_enforce() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let now = SystemTime::now();
+        for (name, days) in [("a.mov", 3), ("b.mov", 2), ("c.mov", 1), (".big", 4)] {
+            let path = root.join(name);
+            std::fs::write(&path, vec![0u8; 400]).unwrap();
+            std::fs::File::open(&path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(days * 86400))
+                .unwrap();
+        }
+
+        let mut watch: WatchConfig = toml::from_str(&format!(
+            "path = '{}'\nmax_size = '1KB'\nquota_action = 'delete'",
+            root.display()
+        ))
+        .unwrap();
+        // 1600 bytes: the two oldest visible files go, the hidden one stays
+        let run = enforce(&watch, false).unwrap();
+        assert_eq!(run.size, 1600);
+        assert_eq!(run.freed(), 800);
+        assert!(!run.over);
+        assert!(!root.join("a.mov").exists());
+        assert!(!root.join("b.mov").exists());
+        assert!(root.join("c.mov").exists());
+        assert!(root.join(".big").exists());
+        assert_eq!(
+            summary(&watch, &run, false).unwrap(),
+            format!(
+                "{}: deleted 2 file(s) (800 B) to stay under 1KB",
+                root.display()
+            )
+        );
+
+        // Under the quota, nothing happens
+        let run = enforce(&watch, false).unwrap();
+        assert!(run.removed.is_empty());
+        assert!(summary(&watch, &run, false).is_none());
+
+        watch.max_size = Some(ByteSize::parse("100B").unwrap());
+        let run = enforce(&watch, true).unwrap();
+        assert_eq!(run.removed.len(), 1);
+        assert!(run.over);
+        assert!(root.join("c.mov").exists());
+
+        watch.quota_action = RetentionAction::Archive;
+        assert!(validate(&watch).is_err());
+    }
+}
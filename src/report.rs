@@ -0,0 +1,224 @@
+//! "Inbox zero" report of files no rule handles
+//!
+//! Lists files in watched folders that no rule matches and that have been
+//! sitting there for a while, so gaps in rule coverage surface instead of
+//! files piling up unnoticed. The daemon sends it as a notification on the
+//! `[report]` schedule; `hazelnut report` prints it on demand.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+use crate::config::{Config, WatchConfig};
+use crate::rules::RuleEngine;
+use crate::watcher::{DirFilter, EntryKind, SymlinkGuard};
+
+/// Unmatched files found in one watched folder
+#[derive(Debug, Clone)]
+pub struct WatchReport {
+    /// Watched folder (expanded)
+    pub path: PathBuf,
+    /// Files no rule matches, oldest first
+    pub unmatched: Vec<PathBuf>,
+}
+
+impl WatchReport {
+    /// One-line summary, e.g. "Downloads currently holds 12 unmatched files older than 7 days"
+    pub fn summary(&self, older_than_days: u64) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string());
+        let files = if self.unmatched.len() == 1 {
+            "file"
+        } else {
+            "files"
+        };
+        if older_than_days == 0 {
+            format!(
+                "{} currently holds {} unmatched {}",
+                name,
+                self.unmatched.len(),
+                files
+            )
+        } else {
+            format!(
+                "{} currently holds {} unmatched {} older than {} days",
+                name,
+                self.unmatched.len(),
+                files,
+                older_than_days
+            )
+        }
+    }
+
+    /// Summary followed by up to `max_files` file names
+    pub fn message(&self, older_than_days: u64, max_files: usize) -> String {
+        let mut message = self.summary(older_than_days);
+        for file in self.unmatched.iter().take(max_files) {
+            let name = file.file_name().unwrap_or(file.as_os_str());
+            message.push_str(&format!("\n• {}", name.to_string_lossy()));
+        }
+        let remaining = self.unmatched.len().saturating_sub(max_files);
+        if remaining > 0 {
+            message.push_str(&format!("\n…and {} more", remaining));
+        }
+        message
+    }
+}
+
+/// Build reports for every watched folder that holds unmatched files
+pub fn build(config: &Config) -> Vec<WatchReport> {
+    let engine = RuleEngine::new(config.rules.clone());
+    config
+        .watches
+        .iter()
+        .filter_map(
+            |watch| match unmatched_files(watch, &engine, config.report.older_than_days) {
+                Ok(unmatched) if !unmatched.is_empty() => Some(WatchReport {
+                    path: crate::expand_path(&watch.path),
+                    unmatched,
+                }),
+                Ok(_) => None,
+                Err(e) => {
+                    warn!("Failed to scan {} for report: {}", watch.path.display(), e);
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+/// Files in a watched folder that no applicable rule matches and that are
+/// at least `older_than_days` old. Hidden files are skipped.
+pub fn unmatched_files(
+    watch: &WatchConfig,
+    engine: &RuleEngine,
+    older_than_days: u64,
+) -> Result<Vec<PathBuf>> {
+    // Remote watches keep copies of files no rule moved away
+    let root = crate::sftp::local_dir(&watch.path);
+    let min_age = Duration::from_secs(older_than_days * 86400);
+    let now = SystemTime::now();
+    let allowed = (!watch.rules.is_empty()).then_some(watch.rules.as_slice());
+    let filter = DirFilter::from_watch(watch);
+
+    let mut found: Vec<(SystemTime, PathBuf)> = Vec::new();
+    let mut guard = SymlinkGuard::new(&root);
+    let mut stack = vec![(root, 0usize)];
+    while let Some((dir, depth)) = stack.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if is_hidden(&path) {
+                continue;
+            }
+            match filter.entry_kind(&entry, &mut guard) {
+                EntryKind::Dir => {
+                    if watch.recursive
+                        && filter.allows_dir(&entry.file_name().to_string_lossy(), depth + 1)
+                    {
+                        stack.push((path, depth + 1));
+                    }
+                    continue;
+                }
+                EntryKind::File => {}
+                EntryKind::Skip => continue,
+            }
+            if !path.is_file() {
+                continue;
+            }
+
+            let Ok(modified) = std::fs::metadata(&path).and_then(|m| m.modified()) else {
+                continue;
+            };
+            if now.duration_since(modified).unwrap_or_default() < min_age {
+                continue;
+            }
+
+            match engine.matches_any(&path, allowed) {
+                Ok(false) => found.push((modified, path)),
+                Ok(true) => {}
+                Err(e) => debug!("Skipping {} in report: {}", path.display(), e),
+            }
+        }
+    }
+
+    found.sort();
+    Ok(found.into_iter().map(|(_, path)| path).collect())
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with('.'))
+}
+
+/// Build the report and send one notification per folder with unmatched files.
+/// Returns the reports that were sent.
+pub fn send(config: &Config) -> Vec<WatchReport> {
+    let reports = build(config);
+    for report in &reports {
+        crate::notifications::notify(
+            crate::notifications::NotificationKind::Report,
+            &report.message(config.report.older_than_days, config.report.max_files),
+        );
+    }
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Action, Condition, Rule};
+
+    #[test]
+    fn test_unmatched_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("doc.pdf"), "x").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "x").unwrap();
+        std::fs::write(dir.path().join(".DS_Store"), "x").unwrap();
+
+        let engine = RuleEngine::new(vec![Rule::new(
+            "PDFs",
+            Condition {
+                extension: Some("pdf".to_string()),
+                ..Default::default()
+            },
+            Action::Nothing,
+        )]);
+        let watch = WatchConfig {
+            path: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+
+        let unmatched = unmatched_files(&watch, &engine, 0).unwrap();
+        assert_eq!(unmatched, vec![dir.path().join("notes.txt")]);
+
+        // Nothing is old enough yet
+        assert!(unmatched_files(&watch, &engine, 7).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_report_message() {
+        let report = WatchReport {
+            path: PathBuf::from("/home/me/Downloads"),
+            unmatched: vec![
+                PathBuf::from("/home/me/Downloads/a.zip"),
+                PathBuf::from("/home/me/Downloads/b.iso"),
+                PathBuf::from("/home/me/Downloads/c.dmg"),
+            ],
+        };
+
+        assert_eq!(
+            report.summary(7),
+            "Downloads currently holds 3 unmatched files older than 7 days"
+        );
+        assert_eq!(
+            report.message(7, 2),
+            "Downloads currently holds 3 unmatched files older than 7 days\n• a.zip\n• b.iso\n…and 1 more"
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Retention policies - folders emptied of old files on a schedule
+//!
+//! A `[[retention]]` entry names a folder, an age, and what happens to what
+//! is older: it's trashed, zipped into `archive_to`, or deleted (into the
+//! quarantine, if that's enabled). The daemon applies each policy on its
+//! `schedule`, nightly at 03:00 by default; `hazelnut retention` applies
+//! them all once.
+//!
+//! Age is the time since the file was last modified; a folder counts as
+//! modified when anything inside it was. Hidden files and files an undo put
+//! back are left alone. What a policy removes is recorded in the action
+//! history like a rule's actions, so trashed files can be put back with
+//! `hazelnut undo`.
+
+use anyhow::{Result, bail};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
+
+use crate::rules::Action;
+use crate::schedule::Schedule;
+
+/// `[[retention]]`: a folder cleared of files older than an age
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RetentionPolicy {
+    /// Folder to clear
+    pub path: PathBuf,
+
+    /// Files not modified for this many days are removed
+    pub max_age_days: u64,
+
+    /// What happens to them
+    #[serde(default)]
+    pub action: RetentionAction,
+
+    /// Folder the archives go in (required for `action = "archive"`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_to: Option<PathBuf>,
+
+    /// When to apply the policy (cron syntax, default nightly at 03:00)
+    #[serde(default = "default_schedule")]
+    pub schedule: Schedule,
+
+    /// Also clear old files from subfolders (which themselves are kept)
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+fn default_schedule() -> Schedule {
+    Schedule::parse("0 3 * * *").expect("valid default schedule")
+}
+
+/// What a retention policy does with an old file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionAction {
+    /// Move it to the trash
+    #[default]
+    Trash,
+    /// Zip it into `archive_to` and remove it
+    Archive,
+    /// Delete it
+    Delete,
+}
+
+impl RetentionAction {
+    /// The action run on each file, archiving into `archive_to`
+    pub fn to_action(self, archive_to: Option<PathBuf>) -> Action {
+        match self {
+            RetentionAction::Trash => Action::Trash,
+            RetentionAction::Delete => Action::Delete,
+            RetentionAction::Archive => Action::Archive {
+                destination: archive_to,
+                delete_original: true,
+            },
+        }
+    }
+}
+
+/// What applying a policy did
+#[derive(Debug, Default)]
+pub struct RetentionRun {
+    /// Files removed (or that would be, in a dry run)
+    pub removed: Vec<PathBuf>,
+    /// Files the action failed on, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl RetentionPolicy {
+    /// Check the settings the parser can't
+    pub fn validate(&self) -> Result<()> {
+        if self.max_age_days == 0 {
+            bail!(
+                "retention for {}: max_age_days must be at least 1",
+                self.path.display()
+            );
+        }
+        if self.action == RetentionAction::Archive && self.archive_to.is_none() {
+            bail!(
+                "retention for {}: archive_to is required when the action is \"archive\"",
+                self.path.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// The action run on each old file
+    pub fn action(&self) -> Action {
+        self.action.to_action(self.archive_to.clone())
+    }
+
+    /// Name the policy's actions are recorded under in the history
+    pub fn label(&self) -> String {
+        format!("Retention {}", self.path.display())
+    }
+
+    /// Files and folders in the policy's folder older than `max_age_days`
+    /// at `now`
+    pub fn expired(&self, now: SystemTime) -> Vec<PathBuf> {
+        let max_age = Duration::from_secs(self.max_age_days.saturating_mul(86400));
+        let cutoff = now.checked_sub(max_age).unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut expired: Vec<_> = removable(&crate::expand_path(&self.path), self.recursive)
+            .into_iter()
+            .filter(|(_, modified)| *modified < cutoff)
+            .map(|(path, _)| path)
+            .collect();
+        expired.sort();
+        expired
+    }
+
+    /// Remove the expired files, recording each in the history. A dry run
+    /// only lists them.
+    pub fn apply(&self, dry_run: bool) -> RetentionRun {
+        let action = self.action();
+        let label = self.label();
+        let mut run = RetentionRun::default();
+        for path in self.expired(SystemTime::now()) {
+            if dry_run {
+                info!(
+                    "[dry run] retention would {} {}",
+                    action.kind(),
+                    path.display()
+                );
+                run.removed.push(path);
+                continue;
+            }
+            let checksum = crate::history::checksum(&path);
+            let started = Instant::now();
+            let result = action.execute_to(&path);
+            crate::logfile::log_action(&label, &action, &path, &result, started.elapsed());
+            crate::history::record(&label, &action, &path, &result, checksum.as_deref(), None);
+            match result {
+                Ok(_) => run.removed.push(path),
+                Err(e) => {
+                    warn!(
+                        "Retention failed to {} {}: {:#}",
+                        action.kind(),
+                        path.display(),
+                        e
+                    );
+                    run.failed.push((path, format!("{:#}", e)));
+                }
+            }
+        }
+        run
+    }
+}
+
+/// The files in `dir` a policy may remove, with when each was last
+/// modified. Unless `recursive`, a subfolder counts as one file. Hidden files
+/// and files an undo put back are left out.
+pub(crate) fn removable(dir: &Path, recursive: bool) -> Vec<(PathBuf, SystemTime)> {
+    let mut found = Vec::new();
+    collect_removable(dir, recursive, &mut found);
+    found
+}
+
+fn collect_removable(dir: &Path, recursive: bool, found: &mut Vec<(PathBuf, SystemTime)>) {
+    let Ok(read) = std::fs::read_dir(dir) else {
+        warn!("Can't read {}", dir.display());
+        return;
+    };
+    for entry in read.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() && recursive {
+            collect_removable(&path, recursive, found);
+            continue;
+        }
+        let modified = if metadata.is_dir() {
+            newest_modified(&path)
+        } else {
+            metadata.modified().ok()
+        };
+        if let Some(modified) = modified
+            && !crate::history::is_restored(&path)
+        {
+            found.push((path, modified));
+        }
+    }
+}
+
+/// When a folder or anything in it was last modified
+fn newest_modified(dir: &Path) -> Option<SystemTime> {
+    let own = dir.symlink_metadata().and_then(|m| m.modified()).ok();
+    let Ok(read) = std::fs::read_dir(dir) else {
+        return own;
+    };
+    read.flatten()
+        .filter_map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => newest_modified(&entry.path()),
+            _ => entry.metadata().and_then(|m| m.modified()).ok(),
+        })
+        .chain(own)
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn age(path: &Path, days: u64) {
+        let time = SystemTime::now() - Duration::from_secs(days * 86400);
+        std::fs::File::open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for name in ["old.pdf", "new.pdf", ".hidden"] {
+            std::fs::write(root.join(name), "").unwrap();
+        }
+        age(&root.join("old.pdf"), 40);
+        age(&root.join(".hidden"), 40);
+        // A folder is as new as the newest file in it
+        std::fs::create_dir_all(root.join("project/src")).unwrap();
+        std::fs::write(root.join("project/src/main.rs"), "").unwrap();
+        std::fs::write(root.join("project/notes.txt"), "").unwrap();
+        age(&root.join("project/notes.txt"), 40);
+        age(&root.join("project/src"), 40);
+        age(&root.join("project"), 40);
+
+        let policy: RetentionPolicy =
+            toml::from_str(&format!("path = '{}'\nmax_age_days = 30", root.display())).unwrap();
+        assert_eq!(policy.action, RetentionAction::Trash);
+        assert_eq!(policy.schedule.as_str(), "0 3 * * *");
+        assert_eq!(policy.expired(SystemTime::now()), [root.join("old.pdf")]);
+
+        age(&root.join("project/src/main.rs"), 40);
+        age(&root.join("project/src"), 40);
+        assert_eq!(
+            policy.expired(SystemTime::now()),
+            [root.join("old.pdf"), root.join("project")]
+        );
+
+        // Recursive policies look at the files inside instead
+        let recursive = RetentionPolicy {
+            recursive: true,
+            ..policy.clone()
+        };
+        std::fs::write(root.join("project/src/lib.rs"), "").unwrap();
+        assert_eq!(
+            recursive.expired(SystemTime::now()),
+            [
+                root.join("old.pdf"),
+                root.join("project/notes.txt"),
+                root.join("project/src/main.rs"),
+            ]
+        );
+
+        let run = RetentionPolicy {
+            action: RetentionAction::Delete,
+            ..recursive
+        }
+        .apply(false);
+        assert_eq!(run.removed.len(), 3);
+        assert!(run.failed.is_empty());
+        assert!(!root.join("old.pdf").exists());
+        assert!(root.join("new.pdf").exists());
+        assert!(root.join("project/src/lib.rs").exists());
+
+        let archive = RetentionPolicy {
+            action: RetentionAction::Archive,
+            ..policy
+        };
+        assert!(archive.validate().is_err());
+    }
+}
@@ -2,11 +2,16 @@
 
 use anyhow::{Context, Result};
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::{debug, info};
 
+use crate::plugins::PluginCall;
+use crate::quarantine::Reason;
+
 #[cfg(unix)]
 use libc;
 
@@ -15,8 +20,8 @@ static DATE_FORMAT_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"\{date:([^}]+)\}").expect("invalid date format regex"));
 
 /// Action to perform on a matched file
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "lowercase")]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(remote = "Self", tag = "type", rename_all = "lowercase")]
 pub enum Action {
     /// Move file to a destination folder
     Move {
@@ -24,9 +29,13 @@ pub enum Action {
         /// Create destination if it doesn't exist
         #[serde(default = "default_true")]
         create_destination: bool,
-        /// Overwrite if file exists
+        /// What to do when the destination has a file of the same name
         #[serde(default)]
-        overwrite: bool,
+        on_conflict: OnConflict,
+        /// Keep extended attributes, timestamps and permissions when the
+        /// move copies the file to another filesystem
+        #[serde(default = "default_true", skip_serializing_if = "is_true")]
+        preserve_metadata: bool,
     },
 
     /// Copy file to a destination folder
@@ -35,7 +44,10 @@ pub enum Action {
         #[serde(default = "default_true")]
         create_destination: bool,
         #[serde(default)]
-        overwrite: bool,
+        on_conflict: OnConflict,
+        /// Keep extended attributes, timestamps and permissions on the copy
+        #[serde(default = "default_true", skip_serializing_if = "is_true")]
+        preserve_metadata: bool,
     },
 
     /// Rename the file
@@ -67,74 +79,270 @@ pub enum Action {
         delete_original: bool,
     },
 
+    /// Move the file through storage tiers as it ages
+    Tier {
+        /// Stages ordered by age; the file goes to the oldest stage it qualifies for
+        stages: Vec<TierStage>,
+    },
+
+    /// Run a Rhai script that can move, copy or rename the file
+    Script {
+        /// The script (`file` holds the file's path and metadata)
+        script: String,
+    },
+
     /// Do nothing (useful for testing conditions)
     Nothing,
+
+    /// Hand the file to a plugin (`type = "plugin:<name>"`)
+    #[serde(skip)]
+    Plugin(PluginCall),
+}
+
+// A plugin's `type` names the plugin, so it can't be one of the derived
+// variants; the derived (remote) impls handle every other action
+
+impl Serialize for Action {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Action::Plugin(call) => call.serialize(serializer),
+            _ => Action::serialize(self, serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Action {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let table = toml::Table::deserialize(deserializer)?;
+        let plugin = table
+            .get("type")
+            .and_then(toml::Value::as_str)
+            .is_some_and(|kind| kind.starts_with(crate::plugins::TYPE_PREFIX));
+        let table = toml::Value::Table(table);
+        let action = if plugin {
+            PluginCall::deserialize(table).map(Action::Plugin)
+        } else {
+            Action::deserialize(table)
+        };
+        action.map_err(serde::de::Error::custom)
+    }
 }
 
+/// What a move or copy does when its destination already has a file of
+/// the same name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// Fail, leaving the file where it is
+    #[default]
+    Fail,
+    /// Leave the file where it is, without an error
+    Skip,
+    /// Replace the existing file
+    Overwrite,
+    /// Add a number to the name: `report (1).pdf`
+    RenameWithSuffix,
+    /// Add the time to the name: `report (2026-05-01 14.02.11).pdf`
+    RenameWithTimestamp,
+    /// Replace the existing file if this one was modified later, otherwise skip
+    KeepNewer,
+}
+
+std::thread_local! {
+    /// How the last move or copy on this thread got past an existing file
+    static CONFLICT: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+    /// Checksum the last move across filesystems on this thread was verified by
+    static VERIFIED: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Moves across filesystems compare checksums before removing the original
+static VERIFY_MOVES: AtomicBool = AtomicBool::new(false);
+
+/// Set whether moves across filesystems are verified (`verify_moves`)
+pub fn set_verify_moves(verify: bool) {
+    VERIFY_MOVES.store(verify, Ordering::Relaxed);
+}
+
+/// Checksum (`algorithm:hex`) the last move on this thread was verified by,
+/// if it crossed filesystems with `verify_moves` on. Taken once.
+pub fn take_verified() -> Option<String> {
+    VERIFIED.with(|verified| verified.borrow_mut().take())
+}
+
+/// How the last move or copy on this thread resolved a name conflict, e.g.
+/// "renamed to report (1).pdf" (None if there was none). Taken once.
+pub fn take_conflict() -> Option<String> {
+    CONFLICT.with(|conflict| conflict.borrow_mut().take())
+}
+
+fn note_conflict(outcome: String) {
+    info!("Destination taken: {}", outcome);
+    CONFLICT.with(|conflict| *conflict.borrow_mut() = Some(outcome));
+}
+
+/// One stage of a tiered storage lifecycle
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TierStage {
+    /// Days since the file was first added before it moves to this stage
+    pub after_days: u64,
+    /// Folder for files that reached this stage
+    pub destination: PathBuf,
+}
+
+/// Give up looking for a free `name (n)` after this many attempts
+const MAX_NAME_ATTEMPTS: u32 = 10_000;
+
 fn default_true() -> bool {
     true
 }
 
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
 impl Action {
+    /// Check what can be checked before the action runs (a script compiles)
+    pub fn validate(&self) -> Result<()> {
+        if let Action::Script { script } = self {
+            super::script::validate(script)?;
+        }
+        Ok(())
+    }
+
+    /// The action's `type` in the config, e.g. `move`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Action::Move { .. } => "move",
+            Action::Copy { .. } => "copy",
+            Action::Rename { .. } => "rename",
+            Action::Trash => "trash",
+            Action::Delete => "delete",
+            Action::Run { .. } => "run",
+            Action::Archive { .. } => "archive",
+            Action::Tier { .. } => "tier",
+            Action::Script { .. } => "script",
+            Action::Nothing => "nothing",
+            Action::Plugin(_) => "plugin",
+        }
+    }
+
+    /// Folder a move or copy places files in (expanded)
+    pub fn destination(&self) -> Option<PathBuf> {
+        match self {
+            Action::Move { destination, .. } | Action::Copy { destination, .. } => {
+                Some(expand_path(destination))
+            }
+            _ => None,
+        }
+    }
+
+    /// Where `execute_to` would put a file, without touching it: the new
+    /// path for moves, copies, renames, archives and due tiers (None for
+    /// the other actions). Name conflicts aren't accounted for.
+    pub fn preview_destination(&self, path: &Path) -> Option<PathBuf> {
+        match self {
+            Action::Move { destination, .. } | Action::Copy { destination, .. } => {
+                Some(expand_path(destination).join(path.file_name()?))
+            }
+            Action::Rename { pattern } => {
+                let new_name = expand_pattern(pattern, path).ok()?;
+                Some(path.parent().unwrap_or(Path::new(".")).join(new_name))
+            }
+            Action::Archive { destination, .. } => {
+                let dest = destination
+                    .as_ref()
+                    .map(|p| expand_path(p))
+                    .unwrap_or_else(|| path.parent().unwrap_or(Path::new(".")).to_path_buf());
+                Some(dest.join(format!("{}.zip", path.file_stem()?.to_string_lossy())))
+            }
+            Action::Tier { stages } => {
+                let added = crate::date_added::peek_date_added(path);
+                let age_days = (chrono::Local::now() - added).num_days().max(0) as u64;
+                let dest = expand_path(&due_stage(stages, age_days)?.destination);
+                if path.parent().is_some_and(|parent| same_dir(parent, &dest)) {
+                    return None;
+                }
+                Some(dest.join(path.file_name()?))
+            }
+            Action::Trash
+            | Action::Delete
+            | Action::Run { .. }
+            | Action::Script { .. }
+            | Action::Nothing
+            | Action::Plugin(_) => None,
+        }
+    }
+
+    /// Move or copy a file next to an existing one of the same name as a
+    /// numbered version (`name (n).ext`) instead of overwriting it. Other
+    /// actions run as usual.
+    pub fn execute_as_version(&self, path: &Path) -> Result<Option<PathBuf>> {
+        let (Action::Move {
+            create_destination,
+            preserve_metadata,
+            ..
+        }
+        | Action::Copy {
+            create_destination,
+            preserve_metadata,
+            ..
+        }) = self
+        else {
+            return self.execute_to(path);
+        };
+        let dest = self.destination().context("Action has no destination")?;
+        if *create_destination {
+            std::fs::create_dir_all(&dest)
+                .with_context(|| format!("Failed to create directory: {}", dest.display()))?;
+        }
+        let filename = path.file_name().context("File has no name")?;
+        let dest_path = reserve_unique(&dest.join(filename), path.is_dir())?;
+
+        let result = if matches!(self, Action::Move { .. }) {
+            info!("Moving {} -> {}", path.display(), dest_path.display());
+            move_to(path, &dest_path, *preserve_metadata)
+        } else {
+            info!("Copying {} -> {}", path.display(), dest_path.display());
+            copy_to(path, &dest_path, *preserve_metadata)
+        };
+        if let Err(e) = result {
+            release(&dest_path);
+            return Err(e);
+        }
+        Ok(Some(dest_path))
+    }
+
     /// Execute this action on a file
     pub fn execute(&self, path: &Path) -> Result<()> {
+        self.execute_to(path).map(|_| ())
+    }
+
+    /// Execute this action on a file, returning where the result landed
+    /// (the moved, copied, renamed or archived file), if anywhere
+    pub fn execute_to(&self, path: &Path) -> Result<Option<PathBuf>> {
+        let mut landed = None;
+
         match self {
             Action::Move {
                 destination,
                 create_destination,
-                overwrite,
+                on_conflict,
+                preserve_metadata,
             } => {
-                let dest = expand_path(destination);
-
-                if *create_destination {
-                    std::fs::create_dir_all(&dest).with_context(|| {
-                        format!("Failed to create directory: {}", dest.display())
-                    })?;
-                }
-
-                let filename = path.file_name().context("File has no name")?;
-                let dest_path = dest.join(filename);
-
-                if dest_path.exists() && !overwrite {
-                    anyhow::bail!(
-                        "Destination exists and overwrite is false: {}",
-                        dest_path.display()
-                    );
-                }
-
-                info!("Moving {} -> {}", path.display(), dest_path.display());
-                if std::fs::rename(path, &dest_path).is_err() {
-                    // rename fails across filesystems; fall back to copy + remove
-                    if path.is_dir() {
-                        copy_dir_recursive(path, &dest_path).with_context(|| {
-                            format!(
-                                "Failed to copy directory {} to {}",
-                                path.display(),
-                                dest_path.display()
-                            )
-                        })?;
-                        std::fs::remove_dir_all(path).with_context(|| {
-                            format!("Failed to remove original directory {}", path.display())
-                        })?;
-                    } else {
-                        std::fs::copy(path, &dest_path).with_context(|| {
-                            format!(
-                                "Failed to copy {} to {}",
-                                path.display(),
-                                dest_path.display()
-                            )
-                        })?;
-                        std::fs::remove_file(path).with_context(|| {
-                            format!("Failed to remove original file {}", path.display())
-                        })?;
-                    }
-                }
+                landed = move_into(
+                    path,
+                    &expand_path(destination),
+                    *create_destination,
+                    *on_conflict,
+                    *preserve_metadata,
+                )?;
             }
 
             Action::Copy {
                 destination,
                 create_destination,
-                overwrite,
+                on_conflict,
+                preserve_metadata,
             } => {
                 let dest = expand_path(destination);
 
@@ -143,25 +351,42 @@ impl Action {
                 }
 
                 let filename = path.file_name().context("File has no name")?;
-                let dest_path = dest.join(filename);
-
-                if dest_path.exists() && !overwrite {
-                    anyhow::bail!(
-                        "Destination exists and overwrite is false: {}",
-                        dest_path.display()
-                    );
-                }
+                let Some((dest_path, reserved)) =
+                    claim_destination(path, dest.join(filename), *on_conflict)?
+                else {
+                    return Ok(None);
+                };
 
                 info!("Copying {} -> {}", path.display(), dest_path.display());
-                std::fs::copy(path, &dest_path)?;
+                if let Err(e) = copy_to(path, &dest_path, *preserve_metadata) {
+                    if reserved {
+                        release(&dest_path);
+                    }
+                    return Err(e);
+                }
+                landed = Some(dest_path);
             }
 
             Action::Rename { pattern } => {
                 let new_name = expand_pattern(pattern, path)?;
-                let new_path = path.parent().unwrap_or(Path::new(".")).join(&new_name);
+                let mut new_path = path.parent().unwrap_or(Path::new(".")).join(&new_name);
+
+                // A case-only change names the same file on case-insensitive
+                // filesystems, so there's nothing to reserve. On others the
+                // new name may belong to another file, which must survive.
+                let same_file = is_same_file(path, &new_path);
+                if !same_file {
+                    new_path = reserve_unique(&new_path, path.is_dir())?;
+                }
 
                 info!("Renaming {} -> {}", path.display(), new_path.display());
-                std::fs::rename(path, &new_path)?;
+                if let Err(e) = std::fs::rename(path, &new_path) {
+                    if !same_file {
+                        release(&new_path);
+                    }
+                    return Err(e.into());
+                }
+                landed = Some(new_path);
             }
 
             Action::Trash => {
@@ -178,41 +403,22 @@ impl Action {
                     std::fs::create_dir_all(&trash_dir)?;
 
                     let filename = path.file_name().context("File has no name")?;
-                    let mut trash_path = trash_dir.join(filename);
-
-                    if trash_path.exists() {
-                        let stem = path
-                            .file_stem()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .to_string();
-                        let ext = path
-                            .extension()
-                            .map(|e| format!(".{}", e.to_string_lossy()))
-                            .unwrap_or_default();
-                        let mut counter = 1u32;
-                        loop {
-                            if counter > 10000 {
-                                anyhow::bail!(
-                                    "Too many duplicate files in trash for: {}",
-                                    path.display()
-                                );
-                            }
-                            trash_path = trash_dir.join(format!("{}_{}{}", stem, counter, ext));
-                            if !trash_path.exists() {
-                                break;
-                            }
-                            counter += 1;
-                        }
-                    }
+                    let trash_path = reserve_unique(&trash_dir.join(filename), path.is_dir())?;
 
                     if std::fs::rename(path, &trash_path).is_err() {
-                        std::fs::copy(path, &trash_path)?;
+                        if let Err(e) = std::fs::copy(path, &trash_path) {
+                            release(&trash_path);
+                            return Err(e.into());
+                        }
                         std::fs::remove_file(path)?;
                     }
                 }
             }
 
+            Action::Delete if crate::quarantine::is_enabled() => {
+                crate::quarantine::stash(path, Reason::Deleted)?;
+            }
+
             Action::Delete => {
                 info!("Deleting {}", path.display());
                 if path.is_dir() {
@@ -371,60 +577,23 @@ impl Action {
 
                 let filename = path.file_stem().context("File has no name")?;
                 let archive_name = format!("{}.zip", filename.to_string_lossy());
-                let archive_path = dest.join(&archive_name);
+                let archive_path = reserve_unique(&dest.join(&archive_name), false)?;
 
                 info!("Archiving {} -> {}", path.display(), archive_path.display());
 
-                // Create the zip archive
-                let zip_file = std::fs::File::create(&archive_path)?;
-                let mut zip = zip::ZipWriter::new(zip_file);
-                let options = zip::write::SimpleFileOptions::default()
-                    .compression_method(zip::CompressionMethod::Deflated);
-
-                if path.is_dir() {
-                    // Recursively add all files in the directory
-                    fn add_dir_to_zip(
-                        zip: &mut zip::ZipWriter<std::fs::File>,
-                        dir: &Path,
-                        base: &Path,
-                        options: zip::write::SimpleFileOptions,
-                    ) -> Result<()> {
-                        for entry in std::fs::read_dir(dir)? {
-                            let entry = entry?;
-                            let entry_path = entry.path();
-                            let relative = entry_path
-                                .strip_prefix(base)
-                                .unwrap_or(&entry_path)
-                                .to_string_lossy();
-                            if entry_path.is_dir() {
-                                zip.add_directory(format!("{}/", relative), options)?;
-                                add_dir_to_zip(zip, &entry_path, base, options)?;
-                            } else {
-                                zip.start_file(relative.as_ref(), options)?;
-                                let mut source = std::fs::File::open(&entry_path)?;
-                                std::io::copy(&mut source, zip)?;
-                            }
-                        }
-                        Ok(())
-                    }
-                    add_dir_to_zip(
-                        &mut zip,
-                        path,
-                        path.parent().unwrap_or(Path::new(".")),
-                        options,
-                    )?;
-                } else {
-                    let file_name = path
-                        .file_name()
-                        .context("File has no name")?
-                        .to_string_lossy();
-                    zip.start_file(file_name.as_ref(), options)?;
-                    let mut source = std::fs::File::open(path)?;
-                    std::io::copy(&mut source, &mut zip)?;
+                // Build the archive next to its destination and rename it into
+                // place, so a failure never leaves a partial archive behind
+                let staging = staging_path(&archive_path);
+                let result = write_zip(path, &staging)
+                    .and_then(|()| Ok(std::fs::rename(&staging, &archive_path)?));
+                if let Err(e) = result {
+                    let _ = std::fs::remove_file(&staging);
+                    release(&archive_path);
+                    return Err(e);
                 }
-                zip.finish()?;
 
                 info!("Created archive: {}", archive_path.display());
+                landed = Some(archive_path);
 
                 if *delete_original {
                     if path.is_dir() {
@@ -435,12 +604,492 @@ impl Action {
                 }
             }
 
+            Action::Tier { stages } => {
+                let added = crate::date_added::date_added(path);
+                let age_days = (chrono::Local::now() - added).num_days().max(0) as u64;
+
+                let Some(stage) = due_stage(stages, age_days) else {
+                    debug!(
+                        "{} is {} days old, no tier due yet",
+                        path.display(),
+                        age_days
+                    );
+                    return Ok(None);
+                };
+
+                let dest = expand_path(&stage.destination);
+                if path.parent().is_some_and(|parent| same_dir(parent, &dest)) {
+                    debug!("{} is already in its tier", path.display());
+                    return Ok(None);
+                }
+
+                info!(
+                    "Tiering {} ({} days old) -> {}",
+                    path.display(),
+                    age_days,
+                    dest.display()
+                );
+                let new_path = move_into(path, &dest, true, OnConflict::Fail, true)?;
+                if let Some(new_path) = &new_path {
+                    crate::date_added::record_move(path, new_path);
+                }
+                landed = new_path;
+            }
+
+            Action::Script { script } => {
+                info!("Running script on {}", path.display());
+                landed = super::script::execute(script, path)?;
+            }
+
             Action::Nothing => {
                 debug!("No action for {}", path.display());
             }
+
+            Action::Plugin(call) => {
+                info!("Running plugin '{}' on {}", call.name, path.display());
+                landed = call.execute(path)?;
+            }
         }
 
-        Ok(())
+        Ok(landed)
+    }
+}
+
+/// Write a zip archive of a file or folder tree to `zip_path`
+fn write_zip(path: &Path, zip_path: &Path) -> Result<()> {
+    let zip_file = std::fs::File::create(zip_path)?;
+    let mut zip = zip::ZipWriter::new(zip_file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    if path.is_dir() {
+        // Recursively add all files in the directory
+        fn add_dir_to_zip(
+            zip: &mut zip::ZipWriter<std::fs::File>,
+            dir: &Path,
+            base: &Path,
+            options: zip::write::SimpleFileOptions,
+        ) -> Result<()> {
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                let entry_path = entry.path();
+                let relative = entry_path
+                    .strip_prefix(base)
+                    .unwrap_or(&entry_path)
+                    .to_string_lossy();
+                if entry_path.is_dir() {
+                    zip.add_directory(format!("{}/", relative), options)?;
+                    add_dir_to_zip(zip, &entry_path, base, options)?;
+                } else {
+                    zip.start_file(relative.as_ref(), options)?;
+                    let mut source = std::fs::File::open(&entry_path)?;
+                    std::io::copy(&mut source, zip)?;
+                }
+            }
+            Ok(())
+        }
+        add_dir_to_zip(
+            &mut zip,
+            path,
+            path.parent().unwrap_or(Path::new(".")),
+            options,
+        )?;
+    } else {
+        let file_name = path
+            .file_name()
+            .context("File has no name")?
+            .to_string_lossy();
+        zip.start_file(file_name.as_ref(), options)?;
+        let mut source = std::fs::File::open(path)?;
+        std::io::copy(&mut source, &mut zip)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// Move a file or directory into `dest`, returning its new path (None if
+/// `on_conflict` left it where it is).
+/// Falls back to copy + remove when renaming across filesystems.
+fn move_into(
+    path: &Path,
+    dest: &Path,
+    create_destination: bool,
+    on_conflict: OnConflict,
+    preserve_metadata: bool,
+) -> Result<Option<PathBuf>> {
+    if create_destination {
+        std::fs::create_dir_all(dest)
+            .with_context(|| format!("Failed to create directory: {}", dest.display()))?;
+    }
+
+    let filename = path.file_name().context("File has no name")?;
+    let Some((dest_path, reserved)) = claim_destination(path, dest.join(filename), on_conflict)?
+    else {
+        return Ok(None);
+    };
+
+    info!("Moving {} -> {}", path.display(), dest_path.display());
+    let result = move_to(path, &dest_path, preserve_metadata);
+    if result.is_err() && reserved {
+        release(&dest_path);
+    }
+    result.map(|_| Some(dest_path))
+}
+
+/// Where a move or copy of `path` to `dest_path` should write, settling a
+/// file already there by `on_conflict`: the path and whether it's a
+/// reserved placeholder, or None to leave the file alone
+fn claim_destination(
+    path: &Path,
+    dest_path: PathBuf,
+    on_conflict: OnConflict,
+) -> Result<Option<(PathBuf, bool)>> {
+    let is_dir = path.is_dir();
+    match reserve(&dest_path, is_dir) {
+        Ok(()) => return Ok(Some((dest_path, true))),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to reserve destination {}", dest_path.display()));
+        }
+    }
+    let name = dest_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let newer = || {
+        let modified = |p: &Path| p.metadata().and_then(|m| m.modified()).ok();
+        matches!((modified(path), modified(&dest_path)), (Some(a), Some(b)) if a > b)
+    };
+    match on_conflict {
+        OnConflict::Fail => anyhow::bail!(
+            "Destination exists and on_conflict is \"fail\": {}",
+            dest_path.display()
+        ),
+        OnConflict::Skip => {
+            note_conflict(format!("skipped, {} already exists", name));
+            Ok(None)
+        }
+        OnConflict::Overwrite => {
+            let claimed = replace_destination(&dest_path, is_dir)?;
+            note_conflict(format!("replaced the existing {}{}", name, claimed.1));
+            Ok(Some((dest_path, claimed.0)))
+        }
+        OnConflict::KeepNewer if newer() => {
+            let claimed = replace_destination(&dest_path, is_dir)?;
+            note_conflict(format!("replaced the older {}{}", name, claimed.1));
+            Ok(Some((dest_path, claimed.0)))
+        }
+        OnConflict::KeepNewer => {
+            note_conflict(format!("skipped, the existing {} is newer", name));
+            Ok(None)
+        }
+        OnConflict::RenameWithSuffix | OnConflict::RenameWithTimestamp => {
+            let wanted = match on_conflict {
+                OnConflict::RenameWithTimestamp => timestamped_path(&dest_path),
+                _ => dest_path,
+            };
+            let claimed = reserve_unique(&wanted, is_dir)?;
+            note_conflict(format!(
+                "{} exists, renamed to {}",
+                name,
+                claimed.file_name().unwrap_or_default().to_string_lossy()
+            ));
+            Ok(Some((claimed, true)))
+        }
+    }
+}
+
+/// Make way for a file replacing `dest_path`. With the quarantine enabled,
+/// the file there is moved into it and the name reserved; otherwise it's
+/// written over. Returns whether the name is reserved and a note for the
+/// conflict message.
+fn replace_destination(dest_path: &Path, is_dir: bool) -> Result<(bool, &'static str)> {
+    if !crate::quarantine::is_enabled() {
+        return Ok((false, ""));
+    }
+    crate::quarantine::stash(dest_path, Reason::Replaced)?;
+    reserve_exact(dest_path, is_dir)?;
+    Ok((true, " (kept in quarantine)"))
+}
+
+/// `name (2026-05-01 14.02.11).ext`, with the current time
+fn timestamped_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let time = chrono::Local::now().format("%Y-%m-%d %H.%M.%S");
+    let name = match path.extension() {
+        Some(ext) => format!("{} ({}).{}", stem, time, ext.to_string_lossy()),
+        None => format!("{} ({})", stem, time),
+    };
+    path.with_file_name(name)
+}
+
+/// Rename `path` to `dest_path` (replacing a reserved placeholder),
+/// copying and removing the original when renaming across filesystems.
+/// With `verify_moves`, the copy's checksum must match the original's
+/// before the original is removed.
+fn move_to(path: &Path, dest_path: &Path, preserve_metadata: bool) -> Result<()> {
+    if std::fs::rename(path, dest_path).is_err() {
+        // rename fails across filesystems; fall back to copy + remove
+        if path.is_dir() {
+            // Verified while staged, so a bad copy never replaces what's
+            // at `dest_path`
+            let verify = VERIFY_MOVES.load(Ordering::Relaxed);
+            copy_dir_to(path, dest_path, preserve_metadata, |staged| {
+                if verify {
+                    verify_copy(path, staged)?;
+                }
+                Ok(())
+            })
+            .with_context(|| {
+                format!(
+                    "Failed to copy directory {} to {}",
+                    path.display(),
+                    dest_path.display()
+                )
+            })?;
+            std::fs::remove_dir_all(path).with_context(|| {
+                format!("Failed to remove original directory {}", path.display())
+            })?;
+        } else {
+            crate::progress::copy_file(path, dest_path).with_context(|| {
+                format!(
+                    "Failed to copy {} to {}",
+                    path.display(),
+                    dest_path.display()
+                )
+            })?;
+            if preserve_metadata {
+                super::metadata::preserve(path, dest_path);
+            }
+            if VERIFY_MOVES.load(Ordering::Relaxed) {
+                let checksum = verify_copy(path, dest_path).inspect_err(|_| {
+                    let _ = std::fs::remove_file(dest_path);
+                })?;
+                VERIFIED.with(|verified| *verified.borrow_mut() = checksum);
+            }
+            std::fs::remove_file(path)
+                .with_context(|| format!("Failed to remove original file {}", path.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that `copy` has the contents of `original`, file by file for a
+/// folder. Returns the checksum of a file.
+fn verify_copy(original: &Path, copy: &Path) -> Result<Option<String>> {
+    if original.is_dir() {
+        for entry in std::fs::read_dir(original)
+            .with_context(|| format!("Failed to read {}", original.display()))?
+        {
+            let entry = entry?;
+            verify_copy(&entry.path(), &copy.join(entry.file_name()))?;
+        }
+        return Ok(None);
+    }
+    if !original.is_file() {
+        return Ok(None);
+    }
+    let algorithm = crate::hashing::algorithm();
+    let hash = |path: &Path| {
+        crate::hashing::hash_file(path, algorithm)
+            .with_context(|| format!("Failed to hash {}", path.display()))
+    };
+    let expected = hash(original)?;
+    if hash(copy)? != expected {
+        anyhow::bail!(
+            "The copy at {} doesn't match the original (checksum mismatch); the original was kept",
+            copy.display()
+        );
+    }
+    debug!("Verified {} ({})", copy.display(), expected);
+    Ok(Some(format!("{}:{}", algorithm.name(), expected)))
+}
+
+/// Move a file or directory to exactly `dest_path`, failing if something
+/// is already there
+pub(crate) fn move_exact(path: &Path, dest_path: &Path) -> Result<()> {
+    reserve_exact(dest_path, path.is_dir())?;
+    info!("Moving {} -> {}", path.display(), dest_path.display());
+    let result = move_to(path, dest_path, true);
+    if result.is_err() {
+        release(dest_path);
+    }
+    result
+}
+
+/// Copy a file or directory to exactly `dest_path`, failing if something
+/// is already there
+pub(crate) fn copy_exact(path: &Path, dest_path: &Path) -> Result<()> {
+    reserve_exact(dest_path, path.is_dir())?;
+    info!("Copying {} -> {}", path.display(), dest_path.display());
+    let result = copy_to(path, dest_path, true);
+    if result.is_err() {
+        release(dest_path);
+    }
+    result
+}
+
+/// Copy a file, or a folder tree through a staging folder next to
+/// `dest_path`, so the destination only ever holds a complete copy
+fn copy_to(path: &Path, dest_path: &Path, preserve_metadata: bool) -> Result<()> {
+    if !path.is_dir() {
+        crate::progress::copy_file(path, dest_path)?;
+        if preserve_metadata {
+            super::metadata::preserve(path, dest_path);
+        }
+        return Ok(());
+    }
+    copy_dir_to(path, dest_path, preserve_metadata, |_| Ok(()))
+}
+
+/// Copy a folder tree into a staging folder next to `dest_path` and rename
+/// it into place once `check` accepts the staged copy
+fn copy_dir_to(
+    path: &Path,
+    dest_path: &Path,
+    preserve_metadata: bool,
+    check: impl FnOnce(&Path) -> Result<()>,
+) -> Result<()> {
+    let staging = staging_path(dest_path);
+    let _ = std::fs::remove_dir_all(&staging);
+    let result = copy_dir_recursive(path, &staging).and_then(|()| {
+        if preserve_metadata {
+            super::metadata::preserve(path, &staging);
+        }
+        check(&staging)?;
+        // Replace the reserved placeholder (or the old copy when overwriting)
+        if dest_path.is_dir() {
+            std::fs::remove_dir_all(dest_path)?;
+        }
+        Ok(std::fs::rename(&staging, dest_path)?)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_dir_all(&staging);
+    }
+    result
+}
+
+/// Hidden `.name.part` next to `path`, for building a result before it's
+/// renamed into place (watchers skip `.part` files as in progress)
+fn staging_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.part", name))
+}
+
+/// Atomically claim `path` with an empty placeholder (a file, or a directory
+/// when moving one), so concurrent actions can't pick the same name.
+/// The placeholder is then replaced by renaming or copying onto it.
+fn reserve(path: &Path, is_dir: bool) -> std::io::Result<()> {
+    if is_dir {
+        std::fs::create_dir(path)
+    } else {
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map(|_| ())
+    }
+}
+
+/// Reserve exactly `path`, failing if something is already there
+fn reserve_exact(path: &Path, is_dir: bool) -> Result<()> {
+    match reserve(path, is_dir) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            anyhow::bail!("Destination exists: {}", path.display())
+        }
+        Err(e) => {
+            Err(e).with_context(|| format!("Failed to reserve destination {}", path.display()))
+        }
+    }
+}
+
+/// Reserve `path`, or the first free `name (n).ext` next to it
+fn reserve_unique(path: &Path, is_dir: bool) -> Result<PathBuf> {
+    for n in 0..=MAX_NAME_ATTEMPTS {
+        let candidate = if n == 0 {
+            path.to_path_buf()
+        } else {
+            numbered_path(path, n)
+        };
+        match reserve(&candidate, is_dir) {
+            Ok(()) => return Ok(candidate),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Failed to reserve destination {}", candidate.display())
+                });
+            }
+        }
+    }
+    anyhow::bail!("Too many files named like {}", path.display())
+}
+
+/// Whether `a` and `b` are the same file (as two spellings of one name are
+/// on case-insensitive filesystems). False if either doesn't exist.
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        match (std::fs::symlink_metadata(a), std::fs::symlink_metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        match (a.canonicalize(), b.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Remove a placeholder left by a failed action (only if still empty)
+fn release(path: &Path) {
+    if path.is_dir() {
+        let _ = std::fs::remove_dir(path);
+    } else if path.metadata().is_ok_and(|m| m.len() == 0) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// `report.pdf` → `report (n).pdf`. An existing ` (m)` suffix is replaced
+/// rather than extended, so `report (1).pdf` never becomes `report (1) (1).pdf`.
+fn numbered_path(path: &Path, n: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let base = match stem.strip_suffix(')').and_then(|s| s.rsplit_once(" (")) {
+        Some((base, digits))
+            if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            base
+        }
+        _ => stem.as_ref(),
+    };
+    let name = match path.extension() {
+        Some(ext) => format!("{} ({}).{}", base, n, ext.to_string_lossy()),
+        None => format!("{} ({})", base, n),
+    };
+    path.with_file_name(name)
+}
+
+/// Pick the latest stage whose age threshold has been reached
+fn due_stage(stages: &[TierStage], age_days: u64) -> Option<&TierStage> {
+    stages
+        .iter()
+        .filter(|s| age_days >= s.after_days)
+        .max_by_key(|s| s.after_days)
+}
+
+/// Compare two directories, resolving symlinks where possible
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
     }
 }
 
@@ -453,7 +1102,7 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
         if entry.file_type()?.is_dir() {
             copy_dir_recursive(&entry.path(), &dest_child)?;
         } else {
-            std::fs::copy(entry.path(), &dest_child)?;
+            crate::progress::copy_file(&entry.path(), &dest_child)?;
         }
     }
     Ok(())
@@ -518,7 +1167,7 @@ fn expand_pattern_inner(pattern: &str, path: &Path, shell_escape: bool) -> Resul
 }
 
 /// Expand pattern variables like {name}, {ext}, {date}
-fn expand_pattern(pattern: &str, path: &Path) -> Result<String> {
+pub(crate) fn expand_pattern(pattern: &str, path: &Path) -> Result<String> {
     expand_pattern_inner(pattern, path, false)
 }
 
@@ -541,6 +1190,222 @@ mod tests {
         assert_eq!(expand_pattern("{name}.{ext}", path).unwrap(), "test.pdf");
     }
 
+    #[test]
+    fn test_due_stage() {
+        let stages = vec![
+            TierStage {
+                after_days: 180,
+                destination: PathBuf::from("/mnt/external"),
+            },
+            TierStage {
+                after_days: 30,
+                destination: PathBuf::from("~/Archive"),
+            },
+        ];
+
+        assert!(due_stage(&stages, 10).is_none());
+        assert_eq!(due_stage(&stages, 30).unwrap().after_days, 30);
+        assert_eq!(due_stage(&stages, 365).unwrap().after_days, 180);
+    }
+
+    #[test]
+    fn test_numbered_path() {
+        assert_eq!(
+            numbered_path(Path::new("/tmp/report.pdf"), 1),
+            PathBuf::from("/tmp/report (1).pdf")
+        );
+        assert_eq!(
+            numbered_path(Path::new("/tmp/report (1).pdf"), 2),
+            PathBuf::from("/tmp/report (2).pdf")
+        );
+        assert_eq!(
+            numbered_path(Path::new("/tmp/notes (draft)"), 1),
+            PathBuf::from("/tmp/notes (draft) (1)")
+        );
+    }
+
+    #[test]
+    fn test_reserve_unique_concurrent() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("report.pdf");
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let target = target.clone();
+                std::thread::spawn(move || reserve_unique(&target, false).unwrap())
+            })
+            .collect();
+        let mut reserved: Vec<PathBuf> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        reserved.sort();
+        reserved.dedup();
+
+        // Every thread got its own name, none of them doubly numbered
+        assert_eq!(reserved.len(), 8);
+        assert!(
+            reserved
+                .iter()
+                .all(|p| !p.to_string_lossy().contains(") ("))
+        );
+    }
+
+    #[test]
+    fn test_rename_case_only_keeps_other_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let upper = dir.path().join("Report.pdf");
+        std::fs::write(&upper, "upper").unwrap();
+        let lower = dir.path().join("report.pdf");
+        if !lower.exists() {
+            // Case-sensitive filesystem: a second, distinct file
+            std::fs::write(&lower, "lower").unwrap();
+        }
+        let case_sensitive = !is_same_file(&upper, &lower);
+
+        let rename = Action::Rename {
+            pattern: "report.{ext}".to_string(),
+        };
+        let landed = rename.execute_to(&upper).unwrap().unwrap();
+
+        if case_sensitive {
+            // The existing report.pdf is untouched; the renamed file got a
+            // free name next to it
+            assert_eq!(std::fs::read_to_string(&lower).unwrap(), "lower");
+            assert_eq!(landed, dir.path().join("report (1).pdf"));
+            assert_eq!(std::fs::read_to_string(&landed).unwrap(), "upper");
+        } else {
+            assert_eq!(landed, lower);
+            assert_eq!(std::fs::read_to_string(&lower).unwrap(), "upper");
+        }
+    }
+
+    #[test]
+    fn test_move_does_not_clobber() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        let dest = dir.path().join("dest");
+        std::fs::create_dir(&dest).unwrap();
+        std::fs::write(&src, "new").unwrap();
+        std::fs::write(dest.join("a.txt"), "old").unwrap();
+
+        assert!(move_into(&src, &dest, false, OnConflict::Fail, true).is_err());
+        assert_eq!(std::fs::read_to_string(dest.join("a.txt")).unwrap(), "old");
+        assert!(src.exists());
+    }
+
+    #[test]
+    fn test_on_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("dest");
+        std::fs::create_dir(&dest).unwrap();
+        let existing = dest.join("a.txt");
+        let src = dir.path().join("a.txt");
+        let put = |content: &str| std::fs::write(&src, content).unwrap();
+        std::fs::write(&existing, "old").unwrap();
+
+        put("new");
+        assert_eq!(
+            move_into(&src, &dest, false, OnConflict::Skip, true).unwrap(),
+            None
+        );
+        assert!(src.exists());
+        assert_eq!(take_conflict().unwrap(), "skipped, a.txt already exists");
+
+        let landed = move_into(&src, &dest, false, OnConflict::RenameWithSuffix, true).unwrap();
+        assert_eq!(landed, Some(dest.join("a (1).txt")));
+        assert_eq!(
+            take_conflict().unwrap(),
+            "a.txt exists, renamed to a (1).txt"
+        );
+
+        put("newer");
+        let landed = move_into(&src, &dest, false, OnConflict::RenameWithTimestamp, true)
+            .unwrap()
+            .unwrap();
+        assert!(landed.to_string_lossy().contains("a ("));
+        assert_ne!(landed, dest.join("a (1).txt"));
+
+        // The existing file is newer than this one, then older
+        put("stale");
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        std::fs::File::options()
+            .write(true)
+            .open(&src)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        assert_eq!(
+            move_into(&src, &dest, false, OnConflict::KeepNewer, true).unwrap(),
+            None
+        );
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "old");
+        std::fs::File::options()
+            .write(true)
+            .open(&existing)
+            .unwrap()
+            .set_modified(old - std::time::Duration::from_secs(3600))
+            .unwrap();
+        assert_eq!(
+            move_into(&src, &dest, false, OnConflict::KeepNewer, true).unwrap(),
+            Some(existing.clone())
+        );
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "stale");
+        assert_eq!(take_conflict().unwrap(), "replaced the older a.txt");
+
+        put("latest");
+        move_into(&src, &dest, false, OnConflict::Overwrite, true).unwrap();
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "latest");
+        assert!(take_conflict().is_some());
+        assert!(take_conflict().is_none());
+    }
+
+    #[test]
+    fn test_verify_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("photos");
+        std::fs::create_dir_all(original.join("2024")).unwrap();
+        std::fs::write(original.join("2024/a.jpg"), "pixels").unwrap();
+        std::fs::write(original.join("b.jpg"), "more pixels").unwrap();
+        let copy = dir.path().join("copy");
+        copy_to(&original, &copy, true).unwrap();
+
+        assert!(verify_copy(&original, &copy).unwrap().is_none());
+        let checksum = verify_copy(&original.join("b.jpg"), &copy.join("b.jpg"))
+            .unwrap()
+            .unwrap();
+        assert!(checksum.starts_with("blake3:"));
+
+        // A flipped byte in any file fails the move, as does a missing file
+        std::fs::write(copy.join("2024/a.jpg"), "pixelz").unwrap();
+        assert!(verify_copy(&original, &copy).is_err());
+        std::fs::remove_file(copy.join("2024/a.jpg")).unwrap();
+        assert!(verify_copy(&original, &copy).is_err());
+    }
+
+    #[test]
+    fn test_failed_check_keeps_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("photos");
+        std::fs::create_dir(&original).unwrap();
+        std::fs::write(original.join("a.jpg"), "new pixels").unwrap();
+        let dest = dir.path().join("dest");
+        std::fs::create_dir(&dest).unwrap();
+        std::fs::write(dest.join("b.jpg"), "old pixels").unwrap();
+
+        // A copy that fails verification is dropped before it replaces anything
+        assert!(
+            copy_dir_to(&original, &dest, false, |_| anyhow::bail!(
+                "checksum mismatch"
+            ))
+            .is_err()
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest.join("b.jpg")).unwrap(),
+            "old pixels"
+        );
+        assert!(!dest.join("a.jpg").exists());
+        assert!(!staging_path(&dest).exists());
+        assert!(original.join("a.jpg").exists());
+    }
+
     #[test]
     fn test_expand_path() {
         // This test depends on the home directory existing
@@ -1,10 +1,18 @@
 //! Rule conditions - matching files based on attributes
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use regex::Regex;
+
+use super::folder::FolderSummary;
+use super::media::MediaInfo;
+use super::origin;
+use super::script;
+use crate::plugins::PluginCall;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
+use tracing::warn;
 
 // Simple thread-local caches for compiled patterns.
 // Capped at 1000 entries; cleared entirely when the cap is exceeded.
@@ -16,7 +24,7 @@ std::thread_local! {
 }
 
 /// Conditions for matching files
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 pub struct Condition {
     /// Match file extension (without dot, e.g., "pdf")
     #[serde(default)]
@@ -57,11 +65,83 @@ pub struct Condition {
     /// File is hidden (starts with .)
     #[serde(default)]
     pub is_hidden: Option<bool>,
+
+    /// Folder contains an entry whose name matches one of these globs (at any depth)
+    #[serde(default)]
+    pub contains: Vec<String>,
+
+    /// Media duration greater than (in seconds)
+    #[serde(default)]
+    pub duration_secs_greater_than: Option<f64>,
+
+    /// Media duration less than (in seconds)
+    #[serde(default)]
+    pub duration_secs_less_than: Option<f64>,
+
+    /// Video width greater than (in pixels)
+    #[serde(default)]
+    pub width_greater_than: Option<u32>,
+
+    /// Video width less than (in pixels)
+    #[serde(default)]
+    pub width_less_than: Option<u32>,
+
+    /// Video height greater than (in pixels)
+    #[serde(default)]
+    pub height_greater_than: Option<u32>,
+
+    /// Video height less than (in pixels)
+    #[serde(default)]
+    pub height_less_than: Option<u32>,
+
+    /// Any stream uses this codec (e.g., "h264", "hevc", "aac")
+    #[serde(default)]
+    pub codec: Option<String>,
+
+    /// Video frame rate greater than (frames per second)
+    #[serde(default)]
+    pub framerate_greater_than: Option<f64>,
+
+    /// Video frame rate less than (frames per second)
+    #[serde(default)]
+    pub framerate_less_than: Option<f64>,
+
+    /// Created by one of these applications (macOS only)
+    #[serde(default)]
+    pub created_by: Vec<String>,
+
+    /// Not created by any of these applications (macOS only)
+    #[serde(default)]
+    pub not_created_by: Vec<String>,
+
+    /// Checks by plugins, each `{ type = "plugin:<name>", ... }` (all must match)
+    #[serde(default)]
+    pub plugins: Vec<PluginCall>,
+
+    /// Rhai script that must return true, e.g. `file.size > 1000 && file.ext == "pdf"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
 }
 
 impl Condition {
     /// Check if a file matches this condition
     pub fn matches(&self, path: &Path) -> Result<bool> {
+        self.check(path, None)
+    }
+
+    /// Check a folder as a unit: sizes are totals over its tree, age counts
+    /// from the last change anywhere inside, and `contains` looks at every level
+    pub fn matches_folder(&self, dir: &Path) -> Result<bool> {
+        if !dir.is_dir() {
+            return Ok(false);
+        }
+        match FolderSummary::scan(dir, &self.contains_patterns()?) {
+            Ok(summary) => self.check(dir, Some(&summary)),
+            Err(_) => Ok(false),
+        }
+    }
+
+    fn check(&self, path: &Path, folder: Option<&FolderSummary>) -> Result<bool> {
         // Check extension
         if let Some(ref ext) = self.extension
             && !check_extension(path, ext)
@@ -97,39 +177,43 @@ impl Condition {
             || self.age_days_greater_than.is_some()
             || self.age_days_less_than.is_some()
         {
-            let metadata = match path.metadata() {
-                Ok(m) => m,
-                Err(_) => return Ok(false),
+            let (size, age) = match folder {
+                Some(summary) => (summary.size, Some(summary.age_days())),
+                None => match path.metadata() {
+                    Ok(metadata) => (
+                        metadata.len(),
+                        metadata.modified().ok().map(|modified| {
+                            modified.elapsed().map(|d| d.as_secs() / 86400).unwrap_or(0)
+                        }),
+                    ),
+                    Err(_) => return Ok(false),
+                },
             };
 
             if let Some(min) = self.size_greater_than
-                && metadata.len() <= min
+                && size <= min
             {
                 return Ok(false);
             }
             if let Some(max) = self.size_less_than
-                && metadata.len() >= max
+                && size >= max
             {
                 return Ok(false);
             }
 
             if self.age_days_greater_than.is_some() || self.age_days_less_than.is_some() {
-                match metadata.modified() {
-                    Ok(modified) => {
-                        let age = modified.elapsed().map(|d| d.as_secs() / 86400).unwrap_or(0);
-
-                        if let Some(min_days) = self.age_days_greater_than
-                            && age <= min_days
-                        {
-                            return Ok(false);
-                        }
-                        if let Some(max_days) = self.age_days_less_than
-                            && age >= max_days
-                        {
-                            return Ok(false);
-                        }
-                    }
-                    Err(_) => return Ok(false),
+                let Some(age) = age else {
+                    return Ok(false);
+                };
+                if let Some(min_days) = self.age_days_greater_than
+                    && age <= min_days
+                {
+                    return Ok(false);
+                }
+                if let Some(max_days) = self.age_days_less_than
+                    && age >= max_days
+                {
+                    return Ok(false);
                 }
             }
         }
@@ -150,8 +234,537 @@ impl Condition {
             }
         }
 
+        // Check folder contents (files never match)
+        if !self.contains.is_empty() {
+            let found = match folder {
+                Some(summary) => summary.contains_match,
+                None => {
+                    path.is_dir()
+                        && FolderSummary::scan(path, &self.contains_patterns()?)
+                            .is_ok_and(|summary| summary.contains_match)
+                }
+            };
+            if !found {
+                return Ok(false);
+            }
+        }
+
+        // Check media metadata (probed once, only if needed)
+        if self.has_media_conditions() {
+            let Some(info) = MediaInfo::probe(path) else {
+                return Ok(false);
+            };
+            if !self.media_matches(&info) {
+                return Ok(false);
+            }
+        }
+
+        // Check the creating application (looked up once, only if needed)
+        if !self.created_by.is_empty() || !self.not_created_by.is_empty() {
+            let creator = origin::creator_app(path);
+            if !self.creator_matches(creator.as_deref()) {
+                return Ok(false);
+            }
+        }
+
+        // A script (or plugin) that fails doesn't match, like a file that
+        // can't be probed
+        if let Some(ref source) = self.script {
+            match script::matches(source, path) {
+                Ok(true) => {}
+                Ok(false) => return Ok(false),
+                Err(e) => {
+                    warn!("{}: {:#}", path.display(), e);
+                    return Ok(false);
+                }
+            }
+        }
+
+        // Ask plugins last, as each check starts a program
+        for plugin in &self.plugins {
+            match plugin.matches(path) {
+                Ok(true) => {}
+                Ok(false) => return Ok(false),
+                Err(e) => {
+                    warn!("{}: {:#}", path.display(), e);
+                    return Ok(false);
+                }
+            }
+        }
+
         Ok(true)
     }
+
+    /// Check every set condition against a file (or, with `folder`, a folder
+    /// as a unit) without stopping at the first mismatch, along with what
+    /// the file actually has. All checks pass exactly when `matches` (or
+    /// `matches_folder`) would.
+    pub fn explain(&self, path: &Path, folder: bool) -> Vec<ConditionCheck> {
+        let mut checks = Vec::new();
+        let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let extension = match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => ext.to_string(),
+            None => "(none)".to_string(),
+        };
+
+        let summary = if folder {
+            let summary = if path.is_dir() {
+                self.contains_patterns()
+                    .ok()
+                    .and_then(|patterns| FolderSummary::scan(path, &patterns).ok())
+            } else {
+                None
+            };
+            if summary.is_none() {
+                checks.push(ConditionCheck::new(
+                    "folder",
+                    "a readable folder".to_string(),
+                    if path.is_dir() {
+                        "can't be read".to_string()
+                    } else {
+                        "not a folder".to_string()
+                    },
+                    false,
+                ));
+            }
+            summary
+        } else {
+            None
+        };
+
+        if let Some(ref ext) = self.extension {
+            checks.push(ConditionCheck::new(
+                "extension",
+                ext.clone(),
+                extension.clone(),
+                check_extension(path, ext),
+            ));
+        }
+        if !self.extensions.is_empty() {
+            checks.push(ConditionCheck::new(
+                "extensions",
+                self.extensions.join(", "),
+                extension,
+                self.extensions.iter().any(|ext| check_extension(path, ext)),
+            ));
+        }
+        if let Some(ref pattern) = self.name_matches {
+            let (actual, passed) = match check_glob(path, pattern) {
+                Ok(passed) => (filename.to_string(), passed),
+                Err(e) => (format!("invalid pattern: {}", e), false),
+            };
+            checks.push(ConditionCheck::new(
+                "name_matches",
+                pattern.clone(),
+                actual,
+                passed,
+            ));
+        }
+        if let Some(ref pattern) = self.name_regex {
+            let (actual, passed) = match check_regex(path, pattern) {
+                Ok(passed) => (filename.to_string(), passed),
+                Err(e) => (format!("invalid pattern: {}", e), false),
+            };
+            checks.push(ConditionCheck::new(
+                "name_regex",
+                pattern.clone(),
+                actual,
+                passed,
+            ));
+        }
+
+        if self.size_greater_than.is_some()
+            || self.size_less_than.is_some()
+            || self.age_days_greater_than.is_some()
+            || self.age_days_less_than.is_some()
+        {
+            let (size, age) = match (&summary, folder) {
+                (Some(summary), _) => (Some(summary.size), Some(summary.age_days())),
+                (None, true) => (None, None),
+                (None, false) => match path.metadata() {
+                    Ok(metadata) => (
+                        Some(metadata.len()),
+                        metadata.modified().ok().map(|modified| {
+                            modified.elapsed().map(|d| d.as_secs() / 86400).unwrap_or(0)
+                        }),
+                    ),
+                    Err(_) => (None, None),
+                },
+            };
+            let missing = "can't be read";
+            checks.extend(bound(
+                "size_greater_than",
+                self.size_greater_than,
+                size,
+                true,
+                " bytes",
+                missing,
+            ));
+            checks.extend(bound(
+                "size_less_than",
+                self.size_less_than,
+                size,
+                false,
+                " bytes",
+                missing,
+            ));
+            checks.extend(bound(
+                "age_days_greater_than",
+                self.age_days_greater_than,
+                age,
+                true,
+                " days",
+                missing,
+            ));
+            checks.extend(bound(
+                "age_days_less_than",
+                self.age_days_less_than,
+                age,
+                false,
+                " days",
+                missing,
+            ));
+        }
+
+        if let Some(is_dir) = self.is_directory {
+            checks.push(ConditionCheck::new(
+                "is_directory",
+                is_dir.to_string(),
+                path.is_dir().to_string(),
+                path.is_dir() == is_dir,
+            ));
+        }
+        if let Some(is_hidden) = self.is_hidden {
+            let hidden = filename.starts_with('.');
+            checks.push(ConditionCheck::new(
+                "is_hidden",
+                is_hidden.to_string(),
+                hidden.to_string(),
+                hidden == is_hidden,
+            ));
+        }
+
+        if !self.contains.is_empty() {
+            let (actual, passed) = match (&summary, self.contains_patterns()) {
+                (_, Err(e)) => (format!("invalid pattern: {}", e), false),
+                (Some(summary), _) => found(summary.contains_match),
+                (None, _) if folder => ("can't be read".to_string(), false),
+                (None, Ok(_)) if !path.is_dir() => ("not a folder".to_string(), false),
+                (None, Ok(patterns)) => found(
+                    FolderSummary::scan(path, &patterns)
+                        .is_ok_and(|summary| summary.contains_match),
+                ),
+            };
+            checks.push(ConditionCheck::new(
+                "contains",
+                self.contains.join(", "),
+                actual,
+                passed,
+            ));
+        }
+
+        if self.has_media_conditions() {
+            let info = MediaInfo::probe(path);
+            let missing = if info.is_some() {
+                "unknown"
+            } else {
+                "not a media file"
+            };
+            let info = info.as_ref();
+            let duration = info.and_then(|i| i.duration_secs);
+            let width = info.and_then(|i| i.width);
+            let height = info.and_then(|i| i.height);
+            let framerate = info.and_then(|i| i.framerate);
+            checks.extend(
+                [
+                    bound(
+                        "duration_secs_greater_than",
+                        self.duration_secs_greater_than,
+                        duration,
+                        true,
+                        "s",
+                        missing,
+                    ),
+                    bound(
+                        "duration_secs_less_than",
+                        self.duration_secs_less_than,
+                        duration,
+                        false,
+                        "s",
+                        missing,
+                    ),
+                    bound(
+                        "width_greater_than",
+                        self.width_greater_than,
+                        width,
+                        true,
+                        " px",
+                        missing,
+                    ),
+                    bound(
+                        "width_less_than",
+                        self.width_less_than,
+                        width,
+                        false,
+                        " px",
+                        missing,
+                    ),
+                    bound(
+                        "height_greater_than",
+                        self.height_greater_than,
+                        height,
+                        true,
+                        " px",
+                        missing,
+                    ),
+                    bound(
+                        "height_less_than",
+                        self.height_less_than,
+                        height,
+                        false,
+                        " px",
+                        missing,
+                    ),
+                    bound(
+                        "framerate_greater_than",
+                        self.framerate_greater_than,
+                        framerate,
+                        true,
+                        " fps",
+                        missing,
+                    ),
+                    bound(
+                        "framerate_less_than",
+                        self.framerate_less_than,
+                        framerate,
+                        false,
+                        " fps",
+                        missing,
+                    ),
+                ]
+                .into_iter()
+                .flatten(),
+            );
+            if let Some(ref codec) = self.codec {
+                let (actual, passed) = match info {
+                    Some(info) if !info.codecs.is_empty() => (
+                        info.codecs.join(", "),
+                        info.codecs.iter().any(|c| c.eq_ignore_ascii_case(codec)),
+                    ),
+                    _ => (missing.to_string(), false),
+                };
+                checks.push(ConditionCheck::new("codec", codec.clone(), actual, passed));
+            }
+        }
+
+        if !self.created_by.is_empty() || !self.not_created_by.is_empty() {
+            let creator = origin::creator_app(path);
+            let actual = creator.clone().unwrap_or_else(|| "unknown".to_string());
+            let made_by = |apps: &[String]| {
+                creator
+                    .as_deref()
+                    .is_some_and(|c| apps.iter().any(|app| origin::creator_matches(c, app)))
+            };
+            if !self.created_by.is_empty() {
+                checks.push(ConditionCheck::new(
+                    "created_by",
+                    self.created_by.join(", "),
+                    actual.clone(),
+                    made_by(&self.created_by),
+                ));
+            }
+            if !self.not_created_by.is_empty() {
+                checks.push(ConditionCheck::new(
+                    "not_created_by",
+                    self.not_created_by.join(", "),
+                    actual,
+                    !made_by(&self.not_created_by),
+                ));
+            }
+        }
+
+        if let Some(ref source) = self.script {
+            let (actual, passed) = match script::matches(source, path) {
+                Ok(true) => ("true".to_string(), true),
+                Ok(false) => ("false".to_string(), false),
+                Err(e) => (format!("{:#}", e), false),
+            };
+            checks.push(ConditionCheck::new(
+                "script",
+                source.trim().to_string(),
+                actual,
+                passed,
+            ));
+        }
+        for plugin in &self.plugins {
+            let options = plugin
+                .options
+                .iter()
+                .map(|(key, value)| format!("{} = {}", key, value))
+                .collect::<Vec<_>>();
+            let (actual, passed) = match plugin.matches(path) {
+                Ok(true) => ("matches".to_string(), true),
+                Ok(false) => ("doesn't match".to_string(), false),
+                Err(e) => (format!("{:#}", e), false),
+            };
+            checks.push(ConditionCheck::new(
+                "plugins",
+                format!("{} ({})", plugin.name, options.join(", ")),
+                actual,
+                passed,
+            ));
+        }
+
+        checks
+    }
+
+    /// Check that the glob and regex patterns compile
+    pub fn validate(&self) -> Result<()> {
+        if let Some(pattern) = &self.name_matches {
+            glob::Pattern::new(pattern)
+                .with_context(|| format!("Invalid name_matches pattern '{}'", pattern))?;
+        }
+        if let Some(pattern) = &self.name_regex {
+            Regex::new(pattern)
+                .with_context(|| format!("Invalid name_regex pattern '{}'", pattern))?;
+        }
+        for pattern in &self.contains {
+            glob::Pattern::new(pattern)
+                .with_context(|| format!("Invalid contains pattern '{}'", pattern))?;
+        }
+        if let Some(source) = &self.script {
+            script::validate(source)?;
+        }
+        Ok(())
+    }
+
+    fn contains_patterns(&self) -> Result<Vec<glob::Pattern>> {
+        self.contains
+            .iter()
+            .map(|p| Ok(glob::Pattern::new(p)?))
+            .collect()
+    }
+
+    /// Check the creator-app conditions. An unknown creator never matches
+    /// `created_by`, and is never excluded by `not_created_by`.
+    fn creator_matches(&self, creator: Option<&str>) -> bool {
+        if !self.created_by.is_empty() {
+            let Some(creator) = creator else {
+                return false;
+            };
+            if !self
+                .created_by
+                .iter()
+                .any(|app| origin::creator_matches(creator, app))
+            {
+                return false;
+            }
+        }
+
+        match creator {
+            Some(creator) => !self
+                .not_created_by
+                .iter()
+                .any(|app| origin::creator_matches(creator, app)),
+            None => true,
+        }
+    }
+
+    /// Whether any video/audio metadata condition is set
+    fn has_media_conditions(&self) -> bool {
+        self.duration_secs_greater_than.is_some()
+            || self.duration_secs_less_than.is_some()
+            || self.width_greater_than.is_some()
+            || self.width_less_than.is_some()
+            || self.height_greater_than.is_some()
+            || self.height_less_than.is_some()
+            || self.codec.is_some()
+            || self.framerate_greater_than.is_some()
+            || self.framerate_less_than.is_some()
+    }
+
+    /// Check the media conditions against already-probed metadata.
+    /// A condition on a value the file doesn't have (e.g. width of an
+    /// audio file) doesn't match.
+    fn media_matches(&self, info: &MediaInfo) -> bool {
+        fn greater<T: PartialOrd>(value: Option<T>, min: Option<T>) -> bool {
+            match min {
+                Some(min) => value.is_some_and(|v| v > min),
+                None => true,
+            }
+        }
+        fn less<T: PartialOrd>(value: Option<T>, max: Option<T>) -> bool {
+            match max {
+                Some(max) => value.is_some_and(|v| v < max),
+                None => true,
+            }
+        }
+
+        greater(info.duration_secs, self.duration_secs_greater_than)
+            && less(info.duration_secs, self.duration_secs_less_than)
+            && greater(info.width, self.width_greater_than)
+            && less(info.width, self.width_less_than)
+            && greater(info.height, self.height_greater_than)
+            && less(info.height, self.height_less_than)
+            && greater(info.framerate, self.framerate_greater_than)
+            && less(info.framerate, self.framerate_less_than)
+            && self
+                .codec
+                .as_ref()
+                .is_none_or(|codec| info.codecs.iter().any(|c| c.eq_ignore_ascii_case(codec)))
+    }
+}
+
+/// One condition checked against a file, for the rule tester
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionCheck {
+    /// The condition's config key, e.g. `extension`
+    pub name: &'static str,
+    /// The configured value
+    pub expected: String,
+    /// What the file has
+    pub actual: String,
+    pub passed: bool,
+}
+
+impl ConditionCheck {
+    fn new(name: &'static str, expected: String, actual: String, passed: bool) -> Self {
+        Self {
+            name,
+            expected,
+            actual,
+            passed,
+        }
+    }
+}
+
+/// A `>` (`greater`) or `<` check of `value` against a configured bound,
+/// if one is set; `missing` describes a value the file doesn't have
+fn bound<T: PartialOrd + std::fmt::Display>(
+    name: &'static str,
+    limit: Option<T>,
+    value: Option<T>,
+    greater: bool,
+    unit: &str,
+    missing: &str,
+) -> Option<ConditionCheck> {
+    let limit = limit?;
+    let passed = value
+        .as_ref()
+        .is_some_and(|v| if greater { *v > limit } else { *v < limit });
+    Some(ConditionCheck::new(
+        name,
+        format!("{} {}{}", if greater { ">" } else { "<" }, limit, unit),
+        value
+            .map(|v| format!("{}{}", v, unit))
+            .unwrap_or_else(|| missing.to_string()),
+        passed,
+    ))
+}
+
+fn found(found: bool) -> (String, bool) {
+    let actual = if found { "found" } else { "not found" };
+    (actual.to_string(), found)
 }
 
 fn check_extension(path: &Path, ext: &str) -> bool {
@@ -238,4 +851,62 @@ mod tests {
         assert!(condition.matches(Path::new("/tmp/.hidden")).unwrap());
         assert!(!condition.matches(Path::new("/tmp/visible")).unwrap());
     }
+
+    #[test]
+    fn test_media_match() {
+        let condition = Condition {
+            duration_secs_less_than: Some(60.0),
+            width_greater_than: Some(1280),
+            codec: Some("H264".to_string()),
+            ..Default::default()
+        };
+
+        let clip = MediaInfo {
+            duration_secs: Some(15.0),
+            width: Some(1920),
+            height: Some(1080),
+            codecs: vec!["h264".to_string(), "aac".to_string()],
+            framerate: Some(60.0),
+        };
+        assert!(condition.media_matches(&clip));
+
+        let recording = MediaInfo {
+            duration_secs: Some(3600.0),
+            ..clip.clone()
+        };
+        assert!(!condition.media_matches(&recording));
+
+        let audio = MediaInfo {
+            width: None,
+            ..clip
+        };
+        assert!(!condition.media_matches(&audio));
+
+        // Files that can't be probed never match media conditions
+        assert!(
+            !condition
+                .matches(Path::new("/nonexistent/clip.mp4"))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_creator_match() {
+        let condition = Condition {
+            not_created_by: vec!["Visual Studio Code".to_string()],
+            ..Default::default()
+        };
+        assert!(!condition.creator_matches(Some("Visual Studio Code")));
+        assert!(condition.creator_matches(Some("Preview")));
+        assert!(condition.creator_matches(None));
+
+        let condition = Condition {
+            created_by: vec!["safari".to_string(), "chrome".to_string()],
+            ..Default::default()
+        };
+        assert!(condition.creator_matches(Some("Safari")));
+        assert!(condition.creator_matches(Some("Google Chrome")));
+        assert!(!condition.creator_matches(Some("Preview")));
+        assert!(!condition.creator_matches(None));
+    }
 }
@@ -1,20 +1,113 @@
 //! Rule engine - evaluates and executes rules
 
-use anyhow::Result;
-use std::path::Path;
-use tracing::{debug, info, trace};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+use tracing::{debug, error, info, trace};
+
+use super::{Action, ConditionCheck, Hook, ReimportMode, Rule};
+use crate::activity::ActivityKind;
+use crate::shadow::ShadowRules;
+use crate::stats::Counter;
+
+/// Why a rule would or wouldn't act on a file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// Its action would run
+    Selected,
+    Disabled,
+    /// Not in the watch's `rules` list
+    NotInWatch,
+    /// A folder rule, and the path is a file
+    FoldersOnly,
+    /// A folder rule whose destination is inside the folder
+    OwnDestination,
+    /// Some condition doesn't match
+    NoMatch,
+    /// It matches, but an earlier rule has `stop_processing`
+    Stopped {
+        by: String,
+    },
+}
 
-use super::{Action, Rule};
+/// A rule checked against a file, for the rule tester
+#[derive(Debug, Clone)]
+pub struct RuleExplanation {
+    pub rule: String,
+    /// The action's `type`, e.g. `move`
+    pub action: &'static str,
+    pub folder: bool,
+    pub verdict: Verdict,
+    /// Every set condition, passed or not
+    pub checks: Vec<ConditionCheck>,
+}
 
 /// Engine for evaluating rules against files
 pub struct RuleEngine {
     rules: Vec<Rule>,
+    hooks: Vec<Hook>,
+    shadow: Option<Box<ShadowRules>>,
+    /// Whether filed files are fingerprinted (some rule sets `on_reimport`)
+    fingerprints: bool,
+    /// Only log and notify what actions would do
+    dry_run: bool,
+    /// Files held back by quiet hours or a rule's active window
+    deferred: Mutex<IndexMap<PathBuf, Hold>>,
+    /// One lock per destination folder, so actions running in parallel
+    /// don't race for the same free name
+    destinations: Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>,
+}
+
+/// What a deferred file is waiting for
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Hold {
+    /// The end of quiet hours
+    quiet: bool,
+    /// The latest window of the rules it matched to open
+    until: Option<DateTime<Local>>,
+}
+
+impl Hold {
+    fn is_over(&self, now: DateTime<Local>, quiet: bool) -> bool {
+        !(self.quiet && quiet) && self.until.is_none_or(|until| now >= until)
+    }
 }
 
 impl RuleEngine {
     /// Create a new rule engine with the given rules
     pub fn new(rules: Vec<Rule>) -> Self {
-        Self { rules }
+        Self {
+            fingerprints: rules.iter().any(|r| r.on_reimport.is_some()),
+            rules,
+            hooks: Vec::new(),
+            shadow: None,
+            dry_run: false,
+            deferred: Mutex::new(IndexMap::new()),
+            destinations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set the destination hooks run after actions place a file
+    pub fn with_hooks(mut self, hooks: Vec<Hook>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Set candidate rules to evaluate in shadow before each file is processed
+    pub fn with_shadow(mut self, shadow: Option<ShadowRules>) -> Self {
+        self.shadow = shadow.map(Box::new);
+        self
+    }
+
+    /// Only log and notify what matched actions would do, without touching
+    /// any file
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
     }
 
     /// Evaluate rules for a file and return the first matching action
@@ -26,6 +119,9 @@ impl RuleEngine {
                 trace!("Skipping disabled rule: {}", rule.name);
                 continue;
             }
+            if rule.folder {
+                continue;
+            }
 
             if rule.condition.matches(path)? {
                 info!("Rule '{}' matched: {}", rule.name, path.display());
@@ -44,7 +140,7 @@ impl RuleEngine {
         let mut actions = Vec::new();
 
         for rule in &self.rules {
-            if !rule.enabled {
+            if !rule.enabled || rule.folder {
                 continue;
             }
 
@@ -75,7 +171,7 @@ impl RuleEngine {
                 );
                 let mut actions = Vec::new();
                 for rule in &self.rules {
-                    if !rule.enabled {
+                    if !rule.enabled || rule.folder {
                         continue;
                     }
                     if !names.iter().any(|n| n == &rule.name) {
@@ -96,42 +192,428 @@ impl RuleEngine {
         }
     }
 
+    /// Enabled (and allowed) rules that match, respecting `stop_processing`,
+    /// without logging each match
+    pub fn matching_rules(
+        &self,
+        path: &Path,
+        allowed_rules: Option<&[String]>,
+    ) -> Result<Vec<&Rule>> {
+        let mut matched = Vec::new();
+        for rule in self
+            .enabled_rules()
+            .filter(|r| applies(r, allowed_rules, false))
+        {
+            if rule.condition.matches(path)? {
+                matched.push(rule);
+                if rule.stop_processing {
+                    break;
+                }
+            }
+        }
+        Ok(matched)
+    }
+
+    /// Check every rule against a path, in order, saying which would act on
+    /// it and why the others wouldn't. Folder rules are checked against
+    /// directories, the same way the watcher does.
+    pub fn explain(&self, path: &Path, allowed_rules: Option<&[String]>) -> Vec<RuleExplanation> {
+        let is_dir = path.is_dir();
+        // File and folder rules are evaluated separately, so stop separately
+        let mut stopped_by: [Option<&str>; 2] = [None, None];
+        self.rules
+            .iter()
+            .map(|rule| {
+                let checks = if rule.folder && !is_dir {
+                    Vec::new()
+                } else {
+                    rule.condition.explain(path, rule.folder)
+                };
+                let stopped = &mut stopped_by[usize::from(rule.folder)];
+                let verdict = if !rule.enabled {
+                    Verdict::Disabled
+                } else if !applies(rule, allowed_rules, rule.folder) {
+                    Verdict::NotInWatch
+                } else if rule.folder && !is_dir {
+                    Verdict::FoldersOnly
+                } else if rule.folder && places_inside(&rule.action, path) {
+                    Verdict::OwnDestination
+                } else if !checks.iter().all(|c| c.passed) {
+                    Verdict::NoMatch
+                } else if let Some(by) = stopped {
+                    Verdict::Stopped { by: by.to_string() }
+                } else {
+                    if rule.stop_processing {
+                        *stopped = Some(&rule.name);
+                    }
+                    Verdict::Selected
+                };
+                RuleExplanation {
+                    rule: rule.name.clone(),
+                    action: rule.action.kind(),
+                    folder: rule.folder,
+                    verdict,
+                    checks,
+                }
+            })
+            .collect()
+    }
+
+    /// Whether any enabled (and allowed) rule matches, without logging each match
+    pub fn matches_any(&self, path: &Path, allowed_rules: Option<&[String]>) -> Result<bool> {
+        for rule in self
+            .enabled_rules()
+            .filter(|r| applies(r, allowed_rules, false))
+        {
+            if rule.condition.matches(path)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether any enabled (and allowed) rule matches whole folders
+    pub fn has_folder_rules(&self, allowed_rules: Option<&[String]>) -> bool {
+        self.enabled_rules()
+            .any(|r| applies(r, allowed_rules, true))
+    }
+
+    /// Enabled (and allowed) folder rules that match a folder as a unit,
+    /// respecting `stop_processing`
+    pub fn matching_folder_rules(
+        &self,
+        dir: &Path,
+        allowed_rules: Option<&[String]>,
+    ) -> Result<Vec<&Rule>> {
+        let mut matched = Vec::new();
+        for rule in self
+            .enabled_rules()
+            .filter(|r| applies(r, allowed_rules, true))
+        {
+            // e.g. the folder projects are filed into, inside the watch
+            if places_inside(&rule.action, dir) {
+                trace!("Skipping rule '{}' for its own destination", rule.name);
+                continue;
+            }
+            if rule.condition.matches_folder(dir)? {
+                matched.push(rule);
+                if rule.stop_processing {
+                    break;
+                }
+            }
+        }
+        Ok(matched)
+    }
+
+    /// Evaluate folder rules for a folder and return the matching actions
+    pub fn evaluate_folder(&self, dir: &Path) -> Result<Vec<Action>> {
+        Ok(self
+            .matching_folder_rules(dir, None)?
+            .into_iter()
+            .map(|rule| rule.action.clone())
+            .collect())
+    }
+
+    /// Evaluate folder rules for a folder and execute all matching actions,
+    /// counting each rule's result in the hourly statistics of `watch`
+    pub fn process_folder(
+        &self,
+        dir: &Path,
+        allowed_rules: Option<&[String]>,
+        watch: Option<&Path>,
+    ) -> Result<bool> {
+        let rules = self.matching_folder_rules(dir, allowed_rules)?;
+        if rules.is_empty() {
+            debug!("No folder rules matched for: {}", dir.display());
+            return Ok(false);
+        }
+        self.run_matched(dir, rules, watch)
+    }
+
     /// Evaluate filtered rules and execute all matching actions
     pub fn process_filtered(&self, path: &Path, allowed_rules: Option<&[String]>) -> Result<bool> {
-        let actions = self.evaluate_filtered(path, allowed_rules)?;
-        if actions.is_empty() {
+        self.process_in_watch(path, allowed_rules, None)
+    }
+
+    /// Like `process_filtered`, counting each rule's result in the hourly
+    /// statistics of `watch`
+    pub fn process_in_watch(
+        &self,
+        path: &Path,
+        allowed_rules: Option<&[String]>,
+        watch: Option<&Path>,
+    ) -> Result<bool> {
+        if let Some(ref shadow) = self.shadow {
+            shadow.compare(self, path, allowed_rules);
+        }
+        let rules = self.matching_rules(path, allowed_rules)?;
+        if rules.is_empty() {
+            debug!("No rules matched for: {}", path.display());
             return Ok(false);
         }
-        for action in &actions {
-            action.execute(path)?;
-            // After a destructive action, the file is gone — stop processing
-            if matches!(
-                action,
-                Action::Move { .. } | Action::Rename { .. } | Action::Trash | Action::Delete
-            ) {
+        self.run_matched(path, rules, watch)
+    }
+
+    /// Execute the actions of matched rules, unless quiet hours or a rule's
+    /// active window defer them. A deferred file waits for all of its rules,
+    /// so they still run in order.
+    fn run_matched(&self, path: &Path, rules: Vec<&Rule>, watch: Option<&Path>) -> Result<bool> {
+        let now = Local::now();
+        let quiet = rules.iter().any(|r| r.defer_in_quiet_hours) && crate::quiet_hours::is_quiet();
+        let mut until = None;
+        for rule in rules.iter().filter(|r| !r.is_active_at(now)) {
+            info!(
+                "Rule '{}' is outside its active hours: deferring {}",
+                rule.name,
+                path.display()
+            );
+            until = until.max(rule.next_active(now));
+        }
+        if quiet {
+            info!("Quiet hours: deferring {}", path.display());
+        }
+        if quiet || until.is_some() {
+            self.defer(path.to_path_buf(), Hold { quiet, until });
+            return Ok(true);
+        }
+        for rule in rules {
+            info!("Rule '{}' matched: {}", rule.name, path.display());
+            crate::activity::publish(ActivityKind::Matched {
+                path: path.to_path_buf(),
+                rule: rule.name.clone(),
+            });
+            let result = self.execute_rule(rule, path, watch);
+            let counter = match result {
+                Ok(_) => Some(Counter::Processed),
+                // The file vanished before the action ran; not a failure
+                // (unlike e.g. a missing command)
+                Err(ref e)
+                    if !path.exists()
+                        && e.downcast_ref::<std::io::Error>()
+                            .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound) =>
+                {
+                    None
+                }
+                Err(ref e) => {
+                    crate::activity::publish(ActivityKind::Failed {
+                        path: path.to_path_buf(),
+                        rule: rule.name.clone(),
+                        action: rule.action.kind().to_string(),
+                        error: format!("{:#}", e),
+                    });
+                    Some(Counter::Failed)
+                }
+            };
+            if let (Some(watch), Some(counter)) = (watch, counter)
+                && !self.dry_run
+            {
+                crate::stats::record(watch, &rule.name, counter);
+            }
+            if !result? {
                 break;
             }
         }
         Ok(true)
     }
 
+    /// Hold a file back until quiet hours end or a rule's window opens
+    fn defer(&self, path: PathBuf, hold: Hold) {
+        if let Ok(mut deferred) = self.deferred.lock() {
+            deferred.insert(path, hold);
+        }
+    }
+
+    /// Whether a file is waiting for quiet hours to end or a rule's window
+    pub fn is_deferred(&self, path: &Path) -> bool {
+        self.deferred
+            .lock()
+            .is_ok_and(|deferred| deferred.contains_key(path))
+    }
+
+    /// Take the deferred files whose wait is over, to be processed again
+    pub fn take_deferred(&self) -> Vec<PathBuf> {
+        let now = Local::now();
+        let quiet = crate::quiet_hours::is_quiet();
+        let Ok(mut deferred) = self.deferred.lock() else {
+            return Vec::new();
+        };
+        let (due, waiting): (IndexMap<_, _>, IndexMap<_, _>) = deferred
+            .drain(..)
+            .partition(|(_, hold)| hold.is_over(now, quiet));
+        *deferred = waiting;
+        due.into_keys().collect()
+    }
+
+    /// Take over the files another engine deferred (e.g. on config reload)
+    pub(crate) fn adopt_deferred(&self, old: &RuleEngine) {
+        let Ok(mut old) = old.deferred.lock() else {
+            return;
+        };
+        if let Ok(mut deferred) = self.deferred.lock() {
+            deferred.extend(old.drain(..));
+        }
+    }
+
     /// Evaluate rules and execute all matching actions
     pub fn process(&self, path: &Path) -> Result<bool> {
-        let actions = self.evaluate_all(path)?;
-        if actions.is_empty() {
-            return Ok(false);
+        self.process_filtered(path, None)
+    }
+
+    /// Run a matched rule's action, honoring its `on_reimport` setting.
+    /// Returns whether later rules can still act on the file.
+    fn execute_rule(&self, rule: &Rule, path: &Path, watch: Option<&Path>) -> Result<bool> {
+        let action = &rule.action;
+        if self.dry_run {
+            return Ok(dry_run(&rule.name, action, path));
         }
-        for action in &actions {
-            action.execute(path)?;
-            // After a destructive action, the file is gone — stop processing
-            if matches!(
-                action,
-                Action::Move { .. } | Action::Rename { .. } | Action::Trash | Action::Delete
-            ) {
-                break;
+        let files_away = action.destination().is_some();
+        let mut as_version = false;
+
+        if let Some(mode) = rule.on_reimport
+            && files_away
+            && path.is_file()
+            && let Some(copy) = crate::fingerprint::find_filed_copy(path)
+        {
+            match mode {
+                ReimportMode::Skip => {
+                    info!(
+                        "{} is identical to already organized {}, skipping",
+                        path.display(),
+                        copy.display()
+                    );
+                    return Ok(false);
+                }
+                ReimportMode::Replace => {
+                    info!(
+                        "{} is identical to already organized {}, replacing it",
+                        path.display(),
+                        copy.display()
+                    );
+                    std::fs::remove_file(&copy).with_context(|| {
+                        format!("Failed to remove earlier copy {}", copy.display())
+                    })?;
+                    crate::fingerprint::forget(&copy);
+                }
+                ReimportMode::Version => {
+                    info!(
+                        "{} is identical to already organized {}, keeping both",
+                        path.display(),
+                        copy.display()
+                    );
+                    as_version = true;
+                }
+            }
+        }
+
+        // Measured now, as the action may move the file
+        let size = watch
+            .and_then(|_| std::fs::metadata(path).ok())
+            .filter(|m| m.is_file())
+            .map_or(0, |m| m.len());
+
+        let lock = self.destination_lock(action, path);
+        let guard = lock
+            .as_deref()
+            .map(|l| l.lock().unwrap_or_else(PoisonError::into_inner));
+        let (landed, conflict) = record_action(&rule.name, action, path, || {
+            if let Some(result) = crate::sftp::execute(action, path) {
+                result
+            } else if as_version {
+                action.execute_as_version(path)
+            } else {
+                action.execute_to(path)
+            }
+        })?;
+        drop(guard);
+        crate::activity::publish(ActivityKind::Applied {
+            path: path.to_path_buf(),
+            rule: rule.name.clone(),
+            action: action.kind().to_string(),
+            destination: landed.clone(),
+        });
+        if let Some(notify) = &rule.notify
+            && notify.is_enabled()
+        {
+            let message = notify.message(rule, path, landed.as_deref(), conflict.as_deref());
+            crate::notifications::notify_organized(&rule.name, &message);
+        }
+        if let Some(landed) = landed {
+            if self.fingerprints && files_away && landed.is_file() {
+                crate::fingerprint::record_filed(&landed);
+            }
+            if let Some(watch) = watch
+                && landed != path
+                && matches!(
+                    action,
+                    Action::Move { .. } | Action::Copy { .. } | Action::Tier { .. }
+                )
+                && let Some(folder) = landed.parent()
+            {
+                crate::stats::record_filed(watch, &rule.name, size, folder);
+            }
+            self.run_hooks(&landed);
+        }
+        // After a destructive action, the file is gone — stop processing
+        Ok(!file_consumed(action, path))
+    }
+
+    /// Apply a watch's `unmatched_action` to a file no rule matched
+    pub fn process_unmatched(&self, path: &Path, action: &Action) -> Result<()> {
+        if self.dry_run {
+            dry_run("", action, path);
+            return Ok(());
+        }
+        info!(
+            "No rule matched {}, applying unmatched action",
+            path.display()
+        );
+        let lock = self.destination_lock(action, path);
+        let guard = lock
+            .as_deref()
+            .map(|l| l.lock().unwrap_or_else(PoisonError::into_inner));
+        let (landed, _) = record_action("", action, path, || {
+            match crate::sftp::execute(action, path) {
+                Some(result) => result,
+                None => action.execute_to(path),
+            }
+        })?;
+        drop(guard);
+        if let Some(landed) = landed {
+            self.run_hooks(&landed);
+        }
+        Ok(())
+    }
+
+    /// The lock for the folder an action puts a file in (None for actions
+    /// that don't place it anywhere). Holding it while the action runs keeps
+    /// two files from picking the same free name at once.
+    fn destination_lock(&self, action: &Action, path: &Path) -> Option<Arc<Mutex<()>>> {
+        let folder = action.preview_destination(path)?.parent()?.to_path_buf();
+        let mut locks = self
+            .destinations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // Forget folders no action is using
+        if locks.len() > 256 {
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+        }
+        Some(locks.entry(folder).or_default().clone())
+    }
+
+    /// Run the hooks whose destination a file landed in.
+    /// Hook failures are logged but don't fail the rule that placed the file.
+    pub fn run_hooks(&self, landed: &Path) {
+        for hook in self.hooks.iter().filter(|h| h.applies_to(landed)) {
+            info!("Running hook for {}: {}", landed.display(), hook.command);
+            if let Err(e) = hook.run(landed) {
+                error!(
+                    "Hook '{}' failed for {}: {}",
+                    hook.command,
+                    landed.display(),
+                    e
+                );
             }
         }
-        Ok(true)
     }
 
     /// Get all rules
@@ -139,6 +621,11 @@ impl RuleEngine {
         &self.rules
     }
 
+    /// Get destination hooks
+    pub fn hooks(&self) -> &[Hook] {
+        &self.hooks
+    }
+
     /// Get enabled rules only
     pub fn enabled_rules(&self) -> impl Iterator<Item = &Rule> {
         self.rules.iter().filter(|r| r.enabled)
@@ -146,6 +633,7 @@ impl RuleEngine {
 
     /// Add a rule
     pub fn add_rule(&mut self, rule: Rule) {
+        self.fingerprints |= rule.on_reimport.is_some();
         self.rules.push(rule);
     }
 
@@ -159,10 +647,121 @@ impl RuleEngine {
     }
 }
 
+/// Whether a rule is allowed for a watch and of the right kind (folder or file)
+fn applies(rule: &Rule, allowed_rules: Option<&[String]>, folder: bool) -> bool {
+    rule.folder == folder
+        && allowed_rules
+            .is_none_or(|names| names.is_empty() || names.iter().any(|n| n == &rule.name))
+}
+
+/// Whether an action would put its result inside `dir` (so `dir` can't be
+/// moved or archived by it)
+fn places_inside(action: &Action, dir: &Path) -> bool {
+    let destinations: Vec<PathBuf> = match action {
+        Action::Archive {
+            destination: Some(destination),
+            ..
+        } => vec![crate::expand_path(destination)],
+        Action::Tier { stages } => stages
+            .iter()
+            .map(|stage| crate::expand_path(&stage.destination))
+            .collect(),
+        _ => action.destination().into_iter().collect(),
+    };
+    destinations.into_iter().any(|dest| {
+        std::fs::canonicalize(&dest)
+            .unwrap_or(dest)
+            .starts_with(dir)
+    })
+}
+
+/// Run an action on a file and record it in the history, whether it
+/// worked or not. Returns where the file landed and how a name collision
+/// at the destination was resolved, if there was one.
+fn record_action(
+    rule: &str,
+    action: &Action,
+    path: &Path,
+    run: impl FnOnce() -> Result<Option<PathBuf>>,
+) -> Result<(Option<PathBuf>, Option<String>)> {
+    // Nothing is left to hash after these
+    let before = matches!(action, Action::Trash | Action::Delete)
+        .then(|| crate::history::checksum(path))
+        .flatten();
+    // Left over from an action that failed before it could be recorded
+    let _ = super::take_conflict();
+    let _ = super::take_verified();
+    let started = Instant::now();
+    let result = run();
+    crate::logfile::log_action(rule, action, path, &result, started.elapsed());
+    let conflict = super::take_conflict();
+    // A verified move across filesystems already hashed the file
+    let verified = super::take_verified();
+    if crate::history::is_recorded(action, &result) {
+        let checksum = before.or(verified).or_else(|| match &result {
+            Ok(Some(landed)) if !path.exists() => crate::history::checksum(landed),
+            _ => crate::history::checksum(path),
+        });
+        crate::history::record(
+            rule,
+            action,
+            path,
+            &result,
+            checksum.as_deref(),
+            conflict.as_deref(),
+        );
+    }
+    result.map(|landed| (landed, conflict))
+}
+
+/// Log and notify what an action would do to a file, for a dry run.
+/// Returns whether later rules could still act on the file.
+fn dry_run(rule: &str, action: &Action, path: &Path) -> bool {
+    let destination = action.preview_destination(path);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let (logged, notified) = match &destination {
+        Some(destination) => (
+            format!(
+                "{} {} → {}",
+                action.kind(),
+                path.display(),
+                destination.display()
+            ),
+            format!("{} {} → {}", action.kind(), name, destination.display()),
+        ),
+        None => (
+            format!("{} {}", action.kind(), path.display()),
+            format!("{} {}", action.kind(), name),
+        ),
+    };
+    if rule.is_empty() {
+        info!("[dry-run] Unmatched action would {}", logged);
+    } else {
+        info!("[dry-run] Rule '{}' would {}", rule, logged);
+        crate::notifications::notify_organized(rule, &format!("Dry run: would {}", notified));
+    }
+    match action {
+        Action::Move { .. } | Action::Rename { .. } | Action::Trash | Action::Delete => false,
+        Action::Tier { .. } => destination.is_none(),
+        _ => true,
+    }
+}
+
+/// Whether an action moved or removed the file, so later actions can't run on it
+fn file_consumed(action: &Action, path: &Path) -> bool {
+    match action {
+        Action::Move { .. } | Action::Rename { .. } | Action::Trash | Action::Delete => true,
+        // A tier only moves the file once a stage is due, and a script or
+        // plugin may or may not move it
+        Action::Tier { .. } | Action::Script { .. } | Action::Plugin(_) => !path.exists(),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::rules::Condition;
+    use crate::rules::{Condition, OnConflict};
     use std::path::PathBuf;
 
     #[test]
@@ -176,7 +775,8 @@ mod tests {
             Action::Move {
                 destination: PathBuf::from("/tmp/pdfs"),
                 create_destination: true,
-                overwrite: false,
+                on_conflict: OnConflict::Fail,
+                preserve_metadata: true,
             },
         )];
 
@@ -200,6 +800,13 @@ mod tests {
             },
             action: Action::Delete,
             stop_processing: false,
+            on_reimport: None,
+            defer_in_quiet_hours: false,
+            active_hours: None,
+            active_days: Default::default(),
+            folder: false,
+            notify_limit: None,
+            notify: None,
         }];
 
         let engine = RuleEngine::new(rules);
@@ -220,7 +827,8 @@ mod tests {
                 Action::Move {
                     destination: PathBuf::from("/tmp/pdfs"),
                     create_destination: true,
-                    overwrite: false,
+                    on_conflict: OnConflict::Fail,
+                    preserve_metadata: true,
                 },
             ),
             Rule::new(
@@ -232,7 +840,8 @@ mod tests {
                 Action::Move {
                     destination: PathBuf::from("/tmp/images"),
                     create_destination: true,
-                    overwrite: false,
+                    on_conflict: OnConflict::Fail,
+                    preserve_metadata: true,
                 },
             ),
         ];
@@ -265,4 +874,35 @@ mod tests {
             .unwrap();
         assert!(!result.is_empty());
     }
+
+    #[test]
+    fn test_dry_run_touches_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("report.pdf");
+        std::fs::write(&file, "x").unwrap();
+        let pdfs = Condition {
+            extension: Some("pdf".to_string()),
+            ..Default::default()
+        };
+        let engine = RuleEngine::new(vec![
+            Rule::new(
+                "PDFs",
+                pdfs.clone(),
+                Action::Move {
+                    destination: dir.path().join("docs"),
+                    create_destination: true,
+                    on_conflict: OnConflict::Fail,
+                    preserve_metadata: true,
+                },
+            ),
+            Rule::new("Trash", pdfs, Action::Trash),
+        ])
+        .with_dry_run(true);
+
+        assert!(engine.process(&file).unwrap());
+        assert!(file.exists());
+        assert!(!dir.path().join("docs").exists());
+        engine.process_unmatched(&file, &Action::Delete).unwrap();
+        assert!(file.exists());
+    }
 }
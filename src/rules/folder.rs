@@ -0,0 +1,99 @@
+//! Whole-folder summaries for folder rules
+//!
+//! Rules with `folder = true` look at a folder as one unit: its size is the
+//! total of everything inside, its age runs from the last change anywhere in
+//! the tree, and `contains` looks for marker files at any depth.
+
+use anyhow::Result;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Totals for a folder tree (symlinks aren't followed)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FolderSummary {
+    /// Total size of the files inside, in bytes
+    pub size: u64,
+    /// Number of files inside
+    pub files: u64,
+    /// Latest modification of the folder or anything inside
+    pub modified: Option<SystemTime>,
+    /// Whether an entry's name matched one of the scanned patterns
+    pub contains_match: bool,
+}
+
+impl FolderSummary {
+    /// Walk a folder, also checking entry names against `patterns`
+    pub fn scan(dir: &Path, patterns: &[glob::Pattern]) -> Result<Self> {
+        let options = glob::MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+        let mut summary = Self {
+            size: 0,
+            files: 0,
+            modified: std::fs::metadata(dir)?.modified().ok(),
+            contains_match: false,
+        };
+        let mut stack = vec![dir.to_path_buf()];
+        while let Some(dir) = stack.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                if !summary.contains_match {
+                    summary.contains_match =
+                        patterns.iter().any(|p| p.matches_with(&name, options));
+                }
+                if let Ok(modified) = metadata.modified() {
+                    summary.modified = summary.modified.max(Some(modified));
+                }
+                if metadata.is_dir() {
+                    stack.push(entry.path());
+                } else if metadata.is_file() {
+                    summary.size += metadata.len();
+                    summary.files += 1;
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Whole days since anything in the folder changed
+    pub fn age_days(&self) -> u64 {
+        self.modified
+            .and_then(|m| m.elapsed().ok())
+            .map(|d| d.as_secs() / 86400)
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folder_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(project.join("src/nested")).unwrap();
+        std::fs::write(project.join("Cargo.toml"), "[package]").unwrap();
+        std::fs::write(project.join("src/nested/lib.rs"), "fn main() {}").unwrap();
+
+        let patterns = vec![glob::Pattern::new("*.RS").unwrap()];
+        let summary = FolderSummary::scan(&project, &patterns).unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.size, 9 + 12);
+        assert!(summary.contains_match);
+        assert_eq!(summary.age_days(), 0);
+
+        let patterns = vec![glob::Pattern::new("package.json").unwrap()];
+        assert!(
+            !FolderSummary::scan(&project, &patterns)
+                .unwrap()
+                .contains_match
+        );
+    }
+}
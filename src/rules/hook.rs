@@ -0,0 +1,80 @@
+//! Destination hooks - commands run when files land in a folder
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::Action;
+
+/// A command run whenever a rule moves, copies, renames or archives a file
+/// into a destination folder (e.g. to trigger an indexer)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Hook {
+    /// Folder that triggers the hook
+    pub destination: PathBuf,
+
+    /// Also trigger for files landing in subfolders
+    #[serde(default)]
+    pub recursive: bool,
+
+    /// Command to execute
+    pub command: String,
+
+    /// Arguments (supports pattern variables, expanded for the landed file)
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl Hook {
+    /// Whether a file that landed at `path` is in this hook's destination
+    pub fn applies_to(&self, path: &Path) -> bool {
+        let destination = crate::expand_path(&self.destination);
+        let destination = std::fs::canonicalize(&destination).unwrap_or(destination);
+        let Some(parent) = path.parent() else {
+            return false;
+        };
+        let parent = std::fs::canonicalize(parent).unwrap_or_else(|_| parent.to_path_buf());
+
+        if self.recursive {
+            parent.starts_with(&destination)
+        } else {
+            parent == destination
+        }
+    }
+
+    /// Run the hook for a landed file (same behavior and timeout as a `run` action)
+    pub fn run(&self, path: &Path) -> Result<()> {
+        Action::Run {
+            command: self.command.clone(),
+            args: self.args.clone(),
+        }
+        .execute(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_applies_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let inbox = dir.path().join("inbox");
+        std::fs::create_dir_all(inbox.join("sub")).unwrap();
+
+        let mut hook = Hook {
+            destination: inbox.clone(),
+            recursive: false,
+            command: "true".to_string(),
+            args: Vec::new(),
+        };
+
+        assert!(hook.applies_to(&inbox.join("a.pdf")));
+        assert!(!hook.applies_to(&inbox.join("sub").join("a.pdf")));
+        assert!(!hook.applies_to(&dir.path().join("a.pdf")));
+
+        hook.recursive = true;
+        assert!(hook.applies_to(&inbox.join("sub").join("a.pdf")));
+    }
+}
@@ -0,0 +1,176 @@
+//! Media metadata probing for video/audio conditions
+//!
+//! Uses `ffprobe` (part of FFmpeg) to read duration, resolution, codecs
+//! and frame rate. If `ffprobe` is not installed, or the file isn't a
+//! media file, probing returns `None` and media conditions don't match.
+
+use serde::Deserialize;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+/// Metadata extracted from a media file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaInfo {
+    /// Duration in seconds
+    pub duration_secs: Option<f64>,
+    /// Width of the first video stream (pixels)
+    pub width: Option<u32>,
+    /// Height of the first video stream (pixels)
+    pub height: Option<u32>,
+    /// Codec names of all streams (e.g. "h264", "aac")
+    pub codecs: Vec<String>,
+    /// Frame rate of the first video stream (frames per second)
+    pub framerate: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeOutput {
+    #[serde(default)]
+    streams: Vec<FfprobeStream>,
+    #[serde(default)]
+    format: Option<FfprobeFormat>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    duration: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeFormat {
+    duration: Option<String>,
+}
+
+impl MediaInfo {
+    /// Probe a file with `ffprobe`. Returns `None` if the file can't be probed.
+    pub fn probe(path: &Path) -> Option<Self> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v",
+                "quiet",
+                "-print_format",
+                "json",
+                "-show_format",
+                "-show_streams",
+            ])
+            .arg(path)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+
+        match output {
+            Ok(output) if output.status.success() => Self::from_ffprobe_json(&output.stdout),
+            Ok(_) => {
+                debug!("ffprobe could not read {}", path.display());
+                None
+            }
+            Err(e) => {
+                debug!(
+                    "Failed to run ffprobe ({}), media conditions won't match",
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Parse the JSON output of `ffprobe -show_format -show_streams`.
+    pub fn from_ffprobe_json(json: &[u8]) -> Option<Self> {
+        let parsed: FfprobeOutput = serde_json::from_slice(json).ok()?;
+        if parsed.streams.is_empty() {
+            return None;
+        }
+
+        let video = parsed
+            .streams
+            .iter()
+            .find(|s| s.codec_type.as_deref() == Some("video"));
+
+        let duration_secs = parsed
+            .format
+            .as_ref()
+            .and_then(|f| f.duration.as_deref())
+            .or_else(|| parsed.streams.iter().find_map(|s| s.duration.as_deref()))
+            .and_then(|d| d.parse().ok());
+
+        let framerate = video.and_then(|v| {
+            v.avg_frame_rate
+                .as_deref()
+                .and_then(parse_frame_rate)
+                .or_else(|| v.r_frame_rate.as_deref().and_then(parse_frame_rate))
+        });
+
+        Some(Self {
+            duration_secs,
+            width: video.and_then(|v| v.width),
+            height: video.and_then(|v| v.height),
+            codecs: parsed
+                .streams
+                .iter()
+                .filter_map(|s| s.codec_name.clone())
+                .collect(),
+            framerate,
+        })
+    }
+}
+
+/// Parse an ffprobe frame rate like "30000/1001" or "25".
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let value = match rate.split_once('/') {
+        Some((num, den)) => {
+            let num: f64 = num.parse().ok()?;
+            let den: f64 = den.parse().ok()?;
+            if den == 0.0 {
+                return None;
+            }
+            num / den
+        }
+        None => rate.parse().ok()?,
+    };
+    (value > 0.0).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ffprobe_output() {
+        let json = br#"{
+            "streams": [
+                {"codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080,
+                 "avg_frame_rate": "30000/1001", "r_frame_rate": "30/1"},
+                {"codec_type": "audio", "codec_name": "aac"}
+            ],
+            "format": {"duration": "12.500000"}
+        }"#;
+
+        let info = MediaInfo::from_ffprobe_json(json).unwrap();
+        assert_eq!(info.duration_secs, Some(12.5));
+        assert_eq!(info.width, Some(1920));
+        assert_eq!(info.height, Some(1080));
+        assert_eq!(info.codecs, vec!["h264", "aac"]);
+        assert!((info.framerate.unwrap() - 29.97).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_parse_audio_only_and_invalid() {
+        let json =
+            br#"{"streams": [{"codec_type": "audio", "codec_name": "mp3", "duration": "180.0"}]}"#;
+        let info = MediaInfo::from_ffprobe_json(json).unwrap();
+        assert_eq!(info.duration_secs, Some(180.0));
+        assert_eq!(info.width, None);
+        assert_eq!(info.framerate, None);
+
+        assert!(MediaInfo::from_ffprobe_json(b"{}").is_none());
+        assert!(MediaInfo::from_ffprobe_json(b"not json").is_none());
+        assert_eq!(parse_frame_rate("0/0"), None);
+    }
+}
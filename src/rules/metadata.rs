@@ -0,0 +1,148 @@
+//! File metadata carried over to copies
+//!
+//! A copy gets the original's permissions but fresh timestamps and, on most
+//! platforms, none of its extended attributes, where macOS keeps Finder
+//! tags, the quarantine flag and where a download came from. Moves across
+//! filesystems are copies too, so without this a moved file would lose what
+//! Spotlight-based rules look at. What can't be carried over (a drive
+//! without extended attributes, say) is logged as a warning.
+
+use std::fs::{FileTimes, Metadata};
+use std::path::Path;
+use tracing::warn;
+
+/// Give `copy` the extended attributes, timestamps and permissions of
+/// `original`, and the same for everything inside a copied folder
+pub(crate) fn preserve(original: &Path, copy: &Path) {
+    let Ok(metadata) = std::fs::metadata(original) else {
+        return;
+    };
+    if metadata.is_dir()
+        && let Ok(read) = std::fs::read_dir(original)
+    {
+        for entry in read.flatten() {
+            preserve(&entry.path(), &copy.join(entry.file_name()));
+        }
+    }
+
+    #[cfg(unix)]
+    if let Err(e) = copy_xattrs(original, copy) {
+        warn!(
+            "Couldn't keep the extended attributes of {}: {}",
+            copy.display(),
+            e
+        );
+    }
+    // Permissions last: a read-only copy may refuse the rest
+    if let Err(e) = set_times(&metadata, copy) {
+        warn!("Couldn't keep the timestamps of {}: {}", copy.display(), e);
+    }
+    if let Err(e) = std::fs::set_permissions(copy, metadata.permissions()) {
+        warn!("Couldn't keep the permissions of {}: {}", copy.display(), e);
+    }
+}
+
+/// Copy each extended attribute; one the copy's filesystem refuses doesn't
+/// stop the rest
+#[cfg(unix)]
+fn copy_xattrs(original: &Path, copy: &Path) -> std::io::Result<()> {
+    let names = match xattr::list(original) {
+        Ok(names) => names,
+        Err(e) if e.kind() == std::io::ErrorKind::Unsupported => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let mut failed = None;
+    for name in names {
+        let result = xattr::get(original, &name).and_then(|value| match value {
+            Some(value) => xattr::set(copy, &name, &value),
+            None => Ok(()),
+        });
+        if let Err(e) = result {
+            failed.get_or_insert(std::io::Error::new(
+                e.kind(),
+                format!("{}: {}", name.to_string_lossy(), e),
+            ));
+        }
+    }
+    failed.map_or(Ok(()), Err)
+}
+
+/// Set the access, modification and (where the platform allows) creation
+/// times
+fn set_times(original: &Metadata, copy: &Path) -> std::io::Result<()> {
+    let mut times = FileTimes::new();
+    if let Ok(accessed) = original.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    if let Ok(modified) = original.modified() {
+        times = times.set_modified(modified);
+    }
+    #[cfg(target_os = "macos")]
+    if let Ok(created) = original.created() {
+        use std::os::macos::fs::FileTimesExt;
+        times = times.set_created(created);
+    }
+    #[cfg(windows)]
+    if let Ok(created) = original.created() {
+        use std::os::windows::fs::FileTimesExt;
+        times = times.set_created(created);
+    }
+
+    #[cfg(unix)]
+    let file = std::fs::File::open(copy)?;
+    #[cfg(not(unix))]
+    let file = std::fs::OpenOptions::new().write(true).open(copy)?;
+    file.set_times(times)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_preserve() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("album");
+        let copy = dir.path().join("copy");
+        std::fs::create_dir_all(original.join("disc 1")).unwrap();
+        std::fs::write(original.join("disc 1/track.flac"), "music").unwrap();
+        std::fs::create_dir_all(copy.join("disc 1")).unwrap();
+        std::fs::write(copy.join("disc 1/track.flac"), "music").unwrap();
+
+        let old = SystemTime::now() - Duration::from_secs(30 * 86400);
+        std::fs::File::open(original.join("disc 1/track.flac"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        #[cfg(unix)]
+        let tagged = xattr::set(
+            original.join("disc 1/track.flac"),
+            "user.hazelnut.tag",
+            b"Red",
+        )
+        .is_ok();
+        let mut readonly = std::fs::metadata(original.join("disc 1/track.flac"))
+            .unwrap()
+            .permissions();
+        readonly.set_readonly(true);
+        std::fs::set_permissions(original.join("disc 1/track.flac"), readonly).unwrap();
+
+        preserve(&original, &copy);
+        let copied = std::fs::metadata(copy.join("disc 1/track.flac")).unwrap();
+        assert_eq!(copied.modified().unwrap(), old);
+        assert!(copied.permissions().readonly());
+        // tmpfs and some other filesystems don't take user attributes
+        #[cfg(unix)]
+        if tagged {
+            assert_eq!(
+                xattr::get(copy.join("disc 1/track.flac"), "user.hazelnut.tag").unwrap(),
+                Some(b"Red".to_vec())
+            );
+        }
+        assert_eq!(
+            std::fs::metadata(&copy).unwrap().modified().unwrap(),
+            std::fs::metadata(&original).unwrap().modified().unwrap()
+        );
+    }
+}
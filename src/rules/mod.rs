@@ -3,15 +3,30 @@
 mod action;
 mod condition;
 mod engine;
+mod folder;
+mod hook;
+mod media;
+mod metadata;
+mod origin;
+mod script;
+mod window;
 
-pub use action::Action;
-pub use condition::Condition;
-pub use engine::RuleEngine;
+pub use action::{Action, OnConflict, TierStage, set_verify_moves, take_conflict, take_verified};
+pub(crate) use action::{copy_exact, expand_pattern, move_exact};
+pub use condition::{Condition, ConditionCheck};
+pub use engine::{RuleEngine, RuleExplanation, Verdict};
+pub use folder::FolderSummary;
+pub use hook::Hook;
+pub use media::MediaInfo;
+pub use window::{ActiveDays, ActiveHours};
 
+use chrono::{DateTime, Local};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 /// A rule that matches files and performs actions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Rule {
     /// Human-readable name
     pub name: String,
@@ -30,6 +45,110 @@ pub struct Rule {
     /// Stop processing further rules if this matches
     #[serde(default)]
     pub stop_processing: bool,
+
+    /// What to do when a file is identical to one a move or copy already filed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_reimport: Option<ReimportMode>,
+
+    /// Leave matched files alone during quiet hours (e.g. IO-heavy conversions)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub defer_in_quiet_hours: bool,
+
+    /// Only act between these times ("HH:MM-HH:MM", local time); files
+    /// matched outside them wait until the window opens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_hours: Option<ActiveHours>,
+
+    /// Only act on these days, e.g. `["mon-fri"]` (empty = every day)
+    #[serde(default, skip_serializing_if = "ActiveDays::is_empty")]
+    pub active_days: ActiveDays,
+
+    /// Match folders directly inside a watch as a unit instead of files:
+    /// conditions look at the whole folder and the action moves or
+    /// archives its tree at once, after it stops changing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub folder: bool,
+
+    /// At most this many error notifications from the rule, e.g. `"3/h"`;
+    /// the rest are summed up once the period is over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_limit: Option<crate::notifications::RateLimit>,
+
+    /// Notifications for this rule regardless of `notifications_enabled`:
+    /// `false` silences it, `true` or a message template also reports each
+    /// file it handles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify: Option<RuleNotify>,
+}
+
+/// A rule's `notify` setting
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum RuleNotify {
+    Enabled(bool),
+    /// Message for each file, e.g. `"Invoice {name} filed to {destination}"`
+    Template(String),
+}
+
+impl RuleNotify {
+    pub fn is_enabled(&self) -> bool {
+        *self != RuleNotify::Enabled(false)
+    }
+
+    /// Message for a file the rule handled: the template with the usual
+    /// pattern variables plus `{rule}`, `{destination}` (the folder it
+    /// landed in) and `{conflict}` (how a name collision was resolved)
+    pub fn message(
+        &self,
+        rule: &Rule,
+        path: &Path,
+        landed: Option<&Path>,
+        conflict: Option<&str>,
+    ) -> String {
+        let destination = landed
+            .filter(|landed| *landed != path)
+            .and_then(Path::parent)
+            .map(|dir| dir.display().to_string());
+        match self {
+            RuleNotify::Template(template) => {
+                let template = template
+                    .replace("{rule}", &rule.name)
+                    .replace("{destination}", destination.as_deref().unwrap_or_default())
+                    .replace("{conflict}", conflict.unwrap_or_default());
+                action::expand_pattern(&template, path).unwrap_or(template)
+            }
+            RuleNotify::Enabled(_) => {
+                let filename = path.file_name().unwrap_or_default().to_string_lossy();
+                let message = match destination {
+                    Some(destination) => {
+                        format!("Rule '{}': {} → {}", rule.name, filename, destination)
+                    }
+                    None => format!(
+                        "Rule '{}': {} ({})",
+                        rule.name,
+                        filename,
+                        rule.action.kind()
+                    ),
+                };
+                match conflict {
+                    Some(conflict) => format!("{} ({})", message, conflict),
+                    None => message,
+                }
+            }
+        }
+    }
+}
+
+/// Handling of a file byte-identical to one hazelnut already organized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReimportMode {
+    /// Leave the new file where it is
+    Skip,
+    /// Remove the earlier copy and file the new one
+    Replace,
+    /// File the new one next to the earlier copy under a numbered name
+    Version,
 }
 
 fn default_enabled() -> bool {
@@ -45,6 +164,91 @@ impl Rule {
             condition,
             action,
             stop_processing: false,
+            on_reimport: None,
+            defer_in_quiet_hours: false,
+            active_hours: None,
+            active_days: ActiveDays::default(),
+            folder: false,
+            notify_limit: None,
+            notify: None,
         }
     }
+
+    /// Whether `now` is inside the rule's active hours and days
+    pub fn is_active_at(&self, now: DateTime<Local>) -> bool {
+        window::is_active(self.active_hours.as_ref(), &self.active_days, now)
+    }
+
+    /// When the rule may next act (`now` if it's active)
+    pub fn next_active(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        window::next_active(self.active_hours.as_ref(), &self.active_days, now)
+    }
+}
+
+/// Rename rules whose names `existing` or an earlier rule in the list
+/// already has, by appending a number: `Screenshots (2)`
+pub fn unique_names(rules: Vec<Rule>, existing: &[Rule]) -> Vec<Rule> {
+    let mut taken: Vec<String> = existing.iter().map(|r| r.name.clone()).collect();
+    rules
+        .into_iter()
+        .map(|mut rule| {
+            let base = rule.name.clone();
+            let mut n = 2;
+            while taken.contains(&rule.name) {
+                rule.name = format!("{} ({})", base, n);
+                n += 1;
+            }
+            taken.push(rule.name.clone());
+            rule
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notify_message() {
+        let rule: Rule = toml::from_str(
+            r#"
+            name = "Invoices"
+            notify = "Invoice {name} filed to {destination}"
+            action = { type = "move", destination = "/srv/invoices" }
+            "#,
+        )
+        .unwrap();
+        let notify = rule.notify.clone().unwrap();
+        assert!(notify.is_enabled());
+        let path = Path::new("/home/me/Downloads/acme-0042.pdf");
+        let landed = Path::new("/srv/invoices/acme-0042.pdf");
+        assert_eq!(
+            notify.message(&rule, path, Some(landed), None),
+            "Invoice acme-0042 filed to /srv/invoices"
+        );
+        assert_eq!(
+            RuleNotify::Enabled(true).message(&rule, path, Some(landed), None),
+            "Rule 'Invoices': acme-0042.pdf → /srv/invoices"
+        );
+        assert_eq!(
+            RuleNotify::Enabled(true).message(
+                &rule,
+                path,
+                Some(landed),
+                Some("acme-0042.pdf exists, renamed to acme-0042 (1).pdf")
+            ),
+            "Rule 'Invoices': acme-0042.pdf → /srv/invoices (acme-0042.pdf exists, renamed to acme-0042 (1).pdf)"
+        );
+
+        let rule: Rule = toml::from_str(
+            r#"
+            name = "Quiet"
+            notify = false
+            action = { type = "delete" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rule.notify, Some(RuleNotify::Enabled(false)));
+        assert!(!rule.notify.unwrap().is_enabled());
+    }
 }
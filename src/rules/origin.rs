@@ -0,0 +1,73 @@
+//! File origin lookup for creator-app conditions
+//!
+//! On macOS, Spotlight records the application that created a file in the
+//! `kMDItemCreator` attribute, which `mdls` can read. Other platforms don't
+//! keep this information, so the creator is always unknown there.
+
+use std::path::Path;
+#[cfg(target_os = "macos")]
+use std::process::{Command, Stdio};
+#[cfg(target_os = "macos")]
+use tracing::debug;
+
+/// Name of the application that created a file, if known
+#[cfg(target_os = "macos")]
+pub fn creator_app(path: &Path) -> Option<String> {
+    let output = Command::new("mdls")
+        .args(["-raw", "-name", "kMDItemCreator"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            parse_mdls_value(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(_) => None,
+        Err(e) => {
+            debug!("Failed to run mdls ({}), creator is unknown", e);
+            None
+        }
+    }
+}
+
+/// Name of the application that created a file, if known
+#[cfg(not(target_os = "macos"))]
+pub fn creator_app(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Parse the output of `mdls -raw`, which prints `(null)` for missing attributes
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_mdls_value(raw: &str) -> Option<String> {
+    let value = raw.trim().trim_matches('"').trim();
+    if value.is_empty() || value == "(null)" {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Whether a creator name matches a configured app name
+/// (case-insensitive, so "code" matches "Visual Studio Code")
+pub fn creator_matches(creator: &str, app: &str) -> bool {
+    creator.to_lowercase().contains(&app.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mdls_value() {
+        assert_eq!(
+            parse_mdls_value("\"Visual Studio Code\""),
+            Some("Visual Studio Code".to_string())
+        );
+        assert_eq!(parse_mdls_value("(null)"), None);
+        assert_eq!(parse_mdls_value(""), None);
+        assert!(creator_matches("Visual Studio Code", "code"));
+        assert!(!creator_matches("Preview", "code"));
+    }
+}
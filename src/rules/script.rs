@@ -0,0 +1,312 @@
+//! Rhai scripts - rule conditions and actions written as code
+//!
+//! A script sees the file as a `file` map (`path`, `name`, `stem`, `ext`,
+//! `dir`, `size`, `modified`, `age_days`, `is_dir`, `is_hidden`) and can use
+//! path helpers (`join`, `parent`, `file_name`, `stem`, `extension`,
+//! `exists`, `is_dir`, `expand`) and `log`. Action scripts can also
+//! `move_to`, `copy_to` and `rename` the file. Scripts have no other access
+//! to the system, and are stopped once they run too long.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Whether a condition script returns true for a file
+pub fn matches(script: &str, path: &Path) -> Result<bool> {
+    imp::matches(script, path)
+}
+
+/// Run an action script on a file, returning where the file was last
+/// moved, copied or renamed to, if anywhere
+pub fn execute(script: &str, path: &Path) -> Result<Option<PathBuf>> {
+    imp::execute(script, path)
+}
+
+/// Check that a script compiles
+pub fn validate(script: &str) -> Result<()> {
+    imp::validate(script)
+}
+
+#[cfg(feature = "scripting")]
+mod imp {
+    use anyhow::{Result, anyhow};
+    use rhai::{AST, Dynamic, Engine, EvalAltResult, Map, Scope};
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+    use tracing::{debug, info};
+
+    use crate::rules::{Action, OnConflict};
+
+    /// Operations a script may run before it is stopped
+    const MAX_OPERATIONS: u64 = 1_000_000;
+
+    /// Compiled scripts kept per thread (cleared when it grows past this)
+    const CACHE_MAX_ENTRIES: usize = 100;
+
+    std::thread_local! {
+        static CONDITION_ENGINE: Engine = condition_engine();
+        static AST_CACHE: RefCell<HashMap<String, AST>> = RefCell::new(HashMap::new());
+    }
+
+    type ScriptResult<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+    /// An engine with the limits and functions every script gets
+    fn sandboxed() -> Engine {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(1 << 20)
+            .set_max_array_size(10_000)
+            .set_max_map_size(10_000)
+            .disable_symbol("eval")
+            // No `import`, which would read other scripts from disk
+            .set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new())
+            .on_print(|text| info!("[script] {}", text))
+            .on_debug(|text, _, _| debug!("[script] {}", text));
+
+        engine
+            .register_fn("log", |text: &str| info!("[script] {}", text))
+            .register_fn("join", |base: &str, name: &str| {
+                path_string(&Path::new(base).join(name))
+            })
+            .register_fn("parent", |path: &str| {
+                Path::new(path)
+                    .parent()
+                    .map(path_string)
+                    .unwrap_or_default()
+            })
+            .register_fn("file_name", |path: &str| {
+                os_string(Path::new(path).file_name())
+            })
+            .register_fn("stem", |path: &str| os_string(Path::new(path).file_stem()))
+            .register_fn("extension", |path: &str| {
+                os_string(Path::new(path).extension())
+            })
+            .register_fn("exists", |path: &str| {
+                crate::expand_path(Path::new(path)).exists()
+            })
+            .register_fn("is_dir", |path: &str| {
+                crate::expand_path(Path::new(path)).is_dir()
+            })
+            .register_fn("expand", |path: &str| {
+                path_string(&crate::expand_path(Path::new(path)))
+            });
+        engine
+    }
+
+    fn condition_engine() -> Engine {
+        sandboxed()
+    }
+
+    /// An engine whose `move_to`, `copy_to` and `rename` act on the file at
+    /// `current`, updating it (and `landed`) as it goes
+    fn action_engine(
+        current: Rc<RefCell<PathBuf>>,
+        landed: Rc<RefCell<Option<PathBuf>>>,
+    ) -> Engine {
+        let mut engine = sandboxed();
+        let run = move |action: Action, moves: bool| -> ScriptResult<String> {
+            let from = current.borrow().clone();
+            let to = action
+                .execute_to(&from)
+                .map_err(|e| format!("{:#}", e))?
+                .ok_or("The action didn't place the file anywhere")?;
+            if moves {
+                *current.borrow_mut() = to.clone();
+            }
+            *landed.borrow_mut() = Some(to.clone());
+            Ok(path_string(&to))
+        };
+        let run_move = run.clone();
+        let run_copy = run.clone();
+        engine
+            .register_fn("move_to", move |dir: &str| {
+                run_move(
+                    Action::Move {
+                        destination: dir.into(),
+                        create_destination: true,
+                        on_conflict: OnConflict::Fail,
+                        preserve_metadata: true,
+                    },
+                    true,
+                )
+            })
+            .register_fn("copy_to", move |dir: &str| {
+                run_copy(
+                    Action::Copy {
+                        destination: dir.into(),
+                        create_destination: true,
+                        on_conflict: OnConflict::Fail,
+                        preserve_metadata: true,
+                    },
+                    false,
+                )
+            })
+            .register_fn("rename", move |name: &str| {
+                run(
+                    Action::Rename {
+                        pattern: name.to_string(),
+                    },
+                    true,
+                )
+            });
+        engine
+    }
+
+    pub fn matches(script: &str, path: &Path) -> Result<bool> {
+        let ast = compiled(script)?;
+        let mut scope = file_scope(path);
+        CONDITION_ENGINE
+            .with(|engine| engine.eval_ast_with_scope::<bool>(&mut scope, &ast))
+            .map_err(|e| anyhow!("Condition script: {}", e))
+    }
+
+    pub fn execute(script: &str, path: &Path) -> Result<Option<PathBuf>> {
+        let ast = compiled(script)?;
+        let current = Rc::new(RefCell::new(path.to_path_buf()));
+        let landed = Rc::new(RefCell::new(None));
+        let engine = action_engine(current, landed.clone());
+        let mut scope = file_scope(path);
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| anyhow!("Action script: {}", e))?;
+        Ok(landed.take())
+    }
+
+    pub fn validate(script: &str) -> Result<()> {
+        compiled(script).map(|_| ())
+    }
+
+    fn compiled(script: &str) -> Result<AST> {
+        if let Some(ast) = AST_CACHE.with(|cache| cache.borrow().get(script).cloned()) {
+            return Ok(ast);
+        }
+        let ast = CONDITION_ENGINE
+            .with(|engine| engine.compile(script))
+            .map_err(|e| anyhow!("Invalid script: {}", e))?;
+        AST_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            if cache.len() >= CACHE_MAX_ENTRIES {
+                cache.clear();
+            }
+            cache.insert(script.to_string(), ast.clone());
+        });
+        Ok(ast)
+    }
+
+    /// A scope with the `file` map
+    fn file_scope(path: &Path) -> Scope<'static> {
+        let metadata = path.metadata().ok();
+        let modified = metadata
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok());
+        let age_days = metadata
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.elapsed().ok())
+            .map(|d| d.as_secs() / 86400);
+        let name = os_string(path.file_name());
+
+        let mut file = Map::new();
+        file.insert("path".into(), path_string(path).into());
+        file.insert("stem".into(), os_string(path.file_stem()).into());
+        file.insert("ext".into(), os_string(path.extension()).into());
+        file.insert(
+            "dir".into(),
+            path.parent().map(path_string).unwrap_or_default().into(),
+        );
+        file.insert("is_hidden".into(), name.starts_with('.').into());
+        file.insert("name".into(), name.into());
+        file.insert(
+            "size".into(),
+            Dynamic::from_int(metadata.as_ref().map_or(0, |m| m.len() as i64)),
+        );
+        file.insert(
+            "modified".into(),
+            Dynamic::from_int(modified.map_or(0, |d| d.as_secs() as i64)),
+        );
+        file.insert(
+            "age_days".into(),
+            Dynamic::from_int(age_days.map_or(0, |days| days as i64)),
+        );
+        file.insert("is_dir".into(), metadata.is_some_and(|m| m.is_dir()).into());
+
+        let mut scope = Scope::new();
+        scope.push_constant("file", file);
+        scope
+    }
+
+    fn path_string(path: &Path) -> String {
+        path.to_string_lossy().into_owned()
+    }
+
+    fn os_string(name: Option<&std::ffi::OsStr>) -> String {
+        name.map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+mod imp {
+    use anyhow::{Result, bail};
+    use std::path::{Path, PathBuf};
+
+    const DISABLED: &str =
+        "This build of hazelnut has no scripting (enable the `scripting` feature)";
+
+    pub fn matches(_script: &str, _path: &Path) -> Result<bool> {
+        bail!(DISABLED)
+    }
+
+    pub fn execute(_script: &str, _path: &Path) -> Result<Option<PathBuf>> {
+        bail!(DISABLED)
+    }
+
+    pub fn validate(_script: &str) -> Result<()> {
+        bail!(DISABLED)
+    }
+}
+
+#[cfg(all(test, feature = "scripting"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("Invoice-2024.pdf");
+        std::fs::write(&file, "12345").unwrap();
+
+        assert!(matches(r#"file.ext == "pdf" && file.size > 3"#, &file).unwrap());
+        assert!(!matches(r#"file.name.starts_with("Receipt")"#, &file).unwrap());
+        // Not a bool, a syntax error or a runaway loop are errors
+        assert!(matches("file.size", &file).is_err());
+        assert!(validate("file.size >").is_err());
+        assert!(matches("loop {}", &file).is_err());
+        // Nor can scripts load others
+        let module = dir.path().join("module.rhai");
+        std::fs::write(&module, "fn yes() { true }").unwrap();
+        let import = format!(r#"import "{}" as m; m::yes()"#, module.display());
+        assert!(matches(&import, &file).is_err());
+
+        let year = dir.path().join("2024");
+        let script = format!(
+            r#"
+            let year = file.stem.split("-")[1];
+            copy_to("{}");
+            rename(year + "-" + file.name);
+            move_to(join("{}", year));
+            "#,
+            dir.path().join("backup").display(),
+            dir.path().display(),
+        );
+        let landed = execute(&script, &file).unwrap();
+        assert_eq!(landed, Some(year.join("2024-Invoice-2024.pdf")));
+        assert!(dir.path().join("backup").join("Invoice-2024.pdf").exists());
+        assert!(!file.exists());
+    }
+}
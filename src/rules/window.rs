@@ -0,0 +1,218 @@
+//! Active windows - the hours and days a rule is allowed to act
+//!
+//! Files a rule matches outside its window are held back by the engine and
+//! processed once the window opens.
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone, Weekday};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Daily span of local time ("HH:MM-HH:MM"); an end earlier than the start
+/// means the span runs past midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+pub struct ActiveHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl ActiveHours {
+    /// Whether a time of day falls inside the span
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Whether `time` is in the part of the span after midnight
+    fn is_after_midnight(&self, time: NaiveTime) -> bool {
+        self.start > self.end && time < self.end
+    }
+}
+
+impl TryFrom<String> for ActiveHours {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        let (start, end) = s
+            .split_once('-')
+            .with_context(|| format!("invalid active_hours '{}', expected HH:MM-HH:MM", s))?;
+        let parse = |t: &str| {
+            NaiveTime::parse_from_str(t.trim(), "%H:%M")
+                .with_context(|| format!("invalid time '{}' in active_hours, expected HH:MM", t))
+        };
+        let (start, end) = (parse(start)?, parse(end)?);
+        if start == end {
+            bail!("active_hours '{}' is empty; leave it out to run all day", s);
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl From<ActiveHours> for String {
+    fn from(hours: ActiveHours) -> Self {
+        format!(
+            "{}-{}",
+            hours.start.format("%H:%M"),
+            hours.end.format("%H:%M")
+        )
+    }
+}
+
+/// Days of the week, e.g. `["sat", "sun"]` or `["mon-fri"]` (empty = every day)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct ActiveDays {
+    /// Bit per day, Monday first
+    days: u8,
+    /// As written, for saving the config back
+    source: Vec<String>,
+}
+
+impl ActiveDays {
+    pub fn is_empty(&self) -> bool {
+        self.source.is_empty()
+    }
+
+    /// Whether the rule may act on this day
+    pub fn contains(&self, day: Weekday) -> bool {
+        self.is_empty() || self.days & (1 << day.num_days_from_monday()) != 0
+    }
+}
+
+/// A day's bit number ("mon" or "monday", any case)
+fn parse_day(s: &str) -> Result<u32> {
+    s.trim()
+        .parse::<Weekday>()
+        .map(|day| day.num_days_from_monday())
+        .map_err(|_| anyhow::anyhow!("invalid day '{}', expected mon, tue, … sun", s.trim()))
+}
+
+impl TryFrom<Vec<String>> for ActiveDays {
+    type Error = anyhow::Error;
+
+    fn try_from(source: Vec<String>) -> Result<Self> {
+        let mut days = 0;
+        for entry in &source {
+            match entry.split_once('-') {
+                // A range like "mon-fri" or "fri-mon" (wrapping past Sunday)
+                Some((from, to)) => {
+                    let (from, to) = (parse_day(from)?, parse_day(to)?);
+                    let mut day = from;
+                    loop {
+                        days |= 1 << day;
+                        if day == to {
+                            break;
+                        }
+                        day = (day + 1) % 7;
+                    }
+                }
+                None => days |= 1 << parse_day(entry)?,
+            }
+        }
+        Ok(Self { days, source })
+    }
+}
+
+impl From<ActiveDays> for Vec<String> {
+    fn from(days: ActiveDays) -> Self {
+        days.source
+    }
+}
+
+/// Whether a rule with these settings may act at `now`. A span running past
+/// midnight belongs to the day it started on.
+pub(crate) fn is_active(
+    hours: Option<&ActiveHours>,
+    days: &ActiveDays,
+    now: DateTime<Local>,
+) -> bool {
+    let time = now.time();
+    let mut day = now.weekday();
+    if let Some(hours) = hours {
+        if !hours.contains(time) {
+            return false;
+        }
+        if hours.is_after_midnight(time) {
+            day = day.pred();
+        }
+    }
+    days.contains(day)
+}
+
+/// The first time at or after `now` a rule with these settings may act
+pub(crate) fn next_active(
+    hours: Option<&ActiveHours>,
+    days: &ActiveDays,
+    now: DateTime<Local>,
+) -> Option<DateTime<Local>> {
+    if is_active(hours, days, now) {
+        return Some(now);
+    }
+    // Windows open at their start time (or midnight) on some day this week
+    let start = hours.map_or(NaiveTime::MIN, |h| h.start);
+    (0..=7)
+        .filter_map(|d| {
+            let date = now.date_naive() + Duration::days(d);
+            Local.from_local_datetime(&date.and_time(start)).earliest()
+        })
+        .find(|&time| time > now && is_active(hours, days, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(day: u32, time: &str) -> DateTime<Local> {
+        // 2024-01-01 was a Monday
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let time = NaiveTime::parse_from_str(time, "%H:%M").unwrap();
+        Local
+            .from_local_datetime(&date.and_time(time))
+            .earliest()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_active_window() {
+        let hours = ActiveHours::try_from("22:00 - 06:00".to_string()).unwrap();
+        let weekdays = ActiveDays::try_from(vec!["mon-fri".to_string()]).unwrap();
+        let weekend =
+            ActiveDays::try_from(vec!["Saturday".to_string(), "sun".to_string()]).unwrap();
+        assert_eq!(String::from(hours), "22:00-06:00");
+
+        // Friday night's span runs into Saturday morning
+        assert!(is_active(Some(&hours), &weekdays, at(5, "23:00")));
+        assert!(is_active(Some(&hours), &weekdays, at(6, "05:00")));
+        assert!(!is_active(Some(&hours), &weekdays, at(6, "23:00")));
+        assert!(!is_active(Some(&hours), &weekdays, at(1, "05:00")));
+        assert!(!is_active(
+            Some(&hours),
+            &ActiveDays::default(),
+            at(3, "12:00")
+        ));
+        assert!(is_active(None, &weekend, at(7, "12:00")));
+        assert!(!is_active(None, &weekend, at(8, "12:00")));
+
+        assert_eq!(
+            next_active(Some(&hours), &weekdays, at(6, "12:00")),
+            Some(at(8, "22:00"))
+        );
+        assert_eq!(
+            next_active(None, &weekend, at(3, "09:30")),
+            Some(at(6, "00:00"))
+        );
+        assert_eq!(
+            next_active(None, &weekend, at(6, "09:30")),
+            Some(at(6, "09:30"))
+        );
+
+        assert!(ActiveHours::try_from("9-17".to_string()).is_err());
+        assert!(ActiveHours::try_from("09:00-09:00".to_string()).is_err());
+        assert!(ActiveDays::try_from(vec!["weekday".to_string()]).is_err());
+        assert!(ActiveDays::try_from(vec!["mo".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+//! Cron-like schedules for periodic watch scans
+//!
+//! Filesystem events only fire when something changes, so age-based rules
+//! ("older than 30 days") would never trigger on their own. A watch can set
+//! `schedule = "0 3 * * *"` to have its folder rescanned on a timer.
+//!
+//! Schedules use the standard five cron fields
+//! (`minute hour day-of-month month day-of-week`) with `*`, lists (`1,15`),
+//! ranges (`1-5`), steps (`*/15`), month/day names (`jan`, `mon`) and the
+//! `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` shortcuts.
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::config::WatchConfig;
+
+/// How far ahead to look for the next run before giving up (e.g. `0 0 30 2 *`)
+const MAX_LOOKAHEAD_DAYS: i64 = 366 * 5;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A parsed cron expression
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+pub struct Schedule {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Day-of-month field starts with `*` (cron ORs the two day fields when both are restricted)
+    any_day_of_month: bool,
+    /// Day-of-week field starts with `*`
+    any_day_of_week: bool,
+}
+
+impl Schedule {
+    /// Parse a cron expression
+    pub fn parse(expr: &str) -> Result<Self> {
+        let source = expr.trim();
+        let expanded = match source {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields[..] else {
+            bail!(
+                "Invalid schedule '{}': expected 5 fields (minute hour day month weekday)",
+                source
+            );
+        };
+
+        let parse = |field: &str, name: &str, min: u32, max: u32, names: &[&str]| {
+            parse_field(field, min, max, names)
+                .with_context(|| format!("Invalid {} field in schedule '{}'", name, source))
+        };
+
+        let minutes = parse(minute, "minute", 0, 59, &[])?;
+        let hours = parse(hour, "hour", 0, 23, &[])?;
+        let days_of_month = parse(dom, "day-of-month", 1, 31, &[])?;
+        let months = parse(month, "month", 1, 12, &MONTH_NAMES)?;
+        // 7 is an alias for Sunday
+        let mut days_of_week = parse(dow, "day-of-week", 0, 7, &DAY_NAMES)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            minutes,
+            hours,
+            days_of_month,
+            months,
+            days_of_week,
+            any_day_of_month: dom.starts_with('*'),
+            any_day_of_week: dow.starts_with('*'),
+        })
+    }
+
+    /// The original expression
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the schedule fires on this date (ignoring the time of day)
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !bit(self.months, date.month()) {
+            return false;
+        }
+        let dom = bit(self.days_of_month, date.day());
+        let dow = bit(self.days_of_week, date.weekday().num_days_from_sunday());
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => dom || dow,
+            _ => dom && dow,
+        }
+    }
+
+    /// Next time strictly after `after` at which the schedule fires
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        // Start at the next whole minute
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+
+        for day_offset in 0..MAX_LOOKAHEAD_DAYS {
+            let date = start.date() + Duration::days(day_offset);
+            if !self.matches_date(date) {
+                continue;
+            }
+            for hour in (0..24).filter(|h| bit(self.hours, *h)) {
+                for minute in (0..60).filter(|m| bit(self.minutes, *m)) {
+                    let candidate = date.and_hms_opt(hour, minute, 0)?;
+                    if candidate < start {
+                        continue;
+                    }
+                    // Times skipped by a DST change don't exist locally
+                    if let Some(time) = Local.from_local_datetime(&candidate).earliest() {
+                        return Some(time);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<Schedule> for String {
+    fn from(schedule: Schedule) -> Self {
+        schedule.source
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn bit(mask: u64, n: u32) -> bool {
+    mask & (1 << n) != 0
+}
+
+/// Parse one cron field into a bitmask of allowed values
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let value = |s: &str| -> Result<u32> {
+        let lower = s.to_ascii_lowercase();
+        if let Some(pos) = names.iter().position(|n| *n == lower) {
+            // Month names start at 1, day names at 0
+            return Ok(pos as u32 + min);
+        }
+        let n: u32 = s
+            .parse()
+            .with_context(|| format!("'{}' is not a number", s))?;
+        if n < min || n > max {
+            bail!("{} is out of range {}-{}", n, min, max);
+        }
+        Ok(n)
+    };
+
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .with_context(|| format!("'{}' is not a valid step", step))?;
+                if step == 0 {
+                    bail!("step must be greater than 0");
+                }
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            let (a, b) = (value(a)?, value(b)?);
+            if a > b {
+                bail!("range {} is backwards", range);
+            }
+            (a, b)
+        } else {
+            let a = value(range)?;
+            // "5/15" means "from 5, every 15"
+            (a, if step.is_some() { max } else { a })
+        };
+
+        for n in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            mask |= 1 << n;
+        }
+    }
+    Ok(mask)
+}
+
+/// Tracks when each scheduled watch (or other scheduled item) is next due
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    /// (item index, schedule, next run)
+    entries: Vec<(usize, Schedule, Option<DateTime<Local>>)>,
+}
+
+impl Scheduler {
+    /// Build a scheduler for the watches that define a `schedule`
+    pub fn new(watches: &[WatchConfig], now: DateTime<Local>) -> Self {
+        for watch in watches {
+            if let Some(schedule) = &watch.schedule {
+                debug!(
+                    "Scheduled scans for {} ({}), next at {:?}",
+                    watch.path.display(),
+                    schedule,
+                    schedule.next_after(now)
+                );
+            }
+        }
+        Self::with(watches.iter().map(|w| w.schedule.clone()), now)
+    }
+
+    /// Build a scheduler for a list of items, some of which have a schedule
+    pub fn with(
+        schedules: impl IntoIterator<Item = Option<Schedule>>,
+        now: DateTime<Local>,
+    ) -> Self {
+        let entries = schedules
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, schedule)| {
+                let schedule = schedule?;
+                let next = schedule.next_after(now);
+                Some((i, schedule, next))
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// Indices of the items due at `now`, advancing their next run
+    pub fn due(&mut self, now: DateTime<Local>) -> Vec<usize> {
+        let mut due = Vec::new();
+        for (index, schedule, next) in &mut self.entries {
+            if next.is_some_and(|n| n <= now) {
+                due.push(*index);
+                *next = schedule.next_after(now);
+            }
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Local> {
+        Local
+            .from_local_datetime(
+                &NaiveDate::from_ymd_opt(y, mo, d)
+                    .unwrap()
+                    .and_hms_opt(h, mi, 0)
+                    .unwrap(),
+            )
+            .earliest()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        assert!(Schedule::parse("0 3 * * *").is_ok());
+        assert!(Schedule::parse("*/15 9-17 * * mon-fri").is_ok());
+        assert!(Schedule::parse("@daily").is_ok());
+        assert!(Schedule::parse("0 3 * *").is_err());
+        assert!(Schedule::parse("60 3 * * *").is_err());
+        assert!(Schedule::parse("*/0 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        let daily = Schedule::parse("0 3 * * *").unwrap();
+        assert_eq!(
+            daily.next_after(local(2024, 1, 1, 2, 30)),
+            Some(local(2024, 1, 1, 3, 0))
+        );
+        assert_eq!(
+            daily.next_after(local(2024, 1, 1, 3, 0)),
+            Some(local(2024, 1, 2, 3, 0))
+        );
+
+        // 2024-01-06 is a Saturday; next weekday is Monday the 8th
+        let weekdays = Schedule::parse("30 9 * * 1-5").unwrap();
+        assert_eq!(
+            weekdays.next_after(local(2024, 1, 6, 12, 0)),
+            Some(local(2024, 1, 8, 9, 30))
+        );
+
+        let never = Schedule::parse("0 0 30 2 *").unwrap();
+        assert_eq!(never.next_after(local(2024, 1, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_scheduler_due() {
+        let watches: Vec<WatchConfig> = toml::from_str::<crate::Config>(
+            r#"
+            [[watch]]
+            path = "/a"
+            schedule = "0 * * * *"
+
+            [[watch]]
+            path = "/b"
+            "#,
+        )
+        .unwrap()
+        .watches;
+
+        let mut scheduler = Scheduler::new(&watches, local(2024, 1, 1, 10, 30));
+        assert!(scheduler.due(local(2024, 1, 1, 10, 59)).is_empty());
+        assert_eq!(scheduler.due(local(2024, 1, 1, 11, 0)), vec![0]);
+        assert!(scheduler.due(local(2024, 1, 1, 11, 0)).is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+//! The daemon on Windows
+//!
+//! Windows has no PID file lock that other processes can see, so a running
+//! `hazelnutd` holds a named mutex instead: `Global\hazelnutd` when it runs
+//! as a Windows service (in session 0, for every user), `Local\hazelnutd`
+//! when it runs in the user's session (`hazelnutd run`, or auto-start).
+//! Kernel objects go away with the process, so a crash leaves nothing
+//! behind. Each named instance gets its own mutex and service.
+
+use std::ffi::OsString;
+use std::path::Path;
+
+/// Name of the service and of the daemon's mutex (per instance)
+pub fn name() -> String {
+    crate::instance::suffixed("hazelnutd")
+}
+
+/// Name of the mutex held by a daemon running as a service (`global`) or in
+/// the user's session
+pub fn mutex_name(global: bool) -> String {
+    let namespace = if global { "Global" } else { "Local" };
+    format!("{}\\{}", namespace, name())
+}
+
+/// Arguments the service manager starts `hazelnutd.exe` with. The service
+/// runs as LocalSystem, whose home and config folders aren't the user's,
+/// so the config file is always passed.
+pub fn launch_arguments(config: &Path) -> Vec<OsString> {
+    crate::instance::daemon_args()
+        .into_iter()
+        .map(OsString::from)
+        .chain([
+            OsString::from("--config"),
+            config.as_os_str().to_owned(),
+            OsString::from("service"),
+            OsString::from("run"),
+        ])
+        .collect()
+}
+
+/// Held while the daemon runs; dropping it releases the name
+#[cfg(windows)]
+pub struct DaemonMutex(windows_sys::Win32::Foundation::HANDLE);
+
+#[cfg(windows)]
+impl Drop for DaemonMutex {
+    fn drop(&mut self) {
+        // SAFETY: the handle came from CreateMutexW and is closed only here
+        unsafe { windows_sys::Win32::Foundation::CloseHandle(self.0) };
+    }
+}
+
+/// Take the daemon's name for this process, failing if a daemon of this
+/// instance already runs (as a service or in any session's `Local` name
+/// this process can see)
+#[cfg(windows)]
+pub fn lock(global: bool) -> anyhow::Result<DaemonMutex> {
+    use anyhow::Context;
+    use windows_sys::Win32::Foundation::{CloseHandle, ERROR_ALREADY_EXISTS, GetLastError};
+    use windows_sys::Win32::System::Threading::CreateMutexW;
+
+    if is_running() {
+        anyhow::bail!("Daemon is already running");
+    }
+    let name = wide(&mutex_name(global));
+    // SAFETY: `name` is a NUL-terminated UTF-16 string that outlives the call
+    let handle = unsafe { CreateMutexW(std::ptr::null(), 0, name.as_ptr()) };
+    if handle.is_null() {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("Failed to create {}", mutex_name(global)));
+    }
+    // SAFETY: reads the calling thread's last error, set by CreateMutexW
+    if unsafe { GetLastError() } == ERROR_ALREADY_EXISTS {
+        // SAFETY: the handle is valid and not used afterwards
+        unsafe { CloseHandle(handle) };
+        anyhow::bail!("Daemon is already running");
+    }
+    Ok(DaemonMutex(handle))
+}
+
+/// Whether a daemon of this instance is running
+#[cfg(windows)]
+pub fn is_running() -> bool {
+    exists(&mutex_name(true)) || exists(&mutex_name(false))
+}
+
+/// Whether a named mutex exists. One made by the service may not let this
+/// user open it, which still means it's there.
+#[cfg(windows)]
+fn exists(name: &str) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, ERROR_ACCESS_DENIED, GetLastError};
+    use windows_sys::Win32::System::Threading::{OpenMutexW, SYNCHRONIZATION_SYNCHRONIZE};
+
+    let name = wide(name);
+    // SAFETY: `name` is a NUL-terminated UTF-16 string that outlives the call
+    let handle = unsafe { OpenMutexW(SYNCHRONIZATION_SYNCHRONIZE, 0, name.as_ptr()) };
+    if handle.is_null() {
+        // SAFETY: reads the calling thread's last error, set by OpenMutexW
+        return unsafe { GetLastError() } == ERROR_ACCESS_DENIED;
+    }
+    // SAFETY: the handle is valid and not used afterwards
+    unsafe { CloseHandle(handle) };
+    true
+}
+
+/// NUL-terminated UTF-16, as Windows API calls take strings
+#[cfg(windows)]
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_arguments() {
+        let args = launch_arguments(Path::new(r"C:\Users\me\.config\hazelnut\config.toml"));
+        assert_eq!(
+            args,
+            [
+                "--config",
+                r"C:\Users\me\.config\hazelnut\config.toml",
+                "service",
+                "run"
+            ]
+        );
+        assert_eq!(mutex_name(true), r"Global\hazelnutd");
+        assert_eq!(mutex_name(false), r"Local\hazelnutd");
+    }
+}
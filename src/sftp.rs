@@ -0,0 +1,659 @@
+//! Remote folders over SFTP
+//!
+//! A watch with a `sftp://[user@]host[:port]/path` path polls the remote
+//! directory through the system OpenSSH client, so keys, agents and
+//! `~/.ssh/config` work as they do for `sftp` itself. Files are fetched into
+//! a local staging folder once their size and modification time stop
+//! changing, and go through the rules like local files.
+//!
+//! Actions on a staged file are carried out on the server over the same
+//! (multiplexed) SSH connection: moving it to a local folder fetches it and
+//! removes the remote file, moving it to a `sftp://` folder on the same
+//! server renames it there, and `rename` and `delete` apply to the remote
+//! file. Staged copies of files that stay on the server are kept so they
+//! aren't fetched again, until the remote file goes away.
+
+use anyhow::{Context, Result};
+use notify::event::CreateKind;
+use notify::{Event, EventKind};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::rules::Action;
+
+/// How often a sleeping poller checks whether it should stop
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Seconds an idle shared SSH connection stays open
+const CONTROL_PERSIST_SECS: u32 = 600;
+
+/// A `sftp://[user@]host[:port]/path` location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SftpUrl {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    /// Absolute path on the server
+    pub path: String,
+}
+
+impl SftpUrl {
+    /// Parse a watch or destination path; None if it isn't a `sftp://` URL
+    pub fn parse(path: &Path) -> Option<Self> {
+        let rest = path.to_str()?.strip_prefix("sftp://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+        let (user, host_port) = match authority.rsplit_once('@') {
+            Some((user, host_port)) => (Some(user.to_string()), host_port),
+            None => (None, authority),
+        };
+        let (host, port) = match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port.parse().ok()?)),
+            None => (host_port, None),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        let path = match path.trim_end_matches('/') {
+            "" => "/",
+            trimmed => trimmed,
+        };
+        Some(Self {
+            user,
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// `[user@]host` as passed to ssh
+    fn target(&self) -> String {
+        match self.user {
+            Some(ref user) => format!("{}@{}", user, self.host),
+            None => self.host.clone(),
+        }
+    }
+
+    /// Whether both locations are reached through the same connection
+    pub fn same_server(&self, other: &SftpUrl) -> bool {
+        self.user == other.user && self.host == other.host && self.port == other.port
+    }
+
+    /// Local folder files from this location are fetched into:
+    /// `<cache_dir>/hazelnut/sftp/<[user@]host[_port]>/<path>`
+    pub fn staging_dir(&self) -> Option<PathBuf> {
+        let mut server = self.target();
+        if let Some(port) = self.port {
+            server.push_str(&format!("_{}", port));
+        }
+        dirs::cache_dir().map(|d| {
+            d.join("hazelnut")
+                .join("sftp")
+                .join(server)
+                .join(self.path.trim_start_matches('/'))
+        })
+    }
+}
+
+impl fmt::Display for SftpUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sftp://{}", self.target())?;
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        write!(f, "{}", self.path)
+    }
+}
+
+/// Whether a watch path is a remote (`sftp://`) location
+pub fn is_remote(path: &Path) -> bool {
+    SftpUrl::parse(path).is_some()
+}
+
+/// Local folder holding a watch's files: the staging folder for remote
+/// watches, otherwise the path with `~` expanded
+pub fn local_dir(path: &Path) -> PathBuf {
+    match SftpUrl::parse(path).and_then(|url| url.staging_dir()) {
+        Some(staging) => staging,
+        None => crate::expand_path(path),
+    }
+}
+
+/// A regular file in a remote directory listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub size: u64,
+    /// Modification time as listed by the server (only compared, not parsed)
+    pub modified: String,
+}
+
+/// Shared SSH connection to one server, used through the `sftp` client
+#[derive(Debug)]
+pub struct Connection {
+    server: SftpUrl,
+    control_path: PathBuf,
+}
+
+impl Connection {
+    pub fn new(url: &SftpUrl) -> Self {
+        // Unix socket paths are short, so name the socket after a hash
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        (url.target(), url.port).hash(&mut hasher);
+        Self {
+            server: url.clone(),
+            control_path: std::env::temp_dir()
+                .join(format!("hazelnut-ssh-{:016x}", hasher.finish())),
+        }
+    }
+
+    fn ssh_options(&self, master: bool) -> Vec<String> {
+        vec![
+            "-o".to_string(),
+            "BatchMode=yes".to_string(),
+            "-o".to_string(),
+            format!("ControlMaster={}", if master { "yes" } else { "no" }),
+            "-o".to_string(),
+            format!("ControlPath={}", self.control_path.display()),
+        ]
+    }
+
+    /// Open the shared connection unless it's already up. Commands fall
+    /// back to a connection of their own if this fails.
+    fn ensure_master(&self) {
+        let mut check = Command::new("ssh");
+        check
+            .args(self.ssh_options(false))
+            .args(["-O", "check"])
+            .arg(self.server.target())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if check.status().is_ok_and(|s| s.success()) {
+            return;
+        }
+
+        debug!("Opening SSH connection to {}", self.server.target());
+        let mut master = Command::new("ssh");
+        master
+            .args(self.ssh_options(true))
+            .args(["-o", &format!("ControlPersist={}", CONTROL_PERSIST_SECS)])
+            .args(["-N", "-f"]);
+        if let Some(port) = self.server.port {
+            master.args(["-p", &port.to_string()]);
+        }
+        // The backgrounded master must not hold on to our pipes
+        let status = master
+            .arg(self.server.target())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        if !status.is_ok_and(|s| s.success()) {
+            warn!("Failed to open SSH connection to {}", self.server.target());
+        }
+    }
+
+    /// Run `sftp` batch commands, returning their output
+    fn batch(&self, commands: &str) -> Result<String> {
+        let mut sftp = Command::new("sftp");
+        sftp.args(["-q", "-b", "-"]).args(self.ssh_options(false));
+        if let Some(port) = self.server.port {
+            sftp.args(["-P", &port.to_string()]);
+        }
+        let mut child = sftp
+            .arg(self.server.target())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run sftp (is OpenSSH installed?)")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(commands.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "sftp {}: {}",
+                self.server.target(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Regular files in a remote directory
+    pub fn list(&self, dir: &str) -> Result<Vec<RemoteEntry>> {
+        self.ensure_master();
+        let output = self.batch(&format!("ls -ln {}\n", quote(dir)))?;
+        Ok(parse_listing(&output))
+    }
+
+    /// Download a file, keeping its modification time
+    pub fn fetch(&self, remote: &str, local: &Path) -> Result<()> {
+        self.batch(&format!(
+            "get -p {} {}\n",
+            quote(remote),
+            quote(&local.to_string_lossy())
+        ))
+        .map(|_| ())
+    }
+
+    /// Upload a file, keeping its modification time
+    pub fn put(&self, local: &Path, remote: &str) -> Result<()> {
+        self.batch(&format!(
+            "put -p {} {}\n",
+            quote(&local.to_string_lossy()),
+            quote(remote)
+        ))
+        .map(|_| ())
+    }
+
+    /// Rename or move a file on the server, creating the target folder if needed
+    pub fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let dir = parent(to);
+        // A leading '-' lets the batch go on if the folder already exists
+        self.batch(&format!(
+            "-mkdir {}\nrename {} {}\n",
+            quote(dir),
+            quote(from),
+            quote(to)
+        ))
+        .map(|_| ())
+    }
+
+    /// Delete a file on the server
+    pub fn remove(&self, remote: &str) -> Result<()> {
+        self.batch(&format!("rm {}\n", quote(remote))).map(|_| ())
+    }
+}
+
+/// Quote an argument for an sftp batch command
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Parent folder of a remote path
+fn parent(path: &str) -> &str {
+    match path.rsplit_once('/') {
+        Some(("", _)) => "/",
+        Some((dir, _)) => dir,
+        None => ".",
+    }
+}
+
+/// Join a remote folder and a file name
+fn join(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+/// Parse `ls -ln` output, keeping regular files only
+fn parse_listing(output: &str) -> Vec<RemoteEntry> {
+    let mut entries = Vec::new();
+    for line in output.lines() {
+        if line.starts_with("sftp>") || !line.starts_with('-') {
+            continue;
+        }
+        // mode, links, uid, gid, size, then three date fields and the name
+        let mut rest = line;
+        let mut fields = Vec::with_capacity(8);
+        for _ in 0..8 {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        let name = rest.trim_start();
+        let name = name.rsplit('/').next().unwrap_or(name);
+        let Ok(size) = fields[4].parse() else {
+            continue;
+        };
+        if name.is_empty() {
+            continue;
+        }
+        entries.push(RemoteEntry {
+            name: name.to_string(),
+            size,
+            modified: fields[5..8].join(" "),
+        });
+    }
+    entries
+}
+
+/// A remote folder being watched, and where its files are staged
+#[derive(Debug)]
+pub struct RemoteWatch {
+    pub url: SftpUrl,
+    /// Canonical local staging folder
+    pub staging: PathBuf,
+    connection: Connection,
+}
+
+static REMOTE_WATCHES: LazyLock<RwLock<Vec<Arc<RemoteWatch>>>> =
+    LazyLock::new(|| RwLock::new(Vec::new()));
+
+/// Register a remote watch staged in `staging` (replacing an earlier one)
+pub(crate) fn register(url: SftpUrl, staging: PathBuf) -> Arc<RemoteWatch> {
+    let watch = Arc::new(RemoteWatch {
+        connection: Connection::new(&url),
+        url,
+        staging,
+    });
+    if let Ok(mut watches) = REMOTE_WATCHES.write() {
+        watches.retain(|w| w.staging != watch.staging);
+        watches.push(Arc::clone(&watch));
+    }
+    watch
+}
+
+/// Forget the remote watch staged in `staging`
+pub(crate) fn unregister(staging: &Path) {
+    if let Ok(mut watches) = REMOTE_WATCHES.write() {
+        watches.retain(|w| w.staging != staging);
+    }
+}
+
+/// A staged copy of a remote file
+struct RemoteFile {
+    watch: Arc<RemoteWatch>,
+    /// Path on the server
+    remote: String,
+}
+
+/// The remote file a staged path is a copy of, if any
+fn staged(path: &Path) -> Option<RemoteFile> {
+    let watches = REMOTE_WATCHES.read().ok()?;
+    if watches.is_empty() {
+        return None;
+    }
+    let watch = watches
+        .iter()
+        .find(|w| path.parent() == Some(w.staging.as_path()))?;
+    let name = path.file_name()?.to_str()?;
+    Some(RemoteFile {
+        remote: join(&watch.url.path, name),
+        watch: Arc::clone(watch),
+    })
+}
+
+/// Run an action that involves a remote file (a staged copy) or a `sftp://`
+/// destination. Returns None if it involves neither and runs locally as usual,
+/// otherwise where a local copy landed, like `Action::execute_to`.
+pub fn execute(action: &Action, path: &Path) -> Option<Result<Option<PathBuf>>> {
+    let destination = match action {
+        Action::Move { destination, .. } | Action::Copy { destination, .. } => {
+            SftpUrl::parse(destination)
+        }
+        _ => None,
+    };
+    match (staged(path), destination) {
+        (None, None) => None,
+        (None, Some(destination)) => Some(upload(action, path, &destination)),
+        (Some(file), destination) => Some(file.execute(action, path, destination)),
+    }
+}
+
+/// Move or copy a local file to a remote folder
+fn upload(action: &Action, path: &Path, destination: &SftpUrl) -> Result<Option<PathBuf>> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .context("No file name")?;
+    Connection::new(destination).put(path, &join(&destination.path, &name))?;
+    if matches!(action, Action::Move { .. }) {
+        std::fs::remove_file(path)?;
+    }
+    info!("{} → {}/{}", path.display(), destination, name);
+    Ok(None)
+}
+
+impl RemoteFile {
+    fn execute(
+        &self,
+        action: &Action,
+        staged: &Path,
+        destination: Option<SftpUrl>,
+    ) -> Result<Option<PathBuf>> {
+        let connection = &self.watch.connection;
+        let is_move = matches!(action, Action::Move { .. });
+
+        if let Some(destination) = destination {
+            if !destination.same_server(&self.watch.url) {
+                // No server-to-server transfers: upload the staged copy
+                upload(action, staged, &destination)?;
+                if is_move {
+                    connection.remove(&self.remote)?;
+                }
+                return Ok(None);
+            }
+            let name = staged
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let target = join(&destination.path, &name);
+            if is_move {
+                connection.rename(&self.remote, &target)?;
+                std::fs::remove_file(staged)?;
+            } else {
+                connection.put(staged, &target)?;
+            }
+            info!("{} → {}", self.remote, target);
+            return Ok(None);
+        }
+
+        match action {
+            Action::Move { .. } => {
+                // The staged copy is the fetched file
+                let landed = action.execute_to(staged)?;
+                connection.remove(&self.remote)?;
+                Ok(landed)
+            }
+            Action::Rename { .. } => {
+                let landed = action.execute_to(staged)?;
+                if let Some(new_name) = landed.as_ref().and_then(|l| l.file_name()) {
+                    let target = join(parent(&self.remote), &new_name.to_string_lossy());
+                    connection.rename(&self.remote, &target)?;
+                }
+                Ok(landed)
+            }
+            Action::Delete => {
+                connection.remove(&self.remote)?;
+                std::fs::remove_file(staged)?;
+                Ok(None)
+            }
+            // These work on the fetched copy and leave the remote file alone
+            Action::Copy { .. }
+            | Action::Run { .. }
+            | Action::Archive { .. }
+            | Action::Script { .. }
+            | Action::Nothing
+            | Action::Plugin(_) => action.execute_to(staged),
+            Action::Trash | Action::Tier { .. } => {
+                anyhow::bail!(
+                    "This action is not supported for remote files ({})",
+                    self.remote
+                )
+            }
+        }
+    }
+}
+
+/// Start a background thread that polls a remote folder every `interval`,
+/// fetches new files once they're complete and sends a create event for
+/// each staged copy to `tx`, until `stop` is set or the receiver goes away.
+/// Files already there when polling starts are only processed if
+/// `process_existing` is set.
+pub(crate) fn spawn_poller(
+    watch: Arc<RemoteWatch>,
+    interval: Duration,
+    process_existing: bool,
+    tx: Sender<Result<Event, notify::Error>>,
+    stop: Arc<AtomicBool>,
+) {
+    std::thread::spawn(move || {
+        let mut previous: HashMap<String, (u64, String)> = HashMap::new();
+        // Files left alone until they change (when not processing existing ones)
+        let mut baseline: Option<HashMap<String, (u64, String)>> =
+            (!process_existing).then(HashMap::new);
+        let mut first = true;
+
+        loop {
+            if !first {
+                let mut waited = Duration::ZERO;
+                while waited < interval {
+                    if stop.load(Ordering::Relaxed) {
+                        debug!("Stopped polling {}", watch.url);
+                        return;
+                    }
+                    std::thread::sleep(STOP_CHECK_INTERVAL);
+                    waited += STOP_CHECK_INTERVAL;
+                }
+            }
+
+            let entries = match watch.connection.list(&watch.url.path) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    warn!("Failed to poll {}: {:#}", watch.url, e);
+                    first = false;
+                    continue;
+                }
+            };
+            let current: HashMap<String, (u64, String)> = entries
+                .into_iter()
+                .map(|e| (e.name, (e.size, e.modified)))
+                .collect();
+
+            if first {
+                if let Some(ref mut baseline) = baseline {
+                    baseline.clone_from(&current);
+                }
+                first = false;
+            }
+            if let Some(ref mut baseline) = baseline {
+                baseline.retain(|name, state| current.get(name) == Some(state));
+            }
+
+            for (name, state) in &current {
+                // Complete once unchanged between two polls
+                if previous.get(name) != Some(state)
+                    || baseline.as_ref().is_some_and(|b| b.contains_key(name))
+                {
+                    continue;
+                }
+                let staged = watch.staging.join(name);
+                if std::fs::metadata(&staged).is_ok_and(|m| m.len() == state.0) {
+                    continue;
+                }
+                match fetch(&watch, name, &staged) {
+                    Ok(()) => {
+                        info!("Fetched {}/{}", watch.url, name);
+                        let event =
+                            Event::new(EventKind::Create(CreateKind::File)).add_path(staged);
+                        if tx.send(Ok(event)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => warn!("Failed to fetch {}/{}: {:#}", watch.url, name, e),
+                }
+            }
+
+            remove_stale_copies(&watch.staging, &current);
+            previous = current;
+        }
+    });
+}
+
+/// Fetch a remote file into the staging folder under a temporary name, so
+/// the watcher doesn't see it half-written
+fn fetch(watch: &RemoteWatch, name: &str, staged: &Path) -> Result<()> {
+    let partial = watch.staging.join(format!(".{}.part", name));
+    watch
+        .connection
+        .fetch(&join(&watch.url.path, name), &partial)?;
+    std::fs::rename(&partial, staged)?;
+    Ok(())
+}
+
+/// Remove staged copies of files that are gone from the server
+fn remove_stale_copies(staging: &Path, current: &HashMap<String, (u64, String)>) {
+    let Ok(read_dir) = std::fs::read_dir(staging) else {
+        return;
+    };
+    for entry in read_dir.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if current.contains_key(&name) || name.ends_with(".part") {
+            continue;
+        }
+        if entry.file_type().is_ok_and(|t| t.is_file()) {
+            debug!("Removing stale staged copy {}", entry.path().display());
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        let url =
+            SftpUrl::parse(Path::new("sftp://me@files.example.com:2222/srv/incoming/")).unwrap();
+        assert_eq!(url.user.as_deref(), Some("me"));
+        assert_eq!(url.host, "files.example.com");
+        assert_eq!(url.port, Some(2222));
+        assert_eq!(url.path, "/srv/incoming");
+        assert_eq!(
+            url.to_string(),
+            "sftp://me@files.example.com:2222/srv/incoming"
+        );
+
+        let root = SftpUrl::parse(Path::new("sftp://nas")).unwrap();
+        assert_eq!(root.path, "/");
+        assert!(root.same_server(&SftpUrl::parse(Path::new("sftp://nas/archive")).unwrap()));
+        assert!(!root.same_server(&url));
+
+        assert!(SftpUrl::parse(Path::new("/home/me/Downloads")).is_none());
+        assert!(SftpUrl::parse(Path::new("sftp://host:port/x")).is_none());
+    }
+
+    #[test]
+    fn test_parse_listing() {
+        let output = "\
+sftp> ls -ln \"/srv/incoming\"
+-rw-r--r--    1 1000     1000         1234 Oct 16 17:42 /srv/incoming/report.pdf
+drwxr-xr-x    2 1000     1000         4096 Oct 16 17:40 /srv/incoming/photos
+-rw-r--r--    1 1000     1000            0 Jan  2  2025 /srv/incoming/my notes.txt
+";
+        let entries = parse_listing(output);
+        assert_eq!(
+            entries,
+            vec![
+                RemoteEntry {
+                    name: "report.pdf".to_string(),
+                    size: 1234,
+                    modified: "Oct 16 17:42".to_string(),
+                },
+                RemoteEntry {
+                    name: "my notes.txt".to_string(),
+                    size: 0,
+                    modified: "Jan 2 2025".to_string(),
+                },
+            ]
+        );
+        assert_eq!(quote("a \"b\""), "\"a \\\"b\\\"\"");
+        assert_eq!(parent("/srv/incoming/a.txt"), "/srv/incoming");
+        assert_eq!(parent("/a.txt"), "/");
+    }
+}
@@ -0,0 +1,304 @@
+//! Shadow evaluation of a candidate rule set
+//!
+//! While a big rules refactor is in progress, `[shadow] rules_file` points
+//! at the candidate config. Every file the active rules process is also
+//! evaluated against the candidate rules (without running their actions),
+//! and files where the two would do something different are appended to a
+//! log. `hazelnut shadow` summarizes the log, so the candidate can be checked
+//! against a few days of real traffic before switching over.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::rules::{Rule, RuleEngine};
+
+/// A rule that would handle a file, and what it would do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+    /// Rule name
+    pub rule: String,
+    /// The rule's action as JSON, so renamed rules with the same action compare equal
+    pub action: String,
+}
+
+impl Decision {
+    fn from_rule(rule: &Rule) -> Self {
+        Self {
+            rule: rule.name.clone(),
+            action: serde_json::to_string(&rule.action).unwrap_or_default(),
+        }
+    }
+}
+
+/// A file the active and candidate rules would handle differently
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Divergence {
+    pub timestamp: DateTime<Local>,
+    pub path: PathBuf,
+    /// Rules of the active set that matched, in order
+    pub active: Vec<Decision>,
+    /// Rules of the candidate set that matched, in order
+    pub candidate: Vec<Decision>,
+}
+
+/// Candidate rules evaluated alongside the active ones
+pub struct ShadowRules {
+    engine: RuleEngine,
+    /// Where divergences are appended (None = only logged)
+    log_path: Option<PathBuf>,
+}
+
+impl ShadowRules {
+    /// Load the candidate rules from a config file
+    pub fn load(rules_file: &Path, log_path: Option<PathBuf>) -> Result<Self> {
+        let rules_file = crate::expand_path(rules_file);
+        anyhow::ensure!(
+            rules_file.exists(),
+            "Shadow rules file not found: {}",
+            rules_file.display()
+        );
+        let candidate = Config::load(Some(&rules_file))?;
+        info!(
+            "Shadow-evaluating {} candidate rules from {}",
+            candidate.rules.len(),
+            rules_file.display()
+        );
+        Ok(Self {
+            engine: RuleEngine::new(candidate.rules),
+            log_path,
+        })
+    }
+
+    /// Load the candidate rules configured in `[shadow]`, if any.
+    /// A missing or invalid candidate file is logged and shadowing disabled.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let rules_file = config.shadow.rules_file.as_ref()?;
+        match Self::load(rules_file, default_log_path()) {
+            Ok(shadow) => Some(shadow),
+            Err(e) => {
+                warn!("Shadow evaluation disabled: {:#}", e);
+                None
+            }
+        }
+    }
+
+    /// Evaluate `path` with both rule sets before the active actions run,
+    /// recording a divergence if they disagree
+    pub fn compare(
+        &self,
+        active: &RuleEngine,
+        path: &Path,
+        allowed_rules: Option<&[String]>,
+    ) -> Option<Divergence> {
+        let decide = |engine: &RuleEngine| -> Option<Vec<Decision>> {
+            match engine.matching_rules(path, allowed_rules) {
+                Ok(rules) => Some(rules.into_iter().map(Decision::from_rule).collect()),
+                Err(e) => {
+                    debug!("Shadow evaluation failed for {}: {}", path.display(), e);
+                    None
+                }
+            }
+        };
+        let active = decide(active)?;
+        let candidate = decide(&self.engine)?;
+
+        let actions = |decisions: &[Decision]| -> Vec<String> {
+            decisions.iter().map(|d| d.action.clone()).collect()
+        };
+        if actions(&active) == actions(&candidate) {
+            return None;
+        }
+
+        let divergence = Divergence {
+            timestamp: Local::now(),
+            path: path.to_path_buf(),
+            active,
+            candidate,
+        };
+        info!(
+            "Shadow rules differ for {}: {} -> {}",
+            path.display(),
+            label(&divergence.active, false),
+            label(&divergence.candidate, false)
+        );
+        if let Some(ref log_path) = self.log_path
+            && let Err(e) = append(log_path, &divergence)
+        {
+            warn!("Failed to write shadow log: {}", e);
+        }
+        Some(divergence)
+    }
+}
+
+/// Default log location: `<data_dir>/hazelnut/shadow.jsonl`
+pub fn default_log_path() -> Option<PathBuf> {
+    Config::data_dir().map(|d| d.join("shadow.jsonl"))
+}
+
+fn append(log_path: &Path, divergence: &Divergence) -> Result<()> {
+    if let Some(parent) = log_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .with_context(|| format!("Failed to open {}", log_path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(divergence)?)?;
+    Ok(())
+}
+
+/// Read every divergence from a log (a missing log is empty; bad lines are skipped)
+pub fn read_log(log_path: &Path) -> Result<Vec<Divergence>> {
+    if !log_path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(log_path)
+        .with_context(|| format!("Failed to read {}", log_path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Divergences with the same outcome on both sides
+#[derive(Debug, Clone, PartialEq)]
+pub struct DivergenceGroup {
+    /// What the active rules do, e.g. "Archive" or "(no match)"
+    pub active: String,
+    /// What the candidate rules would do
+    pub candidate: String,
+    pub count: usize,
+    /// Most recent file with this outcome
+    pub example: PathBuf,
+}
+
+/// Group divergences by outcome, most frequent first
+pub fn summarize(divergences: &[Divergence]) -> Vec<DivergenceGroup> {
+    let mut groups: IndexMap<(String, String), DivergenceGroup> = IndexMap::new();
+    for divergence in divergences {
+        // Same rules on both sides means an action changed, so show it
+        let same_rules = divergence
+            .active
+            .iter()
+            .map(|d| &d.rule)
+            .eq(divergence.candidate.iter().map(|d| &d.rule));
+        let active = label(&divergence.active, same_rules);
+        let candidate = label(&divergence.candidate, same_rules);
+        groups
+            .entry((active.clone(), candidate.clone()))
+            .and_modify(|g| {
+                g.count += 1;
+                g.example = divergence.path.clone();
+            })
+            .or_insert(DivergenceGroup {
+                active,
+                candidate,
+                count: 1,
+                example: divergence.path.clone(),
+            });
+    }
+    let mut groups: Vec<DivergenceGroup> = groups.into_values().collect();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.count));
+    groups
+}
+
+fn label(decisions: &[Decision], with_actions: bool) -> String {
+    if decisions.is_empty() {
+        return "(no match)".to_string();
+    }
+    decisions
+        .iter()
+        .map(|d| {
+            if with_actions {
+                format!("{} {}", d.rule, d.action)
+            } else {
+                d.rule.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Action, Condition, OnConflict};
+
+    fn rule(name: &str, extension: &str, destination: &str) -> Rule {
+        Rule::new(
+            name,
+            Condition {
+                extension: Some(extension.to_string()),
+                ..Default::default()
+            },
+            Action::Move {
+                destination: PathBuf::from(destination),
+                create_destination: true,
+                on_conflict: OnConflict::Fail,
+                preserve_metadata: true,
+            },
+        )
+    }
+
+    #[test]
+    fn test_compare_logs_divergences() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("shadow.jsonl");
+        let active = RuleEngine::new(vec![rule("PDFs", "pdf", "/docs")]);
+        let shadow = ShadowRules {
+            engine: RuleEngine::new(vec![
+                rule("Documents", "pdf", "/docs"),
+                rule("Images", "png", "/images"),
+            ]),
+            log_path: Some(log_path.clone()),
+        };
+
+        // Renamed rule with the same action is not a divergence
+        assert!(
+            shadow
+                .compare(&active, Path::new("/dl/a.pdf"), None)
+                .is_none()
+        );
+        assert!(
+            shadow
+                .compare(&active, Path::new("/dl/b.png"), None)
+                .is_some()
+        );
+        assert!(
+            shadow
+                .compare(&active, Path::new("/dl/c.png"), None)
+                .is_some()
+        );
+
+        let logged = read_log(&log_path).unwrap();
+        assert_eq!(logged.len(), 2);
+        let groups = summarize(&logged);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].active, "(no match)");
+        assert_eq!(groups[0].candidate, "Images");
+        assert_eq!(groups[0].count, 2);
+        assert_eq!(groups[0].example, PathBuf::from("/dl/c.png"));
+    }
+
+    #[test]
+    fn test_changed_action_is_labelled() {
+        let active = RuleEngine::new(vec![rule("PDFs", "pdf", "/docs")]);
+        let shadow = ShadowRules {
+            engine: RuleEngine::new(vec![rule("PDFs", "pdf", "/papers")]),
+            log_path: None,
+        };
+        let divergence = shadow
+            .compare(&active, Path::new("/dl/a.pdf"), None)
+            .unwrap();
+        let groups = summarize(&[divergence]);
+        assert!(groups[0].active.contains("/docs"));
+        assert!(groups[0].candidate.contains("/papers"));
+    }
+}
@@ -0,0 +1,474 @@
+//! Hourly processing statistics
+//!
+//! Counts of processed, failed and unmatched files per watch, per rule and
+//! per hour, with the size and destination folders of the files rules moved
+//! or copied, kept for `stats_retention_days` in the data directory.
+//! `hazelnut stats export` dumps them as JSON or CSV for spreadsheets and
+//! dashboards, and the TUI's Stats view summarizes the last day or week.
+//!
+//! Counts are collected in memory and merged into the file about once a
+//! minute, so concurrent writers (the daemon and a TUI's embedded watcher)
+//! don't lose each other's counts.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, DurationRound, Local, Utc};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Instant;
+use tracing::warn;
+
+/// How often collected counts are written out
+const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// What happened to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    /// A rule's action ran
+    Processed,
+    /// A rule's action failed
+    Failed,
+    /// No rule matched
+    Unmatched,
+}
+
+/// Counts for one watch and rule during one hour
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bucket {
+    /// Start of the hour
+    pub hour: DateTime<Utc>,
+    /// Watched folder
+    pub watch: PathBuf,
+    /// Rule name (empty for unmatched files)
+    pub rule: String,
+    #[serde(default)]
+    pub processed: u64,
+    #[serde(default)]
+    pub failed: u64,
+    #[serde(default)]
+    pub unmatched: u64,
+    /// Size of the files the rule moved or copied
+    #[serde(default)]
+    pub bytes: u64,
+    /// Files per folder the rule moved or copied them to
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub destinations: BTreeMap<PathBuf, u64>,
+}
+
+impl Bucket {
+    fn add(&mut self, other: &Bucket) {
+        self.processed += other.processed;
+        self.failed += other.failed;
+        self.unmatched += other.unmatched;
+        self.bytes += other.bytes;
+        for (folder, files) in &other.destinations {
+            *self.destinations.entry(folder.clone()).or_default() += files;
+        }
+    }
+}
+
+/// One rule's counts over a period
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleTotals {
+    /// Rule name (empty for a watch's `unmatched_action`)
+    pub rule: String,
+    pub processed: u64,
+    pub failed: u64,
+    pub bytes: u64,
+}
+
+/// Counts over a period, for the TUI's Stats view
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    /// Rules that processed or failed on a file, most processed first
+    pub rules: Vec<RuleTotals>,
+    /// Files no rule matched
+    pub unmatched: u64,
+    /// Destination folders, most files first
+    pub destinations: Vec<(PathBuf, u64)>,
+    /// Files processed per time slot, oldest first
+    pub processed: Vec<u64>,
+    /// Failures per time slot, oldest first
+    pub failed: Vec<u64>,
+}
+
+impl Summary {
+    pub fn total(&self) -> RuleTotals {
+        self.rules
+            .iter()
+            .fold(RuleTotals::default(), |mut total, r| {
+                total.processed += r.processed;
+                total.failed += r.failed;
+                total.bytes += r.bytes;
+                total
+            })
+    }
+}
+
+type Key = (DateTime<Utc>, PathBuf, String);
+
+/// Hourly buckets, in the order they were first seen
+#[derive(Debug, Default)]
+pub struct Stats {
+    buckets: IndexMap<Key, Bucket>,
+}
+
+impl Stats {
+    /// Load statistics from a file (a missing or unreadable file gives empty stats)
+    pub fn load(path: &Path) -> Self {
+        let buckets: Vec<Bucket> = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let mut stats = Self::default();
+        for bucket in &buckets {
+            stats.merge_bucket(bucket);
+        }
+        stats
+    }
+
+    /// Default location: `<data_dir>/hazelnut/stats.json`
+    pub fn default_path() -> Option<PathBuf> {
+        crate::Config::data_dir().map(|d| d.join("stats.json"))
+    }
+
+    /// Write statistics to a file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string(&self.buckets())?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write statistics {}", path.display()))
+    }
+
+    /// Count a file in the bucket of the hour containing `at`
+    pub fn record(&mut self, at: DateTime<Utc>, watch: &Path, rule: &str, counter: Counter) {
+        let bucket = self.bucket(at, watch, rule);
+        match counter {
+            Counter::Processed => bucket.processed += 1,
+            Counter::Failed => bucket.failed += 1,
+            Counter::Unmatched => bucket.unmatched += 1,
+        }
+    }
+
+    /// Count the size and destination folder of a file a rule moved or copied
+    pub fn record_filed(
+        &mut self,
+        at: DateTime<Utc>,
+        watch: &Path,
+        rule: &str,
+        bytes: u64,
+        folder: &Path,
+    ) {
+        let bucket = self.bucket(at, watch, rule);
+        bucket.bytes += bytes;
+        *bucket.destinations.entry(folder.to_path_buf()).or_default() += 1;
+    }
+
+    fn bucket(&mut self, at: DateTime<Utc>, watch: &Path, rule: &str) -> &mut Bucket {
+        let hour = at.duration_trunc(Duration::hours(1)).unwrap_or(at);
+        self.buckets
+            .entry((hour, watch.to_path_buf(), rule.to_string()))
+            .or_insert_with(|| Bucket {
+                hour,
+                watch: watch.to_path_buf(),
+                rule: rule.to_string(),
+                processed: 0,
+                failed: 0,
+                unmatched: 0,
+                bytes: 0,
+                destinations: BTreeMap::new(),
+            })
+    }
+
+    /// Add another set of counts to this one
+    pub fn merge(&mut self, other: &Stats) {
+        for bucket in other.buckets.values() {
+            self.merge_bucket(bucket);
+        }
+    }
+
+    fn merge_bucket(&mut self, bucket: &Bucket) {
+        self.buckets
+            .entry((bucket.hour, bucket.watch.clone(), bucket.rule.clone()))
+            .and_modify(|b| b.add(bucket))
+            .or_insert_with(|| bucket.clone());
+    }
+
+    /// Drop hours that ended more than `days` before `now`
+    pub fn prune(&mut self, now: DateTime<Utc>, days: u32) {
+        let cutoff = now - Duration::days(i64::from(days));
+        self.buckets
+            .retain(|(hour, _, _), _| *hour + Duration::hours(1) > cutoff);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// All buckets, oldest hour first
+    pub fn buckets(&self) -> Vec<&Bucket> {
+        let mut buckets: Vec<&Bucket> = self.buckets.values().collect();
+        buckets.sort_by(|a, b| (a.hour, &a.watch, &a.rule).cmp(&(b.hour, &b.watch, &b.rule)));
+        buckets
+    }
+
+    /// Counts of the `slots` periods of `slot_hours` hours up to the end of
+    /// the current hour
+    pub fn summary(&self, now: DateTime<Utc>, slots: usize, slot_hours: i64) -> Summary {
+        let slot_hours = slot_hours.max(1);
+        let end = now.duration_trunc(Duration::hours(1)).unwrap_or(now) + Duration::hours(1);
+        let start = end - Duration::hours(slot_hours * slots as i64);
+
+        let mut summary = Summary {
+            processed: vec![0; slots],
+            failed: vec![0; slots],
+            ..Default::default()
+        };
+        let mut rules: IndexMap<&str, RuleTotals> = IndexMap::new();
+        let mut destinations: HashMap<&Path, u64> = HashMap::new();
+        for bucket in self.buckets.values() {
+            if bucket.hour < start || bucket.hour >= end {
+                continue;
+            }
+            let slot = ((bucket.hour - start).num_hours() / slot_hours) as usize;
+            summary.processed[slot] += bucket.processed;
+            summary.failed[slot] += bucket.failed;
+            summary.unmatched += bucket.unmatched;
+            if bucket.processed > 0 || bucket.failed > 0 {
+                let totals = rules.entry(&bucket.rule).or_insert_with(|| RuleTotals {
+                    rule: bucket.rule.clone(),
+                    ..Default::default()
+                });
+                totals.processed += bucket.processed;
+                totals.failed += bucket.failed;
+                totals.bytes += bucket.bytes;
+            }
+            for (folder, files) in &bucket.destinations {
+                *destinations.entry(folder).or_default() += files;
+            }
+        }
+
+        summary.rules = rules.into_values().collect();
+        summary
+            .rules
+            .sort_by(|a, b| b.processed.cmp(&a.processed).then(a.rule.cmp(&b.rule)));
+        summary.destinations = destinations
+            .into_iter()
+            .map(|(folder, files)| (folder.to_path_buf(), files))
+            .collect();
+        summary
+            .destinations
+            .sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        summary
+    }
+
+    /// CSV with a header row; hours are in local time (RFC 3339)
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("hour,watch,rule,processed,failed,unmatched\n");
+        for bucket in self.buckets() {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                bucket.hour.with_timezone(&Local).to_rfc3339(),
+                csv_field(&bucket.watch.display().to_string()),
+                csv_field(&bucket.rule),
+                bucket.processed,
+                bucket.failed,
+                bucket.unmatched
+            ));
+        }
+        csv
+    }
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Days of statistics to keep (0 = don't collect)
+static RETENTION_DAYS: AtomicU32 = AtomicU32::new(0);
+
+/// Counts not written out yet
+static PENDING: LazyLock<Mutex<(Stats, Instant)>> =
+    LazyLock::new(|| Mutex::new((Stats::default(), Instant::now())));
+
+/// Set how many days of statistics are kept (0 = don't collect)
+pub fn init(retention_days: u32) {
+    RETENTION_DAYS.store(retention_days, Ordering::Relaxed);
+}
+
+/// Count a file for a watch and rule
+pub fn record(watch: &Path, rule: &str, counter: Counter) {
+    if RETENTION_DAYS.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.0.record(Utc::now(), watch, rule, counter);
+    }
+}
+
+/// Count the size and destination folder of a file a rule moved or copied
+pub fn record_filed(watch: &Path, rule: &str, bytes: u64, folder: &Path) {
+    if RETENTION_DAYS.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    if let Ok(mut pending) = PENDING.lock() {
+        pending
+            .0
+            .record_filed(Utc::now(), watch, rule, bytes, folder);
+    }
+}
+
+/// The statistics file with the counts not written out yet
+pub fn current() -> Stats {
+    let mut stats = Stats::default_path()
+        .map(|path| Stats::load(&path))
+        .unwrap_or_default();
+    if let Ok(pending) = PENDING.lock() {
+        stats.merge(&pending.0);
+    }
+    stats
+}
+
+/// Write out collected counts if the last write was a while ago
+pub fn flush_if_due() {
+    let due = PENDING
+        .lock()
+        .is_ok_and(|pending| !pending.0.is_empty() && pending.1.elapsed() >= FLUSH_INTERVAL);
+    if due {
+        flush();
+    }
+}
+
+/// Merge collected counts into the statistics file, dropping expired hours
+pub fn flush() {
+    let Some(path) = Stats::default_path() else {
+        return;
+    };
+    let Ok(mut pending) = PENDING.lock() else {
+        return;
+    };
+    pending.1 = Instant::now();
+    if pending.0.is_empty() {
+        return;
+    }
+    let mut stats = Stats::load(&path);
+    stats.merge(&pending.0);
+    stats.prune(Utc::now(), RETENTION_DAYS.load(Ordering::Relaxed));
+    match stats.save(&path) {
+        Ok(()) => pending.0 = Stats::default(),
+        Err(e) => warn!("{:#}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_hourly_buckets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        let downloads = Path::new("/home/me/Downloads");
+        let at = |h: u32, m: u32| Utc.with_ymd_and_hms(2026, 10, 1, h, m, 0).unwrap();
+
+        let mut stats = Stats::default();
+        stats.record(at(9, 5), downloads, "PDFs", Counter::Processed);
+        stats.record(at(9, 55), downloads, "PDFs", Counter::Processed);
+        stats.record(at(10, 1), downloads, "PDFs", Counter::Failed);
+        stats.save(&path).unwrap();
+
+        // A second writer's counts are added to the file's
+        let mut other = Stats::default();
+        other.record(at(9, 30), downloads, "PDFs", Counter::Processed);
+        other.record(at(9, 30), downloads, "", Counter::Unmatched);
+        let mut merged = Stats::load(&path);
+        merged.merge(&other);
+
+        let buckets = merged.buckets();
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[0].hour, at(9, 0));
+        assert_eq!(buckets[0].rule, "");
+        assert_eq!(buckets[0].unmatched, 1);
+        assert_eq!(buckets[1].rule, "PDFs");
+        assert_eq!(buckets[1].processed, 3);
+        assert_eq!(buckets[2].failed, 1);
+
+        merged.prune(at(10, 30) + Duration::days(1), 1);
+        assert_eq!(merged.buckets().len(), 1);
+
+        let mut quoted = Stats::default();
+        quoted.record(
+            at(9, 0),
+            downloads,
+            "Invoices, \"paid\"",
+            Counter::Processed,
+        );
+        let csv = quoted.to_csv();
+        assert!(csv.starts_with("hour,watch,rule,processed,failed,unmatched\n"));
+        assert!(csv.contains(",/home/me/Downloads,\"Invoices, \"\"paid\"\"\",1,0,0\n"));
+    }
+
+    #[test]
+    fn test_summary() {
+        let downloads = Path::new("/home/me/Downloads");
+        let docs = Path::new("/home/me/Documents");
+        let at = |d: u32, h: u32| Utc.with_ymd_and_hms(2026, 10, d, h, 10, 0).unwrap();
+
+        let mut stats = Stats::default();
+        for _ in 0..2 {
+            stats.record(at(2, 9), downloads, "PDFs", Counter::Processed);
+            stats.record_filed(at(2, 9), downloads, "PDFs", 1000, docs);
+        }
+        stats.record(at(2, 11), downloads, "Music", Counter::Processed);
+        stats.record_filed(at(2, 11), downloads, "Music", 5, Path::new("/music"));
+        stats.record(at(2, 11), downloads, "Music", Counter::Failed);
+        stats.record(at(2, 11), downloads, "", Counter::Unmatched);
+        // Before the period
+        stats.record(at(1, 9), downloads, "Music", Counter::Processed);
+
+        // Merging keeps the sizes and destinations
+        let mut merged = Stats::default();
+        merged.merge(&stats);
+
+        let summary = merged.summary(at(2, 11), 12, 2);
+        assert_eq!(summary.processed.len(), 12);
+        // Slots of two hours ending with 11:00-12:00
+        assert_eq!(summary.processed[10], 2);
+        assert_eq!(summary.processed[11], 1);
+        assert_eq!(summary.failed[11], 1);
+        assert_eq!(summary.unmatched, 1);
+        assert_eq!(
+            summary.rules,
+            vec![
+                RuleTotals {
+                    rule: "PDFs".to_string(),
+                    processed: 2,
+                    failed: 0,
+                    bytes: 2000,
+                },
+                RuleTotals {
+                    rule: "Music".to_string(),
+                    processed: 1,
+                    failed: 1,
+                    bytes: 5,
+                },
+            ]
+        );
+        assert_eq!(summary.total().bytes, 2005);
+        assert_eq!(
+            summary.destinations,
+            vec![(docs.to_path_buf(), 2), (PathBuf::from("/music"), 1)]
+        );
+    }
+}
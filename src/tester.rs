@@ -0,0 +1,201 @@
+//! Rule tester
+//!
+//! Checks one file against every rule and reports each condition's result,
+//! which rules' actions would run, and why the others wouldn't, so a config
+//! whose rules don't pick a file up can be debugged without running anything.
+//! The TUI's rule tester shows it.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, WatchConfig};
+use crate::rules::{RuleEngine, RuleExplanation, Verdict};
+use crate::watcher::DirFilter;
+
+/// Every rule checked against one file
+#[derive(Debug, Clone)]
+pub struct TestReport {
+    /// The file tested (absolute)
+    pub path: PathBuf,
+    /// The watched folder the file is in (expanded), if any
+    pub watch: Option<PathBuf>,
+    /// Rules in config order
+    pub rules: Vec<RuleExplanation>,
+    /// The watch's `unmatched_action` type, if it would run instead
+    pub unmatched_action: Option<&'static str>,
+    /// Why the watcher wouldn't look at the file at all
+    pub warnings: Vec<String>,
+}
+
+impl TestReport {
+    /// Rules whose actions would run
+    pub fn selected(&self) -> impl Iterator<Item = &RuleExplanation> {
+        self.rules.iter().filter(|r| r.verdict == Verdict::Selected)
+    }
+}
+
+/// Test a file (or folder) against the config's rules. A path in a watched
+/// folder is checked with that watch's `rules` filter.
+pub fn test(config: &Config, path: &Path) -> Result<TestReport> {
+    let path = std::path::absolute(crate::expand_path(path))?;
+    if !path.exists() {
+        anyhow::bail!("{} doesn't exist", path.display());
+    }
+
+    let mut warnings = Vec::new();
+    let (root, watch) = find_watch(config, &path).unzip();
+    match (&root, watch) {
+        (Some(root), Some(watch)) => {
+            if !DirFilter::from_watch(watch).includes(root, &path, path.is_dir()) {
+                warnings.push(
+                    "It's in a folder the watch excludes (exclude_dirs, max_depth or a symlink)"
+                        .to_string(),
+                );
+            }
+        }
+        _ => warnings
+            .push("It isn't in a watched folder, so the daemon won't process it".to_string()),
+    }
+
+    let allowed = watch
+        .map(|watch| watch.rules.as_slice())
+        .filter(|rules| !rules.is_empty());
+    let engine = RuleEngine::new(config.rules.clone());
+    let rules = engine.explain(&path, allowed);
+
+    // Files no file rule selected get the watch's catch-all action
+    let unmatched_action = watch
+        .and_then(|watch| watch.unmatched_action.as_ref())
+        .filter(|_| {
+            path.is_file()
+                && !rules
+                    .iter()
+                    .any(|r| !r.folder && r.verdict == Verdict::Selected)
+        })
+        .map(|action| action.kind());
+
+    Ok(TestReport {
+        watch: root,
+        path,
+        rules,
+        unmatched_action,
+        warnings,
+    })
+}
+
+/// The local watch a path is in, with its expanded folder (the innermost
+/// one if watches are nested)
+fn find_watch<'a>(config: &'a Config, path: &Path) -> Option<(PathBuf, &'a WatchConfig)> {
+    config
+        .watches
+        .iter()
+        .filter(|watch| !crate::sftp::is_remote(&watch.path))
+        .map(|watch| (crate::expand_path(&watch.path), watch))
+        .filter(|(root, watch)| {
+            path != root
+                && path.starts_with(root)
+                && (watch.recursive || path.parent() == Some(root.as_path()))
+        })
+        .max_by_key(|(root, _)| root.components().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Action, Condition, Rule};
+
+    #[test]
+    fn test_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("report.pdf");
+        std::fs::write(&file, "x").unwrap();
+
+        let pdf = Condition {
+            extension: Some("pdf".to_string()),
+            ..Default::default()
+        };
+        let mut stop = Rule::new("Stop", pdf.clone(), Action::Trash);
+        stop.stop_processing = true;
+        let mut big = Rule::new(
+            "Big PDFs",
+            Condition {
+                size_greater_than: Some(1000),
+                ..pdf.clone()
+            },
+            Action::Delete,
+        );
+        big.enabled = false;
+        let config = Config {
+            watches: vec![WatchConfig {
+                path: dir.path().to_path_buf(),
+                rules: vec![
+                    "Stop".to_string(),
+                    "Big PDFs".to_string(),
+                    "Later".to_string(),
+                ],
+                ..Default::default()
+            }],
+            rules: vec![
+                Rule::new(
+                    "Text",
+                    Condition {
+                        extension: Some("txt".to_string()),
+                        ..Default::default()
+                    },
+                    Action::Trash,
+                ),
+                stop,
+                big,
+                Rule::new("Later", pdf.clone(), Action::Trash),
+                Rule::new("Elsewhere", pdf, Action::Trash),
+            ],
+            ..Default::default()
+        };
+
+        let report = test(&config, &file).unwrap();
+        assert_eq!(report.watch.as_deref(), Some(dir.path()));
+        assert!(report.warnings.is_empty());
+        let verdicts: Vec<_> = report.rules.iter().map(|r| r.verdict.clone()).collect();
+        assert_eq!(
+            verdicts,
+            vec![
+                Verdict::NotInWatch,
+                Verdict::Selected,
+                Verdict::Disabled,
+                Verdict::Stopped {
+                    by: "Stop".to_string()
+                },
+                Verdict::NotInWatch,
+            ]
+        );
+        // Every condition is reported, not just the first that fails
+        let checks = &report.rules[2].checks;
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].passed);
+        assert_eq!(checks[1].name, "size_greater_than");
+        assert_eq!(checks[1].actual, "1 bytes");
+        assert!(!checks[1].passed);
+
+        // The checks agree with the engine
+        let engine = RuleEngine::new(config.rules.clone());
+        for rule in engine.rules() {
+            let passed = rule
+                .condition
+                .explain(&file, false)
+                .iter()
+                .all(|c| c.passed);
+            assert_eq!(
+                passed,
+                rule.condition.matches(&file).unwrap(),
+                "{}",
+                rule.name
+            );
+        }
+
+        let outside = tempfile::tempdir().unwrap();
+        let other = outside.path().join("a.pdf");
+        std::fs::write(&other, "x").unwrap();
+        assert_eq!(test(&config, &other).unwrap().warnings.len(), 1);
+        assert!(test(&config, &outside.path().join("missing.pdf")).is_err());
+    }
+}
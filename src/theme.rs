@@ -4,17 +4,55 @@
 //! Theme palettes are provided by the `ratatui-themes` crate,
 //! with extended UI styling through `ThemeColors`.
 
+use anyhow::{Context, Result};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui_themes::{ThemeName, ThemePalette};
-use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::config::{ColorMode, CustomTheme};
 
 /// Theme wrapper around `ThemeName` from ratatui-themes.
 ///
 /// This provides Hazelnut-specific functionality like loading from config
-/// and creating extended UI color palettes.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(transparent)]
-pub struct Theme(pub ThemeName);
+/// and creating extended UI color palettes. A custom theme carries its own
+/// colors; `name` is then the theme it's based on.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Theme {
+    name: ThemeName,
+    custom: Option<ThemeColors>,
+    depth: ColorDepth,
+}
+
+/// Colors the terminal shows, resolved from `ColorMode`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorDepth {
+    #[default]
+    Truecolor,
+    Ansi256,
+}
+
+impl ColorDepth {
+    /// Resolve `auto` from `$COLORTERM` (or a `*-direct` `$TERM`, or Windows
+    /// Terminal's `$WT_SESSION`)
+    pub fn detect(mode: ColorMode) -> Self {
+        match mode {
+            ColorMode::Truecolor => Self::Truecolor,
+            ColorMode::Ansi256 => Self::Ansi256,
+            ColorMode::Auto => {
+                let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+                let term = std::env::var("TERM").unwrap_or_default();
+                if matches!(colorterm.as_str(), "truecolor" | "24bit")
+                    || term.ends_with("-direct")
+                    || std::env::var_os("WT_SESSION").is_some()
+                {
+                    Self::Truecolor
+                } else {
+                    Self::Ansi256
+                }
+            }
+        }
+    }
+}
 
 impl Theme {
     /// Get all available theme names.
@@ -26,54 +64,143 @@ impl Theme {
     /// Get the next theme in rotation
     #[must_use]
     pub fn next(&self) -> Theme {
-        Theme(self.0.next())
+        self.with_name(self.name.next())
+    }
+
+    /// A built-in theme shown with this theme's color depth
+    #[must_use]
+    pub fn with_name(&self, name: ThemeName) -> Theme {
+        Theme {
+            name,
+            custom: None,
+            depth: self.depth,
+        }
     }
 
     /// Get the display name for the theme.
     #[must_use]
     pub fn name(&self) -> &'static str {
-        self.0.display_name()
+        if self.is_custom() {
+            "Custom"
+        } else {
+            self.name.display_name()
+        }
     }
 
-    /// Load theme from config or use default
-    pub fn load(config: &crate::config::Config) -> Theme {
-        config
-            .general
-            .theme
-            .as_ref()
-            .and_then(|name| name.parse::<ThemeName>().ok())
-            .map(Theme::from)
-            .unwrap_or_default()
+    /// Whether this is the config's `[theme.custom]` theme
+    #[must_use]
+    pub fn is_custom(&self) -> bool {
+        self.custom.is_some()
+    }
+
+    /// Load theme from config or use default. Only a custom theme with an
+    /// invalid color is an error; unknown theme names fall back to the default.
+    pub fn load(config: &crate::config::Config) -> Result<Theme> {
+        let depth = ColorDepth::detect(config.theme.color_mode);
+        let name = config.general.theme.as_deref();
+        if let Some(custom) = &config.theme.custom
+            && name.is_none_or(|name| name == "custom")
+        {
+            return Self::custom(custom, depth).context("Invalid [theme.custom]");
+        }
+        Ok(Theme {
+            name: name
+                .and_then(|name| name.parse::<ThemeName>().ok())
+                .unwrap_or_default(),
+            custom: None,
+            depth,
+        })
+    }
+
+    /// A theme from `[theme.custom]` colors, over its `base` theme's
+    pub fn custom(custom: &CustomTheme, depth: ColorDepth) -> Result<Theme> {
+        let name = match &custom.base {
+            Some(base) => base
+                .parse::<ThemeName>()
+                .map_err(|_| anyhow::anyhow!("Unknown base theme '{}'", base))?,
+            None => ThemeName::default(),
+        };
+        let mut palette = name.palette();
+        for (key, value, color) in [
+            ("background", &custom.background, &mut palette.bg),
+            ("foreground", &custom.foreground, &mut palette.fg),
+            ("muted", &custom.muted, &mut palette.muted),
+            ("accent", &custom.accent, &mut palette.accent),
+            ("secondary", &custom.secondary, &mut palette.secondary),
+            ("selection", &custom.selection, &mut palette.selection),
+            ("success", &custom.success, &mut palette.success),
+            ("warning", &custom.warning, &mut palette.warning),
+            ("error", &custom.error, &mut palette.error),
+            ("info", &custom.info, &mut palette.info),
+        ] {
+            if let Some(value) = value {
+                *color = parse_color(value).with_context(|| format!("{} = \"{}\"", key, value))?;
+            }
+        }
+        Ok(Theme {
+            name,
+            custom: Some(ThemeColors::from_palette(palette)),
+            depth,
+        })
+    }
+
+    /// Built-in themes, then the config's custom theme if it has a valid one
+    #[must_use]
+    pub fn choices(&self, config: &crate::config::Config) -> Vec<Theme> {
+        let mut themes: Vec<Theme> = Self::all()
+            .iter()
+            .map(|&name| self.with_name(name))
+            .collect();
+        if let Some(custom) = &config.theme.custom
+            && let Ok(theme) = Self::custom(custom, self.depth)
+        {
+            themes.push(theme);
+        }
+        themes
     }
 
     /// Get the color palette for this theme
     #[must_use]
     pub fn colors(&self) -> ThemeColors {
-        ThemeColors::from_palette(self.0.palette())
+        let colors = self
+            .custom
+            .unwrap_or_else(|| ThemeColors::from_palette(self.name.palette()));
+        match self.depth {
+            ColorDepth::Truecolor => colors,
+            ColorDepth::Ansi256 => colors.to_ansi256(),
+        }
     }
 
-    /// Get the raw color palette for this theme.
+    /// Get the raw color palette of the built-in theme (the base theme, for
+    /// a custom one).
     #[must_use]
     pub fn palette(&self) -> ThemePalette {
-        self.0.palette()
+        self.name.palette()
     }
 
-    /// Get the inner ThemeName
+    /// Get the inner ThemeName (the base theme, for a custom one)
     #[must_use]
     pub fn inner(&self) -> ThemeName {
-        self.0
+        self.name
     }
 
     /// Get the kebab-case slug for config files
     #[must_use]
     pub fn slug(&self) -> &'static str {
-        self.0.slug()
+        if self.is_custom() {
+            "custom"
+        } else {
+            self.name.slug()
+        }
     }
 }
 
 impl From<ThemeName> for Theme {
     fn from(name: ThemeName) -> Self {
-        Theme(name)
+        Theme {
+            name,
+            ..Default::default()
+        }
     }
 }
 
@@ -83,11 +210,49 @@ impl std::fmt::Display for Theme {
     }
 }
 
+/// Parse `"#rrggbb"`, a color name or a 256-color index
+fn parse_color(value: &str) -> Result<Color> {
+    Color::from_str(value.trim()).map_err(|_| {
+        anyhow::anyhow!("Not a color: use \"#rrggbb\", a name like \"lightblue\" or 0-255")
+    })
+}
+
+/// The 256-color palette entry closest to an RGB color: the nearest of the
+/// 6×6×6 color cube and the grayscale ramp
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |c: u8| -> usize {
+        (0..LEVELS.len())
+            .min_by_key(|&i| (LEVELS[i] as i32 - c as i32).abs())
+            .unwrap_or(0)
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| -> i32 {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    // Gray ramp: 232..=255 are 8, 18, ..., 238
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * gray_step;
+    let gray = (gray_level, gray_level, gray_level);
+
+    if distance(gray) < distance(cube) {
+        232 + gray_step
+    } else {
+        cube_index as u8
+    }
+}
+
 /// Extended color palette for UI elements.
 ///
 /// This provides pre-built styles and derived colors for Hazelnut's UI,
 /// based on a theme's base palette.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ThemeColors {
     // Base colors (from palette)
     pub bg: Color,
@@ -152,6 +317,37 @@ impl ThemeColors {
         }
     }
 
+    /// The same colors from the 256-color palette, for terminals without
+    /// truecolor
+    #[must_use]
+    pub fn to_ansi256(mut self) -> Self {
+        for color in [
+            &mut self.bg,
+            &mut self.bg_secondary,
+            &mut self.bg_highlight,
+            &mut self.fg,
+            &mut self.fg_dim,
+            &mut self.fg_muted,
+            &mut self.primary,
+            &mut self.secondary,
+            &mut self.accent,
+            &mut self.success,
+            &mut self.warning,
+            &mut self.error,
+            &mut self.info,
+            &mut self.border,
+            &mut self.border_focus,
+            &mut self.selection,
+            &mut self.logo_primary,
+            &mut self.logo_secondary,
+        ] {
+            if let Color::Rgb(r, g, b) = *color {
+                *color = Color::Indexed(ansi256(r, g, b));
+            }
+        }
+        self
+    }
+
     /// Adjust color brightness
     fn adjust_brightness(color: Color, amount: i16) -> Color {
         if let Color::Rgb(r, g, b) = color {
@@ -285,3 +481,52 @@ impl ThemeColors {
             .add_modifier(Modifier::BOLD)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_theme() {
+        let custom = CustomTheme {
+            accent: Some("#ff8800".to_string()),
+            selection: Some("208".to_string()),
+            error: Some("lightred".to_string()),
+            ..Default::default()
+        };
+        let theme = Theme::custom(&custom, ColorDepth::Truecolor).unwrap();
+        assert_eq!(theme.slug(), "custom");
+        let colors = theme.colors();
+        assert_eq!(colors.primary, Color::Rgb(255, 136, 0));
+        assert_eq!(colors.selection, Color::Indexed(208));
+        assert_eq!(colors.error, Color::LightRed);
+
+        let theme = Theme::custom(&custom, ColorDepth::Ansi256).unwrap();
+        assert_eq!(theme.colors().primary, Color::Indexed(208));
+
+        let config = crate::Config::parse(
+            "[theme]\ncolor_mode = \"256\"\n[theme.custom]\nhighlight = \"#000000\"\n",
+            std::path::Path::new("config.toml"),
+        )
+        .unwrap();
+        let theme = Theme::load(&config).unwrap();
+        assert!(theme.is_custom());
+        assert_eq!(theme.colors().selection, Color::Indexed(16));
+        assert_eq!(theme.choices(&config).last(), Some(&theme));
+
+        let bad = CustomTheme {
+            accent: Some("orangeish".to_string()),
+            ..Default::default()
+        };
+        let error = Theme::custom(&bad, ColorDepth::Truecolor).unwrap_err();
+        assert!(format!("{:#}", error).contains("accent = \"orangeish\""));
+    }
+
+    #[test]
+    fn test_ansi256() {
+        assert_eq!(ansi256(0, 0, 0), 16);
+        assert_eq!(ansi256(255, 255, 255), 231);
+        assert_eq!(ansi256(128, 128, 128), 244);
+        assert_eq!(ansi256(255, 0, 0), 196);
+    }
+}
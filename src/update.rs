@@ -0,0 +1,338 @@
+//! GitHub Releases: the fallback for update checks, and `hazelnut self-update`
+//!
+//! Update checks ask crates.io first, and the latest GitHub release when
+//! crates.io can't be reached. Installs from a release archive have no
+//! package manager to update them, so `self-update` downloads the archive
+//! for this platform, checks it against the release's `SHA256SUMS.txt` and
+//! renames each binary over the old one. An update that fails or is
+//! interrupted leaves the old binaries in place.
+
+use crate::{VERSION, VersionCheck};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/ricardodantas/hazelnut/releases/latest";
+
+/// How long downloading an archive may take
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Binaries in a release archive, installed next to the running one
+#[cfg(not(windows))]
+const BINARIES: &[&str] = &["hazelnut", "hazelnutd"];
+#[cfg(windows)]
+const BINARIES: &[&str] = &["hazelnut.exe", "hazelnutd.exe"];
+
+/// A published release and its downloads
+#[derive(Debug, Clone)]
+pub struct Release {
+    /// Version, without the tag's `v`
+    pub version: String,
+    assets: Vec<(String, String)>,
+}
+
+impl Release {
+    /// Compare with the running version
+    pub fn version_check(&self) -> VersionCheck {
+        if crate::version_is_newer(&self.version, VERSION) {
+            VersionCheck::UpdateAvailable {
+                latest: self.version.clone(),
+                current: VERSION.to_string(),
+            }
+        } else {
+            VersionCheck::UpToDate
+        }
+    }
+
+    /// Download URL of an asset
+    fn asset(&self, name: &str) -> Option<&str> {
+        self.assets
+            .iter()
+            .find(|(asset, _)| asset == name)
+            .map(|(_, url)| url.as_str())
+    }
+}
+
+/// Look up the latest release on GitHub
+pub fn latest_release(timeout: Duration) -> Result<Release, String> {
+    let json: serde_json::Value = agent(timeout)
+        .get(LATEST_RELEASE_URL)
+        .set("Accept", "application/vnd.github+json")
+        .call()
+        .map_err(|e| format!("Request failed: {}", e))?
+        .into_json()
+        .map_err(|e| format!("Failed to parse response: {}", e))?;
+
+    let version = json
+        .get("tag_name")
+        .and_then(|tag| tag.as_str())
+        .map(|tag| tag.trim_start_matches('v').to_string())
+        .ok_or("Could not parse GitHub response")?;
+    let assets = json
+        .get("assets")
+        .and_then(|assets| assets.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|asset| {
+            Some((
+                asset.get("name")?.as_str()?.to_string(),
+                asset.get("browser_download_url")?.as_str()?.to_string(),
+            ))
+        })
+        .collect();
+    Ok(Release { version, assets })
+}
+
+/// Target triple of the release build for this platform, if there is one
+pub fn release_target() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") if cfg!(target_env = "musl") => Some("x86_64-unknown-linux-musl"),
+        ("linux", "x86_64") => Some("x86_64-unknown-linux-gnu"),
+        ("linux", "aarch64") => Some("aarch64-unknown-linux-gnu"),
+        ("macos", "x86_64") => Some("x86_64-apple-darwin"),
+        ("macos", "aarch64") => Some("aarch64-apple-darwin"),
+        ("windows", "x86_64") => Some("x86_64-pc-windows-msvc"),
+        _ => None,
+    }
+}
+
+/// Install the latest release over the running binaries. Returns the
+/// version installed, or `None` when this is already the latest.
+pub fn self_update() -> Result<Option<String>, String> {
+    let release = latest_release(Duration::from_secs(15))?;
+    if !matches!(
+        release.version_check(),
+        VersionCheck::UpdateAvailable { .. }
+    ) {
+        return Ok(None);
+    }
+    install(&release)?;
+    Ok(Some(release.version))
+}
+
+/// Download a release's archive for this platform, verify it and replace
+/// the binaries next to the running one with those inside
+pub fn install(release: &Release) -> Result<Vec<PathBuf>, String> {
+    let target = release_target().ok_or("There are no release builds for this platform")?;
+    let extension = if cfg!(windows) { "zip" } else { "tar.gz" };
+    let name = format!("hazelnut-{}-{}.{}", release.version, target, extension);
+    let url = release
+        .asset(&name)
+        .ok_or_else(|| format!("Release {} has no {}", release.version, name))?;
+    let sums_url = release.asset("SHA256SUMS.txt").ok_or_else(|| {
+        format!(
+            "Release {} has no SHA256SUMS.txt to check the download against",
+            release.version
+        )
+    })?;
+
+    let archive = download(url)?;
+    let sums = String::from_utf8_lossy(&download(sums_url)?).into_owned();
+    let expected = checksum_for(&sums, &name)
+        .ok_or_else(|| format!("SHA256SUMS.txt doesn't list {}", name))?;
+    let actual = format!("{:x}", Sha256::digest(&archive));
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "Checksum mismatch for {} (expected {}, got {})",
+            name, expected, actual
+        ));
+    }
+
+    let exe = std::env::current_exe()
+        .and_then(|exe| exe.canonicalize())
+        .map_err(|e| format!("Couldn't find the running binary: {}", e))?;
+    let dir = exe
+        .parent()
+        .ok_or("Couldn't find the running binary's folder")?;
+    let binaries = unpack(&archive, BINARIES)?;
+    let mut installed = Vec::new();
+    for (binary, contents) in binaries {
+        let dest = dir.join(binary);
+        replace(&dest, &contents)
+            .map_err(|e| format!("Couldn't replace {}: {}", dest.display(), e))?;
+        installed.push(dest);
+    }
+    Ok(installed)
+}
+
+fn agent(timeout: Duration) -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .user_agent(&format!("hazelnut/{}", VERSION))
+        .build()
+}
+
+fn download(url: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    agent(DOWNLOAD_TIMEOUT)
+        .get(url)
+        .call()
+        .map_err(|e| format!("Download failed: {}", e))?
+        .into_reader()
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Download failed: {}", e))?;
+    Ok(bytes)
+}
+
+/// The hash `sha256sum` listed for a file
+fn checksum_for<'a>(sums: &'a str, name: &str) -> Option<&'a str> {
+    sums.lines().find_map(|line| {
+        let (hash, file) = line.split_once(char::is_whitespace)?;
+        // Binary mode marks the name with `*`
+        let file = file.trim_start().trim_start_matches('*');
+        (file.trim_start_matches("./") == name).then_some(hash)
+    })
+}
+
+/// The named binaries in a release archive; it must have every one
+fn unpack(archive: &[u8], names: &[&str]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut found = Vec::new();
+
+    #[cfg(not(windows))]
+    {
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+        let entries = tar
+            .entries()
+            .map_err(|e| format!("Couldn't read the archive: {}", e))?;
+        for entry in entries {
+            let mut entry = entry.map_err(|e| format!("Couldn't read the archive: {}", e))?;
+            let path = entry
+                .path()
+                .map_err(|e| format!("Couldn't read the archive: {}", e))?;
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if names.contains(&name) && !found.iter().any(|(found, _)| found == name) {
+                let name = name.to_string();
+                let mut contents = Vec::new();
+                entry
+                    .read_to_end(&mut contents)
+                    .map_err(|e| format!("Couldn't read {} from the archive: {}", name, e))?;
+                found.push((name, contents));
+            }
+        }
+    }
+    #[cfg(windows)]
+    {
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive))
+            .map_err(|e| format!("Couldn't read the archive: {}", e))?;
+        for name in names {
+            if let Ok(mut file) = zip.by_name(name) {
+                let mut contents = Vec::new();
+                file.read_to_end(&mut contents)
+                    .map_err(|e| format!("Couldn't read {} from the archive: {}", name, e))?;
+                found.push((name.to_string(), contents));
+            }
+        }
+    }
+
+    if let Some(missing) = names
+        .iter()
+        .find(|name| !found.iter().any(|(found, _)| found == *name))
+    {
+        return Err(format!("The archive has no {}", missing));
+    }
+    Ok(found)
+}
+
+/// Write a binary beside `dest` and rename it into place, so `dest` is
+/// always either the old binary or the whole new one
+fn replace(dest: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let name = dest
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let staged = dest.with_file_name(format!(".{}.new", name));
+    let result = (|| {
+        let mut file = std::fs::File::create(&staged)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dest)
+                .map_or(0o755, |metadata| metadata.permissions().mode() & 0o7777);
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode))?;
+        }
+        // A running executable can't be overwritten on Windows, but it can
+        // be renamed out of the way
+        #[cfg(windows)]
+        if dest.exists() {
+            let old = dest.with_file_name(format!(".{}.old", name));
+            let _ = std::fs::remove_file(&old);
+            std::fs::rename(dest, &old)?;
+        }
+        std::fs::rename(&staged, dest)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&staged);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_for() {
+        let sums = "\
+0123abcd  hazelnut-1.0.0-x86_64-unknown-linux-gnu.tar.gz
+4567ef01 *./hazelnut-1.0.0-aarch64-apple-darwin.tar.gz
+";
+        assert_eq!(
+            checksum_for(sums, "hazelnut-1.0.0-x86_64-unknown-linux-gnu.tar.gz"),
+            Some("0123abcd")
+        );
+        assert_eq!(
+            checksum_for(sums, "hazelnut-1.0.0-aarch64-apple-darwin.tar.gz"),
+            Some("4567ef01")
+        );
+        assert_eq!(checksum_for(sums, "hazelnut-1.0.0.zip"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unpack_and_replace() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::fast(),
+        ));
+        for (name, contents) in [("hazelnut", "new cli"), ("hazelnutd", "new daemon")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+
+        let binaries = unpack(&archive, &["hazelnut", "hazelnutd"]).unwrap();
+        assert_eq!(binaries.len(), 2);
+        assert!(
+            unpack(&archive, &["hazelnut.exe"])
+                .unwrap_err()
+                .contains("hazelnut.exe")
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("hazelnut");
+        std::fs::write(&dest, "old cli").unwrap();
+        std::fs::set_permissions(&dest, std::fs::Permissions::from_mode(0o750)).unwrap();
+        replace(&dest, &binaries[0].1).unwrap();
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "new cli");
+        assert_eq!(
+            std::fs::metadata(&dest).unwrap().permissions().mode() & 0o7777,
+            0o750
+        );
+        assert!(!dir.path().join(".hazelnut.new").exists());
+    }
+}
@@ -0,0 +1,248 @@
+//! Batching and rate limiting for bursts of file events
+//!
+//! Unpacking an archive into a watched folder can produce thousands of files
+//! at once. Ready files go through a bounded queue that is drained at a
+//! limited rate, and a burst ends with one summary notification instead of
+//! one notification per failure.
+
+use indexmap::{IndexMap, IndexSet};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::config::GeneralConfig;
+
+/// Limits for the processing queue
+#[derive(Debug, Clone, Copy)]
+pub struct BatchLimits {
+    /// Files processed per second (0 = unlimited)
+    pub max_files_per_second: u32,
+    /// Files that can wait in the queue; further files are dropped until it drains
+    pub queue_capacity: usize,
+    /// Bursts of at least this many files get one summary notification
+    /// instead of a notification per failure
+    pub summary_threshold: usize,
+    /// Files whose rules run at the same time (1 = one after another)
+    pub workers: usize,
+}
+
+impl Default for BatchLimits {
+    fn default() -> Self {
+        Self {
+            max_files_per_second: 100,
+            queue_capacity: 100_000,
+            summary_threshold: 20,
+            workers: 4,
+        }
+    }
+}
+
+impl BatchLimits {
+    /// Limits from the `[general]` settings
+    pub fn from_config(general: &GeneralConfig) -> Self {
+        Self {
+            max_files_per_second: general.max_files_per_second,
+            queue_capacity: general.queue_capacity,
+            summary_threshold: general.batch_summary_threshold,
+            workers: general.action_workers.max(1),
+        }
+    }
+}
+
+/// What happened to the files of one burst
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BatchSummary {
+    /// Files a rule matched
+    pub processed: usize,
+    /// Files no rule matched
+    pub unmatched: usize,
+    /// Failures by rule name
+    pub failed: IndexMap<String, usize>,
+    /// Files dropped because the queue was full
+    pub dropped: usize,
+}
+
+impl BatchSummary {
+    /// Files handled so far (including failures and drops)
+    pub fn total(&self) -> usize {
+        self.processed + self.unmatched + self.failed_count() + self.dropped
+    }
+
+    /// Number of failed files
+    pub fn failed_count(&self) -> usize {
+        self.failed.values().sum()
+    }
+
+    /// Notification text, e.g. "Organized 9842 of 10000 files, 158 failed (Archive: 158)"
+    pub fn message(&self) -> String {
+        let mut message = format!("Organized {} of {} files", self.processed, self.total());
+        if self.unmatched > 0 {
+            message.push_str(&format!(", {} unmatched", self.unmatched));
+        }
+        if !self.failed.is_empty() {
+            let rules: Vec<String> = self
+                .failed
+                .iter()
+                .map(|(rule, count)| format!("{}: {}", rule, count))
+                .collect();
+            message.push_str(&format!(
+                ", {} failed ({})",
+                self.failed_count(),
+                rules.join(", ")
+            ));
+        }
+        if self.dropped > 0 {
+            message.push_str(&format!(
+                ", {} skipped (queue full; a scan will pick them up)",
+                self.dropped
+            ));
+        }
+        message
+    }
+}
+
+/// Bounded, rate-limited queue of files ready for rule processing
+#[derive(Debug)]
+pub(crate) struct BatchQueue {
+    limits: BatchLimits,
+    queue: IndexSet<PathBuf>,
+    /// Token bucket for the rate limit
+    tokens: f64,
+    last_refill: Instant,
+    /// Outcome of the current burst
+    summary: BatchSummary,
+}
+
+impl Default for BatchQueue {
+    fn default() -> Self {
+        Self::new(BatchLimits::default())
+    }
+}
+
+impl BatchQueue {
+    pub(crate) fn new(limits: BatchLimits) -> Self {
+        Self {
+            limits,
+            queue: IndexSet::new(),
+            tokens: limits.max_files_per_second as f64,
+            last_refill: Instant::now(),
+            summary: BatchSummary::default(),
+        }
+    }
+
+    /// Files whose rules can run at the same time
+    pub(crate) fn workers(&self) -> usize {
+        self.limits.workers.max(1)
+    }
+
+    /// Queue a file; returns false (and counts it as dropped) if the queue is full
+    pub(crate) fn push(&mut self, path: PathBuf) -> bool {
+        if self.queue.len() >= self.limits.queue_capacity && !self.queue.contains(&path) {
+            self.summary.dropped += 1;
+            return false;
+        }
+        self.queue.insert(path);
+        true
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Take as many files as the rate limit allows right now
+    pub(crate) fn take(&mut self) -> Vec<PathBuf> {
+        self.take_at(Instant::now())
+    }
+
+    fn take_at(&mut self, now: Instant) -> Vec<PathBuf> {
+        let rate = self.limits.max_files_per_second as f64;
+        let count = if rate == 0.0 {
+            self.queue.len()
+        } else {
+            // Allow at most one second's worth of files in a single call
+            let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * rate).min(rate);
+            let count = (self.tokens as usize).min(self.queue.len());
+            self.tokens -= count as f64;
+            count
+        };
+        self.last_refill = now;
+        self.queue.drain(..count).collect()
+    }
+
+    /// Whether the current burst (counting `waiting` files not queued yet)
+    /// is big enough to be summarized rather than notified file by file
+    pub(crate) fn is_burst(&self, waiting: usize) -> bool {
+        self.summary.total() + self.queue.len() + waiting >= self.limits.summary_threshold
+    }
+
+    pub(crate) fn record_processed(&mut self) {
+        self.summary.processed += 1;
+    }
+
+    pub(crate) fn record_unmatched(&mut self) {
+        self.summary.unmatched += 1;
+    }
+
+    pub(crate) fn record_failed(&mut self, rule: &str) {
+        *self.summary.failed.entry(rule.to_string()).or_default() += 1;
+    }
+
+    /// End the current burst, returning its summary if it was big enough to
+    /// report. Call once nothing is queued or waiting.
+    pub(crate) fn finish(&mut self) -> Option<BatchSummary> {
+        let burst = self.is_burst(0);
+        let summary = std::mem::take(&mut self.summary);
+        (burst && summary.total() > 0).then_some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rate_limit_and_capacity() {
+        let mut queue = BatchQueue::new(BatchLimits {
+            max_files_per_second: 10,
+            queue_capacity: 25,
+            ..Default::default()
+        });
+        for i in 0..30 {
+            queue.push(PathBuf::from(format!("/in/{i}")));
+        }
+        assert_eq!(queue.summary.dropped, 5);
+
+        let start = queue.last_refill;
+        assert_eq!(queue.take_at(start).len(), 10);
+        assert!(queue.take_at(start).is_empty());
+        assert_eq!(queue.take_at(start + Duration::from_millis(500)).len(), 5);
+        // Idle time doesn't build up more than one second of budget
+        assert_eq!(queue.take_at(start + Duration::from_secs(60)).len(), 10);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_burst_summary() {
+        let mut queue = BatchQueue::new(BatchLimits {
+            summary_threshold: 3,
+            ..Default::default()
+        });
+        queue.record_processed();
+        assert!(!queue.is_burst(0));
+        assert!(queue.is_burst(2));
+        assert_eq!(queue.finish(), None);
+
+        for _ in 0..3 {
+            queue.record_processed();
+        }
+        queue.record_failed("Archive");
+        queue.record_unmatched();
+        let summary = queue.finish().unwrap();
+        assert_eq!(
+            summary.message(),
+            "Organized 3 of 5 files, 1 unmatched, 1 failed (Archive: 1)"
+        );
+        assert_eq!(queue.finish(), None);
+    }
+}
@@ -0,0 +1,367 @@
+//! Subdirectory and symlink filtering for watches
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use tracing::debug;
+
+use crate::config::{SymlinkMode, WatchConfig};
+
+/// Which parts of a watched tree are included, from the watch's
+/// `exclude_dirs`, `max_depth` and `symlinks` settings
+#[derive(Debug, Clone, Default)]
+pub struct DirFilter {
+    exclude: Vec<glob::Pattern>,
+    max_depth: Option<usize>,
+    symlinks: SymlinkMode,
+}
+
+/// How a directory walk treats an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EntryKind {
+    /// A directory to descend into (subject to excludes and depth)
+    Dir,
+    /// Something to hand to the rules
+    File,
+    /// Left alone
+    Skip,
+}
+
+/// Loop detection for walks that follow symlinks: each linked folder is
+/// entered once, and never if it points back into the watched tree
+#[derive(Debug)]
+pub(crate) struct SymlinkGuard {
+    root: PathBuf,
+    visited: HashSet<PathBuf>,
+}
+
+impl SymlinkGuard {
+    pub(crate) fn new(root: &Path) -> Self {
+        Self {
+            root: std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf()),
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Whether the folder a symlink points to hasn't been walked yet
+    fn enter(&mut self, link: &Path) -> bool {
+        let Ok(target) = std::fs::canonicalize(link) else {
+            return false;
+        };
+        // Inside the watch (or inside a folder already followed) means the
+        // link is either a loop or an alias of something walked anyway
+        if target.starts_with(&self.root) || self.visited.iter().any(|v| target.starts_with(v)) {
+            debug!(
+                "Not following {} -> {} (already watched)",
+                link.display(),
+                target.display()
+            );
+            return false;
+        }
+        self.visited.insert(target);
+        true
+    }
+}
+
+impl DirFilter {
+    /// Build the filter for a watch. Invalid patterns are matched literally.
+    pub fn from_watch(watch: &WatchConfig) -> Self {
+        Self {
+            exclude: watch
+                .exclude_dirs
+                .iter()
+                .map(|p| {
+                    glob::Pattern::new(p)
+                        .unwrap_or_else(|_| glob::Pattern::new(&glob::Pattern::escape(p)).unwrap())
+                })
+                .collect(),
+            max_depth: watch.max_depth,
+            symlinks: watch.symlinks,
+        }
+    }
+
+    /// Whether every real subdirectory is included and symlinks are skipped,
+    /// so a native recursive watch covers exactly the right directories
+    pub fn is_unrestricted(&self) -> bool {
+        self.exclude.is_empty() && self.max_depth.is_none() && self.symlinks == SymlinkMode::Ignore
+    }
+
+    /// How symlinks are handled
+    pub fn symlinks(&self) -> SymlinkMode {
+        self.symlinks
+    }
+
+    /// Classify a directory entry, resolving symlinks according to the mode
+    pub(crate) fn entry_kind(
+        &self,
+        entry: &std::fs::DirEntry,
+        guard: &mut SymlinkGuard,
+    ) -> EntryKind {
+        let Ok(file_type) = entry.file_type() else {
+            return EntryKind::Skip;
+        };
+        if !file_type.is_symlink() {
+            return if file_type.is_dir() {
+                EntryKind::Dir
+            } else {
+                EntryKind::File
+            };
+        }
+        match self.symlinks {
+            SymlinkMode::Ignore => EntryKind::Skip,
+            SymlinkMode::Link => EntryKind::File,
+            SymlinkMode::Follow => match std::fs::metadata(entry.path()) {
+                Ok(metadata) if metadata.is_dir() => {
+                    if guard.enter(&entry.path()) {
+                        EntryKind::Dir
+                    } else {
+                        EntryKind::Skip
+                    }
+                }
+                Ok(_) => EntryKind::File,
+                // Dangling link
+                Err(_) => EntryKind::Skip,
+            },
+        }
+    }
+
+    /// Whether a directory named `name` at `depth` below the root should be
+    /// descended into (the root's direct children are at depth 1)
+    pub fn allows_dir(&self, name: &str, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max| depth <= max)
+            && !self.exclude.iter().any(|p| p.matches(name))
+    }
+
+    /// Whether `path` under `root` is inside the included part of the tree
+    pub fn includes(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return true;
+        };
+        let components: Vec<&std::ffi::OsStr> = relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        // The last component is the entry itself; only directories count
+        let dir_count = if is_dir {
+            components.len()
+        } else {
+            components.len().saturating_sub(1)
+        };
+        let allowed = components[..dir_count]
+            .iter()
+            .enumerate()
+            .all(|(i, name)| self.allows_dir(&name.to_string_lossy(), i + 1));
+        if !allowed || self.symlinks == SymlinkMode::Follow {
+            return allowed;
+        }
+
+        // Native recursive watches may follow linked folders on their own,
+        // so drop events that arrive through one
+        let mut current = root.to_path_buf();
+        for (i, name) in components.iter().enumerate() {
+            current.push(name);
+            let is_last = i + 1 == components.len();
+            if is_last && self.symlinks == SymlinkMode::Link {
+                break;
+            }
+            if std::fs::symlink_metadata(&current).is_ok_and(|m| m.file_type().is_symlink()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Remove `dir` if it's empty, then each parent it leaves empty, up to
+    /// but not including `root` (`remove_empty_dirs`). Excluded folders and
+    /// anything reached through a symlink are kept. Returns how many
+    /// folders were removed.
+    pub fn remove_empty_dirs(&self, root: &Path, dir: &Path) -> usize {
+        let mut current = if dir.starts_with(root) {
+            dir.to_path_buf()
+        } else {
+            match std::fs::canonicalize(dir) {
+                Ok(dir) => dir,
+                Err(_) => return 0,
+            }
+        };
+        let mut removed = 0;
+        while current != root
+            && current.starts_with(root)
+            && self.includes(root, &current, true)
+            && std::fs::remove_dir(&current).is_ok()
+        {
+            debug!("Removed empty folder {}", current.display());
+            removed += 1;
+            if !current.pop() {
+                break;
+            }
+        }
+        removed
+    }
+
+    /// All included directories under `root` (including `root` itself)
+    pub fn walk_dirs(&self, root: &Path) -> Vec<PathBuf> {
+        self.walk_dirs_below(root, root)
+    }
+
+    /// Included directories from `dir` downward, with depths counted from
+    /// `root` (used when a directory appears inside an existing watch)
+    pub fn walk_dirs_below(&self, root: &Path, dir: &Path) -> Vec<PathBuf> {
+        let mut guard = SymlinkGuard::new(root);
+        if dir != root
+            && std::fs::symlink_metadata(dir).is_ok_and(|m| m.file_type().is_symlink())
+            && (self.symlinks != SymlinkMode::Follow || !guard.enter(dir))
+        {
+            return Vec::new();
+        }
+        let start_depth = dir
+            .strip_prefix(root)
+            .map(|r| r.components().count())
+            .unwrap_or(0);
+        let mut dirs = vec![dir.to_path_buf()];
+        let mut stack = vec![(dir.to_path_buf(), start_depth)];
+        while let Some((dir, depth)) = stack.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if self.allows_dir(&entry.file_name().to_string_lossy(), depth + 1)
+                    && self.entry_kind(&entry, &mut guard) == EntryKind::Dir
+                {
+                    dirs.push(entry.path());
+                    stack.push((entry.path(), depth + 1));
+                }
+            }
+        }
+        dirs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_filter() {
+        let watch = WatchConfig {
+            path: PathBuf::from("/code"),
+            recursive: true,
+            exclude_dirs: vec!["node_modules".to_string(), ".cache".to_string()],
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let filter = DirFilter::from_watch(&watch);
+        let root = Path::new("/code");
+
+        assert!(filter.includes(root, Path::new("/code/a.txt"), false));
+        assert!(filter.includes(root, Path::new("/code/app/src/main.rs"), false));
+        assert!(!filter.includes(root, Path::new("/code/app/src/deep/x.rs"), false));
+        assert!(!filter.includes(root, Path::new("/code/app/src/deep"), true));
+        assert!(!filter.includes(root, Path::new("/code/node_modules/x.js"), false));
+        assert!(!filter.includes(root, Path::new("/code/app/.cache"), true));
+        assert!(DirFilter::default().includes(root, Path::new("/code/node_modules/x"), false));
+    }
+
+    #[test]
+    fn test_walk_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/nested/deeper")).unwrap();
+        std::fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+
+        let watch = WatchConfig {
+            exclude_dirs: vec!["node_modules".to_string()],
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let mut dirs = DirFilter::from_watch(&watch).walk_dirs(dir.path());
+        dirs.sort();
+        assert_eq!(
+            dirs,
+            vec![
+                dir.path().to_path_buf(),
+                dir.path().join("src"),
+                dir.path().join("src/nested"),
+            ]
+        );
+
+        // A directory created later keeps depths relative to the watch root
+        let below =
+            DirFilter::from_watch(&watch).walk_dirs_below(dir.path(), &dir.path().join("src"));
+        assert_eq!(below.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_empty_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["album/disc 1/extras", "album/covers", "node_modules/pkg"] {
+            std::fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        std::fs::write(root.join("album/covers/front.jpg"), "x").unwrap();
+        let filter = DirFilter::from_watch(&WatchConfig {
+            exclude_dirs: vec!["node_modules".to_string()],
+            ..Default::default()
+        });
+
+        // Up to the first folder that still holds something
+        assert_eq!(
+            filter.remove_empty_dirs(root, &root.join("album/disc 1/extras")),
+            2
+        );
+        assert!(!root.join("album/disc 1").exists());
+        assert!(root.join("album/covers/front.jpg").exists());
+
+        std::fs::remove_file(root.join("album/covers/front.jpg")).unwrap();
+        assert_eq!(
+            filter.remove_empty_dirs(root, &root.join("album/covers")),
+            2
+        );
+        assert!(root.exists());
+        // Excluded folders are kept
+        assert_eq!(
+            filter.remove_empty_dirs(root, &root.join("node_modules/pkg")),
+            0
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_modes() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let root = dir.path().join("watched");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::create_dir_all(outside.path().join("photos")).unwrap();
+        symlink(outside.path().join("photos"), root.join("photos")).unwrap();
+        // Loop back to the watch root
+        symlink(&root, root.join("sub/loop")).unwrap();
+
+        let filter = |symlinks| {
+            DirFilter::from_watch(&WatchConfig {
+                symlinks,
+                ..Default::default()
+            })
+        };
+
+        let mut dirs = filter(SymlinkMode::Follow).walk_dirs(&root);
+        dirs.sort();
+        assert_eq!(
+            dirs,
+            vec![root.clone(), root.join("photos"), root.join("sub")]
+        );
+        assert_eq!(filter(SymlinkMode::Ignore).walk_dirs(&root).len(), 2);
+        assert_eq!(filter(SymlinkMode::Link).walk_dirs(&root).len(), 2);
+
+        let linked_file = root.join("photos/a.jpg");
+        assert!(filter(SymlinkMode::Follow).includes(&root, &linked_file, false));
+        assert!(!filter(SymlinkMode::Ignore).includes(&root, &linked_file, false));
+        assert!(!filter(SymlinkMode::Link).includes(&root, &linked_file, false));
+        // The link itself is handed to rules only in link mode
+        assert!(filter(SymlinkMode::Link).includes(&root, &root.join("photos"), false));
+        assert!(!filter(SymlinkMode::Ignore).includes(&root, &root.join("photos"), false));
+    }
+}
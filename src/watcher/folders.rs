@@ -0,0 +1,172 @@
+//! Folders handled as a unit by folder rules
+//!
+//! When a watch has rules with `folder = true`, events for anything inside
+//! a folder directly in the watch are held back. The folder goes to the
+//! rules once its whole tree has stayed the same for `stable_seconds` and
+//! holds no in-progress downloads, so a project folder that is still being
+//! copied or extracted is never moved half-way.
+
+use indexmap::IndexSet;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::Stability;
+use crate::rules::FolderSummary;
+
+/// Folder trees are walked at most this often while waiting
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A folder waiting for its tree to settle
+#[derive(Debug)]
+struct PendingFolder {
+    /// Tree summary at the last check (None before the first one)
+    state: Option<FolderSummary>,
+    /// When the current state was first seen
+    since: Instant,
+    /// When the tree was last walked
+    checked: Option<Instant>,
+    /// Event paths inside the folder, released to file rules if no folder rule matches
+    held: IndexSet<PathBuf>,
+}
+
+/// Folders waiting for their contents to stop changing
+#[derive(Debug, Default)]
+pub struct PendingFolders {
+    stability: Stability,
+    folders: HashMap<PathBuf, PendingFolder>,
+}
+
+impl PendingFolders {
+    /// Create a tracker with the given settings
+    pub fn new(stability: Stability) -> Self {
+        Self {
+            stability,
+            folders: HashMap::new(),
+        }
+    }
+
+    /// Start (or keep) waiting for a folder, remembering the event path
+    pub fn hold(&mut self, folder: PathBuf, path: PathBuf) {
+        self.hold_at(folder, path, Instant::now());
+    }
+
+    fn hold_at(&mut self, folder: PathBuf, path: PathBuf, now: Instant) {
+        self.folders
+            .entry(folder)
+            .or_insert_with(|| PendingFolder {
+                state: None,
+                since: now,
+                checked: None,
+                held: IndexSet::new(),
+            })
+            .held
+            .insert(path);
+    }
+
+    /// Whether any folder is still waiting
+    pub fn is_empty(&self) -> bool {
+        self.folders.is_empty()
+    }
+
+    /// Remove and return the folders that have settled, with their held
+    /// event paths. Folders that vanished are dropped.
+    pub fn take_settled(&mut self) -> Vec<(PathBuf, Vec<PathBuf>)> {
+        self.take_settled_at(Instant::now())
+    }
+
+    fn take_settled_at(&mut self, now: Instant) -> Vec<(PathBuf, Vec<PathBuf>)> {
+        let stable_for = self.stability.stable_for();
+        let temp_patterns = self.stability.temp_patterns();
+        let mut settled = Vec::new();
+
+        self.folders.retain(|folder, pending| {
+            if pending
+                .checked
+                .is_some_and(|checked| now.duration_since(checked) < CHECK_INTERVAL)
+            {
+                return true;
+            }
+            pending.checked = Some(now);
+            let Ok(current) = FolderSummary::scan(folder, &temp_patterns) else {
+                return false;
+            };
+            if pending.state.as_ref() != Some(&current) {
+                pending.state = Some(current);
+                pending.since = now;
+                return true;
+            }
+            // Temp files inside mean a download is still in progress
+            if now.duration_since(pending.since) < stable_for || current.contains_match {
+                return true;
+            }
+            settled.push((folder.clone(), pending.held.drain(..).collect()));
+            false
+        });
+
+        settled
+    }
+}
+
+/// Whether a folder found by a scan looks complete right now: nothing in
+/// it modified within the stability window and no temp files inside
+pub fn is_settled(stability: &Stability, dir: &Path) -> bool {
+    let Ok(summary) = FolderSummary::scan(dir, &stability.temp_patterns()) else {
+        return false;
+    };
+    let recently_modified = summary
+        .modified
+        .and_then(|m| SystemTime::now().duration_since(m).ok())
+        .is_some_and(|age| age < stability.stable_for());
+    !recently_modified && !summary.contains_match
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folder_settles_as_a_unit() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
+
+        let mut pending = PendingFolders::new(Stability::default());
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        pending.hold_at(project.clone(), project.join("src"), start);
+        pending.hold_at(project.clone(), project.join("src/main.rs"), start);
+
+        // The first check only records the tree
+        assert!(pending.take_settled_at(at(0)).is_empty());
+
+        // A change deep inside restarts the wait
+        std::fs::write(project.join("src/lib.rs"), "pub mod a;").unwrap();
+        assert!(pending.take_settled_at(at(1)).is_empty());
+        assert!(pending.take_settled_at(at(2)).is_empty());
+
+        // So does an in-progress download
+        let partial = project.join("assets.zip.crdownload");
+        std::fs::write(&partial, "").unwrap();
+        assert!(pending.take_settled_at(at(3)).is_empty());
+        assert!(pending.take_settled_at(at(6)).is_empty());
+        std::fs::remove_file(&partial).unwrap();
+        assert!(pending.take_settled_at(at(7)).is_empty());
+
+        let settled = pending.take_settled_at(at(9));
+        assert_eq!(settled.len(), 1);
+        assert_eq!(settled[0].0, project);
+        assert_eq!(
+            settled[0].1,
+            vec![project.join("src"), project.join("src/main.rs")]
+        );
+        assert!(pending.is_empty());
+
+        // Folders that vanish are dropped
+        pending.hold_at(project.clone(), project.clone(), start);
+        std::fs::remove_dir_all(&project).unwrap();
+        assert!(pending.take_settled_at(at(10)).is_empty());
+        assert!(pending.is_empty());
+    }
+}
@@ -1,16 +1,27 @@
 //! Event handler for file system events
+//!
+//! Each path gets its own debounce timer: every event for it restarts the
+//! timer, and the path is released once no event arrived for
+//! `debounce_seconds`. A file that keeps being appended to waits on its own,
+//! while unrelated files go through as soon as their own timer runs out.
 
 use indexmap::IndexMap;
 use notify::Event;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
-/// Maximum number of entries in the debounce map before forcing a cleanup
+/// Maximum number of entries in the debounce maps; past it the paths
+/// waiting longest are released early and old entries are cleaned up
 const MAX_DEBOUNCE_ENTRIES: usize = 10_000;
 
-/// Debounces file system events to avoid processing the same file multiple times
+/// Debounces file system events per path
 pub struct EventHandler {
-    /// Recent events by path (IndexMap preserves insertion order for fair cleanup)
+    /// Paths waiting for their events to stop, by the time of the last one
+    /// (in the order their first event arrived)
+    waiting: IndexMap<PathBuf, Instant>,
+
+    /// Recently released or scanned paths (IndexMap preserves insertion order
+    /// for fair cleanup)
     recent: IndexMap<PathBuf, Instant>,
 
     /// Debounce duration
@@ -21,35 +32,74 @@ impl EventHandler {
     /// Create a new event handler with the given debounce duration
     pub fn new(debounce_seconds: u64) -> Self {
         Self {
+            waiting: IndexMap::new(),
             recent: IndexMap::new(),
             debounce: Duration::from_secs(debounce_seconds),
         }
     }
 
-    /// Check if an event should be processed (returns true if not recently seen)
-    pub fn should_process(&mut self, event: &Event) -> Vec<PathBuf> {
+    /// Start or restart the timers of an event's paths
+    pub fn record(&mut self, event: &Event) {
         let now = Instant::now();
-        let mut paths_to_process = Vec::new();
-
         for path in &event.paths {
-            let should_process = self
-                .recent
-                .get(path)
-                .map(|&last| now.duration_since(last) > self.debounce)
-                .unwrap_or(true);
+            self.record_at(path, now);
+        }
+    }
 
-            if should_process {
-                self.recent.insert(path.clone(), now);
-                paths_to_process.push(path.clone());
+    fn record_at(&mut self, path: &Path, now: Instant) {
+        self.waiting.insert(path.to_path_buf(), now);
+    }
+
+    /// Paths whose events stopped at least the debounce duration ago, in the
+    /// order their first event arrived. Past `MAX_DEBOUNCE_ENTRIES` waiting
+    /// paths, the ones waiting longest are released early.
+    pub fn take_ready(&mut self) -> Vec<PathBuf> {
+        self.take_ready_at(Instant::now())
+    }
+
+    fn take_ready_at(&mut self, now: Instant) -> Vec<PathBuf> {
+        let excess = self.waiting.len().saturating_sub(MAX_DEBOUNCE_ENTRIES);
+        let mut ready = Vec::new();
+        let mut index = 0;
+        self.waiting.retain(|path, &mut last| {
+            index += 1;
+            let quiet = index <= excess || now.duration_since(last) >= self.debounce;
+            if quiet {
+                ready.push(path.clone());
             }
+            !quiet
+        });
+        // Scans skip released files for a while
+        for path in &ready {
+            self.recent.insert(path.clone(), now);
         }
-
-        // If the map has grown too large, force a cleanup
         if self.recent.len() > MAX_DEBOUNCE_ENTRIES {
             self.cleanup();
         }
+        ready
+    }
+
+    /// Whether paths are waiting for their events to stop
+    pub fn has_waiting(&self) -> bool {
+        !self.waiting.is_empty()
+    }
 
-        paths_to_process
+    /// Check a single path (e.g. found by a directory scan): false if events
+    /// for it are waiting, or it was released within the debounce duration
+    pub fn should_process_path(&mut self, path: &Path) -> bool {
+        let now = Instant::now();
+        let should_process = !self.waiting.contains_key(path)
+            && self
+                .recent
+                .get(path)
+                .is_none_or(|&last| now.duration_since(last) > self.debounce);
+        if should_process {
+            self.recent.insert(path.to_path_buf(), now);
+        }
+        if self.recent.len() > MAX_DEBOUNCE_ENTRIES {
+            self.cleanup();
+        }
+        should_process
     }
 
     /// Clean up old entries (call periodically)
@@ -59,6 +109,11 @@ impl EventHandler {
 
         self.recent
             .retain(|_, &mut last| now.duration_since(last) < threshold);
+        // Still too many (debounce 0, or a flood of files): keep the newest
+        if self.recent.len() > MAX_DEBOUNCE_ENTRIES {
+            let excess = self.recent.len() - MAX_DEBOUNCE_ENTRIES;
+            self.recent.drain(..excess);
+        }
     }
 }
 
@@ -70,19 +125,41 @@ mod tests {
     #[test]
     fn test_debounce() {
         let mut handler = EventHandler::new(1);
-
+        let start = Instant::now();
+        let growing = Path::new("/tmp/growing.log");
+
+        handler.record_at(growing, start);
+        handler.record_at(
+            Path::new("/tmp/test.txt"),
+            start + Duration::from_millis(500),
+        );
+        // The log keeps being appended to
+        handler.record_at(growing, start + Duration::from_millis(900));
+        assert!(
+            handler
+                .take_ready_at(start + Duration::from_millis(1200))
+                .is_empty()
+        );
+
+        // The other file doesn't wait for it
+        assert_eq!(
+            handler.take_ready_at(start + Duration::from_millis(1600)),
+            vec![PathBuf::from("/tmp/test.txt")]
+        );
+        assert_eq!(
+            handler.take_ready_at(start + Duration::from_millis(2000)),
+            vec![growing.to_path_buf()]
+        );
+        assert!(!handler.has_waiting());
+
+        // A scan doesn't handle a file waiting for its events to stop
         let event = Event {
             kind: EventKind::Create(notify::event::CreateKind::File),
-            paths: vec![PathBuf::from("/tmp/test.txt")],
+            paths: vec![PathBuf::from("/tmp/new.txt")],
             attrs: Default::default(),
         };
-
-        // First event should be processed
-        let paths = handler.should_process(&event);
-        assert_eq!(paths.len(), 1);
-
-        // Immediate second event should be debounced
-        let paths = handler.should_process(&event);
-        assert_eq!(paths.len(), 0);
+        handler.record(&event);
+        assert!(!handler.should_process_path(Path::new("/tmp/new.txt")));
+        assert!(handler.should_process_path(Path::new("/tmp/other.txt")));
     }
 }
@@ -0,0 +1,191 @@
+//! Detection of files another application is still writing
+//!
+//! A file that has stopped growing may still be open in the app that
+//! produced it (an export that pauses between passes, a database, a
+//! recording), and moving it then loses whatever the app writes next. On
+//! Windows the file can't be opened without sharing while someone has it
+//! open. On Unix, a `flock` or `fcntl` lock on it counts, and on Linux so
+//! does any other process holding it open for writing, found through
+//! `/proc`. macOS has no cheap equivalent of the last check, so there only
+//! locks are seen.
+
+use std::path::Path;
+
+/// Whether another process is writing the file, by the checks above. Only a
+/// sharing violation or a lock held elsewhere counts; a file that is gone or
+/// can't be read for other reasons doesn't.
+pub(crate) fn is_in_use(path: &Path) -> bool {
+    use fs2::FileExt;
+
+    #[cfg(windows)]
+    let file = {
+        use std::os::windows::fs::OpenOptionsExt;
+        std::fs::OpenOptions::new()
+            .read(true)
+            .share_mode(0)
+            .open(path)
+    };
+    #[cfg(not(windows))]
+    let file = std::fs::File::open(path);
+
+    let file = match file {
+        Ok(file) => file,
+        #[cfg(windows)]
+        Err(e)
+            if e.raw_os_error()
+                == Some(windows_sys::Win32::Foundation::ERROR_SHARING_VIOLATION as i32) =>
+        {
+            return true;
+        }
+        Err(_) => return false,
+    };
+    if let Err(e) = file.try_lock_exclusive() {
+        // Filesystems without locks don't make a file in use
+        return e.raw_os_error() == fs2::lock_contended_error().raw_os_error();
+    }
+    let _ = FileExt::unlock(&file);
+
+    #[cfg(unix)]
+    if has_record_lock(&file) {
+        return true;
+    }
+    #[cfg(target_os = "linux")]
+    if writers::is_open_for_writing(path) {
+        return true;
+    }
+    false
+}
+
+/// Whether another process holds a POSIX record (`fcntl`) lock on any part
+/// of the file, as SQLite and office suites take
+#[cfg(unix)]
+fn has_record_lock(file: &std::fs::File) -> bool {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: flock is a plain C struct, valid when zeroed
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    // l_start and l_len of 0 cover the whole file
+    // SAFETY: the descriptor is open for the duration of the call and lock
+    // is a valid out-pointer
+    let result = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETLK, &mut lock) };
+    result == 0 && lock.l_type != libc::F_UNLCK as libc::c_short
+}
+
+#[cfg(target_os = "linux")]
+mod writers {
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+    use std::sync::{LazyLock, Mutex};
+    use std::time::{Duration, Instant};
+
+    /// How long a listing of open files is reused, so a scan of a large
+    /// folder walks `/proc` about once a second rather than once a file
+    const MAX_AGE: Duration = Duration::from_secs(1);
+
+    /// When the listing was taken, and the files in it
+    type Listing = (Instant, HashSet<PathBuf>);
+
+    static OPEN_FOR_WRITING: LazyLock<Mutex<Option<Listing>>> = LazyLock::new(|| Mutex::new(None));
+
+    /// Whether another process has the file open for writing
+    pub(super) fn is_open_for_writing(path: &Path) -> bool {
+        let Ok(path) = std::fs::canonicalize(path) else {
+            return false;
+        };
+        let Ok(mut cached) = OPEN_FOR_WRITING.lock() else {
+            return false;
+        };
+        if cached
+            .as_ref()
+            .is_none_or(|(listed, _)| listed.elapsed() >= MAX_AGE)
+        {
+            *cached = Some((Instant::now(), list()));
+        }
+        cached
+            .as_ref()
+            .is_some_and(|(_, files)| files.contains(&path))
+    }
+
+    /// Files other processes (those this user can see) have open for writing
+    fn list() -> HashSet<PathBuf> {
+        let own = std::process::id().to_string();
+        let mut files = HashSet::new();
+        let Ok(processes) = std::fs::read_dir("/proc") else {
+            return files;
+        };
+        for process in processes.flatten() {
+            let name = process.file_name();
+            let name = name.to_string_lossy();
+            if name == own || !name.bytes().all(|b| b.is_ascii_digit()) {
+                continue;
+            }
+            let Ok(fds) = std::fs::read_dir(process.path().join("fd")) else {
+                continue;
+            };
+            for fd in fds.flatten() {
+                let Ok(target) = std::fs::read_link(fd.path()) else {
+                    continue;
+                };
+                if target.is_absolute()
+                    && is_writable(&process.path().join("fdinfo").join(fd.file_name()))
+                {
+                    files.insert(target);
+                }
+            }
+        }
+        files
+    }
+
+    /// Whether a descriptor was opened for writing, from the access mode in
+    /// its `fdinfo` flags (octal)
+    fn is_writable(fdinfo: &Path) -> bool {
+        std::fs::read_to_string(fdinfo).is_ok_and(|info| {
+            info.lines()
+                .find_map(|line| line.strip_prefix("flags:"))
+                .and_then(|flags| u32::from_str_radix(flags.trim(), 8).ok())
+                .is_some_and(|flags| flags & libc::O_ACCMODE as u32 != libc::O_RDONLY as u32)
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("export.mov");
+        std::fs::write(&file, "frames").unwrap();
+        assert!(!is_in_use(&file));
+        assert!(!is_in_use(&dir.path().join("missing.mov")));
+
+        // A lock taken through another open file, as another app's would be
+        let holder = std::fs::File::open(&file).unwrap();
+        fs2::FileExt::lock_exclusive(&holder).unwrap();
+        assert!(is_in_use(&file));
+        fs2::FileExt::unlock(&holder).unwrap();
+        drop(holder);
+        assert!(!is_in_use(&file));
+
+        // A writer in another process
+        let mut writer = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("exec sleep 5 3>>\"$0\"")
+            .arg(&file)
+            .spawn()
+            .unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(3);
+        let mut seen = false;
+        while !seen && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            seen = is_in_use(&file);
+        }
+        let _ = writer.kill();
+        let _ = writer.wait();
+        // Only Linux sees writers that hold no lock
+        assert_eq!(seen, cfg!(target_os = "linux"));
+    }
+}
@@ -1,32 +1,79 @@
 //! File system watcher
 
+mod batch;
+mod filter;
+mod folders;
 mod handler;
+mod in_use;
+mod pause;
+mod poll;
+mod raw;
+mod stability;
 
+pub use batch::{BatchLimits, BatchSummary};
+pub use filter::DirFilter;
+pub(crate) use filter::{EntryKind, SymlinkGuard};
 pub use handler::EventHandler;
+pub use pause::{is_paused, pause_watch, paused_watches, resume_watch, watch_key};
+pub use poll::is_network_filesystem;
+pub use raw::{RawEvent, raw_events};
+pub use stability::{DEFAULT_TEMP_EXTENSIONS, Stability};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher as NotifyWatcher};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::time::Duration;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
-use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
-use crate::rules::{Rule, RuleEngine};
+use crate::config::{WatchConfig, WatchMode};
+use crate::rules::{Action, RuleEngine};
+use crate::sftp::SftpUrl;
+use batch::BatchQueue;
+use folders::PendingFolders;
+use stability::PendingFiles;
 
 /// File system watcher that monitors directories and applies rules
 pub struct Watcher {
     watcher: RecommendedWatcher,
-    engine: RuleEngine,
+    /// Shared with background scans
+    engine: Arc<RuleEngine>,
     rx: mpsc::Receiver<Result<notify::Event, notify::Error>>,
-    event_handler: EventHandler,
+    /// Sender for poll-mode watches, which produce events themselves
+    tx: mpsc::Sender<Result<notify::Event, notify::Error>>,
+    /// Stop flags for poll-mode watches, by watched path
+    pollers: std::collections::HashMap<std::path::PathBuf, Arc<AtomicBool>>,
+    /// Default seconds between snapshots for poll-mode watches
+    polling_interval_secs: u64,
+    /// Shared with background scans so scanned files are debounced like events
+    event_handler: Arc<Mutex<EventHandler>>,
     files_processed: Arc<AtomicU64>,
+    /// Files no rule matched
+    files_unmatched: Arc<AtomicU64>,
     /// Mapping of watched directory path → allowed rule names (empty = all rules)
     watch_rules: std::collections::HashMap<std::path::PathBuf, Vec<String>>,
     /// Cache of canonical paths for watched directories
     canonical_cache: std::collections::HashMap<std::path::PathBuf, std::path::PathBuf>,
+    /// Subdirectory and symlink filters, by watched path
+    dir_filters: std::collections::HashMap<std::path::PathBuf, DirFilter>,
+    /// Native watches whose directories are registered one by one, because a
+    /// recursive watch would cover excluded or linked directories wrongly
+    dir_watches: std::collections::HashSet<std::path::PathBuf>,
+    /// Files waiting to finish downloading before rules run
+    pending: PendingFiles,
+    /// Folders waiting to settle before folder rules run
+    folders: PendingFolders,
+    /// Settled folders in the queue, with the event paths held back for them
+    ready_folders: std::collections::HashMap<std::path::PathBuf, Vec<std::path::PathBuf>>,
+    /// Files ready for rules, drained at a limited rate
+    queue: BatchQueue,
+    /// Mapping of watched directory path → action for files no rule matches
+    unmatched_actions: std::collections::HashMap<std::path::PathBuf, Action>,
+    /// Mapping of watched directory path → filter, for watches with `remove_empty_dirs`
+    remove_empty_dirs: std::collections::HashMap<std::path::PathBuf, DirFilter>,
 }
 
 impl Watcher {
@@ -38,9 +85,10 @@ impl Watcher {
     ) -> Result<Self> {
         let (tx, rx) = mpsc::channel();
 
+        let event_tx = tx.clone();
         let watcher = RecommendedWatcher::new(
             move |res| {
-                if let Err(e) = tx.send(res) {
+                if let Err(e) = event_tx.send(res) {
                     error!("Failed to send watch event: {}", e);
                 }
             },
@@ -49,15 +97,173 @@ impl Watcher {
 
         Ok(Self {
             watcher,
-            engine,
+            engine: Arc::new(engine),
             rx,
-            event_handler: EventHandler::new(debounce_seconds),
+            tx,
+            pollers: std::collections::HashMap::new(),
+            polling_interval_secs,
+            event_handler: Arc::new(Mutex::new(EventHandler::new(debounce_seconds))),
             files_processed: Arc::new(AtomicU64::new(0)),
+            files_unmatched: Arc::new(AtomicU64::new(0)),
             watch_rules: std::collections::HashMap::new(),
             canonical_cache: std::collections::HashMap::new(),
+            dir_filters: std::collections::HashMap::new(),
+            dir_watches: std::collections::HashSet::new(),
+            unmatched_actions: std::collections::HashMap::new(),
+            remove_empty_dirs: std::collections::HashMap::new(),
+            pending: PendingFiles::default(),
+            folders: PendingFolders::default(),
+            ready_folders: std::collections::HashMap::new(),
+            queue: BatchQueue::default(),
         })
     }
 
+    /// Set the processing rate limit and queue size
+    pub fn with_batching(mut self, limits: BatchLimits) -> Self {
+        self.queue = BatchQueue::new(limits);
+        self
+    }
+
+    /// Set how in-progress downloads are detected
+    pub fn with_stability(mut self, stability: Stability) -> Self {
+        self.folders = PendingFolders::new(stability.clone());
+        self.pending = PendingFiles::new(stability);
+        self
+    }
+
+    /// Start watching a configured folder, picking the change detection
+    /// backend from its `mode` (polling for network filesystems in `auto`)
+    pub fn add_watch(&mut self, watch: &WatchConfig) -> Result<()> {
+        if let Some(url) = SftpUrl::parse(&watch.path) {
+            return self.add_remote_watch(watch, url);
+        }
+        let path = crate::expand_path(&watch.path);
+        let poll = match watch.mode {
+            WatchMode::Poll => true,
+            WatchMode::Events => false,
+            WatchMode::Auto => {
+                let network = is_network_filesystem(&path);
+                if network {
+                    info!(
+                        "{} is on a network filesystem, using polling",
+                        path.display()
+                    );
+                }
+                network
+            }
+        };
+
+        let filter = DirFilter::from_watch(watch);
+        let canonical = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if let Some(action) = &watch.unmatched_action {
+            self.unmatched_actions
+                .insert(canonical.clone(), action.clone());
+        }
+        if watch.remove_empty_dirs {
+            self.remove_empty_dirs.insert(canonical, filter.clone());
+        }
+
+        if !poll {
+            if !watch.recursive || filter.is_unrestricted() {
+                self.watch_with_rules(&path, watch.recursive, watch.rules.clone())?;
+                self.dir_filters.insert(path, filter);
+                return Ok(());
+            }
+            // A recursive native watch would cover excluded directories too
+            // (and follow symlinks differently per platform), so register
+            // each included directory on its own instead
+            let dirs = filter.walk_dirs(&path);
+            for dir in &dirs {
+                self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            }
+            self.register_rules(&path, watch.rules.clone());
+            self.dir_filters.insert(path.clone(), filter);
+            self.dir_watches.insert(path.clone());
+            info!(
+                "Watching: {} ({} directories, excluding {:?}, max depth {:?}, symlinks: {:?})",
+                path.display(),
+                dirs.len(),
+                watch.exclude_dirs,
+                watch.max_depth,
+                watch.symlinks
+            );
+            return Ok(());
+        }
+
+        if !path.is_dir() {
+            anyhow::bail!("Not a directory: {}", path.display());
+        }
+        let interval = watch
+            .poll_interval_secs
+            .unwrap_or(self.polling_interval_secs)
+            .max(1);
+        let stop = Arc::new(AtomicBool::new(false));
+        poll::spawn(
+            path.clone(),
+            watch.recursive,
+            filter.clone(),
+            Duration::from_secs(interval),
+            self.tx.clone(),
+            Arc::clone(&stop),
+        );
+        if let Some(old) = self.pollers.insert(path.clone(), stop) {
+            old.store(true, Ordering::Relaxed);
+        }
+        self.register_rules(&path, watch.rules.clone());
+        self.dir_filters.insert(path.clone(), filter);
+        info!(
+            "Polling: {} every {}s (recursive: {})",
+            path.display(),
+            interval,
+            watch.recursive
+        );
+        Ok(())
+    }
+
+    /// Start polling a remote folder, staging its files locally (see `crate::sftp`)
+    fn add_remote_watch(&mut self, watch: &WatchConfig, url: SftpUrl) -> Result<()> {
+        let staging = url
+            .staging_dir()
+            .context("Could not determine cache directory")?;
+        std::fs::create_dir_all(&staging)
+            .with_context(|| format!("Failed to create {}", staging.display()))?;
+        let staging = std::fs::canonicalize(&staging)?;
+        if watch.recursive {
+            warn!("{}: remote watches aren't recursive", url);
+        }
+        let interval = watch
+            .poll_interval_secs
+            .unwrap_or(self.polling_interval_secs)
+            .max(1);
+
+        let remote = crate::sftp::register(url.clone(), staging.clone());
+        let stop = Arc::new(AtomicBool::new(false));
+        crate::sftp::spawn_poller(
+            remote,
+            Duration::from_secs(interval),
+            watch.scan_on_start,
+            self.tx.clone(),
+            Arc::clone(&stop),
+        );
+        if let Some(old) = self.pollers.insert(staging.clone(), stop) {
+            old.store(true, Ordering::Relaxed);
+        }
+        if let Some(action) = &watch.unmatched_action {
+            self.unmatched_actions
+                .insert(staging.clone(), action.clone());
+        }
+        self.register_rules(&staging, watch.rules.clone());
+        self.dir_filters
+            .insert(staging.clone(), DirFilter::default());
+        info!(
+            "Polling: {} every {}s (staged in {})",
+            url,
+            interval,
+            staging.display()
+        );
+        Ok(())
+    }
+
     /// Start watching a directory
     pub fn watch(&mut self, path: &Path, recursive: bool) -> Result<()> {
         self.watch_with_rules(path, recursive, Vec::new())
@@ -77,31 +283,78 @@ impl Watcher {
         };
 
         self.watcher.watch(path, mode)?;
+        self.register_rules(path, rules);
+        info!("Watching: {} (recursive: {})", path.display(), recursive);
+
+        Ok(())
+    }
+
+    /// Remember which rules apply to files under a watched directory
+    fn register_rules(&mut self, path: &Path, rules: Vec<String>) {
         let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
         self.watch_rules.insert(canonical.clone(), rules);
         self.canonical_cache
             .insert(canonical.clone(), canonical.clone());
-        info!("Watching: {} (recursive: {})", path.display(), recursive);
+    }
 
-        // Initial scan — run in a background thread so TUI startup isn't blocked.
-        let scan_path = path.to_path_buf();
-        let scan_rules: Arc<Vec<Rule>> = Arc::new(self.engine.rules().to_vec());
+    /// Re-evaluate rules against every file already in a watched directory.
+    /// Runs in a background thread so startup isn't blocked; used for
+    /// `scan_on_start` and scheduled rescans.
+    pub fn scan(&self, path: &Path, recursive: bool) {
+        // Remote watches fetch existing files when polling starts
+        if SftpUrl::parse(path).is_some() {
+            return;
+        }
+        let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if is_paused(&canonical) {
+            info!("Watch paused, skipping scan of {}", path.display());
+            return;
+        }
+        let engine = Arc::clone(&self.engine);
         let allowed_rules: Option<Vec<String>> = self
             .watch_rules
             .get(&canonical)
             .filter(|r| !r.is_empty())
             .cloned();
+        let folder_rules = self.engine.has_folder_rules(allowed_rules.as_deref());
+        let target = ScanTarget {
+            path: path.to_path_buf(),
+            root: canonical.clone(),
+            folder_rules,
+            recursive,
+            filter: self.dir_filters.get(path).cloned().unwrap_or_default(),
+            allowed_rules,
+            unmatched_action: self.unmatched_actions.get(&canonical).cloned(),
+            remove_empty_dirs: self.remove_empty_dirs.contains_key(&canonical),
+            stability: self.pending.stability().clone(),
+        };
         let counter = Arc::clone(&self.files_processed);
+        let unmatched = Arc::clone(&self.files_unmatched);
+        let event_handler = Arc::clone(&self.event_handler);
         std::thread::spawn(move || {
-            scan_existing_background(&scan_path, recursive, &scan_rules, allowed_rules, counter);
+            scan_existing_background(&target, &engine, &event_handler, counter, &unmatched);
         });
-
-        Ok(())
     }
 
     /// Stop watching a directory
     pub fn unwatch(&mut self, path: &Path) -> Result<()> {
-        self.watcher.unwatch(path)?;
+        if let Some(staging) = SftpUrl::parse(path).and_then(|url| url.staging_dir()) {
+            let staging = std::fs::canonicalize(&staging).unwrap_or(staging);
+            crate::sftp::unregister(&staging);
+            return self.unwatch(&staging);
+        }
+        let filter = self.dir_filters.remove(path);
+        if let Some(stop) = self.pollers.remove(path) {
+            stop.store(true, Ordering::Relaxed);
+        } else if self.dir_watches.remove(path) {
+            for dir in filter.unwrap_or_default().walk_dirs(path) {
+                if let Err(e) = self.watcher.unwatch(&dir) {
+                    debug!("Failed to unwatch {}: {}", dir.display(), e);
+                }
+            }
+        } else {
+            self.watcher.unwatch(path)?;
+        }
         info!("Stopped watching: {}", path.display());
         Ok(())
     }
@@ -111,6 +364,7 @@ impl Watcher {
         let mut events = Vec::new();
 
         while let Ok(result) = self.rx.try_recv() {
+            raw::record(&result);
             match result {
                 Ok(event) => events.push(event),
                 Err(e) => error!("Watch error: {}", e),
@@ -130,31 +384,9 @@ impl Watcher {
             // Only process create and modify events
             match event.kind {
                 notify::EventKind::Create(_) | notify::EventKind::Modify(_) => {
-                    // Use event handler to debounce
-                    let paths_to_process = self.event_handler.should_process(&event);
-
-                    for path in paths_to_process {
-                        info!("File event detected: {}", path.display());
-                        let allowed = self.allowed_rules_for(&path);
-                        match self.engine.process_filtered(&path, allowed) {
-                            Ok(true) => processed += 1,
-                            Ok(false) => {} // No matching rule
-                            Err(e) => {
-                                // Skip NotFound errors (file gone between event and processing)
-                                if e.downcast_ref::<std::io::Error>().is_some_and(|io_err| {
-                                    io_err.kind() == std::io::ErrorKind::NotFound
-                                }) {
-                                    debug!(
-                                        "File disappeared before processing: {}",
-                                        path.display()
-                                    );
-                                    continue;
-                                }
-                                error!("Rule processing failed for {}: {}", path.display(), e);
-                                let rule_name = self.find_matching_rule_name(&path);
-                                crate::notifications::notify_rule_error(&rule_name, &e.to_string());
-                            }
-                        }
+                    // Each path waits until its events stop
+                    if let Ok(mut handler) = self.event_handler.lock() {
+                        handler.record(&event);
                     }
                 }
                 _ => {
@@ -163,39 +395,323 @@ impl Watcher {
             }
         }
 
+        let debounced = match self.event_handler.lock() {
+            Ok(mut handler) => handler.take_ready(),
+            Err(_) => Vec::new(),
+        };
+        for path in debounced {
+            // Moved or deleted since (e.g. by a rule)
+            if !path.exists() {
+                debug!("Gone before its events stopped: {}", path.display());
+                continue;
+            }
+            if !self.included(&path) {
+                debug!("Ignoring event in excluded directory: {}", path.display());
+                continue;
+            }
+            if self.paused(&path) {
+                debug!("Watch paused, ignoring event: {}", path.display());
+                continue;
+            }
+            if let Some(folder) = self.folder_unit_for(&path) {
+                debug!(
+                    "Waiting for folder {} to settle: {}",
+                    folder.display(),
+                    path.display()
+                );
+                self.folders.hold(folder, path);
+                continue;
+            }
+            if path.is_dir() {
+                self.watch_new_dir(&path);
+                info!("File event detected: {}", path.display());
+                crate::activity::publish(crate::activity::ActivityKind::Detected {
+                    path: path.clone(),
+                });
+                self.enqueue(path);
+                continue;
+            }
+            if self.pending.stability().is_temp_file(&path) {
+                debug!("Download in progress, skipping: {}", path.display());
+                continue;
+            }
+            info!("File event detected: {}", path.display());
+            crate::activity::publish(crate::activity::ActivityKind::Detected {
+                path: path.clone(),
+            });
+            // Rules run once the file stops changing
+            self.pending.track(path);
+        }
+
+        for path in self.pending.take_ready() {
+            self.enqueue(path);
+        }
+
+        for (folder, held) in self.folders.take_settled() {
+            info!("Folder settled: {}", folder.display());
+            self.enqueue_folder(folder, held);
+        }
+
+        for path in self.engine.take_deferred() {
+            info!("No longer deferred, processing {}", path.display());
+            if self.folder_unit_for(&path).as_ref() == Some(&path) {
+                self.enqueue_folder(path, Vec::new());
+            } else {
+                self.enqueue(path);
+            }
+        }
+
+        // Folders are handled here, files by the worker pool
+        let mut outcomes = Vec::new();
+        let mut jobs = Vec::new();
+        for path in self.queue.take() {
+            match self.ready_folders.remove(&path) {
+                Some(held) => outcomes.push(self.process_folder(&path, held)),
+                // Opened again while it waited in the queue
+                None if path.is_file() && in_use::is_in_use(&path) => {
+                    debug!("Open in another application again: {}", path.display());
+                    self.pending.track(path);
+                }
+                None => match self.file_job(path) {
+                    Some(job) => jobs.push(job),
+                    None => outcomes.push(Outcome::Skipped),
+                },
+            }
+        }
+        outcomes.extend(run_file_jobs(
+            &self.engine,
+            &jobs,
+            self.queue.workers(),
+            &self.files_unmatched,
+        ));
+
+        for outcome in outcomes {
+            match outcome {
+                Outcome::Matched => {
+                    processed += 1;
+                    self.queue.record_processed();
+                    crate::notifications::record_organized();
+                }
+                Outcome::Unmatched => self.queue.record_unmatched(),
+                Outcome::Skipped | Outcome::Deferred => {}
+                Outcome::Failed { rule, error } => {
+                    self.queue.record_failed(&rule);
+                    crate::notifications::record_failed();
+                    // Bursts are summarized once they're done, unless only
+                    // errors are notified
+                    if !self.queue.is_burst(self.pending.len())
+                        || crate::notifications::errors_only()
+                    {
+                        crate::notifications::notify_rule_error(&rule, &error);
+                    }
+                }
+            }
+        }
+
+        if self.queue.is_empty()
+            && self.pending.is_empty()
+            && let Some(summary) = self.queue.finish()
+        {
+            info!("Batch complete: {}", summary.message());
+            crate::notifications::notify(
+                crate::notifications::NotificationKind::Batch,
+                &summary.message(),
+            );
+        }
+
         // Periodically clean up old entries
-        self.event_handler.cleanup();
+        if let Ok(mut handler) = self.event_handler.lock() {
+            handler.cleanup();
+        }
 
         self.files_processed
             .fetch_add(processed as u64, Ordering::Relaxed);
         Ok(processed)
     }
 
+    /// Add a file to the processing queue
+    fn enqueue(&mut self, path: PathBuf) {
+        if !self.queue.push(path) {
+            warn!("Processing queue is full, skipping file until the next scan");
+        }
+    }
+
+    /// Queue a settled folder for folder rules
+    fn enqueue_folder(&mut self, folder: PathBuf, held: Vec<PathBuf>) {
+        if self.queue.push(folder.clone()) {
+            self.ready_folders.insert(folder, held);
+        } else {
+            warn!("Processing queue is full, skipping folder until the next scan");
+        }
+    }
+
+    /// Apply folder rules to a settled folder. If none matches, the event
+    /// paths held back for it go through the file rules as usual.
+    fn process_folder(&mut self, folder: &Path, held: Vec<PathBuf>) -> Outcome {
+        if self.paused(folder) {
+            debug!("Watch paused, skipping: {}", folder.display());
+            return Outcome::Skipped;
+        }
+        if crate::history::is_restored(folder) {
+            debug!("Put back by an undo, skipping: {}", folder.display());
+            return Outcome::Skipped;
+        }
+        let allowed = self.allowed_rules_for(folder).map(<[String]>::to_vec);
+        let root = self.watch_root_for(folder).cloned();
+        match self
+            .engine
+            .process_folder(folder, allowed.as_deref(), root.as_deref())
+        {
+            Ok(true) if self.engine.is_deferred(folder) => Outcome::Deferred,
+            Ok(true) => Outcome::Matched,
+            Ok(false) => {
+                for path in held {
+                    if path.is_dir() {
+                        self.enqueue(path);
+                    } else if !self.pending.stability().is_temp_file(&path) {
+                        self.pending.track(path);
+                    }
+                }
+                Outcome::Skipped
+            }
+            Err(e) => {
+                if !folder.exists()
+                    && e.downcast_ref::<std::io::Error>()
+                        .is_some_and(|io_err| io_err.kind() == std::io::ErrorKind::NotFound)
+                {
+                    debug!("Folder disappeared before processing: {}", folder.display());
+                    return Outcome::Skipped;
+                }
+                error!("Rule processing failed for {}: {}", folder.display(), e);
+                let rule = self
+                    .engine
+                    .matching_folder_rules(folder, allowed.as_deref())
+                    .ok()
+                    .and_then(|rules| rules.first().map(|r| r.name.clone()))
+                    .unwrap_or_else(|| "unknown".to_string());
+                Outcome::Failed {
+                    rule,
+                    error: e.to_string(),
+                }
+            }
+        }
+    }
+
+    /// The folder directly inside a watch that a path belongs to, if the
+    /// watch has folder rules (which handle that folder as a unit)
+    fn folder_unit_for(&self, path: &Path) -> Option<PathBuf> {
+        let root = self.watch_root_for(path)?;
+        if !self.engine.has_folder_rules(self.allowed_rules_for(path)) {
+            return None;
+        }
+        let path = if path.starts_with(root) {
+            path.to_path_buf()
+        } else {
+            std::fs::canonicalize(path).ok()?
+        };
+        let top = path.strip_prefix(root).ok()?.components().next()?;
+        let folder = root.join(top);
+        folder.is_dir().then_some(folder)
+    }
+
+    /// What running rules on a file needs from its watch, or None if the
+    /// file should be skipped
+    fn file_job(&self, path: PathBuf) -> Option<FileJob> {
+        // Files that were already waiting when the watch was paused
+        if self.paused(&path) {
+            debug!("Watch paused, skipping: {}", path.display());
+            return None;
+        }
+        if crate::history::is_restored(&path) {
+            debug!("Put back by an undo, skipping: {}", path.display());
+            return None;
+        }
+        let root = self.watch_root_for(&path).cloned();
+        Some(FileJob {
+            allowed: self.allowed_rules_for(&path).map(<[String]>::to_vec),
+            unmatched_action: root
+                .as_ref()
+                .and_then(|root| self.unmatched_actions.get(root))
+                .cloned(),
+            remove_empty_dirs: root
+                .as_ref()
+                .and_then(|root| self.remove_empty_dirs.get(root))
+                .cloned(),
+            root,
+            path,
+        })
+    }
+
+    /// Whether files are waiting for their events to stop, waiting to finish
+    /// downloading or queued for processing (deferred files don't count).
+    /// Callers that only process when events arrive should keep calling
+    /// while this is true.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+            || !self.folders.is_empty()
+            || !self.queue.is_empty()
+            || self
+                .event_handler
+                .lock()
+                .is_ok_and(|handler| handler.has_waiting())
+    }
+
     /// Get total number of files processed
     pub fn files_processed(&self) -> u64 {
         self.files_processed.load(Ordering::Relaxed)
     }
 
+    /// Get total number of files no rule matched
+    pub fn files_unmatched(&self) -> u64 {
+        self.files_unmatched.load(Ordering::Relaxed)
+    }
+
     /// Process events and apply rules (polls + processes, convenience method)
     pub fn process_events(&mut self) -> Result<usize> {
         let events = self.poll()?;
         self.process_polled_events(events)
     }
 
-    /// Carry over files_processed count from a previous watcher (e.g. on config reload)
+    /// Carry over files_processed count (and deferred files) from a previous
+    /// watcher (e.g. on config reload)
     pub fn carry_over_files_processed(&mut self, old: &Watcher) {
         self.files_processed
             .store(old.files_processed(), Ordering::Relaxed);
+        self.files_unmatched
+            .store(old.files_unmatched(), Ordering::Relaxed);
+        self.engine.adopt_deferred(&old.engine);
+    }
+
+    /// Whether a path is outside every excluded subdirectory (and, unless
+    /// symlinks are followed, every linked folder) of its watch
+    fn included(&self, path: &Path) -> bool {
+        self.dir_filters
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .all(|(root, filter)| filter.includes(root, path, path.is_dir()))
     }
 
-    /// Find the name of the first matching rule for a path
-    fn find_matching_rule_name(&self, path: &std::path::Path) -> String {
-        for rule in self.engine.rules() {
-            if rule.enabled && rule.condition.matches(path).unwrap_or(false) {
-                return rule.name.clone();
+    /// Register a directory that appeared inside a per-directory watch
+    fn watch_new_dir(&mut self, dir: &Path) {
+        let Some((root, filter)) = self
+            .dir_filters
+            .iter()
+            .filter(|(root, _)| dir.starts_with(root))
+            .max_by_key(|(root, _)| root.as_os_str().len())
+        else {
+            return;
+        };
+        // Recursive native watches cover new directories by themselves, and
+        // poll-mode watches pick them up in their next snapshot
+        if !self.dir_watches.contains(root) {
+            return;
+        }
+        for new_dir in filter.walk_dirs_below(root, dir) {
+            match self.watcher.watch(&new_dir, RecursiveMode::NonRecursive) {
+                Ok(()) => debug!("Watching new directory: {}", new_dir.display()),
+                Err(e) => debug!("Failed to watch {}: {}", new_dir.display(), e),
             }
         }
-        "unknown".to_string()
     }
 
     /// Get the rule engine
@@ -203,8 +719,22 @@ impl Watcher {
         &self.engine
     }
 
+    /// Whether the watch a path belongs to is paused
+    fn paused(&self, path: &Path) -> bool {
+        self.watch_root_for(path)
+            .is_some_and(|root| is_paused(root))
+    }
+
     /// Find the allowed rules filter for a file path based on which watch directory it belongs to
     fn allowed_rules_for(&self, file_path: &Path) -> Option<&[String]> {
+        self.watch_root_for(file_path)
+            .and_then(|root| self.watch_rules.get(root))
+            .filter(|rules| !rules.is_empty())
+            .map(|rules| rules.as_slice())
+    }
+
+    /// Find the (canonical) watch directory a file path belongs to
+    fn watch_root_for(&self, file_path: &Path) -> Option<&std::path::PathBuf> {
         // Try matching with the raw event path first to avoid a syscall per event.
         // Watch paths are already canonicalized at registration time.
         let mut best_match: Option<(&std::path::PathBuf, &Vec<String>)> = None;
@@ -248,27 +778,214 @@ impl Watcher {
             }
         }
 
-        match best_match {
-            Some((_, rules)) if !rules.is_empty() => Some(rules.as_slice()),
-            _ => None,
+        best_match.map(|(watch_path, _)| watch_path)
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        // Poller threads hold a sender, so they must be told to stop
+        for stop in self.pollers.values() {
+            stop.store(true, Ordering::Relaxed);
         }
     }
 }
 
-/// Run the initial scan in a background thread so TUI startup isn't blocked.
-fn scan_existing_background(
+/// Count a file no rule matched and apply its watch's `unmatched_action`.
+/// Directories, hidden files and files already in the action's destination
+/// are left alone. Returns whether the file was counted.
+fn handle_unmatched(
+    engine: &RuleEngine,
     path: &Path,
+    action: Option<&Action>,
+    counter: &AtomicU64,
+) -> bool {
+    let hidden = path
+        .file_name()
+        .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+    if hidden || !path.is_file() {
+        return false;
+    }
+    if let Some(dest) = action.and_then(Action::destination)
+        && path.starts_with(&dest)
+    {
+        return false;
+    }
+
+    counter.fetch_add(1, Ordering::Relaxed);
+    debug!("No rule matched: {}", path.display());
+    if let Some(action) = action
+        && let Err(e) = engine.process_unmatched(path, action)
+    {
+        error!("Unmatched action failed for {}: {}", path.display(), e);
+    }
+    true
+}
+
+/// A file ready for rules, with the settings of its watch
+struct FileJob {
+    path: PathBuf,
+    allowed: Option<Vec<String>>,
+    /// Canonical watch path
+    root: Option<PathBuf>,
+    unmatched_action: Option<Action>,
+    /// Filter of a watch with `remove_empty_dirs`
+    remove_empty_dirs: Option<DirFilter>,
+}
+
+/// Remove the folders a file left empty when a rule took it out of its
+/// watch's tree
+fn remove_emptied_dirs(root: &Path, filter: &DirFilter, path: &Path) {
+    if path.exists() {
+        return;
+    }
+    let Some(parent) = path.parent() else {
+        return;
+    };
+    let removed = filter.remove_empty_dirs(root, parent);
+    if removed > 0 {
+        info!(
+            "Removed {} empty folder(s) left by {}",
+            removed,
+            path.display()
+        );
+    }
+}
+
+/// Run rules on files using up to `workers` threads. Actions putting files
+/// in the same folder still run one at a time (see the engine's destination
+/// locks), so parallel moves can't pick the same free name.
+fn run_file_jobs(
+    engine: &RuleEngine,
+    jobs: &[FileJob],
+    workers: usize,
+    unmatched: &AtomicU64,
+) -> Vec<Outcome> {
+    let workers = workers.min(jobs.len());
+    if workers <= 1 {
+        return jobs
+            .iter()
+            .map(|job| process_file(engine, job, unmatched))
+            .collect();
+    }
+    let next = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut outcomes = Vec::new();
+                    while let Some(job) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        outcomes.push(process_file(engine, job, unmatched));
+                    }
+                    outcomes
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    })
+}
+
+/// Apply rules to a file (or the watch's unmatched action)
+fn process_file(engine: &RuleEngine, job: &FileJob, unmatched: &AtomicU64) -> Outcome {
+    let path = job.path.as_path();
+    let outcome = run_file_job(engine, job, unmatched);
+    if matches!(outcome, Outcome::Matched | Outcome::Unmatched)
+        && let (Some(root), Some(filter)) = (&job.root, &job.remove_empty_dirs)
+    {
+        remove_emptied_dirs(root, filter, path);
+    }
+    outcome
+}
+
+fn run_file_job(engine: &RuleEngine, job: &FileJob, unmatched: &AtomicU64) -> Outcome {
+    let path = job.path.as_path();
+    match engine.process_in_watch(path, job.allowed.as_deref(), job.root.as_deref()) {
+        Ok(true) if engine.is_deferred(path) => Outcome::Deferred,
+        Ok(true) => Outcome::Matched,
+        Ok(false) => {
+            if handle_unmatched(engine, path, job.unmatched_action.as_ref(), unmatched) {
+                if let Some(root) = &job.root {
+                    crate::stats::record(root, "", crate::stats::Counter::Unmatched);
+                }
+                Outcome::Unmatched
+            } else {
+                Outcome::Skipped
+            }
+        }
+        Err(e) => {
+            // Skip NotFound errors (file gone between event and processing)
+            if !path.exists()
+                && e.downcast_ref::<std::io::Error>()
+                    .is_some_and(|io_err| io_err.kind() == std::io::ErrorKind::NotFound)
+            {
+                debug!("File disappeared before processing: {}", path.display());
+                return Outcome::Skipped;
+            }
+            error!("Rule processing failed for {}: {}", path.display(), e);
+            // The first matching rule's name
+            let rule = engine
+                .rules()
+                .iter()
+                .find(|rule| rule.enabled && rule.condition.matches(path).unwrap_or(false))
+                .map_or_else(|| "unknown".to_string(), |rule| rule.name.clone());
+            Outcome::Failed {
+                rule,
+                error: e.to_string(),
+            }
+        }
+    }
+}
+
+/// Result of running rules on one file
+enum Outcome {
+    /// A rule matched
+    Matched,
+    /// No rule matched
+    Unmatched,
+    /// Nothing to do (file vanished, or not a candidate for rules)
+    Skipped,
+    /// Held back until quiet hours end
+    Deferred,
+    /// Processing failed
+    Failed { rule: String, error: String },
+}
+
+/// A watched directory to scan, with its watch settings
+struct ScanTarget {
+    path: std::path::PathBuf,
+    /// Canonical watch path, for statistics
+    root: std::path::PathBuf,
+    /// Whether folder rules apply to folders directly in the watch
+    folder_rules: bool,
     recursive: bool,
-    rules: &[Rule],
+    filter: DirFilter,
     allowed_rules: Option<Vec<String>>,
+    unmatched_action: Option<Action>,
+    remove_empty_dirs: bool,
+    stability: Stability,
+}
+
+/// Entries scanned between progress updates
+const SCAN_PROGRESS_EVERY: u64 = 100;
+
+/// Scan existing files in a directory (called from a background thread).
+/// Entries are streamed through the rules as they're read.
+fn scan_existing_background(
+    target: &ScanTarget,
+    engine: &RuleEngine,
+    event_handler: &Mutex<EventHandler>,
     counter: Arc<AtomicU64>,
+    unmatched: &AtomicU64,
 ) {
-    let engine = RuleEngine::new(rules.to_vec());
-    let allowed = allowed_rules.as_deref();
+    let path = target.path.as_path();
+    let allowed = target.allowed_rules.as_deref();
 
-    let entries: Box<dyn Iterator<Item = std::fs::DirEntry>> = if recursive {
-        match walkdir(path) {
-            Ok(entries) => entries,
+    let entries: Box<dyn Iterator<Item = std::fs::DirEntry>> = if target.recursive {
+        match walkdir(path, &target.filter) {
+            Ok(entries) => Box::new(entries),
             Err(e) => {
                 error!("Failed to scan directory {}: {}", path.display(), e);
                 return;
@@ -283,22 +1000,99 @@ fn scan_existing_background(
             }
         }
     };
+    let started = std::time::SystemTime::now();
+    let progress = crate::progress::ScanRegistration::new(&target.root);
 
     let mut scanned = 0u64;
     let mut matched = 0u64;
+    // Folders handled as a unit, whose contents are skipped
+    let mut units: Vec<PathBuf> = Vec::new();
 
     for entry in entries {
+        if scanned.is_multiple_of(SCAN_PROGRESS_EVERY) {
+            progress.update(scanned, matched);
+        }
         let file_path = entry.path();
+        if units.iter().any(|unit| file_path.starts_with(unit)) {
+            continue;
+        }
         {
             scanned += 1;
-            match engine.process_filtered(&file_path, allowed) {
+            if changed_since(&entry, started) {
+                debug!("Changed during the scan, skipping: {}", file_path.display());
+                continue;
+            }
+            // Files still being written get an event when they change again
+            if !target.stability.is_settled(&file_path) {
+                debug!("Still being written, skipping: {}", file_path.display());
+                continue;
+            }
+            // Skip files an event just handled (and keep events from re-handling this one)
+            if !event_handler
+                .lock()
+                .map(|mut h| h.should_process_path(&file_path))
+                .unwrap_or(true)
+            {
+                debug!("Recently processed, skipping: {}", file_path.display());
+                continue;
+            }
+            if crate::history::is_restored(&file_path) {
+                debug!("Put back by an undo, skipping: {}", file_path.display());
+                continue;
+            }
+            if target.folder_rules && file_path.parent() == Some(path) && file_path.is_dir() {
+                // Folders still changing get events, which wait for them to settle
+                if !folders::is_settled(&target.stability, &file_path) {
+                    debug!("Folder still changing, skipping: {}", file_path.display());
+                    units.push(file_path);
+                    continue;
+                }
+                match engine.process_folder(&file_path, allowed, Some(&target.root)) {
+                    Ok(true) => {
+                        if !engine.is_deferred(&file_path) {
+                            info!("Processed existing folder: {}", file_path.display());
+                            matched += 1;
+                        }
+                        units.push(file_path);
+                        continue;
+                    }
+                    // Not a project folder: its entries go through file rules
+                    Ok(false) => {}
+                    Err(e) => {
+                        error!("Rule processing failed for {}: {}", file_path.display(), e);
+                        units.push(file_path);
+                        continue;
+                    }
+                }
+            }
+            match engine.process_in_watch(&file_path, allowed, Some(&target.root)) {
+                Ok(true) if engine.is_deferred(&file_path) => {
+                    debug!("Deferred: {}", file_path.display());
+                }
                 Ok(true) => {
+                    info!("Processed existing file: {}", file_path.display());
                     matched += 1;
+                    if target.remove_empty_dirs {
+                        remove_emptied_dirs(&target.root, &target.filter, &file_path);
+                    }
+                }
+                Ok(false) => {
+                    if handle_unmatched(
+                        engine,
+                        &file_path,
+                        target.unmatched_action.as_ref(),
+                        unmatched,
+                    ) {
+                        crate::stats::record(&target.root, "", crate::stats::Counter::Unmatched);
+                        if target.remove_empty_dirs {
+                            remove_emptied_dirs(&target.root, &target.filter, &file_path);
+                        }
+                    }
                 }
-                Ok(false) => {}
                 Err(e) => {
-                    if e.downcast_ref::<std::io::Error>()
-                        .is_some_and(|io_err| io_err.kind() == std::io::ErrorKind::NotFound)
+                    if !file_path.exists()
+                        && e.downcast_ref::<std::io::Error>()
+                            .is_some_and(|io_err| io_err.kind() == std::io::ErrorKind::NotFound)
                     {
                         debug!(
                             "File disappeared before processing: {}",
@@ -323,32 +1117,213 @@ fn scan_existing_background(
     }
 }
 
-/// Recursively iterate all file entries from a directory tree.
-/// Returns a boxed iterator to avoid collecting into a Vec.
-fn walkdir(path: &Path) -> Result<Box<dyn Iterator<Item = std::fs::DirEntry>>> {
-    let mut stack = vec![path.to_path_buf()];
-    let mut entries = Vec::new();
-
-    // NOTE: We still collect into a Vec internally because returning a true
-    // lazy iterator over a recursive directory walk requires either unsafe
-    // self-referential types or a crate like `walkdir`. The allocation is
-    // bounded by the number of files on disk, which is unavoidable for a
-    // full-tree scan. The Box<dyn Iterator> signature keeps the public API
-    // ready for a zero-alloc implementation in the future.
-    while let Some(dir) = stack.pop() {
-        for entry in std::fs::read_dir(&dir)? {
-            let entry = entry?;
-            let ft = entry.file_type()?;
-            if ft.is_symlink() {
+/// Walk a directory tree, skipping subdirectories the filter excludes and
+/// handling symlinks per its mode. Entries are read one directory at a time
+/// as the walk goes, so memory doesn't grow with the number of files.
+fn walkdir<'a>(path: &Path, filter: &'a DirFilter) -> Result<Walk<'a>> {
+    Ok(Walk {
+        filter,
+        guard: SymlinkGuard::new(path),
+        pending: Vec::new(),
+        current: Some((std::fs::read_dir(path)?, 0)),
+    })
+}
+
+/// Lazy recursive directory walk, see [`walkdir`]
+struct Walk<'a> {
+    filter: &'a DirFilter,
+    guard: SymlinkGuard,
+    /// Directories still to read, with their depth
+    pending: Vec<(PathBuf, usize)>,
+    /// The directory being read
+    current: Option<(std::fs::ReadDir, usize)>,
+}
+
+impl Iterator for Walk<'_> {
+    type Item = std::fs::DirEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some((entries, depth)) = &mut self.current else {
+                let (dir, depth) = self.pending.pop()?;
+                match std::fs::read_dir(&dir) {
+                    Ok(entries) => self.current = Some((entries, depth)),
+                    // Moved by a rule in the meantime, or unreadable
+                    Err(e) => debug!("Failed to read directory {}: {}", dir.display(), e),
+                }
                 continue;
+            };
+            let depth = *depth;
+            match entries.next() {
+                None => self.current = None,
+                Some(Err(e)) => debug!("Failed to read directory entry: {}", e),
+                Some(Ok(entry)) => match self.filter.entry_kind(&entry, &mut self.guard) {
+                    EntryKind::Dir => {
+                        if self
+                            .filter
+                            .allows_dir(&entry.file_name().to_string_lossy(), depth + 1)
+                        {
+                            self.pending.push((entry.path(), depth + 1));
+                            return Some(entry);
+                        }
+                    }
+                    EntryKind::File => return Some(entry),
+                    EntryKind::Skip => {}
+                },
             }
-            if ft.is_dir() {
-                stack.push(entry.path());
-                entries.push(entry);
-            } else {
-                entries.push(entry);
-            }
         }
     }
-    Ok(Box::new(entries.into_iter()))
+}
+
+/// Whether an entry was renamed, moved in or otherwise changed after
+/// `since` (Unix only). Scans read folders while rules act on them, so a
+/// file a rule just renamed can show up again; whatever changed it also
+/// sent an event, which handles it instead.
+fn changed_since(entry: &std::fs::DirEntry, since: std::time::SystemTime) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        entry.metadata().is_ok_and(|metadata| {
+            let changed = std::time::UNIX_EPOCH
+                + Duration::new(metadata.ctime().max(0) as u64, metadata.ctime_nsec() as u32);
+            changed >= since
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (entry, since);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::OnConflict;
+
+    #[test]
+    fn test_handle_unmatched() {
+        let dir = tempfile::tempdir().unwrap();
+        let unsorted = dir.path().join("_Unsorted");
+        let file = dir.path().join("mystery.bin");
+        std::fs::write(&file, "x").unwrap();
+
+        let engine = RuleEngine::new(Vec::new());
+        let action = Action::Move {
+            destination: unsorted.clone(),
+            create_destination: true,
+            on_conflict: OnConflict::Fail,
+            preserve_metadata: true,
+        };
+        let counter = AtomicU64::new(0);
+
+        handle_unmatched(&engine, &file, Some(&action), &counter);
+        assert!(!file.exists());
+        assert!(unsorted.join("mystery.bin").exists());
+        assert_eq!(counter.load(Ordering::Relaxed), 1);
+
+        // Files already in the destination aren't moved or counted again
+        handle_unmatched(
+            &engine,
+            &unsorted.join("mystery.bin"),
+            Some(&action),
+            &counter,
+        );
+        assert_eq!(counter.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_run_file_jobs_in_parallel() {
+        let dir = tempfile::tempdir().unwrap();
+        let docs = dir.path().join("docs");
+        let engine = RuleEngine::new(vec![crate::rules::Rule::new(
+            "PDFs",
+            crate::rules::Condition {
+                extension: Some("pdf".to_string()),
+                ..Default::default()
+            },
+            Action::Move {
+                destination: docs.clone(),
+                create_destination: true,
+                on_conflict: OnConflict::Fail,
+                preserve_metadata: true,
+            },
+        )]);
+        let inbox = dir.path().join("inbox");
+        let jobs: Vec<FileJob> = (0..12)
+            .map(|i| {
+                let path = inbox
+                    .join(format!("batch {}", i % 3))
+                    .join(format!("{i}.pdf"));
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, "x").unwrap();
+                FileJob {
+                    path,
+                    allowed: None,
+                    root: Some(inbox.clone()),
+                    unmatched_action: None,
+                    remove_empty_dirs: Some(DirFilter::default()),
+                }
+            })
+            .collect();
+        let counter = AtomicU64::new(0);
+
+        let outcomes = run_file_jobs(&engine, &jobs, 4, &counter);
+        assert_eq!(outcomes.len(), 12);
+        assert!(outcomes.iter().all(|o| matches!(o, Outcome::Matched)));
+        assert_eq!(std::fs::read_dir(&docs).unwrap().count(), 12);
+        // Whichever job emptied a batch folder removed it
+        assert_eq!(std::fs::read_dir(&inbox).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_scan_streams_and_renames_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        for i in 0..1500 {
+            std::fs::write(dir.path().join(format!("{i}.txt")), "x").unwrap();
+        }
+        std::fs::write(nested.join("deep.txt"), "x").unwrap();
+        let filter = DirFilter::default();
+        assert_eq!(walkdir(dir.path(), &filter).unwrap().count(), 1503);
+
+        // Renamed files can come up again while a folder larger than one
+        // directory read is streamed
+        let engine = RuleEngine::new(vec![crate::rules::Rule::new(
+            "Tag",
+            crate::rules::Condition {
+                extension: Some("txt".to_string()),
+                ..Default::default()
+            },
+            Action::Rename {
+                pattern: "done-{filename}".to_string(),
+            },
+        )]);
+        let target = ScanTarget {
+            path: dir.path().to_path_buf(),
+            root: dir.path().to_path_buf(),
+            folder_rules: false,
+            recursive: true,
+            filter,
+            allowed_rules: None,
+            unmatched_action: None,
+            remove_empty_dirs: false,
+            stability: Stability::new(0, Vec::new()),
+        };
+        let counter = Arc::new(AtomicU64::new(0));
+        scan_existing_background(
+            &target,
+            &engine,
+            &Mutex::new(EventHandler::new(0)),
+            Arc::clone(&counter),
+            &AtomicU64::new(0),
+        );
+        assert_eq!(counter.load(Ordering::Relaxed), 1501);
+        assert!(dir.path().join("done-7.txt").exists());
+        assert!(nested.join("done-deep.txt").exists());
+        assert!(!dir.path().join("done-done-7.txt").exists());
+        assert!(crate::progress::active_scans().is_empty());
+    }
 }
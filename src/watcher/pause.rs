@@ -0,0 +1,49 @@
+//! Runtime pause of individual watches
+//!
+//! A paused watch keeps its file system watch but ignores events and scans,
+//! so files can be dropped into it temporarily without rules firing. Pauses
+//! live in memory only: they survive config reloads, not restarts.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, RwLock};
+
+static PAUSED: LazyLock<RwLock<HashSet<PathBuf>>> = LazyLock::new(|| RwLock::new(HashSet::new()));
+
+/// The form a watch path is tracked in: `~` expanded and canonicalized
+/// (the staging folder for remote watches)
+pub fn watch_key(path: &Path) -> PathBuf {
+    let local = crate::sftp::local_dir(path);
+    std::fs::canonicalize(&local).unwrap_or(local)
+}
+
+/// Pause a watch; returns false if it was already paused
+pub fn pause_watch(path: &Path) -> bool {
+    PAUSED
+        .write()
+        .map(|mut paused| paused.insert(watch_key(path)))
+        .unwrap_or(false)
+}
+
+/// Resume a paused watch; returns false if it wasn't paused
+pub fn resume_watch(path: &Path) -> bool {
+    PAUSED
+        .write()
+        .map(|mut paused| paused.remove(&watch_key(path)))
+        .unwrap_or(false)
+}
+
+/// Whether a watch root (in `watch_key` form) is paused
+pub fn is_paused(root: &Path) -> bool {
+    PAUSED.read().is_ok_and(|paused| paused.contains(root))
+}
+
+/// All paused watches, in `watch_key` form
+pub fn paused_watches() -> Vec<PathBuf> {
+    let mut paused: Vec<PathBuf> = PAUSED
+        .read()
+        .map(|paused| paused.iter().cloned().collect())
+        .unwrap_or_default();
+    paused.sort();
+    paused
+}
@@ -0,0 +1,281 @@
+//! Polling backend for filesystems that don't deliver change events
+//!
+//! NFS, SMB and most FUSE mounts never report changes made by other machines
+//! (or at all), so watches on them take periodic snapshots of modification
+//! time and size instead, and turn differences into the same `notify` events
+//! the native backend produces.
+
+use notify::event::{CreateKind, DataChange, ModifyKind, RemoveKind};
+use notify::{Event, EventKind};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+use crate::config::SymlinkMode;
+
+use super::filter::{DirFilter, EntryKind, SymlinkGuard};
+
+/// Modification time and size of every entry under a watched directory
+pub(crate) type Snapshot = HashMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// How often a sleeping poller checks whether it should stop
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Start a background thread that polls `path` every `interval` and sends
+/// events to `tx` until `stop` is set or the receiver goes away.
+pub(crate) fn spawn(
+    path: PathBuf,
+    recursive: bool,
+    filter: DirFilter,
+    interval: Duration,
+    tx: Sender<Result<Event, notify::Error>>,
+    stop: Arc<AtomicBool>,
+) {
+    std::thread::spawn(move || {
+        // Existing files are the baseline; `scan_on_start` handles those
+        let mut previous = snapshot(&path, recursive, &filter);
+
+        loop {
+            let mut waited = Duration::ZERO;
+            while waited < interval {
+                if stop.load(Ordering::Relaxed) {
+                    debug!("Stopped polling {}", path.display());
+                    return;
+                }
+                std::thread::sleep(STOP_CHECK_INTERVAL);
+                waited += STOP_CHECK_INTERVAL;
+            }
+
+            let current = snapshot(&path, recursive, &filter);
+            for event in diff(&previous, &current) {
+                if tx.send(Ok(event)).is_err() {
+                    return;
+                }
+            }
+            previous = current;
+        }
+    });
+}
+
+/// Record modification time and size for everything under `root`,
+/// skipping subdirectories the watch's filter excludes
+pub(crate) fn snapshot(root: &Path, recursive: bool, filter: &DirFilter) -> Snapshot {
+    let mut entries = Snapshot::new();
+    let mut stack = vec![(root.to_path_buf(), 0usize)];
+    let mut guard = SymlinkGuard::new(root);
+
+    while let Some((dir, depth)) = stack.pop() {
+        let read_dir = match std::fs::read_dir(&dir) {
+            Ok(rd) => rd,
+            Err(e) => {
+                warn!("Failed to poll {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            match filter.entry_kind(&entry, &mut guard) {
+                EntryKind::Dir => {
+                    if !filter.allows_dir(&entry.file_name().to_string_lossy(), depth + 1) {
+                        continue;
+                    }
+                    if recursive {
+                        stack.push((path.clone(), depth + 1));
+                    }
+                    // A directory's own size and mtime change with its contents,
+                    // which would report it as modified on every new file
+                    entries.insert(path, (None, 0));
+                }
+                EntryKind::File => {
+                    // Followed links report their target's state, others the link's own
+                    let metadata = if filter.symlinks() == SymlinkMode::Follow {
+                        std::fs::metadata(&path)
+                    } else {
+                        entry.metadata()
+                    };
+                    let Ok(metadata) = metadata else {
+                        continue;
+                    };
+                    entries.insert(path, (metadata.modified().ok(), metadata.len()));
+                }
+                EntryKind::Skip => {}
+            }
+        }
+    }
+
+    entries
+}
+
+/// Events describing how `current` differs from `previous`
+pub(crate) fn diff(previous: &Snapshot, current: &Snapshot) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    for (path, state) in current {
+        match previous.get(path) {
+            None => {
+                events.push(Event::new(EventKind::Create(CreateKind::Any)).add_path(path.clone()))
+            }
+            Some(old) if old != state => events.push(
+                Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any)))
+                    .add_path(path.clone()),
+            ),
+            Some(_) => {}
+        }
+    }
+
+    for path in previous.keys().filter(|p| !current.contains_key(*p)) {
+        events.push(Event::new(EventKind::Remove(RemoveKind::Any)).add_path(path.clone()));
+    }
+
+    events
+}
+
+/// Whether `path` lives on a network or FUSE filesystem, where change
+/// events are unreliable and polling should be used instead
+pub fn is_network_filesystem(path: &Path) -> bool {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    match filesystem_type(&path) {
+        Some(fs_type) => {
+            let network = is_network_fs_type(&fs_type);
+            debug!(
+                "{} is on {} (network: {})",
+                path.display(),
+                fs_type,
+                network
+            );
+            network
+        }
+        None => false,
+    }
+}
+
+fn is_network_fs_type(fs_type: &str) -> bool {
+    const NETWORK_TYPES: &[&str] = &[
+        "nfs",
+        "nfs4",
+        "cifs",
+        "smb",
+        "smb2",
+        "smb3",
+        "smbfs",
+        "afpfs",
+        "webdav",
+        "davfs",
+        "9p",
+        "afs",
+        "ceph",
+        "glusterfs",
+        "sshfs",
+        "lustre",
+        "macfuse",
+        "osxfuse",
+    ];
+    let fs_type = fs_type.to_ascii_lowercase();
+    // `fuseblk` is a local disk (NTFS, exFAT) and reports changes normally
+    NETWORK_TYPES.contains(&fs_type.as_str()) || fs_type == "fuse" || fs_type.starts_with("fuse.")
+}
+
+/// Filesystem type of the mount containing `path`, from `/proc/mounts`
+#[cfg(target_os = "linux")]
+fn filesystem_type(path: &Path) -> Option<String> {
+    let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+    mount_fs_type(&mounts, path)
+}
+
+/// Filesystem type of the volume containing `path`, from `statfs`
+#[cfg(target_os = "macos")]
+fn filesystem_type(path: &Path) -> Option<String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is a valid NUL-terminated string and stat is a valid out-pointer
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // SAFETY: f_fstypename is a NUL-terminated C string filled in by statfs
+    let name = unsafe { std::ffi::CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn filesystem_type(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Find the filesystem type of the longest mount point containing `path`
+/// in `/proc/mounts`-formatted text
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn mount_fs_type(mounts: &str, path: &Path) -> Option<String> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            // Spaces in mount points are escaped as \040
+            let mount_point = fields.next()?.replace("\\040", " ");
+            let fs_type = fields.next()?;
+            Some((PathBuf::from(mount_point), fs_type))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.as_os_str().len())
+        .map(|(_, fs_type)| fs_type.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "b").unwrap();
+        let before = snapshot(dir.path(), false, &DirFilter::default());
+
+        std::fs::write(dir.path().join("a.txt"), "changed").unwrap();
+        std::fs::remove_file(dir.path().join("b.txt")).unwrap();
+        std::fs::write(dir.path().join("c.txt"), "c").unwrap();
+        let after = snapshot(dir.path(), false, &DirFilter::default());
+
+        let events = diff(&before, &after);
+        let kind_of = |name: &str| {
+            events
+                .iter()
+                .find(|e| e.paths[0] == dir.path().join(name))
+                .map(|e| e.kind)
+        };
+        assert!(matches!(kind_of("a.txt"), Some(EventKind::Modify(_))));
+        assert!(matches!(kind_of("b.txt"), Some(EventKind::Remove(_))));
+        assert!(matches!(kind_of("c.txt"), Some(EventKind::Create(_))));
+        assert!(diff(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn test_mount_fs_type() {
+        let mounts = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+server:/export /mnt/nas nfs4 rw 0 0
+//host/share /mnt/nas/smb\\040share cifs rw 0 0
+";
+        assert_eq!(
+            mount_fs_type(mounts, Path::new("/home/me/Downloads")).as_deref(),
+            Some("ext4")
+        );
+        assert_eq!(
+            mount_fs_type(mounts, Path::new("/mnt/nas/photos")).as_deref(),
+            Some("nfs4")
+        );
+        assert_eq!(
+            mount_fs_type(mounts, Path::new("/mnt/nas/smb share/x")).as_deref(),
+            Some("cifs")
+        );
+        assert!(is_network_fs_type("fuse.sshfs"));
+        assert!(!is_network_fs_type("fuseblk"));
+        assert!(!is_network_fs_type("ext4"));
+    }
+}
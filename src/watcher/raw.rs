@@ -0,0 +1,159 @@
+//! Raw filesystem notifications, for debugging watches
+//!
+//! Events are recorded as they come out of the notify backend, before
+//! debouncing, filters, pauses or rules, so `hazelnut events --raw` can tell
+//! "nothing arrives" (permissions, backend, network mount) apart from "the
+//! rule doesn't match". Recording only happens while a client is asking for
+//! events, and the buffer is kept small.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// Recording stops this long after the last request for events
+const LISTEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Events kept between requests; older ones are dropped
+const MAX_EVENTS: usize = 1000;
+
+/// One notification as delivered by the backend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawEvent {
+    /// Increasing number, used to fetch only newer events
+    pub seq: u64,
+    pub time: chrono::DateTime<chrono::Local>,
+    /// Event kind, e.g. `Create(File)` or `Modify(Data(Content))`
+    pub kind: String,
+    pub paths: Vec<PathBuf>,
+    /// Backend error, for events that report one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Recorded events and the listening window
+#[derive(Debug, Default)]
+struct RawLog {
+    events: VecDeque<RawEvent>,
+    next_seq: u64,
+    listening_until: Option<Instant>,
+}
+
+impl RawLog {
+    fn record(&mut self, result: &Result<notify::Event, notify::Error>, now: Instant) {
+        if self.listening_until.is_none_or(|until| now >= until) {
+            self.listening_until = None;
+            self.events.clear();
+            return;
+        }
+        let (kind, paths, error) = match result {
+            Ok(event) => (format!("{:?}", event.kind), event.paths.clone(), None),
+            Err(e) => ("Error".to_string(), e.paths.clone(), Some(error_message(e))),
+        };
+        if self.events.len() >= MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.next_seq += 1;
+        self.events.push_back(RawEvent {
+            seq: self.next_seq,
+            time: chrono::Local::now(),
+            kind,
+            paths,
+            error,
+        });
+    }
+
+    fn after(&mut self, after: Option<u64>, roots: &[PathBuf], now: Instant) -> Vec<RawEvent> {
+        self.listening_until = Some(now + LISTEN_TIMEOUT);
+        let Some(after) = after else {
+            return Vec::new();
+        };
+        self.events
+            .iter()
+            .filter(|e| e.seq > after)
+            .filter(|e| {
+                roots.is_empty()
+                    || e.paths.is_empty()
+                    || e.paths
+                        .iter()
+                        .any(|p| roots.iter().any(|root| p.starts_with(root)))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Error text without the paths, which are listed separately
+fn error_message(error: &notify::Error) -> String {
+    match &error.kind {
+        notify::ErrorKind::Generic(message) => message.clone(),
+        notify::ErrorKind::Io(e) => e.to_string(),
+        kind => format!("{:?}", kind),
+    }
+}
+
+static RAW_LOG: LazyLock<Mutex<RawLog>> = LazyLock::new(|| Mutex::new(RawLog::default()));
+
+/// Record an event from the backend, if a client is listening
+pub(super) fn record(result: &Result<notify::Event, notify::Error>) {
+    if let Ok(mut log) = RAW_LOG.lock() {
+        log.record(result, Instant::now());
+    }
+}
+
+/// Events recorded after `after` (none for `None`, which just starts
+/// recording), limited to paths under `root` if given. Also returns the
+/// sequence number to ask from next time.
+pub fn raw_events(after: Option<u64>, root: Option<&Path>) -> (Vec<RawEvent>, u64) {
+    // Events carry paths as watched, which may not be canonical
+    let roots: Vec<PathBuf> = root
+        .map(|root| vec![crate::sftp::local_dir(root), super::watch_key(root)])
+        .unwrap_or_default();
+    let Ok(mut log) = RAW_LOG.lock() else {
+        return (Vec::new(), after.unwrap_or(0));
+    };
+    let events = log.after(after, &roots, Instant::now());
+    (events, log.next_seq)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, EventKind};
+
+    fn create(path: &str) -> Result<notify::Event, notify::Error> {
+        Ok(notify::Event::new(EventKind::Create(CreateKind::File)).add_path(PathBuf::from(path)))
+    }
+
+    #[test]
+    fn test_records_only_while_listening() {
+        let mut log = RawLog::default();
+        let start = Instant::now();
+
+        // Nobody listening yet
+        log.record(&create("/dl/a.pdf"), start);
+        assert!(log.events.is_empty());
+
+        assert!(log.after(None, &[], start).is_empty());
+        log.record(&create("/dl/b.pdf"), start);
+        log.record(&create("/desk/c.pdf"), start);
+        log.record(
+            &Err(notify::Error::generic("permission denied").add_path(PathBuf::from("/dl"))),
+            start,
+        );
+
+        let events = log.after(Some(0), &[], start);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].kind, "Create(File)");
+        assert_eq!(events[2].error.as_deref(), Some("permission denied"));
+
+        let events = log.after(Some(1), &[PathBuf::from("/dl")], start);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].seq, 3);
+
+        // Recording stops once the client goes away
+        log.record(&create("/dl/d.pdf"), start + LISTEN_TIMEOUT * 2);
+        assert!(log.events.is_empty());
+    }
+}
@@ -0,0 +1,309 @@
+//! In-progress download detection
+//!
+//! Browsers and download managers write to a temporary file (`.crdownload`,
+//! `.part`) or grow the final file bit by bit. Files are only handed to the
+//! rule engine once they've stopped changing for a while, aren't still open
+//! in another application (see `in_use`) and have no temporary sibling
+//! still being written.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
+
+use super::in_use::is_in_use;
+
+/// How long a settled file may stay open in another application before
+/// it's given up on (until its next change)
+const MAX_IN_USE_WAIT: Duration = Duration::from_secs(3600);
+
+/// Extensions of files that are still being downloaded
+pub const DEFAULT_TEMP_EXTENSIONS: &[&str] = &[
+    "crdownload",
+    "part",
+    "partial",
+    "download",
+    "opdownload",
+    "tmp",
+];
+
+/// Settings for deciding when a file is complete
+#[derive(Debug, Clone)]
+pub struct Stability {
+    /// How long size and modification time must stay unchanged
+    stable_for: Duration,
+    /// Lowercase extensions of in-progress files
+    temp_extensions: Vec<String>,
+}
+
+impl Default for Stability {
+    fn default() -> Self {
+        Self::new(
+            2,
+            DEFAULT_TEMP_EXTENSIONS
+                .iter()
+                .map(|e| e.to_string())
+                .collect(),
+        )
+    }
+}
+
+impl Stability {
+    /// Create settings from `stable_seconds` and temp extensions (with or without a leading dot)
+    pub fn new(stable_seconds: u64, temp_extensions: Vec<String>) -> Self {
+        Self {
+            stable_for: Duration::from_secs(stable_seconds),
+            temp_extensions: temp_extensions
+                .into_iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect(),
+        }
+    }
+
+    /// How long a file (or folder tree) must stay unchanged
+    pub fn stable_for(&self) -> Duration {
+        self.stable_for
+    }
+
+    /// Glob patterns (`*.part`, …) for temp files inside a folder
+    pub fn temp_patterns(&self) -> Vec<glob::Pattern> {
+        self.temp_extensions
+            .iter()
+            .filter_map(|ext| glob::Pattern::new(&format!("*.{}", glob::Pattern::escape(ext))).ok())
+            .collect()
+    }
+
+    /// Whether the file is a browser/downloader temp file
+    pub fn is_temp_file(&self, path: &Path) -> bool {
+        path.extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .is_some_and(|e| self.temp_extensions.contains(&e))
+    }
+
+    /// Whether a temp file for this one (e.g. `file.pdf.part`) exists next to it,
+    /// as Firefox creates an empty final file while the download is in progress
+    fn has_temp_sibling(&self, path: &Path) -> bool {
+        self.temp_extensions.iter().any(|ext| {
+            let mut sibling = path.as_os_str().to_os_string();
+            sibling.push(".");
+            sibling.push(ext);
+            Path::new(&sibling).exists()
+        })
+    }
+
+    /// Whether a file found by a scan looks complete right now: not a temp
+    /// file, not modified within the stability window and not in use
+    pub fn is_settled(&self, path: &Path) -> bool {
+        if self.is_temp_file(path) || self.has_temp_sibling(path) {
+            return false;
+        }
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return false;
+        };
+        if !metadata.is_file() {
+            return true;
+        }
+        let recently_modified = metadata
+            .modified()
+            .ok()
+            .and_then(|m| SystemTime::now().duration_since(m).ok())
+            .is_some_and(|age| age < self.stable_for);
+        !recently_modified && !is_in_use(path)
+    }
+}
+
+/// Size and modification time of a file when last checked
+#[derive(Debug, Clone, PartialEq)]
+struct FileState {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileState {
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// A file waiting to settle
+#[derive(Debug)]
+struct Pending {
+    /// Last observed state
+    state: FileState,
+    /// When `state` was first observed
+    since: Instant,
+    /// When the file was first found settled but open in another application
+    in_use_since: Option<Instant>,
+}
+
+/// Files waiting for their size to settle before processing
+#[derive(Debug, Default)]
+pub struct PendingFiles {
+    stability: Stability,
+    pending: HashMap<PathBuf, Pending>,
+}
+
+impl PendingFiles {
+    /// Create a tracker with the given settings
+    pub fn new(stability: Stability) -> Self {
+        Self {
+            stability,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// The stability settings
+    pub fn stability(&self) -> &Stability {
+        &self.stability
+    }
+
+    /// Start (or keep) waiting for a file to settle
+    pub fn track(&mut self, path: PathBuf) {
+        self.track_at(path, Instant::now());
+    }
+
+    fn track_at(&mut self, path: PathBuf, now: Instant) {
+        if self.pending.contains_key(&path) {
+            return;
+        }
+        if let Some(state) = FileState::read(&path) {
+            self.pending.insert(
+                path,
+                Pending {
+                    state,
+                    since: now,
+                    in_use_since: None,
+                },
+            );
+        }
+    }
+
+    /// Whether any file is still waiting
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Number of files still waiting
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Remove and return the files that have settled. Files that vanished
+    /// (e.g. renamed from `.part`) are dropped.
+    pub fn take_ready(&mut self) -> Vec<PathBuf> {
+        self.take_ready_at(Instant::now())
+    }
+
+    fn take_ready_at(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut ready = Vec::new();
+        let stable_for = self.stability.stable_for;
+        let stability = &self.stability;
+
+        self.pending.retain(|path, pending| {
+            let Some(current) = FileState::read(path) else {
+                return false;
+            };
+            if current != pending.state {
+                pending.state = current;
+                pending.since = now;
+                return true;
+            }
+            if now.duration_since(pending.since) < stable_for || stability.has_temp_sibling(path)
+            {
+                return true;
+            }
+            if is_in_use(path) {
+                let Some(in_use_since) = pending.in_use_since else {
+                    info!(
+                        "{} is still open in another application, waiting until it's closed",
+                        path.display()
+                    );
+                    pending.in_use_since = Some(now);
+                    return true;
+                };
+                if now.duration_since(in_use_since) >= MAX_IN_USE_WAIT {
+                    warn!(
+                        "{} has been open in another application for over an hour, skipping it until it changes",
+                        path.display()
+                    );
+                    return false;
+                }
+                return true;
+            }
+            ready.push(path.clone());
+            false
+        });
+
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_files() {
+        let stability = Stability::default();
+        assert!(stability.is_temp_file(Path::new("/dl/movie.mkv.crdownload")));
+        assert!(stability.is_temp_file(Path::new("/dl/ISO.PART")));
+        assert!(!stability.is_temp_file(Path::new("/dl/report.pdf")));
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("report.pdf");
+        std::fs::write(&file, "").unwrap();
+        std::fs::write(dir.path().join("report.pdf.part"), "partial").unwrap();
+        assert!(stability.has_temp_sibling(&file));
+        assert!(!stability.is_settled(&file));
+    }
+
+    #[test]
+    fn test_pending_until_stable() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("video.mp4");
+        std::fs::write(&file, "a").unwrap();
+
+        let mut pending = PendingFiles::new(Stability::new(2, Vec::new()));
+        let start = Instant::now();
+        pending.track_at(file.clone(), start);
+
+        // Not stable long enough yet
+        assert!(
+            pending
+                .take_ready_at(start + Duration::from_secs(1))
+                .is_empty()
+        );
+
+        // Growing resets the clock
+        std::fs::write(&file, "abc").unwrap();
+        assert!(
+            pending
+                .take_ready_at(start + Duration::from_secs(3))
+                .is_empty()
+        );
+        assert!(
+            pending
+                .take_ready_at(start + Duration::from_secs(4))
+                .is_empty()
+        );
+        assert_eq!(
+            pending.take_ready_at(start + Duration::from_secs(5)),
+            vec![file.clone()]
+        );
+        assert!(pending.is_empty());
+
+        // Files that disappear are dropped
+        pending.track_at(file.clone(), start);
+        std::fs::remove_file(&file).unwrap();
+        assert!(
+            pending
+                .take_ready_at(start + Duration::from_secs(10))
+                .is_empty()
+        );
+        assert!(pending.is_empty());
+    }
+}